@@ -17,19 +17,33 @@
 //! - Trust-On-First-Use (TOFU): Accept certificate on first pairing
 //! - SHA256 fingerprint verification prevents MITM attacks
 //! - Certificates stored and verified on subsequent connections
+//!
+//! ## Rotation
+//!
+//! [`CertificateManager::rotate`] replaces the device certificate with a
+//! fresh key pair under the same device ID. Paired peers pinned the old
+//! fingerprint, so the manager keeps the old certificate for an overlap
+//! period and returns a [`CertificateRotation`] to send them over a
+//! connection still authenticated by the old certificate. Peers apply it
+//! with [`PairedDevices::accept_rotation`](crate::crypto::pairing::PairedDevices::accept_rotation),
+//! passing the fingerprint that connection's TLS handshake authenticated.
 
 use crate::error::{ProtocolError, Result};
+use crate::protocol::DeviceId;
 use rcgen::{CertificateParams, DistinguishedName, DnType, KeyPair};
 use rsa::{RsaPrivateKey, pkcs8::EncodePrivateKey};
 use sha2::{Digest, Sha256};
 use std::fs;
 use std::path::Path;
-use std::time::Duration;
-use tracing::{debug, info};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::{debug, info, warn};
 
 /// Certificate validity period (10 years)
 const CERT_VALIDITY_YEARS: u32 = 10;
 
+/// Default time a rotated-out certificate is kept for peers to catch up (7 days)
+pub const DEFAULT_ROTATION_OVERLAP: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
 /// Organization name in certificate
 const CERT_ORG: &str = "KDE";
 
@@ -42,7 +56,7 @@ const CERT_ORG_UNIT: &str = "Kde connect";
 #[derive(Debug, Clone)]
 pub struct CertificateInfo {
     /// Device ID (UUID) - used as Common Name
-    pub device_id: String,
+    pub device_id: DeviceId,
 
     /// DER-encoded certificate
     pub certificate: Vec<u8>,
@@ -65,12 +79,22 @@ impl CertificateInfo {
     ///
     /// ```
     /// use cosmic_ext_connect_core::crypto::CertificateInfo;
+    /// use cosmic_ext_connect_core::protocol::DeviceId;
     ///
-    /// let cert_info = CertificateInfo::generate("test_device_id").unwrap();
+    /// let cert_info = CertificateInfo::generate(DeviceId::generate()).unwrap();
     /// println!("Fingerprint: {}", cert_info.fingerprint);
     /// ```
-    pub fn generate(device_id: impl Into<String>) -> Result<Self> {
-        let device_id = device_id.into();
+    pub fn generate(device_id: DeviceId) -> Result<Self> {
+        Self::generate_with_validity(
+            device_id,
+            Duration::from_secs(CERT_VALIDITY_YEARS as u64 * 365 * 24 * 60 * 60),
+        )
+    }
+
+    /// Generate a new self-signed certificate valid for `validity` from now
+    ///
+    /// Like [`generate`](Self::generate), which uses a 10 year validity.
+    pub fn generate_with_validity(device_id: DeviceId, validity: Duration) -> Result<Self> {
 
         info!("Generating RSA 2048-bit certificate for device: {}", device_id);
 
@@ -89,7 +113,7 @@ impl CertificateInfo {
             .map_err(|e| ProtocolError::Certificate(format!("Failed to import key pair: {}", e)))?;
 
         // Create certificate parameters
-        let mut params = CertificateParams::new(vec![device_id.clone()]);
+        let mut params = CertificateParams::new(vec![device_id.to_string()]);
 
         // Set algorithm to RSA with SHA-256 (must match the key pair)
         params.alg = &rcgen::PKCS_RSA_SHA256;
@@ -101,13 +125,12 @@ impl CertificateInfo {
         let mut dn = DistinguishedName::new();
         dn.push(DnType::OrganizationName, CERT_ORG);
         dn.push(DnType::OrganizationalUnitName, CERT_ORG_UNIT);
-        dn.push(DnType::CommonName, device_id.clone());
+        dn.push(DnType::CommonName, device_id.as_str());
         params.distinguished_name = dn;
 
-        // Set validity period (10 years from now)
-        let validity_duration = Duration::from_secs(CERT_VALIDITY_YEARS as u64 * 365 * 24 * 60 * 60);
+        // Set validity period
         params.not_before = time::OffsetDateTime::now_utc();
-        params.not_after = params.not_before + validity_duration;
+        params.not_after = params.not_before + validity;
 
         // Set as not a CA (end-entity certificate)
         params.is_ca = rcgen::IsCa::NoCa;
@@ -154,8 +177,9 @@ impl CertificateInfo {
     ///
     /// ```
     /// use cosmic_ext_connect_core::crypto::CertificateInfo;
+    /// use cosmic_ext_connect_core::protocol::DeviceId;
     ///
-    /// let cert_info = CertificateInfo::generate("test").unwrap();
+    /// let cert_info = CertificateInfo::generate(DeviceId::generate()).unwrap();
     /// let fingerprint = CertificateInfo::calculate_fingerprint(&cert_info.certificate);
     /// assert!(fingerprint.contains(':'));
     /// ```
@@ -182,8 +206,9 @@ impl CertificateInfo {
     ///
     /// ```no_run
     /// use cosmic_ext_connect_core::crypto::CertificateInfo;
+    /// use cosmic_ext_connect_core::protocol::DeviceId;
     ///
-    /// let cert_info = CertificateInfo::generate("test").unwrap();
+    /// let cert_info = CertificateInfo::generate(DeviceId::generate()).unwrap();
     /// cert_info.save_to_files("cert.pem", "key.pem").unwrap();
     /// ```
     pub fn save_to_files(
@@ -301,8 +326,9 @@ impl CertificateInfo {
     ///
     /// ```
     /// use cosmic_ext_connect_core::crypto::CertificateInfo;
+    /// use cosmic_ext_connect_core::protocol::DeviceId;
     ///
-    /// let generated = CertificateInfo::generate("test").unwrap();
+    /// let generated = CertificateInfo::generate(DeviceId::generate()).unwrap();
     /// let loaded = CertificateInfo::from_der(
     ///     generated.certificate.clone(),
     ///     generated.private_key.clone()
@@ -326,8 +352,9 @@ impl CertificateInfo {
 
     /// Extract device ID from certificate Common Name
     ///
-    /// Uses x509-parser to extract CN from certificate DN
-    fn extract_device_id_from_cert(cert_der: &[u8]) -> Result<String> {
+    /// Uses x509-parser to extract CN from certificate DN. A CN that is not a
+    /// valid device ID fails with `ProtocolError::InvalidDeviceId`.
+    fn extract_device_id_from_cert(cert_der: &[u8]) -> Result<DeviceId> {
         use x509_parser::prelude::*;
 
         let (_, cert) = X509Certificate::from_der(cert_der)
@@ -342,7 +369,7 @@ impl CertificateInfo {
                 if attr.attr_type() == &x509_parser::oid_registry::OID_X509_COMMON_NAME {
                     let cn = attr.as_str()
                         .map_err(|e| ProtocolError::Certificate(format!("Failed to extract CN: {}", e)))?;
-                    return Ok(cn.to_string());
+                    return DeviceId::parse(cn);
                 }
             }
         }
@@ -352,6 +379,33 @@ impl CertificateInfo {
         ))
     }
 
+    /// Get the time the certificate expires
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Certificate` if the certificate cannot be parsed.
+    pub fn expires_at(&self) -> Result<SystemTime> {
+        use x509_parser::prelude::*;
+
+        let (_, cert) = X509Certificate::from_der(&self.certificate)
+            .map_err(|e| ProtocolError::Certificate(format!("Failed to parse certificate: {}", e)))?;
+        let not_after = cert.validity().not_after.timestamp();
+        Ok(UNIX_EPOCH + Duration::from_secs(not_after.max(0) as u64))
+    }
+
+    /// Check whether the certificate expires within `duration` from now
+    ///
+    /// Expired and unparseable certificates count as expiring.
+    pub fn is_expiring_within(&self, duration: Duration) -> bool {
+        match self.expires_at() {
+            Ok(expires_at) => expires_at <= SystemTime::now() + duration,
+            Err(e) => {
+                warn!("Treating unreadable certificate as expiring: {}", e);
+                true
+            }
+        }
+    }
+
     /// Validate certificate format and contents
     ///
     /// Checks:
@@ -402,16 +456,138 @@ impl CertificateInfo {
     }
 }
 
+/// Fingerprint change to announce to paired peers after a rotation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertificateRotation {
+    /// Device ID, unchanged by the rotation
+    pub device_id: DeviceId,
+
+    /// Fingerprint peers have pinned
+    pub old_fingerprint: String,
+
+    /// Fingerprint of the new certificate
+    pub new_fingerprint: String,
+}
+
+/// Owns the device certificate and rotates it
+///
+/// After a rotation the previous certificate stays available through
+/// [`previous`](Self::previous) for the overlap period, so connections to
+/// peers that have not yet learned the new fingerprint can still be made.
+#[derive(Debug, Clone)]
+pub struct CertificateManager {
+    /// Certificate presented on new connections
+    current: CertificateInfo,
+
+    /// Rotated-out certificate and when it was retired
+    previous: Option<(CertificateInfo, Instant)>,
+
+    /// How long a rotated-out certificate is kept
+    overlap: Duration,
+
+    /// Validity of certificates generated by rotation
+    validity: Duration,
+}
+
+impl CertificateManager {
+    /// Create a manager for an existing certificate
+    pub fn new(current: CertificateInfo) -> Self {
+        Self {
+            current,
+            previous: None,
+            overlap: DEFAULT_ROTATION_OVERLAP,
+            validity: Duration::from_secs(CERT_VALIDITY_YEARS as u64 * 365 * 24 * 60 * 60),
+        }
+    }
+
+    /// Builder: set how long a rotated-out certificate is kept
+    pub fn with_overlap(mut self, overlap: Duration) -> Self {
+        self.overlap = overlap;
+        self
+    }
+
+    /// Builder: set the validity of certificates generated by rotation
+    pub fn with_validity(mut self, validity: Duration) -> Self {
+        self.validity = validity;
+        self
+    }
+
+    /// Get the current certificate
+    pub fn current(&self) -> &CertificateInfo {
+        &self.current
+    }
+
+    /// Get the rotated-out certificate while its overlap period lasts
+    pub fn previous(&self) -> Option<&CertificateInfo> {
+        self.previous_at(Instant::now())
+    }
+
+    /// Get the rotated-out certificate if its overlap period lasts until `now`
+    pub fn previous_at(&self, now: Instant) -> Option<&CertificateInfo> {
+        self.previous
+            .as_ref()
+            .filter(|(_, retired)| now.saturating_duration_since(*retired) < self.overlap)
+            .map(|(cert, _)| cert)
+    }
+
+    /// Replace the certificate with a fresh key pair for the same device ID
+    ///
+    /// The old certificate is kept for the overlap period. Send the returned
+    /// rotation to paired peers so they trust the new fingerprint.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Certificate` if generation fails; the current
+    /// certificate is left in place.
+    pub fn rotate(&mut self) -> Result<CertificateRotation> {
+        let device_id = self.current.device_id.clone();
+        let fresh = CertificateInfo::generate_with_validity(device_id, self.validity)?;
+        let old = std::mem::replace(&mut self.current, fresh);
+
+        info!(
+            "Rotated certificate for device {}: {} -> {}",
+            old.device_id, old.fingerprint, self.current.fingerprint
+        );
+
+        let rotation = CertificateRotation {
+            device_id: old.device_id.clone(),
+            old_fingerprint: old.fingerprint.clone(),
+            new_fingerprint: self.current.fingerprint.clone(),
+        };
+        self.previous = Some((old, Instant::now()));
+        Ok(rotation)
+    }
+
+    /// Rotate if the current certificate expires within `duration`
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Certificate` if generation fails.
+    pub fn rotate_if_expiring(&mut self, duration: Duration) -> Result<Option<CertificateRotation>> {
+        if self.current.is_expiring_within(duration) {
+            self.rotate().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use tempfile::TempDir;
 
+    const DEVICE: &str = "test_device_0123456789abcdef012345";
+
+    fn device_id() -> DeviceId {
+        DeviceId::parse(DEVICE).unwrap()
+    }
+
     #[test]
     fn test_generate_certificate() {
-        let cert_info = CertificateInfo::generate("test_device_123").unwrap();
+        let cert_info = CertificateInfo::generate(device_id()).unwrap();
 
-        assert_eq!(cert_info.device_id, "test_device_123");
+        assert_eq!(cert_info.device_id, DEVICE);
         assert!(!cert_info.certificate.is_empty());
         assert!(!cert_info.private_key.is_empty());
         assert!(!cert_info.fingerprint.is_empty());
@@ -420,7 +596,7 @@ mod tests {
 
     #[test]
     fn test_fingerprint_format() {
-        let cert_info = CertificateInfo::generate("test").unwrap();
+        let cert_info = CertificateInfo::generate(device_id()).unwrap();
         let fingerprint = &cert_info.fingerprint;
 
         // Should be hex bytes separated by colons
@@ -441,7 +617,7 @@ mod tests {
         let key_path = temp_dir.path().join("key.pem");
 
         // Generate and save
-        let original = CertificateInfo::generate("test_device").unwrap();
+        let original = CertificateInfo::generate(device_id()).unwrap();
         original.save_to_files(&cert_path, &key_path).unwrap();
 
         // Load and verify
@@ -455,7 +631,7 @@ mod tests {
 
     #[test]
     fn test_from_der() {
-        let generated = CertificateInfo::generate("test_device").unwrap();
+        let generated = CertificateInfo::generate(device_id()).unwrap();
         let loaded = CertificateInfo::from_der(
             generated.certificate.clone(),
             generated.private_key.clone(),
@@ -468,13 +644,66 @@ mod tests {
 
     #[test]
     fn test_validate() {
-        let cert_info = CertificateInfo::generate("test").unwrap();
+        let cert_info = CertificateInfo::generate(device_id()).unwrap();
         assert!(cert_info.validate().is_ok());
     }
 
+    #[test]
+    fn test_expiry_detection() {
+        let validity = Duration::from_secs(60 * 60);
+        let short_lived = CertificateInfo::generate_with_validity(device_id(), validity).unwrap();
+        assert!(short_lived.is_expiring_within(Duration::from_secs(2 * 60 * 60)));
+        assert!(!short_lived.is_expiring_within(Duration::from_secs(30 * 60)));
+        assert!(short_lived.validate().is_ok());
+
+        let garbage = CertificateInfo {
+            certificate: vec![0, 1, 2],
+            ..short_lived
+        };
+        assert!(garbage.is_expiring_within(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_rotation_keeps_device_id() {
+        let short_lived =
+            CertificateInfo::generate_with_validity(device_id(), Duration::from_secs(60)).unwrap();
+        let old_fingerprint = short_lived.fingerprint.clone();
+        let mut manager = CertificateManager::new(short_lived).with_overlap(Duration::from_secs(60));
+        assert!(manager.previous().is_none());
+
+        let rotation = manager
+            .rotate_if_expiring(Duration::from_secs(24 * 60 * 60))
+            .unwrap()
+            .unwrap();
+        assert_eq!(rotation.device_id, DEVICE);
+        assert_eq!(rotation.old_fingerprint, old_fingerprint);
+        assert_ne!(rotation.new_fingerprint, old_fingerprint);
+
+        let current = manager.current();
+        assert_eq!(current.device_id, DEVICE);
+        assert_eq!(current.fingerprint, rotation.new_fingerprint);
+        assert!(current.validate().is_ok());
+        assert!(!current.is_expiring_within(Duration::from_secs(24 * 60 * 60)));
+        // The new certificate's CN is the same device ID
+        let reloaded =
+            CertificateInfo::from_der(current.certificate.clone(), current.private_key.clone()).unwrap();
+        assert_eq!(reloaded.device_id, DEVICE);
+
+        // The old certificate is kept only for the overlap
+        assert_eq!(manager.previous().unwrap().fingerprint, old_fingerprint);
+        assert!(manager
+            .previous_at(Instant::now() + Duration::from_secs(61))
+            .is_none());
+
+        assert!(manager
+            .rotate_if_expiring(Duration::from_secs(60))
+            .unwrap()
+            .is_none());
+    }
+
     #[test]
     fn test_fingerprint_consistency() {
-        let cert_info = CertificateInfo::generate("test").unwrap();
+        let cert_info = CertificateInfo::generate(device_id()).unwrap();
         let fp1 = CertificateInfo::calculate_fingerprint(&cert_info.certificate);
         let fp2 = CertificateInfo::calculate_fingerprint(&cert_info.certificate);
 
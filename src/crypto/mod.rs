@@ -5,26 +5,29 @@
 //! This module contains:
 //! - `certificate`: Certificate generation and management
 //! - `tls`: Secure TLS connections (rustls-based)
+//! - `pairing`: Pairing state and verification keys
 //!
-//! ## Pairing Implementation Status
+//! ## Pairing
 //!
-//! Device pairing has been fully implemented in `cosmic-connect-protocol::pairing`.
-//! The pairing module was extracted from the applet and now provides:
-//! - `PairingService`: Manages pairing lifecycle and state
-//! - `PairingHandler`: Handles pairing request/response packets
-//! - `PairingEvent`: Event notifications for UI integration
-//!
-//! The pairing implementation uses the crypto primitives from this module
-//! (certificate generation and TLS) but lives in the protocol crate to avoid
-//! circular dependencies between core crypto and protocol packet handling.
-//!
-//! For pairing functionality, use: `cosmic_connect_protocol::pairing`
+//! The [`pairing`] module provides the verification key shown on both
+//! devices during pairing, a per-device pairing state machine with request
+//! expiry, the auto-accept allowlist and the store of paired devices'
+//! certificate fingerprints.
 
 // Module exports
 pub mod certificate;   // ✅ Extracted (Issue #47)
 pub mod tls;           // ✅ Extracted (Issue #47)
-// Pairing now lives in cosmic-connect-protocol::pairing (Issue #47 complete)
+pub mod pairing;       // ✅ Verification key and pairing state
 
 // Re-exports for convenience
-pub use certificate::CertificateInfo;
-pub use tls::{DeviceInfo, TlsConfig, TlsConnection, TlsServer, should_initiate_connection};
+pub use certificate::{
+    CertificateInfo, CertificateManager, CertificateRotation, DEFAULT_ROTATION_OVERLAP,
+};
+pub use pairing::{
+    spawn_pair_request_timer, verification_key, PairState, PairingAllowlist, PairingEvent,
+    PairingSession, DEFAULT_PAIR_REQUEST_TIMEOUT,
+};
+pub use tls::{
+    DeviceInfo, TlsConfig, TlsConnection, TlsServer, should_initiate_connection,
+    TCP_PORT_FALLBACK_RANGE,
+};
@@ -0,0 +1,926 @@
+//! Pairing State and Verification Keys
+//!
+//! Minimal pairing state machine for a single remote device, plus the
+//! verification key shown to users while a pair request is pending.
+//!
+//! ## Verification Key
+//!
+//! Both devices derive the same short code from the two certificate
+//! fingerprints. The fingerprints are normalized and sorted before hashing,
+//! so the result does not depend on which side initiated pairing. Users
+//! compare the code on both screens to rule out a man-in-the-middle.
+//!
+//! ## Auto-Accept Allowlist
+//!
+//! Kiosk and automation setups can skip user confirmation for known devices.
+//! A [`PairingAllowlist`] names device IDs and certificate fingerprints that
+//! are accepted as soon as they request pairing. The allowlist is empty by
+//! default, so every request needs confirmation unless one is configured.
+//!
+//! ## Paired Devices
+//!
+//! [`PairedDevices`] remembers which certificate fingerprint each paired
+//! device ID belongs to. Device IDs are not secret and can collide (e.g. two
+//! VMs cloned from one image), so a known ID presented with a different
+//! certificate raises [`ProtocolError::DuplicateDeviceId`] instead of
+//! silently replacing the stored trust. The UI can then ask the user, and
+//! [`PairedDevices::replace`] records their decision.
+//!
+//! Plugins can also keep state about a paired device that is worth reusing
+//! on the next connection (e.g. the camera plugin's last-known capabilities)
+//! with [`PairedDevices::cache`]. Cached state is stamped with the time it
+//! was stored, so readers can judge its age, and is dropped when the device
+//! is removed or its certificate replaced.
+//!
+//! ## Request Expiry
+//!
+//! A pending pair request in either direction expires after
+//! [`DEFAULT_PAIR_REQUEST_TIMEOUT`] (30 seconds, as in KDE Connect), or the
+//! timeout set with [`PairingSession::with_request_timeout`]. An expired
+//! request falls back to [`PairState::Unpaired`] and cannot be accepted.
+//! [`spawn_pair_request_timer`] runs the timeout on a tokio timer and emits
+//! [`PairingEvent::Expired`]; accepting or rejecting first cancels it.
+//!
+//! ## Packet Types
+//!
+//! - `cconnect.pair` with body `{"pair": true}` requests or accepts pairing
+//! - `cconnect.pair` with body `{"pair": false}` rejects or unpairs
+
+use crate::crypto::certificate::CertificateRotation;
+use crate::error::{ProtocolError, Result};
+use crate::protocol::{DeviceId, Packet};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::{mpsc, Mutex};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+/// Pair packet type
+pub const PACKET_TYPE_PAIR: &str = "cconnect.pair";
+
+/// Number of hex digits in a verification key
+pub const VERIFICATION_KEY_LENGTH: usize = 8;
+
+/// Derive a verification key from two certificate fingerprints
+///
+/// The key is the first [`VERIFICATION_KEY_LENGTH`] uppercase hex digits of
+/// SHA-256 over the sorted, normalized fingerprints. It is symmetric: swapping
+/// the arguments yields the same key.
+///
+/// # Examples
+///
+/// ```
+/// use cosmic_ext_connect_core::crypto::pairing::verification_key;
+///
+/// let a = verification_key("AA:BB:CC", "11:22:33");
+/// let b = verification_key("11:22:33", "AA:BB:CC");
+/// assert_eq!(a, b);
+/// assert_eq!(a.len(), 8);
+/// ```
+pub fn verification_key(local_fingerprint: &str, remote_fingerprint: &str) -> String {
+    let mut fingerprints = [
+        normalize_fingerprint(local_fingerprint),
+        normalize_fingerprint(remote_fingerprint),
+    ];
+    fingerprints.sort();
+
+    let mut hasher = Sha256::new();
+    hasher.update(fingerprints[0].as_bytes());
+    hasher.update(fingerprints[1].as_bytes());
+    let hash = hasher.finalize();
+
+    let mut key = hex::encode_upper(hash);
+    key.truncate(VERIFICATION_KEY_LENGTH);
+    key
+}
+
+/// Strip separators and case so equivalent fingerprints hash identically
+fn normalize_fingerprint(fingerprint: &str) -> String {
+    fingerprint
+        .chars()
+        .filter(|c| c.is_ascii_hexdigit())
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+/// Devices whose pair requests are accepted without user confirmation
+///
+/// # Examples
+///
+/// ```
+/// use cosmic_ext_connect_core::crypto::pairing::PairingAllowlist;
+/// use cosmic_ext_connect_core::protocol::DeviceId;
+///
+/// let kiosk = DeviceId::generate();
+/// let allowlist = PairingAllowlist::new()
+///     .allow_device(kiosk.clone())
+///     .allow_fingerprint("AA:BB:CC:DD");
+/// assert!(allowlist.contains(Some(&kiosk), "11:22"));
+/// assert!(allowlist.contains(None, "aabbccdd"));
+/// assert!(!allowlist.contains(Some(&DeviceId::generate()), "11:22"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PairingAllowlist {
+    /// Allowed device IDs
+    device_ids: HashSet<DeviceId>,
+
+    /// Allowed certificate fingerprints, normalized
+    fingerprints: HashSet<String>,
+}
+
+impl PairingAllowlist {
+    /// Create an empty allowlist
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow a device ID
+    pub fn allow_device(mut self, device_id: DeviceId) -> Self {
+        self.device_ids.insert(device_id);
+        self
+    }
+
+    /// Allow a certificate fingerprint (separators and case are ignored)
+    pub fn allow_fingerprint(mut self, fingerprint: &str) -> Self {
+        let fingerprint = normalize_fingerprint(fingerprint);
+        if !fingerprint.is_empty() {
+            self.fingerprints.insert(fingerprint);
+        }
+        self
+    }
+
+    /// Check whether the allowlist has no entries
+    pub fn is_empty(&self) -> bool {
+        self.device_ids.is_empty() && self.fingerprints.is_empty()
+    }
+
+    /// Check whether a device matches by ID or fingerprint
+    pub fn contains(&self, device_id: Option<&DeviceId>, fingerprint: &str) -> bool {
+        device_id.is_some_and(|id| self.device_ids.contains(id))
+            || self.fingerprints.contains(&normalize_fingerprint(fingerprint))
+    }
+}
+
+/// Certificate fingerprints of paired devices, by device ID
+///
+/// # Examples
+///
+/// ```
+/// use cosmic_ext_connect_core::crypto::pairing::PairedDevices;
+/// use cosmic_ext_connect_core::protocol::DeviceId;
+/// use cosmic_ext_connect_core::ProtocolError;
+///
+/// let pixel = DeviceId::generate();
+/// let mut paired = PairedDevices::new();
+/// paired.trust(pixel.clone(), "AA:BB:CC:DD").unwrap();
+/// assert!(paired.verify(&pixel, "aabbccdd").unwrap());
+///
+/// // Same ID, different certificate
+/// let err = paired.verify(&pixel, "11:22:33:44").unwrap_err();
+/// assert!(matches!(err, ProtocolError::DuplicateDeviceId { .. }));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PairedDevices {
+    /// Normalized fingerprint of each paired device
+    fingerprints: HashMap<DeviceId, String>,
+
+    /// Plugin state cached per paired device, by key
+    cached: HashMap<DeviceId, HashMap<String, CachedState>>,
+}
+
+/// Plugin state cached for a paired device
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedState {
+    /// Stored value
+    pub value: Value,
+
+    /// When the value was stored
+    pub stored_at: SystemTime,
+}
+
+impl CachedState {
+    /// Get how long ago the value was stored, as of `now`
+    ///
+    /// A `stored_at` in the future (clock changes) counts as zero.
+    pub fn age_at(&self, now: SystemTime) -> Duration {
+        now.duration_since(self.stored_at).unwrap_or_default()
+    }
+}
+
+impl PairedDevices {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check a device's certificate against the stored fingerprint
+    ///
+    /// Returns `true` if the device is paired with this certificate and
+    /// `false` if the device ID is not paired.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::DuplicateDeviceId` if the device ID is paired
+    /// with a different certificate.
+    pub fn verify(&self, device_id: &DeviceId, fingerprint: &str) -> Result<bool> {
+        let Some(stored) = self.fingerprints.get(device_id) else {
+            return Ok(false);
+        };
+
+        let presented = normalize_fingerprint(fingerprint);
+        if *stored != presented {
+            warn!(
+                "Device ID {} presented a different certificate than the paired one",
+                device_id
+            );
+            return Err(ProtocolError::DuplicateDeviceId {
+                device_id: device_id.to_string(),
+                stored: stored.clone(),
+                presented,
+            });
+        }
+        Ok(true)
+    }
+
+    /// Record a device as paired with a certificate
+    ///
+    /// Pairing again with the same certificate is a no-op.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::DuplicateDeviceId` without changing the store
+    /// if the device ID is already paired with a different certificate; use
+    /// [`replace`](Self::replace) once the user confirms.
+    pub fn trust(&mut self, device_id: DeviceId, fingerprint: &str) -> Result<()> {
+        if !self.verify(&device_id, fingerprint)? {
+            info!("Paired with device {}", device_id);
+            self.fingerprints
+                .insert(device_id, normalize_fingerprint(fingerprint));
+        }
+        Ok(())
+    }
+
+    /// Record a device as paired, replacing any stored certificate
+    ///
+    /// Returns the previously stored fingerprint, if any.
+    pub fn replace(&mut self, device_id: DeviceId, fingerprint: &str) -> Option<String> {
+        info!("Replacing trusted certificate of device {}", device_id);
+        self.cached.remove(&device_id);
+        self.fingerprints
+            .insert(device_id, normalize_fingerprint(fingerprint))
+    }
+
+    /// Trust a paired device's new certificate after it rotated
+    ///
+    /// Fingerprints are public, so naming the trusted one proves nothing.
+    /// `peer_fingerprint` must be the fingerprint the TLS handshake
+    /// authenticated for the connection the rotation arrived on (see
+    /// [`TlsConnection::peer_fingerprint`](crate::crypto::TlsConnection::peer_fingerprint)):
+    /// only the holder of the trusted certificate's private key can present
+    /// it, and the rotation must name it as the old fingerprint.
+    ///
+    /// # Errors
+    ///
+    /// The store is unchanged on error.
+    ///
+    /// - `ProtocolError::NotPaired` - The device is not paired
+    /// - `ProtocolError::DuplicateDeviceId` - The connection was not
+    ///   authenticated by the trusted certificate
+    /// - `ProtocolError::Certificate` - The rotation names another old
+    ///   fingerprint than the connection's
+    pub fn accept_rotation(
+        &mut self,
+        rotation: &CertificateRotation,
+        peer_fingerprint: &str,
+    ) -> Result<()> {
+        if !self.verify(&rotation.device_id, peer_fingerprint)? {
+            return Err(ProtocolError::NotPaired(rotation.device_id.to_string()));
+        }
+        if normalize_fingerprint(&rotation.old_fingerprint) != normalize_fingerprint(peer_fingerprint)
+        {
+            return Err(ProtocolError::Certificate(format!(
+                "Rotation for device {} does not start from the connection's certificate",
+                rotation.device_id
+            )));
+        }
+
+        info!("Device {} rotated its certificate", rotation.device_id);
+        self.fingerprints.insert(
+            rotation.device_id.clone(),
+            normalize_fingerprint(&rotation.new_fingerprint),
+        );
+        Ok(())
+    }
+
+    /// Forget a paired device
+    ///
+    /// Returns the stored fingerprint, if the device was paired.
+    pub fn remove(&mut self, device_id: &DeviceId) -> Option<String> {
+        self.cached.remove(device_id);
+        self.fingerprints.remove(device_id)
+    }
+
+    /// Cache plugin state for a paired device, stamped with the current time
+    ///
+    /// Replaces any state cached under the same key.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::NotPaired` if the device is not paired.
+    pub fn cache(
+        &mut self,
+        device_id: &DeviceId,
+        key: impl Into<String>,
+        value: Value,
+    ) -> Result<()> {
+        self.cache_at(device_id, key, value, SystemTime::now())
+    }
+
+    /// Cache plugin state for a paired device, stored at `now`
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::NotPaired` if the device is not paired.
+    pub fn cache_at(
+        &mut self,
+        device_id: &DeviceId,
+        key: impl Into<String>,
+        value: Value,
+        now: SystemTime,
+    ) -> Result<()> {
+        if !self.contains(device_id) {
+            return Err(ProtocolError::NotPaired(device_id.to_string()));
+        }
+        self.cached.entry(device_id.clone()).or_default().insert(
+            key.into(),
+            CachedState {
+                value,
+                stored_at: now,
+            },
+        );
+        Ok(())
+    }
+
+    /// Get plugin state cached for a device
+    pub fn cached(&self, device_id: &DeviceId, key: &str) -> Option<&CachedState> {
+        self.cached.get(device_id)?.get(key)
+    }
+
+    /// Drop plugin state cached for a device, returning it
+    pub fn invalidate(&mut self, device_id: &DeviceId, key: &str) -> Option<CachedState> {
+        self.cached.get_mut(device_id)?.remove(key)
+    }
+
+    /// Check whether a device ID is paired
+    pub fn contains(&self, device_id: &DeviceId) -> bool {
+        self.fingerprints.contains_key(device_id)
+    }
+
+    /// Get the normalized fingerprint a device is paired with
+    pub fn fingerprint(&self, device_id: &DeviceId) -> Option<&str> {
+        self.fingerprints.get(device_id).map(String::as_str)
+    }
+
+    /// Get the number of paired devices
+    pub fn len(&self) -> usize {
+        self.fingerprints.len()
+    }
+
+    /// Check whether no devices are paired
+    pub fn is_empty(&self) -> bool {
+        self.fingerprints.is_empty()
+    }
+}
+
+/// Pairing state with a remote device
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PairState {
+    /// Not paired and no request pending
+    Unpaired,
+
+    /// We sent a pair request and are waiting for the peer
+    Requested,
+
+    /// The peer sent a pair request and is waiting for the user
+    RequestedByPeer,
+
+    /// Pairing complete
+    Paired,
+}
+
+impl PairState {
+    /// Whether a pair request is pending in either direction
+    pub fn is_pending(&self) -> bool {
+        matches!(self, PairState::Requested | PairState::RequestedByPeer)
+    }
+}
+
+/// Time a pair request may stay pending before it expires
+pub const DEFAULT_PAIR_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Pairing event for UI integration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PairingEvent {
+    /// A pending request timed out; holds the state it expired from
+    Expired(PairState),
+}
+
+/// Pairing session with a single remote device
+#[derive(Debug, Clone)]
+pub struct PairingSession {
+    /// Our certificate fingerprint
+    local_fingerprint: String,
+
+    /// Remote certificate fingerprint
+    remote_fingerprint: String,
+
+    /// Current pairing state
+    state: PairState,
+
+    /// Remote device ID, if known, for allowlist matching
+    device_id: Option<DeviceId>,
+
+    /// Devices to pair with without user confirmation
+    allowlist: PairingAllowlist,
+
+    /// Accept packet queued by an allowlist match, not yet sent
+    auto_accept: Option<Packet>,
+
+    /// How long a pair request may stay pending
+    request_timeout: Duration,
+
+    /// When the pending request expires
+    expires_at: Option<Instant>,
+}
+
+impl PairingSession {
+    /// Create a new unpaired session
+    pub fn new(local_fingerprint: impl Into<String>, remote_fingerprint: impl Into<String>) -> Self {
+        Self {
+            local_fingerprint: local_fingerprint.into(),
+            remote_fingerprint: remote_fingerprint.into(),
+            state: PairState::Unpaired,
+            device_id: None,
+            allowlist: PairingAllowlist::default(),
+            auto_accept: None,
+            request_timeout: DEFAULT_PAIR_REQUEST_TIMEOUT,
+            expires_at: None,
+        }
+    }
+
+    /// Set how long a pair request may stay pending
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = timeout;
+        self
+    }
+
+    /// Set the remote device ID
+    pub fn with_device_id(mut self, device_id: DeviceId) -> Self {
+        self.device_id = Some(device_id);
+        self
+    }
+
+    /// Auto-accept pair requests from allowlisted devices
+    pub fn with_allowlist(mut self, allowlist: PairingAllowlist) -> Self {
+        self.allowlist = allowlist;
+        self
+    }
+
+    /// Take the accept packet queued by an allowlisted pair request
+    ///
+    /// Call after [`handle_packet`](Self::handle_packet) and send the packet
+    /// to the peer. Returns `None` if nothing was auto-accepted.
+    pub fn take_auto_accept(&mut self) -> Option<Packet> {
+        self.auto_accept.take()
+    }
+
+    /// Get the current pairing state
+    pub fn state(&self) -> PairState {
+        self.state
+    }
+
+    /// Get when the pending pair request expires
+    pub fn expires_at(&self) -> Option<Instant> {
+        self.expires_at
+    }
+
+    /// Expire the pending pair request if its timeout has passed at `now`
+    ///
+    /// Returns the event to report if the request expired.
+    pub fn expire_at(&mut self, now: Instant) -> Option<PairingEvent> {
+        let expires_at = self.expires_at?;
+        if now < expires_at {
+            return None;
+        }
+
+        let previous = self.state;
+        info!("Pair request expired ({:?})", previous);
+        self.set_state(PairState::Unpaired);
+        Some(PairingEvent::Expired(previous))
+    }
+
+    /// Change state, starting the expiry deadline when a request becomes pending
+    fn set_state(&mut self, state: PairState) {
+        if !state.is_pending() {
+            self.expires_at = None;
+        } else if state != self.state {
+            self.expires_at = Some(Instant::now() + self.request_timeout);
+        }
+        self.state = state;
+    }
+
+    /// Get the verification key while a pair request is pending
+    ///
+    /// Returns `None` when no request is pending, since the code is only
+    /// meaningful while the user is confirming a request.
+    pub fn verification_key(&self) -> Option<String> {
+        self.state
+            .is_pending()
+            .then(|| verification_key(&self.local_fingerprint, &self.remote_fingerprint))
+    }
+
+    /// Start pairing and return the request packet to send
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Pairing` if already paired
+    pub fn request_pairing(&mut self) -> Result<Packet> {
+        if self.state == PairState::Paired {
+            return Err(ProtocolError::Pairing("Already paired".to_string()));
+        }
+
+        self.set_state(PairState::Requested);
+        info!("Requesting pairing");
+        Ok(create_pair_packet(true))
+    }
+
+    /// Accept a pending request from the peer and return the response packet
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Pairing` if the peer has not requested pairing,
+    /// or the request has expired
+    pub fn accept(&mut self) -> Result<Packet> {
+        if self.expire_at(Instant::now()).is_some() {
+            return Err(ProtocolError::Pairing("Pair request expired".to_string()));
+        }
+        if self.state != PairState::RequestedByPeer {
+            return Err(ProtocolError::Pairing(
+                "No pair request from peer to accept".to_string(),
+            ));
+        }
+
+        self.set_state(PairState::Paired);
+        info!("Accepted pair request");
+        Ok(create_pair_packet(true))
+    }
+
+    /// Reject a pending request, or unpair, and return the packet to send
+    pub fn reject(&mut self) -> Packet {
+        self.set_state(PairState::Unpaired);
+        info!("Rejected pairing");
+        create_pair_packet(false)
+    }
+
+    /// Handle an incoming `cconnect.pair` packet and return the new state
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::InvalidPacket` if the packet is not a pair
+    /// packet or is missing the `pair` field.
+    pub fn handle_packet(&mut self, packet: &Packet) -> Result<PairState> {
+        if !packet.is_type(PACKET_TYPE_PAIR) {
+            return Err(ProtocolError::InvalidPacket(format!(
+                "Not a pair packet: {}",
+                packet.packet_type
+            )));
+        }
+
+        let pair = packet
+            .get_body_field::<bool>("pair")
+            .ok_or_else(|| ProtocolError::InvalidPacket("Missing pair field".to_string()))?;
+
+        self.expire_at(Instant::now());
+        let state = match (self.state, pair) {
+            (PairState::Requested, true) => PairState::Paired,
+            (PairState::Paired, true) => PairState::Paired,
+            (_, true) => PairState::RequestedByPeer,
+            (_, false) => PairState::Unpaired,
+        };
+        self.set_state(state);
+
+        if self.state == PairState::RequestedByPeer
+            && self
+                .allowlist
+                .contains(self.device_id.as_ref(), &self.remote_fingerprint)
+        {
+            info!(
+                "Auto-accepting pair request from allowlisted device {}",
+                self.device_id.as_ref().map_or(&self.remote_fingerprint[..], DeviceId::as_str)
+            );
+            self.set_state(PairState::Paired);
+            self.auto_accept = Some(create_pair_packet(true));
+        }
+
+        debug!("Pair packet (pair={}) -> state {:?}", pair, self.state);
+        Ok(self.state)
+    }
+}
+
+/// Run a session's pair request timeout on a tokio timer
+///
+/// Sleeps until the pending request's deadline, expires it and sends
+/// [`PairingEvent::Expired`] on `events`. The task ends without an event
+/// once no request is pending, so accepting or rejecting before the
+/// deadline cancels it. A new request needs a new timer.
+pub fn spawn_pair_request_timer(
+    session: Arc<Mutex<PairingSession>>,
+    events: mpsc::UnboundedSender<PairingEvent>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            let Some(deadline) = session.lock().await.expires_at() else {
+                return;
+            };
+            tokio::time::sleep_until(deadline.into()).await;
+
+            if let Some(event) = session.lock().await.expire_at(Instant::now()) {
+                let _ = events.send(event);
+                return;
+            }
+        }
+    })
+}
+
+/// Create a pair packet
+pub fn create_pair_packet(pair: bool) -> Packet {
+    Packet::new(PACKET_TYPE_PAIR, json!({ "pair": pair }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FP_A: &str = "AA:BB:CC:DD:EE:FF:00:11";
+    const FP_B: &str = "11:22:33:44:55:66:77:88";
+    const FP_C: &str = "99:88:77:66:55:44:33:22";
+
+    #[test]
+    fn test_verification_key_symmetric() {
+        assert_eq!(verification_key(FP_A, FP_B), verification_key(FP_B, FP_A));
+    }
+
+    #[test]
+    fn test_verification_key_differs_per_pair() {
+        assert_ne!(verification_key(FP_A, FP_B), verification_key(FP_A, FP_C));
+        assert_ne!(verification_key(FP_A, FP_B), verification_key(FP_B, FP_C));
+    }
+
+    #[test]
+    fn test_verification_key_format() {
+        let key = verification_key(FP_A, FP_B);
+        assert_eq!(key.len(), VERIFICATION_KEY_LENGTH);
+        assert!(key.chars().all(|c| c.is_ascii_hexdigit() && !c.is_ascii_lowercase()));
+
+        // Separator and case differences do not change the key
+        assert_eq!(key, verification_key("aabbccddeeff0011", FP_B));
+    }
+
+    #[test]
+    fn test_session_exposes_key_only_when_pending() {
+        let mut local = PairingSession::new(FP_A, FP_B);
+        let mut remote = PairingSession::new(FP_B, FP_A);
+        assert_eq!(local.verification_key(), None);
+
+        let request = local.request_pairing().unwrap();
+        assert_eq!(remote.handle_packet(&request).unwrap(), PairState::RequestedByPeer);
+
+        // Both ends display the same code
+        assert!(local.verification_key().is_some());
+        assert_eq!(local.verification_key(), remote.verification_key());
+
+        let response = remote.accept().unwrap();
+        assert_eq!(local.handle_packet(&response).unwrap(), PairState::Paired);
+        assert_eq!(local.verification_key(), None);
+        assert_eq!(remote.verification_key(), None);
+    }
+
+    #[test]
+    fn test_allowlisted_device_auto_pairs() {
+        let kiosk = DeviceId::generate();
+        let allowlist = PairingAllowlist::new().allow_device(kiosk.clone());
+        let mut session = PairingSession::new(FP_A, FP_B)
+            .with_device_id(kiosk)
+            .with_allowlist(allowlist);
+
+        let state = session.handle_packet(&create_pair_packet(true)).unwrap();
+        assert_eq!(state, PairState::Paired);
+
+        let accept = session.take_auto_accept().unwrap();
+        assert_eq!(accept.body["pair"], true);
+        assert!(session.take_auto_accept().is_none());
+
+        // Matching by fingerprint works without a device ID
+        let allowlist = PairingAllowlist::new().allow_fingerprint("1122334455667788");
+        let mut session = PairingSession::new(FP_A, FP_B).with_allowlist(allowlist);
+        session.handle_packet(&create_pair_packet(true)).unwrap();
+        assert_eq!(session.state(), PairState::Paired);
+    }
+
+    #[test]
+    fn test_unlisted_device_stays_pending() {
+        let kiosk = DeviceId::generate();
+        let allowlist = PairingAllowlist::new()
+            .allow_device(kiosk.clone())
+            .allow_fingerprint(FP_C);
+        let mut session = PairingSession::new(FP_A, FP_B)
+            .with_device_id(DeviceId::generate())
+            .with_allowlist(allowlist);
+
+        let state = session.handle_packet(&create_pair_packet(true)).unwrap();
+        assert_eq!(state, PairState::RequestedByPeer);
+        assert!(session.take_auto_accept().is_none());
+
+        // No allowlist: manual confirmation
+        let mut session = PairingSession::new(FP_A, FP_B).with_device_id(kiosk);
+        session.handle_packet(&create_pair_packet(true)).unwrap();
+        assert_eq!(session.state(), PairState::RequestedByPeer);
+    }
+
+    #[test]
+    fn test_session_reject() {
+        let mut session = PairingSession::new(FP_A, FP_B);
+        session.handle_packet(&create_pair_packet(true)).unwrap();
+
+        let packet = session.reject();
+        assert_eq!(packet.body["pair"], false);
+        assert_eq!(session.state(), PairState::Unpaired);
+        assert!(session.accept().is_err());
+    }
+
+    #[test]
+    fn test_paired_devices_flags_duplicate_id() {
+        let vm_image = DeviceId::generate();
+        let mut paired = PairedDevices::new();
+        paired.trust(vm_image.clone(), FP_A).unwrap();
+        assert!(paired.verify(&vm_image, FP_A).unwrap());
+        assert!(!paired.verify(&DeviceId::generate(), FP_B).unwrap());
+
+        // A clone presents the same ID with its own certificate
+        match paired.trust(vm_image.clone(), FP_B) {
+            Err(ProtocolError::DuplicateDeviceId {
+                device_id,
+                stored,
+                presented,
+            }) => {
+                assert_eq!(device_id, vm_image.as_str());
+                assert_eq!(stored, normalize_fingerprint(FP_A));
+                assert_eq!(presented, normalize_fingerprint(FP_B));
+            }
+            other => panic!("expected DuplicateDeviceId, got {:?}", other),
+        }
+        assert!(matches!(
+            paired.verify(&vm_image, FP_B),
+            Err(ProtocolError::DuplicateDeviceId { .. })
+        ));
+
+        // Trust is unchanged until the user decides
+        assert_eq!(paired.fingerprint(&vm_image), Some(normalize_fingerprint(FP_A).as_str()));
+        assert_eq!(paired.replace(vm_image.clone(), FP_B), Some(normalize_fingerprint(FP_A)));
+        assert!(paired.verify(&vm_image, FP_B).unwrap());
+        assert_eq!(paired.len(), 1);
+    }
+
+    #[test]
+    fn test_paired_devices_cache() {
+        let pixel = DeviceId::generate();
+        let mut paired = PairedDevices::new();
+        let now = SystemTime::now();
+        assert!(matches!(
+            paired.cache_at(&pixel, "camera", json!(1), now),
+            Err(ProtocolError::NotPaired(_))
+        ));
+
+        paired.trust(pixel.clone(), "AA:BB").unwrap();
+        paired.cache_at(&pixel, "camera", json!(1), now).unwrap();
+        let cached = paired.cached(&pixel, "camera").unwrap();
+        assert_eq!(cached.value, json!(1));
+        assert_eq!(cached.age_at(now + Duration::from_secs(60)), Duration::from_secs(60));
+        assert_eq!(cached.age_at(now - Duration::from_secs(60)), Duration::ZERO);
+
+        // A new certificate may be a different phone
+        paired.replace(pixel.clone(), "CC:DD");
+        assert!(paired.cached(&pixel, "camera").is_none());
+
+        paired.cache_at(&pixel, "camera", json!(2), now).unwrap();
+        assert_eq!(paired.invalidate(&pixel, "camera").unwrap().value, json!(2));
+        paired.cache_at(&pixel, "camera", json!(3), now).unwrap();
+        paired.remove(&pixel);
+        assert!(paired.cached(&pixel, "camera").is_none());
+    }
+
+    #[test]
+    fn test_paired_devices_accept_rotation() {
+        let phone = DeviceId::generate();
+        let mut paired = PairedDevices::new();
+        paired.trust(phone.clone(), FP_A).unwrap();
+
+        let rotation = CertificateRotation {
+            device_id: phone.clone(),
+            old_fingerprint: FP_A.to_string(),
+            new_fingerprint: FP_B.to_string(),
+        };
+
+        // Anyone can name the trusted fingerprint, but an on-path host
+        // cannot authenticate a connection with it
+        let hijack = CertificateRotation {
+            new_fingerprint: FP_C.to_string(),
+            ..rotation.clone()
+        };
+        assert!(matches!(
+            paired.accept_rotation(&hijack, FP_C),
+            Err(ProtocolError::DuplicateDeviceId { .. })
+        ));
+        assert!(paired.verify(&phone, FP_A).unwrap());
+
+        // The rotation must start from the connection's certificate
+        let mismatched = CertificateRotation {
+            old_fingerprint: FP_C.to_string(),
+            ..rotation.clone()
+        };
+        assert!(matches!(
+            paired.accept_rotation(&mismatched, FP_A),
+            Err(ProtocolError::Certificate(_))
+        ));
+
+        paired.accept_rotation(&rotation, FP_A).unwrap();
+        assert!(paired.verify(&phone, FP_B).unwrap());
+
+        // Replaying the rotation over an old-certificate connection fails
+        assert!(matches!(
+            paired.accept_rotation(&rotation, FP_A),
+            Err(ProtocolError::DuplicateDeviceId { .. })
+        ));
+
+        let unknown = CertificateRotation {
+            device_id: DeviceId::generate(),
+            ..rotation
+        };
+        assert!(matches!(
+            paired.accept_rotation(&unknown, FP_A),
+            Err(ProtocolError::NotPaired(_))
+        ));
+        assert_eq!(paired.len(), 1);
+    }
+
+    #[test]
+    fn test_expired_request_cannot_be_accepted() {
+        let mut session = PairingSession::new(FP_A, FP_B);
+        session.handle_packet(&create_pair_packet(true)).unwrap();
+        let deadline = session.expires_at().unwrap();
+
+        assert_eq!(session.expire_at(deadline - Duration::from_millis(1)), None);
+        assert_eq!(
+            session.expire_at(deadline),
+            Some(PairingEvent::Expired(PairState::RequestedByPeer))
+        );
+        assert_eq!(session.state(), PairState::Unpaired);
+        assert!(session.expires_at().is_none());
+        assert!(session.accept().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_pair_request_timer_expires_request() {
+        let session = Arc::new(Mutex::new(
+            PairingSession::new(FP_A, FP_B).with_request_timeout(Duration::from_millis(50)),
+        ));
+        session.lock().await.request_pairing().unwrap();
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let timer = spawn_pair_request_timer(session.clone(), tx);
+
+        let event = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await.unwrap();
+        assert_eq!(event, Some(PairingEvent::Expired(PairState::Requested)));
+        assert_eq!(session.lock().await.state(), PairState::Unpaired);
+        timer.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_pair_request_timer_cancelled_by_accept() {
+        let session = Arc::new(Mutex::new(
+            PairingSession::new(FP_A, FP_B).with_request_timeout(Duration::from_millis(50)),
+        ));
+        session.lock().await.handle_packet(&create_pair_packet(true)).unwrap();
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let timer = spawn_pair_request_timer(session.clone(), tx);
+        session.lock().await.accept().unwrap();
+
+        timer.await.unwrap();
+        assert_eq!(rx.recv().await, None);
+        assert_eq!(session.lock().await.state(), PairState::Paired);
+    }
+}
@@ -25,14 +25,47 @@
 //!
 //! - Lexicographically **smaller** device ID → Initiates TCP connection → TLS SERVER
 //! - Lexicographically **larger** device ID → Accepts TCP connection → TLS CLIENT
+//!
+//! ## Session Resumption
+//!
+//! [`TlsConfig`] caches TLS sessions in both roles, so reconnecting to a
+//! recently connected peer (after a Wi-Fi blip, say) resumes the earlier
+//! session instead of paying for a full handshake. Reuse one `TlsConfig` for
+//! all connections to benefit. If the peer no longer knows the session, the
+//! handshake falls back to a full one. A resumed session keeps the peer
+//! certificate it was established with, so [`TlsConnection::peer_fingerprint`]
+//! is unaffected; [`TlsConnection::is_resumed`] tells the two apart.
+//! Resumption can be disabled for debugging with
+//! [`TlsConfig::with_session_resumption`].
+//!
+//! ## Link Quality
+//!
+//! A [`TlsConnection`] times its pings: the time from a sent `ping` to the
+//! next `ping` from the peer (the ping plugin answers every ping) is an RTT
+//! sample, and a ping that goes unanswered for
+//! [`DEFAULT_HEARTBEAT_TIMEOUT`](crate::network::reachability::DEFAULT_HEARTBEAT_TIMEOUT)
+//! counts as lost. [`TlsConnection::network_stats`] reports the resulting
+//! RTT, jitter and loss, also through the [`Transport`] implementation.
 
 use crate::crypto::CertificateInfo;
 use crate::error::{ProtocolError, Result};
-use crate::protocol::Packet;
+use crate::network::reachability::ReachabilityProbe;
+use crate::network::transport::{
+    encode_batch, LatencyCategory, NetworkStats, Transport, TransportAddress,
+    TransportCapabilities,
+};
+use crate::plugins::PluginManager;
+use crate::protocol::{
+    check_peer_version, min_supported_version, DeviceId, Packet, PacketCodec, PacketType,
+};
+use async_trait::async_trait;
 use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime};
+use rustls::client::Resumption;
+use rustls::server::{NoServerSessionStorage, ServerSessionMemoryCache};
 use rustls::{ClientConfig, ServerConfig};
 use std::io;
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::{TcpListener, TcpStream};
@@ -46,12 +79,26 @@ const TLS_TIMEOUT: Duration = Duration::from_secs(300);
 /// Maximum packet size (10MB - supports file transfer metadata)
 const MAX_PACKET_SIZE: usize = 10 * 1024 * 1024;
 
+/// Number of ports after the base port tried by [`TlsServer::bind_from`]
+pub const TCP_PORT_FALLBACK_RANGE: u16 = 48;
+
+/// Number of TLS sessions cached for resumption, per role
+const TLS_SESSION_CACHE_SIZE: usize = 256;
+
+/// Number of TLS 1.3 session tickets sent to a client after a full handshake
+const TLS13_TICKETS: usize = 4;
+
 /// Trust-On-First-Use certificate verifier
 ///
 /// Accepts any certificate without verification. Certificate fingerprint
 /// verification happens at the application layer during pairing.
-#[derive(Debug)]
-struct TofuCertVerifier;
+///
+/// Resumed handshakes skip certificate verification, so `verified` staying
+/// unset after a handshake means the session was resumed.
+#[derive(Debug, Default)]
+struct TofuCertVerifier {
+    verified: Arc<AtomicBool>,
+}
 
 impl rustls::client::danger::ServerCertVerifier for TofuCertVerifier {
     fn verify_server_cert(
@@ -64,6 +111,7 @@ impl rustls::client::danger::ServerCertVerifier for TofuCertVerifier {
     ) -> std::result::Result<rustls::client::danger::ServerCertVerified, rustls::Error> {
         // Accept any certificate (TOFU model)
         // Application layer will verify SHA256 fingerprint during pairing
+        self.verified.store(true, Ordering::Relaxed);
         Ok(rustls::client::danger::ServerCertVerified::assertion())
     }
 
@@ -102,8 +150,12 @@ impl rustls::client::danger::ServerCertVerifier for TofuCertVerifier {
 }
 
 /// Trust-On-First-Use client certificate verifier
-#[derive(Debug)]
-struct TofuClientCertVerifier;
+///
+/// Sets `verified` like [`TofuCertVerifier`].
+#[derive(Debug, Default)]
+struct TofuClientCertVerifier {
+    verified: Arc<AtomicBool>,
+}
 
 impl rustls::server::danger::ClientCertVerifier for TofuClientCertVerifier {
     fn root_hint_subjects(&self) -> &[rustls::DistinguishedName] {
@@ -118,6 +170,7 @@ impl rustls::server::danger::ClientCertVerifier for TofuClientCertVerifier {
         _now: UnixTime,
     ) -> std::result::Result<rustls::server::danger::ClientCertVerified, rustls::Error> {
         // Accept any certificate (TOFU model)
+        self.verified.store(true, Ordering::Relaxed);
         Ok(rustls::server::danger::ClientCertVerified::assertion())
     }
 
@@ -187,6 +240,7 @@ pub fn should_initiate_connection(our_device_id: &str, peer_device_id: &str) ->
 /// TLS configuration for KDE Connect
 ///
 /// Provides both client and server configurations for inverted TLS roles.
+/// Session resumption is enabled; see the [module docs](self#session-resumption).
 pub struct TlsConfig {
     /// Client configuration (used by TCP acceptor)
     client_config: Arc<ClientConfig>,
@@ -194,6 +248,20 @@ pub struct TlsConfig {
     server_config: Arc<ServerConfig>,
 }
 
+/// Per-connection TLS configuration that reports whether the handshake resumed
+struct HandshakeConfig<C> {
+    config: Arc<C>,
+    /// Set once the peer certificate is verified in a full handshake
+    verified: Arc<AtomicBool>,
+}
+
+impl<C> HandshakeConfig<C> {
+    /// Check whether the completed handshake resumed an earlier session
+    fn resumed(&self) -> bool {
+        !self.verified.load(Ordering::Relaxed)
+    }
+}
+
 impl TlsConfig {
     /// Create TLS configuration from certificate
     ///
@@ -209,28 +277,53 @@ impl TlsConfig {
             .map_err(|e| ProtocolError::Certificate(format!("Invalid private key: {:?}", e)))?;
 
         // Create client config (for accepting TCP connections)
-        let client_config = ClientConfig::builder()
+        let mut client_config = ClientConfig::builder()
             .dangerous()
-            .with_custom_certificate_verifier(Arc::new(TofuCertVerifier))
+            .with_custom_certificate_verifier(Arc::new(TofuCertVerifier::default()))
             .with_client_auth_cert(vec![cert_der.clone()], key_der.clone_key())
             .map_err(|e| {
                 ProtocolError::Certificate(format!("Failed to create client config: {}", e))
             })?;
 
         // Create server config (for initiating TCP connections)
-        let server_config = ServerConfig::builder()
-            .with_client_cert_verifier(Arc::new(TofuClientCertVerifier))
+        let mut server_config = ServerConfig::builder()
+            .with_client_cert_verifier(Arc::new(TofuClientCertVerifier::default()))
             .with_single_cert(vec![cert_der], key_der)
             .map_err(|e| {
                 ProtocolError::Certificate(format!("Failed to create server config: {}", e))
             })?;
 
+        // Cache sessions in both roles for resumption on reconnect
+        client_config.resumption = Resumption::in_memory_sessions(TLS_SESSION_CACHE_SIZE);
+        server_config.session_storage = ServerSessionMemoryCache::new(TLS_SESSION_CACHE_SIZE);
+        server_config.send_tls13_tickets = TLS13_TICKETS;
+
         Ok(Self {
             client_config: Arc::new(client_config),
             server_config: Arc::new(server_config),
         })
     }
 
+    /// Enable or disable TLS session resumption
+    ///
+    /// Enabled by default. Disabling it forces a full handshake on every
+    /// connection, which is useful when debugging handshake problems.
+    pub fn with_session_resumption(mut self, enabled: bool) -> Self {
+        let client_config = Arc::make_mut(&mut self.client_config);
+        let server_config = Arc::make_mut(&mut self.server_config);
+
+        if enabled {
+            client_config.resumption = Resumption::in_memory_sessions(TLS_SESSION_CACHE_SIZE);
+            server_config.session_storage = ServerSessionMemoryCache::new(TLS_SESSION_CACHE_SIZE);
+            server_config.send_tls13_tickets = TLS13_TICKETS;
+        } else {
+            client_config.resumption = Resumption::disabled();
+            server_config.session_storage = Arc::new(NoServerSessionStorage {});
+            server_config.send_tls13_tickets = 0;
+        }
+        self
+    }
+
     /// Get client configuration (for TCP acceptor → TLS client)
     pub fn client_config(&self) -> Arc<ClientConfig> {
         Arc::clone(&self.client_config)
@@ -240,6 +333,38 @@ impl TlsConfig {
     pub fn server_config(&self) -> Arc<ServerConfig> {
         Arc::clone(&self.server_config)
     }
+
+    /// Get a client configuration for one handshake, sharing the session cache
+    fn client_handshake(&self) -> HandshakeConfig<ClientConfig> {
+        let verifier = TofuCertVerifier::default();
+        let verified = Arc::clone(&verifier.verified);
+
+        let mut config = (*self.client_config).clone();
+        config.dangerous().set_certificate_verifier(Arc::new(verifier));
+
+        HandshakeConfig {
+            config: Arc::new(config),
+            verified,
+        }
+    }
+
+    /// Get a server configuration for one handshake, sharing the session cache
+    fn server_handshake(&self) -> HandshakeConfig<ServerConfig> {
+        let verifier = TofuClientCertVerifier::default();
+        let verified = Arc::clone(&verifier.verified);
+
+        let shared = &self.server_config;
+        let mut config = ServerConfig::builder()
+            .with_client_cert_verifier(Arc::new(verifier))
+            .with_cert_resolver(Arc::clone(&shared.cert_resolver));
+        config.session_storage = Arc::clone(&shared.session_storage);
+        config.send_tls13_tickets = shared.send_tls13_tickets;
+
+        HandshakeConfig {
+            config: Arc::new(config),
+            verified,
+        }
+    }
 }
 
 /// TLS connection to a remote device
@@ -249,7 +374,23 @@ pub struct TlsConnection {
     /// Remote address
     remote_addr: SocketAddr,
     /// Device ID of remote peer (if known)
-    device_id: Option<String>,
+    device_id: Option<DeviceId>,
+    /// Partially received packet data, kept across reads
+    codec: PacketCodec,
+    /// Whether the handshake resumed an earlier session
+    resumed: bool,
+    /// Ping round trips, feeding [`network_stats`](Self::network_stats)
+    heartbeat: ReachabilityProbe,
+}
+
+impl std::fmt::Debug for TlsConnection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TlsConnection")
+            .field("remote_addr", &self.remote_addr)
+            .field("device_id", &self.device_id)
+            .field("resumed", &self.resumed)
+            .finish_non_exhaustive()
+    }
 }
 
 impl TlsConnection {
@@ -304,7 +445,8 @@ impl TlsConnection {
         debug!("Plain-text identity packet sent to {}", addr);
 
         // Create TLS acceptor with SERVER config (inverted role!)
-        let acceptor = TlsAcceptor::from(config.server_config());
+        let handshake = config.server_handshake();
+        let acceptor = TlsAcceptor::from(Arc::clone(&handshake.config));
 
         // Perform TLS handshake as SERVER
         let tls_stream = timeout(TLS_TIMEOUT, acceptor.accept(tcp_stream))
@@ -323,13 +465,15 @@ impl TlsConnection {
                 ))
             })?;
 
-        info!("TLS connection established to {} (as TLS SERVER)", addr);
+        let resumed = handshake.resumed();
+        info!(
+            "TLS connection established to {} (as TLS SERVER, resumed: {})",
+            addr, resumed
+        );
 
-        Ok(Self {
-            stream: tokio_rustls::TlsStream::Server(tls_stream),
-            remote_addr: addr,
-            device_id: None,
-        })
+        let mut connection = Self::from_stream(tokio_rustls::TlsStream::Server(tls_stream), addr);
+        connection.resumed = resumed;
+        Ok(connection)
     }
 
     /// Create from an accepted TLS stream
@@ -338,17 +482,34 @@ impl TlsConnection {
             stream,
             remote_addr,
             device_id: None,
+            codec: PacketCodec::with_max_packet_size(MAX_PACKET_SIZE),
+            resumed: false,
+            heartbeat: ReachabilityProbe::new(),
         }
     }
 
     /// Set the device ID for this connection
-    pub fn set_device_id(&mut self, device_id: String) {
+    pub fn set_device_id(&mut self, device_id: DeviceId) {
         self.device_id = Some(device_id);
     }
 
     /// Get the device ID if known
-    pub fn device_id(&self) -> Option<&str> {
-        self.device_id.as_deref()
+    pub fn device_id(&self) -> Option<&DeviceId> {
+        self.device_id.as_ref()
+    }
+
+    /// Get the SHA-256 fingerprint of the peer's certificate
+    ///
+    /// Check it against [`PairedDevices`](crate::crypto::pairing::PairedDevices)
+    /// before trusting the device ID the peer claims.
+    pub fn peer_fingerprint(&self) -> Option<String> {
+        let certificates = match &self.stream {
+            TlsStream::Client(stream) => stream.get_ref().1.peer_certificates(),
+            TlsStream::Server(stream) => stream.get_ref().1.peer_certificates(),
+        };
+        certificates
+            .and_then(|chain| chain.first())
+            .map(|cert| CertificateInfo::calculate_fingerprint(cert))
     }
 
     /// Get remote address
@@ -356,10 +517,50 @@ impl TlsConnection {
         self.remote_addr
     }
 
+    /// Check whether the TLS handshake resumed an earlier session
+    ///
+    /// `false` means a full handshake was performed.
+    pub fn is_resumed(&self) -> bool {
+        self.resumed
+    }
+
+    /// Get the RTT, jitter and loss estimated from ping round trips
+    ///
+    /// See the [module docs](self#link-quality).
+    pub fn network_stats(&self) -> NetworkStats {
+        self.heartbeat.network_stats()
+    }
+
     /// Send a packet over the TLS connection
     pub async fn send_packet(&mut self, packet: &Packet) -> Result<()> {
-        let bytes = packet.to_bytes()?;
+        let bytes = Self::encode(packet)?;
+        self.write_packet(packet, &bytes).await
+    }
 
+    /// Send a packet on a plugin's behalf, enforcing its outgoing rate limit
+    ///
+    /// The packet is serialized once and its length checked with
+    /// [`PluginManager::check_outgoing`] before anything is written.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::RateLimited` if the plugin is over its limit,
+    /// in which case nothing is sent, or any error from
+    /// [`send_packet`](Self::send_packet).
+    pub async fn send_plugin_packet(
+        &mut self,
+        plugins: &PluginManager,
+        plugin: &str,
+        packet: &Packet,
+    ) -> Result<()> {
+        let bytes = Self::encode(packet)?;
+        plugins.check_outgoing(plugin, packet, bytes.len())?;
+        self.write_packet(packet, &bytes).await
+    }
+
+    /// Serialize a packet, refusing ones over [`MAX_PACKET_SIZE`]
+    fn encode(packet: &Packet) -> Result<Vec<u8>> {
+        let bytes = packet.to_bytes()?;
         if bytes.len() > MAX_PACKET_SIZE {
             return Err(ProtocolError::InvalidPacket(format!(
                 "Packet too large: {} bytes (max {})",
@@ -367,7 +568,10 @@ impl TlsConnection {
                 MAX_PACKET_SIZE
             )));
         }
+        Ok(bytes)
+    }
 
+    async fn write_packet(&mut self, packet: &Packet, bytes: &[u8]) -> Result<()> {
         debug!(
             "Sending packet '{}' ({} bytes) to {}",
             packet.packet_type,
@@ -376,37 +580,74 @@ impl TlsConnection {
         );
 
         // KDE Connect protocol: Send packet data followed by newline
-        self.stream.write_all(&bytes).await?;
+        self.stream.write_all(bytes).await?;
         self.stream.flush().await?;
+        if is_ping(packet) {
+            self.ping_sent();
+        }
 
         debug!("Packet sent successfully to {}", self.remote_addr);
         Ok(())
     }
 
+    /// Send several packets with a single write and flush
+    ///
+    /// All packets are size-checked before anything is written, so an
+    /// oversized packet rejects the whole batch.
+    pub async fn send_batch(&mut self, packets: &[Packet]) -> Result<()> {
+        let bytes = encode_batch(packets, MAX_PACKET_SIZE)?;
+
+        debug!(
+            "Sending batch of {} packets ({} bytes) to {}",
+            packets.len(),
+            bytes.len(),
+            self.remote_addr
+        );
+
+        self.stream.write_all(&bytes).await?;
+        self.stream.flush().await?;
+        if packets.iter().any(is_ping) {
+            self.ping_sent();
+        }
+
+        debug!("Batch sent successfully to {}", self.remote_addr);
+        Ok(())
+    }
+
+    /// Start timing a ping round trip
+    fn ping_sent(&mut self) {
+        // Expire the previous ping first so an unanswered one counts as lost
+        self.heartbeat.update();
+        self.heartbeat.ping_sent();
+    }
+
     /// Receive a packet from the TLS connection
     pub async fn receive_packet(&mut self) -> Result<Packet> {
         debug!("Waiting for packet from {}", self.remote_addr);
 
-        // Read until newline (packet delimiter)
-        let mut packet_bytes = Vec::new();
-        let mut byte_buf = [0u8; 1];
+        // Bytes past the end of a packet stay in the codec for the next call
+        let mut read_buf = [0u8; 4096];
 
-        loop {
-            match timeout(TLS_TIMEOUT, self.stream.read_exact(&mut byte_buf)).await {
-                Ok(Ok(_)) => {
-                    packet_bytes.push(byte_buf[0]);
-                    if byte_buf[0] == b'\n' {
-                        break;
-                    }
-                    if packet_bytes.len() > MAX_PACKET_SIZE {
-                        error!("Packet too large: {} bytes", packet_bytes.len());
-                        return Err(ProtocolError::InvalidPacket(format!(
-                            "Packet too large: {} bytes (max {})",
-                            packet_bytes.len(),
-                            MAX_PACKET_SIZE
+        let packet = loop {
+            if let Some(packet) = self.codec.decode()? {
+                break packet;
+            }
+
+            match timeout(TLS_TIMEOUT, self.stream.read(&mut read_buf)).await {
+                Ok(Ok(0)) => match self.codec.decode_eof() {
+                    Ok(Some(packet)) => break packet,
+                    Ok(None) => {
+                        return Err(ProtocolError::Io(io::Error::new(
+                            io::ErrorKind::UnexpectedEof,
+                            "Connection closed",
                         )));
                     }
-                }
+                    Err(e) => {
+                        warn!("Connection to {} closed mid-packet: {}", self.remote_addr, e);
+                        return Err(e);
+                    }
+                },
+                Ok(Ok(n)) => self.codec.feed(&read_buf[..n]),
                 Ok(Err(e)) => {
                     warn!("Error reading packet from {}: {}", self.remote_addr, e);
                     return Err(ProtocolError::Io(e));
@@ -418,19 +659,16 @@ impl TlsConnection {
                     )));
                 }
             }
-        }
-
-        debug!(
-            "Received packet ({} bytes) from {}",
-            packet_bytes.len(),
-            self.remote_addr
-        );
+        };
 
-        let packet = Packet::from_bytes(&packet_bytes)?;
         debug!(
             "Received packet type '{}' from {}",
             packet.packet_type, self.remote_addr
         );
+        if is_ping(&packet) {
+            self.heartbeat.update();
+            self.heartbeat.record_seen();
+        }
 
         Ok(packet)
     }
@@ -443,10 +681,51 @@ impl TlsConnection {
     }
 }
 
+#[async_trait]
+impl Transport for TlsConnection {
+    fn capabilities(&self) -> TransportCapabilities {
+        TransportCapabilities {
+            max_packet_size: MAX_PACKET_SIZE,
+            reliable: true,
+            connection_oriented: true,
+            latency: LatencyCategory::Low,
+        }
+    }
+
+    fn remote_address(&self) -> TransportAddress {
+        TransportAddress::Tcp(self.remote_addr)
+    }
+
+    async fn send_packet(&mut self, packet: &Packet) -> Result<()> {
+        TlsConnection::send_packet(self, packet).await
+    }
+
+    async fn send_batch(&mut self, packets: &[Packet]) -> Result<()> {
+        TlsConnection::send_batch(self, packets).await
+    }
+
+    async fn receive_packet(&mut self) -> Result<Packet> {
+        TlsConnection::receive_packet(self).await
+    }
+
+    async fn close(self: Box<Self>) -> Result<()> {
+        TlsConnection::close(*self).await
+    }
+
+    fn network_stats(&self) -> NetworkStats {
+        TlsConnection::network_stats(self)
+    }
+}
+
+/// Check whether a packet is a ping, in either namespace
+fn is_ping(packet: &Packet) -> bool {
+    PacketType::parse(&packet.packet_type) == Some(PacketType::Ping)
+}
+
 /// Device information for identity packets
 #[derive(Debug, Clone)]
 pub struct DeviceInfo {
-    pub device_id: String,
+    pub device_id: DeviceId,
     pub device_name: String,
     pub device_type: String,
     pub protocol_version: i32,
@@ -475,10 +754,13 @@ impl TlsServer {
     /// * `addr` - Local address to bind to
     /// * `cert_info` - Our device certificate
     /// * `device_info` - Our device information for identity packet
+    ///
+    /// The advertised `tcp_port` is replaced with the port actually bound,
+    /// so binding port 0 still produces a usable identity packet.
     pub async fn new(
         addr: SocketAddr,
         cert_info: &CertificateInfo,
-        device_info: DeviceInfo,
+        mut device_info: DeviceInfo,
     ) -> Result<Self> {
         info!("Starting TLS server on {}", addr);
 
@@ -490,6 +772,7 @@ impl TlsServer {
         let local_addr = listener.local_addr()?;
 
         info!("TLS server listening on {}", local_addr);
+        device_info.tcp_port = local_addr.port();
 
         Ok(Self {
             listener,
@@ -499,11 +782,50 @@ impl TlsServer {
         })
     }
 
+    /// Create a TLS server on the first free port from `base_port`
+    ///
+    /// Tries `base_port` through `base_port + TCP_PORT_FALLBACK_RANGE`, so
+    /// several instances can share a host. The chosen port is advertised as
+    /// `tcpPort` in our identity packet.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Io` with `AddrInUse` if every port in the
+    /// range is taken.
+    pub async fn bind_from(
+        ip: IpAddr,
+        base_port: u16,
+        cert_info: &CertificateInfo,
+        device_info: DeviceInfo,
+    ) -> Result<Self> {
+        let last_port = base_port.saturating_add(TCP_PORT_FALLBACK_RANGE);
+
+        for port in base_port..=last_port {
+            match Self::new(SocketAddr::new(ip, port), cert_info, device_info.clone()).await {
+                Ok(server) => return Ok(server),
+                Err(ProtocolError::Io(e)) if e.kind() == io::ErrorKind::AddrInUse => {
+                    debug!("TCP port {} in use, trying next", port);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        Err(ProtocolError::Io(io::Error::new(
+            io::ErrorKind::AddrInUse,
+            format!("Failed to bind to any port in range {}-{}", base_port, last_port),
+        )))
+    }
+
     /// Get the local address
     pub fn local_addr(&self) -> SocketAddr {
         self.local_addr
     }
 
+    /// Get our device information, with `tcp_port` set to the bound port
+    pub fn device_info(&self) -> &DeviceInfo {
+        &self.device_info
+    }
+
     /// Accept an incoming connection with KDE Connect handshake
     ///
     /// KDE Connect protocol v8 handshake:
@@ -593,11 +915,20 @@ impl TlsServer {
 
         info!("Received identity from {} at {}", device_name, remote_addr);
 
+        // Turn away peers on an unsupported protocol version before the handshake
+        let ours = self.device_info.protocol_version as u32;
+        let protocol_version = check_peer_version(ours, peer_protocol_version(&remote_identity))
+            .map_err(|e| {
+                warn!("Rejecting {} at {}: {}", device_name, remote_addr, e);
+                e
+            })?;
+
         // KDE Connect quirk: TCP acceptor acts as TLS CLIENT
         debug!("Starting TLS handshake as CLIENT with {}", remote_addr);
 
         // Create TLS connector with CLIENT config (inverted role!)
-        let connector = TlsConnector::from(self.config.client_config());
+        let handshake = self.config.client_handshake();
+        let connector = TlsConnector::from(Arc::clone(&handshake.config));
 
         // Use IP address as SNI name (KDE Connect doesn't use real domain names)
         let server_name = ServerName::try_from(remote_addr.ip().to_string())
@@ -621,15 +952,13 @@ impl TlsServer {
                 ))
             })?;
 
-        info!("TLS connection established with {} at {}", device_name, remote_addr);
+        let resumed = handshake.resumed();
+        info!(
+            "TLS connection established with {} at {} (resumed: {})",
+            device_name, remote_addr, resumed
+        );
 
         // Protocol v8: Post-TLS identity exchange
-        let protocol_version = remote_identity
-            .body
-            .get("protocolVersion")
-            .and_then(|v| v.as_i64())
-            .unwrap_or(7) as i32;
-
         if protocol_version >= 8 {
             debug!(
                 "Protocol v8 detected - performing post-TLS identity exchange with {}",
@@ -739,6 +1068,11 @@ impl TlsServer {
                     "Device ID changed during TLS handshake".to_string(),
                 ));
             }
+            check_peer_version(ours, peer_protocol_version(&encrypted_identity))
+                .map_err(|e| {
+                    warn!("Rejecting {}: {}", remote_addr, e);
+                    e
+                })?;
 
             info!(
                 "Protocol v8 post-TLS identity exchange completed successfully with {}",
@@ -746,25 +1080,47 @@ impl TlsServer {
             );
 
             // Use encrypted identity as authoritative
-            Ok((
-                TlsConnection::from_stream(tokio_rustls::TlsStream::Client(tls_stream), remote_addr),
-                encrypted_identity,
-            ))
+            let mut connection =
+                TlsConnection::from_stream(TlsStream::Client(tls_stream), remote_addr);
+            connection.resumed = resumed;
+            Ok((connection, encrypted_identity))
         } else {
             // Protocol v7: No post-TLS identity exchange
-            Ok((
-                TlsConnection::from_stream(tokio_rustls::TlsStream::Client(tls_stream), remote_addr),
-                remote_identity,
-            ))
+            let mut connection =
+                TlsConnection::from_stream(TlsStream::Client(tls_stream), remote_addr);
+            connection.resumed = resumed;
+            Ok((connection, remote_identity))
         }
     }
 }
 
+/// Protocol version an identity packet declares
+///
+/// A peer that does not state its version is treated as the oldest
+/// supported one.
+fn peer_protocol_version(identity: &Packet) -> u32 {
+    identity
+        .body
+        .get("protocolVersion")
+        .and_then(|v| v.as_u64())
+        .map_or(min_supported_version() as u32, |v| {
+            u32::try_from(v).unwrap_or(u32::MAX)
+        })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use serde_json::json;
 
+    const DEVICE1: &str = "device1_0123456789abcdef0123456789";
+    const DEVICE2: &str = "device2_0123456789abcdef0123456789";
+    const TEST_DEVICE: &str = "test_device_0123456789abcdef012345";
+
+    fn device_id(id: &str) -> DeviceId {
+        DeviceId::parse(id).unwrap()
+    }
+
     #[test]
     fn test_should_initiate_connection() {
         // Device with smaller ID should initiate
@@ -783,16 +1139,16 @@ mod tests {
 
     #[test]
     fn test_tls_config_creation() {
-        let cert_info = CertificateInfo::generate("test_device").unwrap();
+        let cert_info = CertificateInfo::generate(device_id(TEST_DEVICE)).unwrap();
         let config = TlsConfig::new(&cert_info);
         assert!(config.is_ok());
     }
 
     #[tokio::test]
     async fn test_tls_server_creation() {
-        let cert_info = CertificateInfo::generate("test_device").unwrap();
+        let cert_info = CertificateInfo::generate(device_id(TEST_DEVICE)).unwrap();
         let device_info = DeviceInfo {
-            device_id: "test_device".to_string(),
+            device_id: device_id(TEST_DEVICE),
             device_name: "Test Device".to_string(),
             device_type: "desktop".to_string(),
             protocol_version: 8,
@@ -804,17 +1160,49 @@ mod tests {
         let server_addr = "127.0.0.1:0".parse().unwrap();
         let server = TlsServer::new(server_addr, &cert_info, device_info).await;
         assert!(server.is_ok());
+
+        // The advertised port is the one actually bound
+        let server = server.unwrap();
+        assert_eq!(server.device_info().tcp_port, server.local_addr().port());
+    }
+
+    #[tokio::test]
+    async fn test_tls_server_bind_from_skips_taken_port() {
+        let cert_info = CertificateInfo::generate(device_id(TEST_DEVICE)).unwrap();
+        let device_info = DeviceInfo {
+            device_id: device_id(TEST_DEVICE),
+            device_name: "Test Device".to_string(),
+            device_type: "desktop".to_string(),
+            protocol_version: 8,
+            incoming_capabilities: vec![],
+            outgoing_capabilities: vec![],
+            tcp_port: 1716,
+        };
+
+        // Occupy an OS-assigned port and use it as the base
+        let taken = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_port = taken.local_addr().unwrap().port();
+
+        let ip = "127.0.0.1".parse().unwrap();
+        let server = TlsServer::bind_from(ip, base_port, &cert_info, device_info)
+            .await
+            .unwrap();
+        let port = server.local_addr().port();
+
+        assert_ne!(port, base_port);
+        assert!(port <= base_port.saturating_add(TCP_PORT_FALLBACK_RANGE));
+        assert_eq!(server.device_info().tcp_port, port);
     }
 
     #[tokio::test]
     async fn test_tls_handshake_and_packet_exchange() {
         // Generate certificates for two devices
-        let device1_cert = CertificateInfo::generate("device1").unwrap();
-        let device2_cert = CertificateInfo::generate("device2").unwrap();
+        let device1_cert = CertificateInfo::generate(device_id(DEVICE1)).unwrap();
+        let device2_cert = CertificateInfo::generate(device_id(DEVICE2)).unwrap();
 
         // Create device info for server (device2)
         let device2_info = DeviceInfo {
-            device_id: "device2".to_string(),
+            device_id: device_id(DEVICE2),
             device_name: "Test Device 2".to_string(),
             device_type: "desktop".to_string(),
             protocol_version: 8,
@@ -835,9 +1223,11 @@ mod tests {
             .unwrap();
 
         // Spawn server task
+        let device1_fingerprint = device1_cert.fingerprint.clone();
         let server_task = tokio::spawn(async move {
             // Accept connection (will act as TLS CLIENT due to inverted roles)
             let (mut conn, identity) = server.accept().await.unwrap();
+            assert_eq!(conn.peer_fingerprint(), Some(device1_fingerprint));
 
             // Verify we received identity packet
             assert_eq!(identity.packet_type, "cconnect.identity");
@@ -847,7 +1237,7 @@ mod tests {
                     .get("deviceId")
                     .and_then(|v| v.as_str())
                     .unwrap(),
-                "device1"
+                DEVICE1
             );
 
             // Receive test packet from client
@@ -880,7 +1270,7 @@ mod tests {
             let identity_packet = Packet::new(
                 "cconnect.identity",
                 json!({
-                    "deviceId": "device1",
+                    "deviceId": DEVICE1,
                     "deviceName": "Test Device 1",
                     "deviceType": "desktop",
                     "protocolVersion": 8,
@@ -902,7 +1292,7 @@ mod tests {
             let encrypted_identity_packet = Packet::new(
                 "cconnect.identity",
                 json!({
-                    "deviceId": "device1",
+                    "deviceId": DEVICE1,
                     "deviceName": "Test Device 1",
                     "deviceType": "desktop",
                     "protocolVersion": 8,
@@ -935,7 +1325,7 @@ mod tests {
                     .get("deviceId")
                     .and_then(|v| v.as_str())
                     .unwrap(),
-                "device2"
+                DEVICE2
             );
 
             // Now we can create TlsConnection and exchange packets
@@ -963,6 +1353,255 @@ mod tests {
         client_result.unwrap();
     }
 
+    /// Connect to `server` from `config` with protocol v7, skipping the
+    /// post-TLS identity exchange, and send one ping over the connection
+    async fn connect_and_ping(server: &TlsServer, config: &TlsConfig) -> (bool, bool) {
+        let identity = Packet::new(
+            "cconnect.identity",
+            json!({ "deviceId": DEVICE1, "protocolVersion": 7 }),
+        );
+        let identity_bytes = identity.to_bytes().unwrap();
+
+        let (accepted, connected) = tokio::join!(
+            server.accept(),
+            TlsConnection::connect(server.local_addr(), config, &identity_bytes)
+        );
+        let (mut accepted, _) = accepted.unwrap();
+        let mut connected = connected.unwrap();
+
+        // Reading on the TLS client side also processes the session tickets
+        connected
+            .send_packet(&Packet::new("cconnect.ping", json!({})))
+            .await
+            .unwrap();
+        accepted.receive_packet().await.unwrap();
+        assert!(accepted.peer_fingerprint().is_some());
+        assert!(connected.peer_fingerprint().is_some());
+
+        let resumed = (accepted.is_resumed(), connected.is_resumed());
+        connected.close().await.unwrap();
+        resumed
+    }
+
+    fn test_server_info() -> DeviceInfo {
+        DeviceInfo {
+            device_id: device_id(DEVICE2),
+            device_name: "Test Device 2".to_string(),
+            device_type: "desktop".to_string(),
+            protocol_version: 7,
+            incoming_capabilities: vec![],
+            outgoing_capabilities: vec![],
+            tcp_port: 1816,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_resumes_session() {
+        let device1_cert = CertificateInfo::generate(device_id(DEVICE1)).unwrap();
+        let device2_cert = CertificateInfo::generate(device_id(DEVICE2)).unwrap();
+        let addr = "127.0.0.1:0".parse().unwrap();
+        let server = TlsServer::new(addr, &device2_cert, test_server_info())
+            .await
+            .unwrap();
+        let config = TlsConfig::new(&device1_cert).unwrap();
+
+        assert_eq!(connect_and_ping(&server, &config).await, (false, false));
+        assert_eq!(connect_and_ping(&server, &config).await, (true, true));
+
+        // A peer that no longer knows the session gets a full handshake
+        let fresh = TlsConfig::new(&device1_cert).unwrap();
+        assert_eq!(connect_and_ping(&server, &fresh).await, (false, false));
+    }
+
+    #[tokio::test]
+    async fn test_ping_round_trip_feeds_network_stats() {
+        let device1_cert = CertificateInfo::generate(device_id(DEVICE1)).unwrap();
+        let device2_cert = CertificateInfo::generate(device_id(DEVICE2)).unwrap();
+        let addr = "127.0.0.1:0".parse().unwrap();
+        let server = TlsServer::new(addr, &device2_cert, test_server_info())
+            .await
+            .unwrap();
+        let config = TlsConfig::new(&device1_cert).unwrap();
+        let identity = Packet::new(
+            "cconnect.identity",
+            json!({ "deviceId": DEVICE1, "protocolVersion": 7 }),
+        );
+        let identity_bytes = identity.to_bytes().unwrap();
+
+        let (accepted, connected) = tokio::join!(
+            server.accept(),
+            TlsConnection::connect(server.local_addr(), &config, &identity_bytes)
+        );
+        let (mut accepted, _) = accepted.unwrap();
+        let mut connected: Box<dyn Transport> = Box::new(connected.unwrap());
+
+        connected
+            .send_packet(&Packet::new("kdeconnect.ping", json!({})))
+            .await
+            .unwrap();
+        accepted.receive_packet().await.unwrap();
+        accepted
+            .send_packet(&Packet::new("cconnect.ping", json!({})))
+            .await
+            .unwrap();
+        connected.receive_packet().await.unwrap();
+
+        let stats = connected.network_stats();
+        assert_eq!(stats.samples, 1);
+        assert!(stats.rtt.is_some());
+
+        // Receiving a ping that answers nothing is not a sample
+        assert_eq!(accepted.network_stats().samples, 0);
+        connected.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_send_batch_received_in_order() {
+        let device1_cert = CertificateInfo::generate(device_id(DEVICE1)).unwrap();
+        let device2_cert = CertificateInfo::generate(device_id(DEVICE2)).unwrap();
+        let addr = "127.0.0.1:0".parse().unwrap();
+        let server = TlsServer::new(addr, &device2_cert, test_server_info())
+            .await
+            .unwrap();
+        let config = TlsConfig::new(&device1_cert).unwrap();
+        let identity = Packet::new(
+            "cconnect.identity",
+            json!({ "deviceId": DEVICE1, "protocolVersion": 7 }),
+        );
+        let identity_bytes = identity.to_bytes().unwrap();
+
+        let (accepted, connected) = tokio::join!(
+            server.accept(),
+            TlsConnection::connect(server.local_addr(), &config, &identity_bytes)
+        );
+        let (mut accepted, _) = accepted.unwrap();
+        let mut connected = connected.unwrap();
+
+        let batch: Vec<Packet> = (0..5)
+            .map(|n| Packet::new("cconnect.mousepad.request", json!({ "dx": n })))
+            .collect();
+        connected.send_batch(&batch).await.unwrap();
+
+        for n in 0..5 {
+            let packet = accepted.receive_packet().await.unwrap();
+            assert_eq!(packet.packet_type, "cconnect.mousepad.request");
+            assert_eq!(packet.body["dx"], n);
+        }
+
+        // An empty batch writes nothing and the connection stays usable
+        connected.send_batch(&[]).await.unwrap();
+        connected
+            .send_packet(&Packet::new("cconnect.ping", json!({})))
+            .await
+            .unwrap();
+        assert!(accepted.receive_packet().await.unwrap().is_type("cconnect.ping"));
+        connected.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_session_resumption_disabled() {
+        let device1_cert = CertificateInfo::generate(device_id(DEVICE1)).unwrap();
+        let device2_cert = CertificateInfo::generate(device_id(DEVICE2)).unwrap();
+        let addr = "127.0.0.1:0".parse().unwrap();
+        let server = TlsServer::new(addr, &device2_cert, test_server_info())
+            .await
+            .unwrap();
+        let config = TlsConfig::new(&device1_cert)
+            .unwrap()
+            .with_session_resumption(false);
+
+        assert_eq!(connect_and_ping(&server, &config).await, (false, false));
+        assert_eq!(connect_and_ping(&server, &config).await, (false, false));
+    }
+
+    #[tokio::test]
+    async fn test_plugin_packets_are_rate_limited() {
+        use crate::plugins::RateLimit;
+
+        let device1_cert = CertificateInfo::generate(device_id(DEVICE1)).unwrap();
+        let device2_cert = CertificateInfo::generate(device_id(DEVICE2)).unwrap();
+        let addr = "127.0.0.1:0".parse().unwrap();
+        let server = TlsServer::new(addr, &device2_cert, test_server_info())
+            .await
+            .unwrap();
+        let config = TlsConfig::new(&device1_cert).unwrap();
+        let identity = Packet::new(
+            "cconnect.identity",
+            json!({ "deviceId": DEVICE1, "protocolVersion": 7 }),
+        );
+        let identity_bytes = identity.to_bytes().unwrap();
+        let (accepted, connected) = tokio::join!(
+            server.accept(),
+            TlsConnection::connect(server.local_addr(), &config, &identity_bytes)
+        );
+        let (mut accepted, _) = accepted.unwrap();
+        let mut connected = connected.unwrap();
+
+        let mut plugins = PluginManager::new();
+        let limit = RateLimit {
+            packets_per_sec: 1,
+            bytes_per_sec: 1024,
+        };
+        plugins.set_rate_limit("ping", limit);
+        let ping = Packet::new("cconnect.ping", json!({}));
+        connected
+            .send_plugin_packet(&plugins, "ping", &ping)
+            .await
+            .unwrap();
+        let err = connected
+            .send_plugin_packet(&plugins, "ping", &ping)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ProtocolError::RateLimited { .. }));
+
+        // Only the admitted packet was written
+        connected.send_packet(&Packet::new("cconnect.battery", json!({}))).await.unwrap();
+        assert_eq!(accepted.receive_packet().await.unwrap().packet_type, "cconnect.ping");
+        assert_eq!(accepted.receive_packet().await.unwrap().packet_type, "cconnect.battery");
+        connected.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_protocol_version_rejected() {
+        let device1_cert = CertificateInfo::generate(device_id(DEVICE1)).unwrap();
+        let device2_cert = CertificateInfo::generate(device_id(DEVICE2)).unwrap();
+        let addr = "127.0.0.1:0".parse().unwrap();
+        let server = TlsServer::new(addr, &device2_cert, test_server_info())
+            .await
+            .unwrap();
+        let config = TlsConfig::new(&device1_cert).unwrap();
+        let identity = Packet::new(
+            "cconnect.identity",
+            json!({ "deviceId": DEVICE1, "protocolVersion": 6 }),
+        );
+        let identity_bytes = identity.to_bytes().unwrap();
+
+        let (accepted, _) = tokio::join!(
+            server.accept(),
+            TlsConnection::connect(server.local_addr(), &config, &identity_bytes)
+        );
+        assert!(matches!(
+            accepted,
+            Err(ProtocolError::UnsupportedVersion { ours: 7, theirs: 6 })
+        ));
+
+        // An unstated version counts as the oldest supported one, and a
+        // newer peer is held to ours
+        for identity in [
+            json!({ "deviceId": DEVICE1 }),
+            json!({ "deviceId": DEVICE1, "protocolVersion": 9 }),
+        ] {
+            let identity = Packet::new("cconnect.identity", identity);
+            let identity_bytes = identity.to_bytes().unwrap();
+            let (accepted, connected) = tokio::join!(
+                server.accept(),
+                TlsConnection::connect(server.local_addr(), &config, &identity_bytes)
+            );
+            accepted.unwrap();
+            connected.unwrap();
+        }
+    }
+
     #[test]
     fn test_device_id_comparison_determines_roles() {
         // This test verifies the TLS role determination logic
@@ -72,6 +72,73 @@ pub enum ProtocolError {
     #[error("Device not paired: {0}")]
     NotPaired(String),
 
+    /// Peer speaks a protocol version older than we support
+    #[error("Unsupported protocol version {theirs} (ours: {ours})")]
+    UnsupportedVersion {
+        /// Our protocol version
+        ours: u32,
+        /// The peer's protocol version
+        theirs: u32,
+    },
+
+    /// Stream ended in the middle of a packet
+    #[error("Connection closed mid-packet ({buffered} bytes buffered)")]
+    TruncatedPacket {
+        /// Bytes of the incomplete packet that were discarded
+        buffered: usize,
+    },
+
+    /// Received payload does not match its declared checksum
+    #[error("Checksum mismatch: expected {expected}, got {actual}")]
+    ChecksumMismatch {
+        /// Hex-encoded SHA-256 declared by the sender
+        expected: String,
+        /// Hex-encoded SHA-256 of the bytes received
+        actual: String,
+    },
+
+    /// Peer declared a payload above our size ceiling
+    #[error("Payload too large: {size} bytes (max {max})")]
+    PayloadTooLarge {
+        /// Size declared by the peer
+        size: u64,
+        /// Largest payload we accept
+        max: u64,
+    },
+
+    /// A paired device ID was presented with a different certificate
+    #[error("Device ID {device_id} is paired with a different certificate")]
+    DuplicateDeviceId {
+        /// The device ID claimed by both certificates
+        device_id: String,
+        /// Normalized fingerprint of the paired certificate
+        stored: String,
+        /// Normalized fingerprint of the presented certificate
+        presented: String,
+    },
+
+    /// Device ID does not follow KDE Connect's format
+    #[error("Invalid device ID: {0}")]
+    InvalidDeviceId(String),
+
+    /// Plugin exceeded its outgoing rate limit
+    #[error("Plugin '{plugin}' rate limited, retry in {retry_after:?}")]
+    RateLimited {
+        /// Name of the plugin that sent too much
+        plugin: String,
+        /// Time until the packet would be admitted
+        retry_after: std::time::Duration,
+    },
+
+    /// Peer explicitly refused to start a camera stream
+    #[error("Camera {camera_id} refused to start: {reason}")]
+    StreamRefused {
+        /// Camera the start request was for
+        camera_id: u32,
+        /// Error the peer reported
+        reason: String,
+    },
+
     /// Generic error
     #[error("{0}")]
     Other(String),
@@ -37,7 +37,7 @@ use crate::plugins::{
     PluginManager as CorePluginManager,
     notification_image::NotificationImage,
 };
-use crate::protocol::Packet;
+use crate::protocol::{DeviceId, Packet};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tracing::{error, info};
@@ -103,7 +103,7 @@ pub struct FfiCertificate {
 impl From<CertificateInfo> for FfiCertificate {
     fn from(cert: CertificateInfo) -> Self {
         Self {
-            device_id: cert.device_id,
+            device_id: cert.device_id.into_string(),
             certificate: cert.certificate,
             private_key: cert.private_key,
             fingerprint: cert.fingerprint,
@@ -111,14 +111,16 @@ impl From<CertificateInfo> for FfiCertificate {
     }
 }
 
-impl From<FfiCertificate> for CertificateInfo {
-    fn from(ffi: FfiCertificate) -> Self {
-        Self {
-            device_id: ffi.device_id,
+impl TryFrom<FfiCertificate> for CertificateInfo {
+    type Error = ProtocolError;
+
+    fn try_from(ffi: FfiCertificate) -> Result<Self> {
+        Ok(Self {
+            device_id: DeviceId::try_from(ffi.device_id)?,
             certificate: ffi.certificate,
             private_key: ffi.private_key,
             fingerprint: ffi.fingerprint,
-        }
+        })
     }
 }
 
@@ -1542,7 +1544,10 @@ pub fn get_notification_image_data(image: Arc<NotificationImage>) -> Vec<u8> {
 // ==========================================================================
 
 /// Generate a new self-signed certificate
+///
+/// Fails with `ProtocolError::InvalidDeviceId` if `device_id` is malformed.
 pub fn generate_certificate(device_id: String) -> Result<FfiCertificate> {
+    let device_id = DeviceId::try_from(device_id)?;
     let cert = CertificateInfo::generate(device_id)?;
     Ok(cert.into())
 }
@@ -1553,7 +1558,6 @@ pub fn load_certificate(cert_path: String, key_path: String) -> Result<FfiCertif
     Ok(cert.into())
 }
 
-/// Save certificate to PEM files
 // ==========================================================================
 // Presenter Plugin
 // ==========================================================================
@@ -2259,8 +2263,9 @@ pub fn create_webcam_capability(cameras_json: String) -> Result<FfiPacket> {
 // Certificate Management
 // ==========================================================================
 
+/// Save certificate to PEM files
 pub fn save_certificate(cert: FfiCertificate, cert_path: String, key_path: String) -> Result<()> {
-    let cert_info: CertificateInfo = cert.into();
+    let cert_info = CertificateInfo::try_from(cert)?;
     cert_info.save_to_files(cert_path, key_path)
 }
 
@@ -2290,13 +2295,15 @@ pub fn start_discovery(
 
     // Convert FfiDeviceInfo to discovery::DeviceInfo
     let device_info = discovery::DeviceInfo {
-        device_id: local_device.device_id,
+        device_id: DeviceId::try_from(local_device.device_id)?,
         device_name: local_device.device_name,
         device_type,
         protocol_version: local_device.protocol_version as u32,
         incoming_capabilities: local_device.incoming_capabilities,
         outgoing_capabilities: local_device.outgoing_capabilities,
         tcp_port: local_device.tcp_port,
+        nickname: None,
+        addresses: Vec::new(),
     };
 
     Ok(Arc::new(DiscoveryService::new(device_info, callback)))
@@ -2562,7 +2569,10 @@ impl PayloadTransferHandle {
 /// Start a payload download
 ///
 /// Downloads a file payload from a remote device via TCP connection.
-/// Progress, completion, and errors are reported via the callback.
+/// Progress, completion, and errors are reported via the callback. If the
+/// connection does not open within
+/// [`DEFAULT_PAYLOAD_CONNECT_TIMEOUT`](crate::protocol::DEFAULT_PAYLOAD_CONNECT_TIMEOUT),
+/// the transfer fails with an error.
 ///
 /// # Arguments
 /// * `device_host` - IP address of the remote device
@@ -2579,6 +2589,7 @@ pub fn start_payload_download(
     callback: Box<dyn PayloadCallback>,
 ) -> Result<Arc<PayloadTransferHandle>> {
     use std::sync::atomic::{AtomicU64, Ordering};
+    use crate::protocol::{PayloadReceiver, DEFAULT_PAYLOAD_CONNECT_TIMEOUT};
     use tokio::io::AsyncReadExt;
     use tokio::net::TcpStream;
 
@@ -2600,10 +2611,13 @@ pub fn start_payload_download(
         let callback = Arc::clone(&handle_clone.callback);
         let cancel_token = Arc::clone(&handle_clone.cancel_token);
 
-        // Attempt to connect
+        // Attempt to connect, giving up if the sender never opens the port
         let addr = format!("{}:{}", device_host, port);
-        let mut stream = match TcpStream::connect(&addr).await {
-            Ok(s) => s,
+        let connection = TcpStream::connect(addr.clone());
+        let established =
+            PayloadReceiver::establish(connection, DEFAULT_PAYLOAD_CONNECT_TIMEOUT).await;
+        let mut stream = match established {
+            Ok(receiver) => receiver.into_inner(),
             Err(e) => {
                 callback.on_error(format!("Failed to connect to {}: {}", addr, e));
                 return;
@@ -2810,9 +2824,15 @@ mod tests {
 
     #[test]
     fn test_generate_certificate() {
-        let cert = generate_certificate("test_device".to_string()).unwrap();
+        assert!(matches!(
+            generate_certificate("test_device".to_string()),
+            Err(ProtocolError::InvalidDeviceId(_))
+        ));
+
+        let device_id = DeviceId::generate();
+        let cert = generate_certificate(device_id.to_string()).unwrap();
 
-        assert_eq!(cert.device_id, "test_device");
+        assert_eq!(cert.device_id, device_id.as_str());
         assert!(!cert.certificate.is_empty());
         assert!(!cert.private_key.is_empty());
         assert!(!cert.fingerprint.is_empty());
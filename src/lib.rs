@@ -30,6 +30,9 @@
 //! assert_eq!(bytes.last(), Some(&b'\n'));
 //! ```
 
+// The UniFFI scaffolding included below leaves blank lines after doc comments
+#![allow(clippy::empty_line_after_doc_comments)]
+
 // Re-export commonly used types
 pub use error::{ProtocolError, Result};
 pub use protocol::Packet;
@@ -3,6 +3,7 @@
 //! This module defines events emitted by the discovery service.
 
 use super::DeviceInfo;
+use crate::protocol::DeviceId;
 use std::net::SocketAddr;
 
 /// Events emitted by the discovery service
@@ -27,7 +28,34 @@ pub enum DiscoveryEvent {
     /// A device has timed out (not seen for configured duration)
     DeviceTimeout {
         /// ID of the device that timed out
-        device_id: String,
+        device_id: DeviceId,
+    },
+
+    /// The network went down while a device was known
+    ///
+    /// The device is kept instead of timing out, since it was our side
+    /// that lost the network.
+    DeviceSuspended {
+        /// ID of the suspended device
+        device_id: DeviceId,
+    },
+
+    /// The network came back and a suspended device is being refreshed
+    ///
+    /// It is re-probed right away and times out as usual if it does not
+    /// announce itself again.
+    DeviceResumed {
+        /// ID of the resumed device
+        device_id: DeviceId,
+    },
+
+    /// This device's identity changed and was re-announced
+    ///
+    /// Re-send [`DeviceInfo::to_identity_packet`] over active connections so
+    /// paired peers see the change too.
+    IdentityUpdated {
+        /// The new identity
+        info: DeviceInfo,
     },
 
     /// Discovery service started successfully
@@ -44,6 +72,15 @@ pub enum DiscoveryEvent {
         /// Error message
         message: String,
     },
+
+    /// This subscriber fell behind and missed events
+    ///
+    /// The oldest events were dropped so the discovery loop is never
+    /// blocked by a slow subscriber. Reload the device list if needed.
+    Lagged {
+        /// Number of events dropped
+        skipped: u64,
+    },
 }
 
 impl DiscoveryEvent {
@@ -62,12 +99,29 @@ impl DiscoveryEvent {
         matches!(self, DiscoveryEvent::DeviceTimeout { .. })
     }
 
+    /// Check if this is a device suspended event
+    pub fn is_device_suspended(&self) -> bool {
+        matches!(self, DiscoveryEvent::DeviceSuspended { .. })
+    }
+
+    /// Check if this is a device resumed event
+    pub fn is_device_resumed(&self) -> bool {
+        matches!(self, DiscoveryEvent::DeviceResumed { .. })
+    }
+
+    /// Check if this subscriber missed events
+    pub fn is_lagged(&self) -> bool {
+        matches!(self, DiscoveryEvent::Lagged { .. })
+    }
+
     /// Get device ID if this event is device-related
-    pub fn device_id(&self) -> Option<&str> {
+    pub fn device_id(&self) -> Option<&DeviceId> {
         match self {
             DiscoveryEvent::DeviceDiscovered { info, .. } => Some(&info.device_id),
             DiscoveryEvent::DeviceUpdated { info, .. } => Some(&info.device_id),
             DiscoveryEvent::DeviceTimeout { device_id } => Some(device_id),
+            DiscoveryEvent::DeviceSuspended { device_id } => Some(device_id),
+            DiscoveryEvent::DeviceResumed { device_id } => Some(device_id),
             _ => None,
         }
     }
@@ -91,7 +145,7 @@ mod tests {
         assert!(!discovered.is_device_timeout());
 
         let timeout = DiscoveryEvent::DeviceTimeout {
-            device_id: "test_id".to_string(),
+            device_id: DeviceId::generate(),
         };
         assert!(timeout.is_device_timeout());
         assert!(!timeout.is_device_discovered());
@@ -99,19 +153,20 @@ mod tests {
 
     #[test]
     fn test_device_id_extraction() {
-        let info = DeviceInfo::with_id("test_123", "Test", DeviceType::Desktop, 1816);
+        let device_id = DeviceId::generate();
+        let info = DeviceInfo::with_id(device_id.clone(), "Test", DeviceType::Desktop, 1816);
         let addr = "192.168.1.100:1816".parse().unwrap();
 
         let discovered = DiscoveryEvent::DeviceDiscovered {
             info: info.clone(),
             address: addr,
         };
-        assert_eq!(discovered.device_id(), Some("test_123"));
+        assert_eq!(discovered.device_id(), Some(&device_id));
 
         let timeout = DiscoveryEvent::DeviceTimeout {
-            device_id: "timeout_id".to_string(),
+            device_id: device_id.clone(),
         };
-        assert_eq!(timeout.device_id(), Some("timeout_id"));
+        assert_eq!(timeout.device_id(), Some(&device_id));
 
         let started = DiscoveryEvent::ServiceStarted { port: 1816 };
         assert_eq!(started.device_id(), None);
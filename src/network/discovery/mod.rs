@@ -14,6 +14,7 @@
 //!
 //! ```no_run
 //! use cosmic_ext_connect_core::discovery::{DiscoveryService, DeviceInfo, DeviceType};
+//! use tokio_stream::StreamExt;
 //!
 //! #[tokio::main]
 //! async fn main() {
@@ -21,13 +22,13 @@
 //!     let mut service = DiscoveryService::with_defaults(device_info).unwrap();
 //!
 //!     // Subscribe to events
-//!     let mut events = service.subscribe().await;
+//!     let mut events = service.events();
 //!
 //!     // Start service
 //!     service.start().await.unwrap();
 //!
 //!     // Handle events
-//!     while let Some(event) = events.recv().await {
+//!     while let Some(event) = events.next().await {
 //!         println!("Discovery event: {:?}", event);
 //!     }
 //! }
@@ -52,15 +53,17 @@
 
 pub mod events;
 pub mod service;
+pub mod subnet;
 
-use crate::protocol::{Packet, PROTOCOL_VERSION};
+use crate::crypto::TlsServer;
+use crate::protocol::identity::DEFAULT_TCP_PORT;
+use crate::protocol::{min_supported_version, DeviceId, Packet, PROTOCOL_VERSION};
 use crate::error::{ProtocolError, Result};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::net::{IpAddr, SocketAddr, UdpSocket};
 use std::time::Duration;
 use tracing::{debug, error, info, warn};
-use uuid::Uuid;
 
 /// Default timeout for discovery operations
 pub const DISCOVERY_TIMEOUT: Duration = Duration::from_secs(5);
@@ -68,33 +71,13 @@ pub const DISCOVERY_TIMEOUT: Duration = Duration::from_secs(5);
 // Re-export main types
 pub use events::DiscoveryEvent;
 pub use service::{
-    DiscoveryConfig, DiscoveryService, BROADCAST_ADDR, DEFAULT_BROADCAST_INTERVAL,
-    DEFAULT_DEVICE_TIMEOUT, DISCOVERY_PORT, PORT_RANGE_END, PORT_RANGE_START,
+    AddressFamilyPreference, DiscoveryConfig, DiscoveryEventStream, DiscoveryResolver,
+    DiscoveryService, PowerMode, BROADCAST_ADDR, DEFAULT_BROADCAST_INTERVAL,
+    DEFAULT_DEVICE_TIMEOUT, DEFAULT_DISCOVERY_TTL, DEFAULT_EVENT_CAPACITY,
+    DEFAULT_MANUAL_RETRY_INTERVAL, DISCOVERY_PORT, PORT_RANGE_END, PORT_RANGE_START,
 };
-
-/// Device types supported by COSMIC Connect
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(rename_all = "lowercase")]
-pub enum DeviceType {
-    Desktop,
-    Laptop,
-    Phone,
-    Tablet,
-    Tv,
-}
-
-impl DeviceType {
-    /// Convert device type to string
-    pub fn as_str(&self) -> &'static str {
-        match self {
-            DeviceType::Desktop => "desktop",
-            DeviceType::Laptop => "laptop",
-            DeviceType::Phone => "phone",
-            DeviceType::Tablet => "tablet",
-            DeviceType::Tv => "tv",
-        }
-    }
-}
+pub use subnet::Ipv4Subnet;
+pub use crate::protocol::DeviceType;
 
 /// Device identity information
 ///
@@ -103,7 +86,7 @@ impl DeviceType {
 #[serde(rename_all = "camelCase")]
 pub struct DeviceInfo {
     /// Unique device identifier (UUIDv4 with underscores)
-    pub device_id: String,
+    pub device_id: DeviceId,
 
     /// Human-readable device name (1-32 characters)
     pub device_name: String,
@@ -122,6 +105,19 @@ pub struct DeviceInfo {
 
     /// TCP port for connections
     pub tcp_port: u16,
+
+    /// Local name chosen by the user, overriding `device_name` for display
+    ///
+    /// Never sent to peers; identity packets always carry `device_name`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nickname: Option<String>,
+
+    /// TCP addresses discovery has heard the device at, preferred first
+    ///
+    /// Holds at most one address per IP family; see
+    /// [`DiscoveryConfig::address_preference`]. Never sent to peers.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub addresses: Vec<SocketAddr>,
 }
 
 impl DeviceInfo {
@@ -150,38 +146,35 @@ impl DeviceInfo {
         }
 
         Self {
-            device_id: Self::generate_device_id(),
+            device_id: DeviceId::generate(),
             device_name,
             device_type,
             protocol_version: PROTOCOL_VERSION as u32,
             incoming_capabilities: Vec::new(),
             outgoing_capabilities: Vec::new(),
             tcp_port,
+            nickname: None,
+            addresses: Vec::new(),
         }
     }
 
-    /// Generate a UUIDv4 device ID with underscores
-    ///
-    /// KDE Connect uses UUIDs with underscores instead of hyphens
-    fn generate_device_id() -> String {
-        Uuid::new_v4().to_string().replace('-', "_")
-    }
-
     /// Create a DeviceInfo with explicit device ID
     pub fn with_id(
-        device_id: impl Into<String>,
+        device_id: DeviceId,
         device_name: impl Into<String>,
         device_type: DeviceType,
         tcp_port: u16,
     ) -> Self {
         Self {
-            device_id: device_id.into(),
+            device_id,
             device_name: device_name.into(),
             device_type,
             protocol_version: PROTOCOL_VERSION as u32,
             incoming_capabilities: Vec::new(),
             outgoing_capabilities: Vec::new(),
             tcp_port,
+            nickname: None,
+            addresses: Vec::new(),
         }
     }
 
@@ -209,6 +202,16 @@ impl DeviceInfo {
         self
     }
 
+    /// Advertise the port a TLS listener is bound to
+    ///
+    /// [`TlsServer::bind_from`] may fall back to a later port and port 0
+    /// binds whatever the OS picks, so take `tcp_port` from the listener
+    /// rather than from the port that was asked for.
+    pub fn with_listener(mut self, listener: &TlsServer) -> Self {
+        self.tcp_port = listener.local_addr().port();
+        self
+    }
+
     /// Convert DeviceInfo to an identity packet
     ///
     /// Field order matches official KDE Connect implementation:
@@ -229,6 +232,12 @@ impl DeviceInfo {
     }
 
     /// Parse DeviceInfo from an identity packet
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::InvalidPacket` if the packet is not an
+    /// identity packet or lacks a required field, or
+    /// `ProtocolError::InvalidDeviceId` if its device ID is malformed.
     pub fn from_identity_packet(packet: &Packet) -> Result<Self> {
         if !packet.is_type("cconnect.identity") {
             return Err(ProtocolError::InvalidPacket(
@@ -239,6 +248,7 @@ impl DeviceInfo {
         let device_id = packet
             .get_body_field::<String>("deviceId")
             .ok_or_else(|| ProtocolError::InvalidPacket("Missing deviceId".to_string()))?;
+        let device_id = DeviceId::try_from(device_id)?;
 
         let device_name = packet
             .get_body_field::<String>("deviceName")
@@ -248,27 +258,20 @@ impl DeviceInfo {
             .get_body_field::<String>("deviceType")
             .ok_or_else(|| ProtocolError::InvalidPacket("Missing deviceType".to_string()))?;
 
-        let device_type = match device_type_str.as_str() {
-            "desktop" => DeviceType::Desktop,
-            "laptop" => DeviceType::Laptop,
-            "phone" => DeviceType::Phone,
-            "tablet" => DeviceType::Tablet,
-            "tv" => DeviceType::Tv,
-            _ => {
-                return Err(ProtocolError::InvalidPacket(format!(
-                    "Unknown device type: {}",
-                    device_type_str
-                )))
-            }
-        };
+        let device_type: DeviceType = device_type_str.parse().unwrap_or(DeviceType::Unknown);
+        if device_type == DeviceType::Unknown {
+            debug!("Unrecognised device type '{}', using fallback", device_type_str);
+        }
 
+        // A peer that does not state its version gets the oldest behavior
         let protocol_version = packet
             .get_body_field::<u32>("protocolVersion")
-            .unwrap_or(PROTOCOL_VERSION as u32);
+            .unwrap_or(min_supported_version() as u32);
 
+        // Some UDP-only builds omit tcpPort
         let tcp_port = packet
             .get_body_field::<u16>("tcpPort")
-            .ok_or_else(|| ProtocolError::InvalidPacket("Missing tcpPort".to_string()))?;
+            .unwrap_or(DEFAULT_TCP_PORT);
 
         // Handle both native JSON arrays and stringified JSON arrays.
         // Some clients (e.g. Android) send capabilities as a JSON string
@@ -299,8 +302,27 @@ impl DeviceInfo {
             incoming_capabilities,
             outgoing_capabilities,
             tcp_port,
+            nickname: None,
+            addresses: Vec::new(),
         })
     }
+
+    /// Set or clear the local nickname
+    ///
+    /// An empty or whitespace-only nickname clears it.
+    pub fn set_nickname(&mut self, nickname: Option<String>) {
+        self.nickname = nickname
+            .map(|n| n.trim().to_string())
+            .filter(|n| !n.is_empty());
+    }
+
+    /// Get the name to show the user: the nickname if set, else `device_name`
+    pub fn display_name(&self) -> &str {
+        self.nickname
+            .as_deref()
+            .filter(|n| !n.is_empty())
+            .unwrap_or(&self.device_name)
+    }
 }
 
 /// Synchronous device discovery (for testing and simple use cases)
@@ -459,12 +481,87 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_device_type_serialization() {
-        assert_eq!(DeviceType::Desktop.as_str(), "desktop");
-        assert_eq!(DeviceType::Laptop.as_str(), "laptop");
-        assert_eq!(DeviceType::Phone.as_str(), "phone");
-        assert_eq!(DeviceType::Tablet.as_str(), "tablet");
-        assert_eq!(DeviceType::Tv.as_str(), "tv");
+    fn test_identity_unknown_device_type() {
+        let packet = Packet::new(
+            "cconnect.identity",
+            json!({
+                "deviceId": "fridge_0123456789abcdef0123456789",
+                "deviceName": "Fridge",
+                "deviceType": "fridge",
+                "tcpPort": 1816
+            }),
+        );
+        let info = DeviceInfo::from_identity_packet(&packet).unwrap();
+        assert_eq!(info.device_type, DeviceType::Unknown);
+    }
+
+    #[test]
+    fn test_nickname_overrides_display_name() {
+        let mut info = DeviceInfo::new("Pixel 7", DeviceType::Phone, 1716);
+        assert_eq!(info.display_name(), "Pixel 7");
+
+        info.set_nickname(Some("Work Phone".to_string()));
+        assert_eq!(info.display_name(), "Work Phone");
+
+        // The nickname stays local
+        let packet = info.to_identity_packet();
+        assert_eq!(packet.body["deviceName"], "Pixel 7");
+        assert!(packet.body.get("nickname").is_none());
+
+        // Persisted with the device and restored
+        let stored = serde_json::to_string(&info).unwrap();
+        let restored: DeviceInfo = serde_json::from_str(&stored).unwrap();
+        assert_eq!(restored.display_name(), "Work Phone");
+
+        info.set_nickname(Some("  ".to_string()));
+        assert_eq!(info.nickname, None);
+        assert_eq!(info.display_name(), "Pixel 7");
+
+        info.set_nickname(Some("Work Phone".to_string()));
+        info.set_nickname(None);
+        assert_eq!(info.display_name(), "Pixel 7");
+    }
+
+    #[tokio::test]
+    async fn test_tcp_port_from_listener() {
+        let info = DeviceInfo::new("Laptop", DeviceType::Laptop, 1816);
+        let cert_info = crate::crypto::CertificateInfo::generate(info.device_id.clone()).unwrap();
+        let tls_info = crate::crypto::DeviceInfo {
+            device_id: info.device_id.clone(),
+            device_name: info.device_name.clone(),
+            device_type: info.device_type.as_str().to_string(),
+            protocol_version: 8,
+            incoming_capabilities: vec![],
+            outgoing_capabilities: vec![],
+            tcp_port: 0,
+        };
+        let listener = TlsServer::new("127.0.0.1:0".parse().unwrap(), &cert_info, tls_info)
+            .await
+            .unwrap();
+
+        let info = info.with_listener(&listener);
+        assert_eq!(info.tcp_port, listener.local_addr().port());
+        assert_ne!(info.tcp_port, 0);
+        assert_eq!(
+            info.to_identity_packet().body["tcpPort"],
+            listener.local_addr().port()
+        );
+    }
+
+    #[test]
+    fn test_identity_device_id_validated() {
+        let info = DeviceInfo::new("Laptop", DeviceType::Laptop, 1816);
+        let parsed = DeviceInfo::from_identity_packet(&info.to_identity_packet()).unwrap();
+        assert_eq!(parsed.device_id, info.device_id);
+
+        for device_id in ["abc", "pixel-7-0123456789abcdef0123456789"] {
+            let mut packet = info.to_identity_packet();
+            packet.body["deviceId"] = json!(device_id);
+            assert!(matches!(
+                DeviceInfo::from_identity_packet(&packet),
+                Err(ProtocolError::InvalidDeviceId(_))
+            ));
+        }
     }
 
     #[test]
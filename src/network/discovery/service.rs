@@ -2,16 +2,92 @@
 //!
 //! This module provides an async service that continuously broadcasts device identity
 //! and listens for other devices on the network.
+//!
+//! ## Probe Replies
+//!
+//! When an identity packet arrives, broadcast or unicast, the service answers
+//! the sender with a directed identity right away, so a device that probes us
+//! learns about us without waiting for the next broadcast. Replies to a known
+//! device are sent at most once per broadcast interval; otherwise two services
+//! would keep answering each other's replies. Set
+//! [`DiscoveryConfig::respond_to_probes`] to `false` to only listen.
+//!
+//! ## Dual-Stack Devices
+//!
+//! A device announcing over both IPv4 and IPv6 is one device: announcements
+//! are coalesced by device ID, and the [`DeviceInfo::addresses`] in its
+//! events list the latest address in each family, ordered by
+//! [`DiscoveryConfig::address_preference`]. [`DiscoveryResolver`] connects to
+//! the first of them. Each family times out on its own, so a device that
+//! stops announcing on one family stays visible on the other.
+//!
+//! ## Network Loss
+//!
+//! A device that stops announcing times out after
+//! [`DiscoveryConfig::device_timeout`], but when our own network drops every
+//! device would go quiet at once and flicker out of the UI. While no
+//! non-loopback interface is up, known devices are suspended instead: each
+//! gets a [`DiscoveryEvent::DeviceSuspended`] and none times out. When an
+//! interface comes back, every suspended device gets a
+//! [`DiscoveryEvent::DeviceResumed`] and a fresh timeout, and the service
+//! broadcasts and re-probes manual devices right away so they refresh.
+//! Interfaces are polled every [`DiscoveryConfig::interface_poll_interval`];
+//! platforms with their own connectivity notifications can report them with
+//! [`DiscoveryService::set_network_available`] instead.
+//!
+//! ## Renaming
+//!
+//! [`DiscoveryService::update_identity`] swaps the identity a running
+//! service announces, for example after the user renamed the desktop. The
+//! new identity is broadcast and sent to manual devices right away instead
+//! of at the next tick, and a [`DiscoveryEvent::IdentityUpdated`] asks the
+//! owners of active connections to re-send it over TCP, so paired peers
+//! update the displayed name too.
+//!
+//! ## Events
+//!
+//! [`DiscoveryService::events`] returns a [`Stream`] of [`DiscoveryEvent`]s.
+//! Every stream gets every event sent after it was created, so subscribe
+//! before calling [`DiscoveryService::start`]. Each subscriber has its own
+//! bounded buffer of [`DiscoveryConfig::event_capacity`] events; a subscriber
+//! that falls behind loses the oldest events and is sent a
+//! [`DiscoveryEvent::Lagged`] instead of slowing down discovery.
+//!
+//! ```no_run
+//! use cosmic_ext_connect_core::discovery::{DeviceInfo, DeviceType, DiscoveryService};
+//! use tokio_stream::StreamExt;
+//!
+//! # async fn example() -> cosmic_ext_connect_core::Result<()> {
+//! let device_info = DeviceInfo::new("My Computer", DeviceType::Desktop, 1816);
+//! let mut service = DiscoveryService::with_defaults(device_info)?;
+//! let mut events = service.events();
+//! service.start().await?;
+//!
+//! while let Some(event) = events.next().await {
+//!     println!("Discovery event: {:?}", event);
+//! }
+//! # Ok(())
+//! # }
+//! ```
 
 use super::events::DiscoveryEvent;
+use super::subnet::{self, Ipv4Subnet};
 use super::DeviceInfo;
+use crate::network::transport::{AddressResolver, TransportAddress};
+use crate::protocol::{check_peer_version, DeviceId};
 use crate::{Packet, ProtocolError, Result};
-use std::collections::HashMap;
+use async_trait::async_trait;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::pin::Pin;
 use std::sync::Arc;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
-use tokio::sync::{mpsc, RwLock};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::{mpsc, watch, RwLock};
 use tokio::time::interval;
+use tokio_stream::{Stream, StreamExt};
 use tracing::{debug, error, info, warn};
 
 /// Default UDP port for device discovery (COSMIC Connect uses 1816 to avoid conflict with KDE Connect's 1716)
@@ -30,10 +106,175 @@ pub const DEFAULT_BROADCAST_INTERVAL: Duration = Duration::from_secs(5);
 /// Default device timeout (30 seconds)
 pub const DEFAULT_DEVICE_TIMEOUT: Duration = Duration::from_secs(30);
 
+/// Default TTL for discovery packets (1 = link-local, never routed)
+pub const DEFAULT_DISCOVERY_TTL: u32 = 1;
+
+/// Default retry interval for manually-added devices that have not responded (10 seconds)
+pub const DEFAULT_MANUAL_RETRY_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Default number of events buffered per subscriber
+pub const DEFAULT_EVENT_CAPACITY: usize = 64;
+
+/// Default interval between network interface checks (2 seconds)
+pub const DEFAULT_INTERFACE_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Power mode for the discovery service
+///
+/// Lower-power modes broadcast less often and poll the socket less
+/// frequently, relying more on announcements from other devices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum PowerMode {
+    /// Broadcast every `broadcast_interval`
+    #[default]
+    Active,
+
+    /// Broadcast every 3x `broadcast_interval`
+    Balanced,
+
+    /// Broadcast every 12x `broadcast_interval`
+    PowerSaver,
+}
+
+impl PowerMode {
+    /// Get the effective broadcast interval for a configured base interval
+    pub fn broadcast_interval(&self, base: Duration) -> Duration {
+        match self {
+            PowerMode::Active => base,
+            PowerMode::Balanced => base * 3,
+            PowerMode::PowerSaver => base * 12,
+        }
+    }
+
+    /// Get how long the listener sleeps when no packet is waiting
+    pub fn listener_poll_interval(&self) -> Duration {
+        match self {
+            PowerMode::Active => Duration::from_millis(10),
+            PowerMode::Balanced => Duration::from_millis(50),
+            PowerMode::PowerSaver => Duration::from_millis(250),
+        }
+    }
+}
+
+/// Address family to connect over when a device announces on both
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum AddressFamilyPreference {
+    /// Prefer IPv6, falling back to IPv4
+    #[default]
+    Ipv6First,
+
+    /// Prefer IPv4, falling back to IPv6
+    Ipv4First,
+}
+
+impl AddressFamilyPreference {
+    /// Sort addresses so the preferred family comes first
+    pub fn sort(&self, addresses: &mut [SocketAddr]) {
+        let v4_last = *self == AddressFamilyPreference::Ipv6First;
+        addresses.sort_by_key(|address| address.is_ipv4() == v4_last);
+    }
+}
+
+/// When discovery last heard from a device at an address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SeenAddress {
+    /// UNIX timestamp (seconds) of the last announcement
+    last_seen: u64,
+    /// TCP address the device advertised in that announcement
+    address: SocketAddr,
+}
+
+/// When and where discovery last heard from a device, per address family
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct SeenDevice {
+    v4: Option<SeenAddress>,
+    v6: Option<SeenAddress>,
+}
+
+impl SeenDevice {
+    /// Record an announcement, returning the previous address in its family
+    fn record(&mut self, address: SocketAddr, now: u64) -> Option<SocketAddr> {
+        let slot = if address.is_ipv4() { &mut self.v4 } else { &mut self.v6 };
+        let previous = slot.map(|seen| seen.address);
+        *slot = Some(SeenAddress {
+            last_seen: now,
+            address,
+        });
+        previous
+    }
+
+    /// Mark every known family as heard from at `now`
+    fn refresh(&mut self, now: u64) {
+        for seen in [&mut self.v4, &mut self.v6].into_iter().flatten() {
+            seen.last_seen = now;
+        }
+    }
+
+    /// Forget families not heard from within `timeout` seconds of `now`
+    ///
+    /// Returns `false` once no family is left.
+    fn expire(&mut self, now: u64, timeout: u64) -> bool {
+        for slot in [&mut self.v4, &mut self.v6] {
+            if slot.is_some_and(|seen| now.saturating_sub(seen.last_seen) > timeout) {
+                *slot = None;
+            }
+        }
+        self.v4.is_some() || self.v6.is_some()
+    }
+
+    /// Get the known addresses, preferred family first
+    fn addresses(&self, preference: AddressFamilyPreference) -> Vec<SocketAddr> {
+        let mut addresses: Vec<_> = [self.v4, self.v6]
+            .into_iter()
+            .flatten()
+            .map(|seen| seen.address)
+            .collect();
+        preference.sort(&mut addresses);
+        addresses
+    }
+}
+
+/// Devices by ID
+type SeenDevices = Arc<RwLock<HashMap<DeviceId, SeenDevice>>>;
+
+/// [`AddressResolver`] backed by a running [`DiscoveryService`]
+///
+/// Resolves a device ID to the source IP of its latest announcement in the
+/// preferred address family and the `tcpPort` it advertised there. Devices
+/// that have timed out are unknown.
+#[derive(Debug, Clone)]
+pub struct DiscoveryResolver {
+    seen: SeenDevices,
+    preference: AddressFamilyPreference,
+}
+
+impl DiscoveryResolver {
+    /// Get the TCP address to connect to a device at
+    pub async fn device_address(&self, device_id: &DeviceId) -> Option<SocketAddr> {
+        self.device_addresses(device_id).await.first().copied()
+    }
+
+    /// Get every TCP address a device is announced at, preferred first
+    pub async fn device_addresses(&self, device_id: &DeviceId) -> Vec<SocketAddr> {
+        self.seen
+            .read()
+            .await
+            .get(device_id)
+            .map(|seen| seen.addresses(self.preference))
+            .unwrap_or_default()
+    }
+}
+
+#[async_trait]
+impl AddressResolver for DiscoveryResolver {
+    async fn resolve(&self, device_id: &DeviceId) -> Option<TransportAddress> {
+        self.device_address(device_id).await.map(TransportAddress::Tcp)
+    }
+}
+
 /// Configuration for discovery service
 #[derive(Debug, Clone)]
 pub struct DiscoveryConfig {
-    /// How often to broadcast identity packets
+    /// How often to broadcast identity packets in [`PowerMode::Active`]
     pub broadcast_interval: Duration,
 
     /// How long before a device is considered timed out
@@ -41,6 +282,54 @@ pub struct DiscoveryConfig {
 
     /// Whether to enable device timeout checking
     pub enable_timeout_check: bool,
+
+    /// IP TTL for broadcast and directed identity packets
+    ///
+    /// The default of 1 keeps announcements on the local link. Raise it only
+    /// if peers are reachable through routers.
+    pub ttl: u32,
+
+    /// Drop announcements whose source is not in a local interface's subnet
+    ///
+    /// Local subnets are enumerated when the service starts. If they cannot
+    /// be enumerated, no scoping is applied.
+    pub restrict_to_local_subnet: bool,
+
+    /// How often to re-probe manually-added devices that have not responded
+    pub manual_retry_interval: Duration,
+
+    /// Initial power mode
+    pub power_mode: PowerMode,
+
+    /// Answer identity packets with a directed identity to the sender
+    pub respond_to_probes: bool,
+
+    /// UDP port to listen on and broadcast to
+    ///
+    /// With the default [`DISCOVERY_PORT`] the service falls back to
+    /// [`PORT_RANGE_START`]..=[`PORT_RANGE_END`] if the port is taken. A
+    /// custom port is bound exactly, so instances sharing a host (tests,
+    /// containers) must each pick their own. Port 0 lets the OS choose and
+    /// broadcasts to [`DISCOVERY_PORT`].
+    pub port: u16,
+
+    /// Events buffered per subscriber before the oldest are dropped (minimum 1)
+    pub event_capacity: usize,
+
+    /// Only surface these device ids (empty allows every device)
+    pub allowed_devices: HashSet<DeviceId>,
+
+    /// Never surface these device ids, even if allowed
+    pub denied_devices: HashSet<DeviceId>,
+
+    /// Address family to connect over when a device announces on both
+    pub address_preference: AddressFamilyPreference,
+
+    /// Watch network interfaces and suspend devices while none is up
+    pub watch_interfaces: bool,
+
+    /// How often to check whether a network interface is up
+    pub interface_poll_interval: Duration,
 }
 
 impl Default for DiscoveryConfig {
@@ -49,27 +338,117 @@ impl Default for DiscoveryConfig {
             broadcast_interval: DEFAULT_BROADCAST_INTERVAL,
             device_timeout: DEFAULT_DEVICE_TIMEOUT,
             enable_timeout_check: true,
+            ttl: DEFAULT_DISCOVERY_TTL,
+            restrict_to_local_subnet: false,
+            manual_retry_interval: DEFAULT_MANUAL_RETRY_INTERVAL,
+            power_mode: PowerMode::default(),
+            respond_to_probes: true,
+            port: DISCOVERY_PORT,
+            event_capacity: DEFAULT_EVENT_CAPACITY,
+            allowed_devices: HashSet::new(),
+            denied_devices: HashSet::new(),
+            address_preference: AddressFamilyPreference::default(),
+            watch_interfaces: true,
+            interface_poll_interval: DEFAULT_INTERFACE_POLL_INTERVAL,
         }
     }
 }
 
+impl DiscoveryConfig {
+    /// Check whether an announcement from `src` passes subnet scoping
+    ///
+    /// Always true when `restrict_to_local_subnet` is disabled or no local
+    /// subnets are known, e.g. on platforms that cannot enumerate interfaces.
+    pub fn accepts_source(&self, src: IpAddr, local_subnets: &[Ipv4Subnet]) -> bool {
+        !self.restrict_to_local_subnet
+            || local_subnets.is_empty()
+            || subnet::in_any_subnet(src, local_subnets)
+    }
+
+    /// Check whether announcements from `device_id` pass the allow and deny lists
+    ///
+    /// The denylist wins over the allowlist.
+    pub fn accepts_device(&self, device_id: &DeviceId) -> bool {
+        !self.denied_devices.contains(device_id)
+            && (self.allowed_devices.is_empty() || self.allowed_devices.contains(device_id))
+    }
+
+    /// Get the UDP port identity broadcasts are sent to
+    pub fn broadcast_port(&self) -> u16 {
+        if self.port == 0 {
+            DISCOVERY_PORT
+        } else {
+            self.port
+        }
+    }
+}
+
+/// Rate limit for directed identity replies, per address
+struct ProbeReplies {
+    /// Minimum time between replies to a known device
+    cooldown: Duration,
+
+    /// When we last replied to each address
+    last_sent: HashMap<SocketAddr, Instant>,
+}
+
+impl ProbeReplies {
+    fn new(cooldown: Duration) -> Self {
+        Self {
+            cooldown,
+            last_sent: HashMap::new(),
+        }
+    }
+
+    /// Check whether to reply to `addr`, recording the reply if so
+    ///
+    /// New devices are always answered.
+    fn should_reply(&mut self, addr: SocketAddr, is_new: bool, now: Instant) -> bool {
+        let cooldown = self.cooldown;
+        self.last_sent
+            .retain(|_, sent| now.duration_since(*sent) < cooldown);
+
+        if !is_new && self.last_sent.contains_key(&addr) {
+            return false;
+        }
+        self.last_sent.insert(addr, now);
+        true
+    }
+}
+
+/// What the listener needs to handle one announcement
+struct PacketContext<'a> {
+    /// Our identity, to ignore our own broadcasts and to reply with
+    own_device_info: &'a DeviceInfo,
+
+    /// Device filters and address preference
+    config: &'a DiscoveryConfig,
+
+    /// Socket directed identity replies are sent from
+    socket: &'a UdpSocket,
+
+    /// Where discovery events are sent
+    event_tx: &'a broadcast::Sender<DiscoveryEvent>,
+
+    /// Devices heard from so far
+    last_seen: &'a SeenDevices,
+}
+
 /// Async discovery service
 ///
 /// Runs two concurrent tasks:
-/// - Broadcaster: Sends identity packets at regular intervals
+/// - Broadcaster: Sends identity packets at regular intervals, and directed
+///   identity probes to manually-added devices
 /// - Listener: Receives and processes incoming identity packets
 pub struct DiscoveryService {
-    /// This device's information
-    device_info: DeviceInfo,
+    /// This device's information, watched by the broadcaster and listener
+    device_info: watch::Sender<DeviceInfo>,
 
     /// UDP socket for broadcasting and receiving
     socket: Arc<UdpSocket>,
 
-    /// Event channel sender
-    event_tx: mpsc::UnboundedSender<DiscoveryEvent>,
-
-    /// Event channel receiver
-    event_rx: Arc<RwLock<mpsc::UnboundedReceiver<DiscoveryEvent>>>,
+    /// Event channel sender, one receiver per subscriber
+    event_tx: broadcast::Sender<DiscoveryEvent>,
 
     /// Service configuration
     config: DiscoveryConfig,
@@ -77,8 +456,17 @@ pub struct DiscoveryService {
     /// Shutdown signal sender
     shutdown_tx: Option<tokio::sync::oneshot::Sender<()>>,
 
-    /// Last seen timestamps for devices (device_id -> timestamp)
-    last_seen: Arc<RwLock<HashMap<String, u64>>>,
+    /// When and where devices were last seen
+    last_seen: SeenDevices,
+
+    /// Manually-added device addresses (address -> has responded)
+    manual_devices: Arc<RwLock<HashMap<SocketAddr, bool>>>,
+
+    /// Current power mode, watched by the broadcaster and listener
+    power_mode: watch::Sender<PowerMode>,
+
+    /// Whether a network interface is up, watched by the broadcaster
+    network_up: Arc<watch::Sender<bool>>,
 }
 
 impl DiscoveryService {
@@ -90,17 +478,23 @@ impl DiscoveryService {
     /// * `config` - Service configuration
     pub fn new(device_info: DeviceInfo, config: DiscoveryConfig) -> Result<Self> {
         // Try to bind to discovery port
-        let socket = Self::bind_socket()?;
-        let (event_tx, event_rx) = mpsc::unbounded_channel();
+        let socket = Self::bind_socket(config.port)?;
+        socket.set_ttl(config.ttl)?;
+        socket.set_multicast_ttl_v4(config.ttl)?;
+        let (event_tx, _) = broadcast::channel(config.event_capacity.max(1));
+        let (power_mode, _) = watch::channel(config.power_mode);
+        let (network_up, _) = watch::channel(true);
 
         Ok(Self {
-            device_info,
+            device_info: watch::channel(device_info).0,
             socket: Arc::new(socket),
             event_tx,
-            event_rx: Arc::new(RwLock::new(event_rx)),
             config,
             shutdown_tx: None,
             last_seen: Arc::new(RwLock::new(HashMap::new())),
+            manual_devices: Arc::new(RwLock::new(HashMap::new())),
+            power_mode,
+            network_up: Arc::new(network_up),
         })
     }
 
@@ -109,8 +503,16 @@ impl DiscoveryService {
         Self::new(device_info, DiscoveryConfig::default())
     }
 
-    /// Bind UDP socket with fallback ports
-    fn bind_socket() -> Result<UdpSocket> {
+    /// Bind UDP socket, with fallback ports for the default port
+    fn bind_socket(port: u16) -> Result<UdpSocket> {
+        if port != DISCOVERY_PORT {
+            let socket = UdpSocket::bind(("0.0.0.0", port))?;
+            info!("Bound to UDP port {}", socket.local_addr()?.port());
+            socket.set_broadcast(true)?;
+            socket.set_nonblocking(true)?;
+            return Ok(socket);
+        }
+
         // Try primary port first
         match UdpSocket::bind(("0.0.0.0", DISCOVERY_PORT)) {
             Ok(socket) => {
@@ -150,20 +552,72 @@ impl DiscoveryService {
         }
     }
 
+    /// Get the identity this service announces
+    pub fn device_info(&self) -> DeviceInfo {
+        self.device_info.borrow().clone()
+    }
+
+    /// Replace the identity this service announces, e.g. after a rename
+    ///
+    /// A running service re-announces right away, and every subscriber gets a
+    /// [`DiscoveryEvent::IdentityUpdated`]. See the [module docs](self#renaming).
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Discovery` if the new identity has a different
+    /// device ID; peers know this device by its ID, so it cannot change.
+    pub fn update_identity(&self, device_info: DeviceInfo) -> Result<()> {
+        let current_id = self.device_info.borrow().device_id.clone();
+        if device_info.device_id != current_id {
+            return Err(ProtocolError::Discovery(format!(
+                "Cannot change device ID from {} to {}",
+                current_id, device_info.device_id
+            )));
+        }
+
+        info!("Discovery identity updated: {}", device_info.device_name);
+        self.device_info.send_replace(device_info.clone());
+        let _ = self
+            .event_tx
+            .send(DiscoveryEvent::IdentityUpdated { info: device_info });
+        Ok(())
+    }
+
     /// Get the local port this service is bound to
     pub fn local_port(&self) -> Result<u16> {
         Ok(self.socket.local_addr()?.port())
     }
 
+    /// Get a resolver for the addresses devices are currently announced at
+    ///
+    /// The resolver shares state with the service, so it keeps tracking
+    /// devices as they move.
+    pub fn resolver(&self) -> DiscoveryResolver {
+        DiscoveryResolver {
+            seen: self.last_seen.clone(),
+            preference: self.config.address_preference,
+        }
+    }
+
+    /// Get a stream of discovery events
+    ///
+    /// The stream yields every event sent after this call, and ends when the
+    /// service is dropped. See the [module docs](self) for how slow
+    /// subscribers are handled.
+    pub fn events(&self) -> DiscoveryEventStream {
+        DiscoveryEventStream::new(self.event_tx.subscribe())
+    }
+
     /// Get a receiver for discovery events
+    ///
+    /// Like [`events`](Self::events), but forwarded into a channel.
     pub async fn subscribe(&self) -> mpsc::UnboundedReceiver<DiscoveryEvent> {
         let (tx, rx) = mpsc::unbounded_channel();
 
         // Create a task to forward events
-        let event_rx = self.event_rx.clone();
+        let mut events = self.events();
         tokio::spawn(async move {
-            let mut rx_lock = event_rx.write().await;
-            while let Some(event) = rx_lock.recv().await {
+            while let Some(event) = events.next().await {
                 if tx.send(event).is_err() {
                     break;
                 }
@@ -173,6 +627,123 @@ impl DiscoveryService {
         rx
     }
 
+    /// Add a device by address, for networks where broadcasts are blocked
+    ///
+    /// Sends a unicast identity probe right away. A device that answers with
+    /// its identity is reported through the normal `DeviceDiscovered` event.
+    /// Until it responds, the probe is repeated every `manual_retry_interval`
+    /// while the service is running; afterwards it is re-sent with every
+    /// broadcast so the device does not time out.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the initial probe cannot be sent. The device stays
+    /// registered and is retried on schedule.
+    pub async fn add_manual(&self, addr: SocketAddr) -> Result<()> {
+        info!("Adding manual device at {}", addr);
+        self.manual_devices.write().await.entry(addr).or_insert(false);
+        Self::send_directed_identity(&self.socket, &self.device_info.borrow(), addr)
+    }
+
+    /// Stop probing a manually-added device
+    ///
+    /// Returns `true` if the address was registered.
+    pub async fn remove_manual(&self, addr: SocketAddr) -> bool {
+        self.manual_devices.write().await.remove(&addr).is_some()
+    }
+
+    /// Get manually-added device addresses
+    pub async fn manual_devices(&self) -> Vec<SocketAddr> {
+        self.manual_devices.read().await.keys().copied().collect()
+    }
+
+    /// Change the power mode
+    ///
+    /// Takes effect immediately on a running service. Switching to
+    /// [`PowerMode::Active`] broadcasts right away so devices reappear fast.
+    pub fn set_power_mode(&self, mode: PowerMode) {
+        let changed = self.power_mode.send_if_modified(|current| {
+            let changed = *current != mode;
+            *current = mode;
+            changed
+        });
+
+        if changed {
+            info!(
+                "Discovery power mode set to {:?} (broadcast every {:?})",
+                mode,
+                self.effective_broadcast_interval()
+            );
+        }
+    }
+
+    /// Get the current power mode
+    pub fn power_mode(&self) -> PowerMode {
+        *self.power_mode.borrow()
+    }
+
+    /// Get the broadcast interval for the current power mode
+    pub fn effective_broadcast_interval(&self) -> Duration {
+        self.power_mode().broadcast_interval(self.config.broadcast_interval)
+    }
+
+    /// Report whether the network is available
+    ///
+    /// Taking the network down suspends every known device; bringing it back
+    /// resumes them and re-probes right away. See the
+    /// [module docs](self#network-loss). Reports that do not change the state
+    /// are ignored.
+    pub async fn set_network_available(&self, available: bool) {
+        Self::apply_network_state(&self.network_up, &self.last_seen, &self.event_tx, available)
+            .await;
+    }
+
+    /// Check whether the network is considered available
+    pub fn network_available(&self) -> bool {
+        *self.network_up.borrow()
+    }
+
+    /// Get the IDs of devices suspended while the network is down
+    pub async fn suspended_devices(&self) -> Vec<DeviceId> {
+        if self.network_available() {
+            return Vec::new();
+        }
+        self.last_seen.read().await.keys().cloned().collect()
+    }
+
+    /// Switch network state, suspending or resuming known devices
+    async fn apply_network_state(
+        network_up: &watch::Sender<bool>,
+        last_seen: &SeenDevices,
+        event_tx: &broadcast::Sender<DiscoveryEvent>,
+        available: bool,
+    ) {
+        // Hold the device lock so the timeout checker sees both changes at once
+        let mut last_seen_map = last_seen.write().await;
+        if !network_up.send_if_modified(|up| std::mem::replace(up, available) != available) {
+            return;
+        }
+
+        let now = current_timestamp();
+        for (device_id, seen) in last_seen_map.iter_mut() {
+            let device_id = device_id.clone();
+            let event = if available {
+                // Give the device a full timeout to announce itself again
+                seen.refresh(now);
+                DiscoveryEvent::DeviceResumed { device_id }
+            } else {
+                DiscoveryEvent::DeviceSuspended { device_id }
+            };
+            let _ = event_tx.send(event);
+        }
+
+        if available {
+            info!("Network is back, resumed {} devices", last_seen_map.len());
+        } else {
+            info!("Network is down, suspended {} devices", last_seen_map.len());
+        }
+    }
+
     /// Start the discovery service
     ///
     /// Spawns background tasks for broadcasting and listening.
@@ -199,24 +770,67 @@ impl DiscoveryService {
             self.spawn_timeout_checker();
         }
 
+        if self.config.watch_interfaces {
+            self.spawn_interface_watcher();
+        }
+
         Ok(())
     }
 
     /// Spawn broadcaster task
     fn spawn_broadcaster(&self, mut shutdown_rx: tokio::sync::oneshot::Receiver<()>) {
         let socket = self.socket.clone();
-        let device_info = self.device_info.clone();
+        let mut identity = self.device_info.subscribe();
+        let mut device_info = identity.borrow_and_update().clone();
         let broadcast_interval = self.config.broadcast_interval;
+        let broadcast_port = self.config.broadcast_port();
+        let manual_devices = self.manual_devices.clone();
+        let manual_retry_interval = self.config.manual_retry_interval;
+        let mut power_mode = self.power_mode.subscribe();
+        let mut network_up = self.network_up.subscribe();
 
         tokio::spawn(async move {
-            let mut interval = interval(broadcast_interval);
+            let mut interval = interval(power_mode.borrow_and_update().broadcast_interval(broadcast_interval));
+            let mut retry_interval = interval_after(manual_retry_interval);
 
             loop {
                 tokio::select! {
                     _ = interval.tick() => {
-                        if let Err(e) = Self::broadcast_identity(&socket, &device_info) {
+                        if let Err(e) = Self::broadcast_identity(&socket, &device_info, broadcast_port) {
+                            error!("Failed to broadcast identity: {}", e);
+                        }
+                        Self::probe_manual_devices(&socket, &device_info, &manual_devices, true).await;
+                    }
+                    _ = retry_interval.tick() => {
+                        Self::probe_manual_devices(&socket, &device_info, &manual_devices, false).await;
+                    }
+                    Ok(()) = power_mode.changed() => {
+                        let mode = *power_mode.borrow_and_update();
+                        let period = mode.broadcast_interval(broadcast_interval);
+                        // Active ticks immediately; other modes wait a full period
+                        interval = if mode == PowerMode::Active {
+                            tokio::time::interval(period)
+                        } else {
+                            interval_after(period)
+                        };
+                    }
+                    Ok(()) = network_up.changed() => {
+                        if !*network_up.borrow_and_update() {
+                            continue;
+                        }
+                        // Re-probe on recovery so suspended devices refresh
+                        if let Err(e) = Self::broadcast_identity(&socket, &device_info, broadcast_port) {
+                            error!("Failed to broadcast identity: {}", e);
+                        }
+                        Self::probe_manual_devices(&socket, &device_info, &manual_devices, true).await;
+                    }
+                    Ok(()) = identity.changed() => {
+                        // Re-announce so peers pick up the new identity now
+                        device_info = identity.borrow_and_update().clone();
+                        if let Err(e) = Self::broadcast_identity(&socket, &device_info, broadcast_port) {
                             error!("Failed to broadcast identity: {}", e);
                         }
+                        Self::probe_manual_devices(&socket, &device_info, &manual_devices, true).await;
                     }
                     _ = &mut shutdown_rx => {
                         info!("Broadcaster shutting down");
@@ -228,10 +842,10 @@ impl DiscoveryService {
     }
 
     /// Broadcast identity packet
-    fn broadcast_identity(socket: &UdpSocket, device_info: &DeviceInfo) -> Result<()> {
+    fn broadcast_identity(socket: &UdpSocket, device_info: &DeviceInfo, port: u16) -> Result<()> {
         let packet = device_info.to_identity_packet();
         let bytes = packet.to_bytes()?;
-        let broadcast_addr = SocketAddr::new(IpAddr::V4(BROADCAST_ADDR), DISCOVERY_PORT);
+        let broadcast_addr = SocketAddr::new(IpAddr::V4(BROADCAST_ADDR), port);
 
         match socket.send_to(&bytes, broadcast_addr) {
             Ok(sent) => {
@@ -248,6 +862,30 @@ impl DiscoveryService {
         }
     }
 
+    /// Send directed identity probes to manually-added devices
+    ///
+    /// Probes only devices that have not responded unless `include_responded` is set.
+    async fn probe_manual_devices(
+        socket: &UdpSocket,
+        device_info: &DeviceInfo,
+        manual_devices: &RwLock<HashMap<SocketAddr, bool>>,
+        include_responded: bool,
+    ) {
+        let targets: Vec<SocketAddr> = manual_devices
+            .read()
+            .await
+            .iter()
+            .filter(|(_, &responded)| include_responded || !responded)
+            .map(|(&addr, _)| addr)
+            .collect();
+
+        for addr in targets {
+            debug!("Probing manual device at {}", addr);
+            // Failures are logged by send_directed_identity and retried next tick
+            let _ = Self::send_directed_identity(socket, device_info, addr);
+        }
+    }
+
     /// Send directed identity packet to a specific device
     /// This is sent in response to discovering a device, matching official KDE Connect behavior
     fn send_directed_identity(
@@ -277,33 +915,76 @@ impl DiscoveryService {
     fn spawn_listener(&self) {
         let socket = self.socket.clone();
         let event_tx = self.event_tx.clone();
-        let own_device_id = self.device_info.device_id.clone();
-        let own_device_info = self.device_info.clone();
+        let identity = self.device_info.subscribe();
         let last_seen = self.last_seen.clone();
+        let config = self.config.clone();
+        let manual_devices = self.manual_devices.clone();
+        let power_mode = self.power_mode.subscribe();
+        let local_subnets = if config.restrict_to_local_subnet {
+            let subnets = subnet::local_subnets();
+            if subnets.is_empty() {
+                warn!("No local subnets found; discovery is not scoped");
+            } else {
+                info!("Restricting discovery to local subnets: {:?}", subnets);
+            }
+            subnets
+        } else {
+            Vec::new()
+        };
 
         tokio::spawn(async move {
             let mut buf = [0u8; 4096];
+            let mut replies = config
+                .respond_to_probes
+                .then(|| ProbeReplies::new(config.broadcast_interval));
 
             loop {
                 match socket.recv_from(&mut buf) {
                     Ok((size, src_addr)) => {
-                        if let Err(e) = Self::handle_packet(
+                        // Manually-added devices bypass subnet scoping
+                        let manual = manual_devices
+                            .read()
+                            .await
+                            .keys()
+                            .any(|addr| addr.ip() == src_addr.ip());
+
+                        if !manual && !config.accepts_source(src_addr.ip(), &local_subnets) {
+                            debug!("Dropping announcement from out-of-subnet source {}", src_addr);
+                            continue;
+                        }
+
+                        let own_device_info = identity.borrow().clone();
+                        let context = PacketContext {
+                            own_device_info: &own_device_info,
+                            config: &config,
+                            socket: &socket,
+                            event_tx: &event_tx,
+                            last_seen: &last_seen,
+                        };
+                        match Self::handle_packet(
+                            &context,
                             &buf[..size],
                             src_addr,
-                            &own_device_id,
-                            &own_device_info,
-                            &socket,
-                            &event_tx,
-                            &last_seen,
+                            replies.as_mut(),
                         )
                         .await
                         {
-                            debug!("Error handling packet from {}: {}", src_addr, e);
+                            Ok(true) if manual => {
+                                // The peer may answer from a different port
+                                for (addr, responded) in manual_devices.write().await.iter_mut() {
+                                    if addr.ip() == src_addr.ip() {
+                                        *responded = true;
+                                    }
+                                }
+                            }
+                            Ok(_) => {}
+                            Err(e) => debug!("Error handling packet from {}: {}", src_addr, e),
                         }
                     }
                     Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
                         // No data available, sleep briefly
-                        tokio::time::sleep(Duration::from_millis(10)).await;
+                        let poll_interval = power_mode.borrow().listener_poll_interval();
+                        tokio::time::sleep(poll_interval).await;
                     }
                     Err(e) => {
                         error!("Error receiving packet: {}", e);
@@ -315,45 +996,80 @@ impl DiscoveryService {
     }
 
     /// Handle incoming packet
+    ///
+    /// Returns `true` if the packet was an accepted peer's identity. Peers are
+    /// answered with a directed identity when `replies` allows it.
     async fn handle_packet(
+        context: &PacketContext<'_>,
         data: &[u8],
         src_addr: SocketAddr,
-        own_device_id: &str,
-        own_device_info: &DeviceInfo,
-        socket: &UdpSocket,
-        event_tx: &mpsc::UnboundedSender<DiscoveryEvent>,
-        last_seen: &Arc<RwLock<HashMap<String, u64>>>,
-    ) -> Result<()> {
+        replies: Option<&mut ProbeReplies>,
+    ) -> Result<bool> {
+        let PacketContext {
+            own_device_info,
+            config,
+            socket,
+            event_tx,
+            last_seen,
+        } = *context;
+
         // Parse packet
         let packet = Packet::from_bytes(data)?;
 
         if !packet.is_type("cconnect.identity") {
             debug!("Ignoring non-identity packet from {}", src_addr);
-            return Ok(());
+            return Ok(false);
         }
 
         // Parse device info
-        let device_info = DeviceInfo::from_identity_packet(&packet)?;
+        let mut device_info = DeviceInfo::from_identity_packet(&packet)?;
 
         // Ignore our own broadcasts
-        if device_info.device_id == own_device_id {
+        if device_info.device_id == own_device_info.device_id {
             debug!("Ignoring our own broadcast");
-            return Ok(());
+            return Ok(false);
+        }
+
+        // Filtered devices are neither recorded nor answered
+        if !config.accepts_device(&device_info.device_id) {
+            debug!("Dropping announcement from filtered device {}", device_info.device_id);
+            return Ok(false);
         }
 
-        let current_time = current_timestamp();
+        // Devices we cannot talk to are not offered for connection
+        let ours = own_device_info.protocol_version;
+        if let Err(e) = check_peer_version(ours, device_info.protocol_version) {
+            debug!("Ignoring {} at {}: {}", device_info.device_id, src_addr, e);
+            return Ok(false);
+        }
+
+        let address = tcp_address(src_addr, device_info.tcp_port);
         let mut last_seen_map = last_seen.write().await;
 
-        // Check if this is a new device or update
+        // Announcements from either family update the same device
         let is_new = !last_seen_map.contains_key(&device_info.device_id);
-        last_seen_map.insert(device_info.device_id.clone(), current_time);
+        let seen = last_seen_map.entry(device_info.device_id.clone()).or_default();
+        let previous = seen.record(address, current_timestamp());
+        device_info.addresses = seen.addresses(config.address_preference);
         drop(last_seen_map);
 
+        if let Some(previous) = previous.filter(|previous| *previous != address) {
+            info!(
+                "Device {} moved from {} to {}",
+                device_info.device_id, previous, address
+            );
+        }
+
         // Send directed identity packet back to discovered device
         // This matches official KDE Connect behavior - devices send both broadcasts
         // AND directed packets to each discovered device
-        if let Err(e) = Self::send_directed_identity(socket, own_device_info, src_addr) {
-            warn!("Failed to send directed identity to {}: {}", src_addr, e);
+        if let Some(replies) = replies {
+            if replies.should_reply(src_addr, is_new, Instant::now()) {
+                // Failures are logged by send_directed_identity
+                let _ = Self::send_directed_identity(socket, own_device_info, src_addr);
+            } else {
+                debug!("Recently answered {}, not replying", src_addr);
+            }
         }
 
         // Emit appropriate event
@@ -380,13 +1096,14 @@ impl DiscoveryService {
         };
 
         let _ = event_tx.send(event);
-        Ok(())
+        Ok(true)
     }
 
     /// Spawn timeout checker task
     fn spawn_timeout_checker(&self) {
         let last_seen = self.last_seen.clone();
         let event_tx = self.event_tx.clone();
+        let network_up = self.network_up.subscribe();
         let timeout_duration = self.config.device_timeout;
 
         tokio::spawn(async move {
@@ -394,22 +1111,63 @@ impl DiscoveryService {
 
             loop {
                 interval.tick().await;
+                Self::expire_devices(
+                    &last_seen,
+                    &network_up,
+                    &event_tx,
+                    timeout_duration,
+                    current_timestamp(),
+                )
+                .await;
+            }
+        });
+    }
 
-                let current_time = current_timestamp();
-                let mut last_seen_map = last_seen.write().await;
-                let mut timed_out = Vec::new();
+    /// Time out devices not heard from within `timeout` of `now`
+    ///
+    /// Suspended devices, while the network is down, never time out.
+    async fn expire_devices(
+        last_seen: &SeenDevices,
+        network_up: &watch::Receiver<bool>,
+        event_tx: &broadcast::Sender<DiscoveryEvent>,
+        timeout: Duration,
+        now: u64,
+    ) {
+        let mut last_seen_map = last_seen.write().await;
+        if !*network_up.borrow() {
+            return;
+        }
+        let mut timed_out = Vec::new();
 
-                for (device_id, &last_seen_time) in last_seen_map.iter() {
-                    if current_time - last_seen_time > timeout_duration.as_secs() {
-                        timed_out.push(device_id.clone());
-                    }
-                }
+        // A device times out once every address family has
+        last_seen_map.retain(|device_id, seen| {
+            let alive = seen.expire(now, timeout.as_secs());
+            if !alive {
+                timed_out.push(device_id.clone());
+            }
+            alive
+        });
 
-                for device_id in timed_out {
-                    info!("Device timed out: {}", device_id);
-                    last_seen_map.remove(&device_id);
-                    let _ = event_tx.send(DiscoveryEvent::DeviceTimeout { device_id });
-                }
+        for device_id in timed_out {
+            info!("Device timed out: {}", device_id);
+            let _ = event_tx.send(DiscoveryEvent::DeviceTimeout { device_id });
+        }
+    }
+
+    /// Spawn network interface watcher task
+    fn spawn_interface_watcher(&self) {
+        let last_seen = self.last_seen.clone();
+        let event_tx = self.event_tx.clone();
+        let network_up = self.network_up.clone();
+        let poll_interval = self.config.interface_poll_interval;
+
+        tokio::spawn(async move {
+            let mut interval = interval(poll_interval);
+
+            loop {
+                interval.tick().await;
+                let available = subnet::network_available();
+                Self::apply_network_state(&network_up, &last_seen, &event_tx, available).await;
             }
         });
     }
@@ -426,6 +1184,85 @@ impl DiscoveryService {
     }
 }
 
+/// Result of one receive, with the receiver handed back for the next
+type EventRecvOutput = (
+    std::result::Result<DiscoveryEvent, RecvError>,
+    broadcast::Receiver<DiscoveryEvent>,
+);
+
+/// Pending receive on a subscriber's event channel
+type EventRecv = Pin<Box<dyn Future<Output = EventRecvOutput> + Send>>;
+
+/// [`Stream`] of discovery events for one subscriber
+///
+/// Created by [`DiscoveryService::events`].
+pub struct DiscoveryEventStream {
+    /// Receive in progress; it hands the receiver back when done
+    recv: EventRecv,
+}
+
+impl DiscoveryEventStream {
+    fn new(rx: broadcast::Receiver<DiscoveryEvent>) -> Self {
+        Self {
+            recv: Box::pin(Self::recv(rx)),
+        }
+    }
+
+    async fn recv(mut rx: broadcast::Receiver<DiscoveryEvent>) -> EventRecvOutput {
+        let result = rx.recv().await;
+        (result, rx)
+    }
+}
+
+impl Stream for DiscoveryEventStream {
+    type Item = DiscoveryEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<DiscoveryEvent>> {
+        let (result, rx) = match self.recv.as_mut().poll(cx) {
+            Poll::Ready(ready) => ready,
+            Poll::Pending => return Poll::Pending,
+        };
+        self.recv = Box::pin(Self::recv(rx));
+
+        match result {
+            Ok(event) => Poll::Ready(Some(event)),
+            Err(RecvError::Lagged(skipped)) => {
+                warn!("Discovery subscriber lagged, dropped {} events", skipped);
+                Poll::Ready(Some(DiscoveryEvent::Lagged { skipped }))
+            }
+            Err(RecvError::Closed) => Poll::Ready(None),
+        }
+    }
+}
+
+impl std::fmt::Debug for DiscoveryEventStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DiscoveryEventStream").finish_non_exhaustive()
+    }
+}
+
+/// Get the TCP address of a device announced from `src_addr`
+///
+/// IPv4-mapped sources from a dual-stack socket count as IPv4. IPv6
+/// sources keep their scope ID, without which a link-local address cannot
+/// be connected to.
+fn tcp_address(src_addr: SocketAddr, tcp_port: u16) -> SocketAddr {
+    let mut address = match src_addr {
+        SocketAddr::V6(v6) => v6
+            .ip()
+            .to_ipv4_mapped()
+            .map_or(src_addr, |v4| SocketAddr::new(IpAddr::V4(v4), 0)),
+        SocketAddr::V4(_) => src_addr,
+    };
+    address.set_port(tcp_port);
+    address
+}
+
+/// Create an interval whose first tick is one period from now
+fn interval_after(period: Duration) -> tokio::time::Interval {
+    tokio::time::interval_at(tokio::time::Instant::now() + period, period)
+}
+
 /// Get current UNIX timestamp in seconds
 fn current_timestamp() -> u64 {
     SystemTime::now()
@@ -439,12 +1276,717 @@ mod tests {
     use super::*;
     use super::super::{DeviceInfo, DeviceType};
 
+    const MANUAL_PHONE: &str = "manual_phone_0123456789abcdef012";
+    const PROBER: &str = "prober_0123456789abcdef012345678";
+    const PHONE: &str = "phone_0123456789abcdef0123456789";
+    const STRANGER: &str = "stranger_0123456789abcdef0123456";
+
+    fn id(device_id: &str) -> DeviceId {
+        DeviceId::parse(device_id).unwrap()
+    }
+
     #[test]
     fn test_discovery_config_defaults() {
         let config = DiscoveryConfig::default();
         assert_eq!(config.broadcast_interval, DEFAULT_BROADCAST_INTERVAL);
         assert_eq!(config.device_timeout, DEFAULT_DEVICE_TIMEOUT);
         assert!(config.enable_timeout_check);
+        assert_eq!(config.ttl, DEFAULT_DISCOVERY_TTL);
+        assert!(!config.restrict_to_local_subnet);
+        assert_eq!(config.manual_retry_interval, DEFAULT_MANUAL_RETRY_INTERVAL);
+        assert_eq!(config.power_mode, PowerMode::Active);
+        assert!(config.respond_to_probes);
+        assert_eq!(config.port, DISCOVERY_PORT);
+        assert_eq!(config.broadcast_port(), DISCOVERY_PORT);
+        assert_eq!(config.address_preference, AddressFamilyPreference::Ipv6First);
+        assert!(config.watch_interfaces);
+        assert_eq!(config.interface_poll_interval, DEFAULT_INTERFACE_POLL_INTERVAL);
+    }
+
+    #[test]
+    fn test_tcp_address_keeps_scope_id() {
+        let link_local: SocketAddr = "[fe80::1%3]:1716".parse().unwrap();
+        let SocketAddr::V6(address) = tcp_address(link_local, 1816) else {
+            panic!("IPv6 source became IPv4");
+        };
+        assert_eq!(address.port(), 1816);
+        assert_eq!(address.scope_id(), 3);
+
+        let mapped: SocketAddr = "[::ffff:192.168.1.5]:1716".parse().unwrap();
+        assert_eq!(tcp_address(mapped, 1816), "192.168.1.5:1816".parse().unwrap());
+        let v4: SocketAddr = "192.168.1.5:1716".parse().unwrap();
+        assert_eq!(tcp_address(v4, 1816), "192.168.1.5:1816".parse().unwrap());
+    }
+
+    #[test]
+    fn test_power_mode_intervals() {
+        let device_info = DeviceInfo::new("Test Device", DeviceType::Desktop, 1816);
+        let service = DiscoveryService::with_defaults(device_info).unwrap();
+        assert_eq!(service.effective_broadcast_interval(), DEFAULT_BROADCAST_INTERVAL);
+
+        service.set_power_mode(PowerMode::Balanced);
+        assert_eq!(service.effective_broadcast_interval(), DEFAULT_BROADCAST_INTERVAL * 3);
+
+        service.set_power_mode(PowerMode::PowerSaver);
+        assert_eq!(service.power_mode(), PowerMode::PowerSaver);
+        assert_eq!(service.effective_broadcast_interval(), DEFAULT_BROADCAST_INTERVAL * 12);
+        assert!(
+            PowerMode::PowerSaver.listener_poll_interval() > PowerMode::Active.listener_poll_interval()
+        );
+    }
+
+    #[test]
+    fn test_subnet_scoping() {
+        let local = [Ipv4Subnet::new(Ipv4Addr::new(192, 168, 1, 0), 24)];
+        let inside: IpAddr = "192.168.1.20".parse().unwrap();
+        let outside: IpAddr = "10.20.30.40".parse().unwrap();
+
+        let scoped = DiscoveryConfig {
+            restrict_to_local_subnet: true,
+            ..Default::default()
+        };
+        assert!(scoped.accepts_source(inside, &local));
+        assert!(!scoped.accepts_source(outside, &local));
+
+        // Unknown local subnets disable scoping rather than drop everything
+        assert!(scoped.accepts_source(outside, &[]));
+
+        let unscoped = DiscoveryConfig::default();
+        assert!(unscoped.accepts_source(outside, &local));
+    }
+
+    #[tokio::test]
+    async fn test_discovery_service_applies_ttl() {
+        let device_info = DeviceInfo::new("Test Device", DeviceType::Desktop, 1816);
+        let config = DiscoveryConfig {
+            ttl: 4,
+            ..Default::default()
+        };
+        let service = DiscoveryService::new(device_info, config).unwrap();
+        assert_eq!(service.socket.ttl().unwrap(), 4);
+    }
+
+    /// Answer identity probes from a UDP socket, ignoring the first `skip`
+    fn spawn_responder(skip: usize) -> SocketAddr {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = socket.local_addr().unwrap();
+        let phone = DeviceInfo::with_id(id(MANUAL_PHONE), "Manual Phone", DeviceType::Phone, 1716);
+        let identity = phone.to_identity_packet().to_bytes().unwrap();
+
+        std::thread::spawn(move || {
+            let mut buf = [0u8; 4096];
+            let mut probes = 0;
+            while let Ok((_, src)) = socket.recv_from(&mut buf) {
+                probes += 1;
+                if probes > skip {
+                    let _ = socket.send_to(&identity, src);
+                }
+            }
+        });
+
+        addr
+    }
+
+    /// Wait for the responder's `DeviceDiscovered`, skipping other traffic
+    async fn expect_discovered(events: &mut mpsc::UnboundedReceiver<DiscoveryEvent>) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let event = events.recv().await.expect("event channel closed");
+                if event.is_device_discovered() && event.device_id() == Some(&id(MANUAL_PHONE)) {
+                    return;
+                }
+            }
+        })
+        .await
+        .expect("no DeviceDiscovered event")
+    }
+
+    #[tokio::test]
+    async fn test_add_manual_discovers_device() {
+        let responder = spawn_responder(0);
+        let device_info = DeviceInfo::new("Test Device", DeviceType::Desktop, 1816);
+        let mut service = DiscoveryService::with_defaults(device_info).unwrap();
+        let mut events = service.subscribe().await;
+        service.start().await.unwrap();
+
+        service.add_manual(responder).await.unwrap();
+
+        expect_discovered(&mut events).await;
+        // Marked as responded once the listener has handled the identity
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while !service.manual_devices.read().await[&responder] {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("manual device not marked as responded");
+
+        assert!(service.remove_manual(responder).await);
+        assert!(service.manual_devices().await.is_empty());
+        service.stop().await;
+    }
+
+    #[tokio::test]
+    async fn test_add_manual_retries_until_response() {
+        // The first probe is lost; the retry must reach the device
+        let responder = spawn_responder(1);
+        let device_info = DeviceInfo::new("Test Device", DeviceType::Desktop, 1816);
+        let config = DiscoveryConfig {
+            broadcast_interval: Duration::from_secs(60),
+            manual_retry_interval: Duration::from_millis(100),
+            ..Default::default()
+        };
+        let mut service = DiscoveryService::new(device_info, config).unwrap();
+        let mut events = service.subscribe().await;
+        service.start().await.unwrap();
+
+        service.add_manual(responder).await.unwrap();
+
+        expect_discovered(&mut events).await;
+        service.stop().await;
+    }
+
+    /// Wait briefly for one probe
+    async fn recv_probe(socket: &tokio::net::UdpSocket) -> bool {
+        let mut buf = [0u8; 4096];
+        tokio::time::timeout(Duration::from_millis(500), socket.recv_from(&mut buf))
+            .await
+            .is_ok()
+    }
+
+    #[tokio::test]
+    async fn test_switch_to_active_broadcasts_immediately() {
+        // Broadcast ticks also probe manual devices, so a silent manual
+        // device observes each broadcast
+        let probe_socket = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let probe_addr = probe_socket.local_addr().unwrap();
+
+        let device_info = DeviceInfo::new("Test Device", DeviceType::Desktop, 1816);
+        let config = DiscoveryConfig {
+            broadcast_interval: Duration::from_secs(60),
+            power_mode: PowerMode::PowerSaver,
+            ..Default::default()
+        };
+        let mut service = DiscoveryService::new(device_info, config).unwrap();
+
+        // Probes from add_manual and the first tick at start, then silence
+        service.add_manual(probe_addr).await.unwrap();
+        service.start().await.unwrap();
+        assert!(recv_probe(&probe_socket).await);
+        assert!(recv_probe(&probe_socket).await);
+        assert!(!recv_probe(&probe_socket).await);
+
+        service.set_power_mode(PowerMode::Active);
+        assert!(recv_probe(&probe_socket).await, "no broadcast after switching to Active");
+        service.stop().await;
+    }
+
+    /// Send an identity probe to a service on localhost and wait for a reply
+    async fn probe_service(socket: &tokio::net::UdpSocket, port: u16) -> Option<DeviceInfo> {
+        let probe = DeviceInfo::with_id(id(PROBER), "Prober", DeviceType::Phone, 1716)
+            .to_identity_packet()
+            .to_bytes()
+            .unwrap();
+        socket.send_to(&probe, ("127.0.0.1", port)).await.unwrap();
+
+        let mut buf = [0u8; 4096];
+        let (size, _) = tokio::time::timeout(Duration::from_millis(500), socket.recv_from(&mut buf))
+            .await
+            .ok()?
+            .unwrap();
+        DeviceInfo::from_identity_packet(&Packet::from_bytes(&buf[..size]).unwrap()).ok()
+    }
+
+    #[tokio::test]
+    async fn test_replies_to_unicast_probe() {
+        let socket = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let device_info = DeviceInfo::new("Test Device", DeviceType::Desktop, 1816);
+        let own_id = device_info.device_id.clone();
+        let config = DiscoveryConfig {
+            broadcast_interval: Duration::from_secs(60),
+            ..Default::default()
+        };
+        let mut service = DiscoveryService::new(device_info, config).unwrap();
+        let port = service.local_port().unwrap();
+        service.start().await.unwrap();
+
+        let reply = probe_service(&socket, port).await.expect("no reply to probe");
+        assert_eq!(reply.device_id, own_id);
+
+        // A known device is not answered again within the cooldown
+        assert!(probe_service(&socket, port).await.is_none());
+        service.stop().await;
+    }
+
+    #[tokio::test]
+    async fn test_probe_replies_can_be_disabled() {
+        let socket = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let device_info = DeviceInfo::new("Test Device", DeviceType::Desktop, 1816);
+        let config = DiscoveryConfig {
+            broadcast_interval: Duration::from_secs(60),
+            respond_to_probes: false,
+            ..Default::default()
+        };
+        let mut service = DiscoveryService::new(device_info, config).unwrap();
+        let port = service.local_port().unwrap();
+        service.start().await.unwrap();
+
+        assert!(probe_service(&socket, port).await.is_none());
+        service.stop().await;
+    }
+
+    /// Handle an announcement as the listener would, without replying
+    async fn handle_announcement(
+        service: &DiscoveryService,
+        data: &[u8],
+        src_addr: SocketAddr,
+    ) -> Result<bool> {
+        let own_device_info = service.device_info();
+        let context = PacketContext {
+            own_device_info: &own_device_info,
+            config: &service.config,
+            socket: &service.socket,
+            event_tx: &service.event_tx,
+            last_seen: &service.last_seen,
+        };
+        DiscoveryService::handle_packet(&context, data, src_addr, None).await
+    }
+
+    /// Find a UDP port that is currently free on this host
+    fn free_udp_port() -> u16 {
+        UdpSocket::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port()
+    }
+
+    /// Wait for a discovery event about `device_id`
+    async fn discovered(
+        rx: &mut mpsc::UnboundedReceiver<DiscoveryEvent>,
+        device_id: &DeviceId,
+    ) -> bool {
+        tokio::time::timeout(Duration::from_secs(2), async {
+            while let Some(event) = rx.recv().await {
+                if event.is_device_discovered() && event.device_id() == Some(device_id) {
+                    return true;
+                }
+            }
+            false
+        })
+        .await
+        .unwrap_or(false)
+    }
+
+    #[tokio::test]
+    async fn test_two_services_on_custom_ports_find_each_other() {
+        let first_info = DeviceInfo::new("First", DeviceType::Desktop, 1716);
+        let second_info = DeviceInfo::new("Second", DeviceType::Desktop, 1717);
+        let (first_id, second_id) = (first_info.device_id.clone(), second_info.device_id.clone());
+
+        let config = |port| DiscoveryConfig {
+            port,
+            broadcast_interval: Duration::from_secs(60),
+            ..Default::default()
+        };
+        let mut first = DiscoveryService::new(first_info, config(free_udp_port())).unwrap();
+        let mut second = DiscoveryService::new(second_info, config(free_udp_port())).unwrap();
+        let second_port = second.local_port().unwrap();
+        assert_ne!(first.local_port().unwrap(), second_port);
+
+        let mut first_events = first.subscribe().await;
+        let mut second_events = second.subscribe().await;
+        second.start().await.unwrap();
+        first.start().await.unwrap();
+
+        // The first service probes the second; the reply completes discovery
+        let second_addr: SocketAddr = ([127, 0, 0, 1], second_port).into();
+        first.add_manual(second_addr).await.unwrap();
+
+        assert!(discovered(&mut second_events, &first_id).await);
+        assert!(discovered(&mut first_events, &second_id).await);
+
+        first.stop().await;
+        second.stop().await;
+    }
+
+    #[tokio::test]
+    async fn test_event_streams_each_get_every_event() {
+        let device_info = DeviceInfo::new("Test Device", DeviceType::Desktop, 1816);
+        let config = DiscoveryConfig {
+            port: free_udp_port(),
+            ..Default::default()
+        };
+        let service = DiscoveryService::new(device_info, config).unwrap();
+        let mut first = service.events();
+        let mut second = service.events();
+
+        let identity = DeviceInfo::with_id(id(PHONE), "Phone", DeviceType::Phone, 1716)
+            .to_identity_packet()
+            .to_bytes()
+            .unwrap();
+        handle_announcement(&service, &identity, "192.168.1.20:1816".parse().unwrap())
+            .await
+            .unwrap();
+
+        for events in [&mut first, &mut second] {
+            let event = tokio::time::timeout(Duration::from_secs(1), events.next())
+                .await
+                .unwrap()
+                .unwrap();
+            assert!(event.is_device_discovered());
+            assert_eq!(event.device_id(), Some(&id(PHONE)));
+        }
+
+        // The stream ends with the service
+        drop(service);
+        assert!(first.next().await.is_none());
+    }
+
+    #[test]
+    fn test_accepts_device() {
+        let config = DiscoveryConfig::default();
+        assert!(config.accepts_device(&DeviceId::generate()));
+
+        let (phone, tablet) = (DeviceId::generate(), DeviceId::generate());
+        let config = DiscoveryConfig {
+            allowed_devices: [phone.clone(), tablet.clone()].into(),
+            denied_devices: [tablet.clone()].into(),
+            ..Default::default()
+        };
+        assert!(config.accepts_device(&phone));
+        assert!(!config.accepts_device(&tablet));
+        assert!(!config.accepts_device(&DeviceId::generate()));
+    }
+
+    #[tokio::test]
+    async fn test_allowlist_filters_announcements() {
+        let device_info = DeviceInfo::new("Test Device", DeviceType::Desktop, 1816);
+        let config = DiscoveryConfig {
+            port: free_udp_port(),
+            allowed_devices: [id(PHONE)].into(),
+            ..Default::default()
+        };
+        let service = DiscoveryService::new(device_info, config).unwrap();
+        let mut events = service.events();
+
+        for device_id in [STRANGER, PHONE] {
+            let identity = DeviceInfo::with_id(id(device_id), "Phone", DeviceType::Phone, 1716)
+                .to_identity_packet()
+                .to_bytes()
+                .unwrap();
+            handle_announcement(&service, &identity, "192.168.1.20:1816".parse().unwrap())
+                .await
+                .unwrap();
+        }
+
+        // Only the listed device surfaces
+        let event = tokio::time::timeout(Duration::from_secs(1), events.next())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(event.is_device_discovered());
+        assert_eq!(event.device_id(), Some(&id(PHONE)));
+        assert!(!service.last_seen.read().await.contains_key(STRANGER));
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_protocol_version_ignored() {
+        let device_info = DeviceInfo::new("Test Device", DeviceType::Desktop, 1816);
+        let config = DiscoveryConfig {
+            port: free_udp_port(),
+            ..Default::default()
+        };
+        let service = DiscoveryService::new(device_info, config).unwrap();
+
+        let mut phone = DeviceInfo::with_id(id(PHONE), "Phone", DeviceType::Phone, 1716);
+        for (protocol_version, accepted) in [(6, false), (7, true), (9, true)] {
+            phone.protocol_version = protocol_version;
+            let identity = phone.to_identity_packet().to_bytes().unwrap();
+            let handled =
+                handle_announcement(&service, &identity, "192.168.1.20:1816".parse().unwrap())
+                    .await
+                    .unwrap();
+            assert_eq!(handled, accepted, "protocol version {}", protocol_version);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_slow_event_subscriber_lags() {
+        let device_info = DeviceInfo::new("Test Device", DeviceType::Desktop, 1816);
+        let config = DiscoveryConfig {
+            port: free_udp_port(),
+            event_capacity: 2,
+            ..Default::default()
+        };
+        let service = DiscoveryService::new(device_info, config).unwrap();
+        let mut events = service.events();
+
+        // Sending never waits for the subscriber
+        for port in 0..5 {
+            let _ = service.event_tx.send(DiscoveryEvent::ServiceStarted { port });
+        }
+
+        match events.next().await {
+            Some(DiscoveryEvent::Lagged { skipped }) => assert_eq!(skipped, 3),
+            other => panic!("expected Lagged, got {:?}", other),
+        }
+        let ports: Vec<_> = events
+            .take(2)
+            .map(|event| match event {
+                DiscoveryEvent::ServiceStarted { port } => port,
+                other => panic!("unexpected event {:?}", other),
+            })
+            .collect()
+            .await;
+        assert_eq!(ports, vec![3, 4]);
+    }
+
+    #[tokio::test]
+    async fn test_resolver_tracks_device_address_changes() {
+        let device_info = DeviceInfo::new("Test Device", DeviceType::Desktop, 1816);
+        let config = DiscoveryConfig {
+            port: free_udp_port(),
+            ..Default::default()
+        };
+        let service = DiscoveryService::new(device_info, config).unwrap();
+        let resolver = service.resolver();
+
+        let identity = DeviceInfo::with_id(id(PHONE), "Phone", DeviceType::Phone, 1716)
+            .to_identity_packet()
+            .to_bytes()
+            .unwrap();
+        let announce = |src: &str| {
+            let src: SocketAddr = src.parse().unwrap();
+            let identity = identity.clone();
+            let service = &service;
+            async move { handle_announcement(service, &identity, src).await.unwrap() }
+        };
+
+        assert_eq!(resolver.resolve(&id(PHONE)).await, None);
+
+        assert!(announce("192.168.1.20:1816").await);
+        let wifi = TransportAddress::Tcp("192.168.1.20:1716".parse().unwrap());
+        assert_eq!(resolver.resolve(&id(PHONE)).await, Some(wifi));
+
+        // Same device, new network: the resolver follows it
+        assert!(announce("172.20.10.3:1816").await);
+        let hotspot = TransportAddress::Tcp("172.20.10.3:1716".parse().unwrap());
+        assert_eq!(resolver.resolve(&id(PHONE)).await, Some(hotspot));
+    }
+
+    #[tokio::test]
+    async fn test_dual_stack_announcements_coalesce() {
+        let device_info = DeviceInfo::new("Test Device", DeviceType::Desktop, 1816);
+        let config = DiscoveryConfig {
+            port: free_udp_port(),
+            ..Default::default()
+        };
+        let service = DiscoveryService::new(device_info, config).unwrap();
+        let mut events = service.events();
+
+        let identity = DeviceInfo::with_id(id(PHONE), "Phone", DeviceType::Phone, 1716)
+            .to_identity_packet()
+            .to_bytes()
+            .unwrap();
+        for src in ["192.168.1.20:1816", "[fe80::20]:1816"] {
+            handle_announcement(&service, &identity, src.parse().unwrap())
+                .await
+                .unwrap();
+        }
+
+        let v4: SocketAddr = "192.168.1.20:1716".parse().unwrap();
+        let v6: SocketAddr = "[fe80::20]:1716".parse().unwrap();
+        assert_eq!(service.last_seen.read().await.len(), 1);
+
+        // The second announcement updates the one device with both addresses
+        let mut received = Vec::new();
+        for _ in 0..2 {
+            let event = tokio::time::timeout(Duration::from_secs(1), events.next()).await;
+            received.push(event.unwrap().unwrap());
+        }
+        assert!(received[0].is_device_discovered());
+        match &received[1] {
+            DiscoveryEvent::DeviceUpdated { info, .. } => assert_eq!(info.addresses, vec![v6, v4]),
+            other => panic!("expected DeviceUpdated, got {:?}", other),
+        }
+        assert_eq!(service.resolver().device_address(&id(PHONE)).await, Some(v6));
+
+        let v4_first = DiscoveryResolver {
+            seen: service.last_seen.clone(),
+            preference: AddressFamilyPreference::Ipv4First,
+        };
+        assert_eq!(v4_first.device_addresses(&id(PHONE)).await, vec![v4, v6]);
+    }
+
+    #[test]
+    fn test_address_families_expire_separately() {
+        let v4: SocketAddr = "192.168.1.20:1716".parse().unwrap();
+        let v6: SocketAddr = "[fe80::20]:1716".parse().unwrap();
+        let mut seen = SeenDevice::default();
+        assert_eq!(seen.record(v4, 100), None);
+        seen.record(v6, 120);
+
+        // IPv4 went quiet; the device stays reachable over IPv6
+        assert!(seen.expire(135, 30));
+        assert_eq!(seen.addresses(AddressFamilyPreference::Ipv4First), vec![v6]);
+        assert!(!seen.expire(151, 30));
+    }
+
+    /// Wait for the next discovery event
+    async fn next_event(events: &mut DiscoveryEventStream) -> DiscoveryEvent {
+        let event = tokio::time::timeout(Duration::from_secs(1), events.next()).await;
+        event.unwrap().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_update_identity_reannounces() {
+        let probe_socket = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let probe_addr = probe_socket.local_addr().unwrap();
+
+        let device_info = DeviceInfo::new("Old Name", DeviceType::Desktop, 1816);
+        let config = DiscoveryConfig {
+            port: free_udp_port(),
+            broadcast_interval: Duration::from_secs(60),
+            ..Default::default()
+        };
+        let mut service = DiscoveryService::new(device_info.clone(), config).unwrap();
+        let mut events = service.events();
+
+        // Probes from add_manual and the first tick at start, then silence
+        service.add_manual(probe_addr).await.unwrap();
+        service.start().await.unwrap();
+        assert!(recv_probe(&probe_socket).await);
+        assert!(recv_probe(&probe_socket).await);
+        assert!(!recv_probe(&probe_socket).await);
+        assert!(matches!(next_event(&mut events).await, DiscoveryEvent::ServiceStarted { .. }));
+
+        let renamed = DeviceInfo {
+            device_name: "New Name".to_string(),
+            ..device_info.clone()
+        };
+        service.update_identity(renamed).unwrap();
+        assert_eq!(service.device_info().device_name, "New Name");
+
+        // Re-announced right away with the new name
+        let mut buf = [0u8; 4096];
+        let (size, _) =
+            tokio::time::timeout(Duration::from_millis(500), probe_socket.recv_from(&mut buf))
+                .await
+                .expect("no re-announcement after rename")
+                .unwrap();
+        let announced =
+            DeviceInfo::from_identity_packet(&Packet::from_bytes(&buf[..size]).unwrap()).unwrap();
+        assert_eq!(announced.device_name, "New Name");
+        assert_eq!(announced.device_id, device_info.device_id);
+
+        // Connection owners are told to re-send the identity
+        match next_event(&mut events).await {
+            DiscoveryEvent::IdentityUpdated { info } => assert_eq!(info.device_name, "New Name"),
+            other => panic!("expected IdentityUpdated, got {:?}", other),
+        }
+
+        // The device ID cannot change
+        let other = DeviceInfo::new("New Name", DeviceType::Desktop, 1816);
+        assert!(matches!(
+            service.update_identity(other),
+            Err(ProtocolError::Discovery(_))
+        ));
+        assert_eq!(service.device_info().device_id, device_info.device_id);
+        service.stop().await;
+    }
+
+    #[tokio::test]
+    async fn test_network_loss_suspends_devices() {
+        let probe_socket = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let probe_addr = probe_socket.local_addr().unwrap();
+
+        let device_info = DeviceInfo::new("Test Device", DeviceType::Desktop, 1816);
+        let config = DiscoveryConfig {
+            port: free_udp_port(),
+            broadcast_interval: Duration::from_secs(60),
+            enable_timeout_check: false,
+            watch_interfaces: false,
+            ..Default::default()
+        };
+        let timeout = config.device_timeout;
+        let mut service = DiscoveryService::new(device_info, config).unwrap();
+        let mut events = service.events();
+
+        // Probes from add_manual and the first tick at start, then silence
+        service.add_manual(probe_addr).await.unwrap();
+        service.start().await.unwrap();
+        assert!(recv_probe(&probe_socket).await);
+        assert!(recv_probe(&probe_socket).await);
+        assert!(!recv_probe(&probe_socket).await);
+        assert!(matches!(next_event(&mut events).await, DiscoveryEvent::ServiceStarted { .. }));
+
+        let identity = DeviceInfo::with_id(id(PHONE), "Phone", DeviceType::Phone, 1716)
+            .to_identity_packet()
+            .to_bytes()
+            .unwrap();
+        handle_announcement(&service, &identity, "192.168.1.20:1816".parse().unwrap())
+            .await
+            .unwrap();
+        assert!(next_event(&mut events).await.is_device_discovered());
+        let expire_at = |now: u64| {
+            let network_up = service.network_up.subscribe();
+            let service = &service;
+            async move {
+                DiscoveryService::expire_devices(
+                    &service.last_seen,
+                    &network_up,
+                    &service.event_tx,
+                    timeout,
+                    now,
+                )
+                .await
+            }
+        };
+
+        // The interface drops: the phone is suspended, not lost
+        service.set_network_available(false).await;
+        assert!(!service.network_available());
+        let event = next_event(&mut events).await;
+        assert!(event.is_device_suspended());
+        assert_eq!(event.device_id(), Some(&id(PHONE)));
+        assert_eq!(service.suspended_devices().await, vec![id(PHONE)]);
+
+        let long_after = current_timestamp() + timeout.as_secs() * 10;
+        expire_at(long_after).await;
+        assert!(service.resolver().device_address(&id(PHONE)).await.is_some());
+
+        // Repeated reports change nothing
+        service.set_network_available(false).await;
+
+        // The interface recovers: the phone resumes and we re-probe at once
+        service.set_network_available(true).await;
+        let event = next_event(&mut events).await;
+        assert!(event.is_device_resumed());
+        assert_eq!(event.device_id(), Some(&id(PHONE)));
+        assert!(service.suspended_devices().await.is_empty());
+        assert!(recv_probe(&probe_socket).await, "no re-probe after recovery");
+
+        // The phone has a fresh timeout to announce itself again
+        let now = current_timestamp();
+        expire_at(now + 1).await;
+        assert!(service.resolver().device_address(&id(PHONE)).await.is_some());
+        expire_at(now + timeout.as_secs() + 2).await;
+        assert!(next_event(&mut events).await.is_device_timeout());
+        assert!(service.resolver().device_address(&id(PHONE)).await.is_none());
+        service.stop().await;
+    }
+
+    #[tokio::test]
+    async fn test_custom_port_is_bound_exactly() {
+        let port = free_udp_port();
+        let device_info = DeviceInfo::new("Test Device", DeviceType::Desktop, 1816);
+        let config = DiscoveryConfig {
+            port,
+            ..Default::default()
+        };
+        let service = DiscoveryService::new(device_info.clone(), config.clone()).unwrap();
+        assert_eq!(service.local_port().unwrap(), port);
+        assert_eq!(config.broadcast_port(), port);
+
+        // No silent fallback when a custom port is taken
+        assert!(DiscoveryService::new(device_info, config).is_err());
     }
 
     #[tokio::test]
@@ -459,6 +2001,6 @@ mod tests {
         let device_info = DeviceInfo::new("Test Device", DeviceType::Desktop, 1816);
         let service = DiscoveryService::with_defaults(device_info).unwrap();
         let port = service.local_port().unwrap();
-        assert!(port >= PORT_RANGE_START && port <= PORT_RANGE_END);
+        assert!((PORT_RANGE_START..=PORT_RANGE_END).contains(&port));
     }
 }
@@ -0,0 +1,268 @@
+//! Local Subnet Scoping
+//!
+//! Helpers for restricting discovery to directly attached IPv4 subnets, so
+//! identity announcements from routed segments can be ignored.
+
+use std::net::{IpAddr, Ipv4Addr};
+
+/// An IPv4 subnet in CIDR form
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Ipv4Subnet {
+    /// Network address (host bits cleared)
+    network: Ipv4Addr,
+
+    /// Prefix length in bits (0-32)
+    prefix_len: u8,
+}
+
+impl Ipv4Subnet {
+    /// Create a subnet from any address inside it and a prefix length
+    ///
+    /// Host bits are cleared and prefix lengths above 32 are clamped.
+    pub fn new(addr: Ipv4Addr, prefix_len: u8) -> Self {
+        let prefix_len = prefix_len.min(32);
+        Self {
+            network: Ipv4Addr::from(u32::from(addr) & Self::mask(prefix_len)),
+            prefix_len,
+        }
+    }
+
+    /// Create a subnet from an address and a netmask such as `255.255.255.0`
+    pub fn from_netmask(addr: Ipv4Addr, netmask: Ipv4Addr) -> Self {
+        Self::new(addr, u32::from(netmask).leading_ones() as u8)
+    }
+
+    /// Get the network address
+    pub fn network(&self) -> Ipv4Addr {
+        self.network
+    }
+
+    /// Get the prefix length
+    pub fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    /// Check whether an address belongs to this subnet
+    ///
+    /// IPv4-mapped IPv6 addresses are compared as IPv4; other IPv6
+    /// addresses never match.
+    pub fn contains(&self, addr: IpAddr) -> bool {
+        let v4 = match addr {
+            IpAddr::V4(v4) => v4,
+            IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+                Some(v4) => v4,
+                None => return false,
+            },
+        };
+        u32::from(v4) & Self::mask(self.prefix_len) == u32::from(self.network)
+    }
+
+    fn mask(prefix_len: u8) -> u32 {
+        u32::MAX.checked_shl(32 - prefix_len as u32).unwrap_or(0)
+    }
+}
+
+impl std::fmt::Display for Ipv4Subnet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix_len)
+    }
+}
+
+/// Check whether `addr` belongs to any of `subnets`
+pub fn in_any_subnet(addr: IpAddr, subnets: &[Ipv4Subnet]) -> bool {
+    subnets.iter().any(|subnet| subnet.contains(addr))
+}
+
+/// Name prefixes of bridges and virtual links that do not reach other devices
+#[cfg(unix)]
+const VIRTUAL_INTERFACE_PREFIXES: &[&str] = &[
+    "docker", "br-", "veth", "virbr", "vboxnet", "vmnet", "lxcbr", "lxdbr", "podman", "cni",
+];
+
+/// An IPv4 address assigned to a local network interface
+#[cfg(unix)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct InterfaceAddr {
+    /// Interface name, e.g. `wlan0`
+    name: String,
+
+    /// `IFF_*` flags
+    flags: u32,
+
+    /// Subnet of the address
+    subnet: Ipv4Subnet,
+}
+
+#[cfg(unix)]
+impl InterfaceAddr {
+    /// Check whether this interface can reach other devices on the network
+    ///
+    /// The interface must be up and running, and neither loopback nor a
+    /// local bridge or virtual link (docker0, virbr0, veth*, ...).
+    fn is_usable(&self) -> bool {
+        let required = (libc::IFF_UP | libc::IFF_RUNNING) as u32;
+        self.flags & required == required
+            && self.flags & libc::IFF_LOOPBACK as u32 == 0
+            && !self.subnet.network().is_loopback()
+            && !VIRTUAL_INTERFACE_PREFIXES
+                .iter()
+                .any(|prefix| self.name.starts_with(prefix))
+    }
+}
+
+/// Check whether any IPv4 interface that reaches the network is up
+///
+/// Loopback, bridges and virtual links do not count, nor do interfaces
+/// that are down or have no carrier. Platforms that cannot enumerate
+/// interfaces are always treated as connected.
+#[cfg(unix)]
+pub fn network_available() -> bool {
+    interface_addrs().iter().any(InterfaceAddr::is_usable)
+}
+
+/// Check whether any IPv4 interface that reaches the network is up
+///
+/// Interface enumeration is not supported on this platform, so the network
+/// is always treated as available.
+#[cfg(not(unix))]
+pub fn network_available() -> bool {
+    true
+}
+
+/// Enumerate the IPv4 subnets of the local network interfaces
+///
+/// Includes loopback. Returns an empty list if interfaces cannot be queried.
+#[cfg(unix)]
+pub fn local_subnets() -> Vec<Ipv4Subnet> {
+    let mut subnets = Vec::new();
+    for addr in interface_addrs() {
+        if !subnets.contains(&addr.subnet) {
+            subnets.push(addr.subnet);
+        }
+    }
+    subnets
+}
+
+/// Enumerate the IPv4 addresses of the local network interfaces
+///
+/// Returns an empty list if interfaces cannot be queried.
+#[cfg(unix)]
+fn interface_addrs() -> Vec<InterfaceAddr> {
+    let mut addrs = Vec::new();
+    let mut ifaddrs: *mut libc::ifaddrs = std::ptr::null_mut();
+
+    // SAFETY: getifaddrs allocates a linked list that we walk read-only and
+    // release with freeifaddrs before returning.
+    unsafe {
+        if libc::getifaddrs(&mut ifaddrs) != 0 {
+            tracing::warn!(
+                "Failed to enumerate network interfaces: {}",
+                std::io::Error::last_os_error()
+            );
+            return addrs;
+        }
+
+        let mut current = ifaddrs;
+        while let Some(ifa) = current.as_ref() {
+            if !ifa.ifa_addr.is_null()
+                && !ifa.ifa_netmask.is_null()
+                && (*ifa.ifa_addr).sa_family as libc::c_int == libc::AF_INET
+            {
+                let addr = &*(ifa.ifa_addr as *const libc::sockaddr_in);
+                let mask = &*(ifa.ifa_netmask as *const libc::sockaddr_in);
+                let subnet = Ipv4Subnet::from_netmask(
+                    Ipv4Addr::from(u32::from_be(addr.sin_addr.s_addr)),
+                    Ipv4Addr::from(u32::from_be(mask.sin_addr.s_addr)),
+                );
+                let name = if ifa.ifa_name.is_null() {
+                    String::new()
+                } else {
+                    std::ffi::CStr::from_ptr(ifa.ifa_name)
+                        .to_string_lossy()
+                        .into_owned()
+                };
+                addrs.push(InterfaceAddr {
+                    name,
+                    flags: ifa.ifa_flags,
+                    subnet,
+                });
+            }
+            current = ifa.ifa_next;
+        }
+
+        libc::freeifaddrs(ifaddrs);
+    }
+
+    addrs
+}
+
+/// Enumerate the IPv4 subnets of the local network interfaces
+///
+/// Interface enumeration is not supported on this platform, so the list is
+/// always empty and subnet scoping is skipped.
+#[cfg(not(unix))]
+pub fn local_subnets() -> Vec<Ipv4Subnet> {
+    Vec::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subnet_contains() {
+        let subnet = Ipv4Subnet::new(Ipv4Addr::new(192, 168, 1, 42), 24);
+        assert_eq!(subnet.network(), Ipv4Addr::new(192, 168, 1, 0));
+        assert_eq!(subnet.to_string(), "192.168.1.0/24");
+
+        assert!(subnet.contains("192.168.1.200".parse().unwrap()));
+        assert!(subnet.contains("::ffff:192.168.1.7".parse().unwrap()));
+        assert!(!subnet.contains("192.168.2.1".parse().unwrap()));
+        assert!(!subnet.contains("fe80::1".parse().unwrap()));
+
+        let any = Ipv4Subnet::new(Ipv4Addr::new(10, 0, 0, 1), 0);
+        assert!(any.contains("8.8.8.8".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_subnet_from_netmask() {
+        let subnet =
+            Ipv4Subnet::from_netmask(Ipv4Addr::new(10, 1, 2, 3), Ipv4Addr::new(255, 255, 0, 0));
+        assert_eq!(subnet.prefix_len(), 16);
+        assert_eq!(subnet.network(), Ipv4Addr::new(10, 1, 0, 0));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_usable_interfaces() {
+        let up = (libc::IFF_UP | libc::IFF_RUNNING) as u32;
+        let interface = |name: &str, flags: u32, addr: [u8; 4]| InterfaceAddr {
+            name: name.to_string(),
+            flags,
+            subnet: Ipv4Subnet::new(Ipv4Addr::from(addr), 24),
+        };
+
+        assert!(interface("wlan0", up, [192, 168, 1, 5]).is_usable());
+        assert!(interface("enp3s0", up | libc::IFF_BROADCAST as u32, [10, 0, 0, 2]).is_usable());
+
+        // Down, or up without a carrier
+        assert!(!interface("wlan0", 0, [192, 168, 1, 5]).is_usable());
+        assert!(!interface("eth0", libc::IFF_UP as u32, [192, 168, 1, 5]).is_usable());
+
+        // Loopback
+        let loopback = up | libc::IFF_LOOPBACK as u32;
+        assert!(!interface("lo", loopback, [127, 0, 0, 1]).is_usable());
+
+        // Local bridges and virtual links
+        assert!(!interface("docker0", up, [172, 17, 0, 1]).is_usable());
+        assert!(!interface("virbr0", up, [192, 168, 122, 1]).is_usable());
+        assert!(!interface("br-1a2b3c", up, [172, 18, 0, 1]).is_usable());
+        assert!(!interface("veth12ab", up, [169, 254, 3, 1]).is_usable());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_local_subnets_include_loopback() {
+        assert!(in_any_subnet("127.0.0.1".parse().unwrap(), &local_subnets()));
+    }
+}
@@ -4,8 +4,10 @@
 //!
 //! ## Implemented Modules
 //!
-//! - [`discovery`] - UDP device discovery on port 1716
+//! - [`discovery`] - UDP device discovery on port 1816 (configurable)
 //! - [`transport`] - Transport abstraction (TCP, Bluetooth)
+//! - [`reachability`] - Heartbeat-based device reachability
+//! - [`presence`] - Last-heard times for staleness checks
 //!
 //! ## Planned Modules
 //!
@@ -27,6 +29,8 @@
 // Module exports
 pub mod discovery;  // ✅ Extracted (Issue #46)
 pub mod transport;  // ✅ Transport abstraction layer
+pub mod reachability; // ✅ Staged heartbeat reachability
+pub mod presence;     // ✅ Shared device staleness
 
 // Re-exports for convenience
 pub use discovery::{
@@ -35,10 +39,15 @@ pub use discovery::{
     PORT_RANGE_END, PORT_RANGE_START,
 };
 
+pub use presence::PresenceTracker;
+pub use reachability::{Reachability, ReachabilityProbe};
+
 pub use transport::{
-    LatencyCategory, Transport, TransportAddress, TransportCapabilities, TransportFactory,
-    TransportPreference, TransportType, KDECONNECT_SERVICE_UUID, MAX_BT_PACKET_SIZE,
-    MAX_TCP_PACKET_SIZE, RFCOMM_READ_CHAR_UUID, RFCOMM_WRITE_CHAR_UUID,
+    AddressParseError, ConnectionQuality, LatencyCategory, MultiplexedTransport, MuxConfig,
+    NetworkStats, NetworkStatsEstimator, QualityMonitor, Transport, TransportAddress,
+    TransportCapabilities, TransportFactory, TransportPreference, TransportType,
+    KDECONNECT_SERVICE_UUID, MAX_BT_PACKET_SIZE, MAX_TCP_PACKET_SIZE, RFCOMM_READ_CHAR_UUID,
+    RFCOMM_WRITE_CHAR_UUID,
 };
 
 // pub use tcp::TcpTransport;
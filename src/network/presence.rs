@@ -0,0 +1,125 @@
+//! Device Presence
+//!
+//! Aggregators such as [`BatteryAggregator`](crate::plugins::battery::BatteryAggregator)
+//! need to know when a device's reported data has gone stale. Rather than
+//! each plugin timing its own reports, [`PresenceTracker`] records when any
+//! packet was last received from each device, and consumers ask whether that
+//! was within their own window.
+//!
+//! The tracker does no I/O. Call [`PresenceTracker::record`] for every
+//! packet received from a device; share one tracker between consumers
+//! behind a lock.
+//!
+//! ```rust
+//! use cosmic_ext_connect_core::network::presence::PresenceTracker;
+//! use cosmic_ext_connect_core::protocol::DeviceId;
+//! use std::time::Duration;
+//!
+//! let (phone, tablet) = (DeviceId::generate(), DeviceId::generate());
+//! let mut presence = PresenceTracker::new();
+//! presence.record(phone.clone());
+//! assert!(!presence.is_stale(&phone, Duration::from_secs(60)));
+//! assert!(presence.is_stale(&tablet, Duration::from_secs(60)));
+//! ```
+
+use crate::protocol::DeviceId;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Last-heard times of devices
+#[derive(Debug, Clone, Default)]
+pub struct PresenceTracker {
+    /// When a packet was last received, by device ID
+    last_seen: HashMap<DeviceId, Instant>,
+}
+
+impl PresenceTracker {
+    /// Create an empty tracker
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a packet received from a device now
+    pub fn record(&mut self, device_id: DeviceId) {
+        self.record_at(device_id, Instant::now());
+    }
+
+    /// Record a packet received from a device at `at`
+    ///
+    /// An earlier time than the one already recorded is ignored.
+    pub fn record_at(&mut self, device_id: DeviceId, at: Instant) {
+        let seen = self.last_seen.entry(device_id).or_insert(at);
+        *seen = (*seen).max(at);
+    }
+
+    /// Get when a device was last heard from
+    pub fn last_seen(&self, device_id: &DeviceId) -> Option<Instant> {
+        self.last_seen.get(device_id).copied()
+    }
+
+    /// Check whether a device has not been heard from `within` the past
+    ///
+    /// Devices that were never heard from are stale.
+    pub fn is_stale(&self, device_id: &DeviceId, within: Duration) -> bool {
+        self.is_stale_at(device_id, within, Instant::now())
+    }
+
+    /// Check whether a device has not been heard from `within` before `now`
+    pub fn is_stale_at(&self, device_id: &DeviceId, within: Duration, now: Instant) -> bool {
+        self.last_seen(device_id)
+            .map_or(true, |seen| now.saturating_duration_since(seen) > within)
+    }
+
+    /// Forget a device
+    pub fn remove(&mut self, device_id: &DeviceId) -> bool {
+        self.last_seen.remove(device_id).is_some()
+    }
+
+    /// Get the number of tracked devices
+    pub fn len(&self) -> usize {
+        self.last_seen.len()
+    }
+
+    /// Check whether no devices are tracked
+    pub fn is_empty(&self) -> bool {
+        self.last_seen.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW: Duration = Duration::from_secs(30);
+
+    fn phone() -> DeviceId {
+        DeviceId::parse("phone_0123456789abcdef0123456789ab").unwrap()
+    }
+
+    #[test]
+    fn test_stale_after_window() {
+        let start = Instant::now();
+        let mut presence = PresenceTracker::new();
+        assert!(presence.is_stale_at(&phone(), WINDOW, start));
+
+        presence.record_at(phone(), start);
+        assert!(!presence.is_stale_at(&phone(), WINDOW, start + WINDOW));
+        assert!(presence.is_stale_at(&phone(), WINDOW, start + WINDOW + Duration::from_secs(1)));
+
+        // Any later packet refreshes the device
+        presence.record_at(phone(), start + WINDOW);
+        assert!(!presence.is_stale_at(&phone(), WINDOW, start + WINDOW * 2));
+    }
+
+    #[test]
+    fn test_out_of_order_record_ignored() {
+        let start = Instant::now();
+        let mut presence = PresenceTracker::new();
+        presence.record_at(phone(), start + WINDOW);
+        presence.record_at(phone(), start);
+        assert_eq!(presence.last_seen(&phone()), Some(start + WINDOW));
+
+        assert!(presence.remove(&phone()));
+        assert!(presence.is_empty());
+    }
+}
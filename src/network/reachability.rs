@@ -0,0 +1,310 @@
+//! Device Reachability
+//!
+//! A dropped connection or a lost ping does not mean a device is gone; a
+//! brief Wi-Fi hiccup looks the same at first. [`ReachabilityProbe`] tracks
+//! heartbeats to a device and classifies it in stages, so the UI can gray a
+//! device out before removing it and does not flap on short outages.
+//!
+//! ## Stages
+//!
+//! - [`Reachability::Online`] - the device answered its last heartbeat
+//! - [`Reachability::Uncertain`] - at least one heartbeat went unanswered
+//! - [`Reachability::Offline`] - several heartbeats in a row went unanswered
+//!
+//! Any packet from the device counts as a response and returns it to
+//! `Online`.
+//!
+//! ## Usage
+//!
+//! The probe does no I/O. The caller sends a ping when
+//! [`ReachabilityProbe::should_ping`] says so, reports it with
+//! [`ReachabilityProbe::ping_sent`], reports incoming packets with
+//! [`ReachabilityProbe::record_seen`], and calls
+//! [`ReachabilityProbe::update`] periodically to expire unanswered pings.
+//!
+//! Answered and expired heartbeats also feed a
+//! [`NetworkStatsEstimator`], so a transport can report RTT, jitter and
+//! loss from [`ReachabilityProbe::network_stats`].
+//!
+//! ```rust
+//! use cosmic_ext_connect_core::network::reachability::{Reachability, ReachabilityProbe};
+//!
+//! let mut probe = ReachabilityProbe::new();
+//! probe.record_seen();
+//! assert_eq!(probe.update(), Reachability::Online);
+//! ```
+
+use crate::network::transport::{NetworkStats, NetworkStatsEstimator};
+use std::time::{Duration, Instant};
+use tracing::{debug, info};
+
+/// Default time between heartbeats
+pub const DEFAULT_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Default time a heartbeat may go unanswered before it counts as missed
+pub const DEFAULT_HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Default number of missed heartbeats in a row before a device is offline
+pub const DEFAULT_OFFLINE_AFTER: u32 = 3;
+
+/// Reachability classification of a device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Reachability {
+    /// The device answered its last heartbeat
+    Online,
+    /// The device missed at least one heartbeat
+    Uncertain,
+    /// The device missed several heartbeats in a row
+    Offline,
+}
+
+/// Heartbeat tracker that classifies a device's reachability
+#[derive(Debug, Clone)]
+pub struct ReachabilityProbe {
+    /// Time between heartbeats
+    interval: Duration,
+    /// Time a heartbeat may go unanswered
+    timeout: Duration,
+    /// Missed heartbeats in a row before the device is offline
+    offline_after: u32,
+    /// When the device was last heard from
+    last_seen: Option<Instant>,
+    /// When the last heartbeat was sent
+    last_ping: Option<Instant>,
+    /// Whether the last heartbeat is still awaiting a response
+    awaiting: bool,
+    /// Heartbeats missed in a row
+    missed: u32,
+    /// Current classification
+    state: Reachability,
+    /// RTT, jitter and loss from heartbeat round trips
+    stats: NetworkStatsEstimator,
+}
+
+impl Default for ReachabilityProbe {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ReachabilityProbe {
+    /// Create a probe with the default interval, timeout and offline threshold
+    pub fn new() -> Self {
+        Self {
+            interval: DEFAULT_HEARTBEAT_INTERVAL,
+            timeout: DEFAULT_HEARTBEAT_TIMEOUT,
+            offline_after: DEFAULT_OFFLINE_AFTER,
+            last_seen: None,
+            last_ping: None,
+            awaiting: false,
+            missed: 0,
+            state: Reachability::Online,
+            stats: NetworkStatsEstimator::new(),
+        }
+    }
+
+    /// Set the time between heartbeats
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Set the time a heartbeat may go unanswered before it counts as missed
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set the number of missed heartbeats in a row before a device is offline
+    pub fn with_offline_after(mut self, missed: u32) -> Self {
+        self.offline_after = missed.max(1);
+        self
+    }
+
+    /// Current classification
+    pub fn state(&self) -> Reachability {
+        self.state
+    }
+
+    /// When the device was last heard from
+    pub fn last_seen(&self) -> Option<Instant> {
+        self.last_seen
+    }
+
+    /// Heartbeats missed in a row
+    pub fn missed(&self) -> u32 {
+        self.missed
+    }
+
+    /// Estimated RTT, jitter and loss from heartbeats so far
+    pub fn network_stats(&self) -> NetworkStats {
+        self.stats.stats()
+    }
+
+    /// Check whether a heartbeat should be sent now
+    pub fn should_ping(&self) -> bool {
+        self.should_ping_at(Instant::now())
+    }
+
+    /// Check whether a heartbeat should be sent at `now`
+    ///
+    /// True when no heartbeat is outstanding and neither a packet nor a
+    /// heartbeat has gone by within the interval.
+    pub fn should_ping_at(&self, now: Instant) -> bool {
+        if self.awaiting {
+            return false;
+        }
+        let last_activity = self.last_seen.max(self.last_ping);
+        last_activity.map_or(true, |at| {
+            now.saturating_duration_since(at) >= self.interval
+        })
+    }
+
+    /// Record that a heartbeat was sent
+    pub fn ping_sent(&mut self) {
+        self.ping_sent_at(Instant::now());
+    }
+
+    /// Record that a heartbeat was sent at `now`
+    pub fn ping_sent_at(&mut self, now: Instant) {
+        self.last_ping = Some(now);
+        self.awaiting = true;
+    }
+
+    /// Record a packet from the device
+    pub fn record_seen(&mut self) {
+        self.record_seen_at(Instant::now());
+    }
+
+    /// Record a packet from the device at `now`
+    ///
+    /// A packet that answers an outstanding heartbeat is an RTT sample.
+    pub fn record_seen_at(&mut self, now: Instant) {
+        if let (true, Some(sent)) = (self.awaiting, self.last_ping) {
+            self.stats.record_rtt(now.saturating_duration_since(sent));
+        }
+        self.last_seen = Some(now);
+        self.awaiting = false;
+        self.missed = 0;
+        self.transition(Reachability::Online);
+    }
+
+    /// Expire an unanswered heartbeat and return the classification
+    pub fn update(&mut self) -> Reachability {
+        self.update_at(Instant::now())
+    }
+
+    /// Expire an unanswered heartbeat as of `now` and return the classification
+    pub fn update_at(&mut self, now: Instant) -> Reachability {
+        let expired = self.awaiting
+            && self
+                .last_ping
+                .is_some_and(|sent| now.saturating_duration_since(sent) >= self.timeout);
+
+        if expired {
+            self.awaiting = false;
+            self.missed += 1;
+            self.stats.record_loss();
+            debug!("Heartbeat missed ({} in a row)", self.missed);
+
+            let state = if self.missed >= self.offline_after {
+                Reachability::Offline
+            } else {
+                Reachability::Uncertain
+            };
+            self.transition(state);
+        }
+
+        self.state
+    }
+
+    fn transition(&mut self, state: Reachability) {
+        if self.state != state {
+            info!(
+                "Device reachability changed: {:?} -> {:?}",
+                self.state, state
+            );
+            self.state = state;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_state_progression_and_recovery() {
+        let mut probe = ReachabilityProbe::new()
+            .with_interval(Duration::from_secs(5))
+            .with_timeout(Duration::from_secs(2))
+            .with_offline_after(3);
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        probe.record_seen_at(at(0));
+        assert_eq!(probe.update_at(at(1)), Reachability::Online);
+        assert!(!probe.should_ping_at(at(4)));
+        assert!(probe.should_ping_at(at(5)));
+
+        // A heartbeat answered in time keeps the device online
+        probe.ping_sent_at(at(5));
+        assert!(!probe.should_ping_at(at(6)));
+        probe.record_seen_at(at(6));
+        assert_eq!(probe.update_at(at(8)), Reachability::Online);
+
+        // One missed heartbeat: uncertain, not yet removed
+        probe.ping_sent_at(at(11));
+        assert_eq!(probe.update_at(at(12)), Reachability::Online);
+        assert_eq!(probe.update_at(at(13)), Reachability::Uncertain);
+        assert_eq!(probe.missed(), 1);
+
+        probe.ping_sent_at(at(16));
+        assert_eq!(probe.update_at(at(18)), Reachability::Uncertain);
+
+        // Third miss in a row: offline
+        probe.ping_sent_at(at(21));
+        assert_eq!(probe.update_at(at(23)), Reachability::Offline);
+        assert_eq!(probe.last_seen(), Some(at(6)));
+
+        // Any packet brings it straight back
+        probe.record_seen_at(at(30));
+        assert_eq!(probe.update_at(at(31)), Reachability::Online);
+        assert_eq!(probe.missed(), 0);
+        assert_eq!(probe.last_seen(), Some(at(30)));
+    }
+
+    #[test]
+    fn test_heartbeats_feed_network_stats() {
+        let mut probe = ReachabilityProbe::new();
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        probe.ping_sent_at(at(0));
+        probe.record_seen_at(at(80));
+        // Not an answer to a heartbeat, so not an RTT sample
+        probe.record_seen_at(at(1_000));
+        probe.ping_sent_at(at(6_000));
+        probe.update_at(at(9_000));
+
+        let stats = probe.network_stats();
+        assert_eq!(stats.rtt, Some(Duration::from_millis(80)));
+        assert_eq!((stats.samples, stats.lost), (1, 1));
+        assert!(stats.loss_rate > 0.0);
+    }
+
+    #[test]
+    fn test_never_seen_device_pings_immediately() {
+        let mut probe = ReachabilityProbe::new();
+        let now = Instant::now();
+
+        assert!(probe.should_ping_at(now));
+        probe.ping_sent_at(now);
+        assert!(!probe.should_ping_at(now + DEFAULT_HEARTBEAT_INTERVAL));
+        assert_eq!(
+            probe.update_at(now + DEFAULT_HEARTBEAT_TIMEOUT),
+            Reachability::Uncertain
+        );
+        assert_eq!(probe.last_seen(), None);
+    }
+}
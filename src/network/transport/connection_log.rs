@@ -0,0 +1,174 @@
+//! Connection Event Log
+//!
+//! Tracing output is hard to correlate after the fact when a connection is
+//! flaky. [`ConnectionLog`] keeps a bounded, structured history of a
+//! device's connection events (connects, disconnects, reconnect attempts
+//! and packet errors) that can be queried in memory or serialized into a
+//! support dump.
+//!
+//! The log is a ring buffer: once `capacity` events are stored, recording
+//! another evicts the oldest.
+
+use chrono::Utc;
+use serde::Serialize;
+use std::collections::VecDeque;
+
+/// Default number of events kept per device
+pub const DEFAULT_CONNECTION_LOG_CAPACITY: usize = 64;
+
+/// Something that happened to a device's connection
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ConnectionEvent {
+    /// A connection was established
+    Connected {
+        /// Address connected to
+        address: String,
+    },
+
+    /// The connection was lost
+    Disconnected {
+        /// Why the connection was lost
+        reason: String,
+    },
+
+    /// A reconnect was started
+    Reconnecting {
+        /// Address of the lost connection
+        address: String,
+    },
+
+    /// A packet could not be sent or received on a live connection
+    PacketError {
+        /// Error description
+        message: String,
+    },
+}
+
+/// A [`ConnectionEvent`] with the time it was recorded
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimestampedEvent {
+    /// UNIX epoch timestamp in milliseconds
+    pub timestamp_ms: i64,
+
+    /// What happened
+    pub event: ConnectionEvent,
+}
+
+/// Bounded history of a device's connection events
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionLog {
+    /// Events from oldest to newest
+    events: VecDeque<TimestampedEvent>,
+
+    /// Maximum number of events kept
+    capacity: usize,
+}
+
+impl ConnectionLog {
+    /// Create an empty log keeping at most `capacity` events (minimum 1)
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            events: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Record an event with the current time
+    pub fn record(&mut self, event: ConnectionEvent) {
+        self.record_at(event, Utc::now().timestamp_millis());
+    }
+
+    /// Record an event with an explicit timestamp, evicting the oldest if full
+    pub fn record_at(&mut self, event: ConnectionEvent, timestamp_ms: i64) {
+        if self.events.len() == self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back(TimestampedEvent {
+            timestamp_ms,
+            event,
+        });
+    }
+
+    /// Get up to `n` of the most recent events, newest first
+    pub fn recent(&self, n: usize) -> Vec<TimestampedEvent> {
+        self.events.iter().rev().take(n).cloned().collect()
+    }
+
+    /// Get the number of events stored
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Check whether no events are stored
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Get the maximum number of events kept
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Remove all events
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+}
+
+impl Default for ConnectionLog {
+    fn default() -> Self {
+        Self::new(DEFAULT_CONNECTION_LOG_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(n: usize) -> ConnectionEvent {
+        ConnectionEvent::PacketError {
+            message: format!("error {}", n),
+        }
+    }
+
+    #[test]
+    fn test_oldest_events_evicted() {
+        let mut log = ConnectionLog::new(3);
+        for n in 0..5 {
+            log.record_at(error(n), n as i64);
+        }
+
+        assert_eq!(log.len(), 3);
+        let recent = log.recent(10);
+        let timestamps: Vec<_> = recent.iter().map(|event| event.timestamp_ms).collect();
+        assert_eq!(timestamps, vec![4, 3, 2]);
+        assert_eq!(recent[0].event, error(4));
+
+        assert_eq!(log.recent(1), vec![recent[0].clone()]);
+        assert!(log.recent(0).is_empty());
+    }
+
+    #[test]
+    fn test_export() {
+        let mut log = ConnectionLog::new(0);
+        assert_eq!(log.capacity(), 1);
+
+        log.record_at(
+            ConnectionEvent::Connected {
+                address: "192.168.1.20:1716".to_string(),
+            },
+            1000,
+        );
+        let json = serde_json::to_value(log.recent(1)).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!([{
+                "timestampMs": 1000,
+                "event": { "type": "connected", "address": "192.168.1.20:1716" }
+            }])
+        );
+    }
+}
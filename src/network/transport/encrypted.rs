@@ -0,0 +1,676 @@
+//! Application-Layer Packet Encryption
+//!
+//! Fallback encryption for transports without TLS (e.g. Bluetooth or debug
+//! transports). Each packet is serialized, sealed with ChaCha20-Poly1305 and
+//! sent inside a `cconnect.encrypted` envelope packet.
+//!
+//! ## Keys and Nonces
+//!
+//! Both devices start from the same shared secret derived from pairing. Each
+//! sending direction uses its own key, derived with HKDF-SHA256 from the
+//! secret, the direction, a random per-session salt and the connection's
+//! handshake nonces. Nonces are a per-key counter, so a nonce is never
+//! reused under the same key; before the counter runs out the sender starts
+//! a new session with a fresh salt.
+//!
+//! Replayed or reordered packets within a session are rejected. Sessions
+//! carry an epoch that the sender increments with every rekey and that is
+//! authenticated with each packet. The receiver only moves on to a session
+//! with a higher epoch than the current one, so packets captured from an
+//! earlier session can never be replayed.
+//!
+//! ## Connection Binding
+//!
+//! A reconnect creates new ciphers on both sides, which resets replay
+//! tracking. So that envelopes captured on an earlier connection cannot be
+//! replayed into a new one, each side contributes a random
+//! [`HandshakeNonce`] when the connection starts, and both nonces go into
+//! every key. [`EncryptedTransport::handshake`] exchanges them in
+//! `cconnect.encrypted.hello` packets. An old envelope then fails
+//! authentication instead of opening, and cannot advance the receiver's
+//! epoch either.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use cosmic_ext_connect_core::network::transport::{handshake_nonce, PacketCipher};
+//! use cosmic_ext_connect_core::Packet;
+//! use serde_json::json;
+//!
+//! let secret = [7u8; 32];
+//! let (initiator_nonce, responder_nonce) = (handshake_nonce(), handshake_nonce());
+//! let mut ours = PacketCipher::new(&secret, true, &initiator_nonce, &responder_nonce).unwrap();
+//! let mut theirs = PacketCipher::new(&secret, false, &responder_nonce, &initiator_nonce).unwrap();
+//!
+//! let sealed = ours.seal(&Packet::new("cconnect.ping", json!({}))).unwrap();
+//! assert_eq!(sealed.packet_type, "cconnect.encrypted");
+//! assert_eq!(theirs.open(&sealed).unwrap().packet_type, "cconnect.ping");
+//! ```
+
+use super::r#trait::{Transport, TransportAddress, TransportCapabilities};
+use super::stats::NetworkStats;
+use crate::{Packet, ProtocolError, Result};
+use async_trait::async_trait;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305, NONCE_LEN};
+use ring::hkdf::{KeyType, Salt, HKDF_SHA256};
+use serde_json::json;
+use tracing::debug;
+
+/// Envelope packet type for encrypted packets
+pub const PACKET_TYPE_ENCRYPTED: &str = "cconnect.encrypted";
+
+/// Packet type carrying a side's handshake nonce
+pub const PACKET_TYPE_ENCRYPTED_HELLO: &str = "cconnect.encrypted.hello";
+
+/// Packets sealed under one session key before the sender rekeys
+pub const DEFAULT_REKEY_AFTER: u64 = 1 << 32;
+
+/// Length of the nonce each side contributes to a connection's keys
+pub const HANDSHAKE_NONCE_LEN: usize = 16;
+
+/// Random value one side contributes to a connection's keys
+pub type HandshakeNonce = [u8; HANDSHAKE_NONCE_LEN];
+
+/// Generate our handshake nonce for a new connection
+pub fn handshake_nonce() -> HandshakeNonce {
+    rand::random()
+}
+
+/// Length of the random per-session salt
+const SESSION_ID_LEN: usize = 16;
+
+/// HKDF info for keys sent by the initiating device
+const INITIATOR_INFO: &[u8] = b"cconnect packet encryption initiator";
+
+/// HKDF info for keys sent by the accepting device
+const RESPONDER_INFO: &[u8] = b"cconnect packet encryption responder";
+
+/// ChaCha20-Poly1305 key length for HKDF output
+struct KeyLen;
+
+impl KeyType for KeyLen {
+    fn len(&self) -> usize {
+        CHACHA20_POLY1305.key_len()
+    }
+}
+
+/// Both handshake nonces, initiator's first
+type ConnectionBinding = [u8; 2 * HANDSHAKE_NONCE_LEN];
+
+fn derive_key(
+    secret: &[u8],
+    session: &[u8; SESSION_ID_LEN],
+    info: &[u8],
+    binding: &ConnectionBinding,
+) -> Result<LessSafeKey> {
+    let mut raw = [0u8; 32];
+    Salt::new(HKDF_SHA256, session)
+        .extract(secret)
+        .expand(&[info, binding], KeyLen)
+        .and_then(|okm| okm.fill(&mut raw))
+        .map_err(|_| ProtocolError::Other("Key derivation failed".to_string()))?;
+
+    let key = UnboundKey::new(&CHACHA20_POLY1305, &raw)
+        .map_err(|_| ProtocolError::Other("Invalid encryption key".to_string()))?;
+    Ok(LessSafeKey::new(key))
+}
+
+fn counter_nonce(counter: u64) -> Nonce {
+    let mut nonce = [0u8; NONCE_LEN];
+    nonce[NONCE_LEN - 8..].copy_from_slice(&counter.to_be_bytes());
+    Nonce::assume_unique_for_key(nonce)
+}
+
+/// Sealing state for packets we send
+struct SendState {
+    epoch: u64,
+    session: [u8; SESSION_ID_LEN],
+    key: LessSafeKey,
+    counter: u64,
+}
+
+/// Opening state for packets the peer sends
+struct RecvState {
+    epoch: u64,
+    session: [u8; SESSION_ID_LEN],
+    key: LessSafeKey,
+    /// Lowest counter still accepted in this session
+    next_counter: u64,
+}
+
+/// Encrypts and decrypts packets with a shared pairing secret
+pub struct PacketCipher {
+    /// Shared secret from pairing
+    secret: Vec<u8>,
+
+    /// HKDF info for our sending direction
+    send_info: &'static [u8],
+
+    /// HKDF info for the peer's sending direction
+    recv_info: &'static [u8],
+
+    /// Handshake nonces of this connection
+    binding: ConnectionBinding,
+
+    send: SendState,
+    recv: Option<RecvState>,
+
+    /// Packets per session before rekeying
+    rekey_after: u64,
+}
+
+impl PacketCipher {
+    /// Create a cipher for one connection from a shared secret
+    ///
+    /// Exactly one side must pass `initiator = true`, e.g. the result of
+    /// [`should_initiate_connection`](crate::crypto::should_initiate_connection),
+    /// so the two directions use different keys. `ours` and `theirs` are the
+    /// [`HandshakeNonce`]s the two sides exchanged for this connection (see
+    /// [`EncryptedTransport::handshake`]).
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Other` if key derivation fails
+    pub fn new(
+        secret: &[u8],
+        initiator: bool,
+        ours: &HandshakeNonce,
+        theirs: &HandshakeNonce,
+    ) -> Result<Self> {
+        let (send_info, recv_info) = if initiator {
+            (INITIATOR_INFO, RESPONDER_INFO)
+        } else {
+            (RESPONDER_INFO, INITIATOR_INFO)
+        };
+        let (first, second) = if initiator {
+            (ours, theirs)
+        } else {
+            (theirs, ours)
+        };
+        let mut binding = [0u8; 2 * HANDSHAKE_NONCE_LEN];
+        binding[..HANDSHAKE_NONCE_LEN].copy_from_slice(first);
+        binding[HANDSHAKE_NONCE_LEN..].copy_from_slice(second);
+
+        let session = rand::random();
+        Ok(Self {
+            send: SendState {
+                epoch: 0,
+                session,
+                key: derive_key(secret, &session, send_info, &binding)?,
+                counter: 0,
+            },
+            secret: secret.to_vec(),
+            send_info,
+            recv_info,
+            binding,
+            recv: None,
+            rekey_after: DEFAULT_REKEY_AFTER,
+        })
+    }
+
+    /// Set how many packets are sealed per session before rekeying
+    pub fn with_rekey_after(mut self, rekey_after: u64) -> Self {
+        self.rekey_after = rekey_after.max(1);
+        self
+    }
+
+    /// Encrypt a packet into an envelope packet
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if serialization, rekeying or sealing fails
+    pub fn seal(&mut self, packet: &Packet) -> Result<Packet> {
+        if self.send.counter >= self.rekey_after {
+            let session = rand::random();
+            self.send = SendState {
+                epoch: self.send.epoch + 1,
+                session,
+                key: derive_key(&self.secret, &session, self.send_info, &self.binding)?,
+                counter: 0,
+            };
+            debug!("Rekeyed packet encryption session");
+        }
+
+        let counter = self.send.counter;
+        self.send.counter += 1;
+
+        let mut data = packet.to_bytes()?;
+        self.send
+            .key
+            .seal_in_place_append_tag(
+                counter_nonce(counter),
+                Aad::from(self.send.epoch.to_be_bytes()),
+                &mut data,
+            )
+            .map_err(|_| ProtocolError::Other("Packet encryption failed".to_string()))?;
+
+        Ok(Packet::new(
+            PACKET_TYPE_ENCRYPTED,
+            json!({
+                "epoch": self.send.epoch,
+                "session": hex::encode(self.send.session),
+                "counter": counter,
+                "payload": hex::encode(data),
+            }),
+        ))
+    }
+
+    /// Decrypt an envelope packet
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::InvalidPacket` if the envelope is malformed,
+    /// replayed, from an earlier session, or fails authentication
+    pub fn open(&mut self, envelope: &Packet) -> Result<Packet> {
+        if !envelope.is_type(PACKET_TYPE_ENCRYPTED) {
+            return Err(ProtocolError::InvalidPacket(format!(
+                "Expected encrypted packet, got '{}'",
+                envelope.packet_type
+            )));
+        }
+
+        let field = |name: &str| {
+            envelope
+                .get_body_field::<String>(name)
+                .and_then(|s| hex::decode(s).ok())
+                .ok_or_else(|| {
+                    ProtocolError::InvalidPacket(format!("Missing or invalid '{}'", name))
+                })
+        };
+        let session: [u8; SESSION_ID_LEN] = field("session")?
+            .try_into()
+            .map_err(|_| ProtocolError::InvalidPacket("Invalid session id".to_string()))?;
+        let mut data = field("payload")?;
+        let counter = envelope
+            .get_body_field::<u64>("counter")
+            .ok_or_else(|| ProtocolError::InvalidPacket("Missing 'counter'".to_string()))?;
+        let epoch = envelope
+            .get_body_field::<u64>("epoch")
+            .ok_or_else(|| ProtocolError::InvalidPacket("Missing 'epoch'".to_string()))?;
+
+        // Only a newer session gets a new key; commit it after authentication
+        let mut new_key = None;
+        let key = match &self.recv {
+            Some(recv) if recv.epoch == epoch && recv.session == session => {
+                if counter < recv.next_counter {
+                    return Err(ProtocolError::InvalidPacket(format!(
+                        "Replayed encrypted packet (counter {})",
+                        counter
+                    )));
+                }
+                &recv.key
+            }
+            Some(recv) if epoch <= recv.epoch => {
+                return Err(ProtocolError::InvalidPacket(format!(
+                    "Encrypted packet from stale session (epoch {}, current {})",
+                    epoch, recv.epoch
+                )));
+            }
+            _ => &*new_key.insert(derive_key(
+                &self.secret,
+                &session,
+                self.recv_info,
+                &self.binding,
+            )?),
+        };
+
+        let plaintext = key
+            .open_in_place(
+                counter_nonce(counter),
+                Aad::from(epoch.to_be_bytes()),
+                &mut data,
+            )
+            .map_err(|_| {
+                ProtocolError::InvalidPacket("Encrypted packet failed authentication".to_string())
+            })?;
+        let packet = Packet::from_bytes(plaintext)?;
+
+        match new_key {
+            Some(key) => {
+                debug!("Peer started a new encryption session");
+                self.recv = Some(RecvState {
+                    epoch,
+                    session,
+                    key,
+                    next_counter: counter + 1,
+                });
+            }
+            None => {
+                if let Some(recv) = self.recv.as_mut() {
+                    recv.next_counter = counter + 1;
+                }
+            }
+        }
+
+        Ok(packet)
+    }
+}
+
+impl std::fmt::Debug for PacketCipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Never print key material
+        f.debug_struct("PacketCipher")
+            .field("send_counter", &self.send.counter)
+            .field("rekey_after", &self.rekey_after)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Transport decorator that encrypts every packet with a [`PacketCipher`]
+///
+/// Any transport can opt in by wrapping it with
+/// [`handshake`](Self::handshake); both peers must use the same shared
+/// secret and opposite `initiator` roles.
+/// Envelopes are larger than the packets they carry (hex-encoded
+/// ciphertext), so oversized packets are rejected by the inner transport.
+#[derive(Debug)]
+pub struct EncryptedTransport<T> {
+    /// Wrapped transport
+    inner: T,
+
+    /// Packet cipher
+    cipher: PacketCipher,
+}
+
+impl<T: Transport> EncryptedTransport<T> {
+    /// Wrap a transport with packet encryption
+    ///
+    /// `cipher` must have been created for this connection's handshake
+    /// nonces; [`handshake`](Self::handshake) does both.
+    pub fn new(inner: T, cipher: PacketCipher) -> Self {
+        Self { inner, cipher }
+    }
+
+    /// Exchange handshake nonces with the peer, then wrap the transport
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the exchange fails, and
+    /// `ProtocolError::InvalidPacket` if the peer's first packet is not a
+    /// valid `cconnect.encrypted.hello`.
+    pub async fn handshake(mut inner: T, secret: &[u8], initiator: bool) -> Result<Self> {
+        let ours = handshake_nonce();
+        let hello = Packet::new(
+            PACKET_TYPE_ENCRYPTED_HELLO,
+            json!({ "nonce": hex::encode(ours) }),
+        );
+        inner.send_packet(&hello).await?;
+
+        let reply = inner.receive_packet().await?;
+        if !reply.is_type(PACKET_TYPE_ENCRYPTED_HELLO) {
+            return Err(ProtocolError::InvalidPacket(format!(
+                "Expected encryption handshake, got '{}'",
+                reply.packet_type
+            )));
+        }
+        let theirs: HandshakeNonce = reply
+            .get_body_field::<String>("nonce")
+            .and_then(|nonce| hex::decode(nonce).ok())
+            .and_then(|nonce| nonce.try_into().ok())
+            .ok_or_else(|| ProtocolError::InvalidPacket("Invalid handshake nonce".to_string()))?;
+
+        let cipher = PacketCipher::new(secret, initiator, &ours, &theirs)?;
+        Ok(Self::new(inner, cipher))
+    }
+
+    /// Get a reference to the wrapped transport
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Consume the wrapper and return the wrapped transport
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+#[async_trait]
+impl<T: Transport> Transport for EncryptedTransport<T> {
+    fn capabilities(&self) -> TransportCapabilities {
+        self.inner.capabilities()
+    }
+
+    fn remote_address(&self) -> TransportAddress {
+        self.inner.remote_address()
+    }
+
+    async fn send_packet(&mut self, packet: &Packet) -> Result<()> {
+        let envelope = self.cipher.seal(packet)?;
+        self.inner.send_packet(&envelope).await
+    }
+
+    async fn send_batch(&mut self, packets: &[Packet]) -> Result<()> {
+        let envelopes = packets
+            .iter()
+            .map(|packet| self.cipher.seal(packet))
+            .collect::<Result<Vec<_>>>()?;
+        self.inner.send_batch(&envelopes).await
+    }
+
+    async fn receive_packet(&mut self) -> Result<Packet> {
+        let envelope = self.inner.receive_packet().await?;
+        self.cipher.open(&envelope)
+    }
+
+    async fn close(self: Box<Self>) -> Result<()> {
+        Box::new(self.inner).close().await
+    }
+
+    fn is_connected(&self) -> bool {
+        self.inner.is_connected()
+    }
+
+    fn is_degraded(&self) -> bool {
+        self.inner.is_degraded()
+    }
+
+    fn network_stats(&self) -> NetworkStats {
+        self.inner.network_stats()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &[u8] = b"shared secret from pairing";
+
+    fn pair() -> (PacketCipher, PacketCipher) {
+        let (initiator, responder) = (handshake_nonce(), handshake_nonce());
+        (
+            PacketCipher::new(SECRET, true, &initiator, &responder).unwrap(),
+            PacketCipher::new(SECRET, false, &responder, &initiator).unwrap(),
+        )
+    }
+
+    fn ping(index: u64) -> Packet {
+        Packet::new(
+            "cconnect.ping",
+            json!({ "message": "hello", "index": index }),
+        )
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let (mut ours, mut theirs) = pair();
+
+        let sealed = ours.seal(&ping(0)).unwrap();
+        assert!(!sealed.body["payload"]
+            .as_str()
+            .unwrap()
+            .contains(&hex::encode("hello")));
+
+        let opened = theirs.open(&sealed).unwrap();
+        assert_eq!(opened.packet_type, "cconnect.ping");
+        assert_eq!(opened.body["message"], "hello");
+
+        // The reverse direction uses its own key
+        let reply = theirs.seal(&ping(1)).unwrap();
+        assert_eq!(ours.open(&reply).unwrap().body["index"], 1);
+    }
+
+    #[test]
+    fn test_tampered_ciphertext_fails_authentication() {
+        let (mut ours, mut theirs) = pair();
+        let mut sealed = ours.seal(&ping(0)).unwrap();
+
+        let mut payload = hex::decode(sealed.body["payload"].as_str().unwrap()).unwrap();
+        payload[0] ^= 0x01;
+        sealed.body["payload"] = json!(hex::encode(payload));
+
+        let err = theirs.open(&sealed).unwrap_err();
+        assert!(err.to_string().contains("authentication"));
+
+        // The wrong secret fails the same way
+        let nonce = handshake_nonce();
+        let mut stranger = PacketCipher::new(b"other secret", false, &nonce, &nonce).unwrap();
+        assert!(stranger.open(&ours.seal(&ping(1)).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_replay_rejected_and_rekey() {
+        let (ours, mut theirs) = pair();
+        let mut ours = ours.with_rekey_after(2);
+
+        let first = ours.seal(&ping(0)).unwrap();
+        theirs.open(&first).unwrap();
+        assert!(theirs.open(&first).is_err());
+
+        let second = ours.seal(&ping(1)).unwrap();
+        let third = ours.seal(&ping(2)).unwrap();
+
+        // The third packet starts a new session with its counter reset
+        assert_ne!(second.body["session"], third.body["session"]);
+        assert_eq!(third.body["counter"], 0);
+
+        theirs.open(&second).unwrap();
+        assert_eq!(theirs.open(&third).unwrap().body["index"], 2);
+    }
+
+    #[test]
+    fn test_old_session_replay_rejected() {
+        let (ours, mut theirs) = pair();
+        let mut ours = ours.with_rekey_after(2);
+
+        let old = [ours.seal(&ping(0)).unwrap(), ours.seal(&ping(1)).unwrap()];
+        theirs.open(&old[0]).unwrap();
+        let new = ours.seal(&ping(2)).unwrap();
+        assert_eq!(new.body["epoch"], 1);
+        theirs.open(&new).unwrap();
+
+        // Packets from the earlier session no longer open, even unseen ones
+        for envelope in &old {
+            let err = theirs.open(envelope).unwrap_err();
+            assert!(err.to_string().contains("stale session"), "{}", err);
+        }
+
+        // Claiming a newer epoch for an old session fails authentication
+        let mut forged = old[1].clone();
+        forged.body["epoch"] = json!(5);
+        assert!(theirs
+            .open(&forged)
+            .unwrap_err()
+            .to_string()
+            .contains("authentication"));
+
+        // The current session is unaffected
+        assert_eq!(
+            theirs.open(&ours.seal(&ping(3)).unwrap()).unwrap().body["index"],
+            3
+        );
+    }
+
+    #[test]
+    fn test_replay_across_connections_rejected() {
+        // An earlier connection with the same pairing secret, rekeyed twice
+        let (earlier, _) = pair();
+        let mut earlier = earlier.with_rekey_after(1);
+        let captured: Vec<Packet> = (0..3).map(|i| earlier.seal(&ping(i)).unwrap()).collect();
+        assert_eq!(captured[2].body["epoch"], 2);
+
+        // Nothing captured opens on the new connection
+        let (mut ours, mut theirs) = pair();
+        for envelope in &captured {
+            let err = theirs.open(envelope).unwrap_err();
+            assert!(err.to_string().contains("authentication"), "{}", err);
+        }
+
+        // A replayed high-epoch session does not lock out epoch 0
+        assert_eq!(
+            theirs.open(&ours.seal(&ping(7)).unwrap()).unwrap().body["index"],
+            7
+        );
+    }
+
+    /// In-memory transport delivering packets to its paired end
+    #[derive(Debug)]
+    struct ChannelTransport {
+        tx: tokio::sync::mpsc::UnboundedSender<Packet>,
+        rx: tokio::sync::mpsc::UnboundedReceiver<Packet>,
+    }
+
+    impl ChannelTransport {
+        fn pair() -> (Self, Self) {
+            let (near_tx, far_rx) = tokio::sync::mpsc::unbounded_channel();
+            let (far_tx, near_rx) = tokio::sync::mpsc::unbounded_channel();
+            (
+                Self {
+                    tx: near_tx,
+                    rx: near_rx,
+                },
+                Self {
+                    tx: far_tx,
+                    rx: far_rx,
+                },
+            )
+        }
+    }
+
+    #[async_trait]
+    impl Transport for ChannelTransport {
+        fn capabilities(&self) -> TransportCapabilities {
+            TransportCapabilities {
+                max_packet_size: 1024,
+                reliable: true,
+                connection_oriented: true,
+                latency: super::super::LatencyCategory::Low,
+            }
+        }
+
+        fn remote_address(&self) -> TransportAddress {
+            TransportAddress::Tcp("127.0.0.1:1816".parse().unwrap())
+        }
+
+        async fn send_packet(&mut self, packet: &Packet) -> Result<()> {
+            self.tx
+                .send(packet.clone())
+                .map_err(|_| ProtocolError::Other("closed".to_string()))
+        }
+
+        async fn receive_packet(&mut self) -> Result<Packet> {
+            self.rx
+                .recv()
+                .await
+                .ok_or_else(|| ProtocolError::Other("closed".to_string()))
+        }
+
+        async fn close(self: Box<Self>) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_handshake_over_transport() {
+        let (near, far) = ChannelTransport::pair();
+        let (near, far) = tokio::join!(
+            EncryptedTransport::handshake(near, SECRET, true),
+            EncryptedTransport::handshake(far, SECRET, false)
+        );
+        let (mut near, mut far) = (near.unwrap(), far.unwrap());
+
+        near.send_packet(&ping(0)).await.unwrap();
+        assert_eq!(far.receive_packet().await.unwrap().body["index"], 0);
+        far.send_packet(&ping(1)).await.unwrap();
+        assert_eq!(near.receive_packet().await.unwrap().body["index"], 1);
+    }
+}
@@ -33,11 +33,53 @@
 //! }
 //! ```
 
+mod connection_log;
+mod encrypted;
+mod mux;
+mod quality;
+mod receive_buffer;
+mod reconnect;
+mod send_queue;
+mod stats;
 mod r#trait;
 
 pub use r#trait::{
-    LatencyCategory, Transport, TransportAddress, TransportCapabilities, TransportFactory,
-    TransportPreference, TransportType,
+    encode_batch, send_sequential, AddressParseError, LatencyCategory, TimeoutTransport,
+    Transport, TransportAddress, TransportCapabilities, TransportFactory, TransportPolicy,
+    TransportPreference, TransportType, DEFAULT_WRITE_TIMEOUT,
+};
+
+pub use encrypted::{
+    handshake_nonce, EncryptedTransport, HandshakeNonce, PacketCipher, DEFAULT_REKEY_AFTER,
+    HANDSHAKE_NONCE_LEN, PACKET_TYPE_ENCRYPTED, PACKET_TYPE_ENCRYPTED_HELLO,
+};
+
+pub use connection_log::{
+    ConnectionEvent, ConnectionLog, TimestampedEvent, DEFAULT_CONNECTION_LOG_CAPACITY,
+};
+
+pub use send_queue::SendQueue;
+
+pub use mux::{
+    advertise_mux, mux_negotiated, Channel, MultiplexedTransport, MuxConfig, MuxSender,
+    DEFAULT_MUX_CHUNK_SIZE, MUX_CAPABILITY, MUX_HEADER_LEN, MUX_MAX_PACKET_SIZE,
+};
+
+pub use receive_buffer::{
+    spawn_receiver, PacketReceiver, PacketSource, ReceiveBufferConfig,
+    DEFAULT_RECEIVE_BUFFER_CAPACITY,
+};
+
+pub use quality::{
+    ConnectionQuality, QualityChange, QualityLimits, QualityMonitor, QualityThresholds,
+    DEFAULT_QUALITY_HYSTERESIS,
+};
+
+pub use stats::{NetworkStats, NetworkStatsEstimator, JITTER_BETA, LOSS_ALPHA, RTT_ALPHA};
+
+pub use reconnect::{
+    AddressResolver, ReconnectPolicy, ReconnectingTransport, DEFAULT_MAX_RECONNECT_BACKOFF,
+    DEFAULT_RECONNECT_ATTEMPTS, DEFAULT_RECONNECT_BACKOFF,
 };
 
 /// KDE Connect Bluetooth service UUID
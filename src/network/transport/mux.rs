@@ -0,0 +1,685 @@
+//! Channel Multiplexing
+//!
+//! A connection carries both bulk media (camera frames, audio) and small
+//! control packets. Written back to back on one stream, a large frame delays
+//! everything queued behind it, so a stop command waits for the frame to
+//! finish. [`MultiplexedTransport`] splits the stream into logical
+//! [`Channel`]s: every packet is cut into chunks, each with a small header
+//! naming its channel, and the writer always sends the next chunk of the
+//! highest-priority channel with data queued. A control packet queued while
+//! a media packet is being written goes out after the current chunk, not
+//! after the whole media packet.
+//!
+//! ## Framing
+//!
+//! Each chunk starts with a 4 byte header:
+//!
+//! | Byte | Field                                     |
+//! |------|-------------------------------------------|
+//! | 0    | Channel ID (0 = control, 1 = bulk)        |
+//! | 1    | Flags (bit 0 = last chunk of the packet)  |
+//! | 2-3  | Chunk length, big-endian                  |
+//!
+//! The receiver reassembles chunks per channel and parses a packet once its
+//! last chunk arrives.
+//!
+//! ## Negotiation
+//!
+//! Both ends must use the multiplexed framing, so it is only used with peers
+//! that advertise [`MUX_CAPABILITY`] in both capability lists of their
+//! identity. [`advertise_mux`] adds it to ours, and
+//! [`MultiplexedTransport::new`] refuses a connection whose negotiated
+//! capabilities lack it. Check [`mux_negotiated`] first to fall back to the
+//! plain framing instead.
+//!
+//! ## Channels
+//!
+//! Packets use the control channel unless their type is registered with
+//! [`MuxConfig::with_bulk`]. Channels are sent in priority order, so they
+//! map onto the same control-before-media split as the receiver's
+//! drop-oldest packet types (see [`ReceiveBufferConfig`](super::ReceiveBufferConfig)).
+//!
+//! ```rust,no_run
+//! use cosmic_ext_connect_core::network::transport::{
+//!     advertise_mux, MultiplexedTransport, MuxConfig, Transport, TransportAddress,
+//! };
+//! use cosmic_ext_connect_core::protocol::Identity;
+//! use cosmic_ext_connect_core::Packet;
+//! use serde_json::json;
+//! use tokio::net::TcpStream;
+//!
+//! # async fn example(peer: Identity) -> cosmic_ext_connect_core::Result<()> {
+//! let ours = advertise_mux(Identity::new(vec![], vec![]));
+//! let negotiated = ours.negotiate(&peer);
+//!
+//! let stream = TcpStream::connect("192.168.1.100:1716").await?;
+//! let address = TransportAddress::Tcp(stream.peer_addr()?);
+//! let config = MuxConfig::new().with_bulk(["cconnect.camera.frame"]);
+//! let mut transport = MultiplexedTransport::new(stream, address, config, &negotiated)?;
+//!
+//! // Concurrent sends from other tasks go through a sender handle
+//! let sender = transport.sender();
+//! tokio::spawn(async move {
+//!     sender.send(&Packet::new("cconnect.camera.stop", json!({}))).await
+//! });
+//!
+//! let packet = transport.receive_packet().await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use super::r#trait::{LatencyCategory, Transport, TransportAddress, TransportCapabilities};
+use super::receive_buffer::DEFAULT_RECEIVE_BUFFER_CAPACITY;
+use crate::protocol::{Identity, NegotiatedCapabilities, DEFAULT_MAX_PACKET_SIZE};
+use crate::{Packet, ProtocolError, Result};
+use async_trait::async_trait;
+use std::collections::{HashSet, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::{mpsc, oneshot, Notify};
+use tokio::task::JoinHandle;
+use tracing::{debug, trace};
+
+/// Length of the header in front of every chunk
+pub const MUX_HEADER_LEN: usize = 4;
+
+/// Default maximum chunk length in bytes
+pub const DEFAULT_MUX_CHUNK_SIZE: usize = 16 * 1024;
+
+/// Largest packet a multiplexed transport sends or reassembles
+///
+/// The same limit as a plain TLS connection, so switching framing does not
+/// change which packets get through.
+pub const MUX_MAX_PACKET_SIZE: usize = DEFAULT_MAX_PACKET_SIZE;
+
+/// Identity capability advertising support for the multiplexed framing
+pub const MUX_CAPABILITY: &str = "cconnect.mux";
+
+/// Header flag marking the last chunk of a packet
+const FLAG_END: u8 = 0x01;
+
+/// Notified once a packet's last chunk is written, or writing fails
+type Completion = oneshot::Sender<Result<()>>;
+
+/// Advertise the multiplexed framing in an identity
+///
+/// Adds [`MUX_CAPABILITY`] to both capability lists.
+pub fn advertise_mux(mut identity: Identity) -> Identity {
+    for list in [
+        &mut identity.incoming_capabilities,
+        &mut identity.outgoing_capabilities,
+    ] {
+        if !list.iter().any(|capability| capability == MUX_CAPABILITY) {
+            list.push(MUX_CAPABILITY.to_string());
+        }
+    }
+    identity
+}
+
+/// Check whether both ends advertised the multiplexed framing
+pub fn mux_negotiated(negotiated: &NegotiatedCapabilities) -> bool {
+    negotiated.is_active(MUX_CAPABILITY)
+}
+
+/// Logical channel of a multiplexed connection, from highest priority
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Channel {
+    /// Small, latency-sensitive packets (commands, status)
+    Control,
+    /// Large media packets
+    Bulk,
+}
+
+impl Channel {
+    /// All channels in priority order
+    pub const ALL: [Channel; 2] = [Channel::Control, Channel::Bulk];
+
+    /// Get the channel's ID in chunk headers
+    pub fn id(&self) -> u8 {
+        match self {
+            Self::Control => 0,
+            Self::Bulk => 1,
+        }
+    }
+
+    /// Look up a channel by its ID in chunk headers
+    pub fn from_id(id: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|channel| channel.id() == id)
+    }
+
+    fn index(&self) -> usize {
+        self.id() as usize
+    }
+}
+
+/// Chunk size and channel assignment of a [`MultiplexedTransport`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MuxConfig {
+    /// Maximum chunk length in bytes (1 to 65535)
+    chunk_size: usize,
+
+    /// Packet types sent on the bulk channel
+    bulk: HashSet<String>,
+}
+
+impl Default for MuxConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MuxConfig {
+    /// Create a config with the default chunk size where every packet type
+    /// uses the control channel
+    pub fn new() -> Self {
+        Self {
+            chunk_size: DEFAULT_MUX_CHUNK_SIZE,
+            bulk: HashSet::new(),
+        }
+    }
+
+    /// Set the maximum chunk length, clamped to 1 to 65535 bytes
+    ///
+    /// Smaller chunks let control packets through sooner at the cost of
+    /// more headers.
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.clamp(1, u16::MAX as usize);
+        self
+    }
+
+    /// Send the given packet types on the bulk channel
+    pub fn with_bulk<I, S>(mut self, packet_types: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.bulk.extend(packet_types.into_iter().map(Into::into));
+        self
+    }
+
+    /// Get the maximum chunk length in bytes
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// Get the channel a packet type is sent on
+    pub fn channel_for(&self, packet_type: &str) -> Channel {
+        if self.bulk.contains(packet_type) {
+            Channel::Bulk
+        } else {
+            Channel::Control
+        }
+    }
+}
+
+/// Packet being written, chunk by chunk
+#[derive(Debug)]
+struct Outgoing {
+    /// Serialized packet
+    bytes: Vec<u8>,
+
+    /// Bytes already written
+    offset: usize,
+
+    /// Notified once the last chunk is written
+    done: Completion,
+}
+
+/// Write queues shared by the senders and the writer task
+#[derive(Debug, Default)]
+struct WriterState {
+    /// Queued packets per channel, indexed by channel ID
+    lanes: [VecDeque<Outgoing>; 2],
+
+    /// Why writing stopped, once it has
+    error: Option<String>,
+
+    /// Whether the transport is closing; queued packets are still written
+    closing: bool,
+}
+
+/// State shared by the senders and the writer task
+#[derive(Debug)]
+struct Shared {
+    config: MuxConfig,
+    state: Mutex<WriterState>,
+    /// Signalled when a packet is queued or the transport closes
+    pending: Notify,
+}
+
+impl Shared {
+    fn state(&self) -> MutexGuard<'_, WriterState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Take the next chunk of the highest-priority channel with data queued
+    ///
+    /// Returns the framed chunk, and the packet's completion sender if it is
+    /// the packet's last chunk.
+    fn next_chunk(&self) -> Option<(Vec<u8>, Option<Completion>)> {
+        let mut state = self.state();
+        let (channel, lane) = Channel::ALL
+            .into_iter()
+            .zip(state.lanes.iter_mut())
+            .find(|(_, lane)| !lane.is_empty())?;
+        let outgoing = lane.front_mut()?;
+
+        let end = (outgoing.offset + self.config.chunk_size).min(outgoing.bytes.len());
+        let chunk = &outgoing.bytes[outgoing.offset..end];
+        let last = end == outgoing.bytes.len();
+
+        let mut frame = Vec::with_capacity(MUX_HEADER_LEN + chunk.len());
+        frame.push(channel.id());
+        frame.push(if last { FLAG_END } else { 0 });
+        frame.extend_from_slice(&(chunk.len() as u16).to_be_bytes());
+        frame.extend_from_slice(chunk);
+        outgoing.offset = end;
+
+        let done = if last {
+            lane.pop_front().map(|outgoing| outgoing.done)
+        } else {
+            None
+        };
+        Some((frame, done))
+    }
+
+    /// Stop writing, failing every queued packet
+    fn fail(&self, reason: String) {
+        let mut state = self.state();
+        for outgoing in state.lanes.iter_mut().flat_map(|lane| lane.drain(..)) {
+            let _ = outgoing
+                .done
+                .send(Err(ProtocolError::Connection(reason.clone())));
+        }
+        state.error = Some(reason);
+    }
+}
+
+/// Cloneable handle for sending on a [`MultiplexedTransport`]
+///
+/// Sends from several tasks interleave by channel priority; each call
+/// returns once its packet is written.
+#[derive(Debug, Clone)]
+pub struct MuxSender {
+    shared: Arc<Shared>,
+}
+
+impl MuxSender {
+    /// Send a packet on the channel its type is assigned to
+    ///
+    /// # Errors
+    ///
+    /// See [`send_on`](Self::send_on).
+    pub async fn send(&self, packet: &Packet) -> Result<()> {
+        let channel = self.shared.config.channel_for(&packet.packet_type);
+        self.send_on(channel, packet).await
+    }
+
+    /// Send a packet on a specific channel
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::InvalidPacket` if the packet is larger than
+    /// [`MUX_MAX_PACKET_SIZE`], and `ProtocolError::Connection` if the
+    /// connection failed or closed before the packet was written.
+    pub async fn send_on(&self, channel: Channel, packet: &Packet) -> Result<()> {
+        let bytes = packet.to_bytes()?;
+        if bytes.len() > MUX_MAX_PACKET_SIZE {
+            return Err(ProtocolError::InvalidPacket(format!(
+                "Packet '{}' too large: {} bytes (max {})",
+                packet.packet_type,
+                bytes.len(),
+                MUX_MAX_PACKET_SIZE
+            )));
+        }
+
+        let (done, written) = oneshot::channel();
+        {
+            let mut state = self.shared.state();
+            if let Some(reason) = &state.error {
+                return Err(ProtocolError::Connection(reason.clone()));
+            }
+            if state.closing {
+                return Err(ProtocolError::Connection("Transport closed".to_string()));
+            }
+            trace!("Queueing '{}' on {:?} channel", packet.packet_type, channel);
+            state.lanes[channel.index()].push_back(Outgoing {
+                bytes,
+                offset: 0,
+                done,
+            });
+        }
+        self.shared.pending.notify_one();
+
+        written
+            .await
+            .unwrap_or_else(|_| Err(ProtocolError::Connection("Writer stopped".to_string())))
+    }
+}
+
+/// Transport multiplexing prioritized channels over one byte stream
+///
+/// Created with [`MultiplexedTransport::new`], which spawns a reader and a
+/// writer task. Dropping the transport stops both.
+#[derive(Debug)]
+pub struct MultiplexedTransport {
+    sender: MuxSender,
+    incoming: mpsc::Receiver<Result<Packet>>,
+    remote_address: TransportAddress,
+    reader: JoinHandle<()>,
+    writer: JoinHandle<()>,
+}
+
+impl MultiplexedTransport {
+    /// Multiplex channels over a connected stream
+    ///
+    /// Must be called within a Tokio runtime.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Connection` if `negotiated` does not include
+    /// [`MUX_CAPABILITY`].
+    pub fn new<S>(
+        stream: S,
+        remote_address: TransportAddress,
+        config: MuxConfig,
+        negotiated: &NegotiatedCapabilities,
+    ) -> Result<Self>
+    where
+        S: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        if !mux_negotiated(negotiated) {
+            return Err(ProtocolError::Connection(format!(
+                "Peer at {} did not advertise {}",
+                remote_address, MUX_CAPABILITY
+            )));
+        }
+
+        let (read_half, write_half) = tokio::io::split(stream);
+        let shared = Arc::new(Shared {
+            config,
+            state: Mutex::new(WriterState::default()),
+            pending: Notify::new(),
+        });
+
+        let (incoming_tx, incoming) = mpsc::channel(DEFAULT_RECEIVE_BUFFER_CAPACITY);
+        let reader = tokio::spawn(read_loop(read_half, incoming_tx));
+        let writer = tokio::spawn(write_loop(Arc::clone(&shared), write_half));
+
+        Ok(Self {
+            sender: MuxSender { shared },
+            incoming,
+            remote_address,
+            reader,
+            writer,
+        })
+    }
+
+    /// Get a handle for sending from other tasks
+    pub fn sender(&self) -> MuxSender {
+        self.sender.clone()
+    }
+
+    /// Get the chunk size and channel assignment
+    pub fn config(&self) -> &MuxConfig {
+        &self.sender.shared.config
+    }
+}
+
+impl Drop for MultiplexedTransport {
+    fn drop(&mut self) {
+        self.reader.abort();
+        self.writer.abort();
+    }
+}
+
+/// Write queued chunks until the transport closes or a write fails
+async fn write_loop<W: AsyncWrite + Unpin>(shared: Arc<Shared>, mut writer: W) {
+    loop {
+        let pending = shared.pending.notified();
+        let Some((frame, done)) = shared.next_chunk() else {
+            if shared.state().closing {
+                break;
+            }
+            pending.await;
+            continue;
+        };
+
+        let written = async {
+            writer.write_all(&frame).await?;
+            writer.flush().await
+        };
+        match written.await {
+            Ok(()) => {
+                if let Some(done) = done {
+                    let _ = done.send(Ok(()));
+                }
+            }
+            Err(e) => {
+                debug!("Multiplexed write failed: {}", e);
+                if let Some(done) = done {
+                    let _ = done.send(Err(ProtocolError::Connection(e.to_string())));
+                }
+                shared.fail(e.to_string());
+                return;
+            }
+        }
+    }
+
+    let _ = writer.shutdown().await;
+    shared.fail("Transport closed".to_string());
+}
+
+/// Reassemble packets until the stream ends or the transport is dropped
+async fn read_loop<R: AsyncRead + Unpin>(mut reader: R, incoming: mpsc::Sender<Result<Packet>>) {
+    let mut partial: [Vec<u8>; 2] = Default::default();
+    loop {
+        match read_chunk(&mut reader, &mut partial).await {
+            Ok(Some(packet)) => {
+                if incoming.send(Ok(packet)).await.is_err() {
+                    break;
+                }
+            }
+            Ok(None) => {}
+            Err(e) => {
+                debug!("Multiplexed read ended: {}", e);
+                let _ = incoming.send(Err(e)).await;
+                break;
+            }
+        }
+    }
+}
+
+/// Read one chunk, returning the packet it completes, if any
+async fn read_chunk<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    partial: &mut [Vec<u8>; 2],
+) -> Result<Option<Packet>> {
+    let mut header = [0u8; MUX_HEADER_LEN];
+    reader.read_exact(&mut header).await?;
+
+    let channel = Channel::from_id(header[0]).ok_or_else(|| {
+        ProtocolError::InvalidPacket(format!("Unknown multiplexed channel {}", header[0]))
+    })?;
+    let len = u16::from_be_bytes([header[2], header[3]]) as usize;
+
+    let buffer = &mut partial[channel.index()];
+    let start = buffer.len();
+    if start + len > MUX_MAX_PACKET_SIZE {
+        return Err(ProtocolError::InvalidPacket(format!(
+            "Multiplexed packet on {:?} channel exceeds {} bytes",
+            channel, MUX_MAX_PACKET_SIZE
+        )));
+    }
+    buffer.resize(start + len, 0);
+    reader.read_exact(&mut buffer[start..]).await?;
+
+    if header[1] & FLAG_END == 0 {
+        return Ok(None);
+    }
+    Packet::from_bytes(&std::mem::take(buffer)).map(Some)
+}
+
+#[async_trait]
+impl Transport for MultiplexedTransport {
+    fn capabilities(&self) -> TransportCapabilities {
+        TransportCapabilities {
+            max_packet_size: MUX_MAX_PACKET_SIZE,
+            reliable: true,
+            connection_oriented: true,
+            latency: LatencyCategory::Low,
+        }
+    }
+
+    fn remote_address(&self) -> TransportAddress {
+        self.remote_address.clone()
+    }
+
+    async fn send_packet(&mut self, packet: &Packet) -> Result<()> {
+        self.sender.send(packet).await
+    }
+
+    async fn receive_packet(&mut self) -> Result<Packet> {
+        self.incoming
+            .recv()
+            .await
+            .unwrap_or_else(|| Err(ProtocolError::Connection("Receive loop ended".to_string())))
+    }
+
+    /// Write the packets already queued, then shut the stream down
+    async fn close(mut self: Box<Self>) -> Result<()> {
+        self.sender.shared.state().closing = true;
+        self.sender.shared.pending.notify_one();
+        (&mut self.writer)
+            .await
+            .map_err(|e| ProtocolError::Connection(format!("Writer task failed: {}", e)))
+    }
+
+    fn is_connected(&self) -> bool {
+        let state = self.sender.shared.state();
+        state.error.is_none() && !state.closing
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::time::Duration;
+    use tokio::io::duplex;
+
+    const FRAME: &str = "cconnect.camera.frame";
+    const STOP: &str = "cconnect.camera.stop";
+
+    fn address() -> TransportAddress {
+        TransportAddress::Tcp("127.0.0.1:1716".parse().unwrap())
+    }
+
+    fn negotiated() -> NegotiatedCapabilities {
+        let identity = advertise_mux(Identity::new(vec![], vec![]));
+        identity.negotiate(&identity)
+    }
+
+    /// Let the writer run until it blocks
+    async fn settle() {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    #[tokio::test]
+    async fn test_control_not_delayed_behind_media() {
+        // The pipe holds less than one chunk, so writes block until read
+        let (local, remote) = duplex(4 * 1024);
+        let config = MuxConfig::new().with_bulk([FRAME]);
+        let transport =
+            MultiplexedTransport::new(local, address(), config.clone(), &negotiated()).unwrap();
+        let sender = transport.sender();
+
+        let frame = Packet::new(FRAME, json!({ "data": "x".repeat(512 * 1024) }));
+        let media = tokio::spawn({
+            let sender = sender.clone();
+            async move { sender.send(&frame).await }
+        });
+
+        // The peer is not reading yet, so the media packet stalls mid-write
+        settle().await;
+        assert!(!media.is_finished());
+
+        let control = tokio::spawn({
+            let sender = sender.clone();
+            async move { sender.send(&Packet::new(STOP, json!({}))).await }
+        });
+        settle().await;
+
+        // The control packet overtakes the rest of the media packet
+        let mut peer = MultiplexedTransport::new(remote, address(), config, &negotiated()).unwrap();
+        assert_eq!(peer.receive_packet().await.unwrap().packet_type, STOP);
+        control.await.unwrap().unwrap();
+
+        let received = peer.receive_packet().await.unwrap();
+        assert_eq!(received.packet_type, FRAME);
+        assert_eq!(received.body["data"].as_str().unwrap().len(), 512 * 1024);
+        media.await.unwrap().unwrap();
+
+        // Closing writes what is queued, then fails later sends
+        Box::new(transport).close().await.unwrap();
+        let stopped = sender.send(&Packet::new(STOP, json!({}))).await;
+        assert!(matches!(stopped, Err(ProtocolError::Connection(_))));
+        assert!(matches!(
+            peer.receive_packet().await,
+            Err(ProtocolError::Io(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_chunks_reassembled_per_channel() {
+        let (mut raw, remote) = duplex(64 * 1024);
+        let mut transport =
+            MultiplexedTransport::new(remote, address(), MuxConfig::new(), &negotiated()).unwrap();
+
+        let frame = |channel: Channel, flags: u8, data: &[u8]| {
+            let mut frame = vec![channel.id(), flags];
+            frame.extend_from_slice(&(data.len() as u16).to_be_bytes());
+            frame.extend_from_slice(data);
+            frame
+        };
+        let media = Packet::new(FRAME, json!({ "n": 1 })).to_bytes().unwrap();
+        let stop = Packet::new(STOP, json!({})).to_bytes().unwrap();
+        let (head, tail) = media.split_at(10);
+
+        // A bulk packet split around a control packet
+        raw.write_all(&frame(Channel::Bulk, 0, head)).await.unwrap();
+        raw.write_all(&frame(Channel::Control, FLAG_END, &stop))
+            .await
+            .unwrap();
+        raw.write_all(&frame(Channel::Bulk, FLAG_END, tail))
+            .await
+            .unwrap();
+        assert_eq!(transport.receive_packet().await.unwrap().packet_type, STOP);
+        let received = transport.receive_packet().await.unwrap();
+        assert_eq!(received.packet_type, FRAME);
+        assert_eq!(received.body["n"], 1);
+
+        // Unknown channel IDs end the stream
+        raw.write_all(&[7, FLAG_END, 0, 0]).await.unwrap();
+        let unknown = transport.receive_packet().await;
+        assert!(matches!(unknown, Err(ProtocolError::InvalidPacket(_))));
+        assert!(matches!(
+            transport.receive_packet().await,
+            Err(ProtocolError::Connection(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_requires_negotiated_capability() {
+        let ours = advertise_mux(Identity::new(vec![], vec![]));
+        assert_eq!(advertise_mux(ours.clone()), ours);
+        assert!(mux_negotiated(&negotiated()));
+
+        // A peer without the capability keeps the plain framing
+        let negotiated = ours.negotiate(&Identity::new(vec![], vec![]));
+        assert!(!mux_negotiated(&negotiated));
+        let (local, _remote) = duplex(1024);
+        let refused = MultiplexedTransport::new(local, address(), MuxConfig::new(), &negotiated);
+        assert!(matches!(refused, Err(ProtocolError::Connection(_))));
+    }
+}
@@ -0,0 +1,287 @@
+//! Connection Quality
+//!
+//! UIs show link quality as a few signal bars, not as RTT and loss figures.
+//! [`QualityMonitor`] classifies [`NetworkStats`] snapshots into a
+//! [`ConnectionQuality`] and reports a [`QualityChange`] whenever the
+//! classification changes. Subscribers get the current quality through a
+//! `watch` channel.
+//!
+//! ## Hysteresis
+//!
+//! Estimates hovering around a threshold would make the bars flap. A link
+//! gets worse as soon as any of RTT, jitter or loss exceeds the threshold of
+//! a worse class, but only gets better once all of them are below the
+//! threshold scaled down by [`QualityThresholds::hysteresis`] (20% by
+//! default).
+//!
+//! ```rust
+//! use cosmic_ext_connect_core::network::transport::{
+//!     ConnectionQuality, NetworkStats, QualityMonitor,
+//! };
+//! use std::time::Duration;
+//!
+//! let mut monitor = QualityMonitor::new();
+//! let stats = NetworkStats {
+//!     rtt: Some(Duration::from_millis(500)),
+//!     ..Default::default()
+//! };
+//! let change = monitor.update(&stats).unwrap();
+//! assert_eq!(change.to, ConnectionQuality::Poor);
+//! assert_eq!(monitor.quality().bars(), 1);
+//! ```
+
+use super::r#trait::Transport;
+use super::stats::NetworkStats;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::info;
+
+/// Default fraction thresholds are scaled down by before quality improves
+pub const DEFAULT_QUALITY_HYSTERESIS: f64 = 0.2;
+
+/// Classification of a connection's quality, from best to worst
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum ConnectionQuality {
+    /// Low latency, little jitter or loss
+    #[default]
+    Good,
+    /// Noticeable latency, jitter or loss
+    Degraded,
+    /// Latency, jitter or loss high enough to disrupt streaming
+    Poor,
+}
+
+impl ConnectionQuality {
+    /// Get the number of signal bars to show (3 for good, 1 for poor)
+    pub fn bars(&self) -> u8 {
+        match self {
+            Self::Good => 3,
+            Self::Degraded => 2,
+            Self::Poor => 1,
+        }
+    }
+}
+
+/// Limits above which a connection falls into a quality class
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QualityLimits {
+    /// Smoothed round-trip time
+    pub rtt: Duration,
+    /// Smoothed RTT variation
+    pub jitter: Duration,
+    /// Fraction of probes lost, from 0.0 to 1.0
+    pub loss_rate: f64,
+}
+
+impl QualityLimits {
+    /// Check whether any estimate exceeds the limits scaled by `scale`
+    ///
+    /// Before the first RTT sample only the loss rate is checked.
+    fn exceeded(&self, stats: &NetworkStats, scale: f64) -> bool {
+        stats.rtt.is_some_and(|rtt| rtt > self.rtt.mul_f64(scale))
+            || stats.jitter > self.jitter.mul_f64(scale)
+            || stats.loss_rate > self.loss_rate * scale
+    }
+}
+
+/// Limits of the degraded and poor classes and their hysteresis
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QualityThresholds {
+    /// Limits above which a connection is degraded
+    pub degraded: QualityLimits,
+    /// Limits above which a connection is poor
+    pub poor: QualityLimits,
+    /// Fraction the limits are scaled down by before quality improves
+    pub hysteresis: f64,
+}
+
+impl Default for QualityThresholds {
+    fn default() -> Self {
+        Self {
+            degraded: QualityLimits {
+                rtt: Duration::from_millis(150),
+                jitter: Duration::from_millis(30),
+                loss_rate: 0.02,
+            },
+            poor: QualityLimits {
+                rtt: Duration::from_millis(400),
+                jitter: Duration::from_millis(100),
+                loss_rate: 0.1,
+            },
+            hysteresis: DEFAULT_QUALITY_HYSTERESIS,
+        }
+    }
+}
+
+impl QualityThresholds {
+    /// Classify stats with every limit scaled by `scale`
+    fn classify(&self, stats: &NetworkStats, scale: f64) -> ConnectionQuality {
+        if self.poor.exceeded(stats, scale) {
+            ConnectionQuality::Poor
+        } else if self.degraded.exceeded(stats, scale) {
+            ConnectionQuality::Degraded
+        } else {
+            ConnectionQuality::Good
+        }
+    }
+}
+
+/// Change of a connection's quality classification
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QualityChange {
+    /// Previous classification
+    pub from: ConnectionQuality,
+    /// New classification
+    pub to: ConnectionQuality,
+    /// Stats that caused the change
+    pub stats: NetworkStats,
+}
+
+/// Tracks a connection's quality classification
+#[derive(Debug)]
+pub struct QualityMonitor {
+    /// Class limits and hysteresis
+    thresholds: QualityThresholds,
+
+    /// Current classification, published to subscribers
+    quality_tx: watch::Sender<ConnectionQuality>,
+}
+
+impl Default for QualityMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl QualityMonitor {
+    /// Create a monitor with the default thresholds, starting at good
+    pub fn new() -> Self {
+        Self {
+            thresholds: QualityThresholds::default(),
+            quality_tx: watch::channel(ConnectionQuality::default()).0,
+        }
+    }
+
+    /// Set the class limits and hysteresis
+    pub fn with_thresholds(mut self, thresholds: QualityThresholds) -> Self {
+        self.thresholds = thresholds;
+        self
+    }
+
+    /// Get the current classification
+    pub fn quality(&self) -> ConnectionQuality {
+        *self.quality_tx.borrow()
+    }
+
+    /// Subscribe to classification changes
+    pub fn subscribe(&self) -> watch::Receiver<ConnectionQuality> {
+        self.quality_tx.subscribe()
+    }
+
+    /// Classify a stats snapshot
+    ///
+    /// Returns the change if the classification changed; subscribers are
+    /// notified of it too.
+    pub fn update(&mut self, stats: &NetworkStats) -> Option<QualityChange> {
+        let current = self.quality();
+        let worse = self.thresholds.classify(stats, 1.0);
+        let next = if worse > current {
+            worse
+        } else {
+            let scale = (1.0 - self.thresholds.hysteresis).max(0.0);
+            self.thresholds.classify(stats, scale).min(current)
+        };
+
+        if next == current {
+            return None;
+        }
+
+        info!("Connection quality {:?} -> {:?}", current, next);
+        self.quality_tx.send_replace(next);
+        Some(QualityChange {
+            from: current,
+            to: next,
+            stats: *stats,
+        })
+    }
+
+    /// Classify a transport's current stats
+    pub fn observe<T: Transport + ?Sized>(&mut self, transport: &T) -> Option<QualityChange> {
+        self.update(&transport.network_stats())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(rtt_ms: u64, jitter_ms: u64, loss_rate: f64) -> NetworkStats {
+        NetworkStats {
+            rtt: Some(Duration::from_millis(rtt_ms)),
+            jitter: Duration::from_millis(jitter_ms),
+            loss_rate,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_quality_transitions_with_hysteresis() {
+        use ConnectionQuality::*;
+
+        let mut monitor = QualityMonitor::new();
+        let mut subscriber = monitor.subscribe();
+        assert!(monitor.update(&NetworkStats::default()).is_none());
+        assert!(monitor.update(&stats(40, 5, 0.0)).is_none());
+
+        // Degrading: each threshold crossed moves down a class
+        let change = monitor.update(&stats(160, 10, 0.0)).unwrap();
+        assert_eq!((change.from, change.to), (Good, Degraded));
+        assert!(subscriber.has_changed().unwrap());
+        assert_eq!(*subscriber.borrow_and_update(), Degraded);
+
+        let change = monitor.update(&stats(200, 20, 0.15)).unwrap();
+        assert_eq!((change.from, change.to), (Degraded, Poor));
+        assert_eq!(change.stats.loss_rate, 0.15);
+
+        // Improving just below a threshold is not enough (hysteresis)
+        assert!(monitor.update(&stats(200, 20, 0.09)).is_none());
+        assert_eq!(monitor.quality(), Poor);
+        assert!(monitor.update(&stats(145, 20, 0.085)).is_none());
+
+        // Below 80% of the poor limits, but not of the degraded ones
+        let change = monitor.update(&stats(145, 20, 0.0)).unwrap();
+        assert_eq!((change.from, change.to), (Poor, Degraded));
+        assert!(monitor.update(&stats(130, 20, 0.0)).is_none());
+
+        let change = monitor.update(&stats(110, 20, 0.0)).unwrap();
+        assert_eq!((change.from, change.to), (Degraded, Good));
+        assert_eq!(monitor.quality().bars(), 3);
+        assert_eq!(*subscriber.borrow_and_update(), Good);
+
+        // Hovering around a threshold does not flap
+        for rtt in [140, 155, 145, 158] {
+            monitor.update(&stats(rtt, 10, 0.0));
+        }
+        assert_eq!(monitor.quality(), Degraded);
+    }
+
+    #[test]
+    fn test_quality_jumps_directly_to_poor() {
+        let mut monitor = QualityMonitor::new().with_thresholds(QualityThresholds {
+            hysteresis: 0.0,
+            ..Default::default()
+        });
+
+        let change = monitor.update(&stats(20, 150, 0.0)).unwrap();
+        assert_eq!(change.to, ConnectionQuality::Poor);
+
+        // Without hysteresis, quality improves right at the limits
+        let change = monitor.update(&stats(150, 30, 0.02)).unwrap();
+        assert_eq!(change.to, ConnectionQuality::Good);
+        assert_eq!(serde_json::to_string(&change.to).unwrap(), "\"good\"");
+    }
+}
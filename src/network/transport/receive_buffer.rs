@@ -0,0 +1,464 @@
+//! Bounded Receive Buffer
+//!
+//! [`spawn_receiver`] runs a transport's receive loop on its own task and
+//! hands the parsed packets to the consumer through a [`PacketReceiver`]
+//! with a fixed capacity. When the consumer falls behind and the buffer is
+//! full, the loop stops reading from the transport until the consumer
+//! catches up. A fast peer then backs up into the socket, and through TCP
+//! flow control into the peer itself, instead of growing memory here or
+//! losing packets.
+//!
+//! The loop reads from any [`PacketSource`]. Every boxed [`Transport`] is
+//! one; since the loop owns it, send through a handle taken beforehand, such
+//! as [`MultiplexedTransport::sender`](super::MultiplexedTransport::sender),
+//! or implement [`PacketSource`] for the read half of a split connection.
+//!
+//! ## Drop-Oldest Packet Types
+//!
+//! For realtime media a late packet is worth less than a fresh one. Packet
+//! types registered with [`ReceiveBufferConfig::with_drop_oldest`] do not
+//! wait for space: when the buffer is full, a new packet of such a type
+//! evicts the oldest queued packet of the same type. If none is queued, it
+//! waits like any other packet. Evictions are counted in
+//! [`PacketReceiver::dropped`].
+//!
+//! ```rust,no_run
+//! use cosmic_ext_connect_core::network::transport::{
+//!     spawn_receiver, ReceiveBufferConfig, Transport,
+//! };
+//!
+//! # async fn example(transport: Box<dyn Transport>) -> cosmic_ext_connect_core::Result<()> {
+//! let config = ReceiveBufferConfig::new(32).with_drop_oldest(["cconnect.camera.frame"]);
+//! let mut receiver = spawn_receiver(transport, config);
+//! while let Ok(packet) = receiver.recv().await {
+//!     println!("Received {}", packet.packet_type);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use super::r#trait::Transport;
+use crate::{Packet, ProtocolError, Result};
+use async_trait::async_trait;
+use std::collections::{HashSet, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tracing::{debug, trace};
+
+/// Default number of packets buffered before reads pause
+pub const DEFAULT_RECEIVE_BUFFER_CAPACITY: usize = 64;
+
+/// Capacity and drop policy of a [`PacketReceiver`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReceiveBufferConfig {
+    /// Packets buffered before reads pause (minimum 1)
+    capacity: usize,
+
+    /// Packet types that evict their oldest queued packet instead of waiting
+    drop_oldest: HashSet<String>,
+}
+
+impl Default for ReceiveBufferConfig {
+    fn default() -> Self {
+        Self::new(DEFAULT_RECEIVE_BUFFER_CAPACITY)
+    }
+}
+
+impl ReceiveBufferConfig {
+    /// Create a config buffering up to `capacity` packets
+    ///
+    /// A capacity of 0 is raised to 1.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            drop_oldest: HashSet::new(),
+        }
+    }
+
+    /// Evict the oldest queued packet of the given types when full
+    pub fn with_drop_oldest<I, S>(mut self, packet_types: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.drop_oldest.extend(packet_types.into_iter().map(Into::into));
+        self
+    }
+
+    /// Get the number of packets buffered before reads pause
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Check whether a packet type evicts its oldest queued packet when full
+    pub fn drops_oldest(&self, packet_type: &str) -> bool {
+        self.drop_oldest.contains(packet_type)
+    }
+}
+
+/// Receive side of a connection, read by [`spawn_receiver`]
+#[async_trait]
+pub trait PacketSource: Send + 'static {
+    /// Receive the next packet
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reception fails; the receive loop stops.
+    async fn receive_packet(&mut self) -> Result<Packet>;
+
+    /// Describe the remote end for logging
+    fn describe(&self) -> String;
+}
+
+#[async_trait]
+impl<T> PacketSource for Box<T>
+where
+    T: Transport + ?Sized + 'static,
+{
+    async fn receive_packet(&mut self) -> Result<Packet> {
+        (**self).receive_packet().await
+    }
+
+    fn describe(&self) -> String {
+        self.remote_address().to_string()
+    }
+}
+
+/// Buffer contents shared by the receive loop and the consumer
+#[derive(Debug, Default)]
+struct BufferState {
+    /// Received packets in arrival order
+    packets: VecDeque<Packet>,
+
+    /// Packets evicted by drop-oldest types
+    dropped: u64,
+
+    /// Whether the receive loop has stopped
+    ended: bool,
+
+    /// Error that stopped the receive loop, until handed to the consumer
+    error: Option<ProtocolError>,
+
+    /// Whether the consumer is gone
+    closed: bool,
+}
+
+/// State shared by the receive loop and the consumer
+#[derive(Debug)]
+struct Shared {
+    config: ReceiveBufferConfig,
+    state: Mutex<BufferState>,
+    /// Signalled when a packet is queued or the loop ends
+    readable: Notify,
+    /// Signalled when the consumer takes a packet
+    writable: Notify,
+}
+
+impl Shared {
+    fn state(&self) -> MutexGuard<'_, BufferState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Queue a packet, waiting while the buffer is full
+    ///
+    /// Returns `false` if the consumer is gone.
+    async fn push(&self, packet: Packet) -> bool {
+        let mut announced = false;
+        loop {
+            let writable = self.writable.notified();
+            {
+                let mut state = self.state();
+                if state.closed {
+                    return false;
+                }
+
+                if state.packets.len() < self.config.capacity {
+                    state.packets.push_back(packet);
+                    self.readable.notify_one();
+                    return true;
+                }
+
+                if self.config.drops_oldest(&packet.packet_type) {
+                    if let Some(oldest) = state
+                        .packets
+                        .iter()
+                        .position(|queued| queued.packet_type == packet.packet_type)
+                    {
+                        trace!("Receive buffer full, dropping oldest '{}'", packet.packet_type);
+                        state.packets.remove(oldest);
+                        state.packets.push_back(packet);
+                        state.dropped += 1;
+                        self.readable.notify_one();
+                        return true;
+                    }
+                }
+            }
+
+            if !announced {
+                debug!("Receive buffer full, pausing reads");
+                announced = true;
+            }
+            writable.await;
+        }
+    }
+
+    /// Record that the receive loop stopped because of `error`
+    fn end(&self, error: ProtocolError) {
+        let mut state = self.state();
+        state.ended = true;
+        state.error = Some(error);
+        self.readable.notify_one();
+    }
+}
+
+/// Consumer side of a transport's receive loop
+///
+/// Created by [`spawn_receiver`]. Dropping it stops the loop.
+#[derive(Debug)]
+pub struct PacketReceiver {
+    shared: Arc<Shared>,
+    task: JoinHandle<()>,
+}
+
+impl PacketReceiver {
+    /// Receive the next packet, waiting until one arrives
+    ///
+    /// # Errors
+    ///
+    /// Once the buffered packets are consumed, returns the error that
+    /// stopped the receive loop, and `ProtocolError::Connection` after that.
+    pub async fn recv(&mut self) -> Result<Packet> {
+        loop {
+            let readable = self.shared.readable.notified();
+            {
+                let mut state = self.shared.state();
+                if let Some(packet) = state.packets.pop_front() {
+                    self.shared.writable.notify_one();
+                    return Ok(packet);
+                }
+                if state.ended {
+                    return Err(state.error.take().unwrap_or_else(|| {
+                        ProtocolError::Connection("Receive loop ended".to_string())
+                    }));
+                }
+            }
+            readable.await;
+        }
+    }
+
+    /// Get the number of buffered packets
+    pub fn len(&self) -> usize {
+        self.shared.state().packets.len()
+    }
+
+    /// Check whether no packets are buffered
+    pub fn is_empty(&self) -> bool {
+        self.shared.state().packets.is_empty()
+    }
+
+    /// Get the number of packets evicted by drop-oldest packet types
+    pub fn dropped(&self) -> u64 {
+        self.shared.state().dropped
+    }
+
+    /// Get the buffer's capacity and drop policy
+    pub fn config(&self) -> &ReceiveBufferConfig {
+        &self.shared.config
+    }
+}
+
+impl Drop for PacketReceiver {
+    fn drop(&mut self) {
+        self.shared.state().closed = true;
+        self.task.abort();
+    }
+}
+
+/// Run a receive loop into a bounded buffer
+///
+/// Packets are read from `source` on a spawned task until it returns an
+/// error or the returned receiver is dropped. Must be called within a Tokio
+/// runtime.
+pub fn spawn_receiver<S: PacketSource>(
+    mut source: S,
+    config: ReceiveBufferConfig,
+) -> PacketReceiver {
+    let shared = Arc::new(Shared {
+        config,
+        state: Mutex::new(BufferState::default()),
+        readable: Notify::new(),
+        writable: Notify::new(),
+    });
+
+    let task = tokio::spawn({
+        let shared = Arc::clone(&shared);
+        async move {
+            loop {
+                match source.receive_packet().await {
+                    Ok(packet) => {
+                        if !shared.push(packet).await {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        debug!("Receive loop for {} ended: {}", source.describe(), e);
+                        shared.end(e);
+                        break;
+                    }
+                }
+            }
+        }
+    });
+
+    PacketReceiver { shared, task }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::transport::{
+        advertise_mux, LatencyCategory, MultiplexedTransport, MuxConfig, TransportAddress,
+        TransportCapabilities,
+    };
+    use crate::protocol::Identity;
+    use async_trait::async_trait;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    /// Transport that plays back `script`, then repeats `endless` forever
+    /// or fails if it is `None`
+    #[derive(Debug, Default)]
+    struct ScriptedTransport {
+        script: VecDeque<Packet>,
+        endless: Option<&'static str>,
+        reads: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl Transport for ScriptedTransport {
+        fn capabilities(&self) -> TransportCapabilities {
+            TransportCapabilities {
+                max_packet_size: 1024,
+                reliable: true,
+                connection_oriented: true,
+                latency: LatencyCategory::Low,
+            }
+        }
+
+        fn remote_address(&self) -> TransportAddress {
+            TransportAddress::Tcp("127.0.0.1:1716".parse().unwrap())
+        }
+
+        async fn send_packet(&mut self, _packet: &Packet) -> Result<()> {
+            Ok(())
+        }
+
+        async fn receive_packet(&mut self) -> Result<Packet> {
+            let read = self.reads.fetch_add(1, Ordering::SeqCst);
+            match (self.script.pop_front(), self.endless) {
+                (Some(packet), _) => Ok(packet),
+                (None, Some(packet_type)) => Ok(Packet::new(packet_type, json!({ "n": read }))),
+                (None, None) => Err(ProtocolError::Network("link down".to_string())),
+            }
+        }
+
+        async fn close(self: Box<Self>) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Let the receive loop run until it blocks
+    ///
+    /// Tests run with paused time, so the sleep only completes once every
+    /// task is idle.
+    async fn settle() {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_full_buffer_pauses_reads() {
+        let reads = Arc::new(AtomicUsize::new(0));
+        let transport = ScriptedTransport {
+            endless: Some("cconnect.ping"),
+            reads: Arc::clone(&reads),
+            ..Default::default()
+        };
+        let mut receiver = spawn_receiver(Box::new(transport), ReceiveBufferConfig::new(4));
+
+        // A stalled consumer: 4 packets buffered and one read waiting for space
+        settle().await;
+        assert_eq!(receiver.len(), 4);
+        assert_eq!(reads.load(Ordering::SeqCst), 5);
+        settle().await;
+        assert_eq!(reads.load(Ordering::SeqCst), 5);
+
+        // Draining resumes reads, in order, until the buffer is full again
+        for n in 0..2 {
+            assert_eq!(receiver.recv().await.unwrap().body["n"], n);
+        }
+        settle().await;
+        assert_eq!(receiver.len(), 4);
+        assert_eq!(reads.load(Ordering::SeqCst), 7);
+        assert_eq!(receiver.dropped(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_drop_oldest_packet_type() {
+        let frame = |n: u32| Packet::new("cconnect.camera.frame", json!({ "n": n }));
+        let script = [
+            Packet::new("cconnect.camera.status", json!({})),
+            frame(0),
+            frame(1),
+            frame(2),
+        ];
+        let transport = ScriptedTransport {
+            script: script.into_iter().collect(),
+            ..Default::default()
+        };
+        let config = ReceiveBufferConfig::new(2).with_drop_oldest(["cconnect.camera.frame"]);
+        let mut receiver = spawn_receiver(Box::new(transport), config);
+
+        // Newer frames evicted older ones; the status packet was kept
+        settle().await;
+        assert_eq!(receiver.dropped(), 2);
+        let status = receiver.recv().await.unwrap();
+        assert_eq!(status.packet_type, "cconnect.camera.status");
+        assert_eq!(receiver.recv().await.unwrap().body["n"], 2);
+
+        // Then the error that ended the loop
+        assert!(matches!(receiver.recv().await, Err(ProtocolError::Network(_))));
+        assert!(matches!(receiver.recv().await, Err(ProtocolError::Connection(_))));
+    }
+
+    #[tokio::test]
+    async fn test_send_handle_outlives_spawned_transport() {
+        let (near, far) = tokio::io::duplex(64 * 1024);
+        let address = TransportAddress::Tcp("127.0.0.1:1716".parse().unwrap());
+        let identity = advertise_mux(Identity::new(vec![], vec![]));
+        let negotiated = identity.negotiate(&identity);
+        let near =
+            MultiplexedTransport::new(near, address.clone(), MuxConfig::new(), &negotiated)
+                .unwrap();
+        let mut far =
+            MultiplexedTransport::new(far, address, MuxConfig::new(), &negotiated).unwrap();
+
+        // Take the sender before handing the transport to the receive loop
+        let sender = near.sender();
+        let mut receiver = spawn_receiver(Box::new(near), ReceiveBufferConfig::default());
+
+        sender
+            .send(&Packet::new("cconnect.ping", json!({})))
+            .await
+            .unwrap();
+        let ping = far.receive_packet().await.unwrap();
+        assert_eq!(ping.packet_type, "cconnect.ping");
+
+        far.send_packet(&Packet::new("cconnect.battery", json!({})))
+            .await
+            .unwrap();
+        let battery = receiver.recv().await.unwrap();
+        assert_eq!(battery.packet_type, "cconnect.battery");
+    }
+}
@@ -0,0 +1,492 @@
+//! Reconnecting Transport
+//!
+//! A phone that moves from Wi-Fi to a hotspot comes back with a new IP
+//! address, so retrying the address a connection was opened with fails
+//! forever. [`ReconnectingTransport`] asks an [`AddressResolver`] (normally
+//! discovery) where the device is *now* before every reconnect attempt and
+//! migrates the connection there.
+//!
+//! The transport reconnects lazily: a connection error closes the inner
+//! transport, and the next send or receive reconnects first. A failed
+//! `send_packet` is retried once on the new connection.
+//!
+//! Every connect, disconnect, reconnect and packet error is recorded in the
+//! transport's [`ConnectionLog`] (see [`ReconnectingTransport::connection_log`]).
+
+use super::connection_log::{ConnectionEvent, ConnectionLog};
+use super::r#trait::{Transport, TransportAddress, TransportCapabilities, TransportFactory};
+use super::stats::NetworkStats;
+use crate::protocol::DeviceId;
+use crate::{Packet, ProtocolError, Result};
+use async_trait::async_trait;
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+/// Default number of connection attempts per reconnect
+pub const DEFAULT_RECONNECT_ATTEMPTS: u32 = 5;
+
+/// Default delay before the second connection attempt
+pub const DEFAULT_RECONNECT_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Default upper bound for the delay between attempts
+pub const DEFAULT_MAX_RECONNECT_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Looks up the address a device can currently be reached at
+#[async_trait]
+pub trait AddressResolver: Send + Sync + Debug {
+    /// Get the device's current address, or `None` if it is unknown
+    async fn resolve(&self, device_id: &DeviceId) -> Option<TransportAddress>;
+}
+
+/// Retry schedule for [`ReconnectingTransport`]
+///
+/// The delay between attempts starts at `initial_backoff` and doubles up to
+/// `max_backoff`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReconnectPolicy {
+    /// Connection attempts per reconnect (minimum 1)
+    pub max_attempts: u32,
+    /// Delay after the first failed attempt
+    pub initial_backoff: Duration,
+    /// Upper bound for the delay between attempts
+    pub max_backoff: Duration,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_RECONNECT_ATTEMPTS,
+            initial_backoff: DEFAULT_RECONNECT_BACKOFF,
+            max_backoff: DEFAULT_MAX_RECONNECT_BACKOFF,
+        }
+    }
+}
+
+/// Transport that reconnects to a device's current address on failure
+#[derive(Debug)]
+pub struct ReconnectingTransport<F> {
+    /// Opens new connections
+    factory: F,
+
+    /// Source of the device's current address
+    resolver: Arc<dyn AddressResolver>,
+
+    /// Device being connected to
+    device_id: DeviceId,
+
+    /// Address of the current (or most recent) connection
+    address: TransportAddress,
+
+    /// Current connection, `None` after a connection error
+    inner: Option<Box<dyn Transport>>,
+
+    /// Capabilities of the most recent connection
+    capabilities: TransportCapabilities,
+
+    /// Retry schedule
+    policy: ReconnectPolicy,
+
+    /// History of connection events
+    log: ConnectionLog,
+}
+
+impl<F: TransportFactory> ReconnectingTransport<F> {
+    /// Connect to a device, retrying according to `policy`
+    ///
+    /// `address` is used if the resolver does not know the device.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Connection` if every attempt fails.
+    pub async fn connect(
+        factory: F,
+        resolver: Arc<dyn AddressResolver>,
+        device_id: DeviceId,
+        address: TransportAddress,
+        policy: ReconnectPolicy,
+    ) -> Result<Self> {
+        let inner = Self::connect_with_retry(
+            &factory,
+            resolver.as_ref(),
+            &device_id,
+            &address,
+            policy,
+        )
+        .await?;
+
+        let mut log = ConnectionLog::default();
+        log.record(ConnectionEvent::Connected {
+            address: inner.remote_address().to_string(),
+        });
+
+        Ok(Self {
+            factory,
+            resolver,
+            device_id,
+            address: inner.remote_address(),
+            capabilities: inner.capabilities(),
+            inner: Some(inner),
+            policy,
+            log,
+        })
+    }
+
+    /// Get the ID of the device this transport connects to
+    pub fn device_id(&self) -> &DeviceId {
+        &self.device_id
+    }
+
+    /// Get the history of this device's connection events
+    pub fn connection_log(&self) -> &ConnectionLog {
+        &self.log
+    }
+
+    /// Drop the current connection and connect to the device's current address
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Connection` if every attempt fails. The
+    /// transport stays disconnected and the next call tries again.
+    pub async fn reconnect(&mut self) -> Result<()> {
+        self.inner = None;
+        self.log.record(ConnectionEvent::Reconnecting {
+            address: self.address.to_string(),
+        });
+
+        let inner = match Self::connect_with_retry(
+            &self.factory,
+            self.resolver.as_ref(),
+            &self.device_id,
+            &self.address,
+            self.policy,
+        )
+        .await
+        {
+            Ok(inner) => inner,
+            Err(e) => {
+                self.log.record(ConnectionEvent::Disconnected {
+                    reason: e.to_string(),
+                });
+                return Err(e);
+            }
+        };
+
+        self.log.record(ConnectionEvent::Connected {
+            address: inner.remote_address().to_string(),
+        });
+        self.address = inner.remote_address();
+        self.capabilities = inner.capabilities();
+        self.inner = Some(inner);
+        Ok(())
+    }
+
+    async fn connect_with_retry(
+        factory: &F,
+        resolver: &dyn AddressResolver,
+        device_id: &DeviceId,
+        fallback: &TransportAddress,
+        policy: ReconnectPolicy,
+    ) -> Result<Box<dyn Transport>> {
+        let attempts = policy.max_attempts.max(1);
+        let mut backoff = policy.initial_backoff;
+        let mut address = fallback.clone();
+        let mut last_error = None;
+
+        for attempt in 1..=attempts {
+            // Ask again every time: the device may move between attempts
+            if let Some(current) = resolver.resolve(device_id).await {
+                if current != address {
+                    info!("Device {} moved from {} to {}", device_id, address, current);
+                    address = current;
+                }
+            }
+
+            debug!("Connecting to {} at {} (attempt {}/{})", device_id, address, attempt, attempts);
+            match factory.connect(address.clone()).await {
+                Ok(transport) => return Ok(transport),
+                Err(e) => {
+                    warn!("Connection attempt {}/{} to {} failed: {}", attempt, attempts, address, e);
+                    last_error = Some(e);
+                }
+            }
+
+            if attempt < attempts {
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(policy.max_backoff);
+            }
+        }
+
+        Err(ProtocolError::Connection(format!(
+            "Could not reach {} after {} attempts: {}",
+            device_id,
+            attempts,
+            last_error.map(|e| e.to_string()).unwrap_or_default()
+        )))
+    }
+
+    /// Get the current connection, reconnecting if it is gone or degraded
+    async fn connection(&mut self) -> Result<&mut Box<dyn Transport>> {
+        let usable = self
+            .inner
+            .as_ref()
+            .is_some_and(|inner| inner.is_connected() && !inner.is_degraded());
+        if !usable {
+            self.reconnect().await?;
+        }
+        Ok(self.inner.as_mut().expect("connected after reconnect"))
+    }
+
+    /// Forget the current connection if `result` is a connection error
+    ///
+    /// Errors other than timeouts are recorded in the connection log.
+    fn check<T>(&mut self, result: Result<T>) -> Result<T> {
+        match &result {
+            Err(e) if is_connection_error(e) => {
+                warn!("Connection to {} lost: {}", self.device_id, e);
+                self.inner = None;
+                self.log.record(ConnectionEvent::Disconnected {
+                    reason: e.to_string(),
+                });
+            }
+            Err(ProtocolError::Timeout) | Ok(_) => {}
+            Err(e) => self.log.record(ConnectionEvent::PacketError {
+                message: e.to_string(),
+            }),
+        }
+        result
+    }
+}
+
+/// Check whether an error means the connection itself is unusable
+fn is_connection_error(error: &ProtocolError) -> bool {
+    matches!(
+        error,
+        ProtocolError::Io(_)
+            | ProtocolError::Connection(_)
+            | ProtocolError::Network(_)
+            | ProtocolError::Tls(_)
+    )
+}
+
+#[async_trait]
+impl<F: TransportFactory> Transport for ReconnectingTransport<F> {
+    fn capabilities(&self) -> TransportCapabilities {
+        self.capabilities
+    }
+
+    fn remote_address(&self) -> TransportAddress {
+        self.address.clone()
+    }
+
+    async fn send_packet(&mut self, packet: &Packet) -> Result<()> {
+        let result = self.connection().await?.send_packet(packet).await;
+        match self.check(result) {
+            Err(e) if self.inner.is_none() => {
+                debug!("Retrying send to {} after reconnect: {}", self.device_id, e);
+                let result = self.connection().await?.send_packet(packet).await;
+                self.check(result)
+            }
+            result => result,
+        }
+    }
+
+    /// Not retried, since part of the batch may already have been sent
+    async fn send_batch(&mut self, packets: &[Packet]) -> Result<()> {
+        let result = self.connection().await?.send_batch(packets).await;
+        self.check(result)
+    }
+
+    async fn receive_packet(&mut self) -> Result<Packet> {
+        let result = self.connection().await?.receive_packet().await;
+        self.check(result)
+    }
+
+    async fn close(self: Box<Self>) -> Result<()> {
+        match self.inner {
+            Some(inner) => inner.close().await,
+            None => Ok(()),
+        }
+    }
+
+    fn is_connected(&self) -> bool {
+        self.inner.as_ref().is_some_and(|inner| inner.is_connected())
+    }
+
+    fn is_degraded(&self) -> bool {
+        self.inner.as_ref().is_some_and(|inner| inner.is_degraded())
+    }
+
+    /// Statistics of the current connection; they restart after a reconnect
+    fn network_stats(&self) -> NetworkStats {
+        self.inner
+            .as_ref()
+            .map(|inner| inner.network_stats())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::transport::{LatencyCategory, TransportType};
+    use serde_json::json;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Mutex;
+
+    /// Transport that fails every send once `broken` is set
+    #[derive(Debug)]
+    struct FlakyTransport {
+        address: TransportAddress,
+        broken: Arc<AtomicBool>,
+    }
+
+    #[async_trait]
+    impl Transport for FlakyTransport {
+        fn capabilities(&self) -> TransportCapabilities {
+            TransportCapabilities {
+                max_packet_size: 1024,
+                reliable: true,
+                connection_oriented: true,
+                latency: LatencyCategory::Low,
+            }
+        }
+
+        fn remote_address(&self) -> TransportAddress {
+            self.address.clone()
+        }
+
+        async fn send_packet(&mut self, _packet: &Packet) -> Result<()> {
+            if self.broken.load(Ordering::SeqCst) {
+                return Err(ProtocolError::Connection("Connection reset".to_string()));
+            }
+            Ok(())
+        }
+
+        async fn receive_packet(&mut self) -> Result<Packet> {
+            Err(ProtocolError::Timeout)
+        }
+
+        async fn close(self: Box<Self>) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Factory recording every address it was asked to connect to
+    #[derive(Debug, Default)]
+    struct RecordingFactory {
+        connects: Mutex<Vec<TransportAddress>>,
+        /// Broken flag of the most recent connection
+        broken: Mutex<Arc<AtomicBool>>,
+    }
+
+    impl RecordingFactory {
+        fn break_connection(&self) {
+            self.broken.lock().unwrap().store(true, Ordering::SeqCst);
+        }
+    }
+
+    #[async_trait]
+    impl TransportFactory for Arc<RecordingFactory> {
+        async fn connect(&self, address: TransportAddress) -> Result<Box<dyn Transport>> {
+            self.connects.lock().unwrap().push(address.clone());
+            let broken = Arc::new(AtomicBool::new(false));
+            *self.broken.lock().unwrap() = broken.clone();
+            Ok(Box::new(FlakyTransport { address, broken }))
+        }
+
+        fn transport_type(&self) -> TransportType {
+            TransportType::Tcp
+        }
+    }
+
+    /// Resolver standing in for discovery
+    #[derive(Debug, Default)]
+    struct StaticResolver(Mutex<Option<TransportAddress>>);
+
+    #[async_trait]
+    impl AddressResolver for StaticResolver {
+        async fn resolve(&self, _device_id: &DeviceId) -> Option<TransportAddress> {
+            self.0.lock().unwrap().clone()
+        }
+    }
+
+    fn tcp(addr: &str) -> TransportAddress {
+        TransportAddress::Tcp(addr.parse().unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_follows_address_change() {
+        let factory = Arc::new(RecordingFactory::default());
+        let resolver = Arc::new(StaticResolver::default());
+        let old = tcp("192.168.1.20:1716");
+        let new = tcp("172.20.10.3:1716");
+
+        let mut transport = ReconnectingTransport::connect(
+            factory.clone(),
+            resolver.clone(),
+            DeviceId::generate(),
+            old.clone(),
+            ReconnectPolicy::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(transport.remote_address(), old);
+
+        // The phone moves to a hotspot and the old connection dies
+        *resolver.0.lock().unwrap() = Some(new.clone());
+        factory.break_connection();
+
+        let packet = Packet::new("cconnect.ping", json!({}));
+        transport.send_packet(&packet).await.unwrap();
+
+        assert_eq!(*factory.connects.lock().unwrap(), vec![old, new.clone()]);
+        assert_eq!(transport.remote_address(), new);
+        assert!(transport.is_connected());
+
+        let events: Vec<_> = transport
+            .connection_log()
+            .recent(10)
+            .into_iter()
+            .map(|entry| entry.event)
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                ConnectionEvent::Connected {
+                    address: "tcp://172.20.10.3:1716".to_string()
+                },
+                ConnectionEvent::Reconnecting {
+                    address: "tcp://192.168.1.20:1716".to_string()
+                },
+                ConnectionEvent::Disconnected {
+                    reason: "Connection error: Connection reset".to_string()
+                },
+                ConnectionEvent::Connected {
+                    address: "tcp://192.168.1.20:1716".to_string()
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unknown_device_uses_last_address() {
+        let factory = Arc::new(RecordingFactory::default());
+        let resolver = Arc::new(StaticResolver::default());
+        let addr = tcp("192.168.1.20:1716");
+
+        let mut transport = ReconnectingTransport::connect(
+            factory.clone(),
+            resolver,
+            DeviceId::generate(),
+            addr.clone(),
+            ReconnectPolicy::default(),
+        )
+        .await
+        .unwrap();
+
+        transport.reconnect().await.unwrap();
+        assert_eq!(*factory.connects.lock().unwrap(), vec![addr.clone(), addr]);
+    }
+}
@@ -0,0 +1,218 @@
+//! Outgoing Packet Queue
+//!
+//! [`SendQueue`] holds packets until the caller flushes them to a transport
+//! with one [`Transport::send_batch`] call.
+//!
+//! ## Coalescing
+//!
+//! Status packets (battery level, camera status) can be produced faster than
+//! they are sent, and only the latest one matters. Packet types registered
+//! with [`SendQueue::with_coalescing`] are queued in replace-by-key mode: a
+//! new packet replaces a still-queued packet of the same type, keeping its
+//! place in the queue, instead of both being sent. All other packets queue
+//! normally. Plugins declare their coalescing types with
+//! [`Plugin::coalescing_packet_types`](crate::plugins::Plugin::coalescing_packet_types),
+//! collected by [`PluginManager::coalescing_packet_types`](crate::plugins::PluginManager::coalescing_packet_types).
+//!
+//! ```rust
+//! use cosmic_ext_connect_core::network::transport::SendQueue;
+//! use cosmic_ext_connect_core::Packet;
+//! use serde_json::json;
+//!
+//! let mut queue = SendQueue::new().with_coalescing(["cconnect.battery"]);
+//! queue.push(Packet::new("cconnect.battery", json!({ "currentCharge": 80 })));
+//! queue.push(Packet::new("cconnect.battery", json!({ "currentCharge": 79 })));
+//! assert_eq!(queue.len(), 1);
+//! ```
+
+use super::r#trait::Transport;
+use crate::{Packet, Result};
+use std::collections::{HashSet, VecDeque};
+use tracing::debug;
+
+/// Queue of packets waiting to be sent
+#[derive(Debug, Clone, Default)]
+pub struct SendQueue {
+    /// Packets in send order
+    packets: VecDeque<Packet>,
+
+    /// Packet types where a newer packet replaces a queued one
+    coalescing: HashSet<String>,
+}
+
+impl SendQueue {
+    /// Create an empty queue where no packet types coalesce
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Coalesce the given packet types
+    pub fn with_coalescing<I, S>(mut self, packet_types: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.coalescing.extend(packet_types.into_iter().map(Into::into));
+        self
+    }
+
+    /// Check whether a packet type coalesces
+    pub fn coalesces(&self, packet_type: &str) -> bool {
+        self.coalescing.contains(packet_type)
+    }
+
+    /// Queue a packet
+    ///
+    /// Returns `true` if the packet replaced a queued packet of the same
+    /// coalescing type.
+    pub fn push(&mut self, packet: Packet) -> bool {
+        if self.coalesces(&packet.packet_type) {
+            if let Some(queued) = self
+                .packets
+                .iter_mut()
+                .find(|queued| queued.packet_type == packet.packet_type)
+            {
+                debug!("Replacing queued '{}' packet", packet.packet_type);
+                *queued = packet;
+                return true;
+            }
+        }
+        self.packets.push_back(packet);
+        false
+    }
+
+    /// Remove and return all queued packets in send order
+    pub fn drain(&mut self) -> Vec<Packet> {
+        self.packets.drain(..).collect()
+    }
+
+    /// Send all queued packets as one batch
+    ///
+    /// Returns the number of packets sent.
+    ///
+    /// # Errors
+    ///
+    /// Returns the transport's error if the batch fails. The packets stay
+    /// queued ahead of any pushed later, so the next flush retries them;
+    /// packets the transport sent before failing are sent again.
+    pub async fn flush<T: Transport + ?Sized>(&mut self, transport: &mut T) -> Result<usize> {
+        if self.packets.is_empty() {
+            return Ok(0);
+        }
+
+        let packets = self.drain();
+        match transport.send_batch(&packets).await {
+            Ok(()) => Ok(packets.len()),
+            Err(e) => {
+                for packet in packets.into_iter().rev() {
+                    self.packets.push_front(packet);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Get the number of queued packets
+    pub fn len(&self) -> usize {
+        self.packets.len()
+    }
+
+    /// Check whether no packets are queued
+    pub fn is_empty(&self) -> bool {
+        self.packets.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::transport::{LatencyCategory, TransportAddress, TransportCapabilities};
+    use crate::ProtocolError;
+    use async_trait::async_trait;
+    use serde_json::json;
+
+    /// Transport that records sent packets, or fails every send
+    #[derive(Debug, Default)]
+    struct RecordingTransport {
+        sent: Vec<Packet>,
+        failing: bool,
+    }
+
+    #[async_trait]
+    impl Transport for RecordingTransport {
+        fn capabilities(&self) -> TransportCapabilities {
+            TransportCapabilities {
+                max_packet_size: 1024,
+                reliable: true,
+                connection_oriented: true,
+                latency: LatencyCategory::Low,
+            }
+        }
+
+        fn remote_address(&self) -> TransportAddress {
+            TransportAddress::Tcp("127.0.0.1:1716".parse().unwrap())
+        }
+
+        async fn send_packet(&mut self, packet: &Packet) -> Result<()> {
+            if self.failing {
+                return Err(ProtocolError::Network("link down".to_string()));
+            }
+            self.sent.push(packet.clone());
+            Ok(())
+        }
+
+        async fn receive_packet(&mut self) -> Result<Packet> {
+            Err(ProtocolError::Timeout)
+        }
+
+        async fn close(self: Box<Self>) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    fn battery(charge: u32) -> Packet {
+        Packet::new("cconnect.battery", json!({ "currentCharge": charge }))
+    }
+
+    #[tokio::test]
+    async fn test_status_updates_coalesce() {
+        let mut queue = SendQueue::new().with_coalescing(["cconnect.battery"]);
+        let mut transport = RecordingTransport::default();
+
+        assert!(!queue.push(battery(80)));
+        assert!(!queue.push(Packet::new("cconnect.ping", json!({}))));
+        assert!(queue.push(battery(79)));
+        assert_eq!(queue.len(), 2);
+
+        assert_eq!(queue.flush(&mut transport).await.unwrap(), 2);
+        let sent: Vec<_> = transport.sent.iter().map(|p| p.packet_type.as_str()).collect();
+        assert_eq!(sent, vec!["cconnect.battery", "cconnect.ping"]);
+        assert_eq!(transport.sent[0].body["currentCharge"], 79);
+
+        // Only still-queued packets are replaced
+        queue.push(battery(78));
+        assert_eq!(queue.flush(&mut transport).await.unwrap(), 1);
+        assert_eq!(transport.sent[2].body["currentCharge"], 78);
+    }
+
+    #[tokio::test]
+    async fn test_other_packets_queue_normally() {
+        let mut queue = SendQueue::new();
+        queue.push(battery(80));
+        queue.push(battery(79));
+        assert_eq!(queue.len(), 2);
+
+        // A failed flush keeps the packets for the next one
+        let mut transport = RecordingTransport {
+            failing: true,
+            ..Default::default()
+        };
+        assert!(queue.flush(&mut transport).await.is_err());
+        assert_eq!(queue.len(), 2);
+
+        transport.failing = false;
+        assert_eq!(queue.flush(&mut transport).await.unwrap(), 2);
+        assert!(queue.is_empty());
+        assert_eq!(queue.flush(&mut transport).await.unwrap(), 0);
+    }
+}
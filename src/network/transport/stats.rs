@@ -0,0 +1,169 @@
+//! Network Quality Estimation
+//!
+//! Jitter buffers and bitrate control need more than byte counts: they need
+//! to know how long round trips take, how much that varies, and how often
+//! probes go unanswered. [`NetworkStatsEstimator`] turns keepalive round
+//! trips into those signals.
+//!
+//! ## Estimates
+//!
+//! The estimators follow TCP's retransmission timer (RFC 6298):
+//!
+//! - RTT: `rtt = (1 - 1/8) * rtt + 1/8 * sample`
+//! - Jitter: `jitter = (1 - 1/4) * jitter + 1/4 * |rtt - sample|`, updated
+//!   before the RTT
+//! - Loss rate: an exponential moving average (weight 1/8) of 1 for every
+//!   lost probe and 0 for every answered one
+//!
+//! The first RTT sample seeds the RTT directly and the jitter with half of
+//! it, so the estimates are usable immediately.
+
+use std::time::Duration;
+
+/// Weight of a new sample in the RTT average
+pub const RTT_ALPHA: f64 = 1.0 / 8.0;
+
+/// Weight of a new deviation in the jitter average
+pub const JITTER_BETA: f64 = 1.0 / 4.0;
+
+/// Weight of a new probe outcome in the loss average
+pub const LOSS_ALPHA: f64 = 1.0 / 8.0;
+
+/// Snapshot of estimated network quality
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct NetworkStats {
+    /// Smoothed round-trip time, `None` before the first sample
+    pub rtt: Option<Duration>,
+    /// Smoothed variation of the RTT
+    pub jitter: Duration,
+    /// Estimated fraction of probes lost, from 0.0 to 1.0
+    pub loss_rate: f64,
+    /// Probes answered so far
+    pub samples: u64,
+    /// Probes lost so far
+    pub lost: u64,
+}
+
+/// Exponential moving average estimator for RTT, jitter and loss
+#[derive(Debug, Clone, Default)]
+pub struct NetworkStatsEstimator {
+    /// Smoothed RTT in microseconds
+    rtt_us: Option<f64>,
+    /// Smoothed RTT variation in microseconds
+    jitter_us: f64,
+    /// Smoothed loss rate
+    loss_rate: f64,
+    /// Probes answered
+    samples: u64,
+    /// Probes lost
+    lost: u64,
+}
+
+impl NetworkStatsEstimator {
+    /// Create an estimator with no samples
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the round-trip time of an answered probe
+    pub fn record_rtt(&mut self, sample: Duration) {
+        let sample_us = sample.as_secs_f64() * 1_000_000.0;
+
+        match self.rtt_us {
+            None => {
+                self.rtt_us = Some(sample_us);
+                self.jitter_us = sample_us / 2.0;
+            }
+            Some(rtt_us) => {
+                self.jitter_us =
+                    (1.0 - JITTER_BETA) * self.jitter_us + JITTER_BETA * (rtt_us - sample_us).abs();
+                self.rtt_us = Some((1.0 - RTT_ALPHA) * rtt_us + RTT_ALPHA * sample_us);
+            }
+        }
+
+        self.loss_rate *= 1.0 - LOSS_ALPHA;
+        self.samples += 1;
+    }
+
+    /// Record a probe that went unanswered
+    pub fn record_loss(&mut self) {
+        self.loss_rate = (1.0 - LOSS_ALPHA) * self.loss_rate + LOSS_ALPHA;
+        self.lost += 1;
+    }
+
+    /// Current estimates
+    pub fn stats(&self) -> NetworkStats {
+        NetworkStats {
+            rtt: self.rtt_us.map(micros),
+            jitter: micros(self.jitter_us),
+            loss_rate: self.loss_rate,
+            samples: self.samples,
+            lost: self.lost,
+        }
+    }
+}
+
+fn micros(us: f64) -> Duration {
+    Duration::from_micros(us.round().max(0.0) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn test_first_sample_seeds_estimates() {
+        let mut estimator = NetworkStatsEstimator::new();
+        assert_eq!(estimator.stats(), NetworkStats::default());
+
+        estimator.record_rtt(ms(80));
+        let stats = estimator.stats();
+        assert_eq!(stats.rtt, Some(ms(80)));
+        assert_eq!(stats.jitter, ms(40));
+        assert_eq!(stats.samples, 1);
+    }
+
+    #[test]
+    fn test_rtt_and_jitter_follow_ema() {
+        let mut estimator = NetworkStatsEstimator::new();
+        estimator.record_rtt(ms(100));
+
+        // jitter = 0.75 * 50 + 0.25 * |100 - 180| = 57.5ms
+        // rtt = 0.875 * 100 + 0.125 * 180 = 110ms
+        estimator.record_rtt(ms(180));
+        let stats = estimator.stats();
+        assert_eq!(stats.rtt, Some(ms(110)));
+        assert_eq!(stats.jitter, Duration::from_micros(57_500));
+
+        // A steady RTT converges and the jitter decays towards zero
+        for _ in 0..100 {
+            estimator.record_rtt(ms(50));
+        }
+        let stats = estimator.stats();
+        let rtt = stats.rtt.unwrap();
+        assert!(rtt > ms(49) && rtt < ms(51), "rtt {:?}", rtt);
+        assert!(stats.jitter < ms(1), "jitter {:?}", stats.jitter);
+    }
+
+    #[test]
+    fn test_loss_rate_rises_and_decays() {
+        let mut estimator = NetworkStatsEstimator::new();
+
+        estimator.record_loss();
+        assert_eq!(estimator.stats().loss_rate, LOSS_ALPHA);
+        estimator.record_loss();
+        let two_losses = 1.0 - (1.0 - LOSS_ALPHA).powi(2);
+        assert!((estimator.stats().loss_rate - two_losses).abs() < 1e-9);
+
+        estimator.record_rtt(ms(20));
+        let decayed = two_losses * (1.0 - LOSS_ALPHA);
+        assert!((estimator.stats().loss_rate - decayed).abs() < 1e-9);
+
+        let stats = estimator.stats();
+        assert_eq!((stats.samples, stats.lost), (1, 2));
+    }
+}
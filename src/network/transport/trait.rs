@@ -2,10 +2,65 @@
 //!
 //! Defines a common interface for different transport types (TCP, Bluetooth, etc.)
 //! that can be used to send and receive KDE Connect packets.
+//!
+//! ## Address Strings
+//!
+//! [`TransportAddress`] parses the forms users paste or keep in config files:
+//!
+//! - `192.168.1.5:1716` or `tcp://192.168.1.5:1716`
+//! - `[fe80::1]:1716` or `tcp://[fe80::1]:1716`
+//! - `bt:AA:BB:CC:DD:EE:FF` or `bluetooth://AA:BB:CC:DD:EE:FF`, optionally
+//!   followed by a service UUID in parentheses
+//!
+//! Its `Display` output is the canonical `tcp://` / `bluetooth://` form,
+//! which parses back to the same address.
 
-use crate::{Packet, Result};
+use super::stats::NetworkStats;
+use crate::{Packet, ProtocolError, Result};
 use async_trait::async_trait;
 use std::fmt::Debug;
+use std::str::FromStr;
+use std::time::Duration;
+use tracing::warn;
+
+/// Default upper bound for a single send (10 seconds)
+pub const DEFAULT_WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Serialize packets into a single newline-framed buffer
+///
+/// Each packet is checked against `max_packet_size` before anything is
+/// returned, so a batch is either written whole or not at all.
+///
+/// # Errors
+///
+/// Returns `ProtocolError::InvalidPacket` naming the index of the first
+/// packet that exceeds `max_packet_size` or fails to serialize.
+pub fn encode_batch(packets: &[Packet], max_packet_size: usize) -> Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+
+    for (index, packet) in packets.iter().enumerate() {
+        let bytes = packet.to_bytes().map_err(|e| {
+            ProtocolError::InvalidPacket(format!(
+                "Batch packet {} ('{}') failed to serialize: {}",
+                index, packet.packet_type, e
+            ))
+        })?;
+
+        if bytes.len() > max_packet_size {
+            return Err(ProtocolError::InvalidPacket(format!(
+                "Batch packet {} ('{}') too large: {} bytes (max {})",
+                index,
+                packet.packet_type,
+                bytes.len(),
+                max_packet_size
+            )));
+        }
+
+        buffer.extend_from_slice(&bytes);
+    }
+
+    Ok(buffer)
+}
 
 /// Transport capabilities and characteristics
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -67,6 +122,266 @@ impl std::fmt::Display for TransportAddress {
     }
 }
 
+impl TransportAddress {
+    /// Get the transport type this address is reached over
+    pub fn transport_type(&self) -> TransportType {
+        match self {
+            TransportAddress::Tcp(_) => TransportType::Tcp,
+            TransportAddress::Bluetooth { .. } => TransportType::Bluetooth,
+        }
+    }
+}
+
+/// Error parsing a [`TransportAddress`] from a string
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum AddressParseError {
+    /// The input was empty
+    #[error("address is empty")]
+    Empty,
+
+    /// A TCP address has no `:port` suffix
+    #[error("missing port in '{0}' (expected host:port)")]
+    MissingPort(String),
+
+    /// The port is not a number from 1 to 65535
+    #[error("invalid port in '{0}' (expected 1-65535)")]
+    InvalidPort(String),
+
+    /// The host is not an IP address; IPv6 addresses need brackets
+    #[error("invalid IP address in '{0}' (IPv6 must be written as [addr]:port)")]
+    InvalidIp(String),
+
+    /// A Bluetooth address is not six colon-separated hex bytes
+    #[error("invalid Bluetooth address '{0}' (expected AA:BB:CC:DD:EE:FF)")]
+    InvalidMac(String),
+
+    /// The Bluetooth service UUID is malformed
+    #[error("invalid service UUID in '{0}'")]
+    InvalidUuid(String),
+}
+
+impl FromStr for TransportAddress {
+    type Err = AddressParseError;
+
+    /// Parse a TCP or Bluetooth address (see the module docs for the forms)
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let input = s.trim();
+        if input.is_empty() {
+            return Err(AddressParseError::Empty);
+        }
+
+        if let Some(rest) = strip_prefix_ignore_case(input, "bluetooth://")
+            .or_else(|| strip_prefix_ignore_case(input, "bt:"))
+        {
+            return parse_bluetooth(rest.trim());
+        }
+
+        let rest = strip_prefix_ignore_case(input, "tcp://").unwrap_or(input);
+        parse_socket_addr(rest).map(TransportAddress::Tcp)
+    }
+}
+
+/// Strip an ASCII prefix regardless of case
+fn strip_prefix_ignore_case<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = s.get(..prefix.len())?;
+    head.eq_ignore_ascii_case(prefix).then(|| &s[prefix.len()..])
+}
+
+/// Parse `ip:port` or `[ipv6]:port`
+fn parse_socket_addr(s: &str) -> std::result::Result<std::net::SocketAddr, AddressParseError> {
+    let Some((host, port)) = s.rsplit_once(':') else {
+        return Err(AddressParseError::MissingPort(s.to_string()));
+    };
+    // An unbracketed IPv6 address has no port at all
+    if host.contains(':') && !host.starts_with('[') {
+        return Err(AddressParseError::InvalidIp(s.to_string()));
+    }
+    match port.parse::<u16>() {
+        Ok(port) if port != 0 => {}
+        _ => return Err(AddressParseError::InvalidPort(s.to_string())),
+    }
+
+    s.parse()
+        .map_err(|_| AddressParseError::InvalidIp(s.to_string()))
+}
+
+/// Parse `AA:BB:CC:DD:EE:FF` with an optional `(service-uuid)` suffix
+fn parse_bluetooth(s: &str) -> std::result::Result<TransportAddress, AddressParseError> {
+    let (mac, service_uuid) = match s.split_once('(') {
+        Some((mac, uuid)) => {
+            let uuid = uuid
+                .strip_suffix(')')
+                .and_then(|uuid| uuid.trim().parse().ok())
+                .ok_or_else(|| AddressParseError::InvalidUuid(s.to_string()))?;
+            (mac.trim(), Some(uuid))
+        }
+        None => (s, None),
+    };
+
+    let octets: Vec<&str> = mac.split(':').collect();
+    let valid = octets.len() == 6
+        && octets
+            .iter()
+            .all(|octet| octet.len() == 2 && octet.chars().all(|c| c.is_ascii_hexdigit()));
+    if !valid {
+        return Err(AddressParseError::InvalidMac(mac.to_string()));
+    }
+
+    Ok(TransportAddress::Bluetooth {
+        address: mac.to_ascii_uppercase(),
+        service_uuid,
+    })
+}
+
+/// Send packets one at a time with [`Transport::send_packet`]
+///
+/// This is the fallback used by [`Transport::send_batch`]; transports that
+/// only sometimes support batched writes can call it directly.
+///
+/// # Errors
+///
+/// Returns `ProtocolError::Network` naming the index of the first packet
+/// that failed. Packets before that index have been sent.
+pub async fn send_sequential<T: Transport + ?Sized>(
+    transport: &mut T,
+    packets: &[Packet],
+) -> Result<()> {
+    for (index, packet) in packets.iter().enumerate() {
+        transport.send_packet(packet).await.map_err(|e| {
+            ProtocolError::Network(format!(
+                "Batch send failed at packet {} ('{}'): {}",
+                index, packet.packet_type, e
+            ))
+        })?;
+    }
+    Ok(())
+}
+
+/// Send policy applied by [`TimeoutTransport`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransportPolicy {
+    /// Maximum time a `send_packet` or `send_batch` call may take
+    pub write_timeout: Duration,
+}
+
+impl TransportPolicy {
+    /// Set the write timeout
+    pub fn with_write_timeout(mut self, write_timeout: Duration) -> Self {
+        self.write_timeout = write_timeout;
+        self
+    }
+}
+
+impl Default for TransportPolicy {
+    fn default() -> Self {
+        Self {
+            write_timeout: DEFAULT_WRITE_TIMEOUT,
+        }
+    }
+}
+
+/// Transport wrapper that bounds every send with the policy's write timeout
+///
+/// A send to a half-dead peer can otherwise block until the TCP send buffer
+/// drains, which may be never. When a send exceeds the timeout it fails with
+/// `ProtocolError::Timeout` and the transport is marked degraded. A timed-out
+/// write may have left a partial packet on the wire, so the flag stays set
+/// until the caller reconnects.
+#[derive(Debug)]
+pub struct TimeoutTransport<T> {
+    /// Wrapped transport
+    inner: T,
+
+    /// Send policy
+    policy: TransportPolicy,
+
+    /// Set once a send has timed out
+    degraded: bool,
+}
+
+impl<T: Transport> TimeoutTransport<T> {
+    /// Wrap a transport with a send policy
+    pub fn new(inner: T, policy: TransportPolicy) -> Self {
+        Self {
+            inner,
+            policy,
+            degraded: false,
+        }
+    }
+
+    /// Get the send policy
+    pub fn policy(&self) -> TransportPolicy {
+        self.policy
+    }
+
+    /// Get a reference to the wrapped transport
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Consume the wrapper and return the wrapped transport
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    fn timed_out(&mut self, what: &str) -> ProtocolError {
+        warn!(
+            "{} to {} timed out after {:?}, marking transport degraded",
+            what,
+            self.inner.remote_address(),
+            self.policy.write_timeout
+        );
+        self.degraded = true;
+        ProtocolError::Timeout
+    }
+}
+
+#[async_trait]
+impl<T: Transport> Transport for TimeoutTransport<T> {
+    fn capabilities(&self) -> TransportCapabilities {
+        self.inner.capabilities()
+    }
+
+    fn remote_address(&self) -> TransportAddress {
+        self.inner.remote_address()
+    }
+
+    async fn send_packet(&mut self, packet: &Packet) -> Result<()> {
+        match tokio::time::timeout(self.policy.write_timeout, self.inner.send_packet(packet)).await {
+            Ok(result) => result,
+            Err(_) => Err(self.timed_out("Send")),
+        }
+    }
+
+    /// The timeout bounds the whole batch, not each packet
+    async fn send_batch(&mut self, packets: &[Packet]) -> Result<()> {
+        match tokio::time::timeout(self.policy.write_timeout, self.inner.send_batch(packets)).await {
+            Ok(result) => result,
+            Err(_) => Err(self.timed_out("Batch send")),
+        }
+    }
+
+    async fn receive_packet(&mut self) -> Result<Packet> {
+        self.inner.receive_packet().await
+    }
+
+    async fn close(self: Box<Self>) -> Result<()> {
+        Box::new(self.inner).close().await
+    }
+
+    fn is_connected(&self) -> bool {
+        self.inner.is_connected()
+    }
+
+    fn is_degraded(&self) -> bool {
+        self.degraded || self.inner.is_degraded()
+    }
+
+    fn network_stats(&self) -> NetworkStats {
+        self.inner.network_stats()
+    }
+}
+
 /// Common transport interface for KDE Connect
 #[async_trait]
 pub trait Transport: Send + Sync + Debug {
@@ -88,6 +403,21 @@ pub trait Transport: Send + Sync + Debug {
     /// or if there's a communication failure.
     async fn send_packet(&mut self, packet: &Packet) -> Result<()>;
 
+    /// Send several packets back to back
+    ///
+    /// Transports that can write a pre-framed buffer should override this
+    /// and write [`encode_batch`] output with a single write and flush. The
+    /// default implementation falls back to sequential [`send_packet`](Self::send_packet)
+    /// calls, stopping at the first failure.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the index of the first packet that was too
+    /// large or failed to send. Packets before that index have been sent.
+    async fn send_batch(&mut self, packets: &[Packet]) -> Result<()> {
+        send_sequential(self, packets).await
+    }
+
     /// Receive a packet
     ///
     /// # Errors
@@ -107,6 +437,24 @@ pub trait Transport: Send + Sync + Debug {
     fn is_connected(&self) -> bool {
         true // Default implementation - override if transport has connection state
     }
+
+    /// Check if the transport has stopped making progress
+    ///
+    /// A degraded transport is still connected but should be replaced,
+    /// for example after a send timed out.
+    fn is_degraded(&self) -> bool {
+        false
+    }
+
+    /// Get estimated round-trip time, jitter and loss
+    ///
+    /// Transports that probe the link (e.g. with keepalive pings) should
+    /// keep a [`NetworkStatsEstimator`](super::NetworkStatsEstimator) and
+    /// report it here, as [`TlsConnection`](crate::crypto::TlsConnection)
+    /// does with ping round trips. The default reports no samples.
+    fn network_stats(&self) -> NetworkStats {
+        NetworkStats::default()
+    }
 }
 
 /// Factory trait for creating transport connections
@@ -143,9 +491,12 @@ impl std::fmt::Display for TransportType {
 }
 
 /// Transport selection preference
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum TransportPreference {
     /// Prefer TCP if available
+    ///
+    /// TCP is generally faster and more reliable, so this is the default.
+    #[default]
     PreferTcp,
 
     /// Prefer Bluetooth if available
@@ -161,16 +512,172 @@ pub enum TransportPreference {
     Only(TransportType),
 }
 
-impl Default for TransportPreference {
-    fn default() -> Self {
-        // TCP is generally faster and more reliable
-        TransportPreference::PreferTcp
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use serde_json::json;
+
+    /// In-memory transport recording each underlying write
+    ///
+    /// When `batched` is false, `send_batch` uses the sequential fallback.
+    #[derive(Debug, Default)]
+    struct MockTransport {
+        writes: Vec<Vec<u8>>,
+        batched: bool,
+    }
+
+    impl MockTransport {
+        const MAX_PACKET_SIZE: usize = 256;
+
+        fn received(&self) -> Vec<Packet> {
+            self.writes
+                .concat()
+                .split(|b| *b == b'\n')
+                .filter(|line| !line.is_empty())
+                .map(|line| Packet::from_bytes(line).unwrap())
+                .collect()
+        }
+    }
+
+    #[async_trait]
+    impl Transport for MockTransport {
+        fn capabilities(&self) -> TransportCapabilities {
+            TransportCapabilities {
+                max_packet_size: Self::MAX_PACKET_SIZE,
+                reliable: true,
+                connection_oriented: true,
+                latency: LatencyCategory::Low,
+            }
+        }
+
+        fn remote_address(&self) -> TransportAddress {
+            TransportAddress::Tcp("127.0.0.1:1816".parse().unwrap())
+        }
+
+        async fn send_packet(&mut self, packet: &Packet) -> Result<()> {
+            self.writes
+                .push(encode_batch(std::slice::from_ref(packet), Self::MAX_PACKET_SIZE)?);
+            Ok(())
+        }
+
+        async fn send_batch(&mut self, packets: &[Packet]) -> Result<()> {
+            if !self.batched {
+                return send_sequential(self, packets).await;
+            }
+            self.writes.push(encode_batch(packets, Self::MAX_PACKET_SIZE)?);
+            Ok(())
+        }
+
+        async fn receive_packet(&mut self) -> Result<Packet> {
+            Err(ProtocolError::Timeout)
+        }
+
+        async fn close(self: Box<Self>) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Transport whose writes never complete, like a peer that stopped reading
+    #[derive(Debug)]
+    struct StalledTransport;
+
+    #[async_trait]
+    impl Transport for StalledTransport {
+        fn capabilities(&self) -> TransportCapabilities {
+            MockTransport::default().capabilities()
+        }
+
+        fn remote_address(&self) -> TransportAddress {
+            MockTransport::default().remote_address()
+        }
+
+        async fn send_packet(&mut self, _packet: &Packet) -> Result<()> {
+            std::future::pending().await
+        }
+
+        async fn receive_packet(&mut self) -> Result<Packet> {
+            std::future::pending().await
+        }
+
+        async fn close(self: Box<Self>) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    fn batch() -> Vec<Packet> {
+        (0..3)
+            .map(|i| Packet::new("cconnect.mpris", json!({ "index": i })))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_send_batch_single_write() {
+        let mut transport = MockTransport {
+            batched: true,
+            ..Default::default()
+        };
+        let packets = batch();
+
+        transport.send_batch(&packets).await.unwrap();
+
+        assert_eq!(transport.writes.len(), 1);
+        let received = transport.received();
+        assert_eq!(received.len(), 3);
+        for (i, packet) in received.iter().enumerate() {
+            assert_eq!(packet.body["index"], i);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_send_batch_reports_failed_packet() {
+        let mut packets = batch();
+        packets.insert(1, Packet::new("cconnect.big", json!({ "data": "x".repeat(512) })));
+
+        // Batched: nothing is written when any packet is oversized
+        let mut transport = MockTransport {
+            batched: true,
+            ..Default::default()
+        };
+        let err = transport.send_batch(&packets).await.unwrap_err();
+        assert!(err.to_string().contains("packet 1"));
+        assert!(transport.writes.is_empty());
+
+        // Sequential fallback: packets before the failure were sent
+        let mut transport = MockTransport::default();
+        let err = transport.send_batch(&packets).await.unwrap_err();
+        assert!(err.to_string().contains("packet 1"));
+        assert_eq!(transport.writes.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_write_timeout_marks_degraded() {
+        let write_timeout = Duration::from_millis(50);
+        let policy = TransportPolicy::default().with_write_timeout(write_timeout);
+        let mut transport = TimeoutTransport::new(StalledTransport, policy);
+        assert!(!transport.is_degraded());
+
+        let start = std::time::Instant::now();
+        let err = transport.send_packet(&batch()[0]).await.unwrap_err();
+        let elapsed = start.elapsed();
+
+        assert!(matches!(err, ProtocolError::Timeout));
+        assert!(elapsed >= write_timeout);
+        assert!(elapsed < write_timeout * 10, "timeout took {:?}", elapsed);
+        assert!(transport.is_degraded());
+
+        let err = transport.send_batch(&batch()).await.unwrap_err();
+        assert!(matches!(err, ProtocolError::Timeout));
+    }
+
+    #[tokio::test]
+    async fn test_write_timeout_passes_through_fast_sends() {
+        let mut transport = TimeoutTransport::new(MockTransport::default(), TransportPolicy::default());
+        transport.send_batch(&batch()).await.unwrap();
+
+        assert!(!transport.is_degraded());
+        assert_eq!(transport.get_ref().received().len(), 3);
+        assert_eq!(transport.policy().write_timeout, DEFAULT_WRITE_TIMEOUT);
+    }
 
     #[test]
     fn test_transport_address_display() {
@@ -184,6 +691,51 @@ mod tests {
         assert_eq!(bt_addr.to_string(), "bluetooth://00:11:22:33:44:55");
     }
 
+    #[test]
+    fn test_transport_address_parse_round_trip() {
+        let cases = [
+            ("192.168.1.5:1716", "tcp://192.168.1.5:1716", TransportType::Tcp),
+            ("[fe80::1]:1716", "tcp://[fe80::1]:1716", TransportType::Tcp),
+            ("tcp://10.0.0.2:1739", "tcp://10.0.0.2:1739", TransportType::Tcp),
+            ("bt:aa:bb:cc:dd:ee:ff", "bluetooth://AA:BB:CC:DD:EE:FF", TransportType::Bluetooth),
+            (
+                "bluetooth://AA:BB:CC:DD:EE:FF (185f3df4-3268-4e3f-9fca-d4d5059915bd)",
+                "bluetooth://AA:BB:CC:DD:EE:FF (185f3df4-3268-4e3f-9fca-d4d5059915bd)",
+                TransportType::Bluetooth,
+            ),
+        ];
+
+        for (input, canonical, transport_type) in cases {
+            let address: TransportAddress = input.parse().unwrap();
+            assert_eq!(address.transport_type(), transport_type, "{}", input);
+            assert_eq!(address.to_string(), canonical);
+            assert_eq!(canonical.parse::<TransportAddress>().unwrap(), address);
+        }
+    }
+
+    #[test]
+    fn test_transport_address_parse_errors() {
+        let cases = [
+            ("", AddressParseError::Empty),
+            ("192.168.1.5", AddressParseError::MissingPort("192.168.1.5".into())),
+            ("192.168.1.5:99999", AddressParseError::InvalidPort("192.168.1.5:99999".into())),
+            ("192.168.1.5:0", AddressParseError::InvalidPort("192.168.1.5:0".into())),
+            ("192.168.1.5:http", AddressParseError::InvalidPort("192.168.1.5:http".into())),
+            ("fe80::1:1716", AddressParseError::InvalidIp("fe80::1:1716".into())),
+            ("phone.local:1716", AddressParseError::InvalidIp("phone.local:1716".into())),
+            ("bt:AA:BB:CC:DD:EE", AddressParseError::InvalidMac("AA:BB:CC:DD:EE".into())),
+            ("bt:AA:BB:CC:DD:EE:GG", AddressParseError::InvalidMac("AA:BB:CC:DD:EE:GG".into())),
+            (
+                "bt:AA:BB:CC:DD:EE:FF (nope)",
+                AddressParseError::InvalidUuid("AA:BB:CC:DD:EE:FF (nope)".into()),
+            ),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(input.parse::<TransportAddress>().unwrap_err(), expected, "{}", input);
+        }
+    }
+
     #[test]
     fn test_transport_type_display() {
         assert_eq!(TransportType::Tcp.to_string(), "TCP");
@@ -2,10 +2,42 @@
 //!
 //! Allows streaming audio between devices (phone ↔ desktop).
 //! Supports codec negotiation and bidirectional audio streaming.
+//!
+//! ## Negotiation
+//!
+//! [`AudioStreamPlugin::negotiate`] picks the format to stream with from
+//! both sides' [`AudioCapability`]:
+//!
+//! - **Codec**: the first shared codec in [`CODEC_PREFERENCE`] (Opus, then
+//!   AAC), falling back to other shared codecs in local order
+//! - **Sample rate**: the highest rate both sides support
+//! - **Channels**: the lower of the two channel limits
+//!
+//! ```rust
+//! use cosmic_ext_connect_core::plugins::audiostream::{AudioCapability, AudioStreamPlugin};
+//!
+//! let desktop = AudioCapability::new(&["aac", "opus"], &[44100, 48000], 2);
+//! let phone = AudioCapability::new(&["opus"], &[48000], 1);
+//!
+//! let format = AudioStreamPlugin::negotiate(&desktop, &phone).unwrap();
+//! assert_eq!(format.codec, "opus");
+//! assert_eq!(format.sample_rate, 48000);
+//! assert_eq!(format.channels, 1);
+//! ```
+//!
+//! ## Volume and Mute
+//!
+//! `cconnect.audiostream.control` packets set the active stream's volume
+//! (`setVolume`, 0.0 to 1.0) or mute it (`setMuted`). Out-of-range volumes
+//! are clamped. The sender applies an [`AudioLevel`] as a gain on the PCM
+//! before encoding, and reports the current level in its status packets
+//! (see [`AudioStreamStatus`]).
 
 use crate::protocol::Packet;
-use crate::error::Result;
-use serde_json::json;
+use crate::error::{ProtocolError, Result};
+use crate::plugins::{RateLimit, MEDIA_RATE_LIMIT};
+use serde_json::{json, Value};
+use tracing::debug;
 
 /// AudioStream status packet type
 pub const PACKET_TYPE_AUDIOSTREAM: &str = "cconnect.audiostream";
@@ -13,6 +45,11 @@ pub const PACKET_TYPE_AUDIOSTREAM: &str = "cconnect.audiostream";
 pub const PACKET_TYPE_AUDIOSTREAM_REQUEST: &str = "cconnect.audiostream.request";
 /// AudioStream capability packet type
 pub const PACKET_TYPE_AUDIOSTREAM_CAPABILITY: &str = "cconnect.audiostream.capability";
+/// AudioStream volume/mute control packet type
+pub const PACKET_TYPE_AUDIOSTREAM_CONTROL: &str = "cconnect.audiostream.control";
+
+/// Outgoing rate limit for this plugin, which streams realtime media
+pub const OUTGOING_RATE_LIMIT: RateLimit = MEDIA_RATE_LIMIT;
 
 /// Create an audio stream status packet
 ///
@@ -39,6 +76,193 @@ pub fn create_audiostream_status(
     })))
 }
 
+/// Create a control packet setting the stream volume
+///
+/// # Arguments
+///
+/// * `volume` - Volume from 0.0 (silent) to 1.0 (full), clamped to that range
+pub fn create_audiostream_set_volume(volume: f32) -> Result<Packet> {
+    Ok(Packet::new(PACKET_TYPE_AUDIOSTREAM_CONTROL, json!({
+        "setVolume": clamp_volume(volume),
+    })))
+}
+
+/// Create a control packet muting or unmuting the stream
+pub fn create_audiostream_set_muted(muted: bool) -> Result<Packet> {
+    Ok(Packet::new(PACKET_TYPE_AUDIOSTREAM_CONTROL, json!({
+        "setMuted": muted,
+    })))
+}
+
+/// Clamp a volume to 0.0..=1.0, treating NaN as silent
+fn clamp_volume(volume: f32) -> f32 {
+    if volume.is_nan() {
+        0.0
+    } else {
+        volume.clamp(0.0, 1.0)
+    }
+}
+
+/// A volume or mute change for the active stream
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AudioControl {
+    /// Set the volume (0.0 to 1.0)
+    SetVolume(f32),
+    /// Mute or unmute
+    SetMuted(bool),
+}
+
+impl AudioControl {
+    /// Parse a control packet, clamping the volume
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::InvalidPacket` if the packet is not a control
+    /// packet or carries neither `setVolume` nor `setMuted`.
+    pub fn from_packet(packet: &Packet) -> Result<Self> {
+        if !packet.is_type(PACKET_TYPE_AUDIOSTREAM_CONTROL) {
+            return Err(ProtocolError::InvalidPacket(format!(
+                "Expected {}, got {}",
+                PACKET_TYPE_AUDIOSTREAM_CONTROL, packet.packet_type
+            )));
+        }
+
+        if let Some(volume) = packet.body["setVolume"].as_f64() {
+            return Ok(Self::SetVolume(clamp_volume(volume as f32)));
+        }
+        if let Some(muted) = packet.body["setMuted"].as_bool() {
+            return Ok(Self::SetMuted(muted));
+        }
+        Err(ProtocolError::InvalidPacket(
+            "Audio control packet has no setVolume or setMuted".to_string(),
+        ))
+    }
+
+    /// Create the control packet
+    pub fn try_to_packet(&self) -> Result<Packet> {
+        match *self {
+            Self::SetVolume(volume) => create_audiostream_set_volume(volume),
+            Self::SetMuted(muted) => create_audiostream_set_muted(muted),
+        }
+    }
+}
+
+/// Volume and mute state of a stream
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AudioLevel {
+    /// Volume from 0.0 to 1.0
+    pub volume: f32,
+    /// Whether the stream is muted
+    pub muted: bool,
+}
+
+impl Default for AudioLevel {
+    fn default() -> Self {
+        Self {
+            volume: 1.0,
+            muted: false,
+        }
+    }
+}
+
+impl AudioLevel {
+    /// Apply a control request
+    pub fn apply(&mut self, control: AudioControl) {
+        match control {
+            AudioControl::SetVolume(volume) => self.volume = clamp_volume(volume),
+            AudioControl::SetMuted(muted) => self.muted = muted,
+        }
+    }
+
+    /// Get the gain to apply to samples (0.0 while muted)
+    pub fn gain(&self) -> f32 {
+        if self.muted {
+            0.0
+        } else {
+            self.volume
+        }
+    }
+
+    /// Scale 16-bit PCM samples by the gain, before encoding
+    pub fn apply_gain(&self, samples: &mut [i16]) {
+        let gain = self.gain();
+        if gain >= 1.0 {
+            return;
+        }
+        for sample in samples {
+            *sample = (*sample as f32 * gain).round() as i16;
+        }
+    }
+}
+
+/// Audio stream status, including the current volume and mute state
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioStreamStatus {
+    /// Whether audio is currently streaming
+    pub is_streaming: bool,
+    /// Active codec
+    pub codec: String,
+    /// Sample rate in Hz
+    pub sample_rate: i32,
+    /// Number of channels
+    pub channels: i32,
+    /// Stream direction
+    pub direction: String,
+    /// Current volume and mute state
+    pub level: AudioLevel,
+}
+
+impl AudioStreamStatus {
+    /// Create the status packet
+    pub fn try_to_packet(&self) -> Result<Packet> {
+        let mut packet = create_audiostream_status(
+            self.is_streaming,
+            &self.codec,
+            self.sample_rate,
+            self.channels,
+            &self.direction,
+        )?;
+        packet.body["volume"] = json!(self.level.volume);
+        packet.body["muted"] = json!(self.level.muted);
+        Ok(packet)
+    }
+
+    /// Parse a status packet
+    ///
+    /// Senders that do not report volume are assumed to be at full volume
+    /// and unmuted.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::InvalidPacket` if the packet is not a status
+    /// packet.
+    pub fn from_packet(packet: &Packet) -> Result<Self> {
+        if !packet.is_type(PACKET_TYPE_AUDIOSTREAM) {
+            return Err(ProtocolError::InvalidPacket(format!(
+                "Expected {}, got {}",
+                PACKET_TYPE_AUDIOSTREAM, packet.packet_type
+            )));
+        }
+
+        let body = &packet.body;
+        let int = |key: &str| body[key].as_i64().and_then(|v| i32::try_from(v).ok()).unwrap_or(0);
+        let defaults = AudioLevel::default();
+        Ok(Self {
+            is_streaming: body["isStreaming"].as_bool().unwrap_or(false),
+            codec: body["codec"].as_str().unwrap_or_default().to_string(),
+            sample_rate: int("sampleRate"),
+            channels: int("channels"),
+            direction: body["direction"].as_str().unwrap_or_default().to_string(),
+            level: AudioLevel {
+                volume: body["volume"]
+                    .as_f64()
+                    .map_or(defaults.volume, |volume| clamp_volume(volume as f32)),
+                muted: body["muted"].as_bool().unwrap_or(defaults.muted),
+            },
+        })
+    }
+}
+
 /// Create an audio stream start request packet
 ///
 /// # Arguments
@@ -93,6 +317,163 @@ pub fn create_audiostream_capability_response(
     })))
 }
 
+/// Audio codecs in negotiation preference order
+pub const CODEC_PREFERENCE: &[&str] = &["opus", "aac"];
+
+/// Audio formats a device supports
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct AudioCapability {
+    /// Supported codec names (e.g. "opus")
+    pub codecs: Vec<String>,
+    /// Supported sample rates in Hz
+    pub sample_rates: Vec<u32>,
+    /// Maximum number of channels
+    pub max_channels: u32,
+}
+
+impl AudioCapability {
+    /// Create a capability from codec names, sample rates and a channel limit
+    pub fn new(codecs: &[&str], sample_rates: &[u32], max_channels: u32) -> Self {
+        Self {
+            codecs: codecs.iter().map(|codec| codec.to_string()).collect(),
+            sample_rates: sample_rates.to_vec(),
+            max_channels,
+        }
+    }
+
+    /// Parse a capability response packet
+    ///
+    /// `supportedCodecs` and `sampleRates` may be JSON arrays or stringified
+    /// arrays, as sent by [`create_audiostream_capability_response`].
+    /// Missing or malformed fields parse as empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::InvalidPacket` if the packet is not a
+    /// capability packet.
+    pub fn from_packet(packet: &Packet) -> Result<Self> {
+        if !packet.is_type(PACKET_TYPE_AUDIOSTREAM_CAPABILITY) {
+            return Err(ProtocolError::InvalidPacket(format!(
+                "Expected {}, got {}",
+                PACKET_TYPE_AUDIOSTREAM_CAPABILITY, packet.packet_type
+            )));
+        }
+
+        Ok(Self {
+            codecs: json_list(&packet.body["supportedCodecs"]),
+            sample_rates: json_list(&packet.body["sampleRates"]),
+            max_channels: packet.body["maxChannels"]
+                .as_u64()
+                .and_then(|channels| u32::try_from(channels).ok())
+                .unwrap_or(0),
+        })
+    }
+
+    /// Check whether a codec is supported (case-insensitive)
+    pub fn supports_codec(&self, codec: &str) -> bool {
+        self.codecs.iter().any(|c| c.eq_ignore_ascii_case(codec))
+    }
+}
+
+/// Parse a JSON array, or a string containing one
+fn json_list<T: serde::de::DeserializeOwned>(value: &Value) -> Vec<T> {
+    let list = match value {
+        Value::String(s) => serde_json::from_str(s).ok(),
+        value => serde_json::from_value(value.clone()).ok(),
+    };
+    list.unwrap_or_default()
+}
+
+/// Audio format both devices agreed to stream with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AudioFormat {
+    /// Codec name, lowercase
+    pub codec: String,
+    /// Sample rate in Hz
+    pub sample_rate: u32,
+    /// Number of channels
+    pub channels: u32,
+}
+
+impl AudioFormat {
+    /// Create a start request packet for this format
+    ///
+    /// # Arguments
+    ///
+    /// * `direction` - Requested stream direction
+    pub fn create_start_request(&self, direction: &str) -> Result<Packet> {
+        create_audiostream_start_request(
+            &self.codec,
+            self.sample_rate as i32,
+            self.channels as i32,
+            direction,
+        )
+    }
+}
+
+/// AudioStream plugin
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AudioStreamPlugin;
+
+impl AudioStreamPlugin {
+    /// Negotiate the format to stream with
+    ///
+    /// Picks the first shared codec in [`CODEC_PREFERENCE`], then any other
+    /// shared codec in `local`'s order; the highest shared sample rate; and
+    /// the lower of the two channel limits.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Plugin` naming the mismatch if the devices
+    /// share no codec or sample rate, or either supports no channels.
+    pub fn negotiate(local: &AudioCapability, remote: &AudioCapability) -> Result<AudioFormat> {
+        let shared = |codec: &str| local.supports_codec(codec) && remote.supports_codec(codec);
+        let codec = CODEC_PREFERENCE
+            .iter()
+            .map(|codec| codec.to_string())
+            .chain(local.codecs.iter().map(|codec| codec.to_lowercase()))
+            .find(|codec| shared(codec))
+            .ok_or_else(|| {
+                ProtocolError::Plugin(format!(
+                    "No common audio codec: local supports [{}], remote supports [{}]",
+                    local.codecs.join(", "),
+                    remote.codecs.join(", ")
+                ))
+            })?;
+
+        let sample_rate = local
+            .sample_rates
+            .iter()
+            .filter(|rate| remote.sample_rates.contains(rate))
+            .max()
+            .copied()
+            .ok_or_else(|| {
+                ProtocolError::Plugin(format!(
+                    "No common audio sample rate: local supports {:?}, remote supports {:?}",
+                    local.sample_rates, remote.sample_rates
+                ))
+            })?;
+
+        let channels = local.max_channels.min(remote.max_channels);
+        if channels == 0 {
+            return Err(ProtocolError::Plugin(format!(
+                "No audio channels: local supports {}, remote supports {}",
+                local.max_channels, remote.max_channels
+            )));
+        }
+
+        debug!(
+            "Negotiated audio format: {} {} Hz, {} channel(s)",
+            codec, sample_rate, channels
+        );
+        Ok(AudioFormat {
+            codec,
+            sample_rate,
+            channels,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -171,4 +552,144 @@ mod tests {
         assert_eq!(packet.body["sampleRates"], "[44100,48000]");
         assert_eq!(packet.body["maxChannels"], 2);
     }
+
+    #[test]
+    fn test_negotiate_clean_overlap() {
+        let local = AudioCapability::new(&["aac", "opus"], &[44100, 48000], 2);
+        let remote = AudioCapability::new(&["AAC", "Opus"], &[48000, 44100, 96000], 2);
+
+        let format = AudioStreamPlugin::negotiate(&local, &remote).unwrap();
+        assert_eq!(
+            format,
+            AudioFormat {
+                codec: "opus".to_string(),
+                sample_rate: 48000,
+                channels: 2,
+            }
+        );
+
+        // AAC when Opus is not shared; other codecs only as a last resort
+        let remote = AudioCapability::new(&["aac", "flac"], &[44100], 2);
+        assert_eq!(AudioStreamPlugin::negotiate(&local, &remote).unwrap().codec, "aac");
+        let local = AudioCapability::new(&["flac"], &[44100], 2);
+        assert_eq!(AudioStreamPlugin::negotiate(&local, &remote).unwrap().codec, "flac");
+    }
+
+    #[test]
+    fn test_negotiate_reconciles_channels() {
+        let local = AudioCapability::new(&["opus"], &[48000], 6);
+        let remote = AudioCapability::new(&["opus"], &[48000], 1);
+
+        let format = AudioStreamPlugin::negotiate(&local, &remote).unwrap();
+        assert_eq!(format.channels, 1);
+        assert_eq!(AudioStreamPlugin::negotiate(&remote, &local).unwrap(), format);
+
+        let packet = format.create_start_request("phone_to_desktop").unwrap();
+        assert_eq!(packet.body["channels"], 1);
+        assert_eq!(packet.body["codec"], "opus");
+    }
+
+    #[test]
+    fn test_negotiate_no_overlap() {
+        let local = AudioCapability::new(&["opus"], &[48000], 2);
+
+        let remote = AudioCapability::new(&["aac"], &[48000], 2);
+        let err = AudioStreamPlugin::negotiate(&local, &remote).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Plugin error: No common audio codec: local supports [opus], remote supports [aac]"
+        );
+
+        let remote = AudioCapability::new(&["opus"], &[44100], 2);
+        let err = AudioStreamPlugin::negotiate(&local, &remote).unwrap_err();
+        assert!(err.to_string().contains("sample rate"), "{}", err);
+
+        let remote = AudioCapability::new(&["opus"], &[48000], 0);
+        assert!(AudioStreamPlugin::negotiate(&local, &remote).is_err());
+    }
+
+    #[test]
+    fn test_capability_from_packet() {
+        let packet = create_audiostream_capability_response(
+            "[\"opus\",\"aac\"]",
+            "[44100,48000]",
+            2
+        ).unwrap();
+        let capability = AudioCapability::from_packet(&packet).unwrap();
+        assert_eq!(capability, AudioCapability::new(&["opus", "aac"], &[44100, 48000], 2));
+
+        let packet = Packet::new(
+            PACKET_TYPE_AUDIOSTREAM_CAPABILITY,
+            json!({ "supportedCodecs": ["opus"], "sampleRates": [48000] }),
+        );
+        let capability = AudioCapability::from_packet(&packet).unwrap();
+        assert_eq!(capability, AudioCapability::new(&["opus"], &[48000], 0));
+
+        assert!(AudioCapability::from_packet(&create_audiostream_stop_request().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_control_packets_clamp_volume() {
+        let packet = create_audiostream_set_volume(0.25).unwrap();
+        assert_eq!(packet.packet_type, "cconnect.audiostream.control");
+        assert_eq!(packet.body["setVolume"], 0.25);
+        assert_eq!(AudioControl::from_packet(&packet).unwrap(), AudioControl::SetVolume(0.25));
+
+        assert_eq!(create_audiostream_set_volume(1.5).unwrap().body["setVolume"], 1.0);
+        assert_eq!(create_audiostream_set_volume(-0.5).unwrap().body["setVolume"], 0.0);
+        assert_eq!(create_audiostream_set_volume(f32::NAN).unwrap().body["setVolume"], 0.0);
+
+        // Out-of-range values from the peer are clamped too
+        let packet = Packet::new(PACKET_TYPE_AUDIOSTREAM_CONTROL, json!({ "setVolume": 3 }));
+        assert_eq!(AudioControl::from_packet(&packet).unwrap(), AudioControl::SetVolume(1.0));
+
+        let packet = AudioControl::SetMuted(true).try_to_packet().unwrap();
+        assert_eq!(packet.body["setMuted"], true);
+        assert_eq!(AudioControl::from_packet(&packet).unwrap(), AudioControl::SetMuted(true));
+
+        let packet = Packet::new(PACKET_TYPE_AUDIOSTREAM_CONTROL, json!({}));
+        assert!(AudioControl::from_packet(&packet).is_err());
+    }
+
+    #[test]
+    fn test_level_gain_applied_to_pcm() {
+        let mut level = AudioLevel::default();
+        let mut samples = [1000i16, -1000, i16::MAX];
+        level.apply_gain(&mut samples);
+        assert_eq!(samples, [1000, -1000, i16::MAX]);
+
+        level.apply(AudioControl::SetVolume(0.5));
+        level.apply_gain(&mut samples);
+        assert_eq!(samples, [500, -500, 16384]);
+
+        level.apply(AudioControl::SetMuted(true));
+        assert_eq!(level.gain(), 0.0);
+        level.apply_gain(&mut samples);
+        assert_eq!(samples, [0, 0, 0]);
+    }
+
+    #[test]
+    fn test_status_round_trip_with_level() {
+        let status = AudioStreamStatus {
+            is_streaming: true,
+            codec: "opus".to_string(),
+            sample_rate: 48000,
+            channels: 2,
+            direction: "phone_to_desktop".to_string(),
+            level: AudioLevel {
+                volume: 0.75,
+                muted: true,
+            },
+        };
+        let packet = status.try_to_packet().unwrap();
+        assert_eq!(packet.body["volume"], 0.75);
+        assert_eq!(packet.body["muted"], true);
+        assert_eq!(AudioStreamStatus::from_packet(&packet).unwrap(), status);
+
+        // Older senders omit the level
+        let packet = create_audiostream_status(true, "aac", 44100, 1, "desktop_to_phone").unwrap();
+        let parsed = AudioStreamStatus::from_packet(&packet).unwrap();
+        assert_eq!(parsed.level, AudioLevel::default());
+        assert_eq!(parsed.sample_rate, 44100);
+    }
 }
@@ -25,15 +25,47 @@
 //! });
 //! # }
 //! ```
+//!
+//! ## Time To Empty
+//!
+//! The plugin keeps recent remote charge samples in a [`DischargeEstimator`]
+//! and estimates the discharge rate with a least-squares fit over them, which
+//! smooths out the 1% granularity of reported levels.
+//! [`BatteryPlugin::time_to_empty`] turns that rate into the time left until
+//! the battery is empty. Charging clears the history, so the estimate starts
+//! over once the device is unplugged.
+//!
+//! ## Multiple Devices
+//!
+//! [`BatteryAggregator`] combines the remote states of several devices'
+//! plugins into one summary for dashboards. Reports age out on their own, or,
+//! with [`BatteryAggregator::evict_absent`], only once a shared
+//! [`PresenceTracker`] has not heard from the device at all.
 
 use crate::error::Result;
-use crate::plugins::Plugin;
-use crate::protocol::Packet;
+use crate::network::presence::PresenceTracker;
+use crate::plugins::{ActionDescriptor, ActionType, Plugin};
+use crate::protocol::{DeviceId, Packet};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
 use tracing::{debug, info, warn};
 
+/// Default time after which a device's battery report is considered stale (10 minutes)
+pub const DEFAULT_BATTERY_STALE_AFTER: Duration = Duration::from_secs(10 * 60);
+
+/// Default age after which charge samples stop counting towards the estimate (1 hour)
+pub const DEFAULT_DISCHARGE_WINDOW: Duration = Duration::from_secs(60 * 60);
+
+/// Samples must span at least this long before a discharge rate is estimated
+pub const MIN_DISCHARGE_SPAN: Duration = Duration::from_secs(5 * 60);
+
+/// Maximum number of charge samples kept
+const MAX_DISCHARGE_SAMPLES: usize = 256;
+
 /// Battery state information
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct BatteryState {
@@ -76,6 +108,107 @@ impl BatteryState {
     }
 }
 
+/// Estimates discharge rate and time to empty from charge samples
+///
+/// Samples older than the window (relative to the newest) are dropped, and
+/// a charging sample clears the history.
+#[derive(Debug, Clone)]
+pub struct DischargeEstimator {
+    /// Charge samples while discharging, oldest first
+    samples: VecDeque<(Instant, i32)>,
+
+    /// How long samples count towards the estimate
+    window: Duration,
+}
+
+impl DischargeEstimator {
+    /// Create an estimator using samples from the last `window`
+    pub fn new(window: Duration) -> Self {
+        Self {
+            samples: VecDeque::new(),
+            window,
+        }
+    }
+
+    /// Record a battery state reported at `at`
+    pub fn record(&mut self, state: &BatteryState, at: Instant) {
+        if state.is_charging {
+            self.samples.clear();
+            return;
+        }
+
+        if self.samples.len() == MAX_DISCHARGE_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((at, state.current_charge));
+
+        let window = self.window;
+        while self
+            .samples
+            .front()
+            .is_some_and(|(sampled, _)| at.saturating_duration_since(*sampled) > window)
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Forget all samples
+    pub fn reset(&mut self) {
+        self.samples.clear();
+    }
+
+    /// Fit a line to the samples, returning (slope in %/s, fitted latest charge)
+    fn fit(&self) -> Option<(f64, f64)> {
+        let (first, _) = *self.samples.front()?;
+        let (last, _) = *self.samples.back()?;
+        if last.saturating_duration_since(first) < MIN_DISCHARGE_SPAN {
+            return None;
+        }
+
+        let points: Vec<(f64, f64)> = self
+            .samples
+            .iter()
+            .map(|(at, charge)| (at.duration_since(first).as_secs_f64(), *charge as f64))
+            .collect();
+        let n = points.len() as f64;
+        let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+        let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+        let covariance: f64 = points.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+        let variance: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+        if variance == 0.0 {
+            return None;
+        }
+
+        let slope = covariance / variance;
+        let latest_x = points[points.len() - 1].0;
+        Some((slope, mean_y + slope * (latest_x - mean_x)))
+    }
+
+    /// Get the estimated discharge rate in percent per hour
+    ///
+    /// `None` until samples span [`MIN_DISCHARGE_SPAN`] or if the charge is
+    /// not falling.
+    pub fn discharge_rate(&self) -> Option<f64> {
+        let (slope, _) = self.fit()?;
+        (slope < 0.0).then(|| -slope * 3600.0)
+    }
+
+    /// Get the estimated time from the latest sample until the battery is empty
+    pub fn time_to_empty(&self) -> Option<Duration> {
+        let (slope, latest) = self.fit()?;
+        if slope >= 0.0 {
+            return None;
+        }
+        Some(Duration::from_secs_f64(latest.max(0.0) / -slope))
+    }
+}
+
+impl Default for DischargeEstimator {
+    fn default() -> Self {
+        Self::new(DEFAULT_DISCHARGE_WINDOW)
+    }
+}
+
 /// Battery plugin for monitoring battery status
 ///
 /// This plugin tracks both local and remote device battery states.
@@ -92,6 +225,9 @@ pub struct BatteryPlugin {
 
     /// Remote device battery state (received via packets)
     remote_battery: Option<BatteryState>,
+
+    /// Remote charge history for the time-to-empty estimate
+    discharge: DischargeEstimator,
 }
 
 impl BatteryPlugin {
@@ -101,6 +237,7 @@ impl BatteryPlugin {
             name: "battery".to_string(),
             local_battery: None,
             remote_battery: None,
+            discharge: DischargeEstimator::default(),
         }
     }
 
@@ -130,6 +267,26 @@ impl BatteryPlugin {
         self.remote_battery.as_ref()
     }
 
+    /// Record a remote battery state reported at `at`
+    ///
+    /// Called for every received `cconnect.battery` packet; exposed so
+    /// platform code can replay reports with their original times.
+    pub fn update_remote_battery_at(&mut self, state: BatteryState, at: Instant) {
+        self.discharge.record(&state, at);
+        self.remote_battery = Some(state);
+    }
+
+    /// Get the estimated time until the remote battery is empty
+    ///
+    /// Measured from the latest report. `None` while charging or until
+    /// enough discharging reports have been received.
+    pub fn time_to_empty(&self) -> Option<Duration> {
+        if self.remote_battery.as_ref()?.is_charging {
+            return None;
+        }
+        self.discharge.time_to_empty()
+    }
+
     /// Create a battery status packet
     ///
     /// Creates a packet containing the current local battery state.
@@ -157,6 +314,159 @@ impl Default for BatteryPlugin {
     }
 }
 
+/// Latest battery state of one device
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceBattery {
+    /// Device ID
+    pub device_id: DeviceId,
+
+    /// Latest reported state
+    pub state: BatteryState,
+}
+
+/// Combines battery states from several devices
+///
+/// Keeps the latest state per device ID and drops devices that have not
+/// reported within the staleness window. Subscribers receive the full
+/// summary (see [`all_states`](Self::all_states)) whenever it changes.
+pub struct BatteryAggregator {
+    /// Latest state and report time by device ID
+    devices: HashMap<DeviceId, (BatteryState, Instant)>,
+
+    /// How long a report stays valid
+    stale_after: Duration,
+
+    /// Summary change notifications
+    summary_tx: watch::Sender<Vec<DeviceBattery>>,
+}
+
+impl BatteryAggregator {
+    /// Create an aggregator with a staleness window
+    pub fn new(stale_after: Duration) -> Self {
+        let (summary_tx, _) = watch::channel(Vec::new());
+        Self {
+            devices: HashMap::new(),
+            stale_after,
+            summary_tx,
+        }
+    }
+
+    /// Subscribe to summary changes
+    pub fn subscribe(&self) -> watch::Receiver<Vec<DeviceBattery>> {
+        self.summary_tx.subscribe()
+    }
+
+    /// Record a device's battery state
+    pub fn update(&mut self, device_id: DeviceId, state: BatteryState) {
+        self.update_at(device_id, state, Instant::now());
+    }
+
+    /// Record a device's battery state reported at `at`
+    pub fn update_at(&mut self, device_id: DeviceId, state: BatteryState, at: Instant) {
+        debug!(
+            "Battery for {}: {}%, charging: {}",
+            device_id, state.current_charge, state.is_charging
+        );
+        self.devices.insert(device_id, (state, at));
+        self.evict_stale(at);
+        self.publish();
+    }
+
+    /// Record the remote state of a device's battery plugin, if known
+    pub fn update_from_plugin(&mut self, device_id: DeviceId, plugin: &BatteryPlugin) {
+        if let Some(state) = plugin.remote_battery() {
+            self.update(device_id, state.clone());
+        }
+    }
+
+    /// Forget a device
+    pub fn remove(&mut self, device_id: &DeviceId) -> bool {
+        let removed = self.devices.remove(device_id).is_some();
+        if removed {
+            self.publish();
+        }
+        removed
+    }
+
+    /// Drop devices whose last report is older than the staleness window
+    ///
+    /// Returns the IDs of evicted devices.
+    pub fn evict_stale(&mut self, now: Instant) -> Vec<DeviceId> {
+        let stale_after = self.stale_after;
+        self.evict_where(|_, reported| now.saturating_duration_since(reported) > stale_after)
+    }
+
+    /// Drop devices `presence` has not heard from within the staleness window
+    ///
+    /// Unlike [`evict_stale`](Self::evict_stale), a device that keeps sending
+    /// other packets keeps its last battery report. Returns the IDs of evicted
+    /// devices.
+    pub fn evict_absent(&mut self, presence: &PresenceTracker, now: Instant) -> Vec<DeviceId> {
+        let stale_after = self.stale_after;
+        self.evict_where(|device_id, _| presence.is_stale_at(device_id, stale_after, now))
+    }
+
+    fn evict_where(&mut self, stale: impl Fn(&DeviceId, Instant) -> bool) -> Vec<DeviceId> {
+        let mut evicted = Vec::new();
+        self.devices.retain(|device_id, (_, reported)| {
+            let fresh = !stale(device_id, *reported);
+            if !fresh {
+                evicted.push(device_id.clone());
+            }
+            fresh
+        });
+
+        if !evicted.is_empty() {
+            info!("Dropped stale battery reports: {:?}", evicted);
+            self.publish();
+        }
+        evicted
+    }
+
+    /// Get all device states, lowest charge first
+    ///
+    /// Ties are ordered by device ID.
+    pub fn all_states(&self) -> Vec<DeviceBattery> {
+        let mut states: Vec<DeviceBattery> = self
+            .devices
+            .iter()
+            .map(|(device_id, (state, _))| DeviceBattery {
+                device_id: device_id.clone(),
+                state: state.clone(),
+            })
+            .collect();
+        states.sort_by(|a, b| {
+            a.state
+                .current_charge
+                .cmp(&b.state.current_charge)
+                .then_with(|| a.device_id.cmp(&b.device_id))
+        });
+        states
+    }
+
+    /// Get the device with the lowest charge
+    pub fn lowest_device(&self) -> Option<DeviceBattery> {
+        self.all_states().into_iter().next()
+    }
+
+    fn publish(&self) {
+        let summary = self.all_states();
+        self.summary_tx.send_if_modified(|current| {
+            if *current == summary {
+                return false;
+            }
+            *current = summary;
+            true
+        });
+    }
+}
+
+impl Default for BatteryAggregator {
+    fn default() -> Self {
+        Self::new(DEFAULT_BATTERY_STALE_AFTER)
+    }
+}
+
 #[async_trait]
 impl Plugin for BatteryPlugin {
     fn name(&self) -> &str {
@@ -174,6 +484,20 @@ impl Plugin for BatteryPlugin {
         vec!["cconnect.battery".to_string()]
     }
 
+    fn coalescing_packet_types(&self) -> Vec<String> {
+        vec!["cconnect.battery".to_string()]
+    }
+
+    fn actions(&self) -> Vec<ActionDescriptor> {
+        vec![
+            ActionDescriptor::new("update_local_battery")
+                .with_parameter("is_charging", ActionType::Bool)
+                .with_parameter("current_charge", ActionType::I32),
+            ActionDescriptor::new("local_battery").with_returns(ActionType::Object),
+            ActionDescriptor::new("remote_battery").with_returns(ActionType::Object),
+        ]
+    }
+
     async fn handle_packet(&mut self, packet: &Packet) -> Result<()> {
         match packet.packet_type.as_str() {
             "cconnect.battery" => {
@@ -199,7 +523,7 @@ impl Plugin for BatteryPlugin {
                     );
                 }
 
-                self.remote_battery = Some(state);
+                self.update_remote_battery_at(state, Instant::now());
             }
 
             "cconnect.battery.request" => {
@@ -235,6 +559,14 @@ mod tests {
     use super::*;
     use serde_json::json;
 
+    const PHONE: &str = "phone_0123456789abcdef0123456789";
+    const TABLET: &str = "tablet_0123456789abcdef0123456789";
+    const EARBUDS: &str = "earbuds_0123456789abcdef0123456789";
+
+    fn id(device_id: &str) -> DeviceId {
+        DeviceId::parse(device_id).unwrap()
+    }
+
     #[test]
     fn test_battery_state_creation() {
         let state = BatteryState::new(true, 85);
@@ -258,6 +590,86 @@ mod tests {
         assert!(state.is_low());
     }
 
+    #[test]
+    fn test_actions() {
+        let actions = BatteryPlugin::new().actions();
+        assert_eq!(
+            actions,
+            vec![
+                ActionDescriptor::new("update_local_battery")
+                    .with_parameter("is_charging", ActionType::Bool)
+                    .with_parameter("current_charge", ActionType::I32),
+                ActionDescriptor::new("local_battery").with_returns(ActionType::Object),
+                ActionDescriptor::new("remote_battery").with_returns(ActionType::Object),
+            ]
+        );
+
+        // Serialized for the FFI layer to enumerate
+        assert_eq!(
+            serde_json::to_value(&actions[0]).unwrap(),
+            json!({
+                "name": "update_local_battery",
+                "parameters": [
+                    { "name": "is_charging", "type": "bool" },
+                    { "name": "current_charge", "type": "i32" },
+                ],
+                "returns": "unit",
+            })
+        );
+    }
+
+    #[test]
+    fn test_time_to_empty_from_declining_series() {
+        let start = Instant::now();
+        let mut plugin = BatteryPlugin::new();
+
+        // 1% every two minutes, reported every 30 seconds
+        for step in 0..=40u64 {
+            let charge = 80 - (step / 4) as i32;
+            plugin.update_remote_battery_at(
+                BatteryState::new(false, charge),
+                start + Duration::from_secs(step * 30),
+            );
+        }
+
+        let rate = plugin.discharge.discharge_rate().unwrap();
+        assert!((rate - 30.0).abs() < 2.0, "rate {}", rate);
+        let remaining = plugin.time_to_empty().unwrap();
+        assert!(
+            remaining > Duration::from_secs(130 * 60) && remaining < Duration::from_secs(150 * 60),
+            "remaining {:?}",
+            remaining
+        );
+    }
+
+    #[test]
+    fn test_time_to_empty_none_while_charging() {
+        let start = Instant::now();
+        let mut plugin = BatteryPlugin::new();
+        assert_eq!(plugin.time_to_empty(), None);
+
+        for minute in 0..10u64 {
+            plugin.update_remote_battery_at(
+                BatteryState::new(false, 50 - minute as i32),
+                start + Duration::from_secs(minute * 60),
+            );
+        }
+        assert!(plugin.time_to_empty().is_some());
+
+        plugin.update_remote_battery_at(
+            BatteryState::new(true, 41),
+            start + Duration::from_secs(10 * 60),
+        );
+        assert_eq!(plugin.time_to_empty(), None);
+
+        // Unplugged again: the old samples no longer count
+        plugin.update_remote_battery_at(
+            BatteryState::new(false, 45),
+            start + Duration::from_secs(20 * 60),
+        );
+        assert_eq!(plugin.time_to_empty(), None);
+    }
+
     #[test]
     fn test_battery_state_charging_not_low() {
         let state = BatteryState::new(true, 10);
@@ -358,6 +770,85 @@ mod tests {
         assert!(is_charging);
     }
 
+    #[test]
+    fn test_aggregator_lowest_device() {
+        let mut aggregator = BatteryAggregator::default();
+        let mut summary = aggregator.subscribe();
+
+        aggregator.update(id(PHONE), BatteryState::new(false, 40));
+        aggregator.update(id(TABLET), BatteryState::new(true, 90));
+        aggregator.update(id(EARBUDS), BatteryState::new(false, 12));
+        assert!(summary.has_changed().unwrap());
+
+        let ids: Vec<_> = aggregator
+            .all_states()
+            .into_iter()
+            .map(|device| device.device_id)
+            .collect();
+        assert_eq!(ids, vec![EARBUDS, PHONE, TABLET]);
+        assert_eq!(summary.borrow_and_update().len(), 3);
+
+        // Earbuds charge past the phone
+        aggregator.update(id(EARBUDS), BatteryState::new(true, 60));
+        assert!(summary.has_changed().unwrap());
+        assert_eq!(aggregator.lowest_device().unwrap().device_id, PHONE);
+    }
+
+    #[tokio::test]
+    async fn test_aggregator_evicts_stale_devices() {
+        let start = Instant::now();
+        let mut aggregator = BatteryAggregator::new(Duration::from_secs(60));
+
+        aggregator.update_at(id(PHONE), BatteryState::new(false, 20), start);
+        aggregator.update_at(id(TABLET), BatteryState::new(false, 50), start);
+        let earbuds = BatteryState::new(false, 70);
+        aggregator.update_at(id(EARBUDS), earbuds, start + Duration::from_secs(30));
+
+        // Reporting later evicts devices silent for over a minute
+        let earbuds = BatteryState::new(false, 65);
+        aggregator.update_at(id(EARBUDS), earbuds, start + Duration::from_secs(90));
+        let remaining: Vec<_> = aggregator.all_states().into_iter().map(|d| d.device_id).collect();
+        assert_eq!(remaining, vec![EARBUDS]);
+
+        assert_eq!(
+            aggregator.evict_stale(start + Duration::from_secs(200)),
+            vec![id(EARBUDS)]
+        );
+        assert!(aggregator.lowest_device().is_none());
+
+        // Plugins feed their remote state
+        let mut plugin = BatteryPlugin::new();
+        let packet = Packet::new(
+            "cconnect.battery",
+            json!({ "isCharging": false, "currentCharge": 33, "thresholdEvent": 0 }),
+        );
+        plugin.handle_packet(&packet).await.unwrap();
+        aggregator.update_from_plugin(id(PHONE), &plugin);
+        assert_eq!(aggregator.lowest_device().unwrap().state.current_charge, 33);
+    }
+
+    #[test]
+    fn test_aggregator_evicts_absent_devices() {
+        let start = Instant::now();
+        let window = Duration::from_secs(60);
+        let mut aggregator = BatteryAggregator::new(window);
+        let mut presence = PresenceTracker::new();
+
+        aggregator.update_at(id(PHONE), BatteryState::new(false, 20), start);
+        aggregator.update_at(id(TABLET), BatteryState::new(false, 50), start);
+        presence.record_at(id(PHONE), start);
+        presence.record_at(id(TABLET), start);
+
+        // The phone keeps sending other packets; the tablet goes quiet
+        let later = start + window + Duration::from_secs(1);
+        presence.record_at(id(PHONE), later);
+        assert!(!presence.is_stale_at(&id(PHONE), window, later));
+        assert!(presence.is_stale_at(&id(TABLET), window, later));
+
+        assert_eq!(aggregator.evict_absent(&presence, later), vec![id(TABLET)]);
+        assert_eq!(aggregator.lowest_device().unwrap().device_id, PHONE);
+    }
+
     #[tokio::test]
     async fn test_lifecycle() {
         let mut plugin = BatteryPlugin::new();
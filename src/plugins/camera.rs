@@ -9,37 +9,187 @@
 //!   - `cconnect.camera.start` - Start camera streaming
 //!   - `cconnect.camera.stop` - Stop camera streaming
 //!   - `cconnect.camera.settings` - Change camera settings
+//!   - `cconnect.camera.flowcontrol` - Pause/resume frame production
+//!   - `cconnect.camera.torch` - Switch the flashlight on/off
+//!   - `cconnect.camera.thumbnail` - Request a preview thumbnail
 //!
 //! - **Android → Desktop**:
 //!   - `cconnect.camera.capability` - Camera capabilities advertisement
 //!   - `cconnect.camera.frame` - Encoded video frame data
 //!   - `cconnect.camera.status` - Streaming status update
+//!   - `cconnect.camera.torch` - Current flashlight state
+//!   - `cconnect.camera.thumbnail` - Preview thumbnail (JPEG payload)
+//!
+//! - **Either direction**:
+//!   - `cconnect.camera.offer` - Media session offer
+//!   - `cconnect.camera.answer` - Media session answer
+//!
+//! ## Multiple Streams
+//!
+//! Several cameras (e.g. front and back) can stream at once. Streams are
+//! keyed by camera ID: status updates carry `cameraId`, and frames carry a
+//! `streamId` naming the camera they belong to. Frames without `streamId`
+//! (single-stream senders) belong to the default stream, the active stream
+//! with the lowest camera ID.
+//!
+//! ## Flow Control
+//!
+//! When the desktop's jitter buffer fills up, it asks the phone to pause the
+//! encoder instead of dropping frames, and to resume once the buffer has
+//! drained. Pause and resume use separate thresholds so that a buffer
+//! hovering around one depth does not toggle the encoder on every frame.
+//!
+//! ## Torch
+//!
+//! The flashlight can be switched without starting a stream, so the phone
+//! can be used as a torch from the desktop. The phone answers with its
+//! current torch state, which is also sent if the torch changes on its own
+//! (e.g. it was switched off on the phone).
+//!
+//! ## Thumbnails
+//!
+//! Before committing to a stream, the desktop can ask for a preview with
+//! [`CameraPlugin::request_thumbnail`]. The phone answers with a
+//! [`CameraThumbnail`] header and a single low-resolution JPEG as payload,
+//! which costs far less than starting a stream. Once the payload has been
+//! read, [`CameraPlugin::receive_thumbnail`] checks it against the header and
+//! keeps it for [`CameraPlugin::thumbnail`].
+//!
+//! ## Decode Recovery
+//!
+//! A corrupted reference frame breaks every frame decoded after it. The
+//! plugin counts consecutive decode failures per stream and escalates when
+//! they persist (see [`DecodeRecoveryPolicy`]):
+//!
+//! 1. After `failure_threshold` failures within `window`, it asks the phone
+//!    for a keyframe with a `requestKeyframe` settings packet
+//! 2. If `reset_threshold` more failures follow, the decoder should be reset
+//!    and the stream re-negotiated with a fresh start packet
+//!
+//! A successfully decoded frame ends the escalation.
+//!
+//! ## Stream Reports
+//!
+//! The plugin counts frames received per stream itself. The desktop
+//! pipeline reports what happens to them next: decode results through
+//! [`CameraPlugin::record_decode_success`] and
+//! [`CameraPlugin::record_decode_failure`], and frames the jitter buffer
+//! released too late or the drop policy discarded through
+//! [`CameraPlugin::record_frame_dropped`]. [`CameraPlugin::stream_report`]
+//! returns the counts together with the fps decoded over the last second.
+//!
+//! ## Downscaling
+//!
+//! On a congested link even the phone's smallest advertised resolution may
+//! be too much. [`CameraStart::with_downscale`] asks the phone to scale its
+//! output to an arbitrary target with the `downscale` flag. A phone that
+//! refuses answers with an error status; [`CameraPlugin::downscale_fallback`]
+//! then builds a start request for the nearest advertised resolution.
+//!
+//! ## Orientation
+//!
+//! Phones often capture in portrait, so frames may need rotating for
+//! display. Status updates and frame headers carry an optional
+//! `orientation` (0, 90, 180 or 270 degrees clockwise). Frames carry it so a
+//! rotation mid-stream reaches the renderer with the next frame;
+//! [`CameraPlugin::orientation`] returns the latest value per stream.
+//!
+//! ## Media Session Negotiation
+//!
+//! To move frames onto a separate media transport, either side can send an
+//! offer [`SessionDescription`] and the other replies with an answer. The
+//! plugin keeps the last offer and answer it received; frames keep flowing
+//! over `cconnect.camera.frame` until a transport is set up.
+//!
+//! ## Capability Cache
+//!
+//! The phone advertises its capabilities on every connection, which delays
+//! the first stream. [`CameraPlugin::save_capabilities`] keeps the last
+//! advertisement in the [`PairedDevices`] store, and
+//! [`CameraPlugin::restore_capabilities`] uses it on reconnect until the
+//! fresh advertisement arrives. If that differs (the phone's hardware
+//! changed), it replaces the cached copy and is reported once by
+//! [`CameraPlugin::take_capability_change`]. Cached capabilities older than
+//! [`DEFAULT_CAPABILITY_CACHE_MAX_AGE`], or the age set with
+//! [`CameraPlugin::with_capability_cache_max_age`], are invalidated instead
+//! of used.
+//!
+//! ## Frame Encryption
+//!
+//! A stream can encrypt its frame payloads on top of TLS, so the bytes stay
+//! opaque to anything that terminates TLS in between. [`CameraStart::with_encryption`]
+//! sets the `encrypt` flag, and [`CameraPlugin::try_create_start_packet`] adds a
+//! fresh X25519 public key as `streamKey`. The phone answers with its own
+//! `streamKey` in [`CameraStatus`], and both devices derive the stream key
+//! from the exchange (see [`StreamKey`]). Each side signs its key with its
+//! device certificate as `streamKeySignature` and checks the other's against
+//! the paired certificate (see [`StreamAuth`]), so a key substituted by
+//! whatever terminates TLS fails authentication. Every payload, including keyframes
+//! and SPS/PPS configuration, is then sealed with ChaCha20-Poly1305 and
+//! carries a 16-byte tag, which [`CameraFrame::size`] includes. Frames whose
+//! sequence number does not advance are rejected as replays.
+//!
+//! ## Unknown Values
+//!
+//! A newer peer may send a [`CameraFacing`], [`FrameType`] or
+//! [`StreamingStatus`] value this version does not know. Such values parse
+//! as the enum's `Unknown` variant instead of failing the packet. The plugin
+//! logs and skips frames and status updates it cannot interpret, and the
+//! stream carries on.
+//!
+//! ## Start Acknowledgment
+//!
+//! A start packet alone does not tell whether the phone started streaming;
+//! it may have crashed or been denied camera permission.
+//! [`CameraPlugin::start_stream`] returns the start packet together with a
+//! [`StartAck`]. Once the packet is sent, [`StartAck::acknowledged`] waits
+//! for a `starting` or `streaming` status for the camera. It fails with
+//! `ProtocolError::StreamRefused` if the phone answers with an `error`
+//! status, and with `ProtocolError::Timeout` if no answer arrives within
+//! [`DEFAULT_START_ACK_TIMEOUT`] (see [`CameraPlugin::with_start_timeout`]).
 //!
 //! ## Example
 //!
 //! ```rust
 //! use cosmic_ext_connect_core::plugins::camera::{CameraPlugin, CameraStart, Resolution};
 //!
-//! # fn example() {
+//! # fn example() -> cosmic_ext_connect_core::error::Result<()> {
 //! let plugin = CameraPlugin::new();
 //!
 //! // Request camera streaming at 720p, 30fps
-//! let start_packet = plugin.create_start_packet(CameraStart {
+//! let start_packet = plugin.try_create_start_packet(CameraStart {
 //!     camera_id: 0,
 //!     resolution: Resolution { width: 1280, height: 720 },
 //!     fps: 30,
 //!     bitrate: 2000,
 //!     codec: "h264".to_string(),
-//! });
+//!     downscale: false,
+//!     encrypt: false,
+//!     stream_key: None,
+//!     stream_key_signature: None,
+//! })?;
+//! # Ok(())
 //! # }
 //! ```
 
-use crate::error::Result;
-use crate::plugins::Plugin;
-use crate::protocol::Packet;
+use crate::crypto::pairing::PairedDevices;
+use crate::crypto::CertificateInfo;
+use crate::error::{ProtocolError, Result};
+use crate::plugins::media_session::SessionDescription;
+use crate::plugins::{Plugin, RateLimit, MEDIA_RATE_LIMIT};
+use crate::protocol::{DeviceId, Packet};
 use async_trait::async_trait;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305, NONCE_LEN};
+use ring::agreement::{agree_ephemeral, EphemeralPrivateKey, UnparsedPublicKey, X25519};
+use ring::hkdf::{KeyType, Salt, HKDF_SHA256};
+use ring::rand::SystemRandom;
+use ring::signature::{self, RsaKeyPair, RSA_PKCS1_2048_8192_SHA256, RSA_PKCS1_SHA256};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::broadcast::{self, error::RecvError};
 use tracing::{debug, info, warn};
 
 // ============================================================================
@@ -64,6 +214,39 @@ pub const PACKET_TYPE_CAMERA_FRAME: &str = "cconnect.camera.frame";
 /// Packet type for camera status update
 pub const PACKET_TYPE_CAMERA_STATUS: &str = "cconnect.camera.status";
 
+/// Packet type for pausing/resuming frame production
+pub const PACKET_TYPE_CAMERA_FLOW_CONTROL: &str = "cconnect.camera.flowcontrol";
+
+/// Packet type for torch requests and torch state reports
+pub const PACKET_TYPE_CAMERA_TORCH: &str = "cconnect.camera.torch";
+
+/// Packet type for thumbnail requests and thumbnail responses
+pub const PACKET_TYPE_CAMERA_THUMBNAIL: &str = "cconnect.camera.thumbnail";
+
+/// Largest thumbnail requested by default (320x240)
+pub const DEFAULT_THUMBNAIL_RESOLUTION: Resolution = Resolution {
+    width: 320,
+    height: 240,
+};
+
+/// Key of cached capabilities in the [`PairedDevices`] store
+pub const CAMERA_CAPABILITY_CACHE_KEY: &str = "camera.capability";
+
+/// Default age after which cached capabilities are not used (7 days)
+pub const DEFAULT_CAPABILITY_CACHE_MAX_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Default time the phone has to acknowledge a start request (5 seconds)
+pub const DEFAULT_START_ACK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Status updates buffered for pending start acknowledgments
+const STATUS_CHANNEL_CAPACITY: usize = 16;
+
+/// Packet type for media session offers
+pub const PACKET_TYPE_CAMERA_OFFER: &str = "cconnect.camera.offer";
+
+/// Packet type for media session answers
+pub const PACKET_TYPE_CAMERA_ANSWER: &str = "cconnect.camera.answer";
+
 // ============================================================================
 // Common Types
 // ============================================================================
@@ -105,20 +288,73 @@ impl Resolution {
 }
 
 /// Camera facing direction
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
 #[serde(rename_all = "lowercase")]
 pub enum CameraFacing {
     /// Front-facing camera (selfie)
     Front,
     /// Back-facing camera (main)
+    #[default]
     Back,
     /// External USB camera
     External,
+    /// Facing sent by a newer peer that this version does not know
+    #[serde(other)]
+    Unknown,
 }
 
-impl Default for CameraFacing {
-    fn default() -> Self {
-        Self::Back
+/// Clockwise rotation to apply to frames for display
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(try_from = "u32", into = "u32")]
+pub enum Orientation {
+    /// Upright
+    #[default]
+    Deg0,
+    /// Rotated a quarter turn clockwise
+    Deg90,
+    /// Upside down
+    Deg180,
+    /// Rotated a quarter turn counter-clockwise
+    Deg270,
+}
+
+impl Orientation {
+    /// Get the rotation in degrees
+    pub fn degrees(&self) -> u32 {
+        match self {
+            Self::Deg0 => 0,
+            Self::Deg90 => 90,
+            Self::Deg180 => 180,
+            Self::Deg270 => 270,
+        }
+    }
+
+    /// Check whether width and height swap when the rotation is applied
+    pub fn swaps_dimensions(&self) -> bool {
+        matches!(self, Self::Deg90 | Self::Deg270)
+    }
+}
+
+impl TryFrom<u32> for Orientation {
+    type Error = ProtocolError;
+
+    fn try_from(degrees: u32) -> Result<Self> {
+        match degrees {
+            0 => Ok(Self::Deg0),
+            90 => Ok(Self::Deg90),
+            180 => Ok(Self::Deg180),
+            270 => Ok(Self::Deg270),
+            other => Err(ProtocolError::InvalidPacket(format!(
+                "Invalid orientation: {} degrees",
+                other
+            ))),
+        }
+    }
+}
+
+impl From<Orientation> for u32 {
+    fn from(orientation: Orientation) -> Self {
+        orientation.degrees()
     }
 }
 
@@ -135,6 +371,9 @@ pub enum FrameType {
     /// P-Frame (delta frame, depends on previous frames)
     #[serde(rename = "pframe")]
     PFrame = 0x03,
+    /// Frame type sent by a newer peer that this version does not know
+    #[serde(rename = "unknown", other)]
+    Unknown = 0xFF,
 }
 
 impl FrameType {
@@ -168,6 +407,9 @@ pub enum StreamingStatus {
     Stopped,
     /// Error occurred
     Error,
+    /// Status sent by a newer peer that this version does not know
+    #[serde(other)]
+    Unknown,
 }
 
 // ============================================================================
@@ -184,10 +426,28 @@ pub struct CameraInfo {
     /// Camera facing direction
     pub facing: CameraFacing,
     /// Maximum supported resolution
-    #[serde(rename = "maxResolution")]
+    #[serde(rename = "maxResolution", alias = "max_resolution")]
     pub max_resolution: Resolution,
     /// Supported resolutions
     pub resolutions: Vec<Resolution>,
+    /// Whether this camera has a flash usable as a torch
+    #[serde(rename = "hasFlash", alias = "has_flash", default)]
+    pub has_flash: bool,
+}
+
+impl CameraInfo {
+    /// Get the advertised resolution closest to `target` in pixel count
+    ///
+    /// Ties go to the smaller resolution. Returns `None` if the camera
+    /// advertises no resolutions.
+    pub fn nearest_resolution(&self, target: Resolution) -> Option<Resolution> {
+        self.resolutions
+            .iter()
+            .copied()
+            .min_by_key(|resolution| {
+                (resolution.pixels().abs_diff(target.pixels()), resolution.pixels())
+            })
+    }
 }
 
 /// Camera capability advertisement (Android → Desktop)
@@ -198,19 +458,19 @@ pub struct CameraCapability {
     /// List of available cameras
     pub cameras: Vec<CameraInfo>,
     /// Supported video codecs (e.g., ["h264", "vp9"])
-    #[serde(rename = "supportedCodecs")]
+    #[serde(rename = "supportedCodecs", alias = "supported_codecs")]
     pub supported_codecs: Vec<String>,
     /// Whether audio streaming is supported
-    #[serde(rename = "audioSupported")]
+    #[serde(rename = "audioSupported", alias = "audio_supported")]
     pub audio_supported: bool,
     /// Maximum total resolution supported
-    #[serde(rename = "maxResolution")]
+    #[serde(rename = "maxResolution", alias = "max_resolution")]
     pub max_resolution: Resolution,
     /// Maximum supported bitrate in kbps
-    #[serde(rename = "maxBitrate")]
+    #[serde(rename = "maxBitrate", alias = "max_bitrate")]
     pub max_bitrate: u32,
     /// Maximum supported frame rate
-    #[serde(rename = "maxFps")]
+    #[serde(rename = "maxFps", alias = "max_fps")]
     pub max_fps: u32,
 }
 
@@ -222,8 +482,51 @@ impl CameraCapability {
     }
 
     /// Create a packet containing this capability info
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Json` if the body cannot be serialized.
+    pub fn try_to_packet(&self) -> Result<Packet> {
+        Packet::try_new(PACKET_TYPE_CAMERA_CAPABILITY, self)
+    }
+
+    /// Create a packet containing this capability info
+    ///
+    /// # Panics
+    ///
+    /// Panics if the body cannot be serialized.
+    #[deprecated(note = "panics if serialization fails; use `try_to_packet`")]
     pub fn to_packet(&self) -> Packet {
-        Packet::new(PACKET_TYPE_CAMERA_CAPABILITY, serde_json::to_value(self).unwrap())
+        self.try_to_packet().expect("camera packet body is serializable")
+    }
+}
+
+/// A video decoder available on the desktop
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DecoderCapability {
+    /// Codec name as advertised in `supportedCodecs` (e.g. "h264")
+    pub codec: String,
+    /// Supported profiles (e.g. ["baseline", "main", "high"])
+    #[serde(default)]
+    pub profiles: Vec<String>,
+    /// Highest supported level (e.g. "4.1")
+    #[serde(
+        rename = "maxLevel",
+        alias = "max_level",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub max_level: Option<String>,
+}
+
+impl DecoderCapability {
+    /// Create a decoder capability with no profile or level restrictions
+    pub fn new(codec: impl Into<String>) -> Self {
+        Self {
+            codec: codec.into(),
+            profiles: Vec::new(),
+            max_level: None,
+        }
     }
 }
 
@@ -231,7 +534,7 @@ impl CameraCapability {
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct CameraStart {
     /// ID of camera to use
-    #[serde(rename = "cameraId")]
+    #[serde(rename = "cameraId", alias = "camera_id")]
     pub camera_id: u32,
     /// Requested resolution
     pub resolution: Resolution,
@@ -241,6 +544,30 @@ pub struct CameraStart {
     pub bitrate: u32,
     /// Video codec to use
     pub codec: String,
+    /// Ask the phone to scale its output to `resolution`, which need not
+    /// be one of the advertised resolutions
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub downscale: bool,
+    /// Encrypt frame payloads with a key agreed through `stream_key`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub encrypt: bool,
+    /// Hex-encoded ephemeral X25519 public key of the desktop, fresh for
+    /// every start
+    #[serde(
+        rename = "streamKey",
+        alias = "stream_key",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub stream_key: Option<String>,
+    /// Hex-encoded signature of `stream_key` by the desktop's certificate
+    #[serde(
+        rename = "streamKeySignature",
+        alias = "stream_key_signature",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub stream_key_signature: Option<String>,
 }
 
 impl CameraStart {
@@ -252,9 +579,28 @@ impl CameraStart {
             fps: 30,
             bitrate: 2000,
             codec: "h264".to_string(),
+            downscale: false,
+            encrypt: false,
+            stream_key: None,
+            stream_key_signature: None,
         }
     }
 
+    /// Builder: ask the phone to downscale its output to `target`
+    pub fn with_downscale(mut self, target: Resolution) -> Self {
+        self.resolution = target;
+        self.downscale = true;
+        self
+    }
+
+    /// Builder: encrypt frame payloads
+    ///
+    /// The stream key is exchanged when the start packet is created.
+    pub fn with_encryption(mut self) -> Self {
+        self.encrypt = true;
+        self
+    }
+
     /// Parse from packet body
     pub fn from_packet(packet: &Packet) -> Result<Self> {
         serde_json::from_value(packet.body.clone())
@@ -262,27 +608,65 @@ impl CameraStart {
     }
 
     /// Create a packet containing this start request
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Json` if the body cannot be serialized.
+    pub fn try_to_packet(&self) -> Result<Packet> {
+        Packet::try_new(PACKET_TYPE_CAMERA_START, self)
+    }
+
+    /// Create a packet containing this start request
+    ///
+    /// # Panics
+    ///
+    /// Panics if the body cannot be serialized.
+    #[deprecated(note = "panics if serialization fails; use `try_to_packet`")]
     pub fn to_packet(&self) -> Packet {
-        Packet::new(PACKET_TYPE_CAMERA_START, serde_json::to_value(self).unwrap())
+        self.try_to_packet().expect("camera packet body is serializable")
     }
 }
 
 /// Request to stop camera streaming (Desktop → Android)
+///
+/// This used to be a unit struct. Code that named the value `CameraStop`
+/// now builds it with [`CameraStop::default()`], which stops all streams.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
-pub struct CameraStop;
+pub struct CameraStop {
+    /// Camera whose stream stops (`None` = all streams)
+    #[serde(
+        rename = "cameraId",
+        alias = "camera_id",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub camera_id: Option<u32>,
+}
 
 impl CameraStop {
-    /// Create a stop packet
+    /// Parse from packet body
+    ///
+    /// An empty, `null` or missing body stops all streams.
+    pub fn from_packet(packet: &Packet) -> Result<Self> {
+        packet.body_or_default()
+    }
+
+    /// Create a stop packet for all streams
     pub fn to_packet() -> Packet {
         Packet::new(PACKET_TYPE_CAMERA_STOP, json!({}))
     }
+
+    /// Create a stop packet for a single camera's stream
+    pub fn for_camera(camera_id: u32) -> Packet {
+        Packet::new(PACKET_TYPE_CAMERA_STOP, json!({ "cameraId": camera_id }))
+    }
 }
 
 /// Request to change camera settings while streaming (Desktop → Android)
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
 pub struct CameraSettings {
     /// Switch to different camera
-    #[serde(rename = "cameraId", skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "cameraId", alias = "camera_id", skip_serializing_if = "Option::is_none")]
     pub camera_id: Option<u32>,
     /// Change resolution
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -299,6 +683,13 @@ pub struct CameraSettings {
     /// Enable/disable autofocus
     #[serde(skip_serializing_if = "Option::is_none")]
     pub autofocus: Option<bool>,
+    /// Ask the encoder to emit a keyframe as soon as possible
+    #[serde(
+        rename = "requestKeyframe",
+        alias = "request_keyframe",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub request_keyframe: Option<bool>,
 }
 
 impl CameraSettings {
@@ -318,205 +709,1778 @@ impl CameraSettings {
         }
     }
 
+    /// Create settings asking a camera's encoder for a keyframe
+    pub fn keyframe_request(camera_id: u32) -> Self {
+        Self {
+            camera_id: Some(camera_id),
+            request_keyframe: Some(true),
+            ..Default::default()
+        }
+    }
+
+    /// Parse from packet body
+    ///
+    /// An empty, `null` or missing body is settings with no changes.
+    pub fn from_packet(packet: &Packet) -> Result<Self> {
+        packet.body_or_default()
+    }
+
+    /// Create a packet containing these settings
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Json` if the body cannot be serialized.
+    pub fn try_to_packet(&self) -> Result<Packet> {
+        Packet::try_new(PACKET_TYPE_CAMERA_SETTINGS, self)
+    }
+
+    /// Create a packet containing these settings
+    ///
+    /// # Panics
+    ///
+    /// Panics if the body cannot be serialized.
+    #[deprecated(note = "panics if serialization fails; use `try_to_packet`")]
+    pub fn to_packet(&self) -> Packet {
+        self.try_to_packet().expect("camera packet body is serializable")
+    }
+}
+
+/// Camera frame header (Android → Desktop)
+///
+/// The actual frame data is sent as payload after the packet.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CameraFrame {
+    /// Type of frame (SPS/PPS, I-frame, P-frame)
+    #[serde(rename = "frameType", alias = "frame_type")]
+    pub frame_type: FrameType,
+    /// Presentation timestamp in microseconds
+    #[serde(rename = "timestampUs", alias = "timestamp_us")]
+    pub timestamp_us: u64,
+    /// Frame sequence number
+    #[serde(rename = "sequenceNumber", alias = "sequence_number")]
+    pub sequence_number: u64,
+    /// Size of frame data in bytes
+    pub size: u64,
+    /// Camera ID of the stream this frame belongs to (`None` = default stream)
+    #[serde(
+        rename = "streamId",
+        alias = "stream_id",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub stream_id: Option<u32>,
+    /// Rotation to apply for display, if the sender reports it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub orientation: Option<Orientation>,
+}
+
+impl CameraFrame {
+    /// Parse from packet body
+    pub fn from_packet(packet: &Packet) -> Result<Self> {
+        serde_json::from_value(packet.body.clone())
+            .map_err(|e| crate::error::ProtocolError::InvalidPacket(e.to_string()))
+    }
+
+    /// Create a packet containing this frame header
+    ///
+    /// Note: The actual frame data is sent as payload
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Json` if the body cannot be serialized, or
+    /// `ProtocolError::InvalidPacket` if `size` does not fit the packet's
+    /// signed payload size.
+    pub fn try_to_packet(&self) -> Result<Packet> {
+        let payload_size = i64::try_from(self.size).map_err(|_| {
+            ProtocolError::InvalidPacket(format!("Frame size {} is too large", self.size))
+        })?;
+        Ok(Packet::try_new(PACKET_TYPE_CAMERA_FRAME, self)?.with_payload_size(payload_size))
+    }
+
+    /// Create a packet containing this frame header
+    ///
+    /// # Panics
+    ///
+    /// Panics if the body cannot be serialized.
+    #[deprecated(note = "panics if serialization fails; use `try_to_packet`")]
+    pub fn to_packet(&self) -> Packet {
+        self.try_to_packet().expect("camera packet body is serializable")
+    }
+}
+
+/// Camera status update (Android → Desktop)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CameraStatus {
+    /// Current streaming status
+    pub status: StreamingStatus,
+    /// Current camera ID
+    #[serde(rename = "cameraId", alias = "camera_id")]
+    pub camera_id: u32,
+    /// Current resolution
+    pub resolution: Resolution,
+    /// Current frame rate
+    pub fps: u32,
+    /// Current bitrate in kbps
+    pub bitrate: u32,
+    /// Error message if status is Error
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Rotation to apply for display, if the sender reports it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub orientation: Option<Orientation>,
+    /// Hex-encoded ephemeral X25519 public key of the phone, answering an
+    /// encrypted start request
+    #[serde(
+        rename = "streamKey",
+        alias = "stream_key",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub stream_key: Option<String>,
+    /// Hex-encoded signature of `stream_key` by the phone's certificate
+    #[serde(
+        rename = "streamKeySignature",
+        alias = "stream_key_signature",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub stream_key_signature: Option<String>,
+}
+
+impl CameraStatus {
+    /// Create a streaming status
+    pub fn streaming(camera_id: u32, resolution: Resolution, fps: u32, bitrate: u32) -> Self {
+        Self {
+            status: StreamingStatus::Streaming,
+            camera_id,
+            resolution,
+            fps,
+            bitrate,
+            error: None,
+            orientation: None,
+            stream_key: None,
+            stream_key_signature: None,
+        }
+    }
+
+    /// Builder: report the rotation to apply for display
+    pub fn with_orientation(mut self, orientation: Orientation) -> Self {
+        self.orientation = Some(orientation);
+        self
+    }
+
+    /// Builder: answer an encrypted start request with the phone's key
+    ///
+    /// `signature` is the key's signature from [`StreamKey::sign`].
+    pub fn with_stream_key(mut self, key: &StreamKey, signature: impl Into<String>) -> Self {
+        self.stream_key = Some(key.public_key());
+        self.stream_key_signature = Some(signature.into());
+        self
+    }
+
+    /// Create a stopped status
+    pub fn stopped() -> Self {
+        Self {
+            status: StreamingStatus::Stopped,
+            camera_id: 0,
+            resolution: Resolution::new(0, 0),
+            fps: 0,
+            bitrate: 0,
+            error: None,
+            orientation: None,
+            stream_key: None,
+            stream_key_signature: None,
+        }
+    }
+
+    /// Create an error status
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            status: StreamingStatus::Error,
+            camera_id: 0,
+            resolution: Resolution::new(0, 0),
+            fps: 0,
+            bitrate: 0,
+            error: Some(message.into()),
+            orientation: None,
+            stream_key: None,
+            stream_key_signature: None,
+        }
+    }
+
+    /// Parse from packet body
+    pub fn from_packet(packet: &Packet) -> Result<Self> {
+        serde_json::from_value(packet.body.clone())
+            .map_err(|e| crate::error::ProtocolError::InvalidPacket(e.to_string()))
+    }
+
+    /// Create a packet containing this status
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Json` if the body cannot be serialized.
+    pub fn try_to_packet(&self) -> Result<Packet> {
+        Packet::try_new(PACKET_TYPE_CAMERA_STATUS, self)
+    }
+
+    /// Create a packet containing this status
+    ///
+    /// # Panics
+    ///
+    /// Panics if the body cannot be serialized.
+    #[deprecated(note = "panics if serialization fails; use `try_to_packet`")]
+    pub fn to_packet(&self) -> Packet {
+        self.try_to_packet().expect("camera packet body is serializable")
+    }
+}
+
+/// Flow control signal
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FlowSignal {
+    /// Pause the encoder
+    Pause,
+    /// Resume the encoder
+    Resume,
+}
+
+/// Flow control request (Desktop → Android)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CameraFlowControl {
+    /// Whether to pause or resume frame production
+    pub signal: FlowSignal,
+    /// Suggested jitter buffer depth to aim for, in frames
+    #[serde(rename = "targetQueueDepth", alias = "target_queue_depth")]
+    pub target_queue_depth: usize,
+}
+
+impl CameraFlowControl {
     /// Parse from packet body
     pub fn from_packet(packet: &Packet) -> Result<Self> {
         serde_json::from_value(packet.body.clone())
             .map_err(|e| crate::error::ProtocolError::InvalidPacket(e.to_string()))
     }
 
-    /// Create a packet containing these settings
-    pub fn to_packet(&self) -> Packet {
-        Packet::new(PACKET_TYPE_CAMERA_SETTINGS, serde_json::to_value(self).unwrap())
+    /// Create a packet from this request
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Json` if the body cannot be serialized.
+    pub fn try_to_packet(&self) -> Result<Packet> {
+        Packet::try_new(PACKET_TYPE_CAMERA_FLOW_CONTROL, self)
+    }
+
+    /// Create a packet from this request
+    ///
+    /// # Panics
+    ///
+    /// Panics if the body cannot be serialized.
+    #[deprecated(note = "panics if serialization fails; use `try_to_packet`")]
+    pub fn to_packet(&self) -> Packet {
+        self.try_to_packet().expect("camera packet body is serializable")
+    }
+}
+
+/// Torch request (Desktop → Android) or torch state (Android → Desktop)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CameraTorch {
+    /// Whether the torch is (or should be) on
+    pub enabled: bool,
+    /// Camera whose flash is used (`None` = phone's choice)
+    #[serde(
+        rename = "cameraId",
+        alias = "camera_id",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub camera_id: Option<u32>,
+}
+
+impl CameraTorch {
+    /// Parse from packet body
+    pub fn from_packet(packet: &Packet) -> Result<Self> {
+        serde_json::from_value(packet.body.clone())
+            .map_err(|e| crate::error::ProtocolError::InvalidPacket(e.to_string()))
+    }
+
+    /// Create a packet from this request or state
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Json` if the body cannot be serialized.
+    pub fn try_to_packet(&self) -> Result<Packet> {
+        Packet::try_new(PACKET_TYPE_CAMERA_TORCH, self)
+    }
+
+    /// Create a packet from this request or state
+    ///
+    /// # Panics
+    ///
+    /// Panics if the body cannot be serialized.
+    #[deprecated(note = "panics if serialization fails; use `try_to_packet`")]
+    pub fn to_packet(&self) -> Packet {
+        self.try_to_packet().expect("camera packet body is serializable")
+    }
+}
+
+/// Thumbnail request (Desktop → Android)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CameraThumbnailRequest {
+    /// Camera to capture the thumbnail with
+    #[serde(rename = "cameraId", alias = "camera_id")]
+    pub camera_id: u32,
+    /// Largest resolution the thumbnail may have
+    #[serde(rename = "maxResolution", alias = "max_resolution")]
+    pub max_resolution: Resolution,
+}
+
+impl CameraThumbnailRequest {
+    /// Request a thumbnail of at most [`DEFAULT_THUMBNAIL_RESOLUTION`]
+    pub fn new(camera_id: u32) -> Self {
+        Self {
+            camera_id,
+            max_resolution: DEFAULT_THUMBNAIL_RESOLUTION,
+        }
+    }
+
+    /// Builder: limit the thumbnail to `max_resolution`
+    pub fn with_max_resolution(mut self, max_resolution: Resolution) -> Self {
+        self.max_resolution = max_resolution;
+        self
+    }
+
+    /// Parse from packet body
+    pub fn from_packet(packet: &Packet) -> Result<Self> {
+        serde_json::from_value(packet.body.clone())
+            .map_err(|e| crate::error::ProtocolError::InvalidPacket(e.to_string()))
+    }
+
+    /// Create a packet from this request
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Json` if the body cannot be serialized.
+    pub fn try_to_packet(&self) -> Result<Packet> {
+        Packet::try_new(PACKET_TYPE_CAMERA_THUMBNAIL, self)
+    }
+}
+
+/// Thumbnail header (Android → Desktop)
+///
+/// The JPEG bytes follow as payload.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CameraThumbnail {
+    /// Camera the thumbnail was captured with
+    #[serde(rename = "cameraId", alias = "camera_id")]
+    pub camera_id: u32,
+    /// Resolution of the JPEG
+    pub resolution: Resolution,
+    /// JPEG size in bytes
+    pub size: u64,
+}
+
+impl CameraThumbnail {
+    /// Parse from a response packet
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::InvalidPacket` if the body is malformed or the
+    /// packet's payload size does not match `size`.
+    pub fn from_packet(packet: &Packet) -> Result<Self> {
+        let thumbnail: Self = serde_json::from_value(packet.body.clone())
+            .map_err(|e| crate::error::ProtocolError::InvalidPacket(e.to_string()))?;
+        if packet.payload_size.and_then(|size| u64::try_from(size).ok()) != Some(thumbnail.size) {
+            return Err(ProtocolError::InvalidPacket(format!(
+                "Thumbnail of {} bytes announced with payload size {:?}",
+                thumbnail.size, packet.payload_size
+            )));
+        }
+        Ok(thumbnail)
+    }
+
+    /// Create a packet containing this thumbnail header
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Json` if the body cannot be serialized, or
+    /// `ProtocolError::InvalidPacket` if `size` does not fit the packet's
+    /// signed payload size.
+    pub fn try_to_packet(&self) -> Result<Packet> {
+        let payload_size = i64::try_from(self.size).map_err(|_| {
+            ProtocolError::InvalidPacket(format!("Thumbnail size {} is too large", self.size))
+        })?;
+        Ok(Packet::try_new(PACKET_TYPE_CAMERA_THUMBNAIL, self)?.with_payload_size(payload_size))
+    }
+}
+
+/// Jitter buffer thresholds for flow control
+///
+/// Frame production is paused when the buffer reaches `pause_depth` and
+/// resumed once it drains to `resume_depth`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlowControlPolicy {
+    /// Buffer depth at which to pause, in frames
+    pub pause_depth: usize,
+    /// Buffer depth at which to resume, in frames
+    pub resume_depth: usize,
+}
+
+impl Default for FlowControlPolicy {
+    fn default() -> Self {
+        // Pause before SmartFrameDropper's default drop threshold (10)
+        // and resume at its target queue size (3)
+        Self {
+            pause_depth: 8,
+            resume_depth: 3,
+        }
+    }
+}
+
+/// Thresholds for recovering a stream from persistent decode failures
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeRecoveryPolicy {
+    /// Consecutive failures within `window` before requesting a keyframe
+    pub failure_threshold: u32,
+    /// Window the failures must fall within
+    pub window: Duration,
+    /// Further failures after the keyframe request before resetting
+    pub reset_threshold: u32,
+}
+
+impl Default for DecodeRecoveryPolicy {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            window: Duration::from_secs(2),
+            reset_threshold: 5,
+        }
+    }
+}
+
+/// Step the caller should take to recover a stream
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecoveryAction {
+    /// Send this keyframe request (a settings packet) to the phone
+    RequestKeyframe(Packet),
+    /// Reset the decoder, then send this start packet to re-negotiate
+    ResetDecoder(Packet),
+}
+
+/// Escalation stage of a stream's decode recovery
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RecoveryStage {
+    /// Decoding normally, or failing below the threshold
+    Normal,
+    /// A keyframe was requested and failures continue
+    KeyframeRequested,
+}
+
+/// Consecutive decode failures of one stream
+#[derive(Debug, Clone)]
+struct DecodeRecovery {
+    /// When each consecutive failure happened, oldest first
+    failures: VecDeque<Instant>,
+    /// Current escalation stage
+    stage: RecoveryStage,
+}
+
+impl DecodeRecovery {
+    fn new() -> Self {
+        Self {
+            failures: VecDeque::new(),
+            stage: RecoveryStage::Normal,
+        }
+    }
+}
+
+// ============================================================================
+// Frame Encryption
+// ============================================================================
+
+/// Length of an X25519 public key
+const STREAM_KEY_LEN: usize = 32;
+
+/// HKDF info prefix for camera stream keys; the camera ID and both public
+/// keys are appended
+const FRAME_KEY_INFO: &[u8] = b"cconnect camera frame";
+
+/// Signed message prefix for stream keys; the camera ID and the public key
+/// are appended
+const STREAM_KEY_SIGNATURE_CONTEXT: &[u8] = b"cconnect camera stream key";
+
+/// ChaCha20-Poly1305 key length for HKDF output
+struct FrameKeyLen;
+
+impl KeyType for FrameKeyLen {
+    fn len(&self) -> usize {
+        CHACHA20_POLY1305.key_len()
+    }
+}
+
+/// One side of an encrypted stream's key exchange
+///
+/// An ephemeral X25519 key pair. The desktop generates one for every
+/// encrypted start request and the phone one for every answer, so each
+/// stream gets a fresh key that does not depend on any long-term secret.
+pub struct StreamKey {
+    private: EphemeralPrivateKey,
+    public: Vec<u8>,
+}
+
+impl StreamKey {
+    /// Generate a fresh key pair
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Other` if key generation fails.
+    pub fn generate() -> Result<Self> {
+        let private = EphemeralPrivateKey::generate(&X25519, &SystemRandom::new())
+            .map_err(|_| ProtocolError::Other("Key generation failed".to_string()))?;
+        let public = private
+            .compute_public_key()
+            .map_err(|_| ProtocolError::Other("Key generation failed".to_string()))?
+            .as_ref()
+            .to_vec();
+        Ok(Self { private, public })
+    }
+
+    /// Hex-encoded public key to send to the peer
+    pub fn public_key(&self) -> String {
+        hex::encode(&self.public)
+    }
+
+    /// Sign the public key for `camera_id` with our certificate
+    ///
+    /// Send the hex-encoded result as `streamKeySignature` alongside
+    /// [`public_key`](Self::public_key).
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Other` if signing fails
+    pub fn sign(&self, camera_id: u32, auth: &StreamAuth) -> Result<String> {
+        auth.sign(camera_id, &self.public)
+    }
+
+    /// Complete the exchange with the peer's signed public key
+    ///
+    /// `peer_signature` must be the peer's signature of `peer_key` for
+    /// `camera_id`, made with the certificate `auth` was created for. The
+    /// stream key is then derived with HKDF-SHA256 from the shared secret,
+    /// the camera ID and both public keys, so both sides derive the same
+    /// cipher whichever of them calls this.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::InvalidPacket` if `peer_key` is malformed or
+    /// its signature is missing or does not verify, or
+    /// `ProtocolError::Other` if key agreement or derivation fails.
+    pub fn agree(
+        self,
+        camera_id: u32,
+        peer_key: &str,
+        peer_signature: Option<&str>,
+        auth: &StreamAuth,
+    ) -> Result<FrameCipher> {
+        let peer = hex::decode(peer_key)
+            .ok()
+            .filter(|key| key.len() == STREAM_KEY_LEN)
+            .ok_or_else(|| {
+                ProtocolError::InvalidPacket("Missing or invalid 'streamKey'".to_string())
+            })?;
+        let peer_signature = peer_signature.ok_or_else(|| {
+            ProtocolError::InvalidPacket("Missing 'streamKeySignature'".to_string())
+        })?;
+        auth.verify(camera_id, &peer, peer_signature)?;
+
+        let (low, high) = if self.public <= peer {
+            (self.public.as_slice(), peer.as_slice())
+        } else {
+            (peer.as_slice(), self.public.as_slice())
+        };
+        let camera_id = camera_id.to_be_bytes();
+        let info = [FRAME_KEY_INFO, &camera_id, low, high];
+
+        let mut raw = [0u8; 32];
+        agree_ephemeral(
+            self.private,
+            &UnparsedPublicKey::new(&X25519, &peer),
+            |shared| {
+                Salt::new(HKDF_SHA256, &[])
+                    .extract(shared)
+                    .expand(&info, FrameKeyLen)
+                    .and_then(|okm| okm.fill(&mut raw))
+            },
+        )
+        .and_then(|derived| derived)
+        .map_err(|_| ProtocolError::Other("Key agreement failed".to_string()))?;
+        let key = UnboundKey::new(&CHACHA20_POLY1305, &raw)
+            .map_err(|_| ProtocolError::Other("Invalid encryption key".to_string()))?;
+        Ok(FrameCipher {
+            key: LessSafeKey::new(key),
+            last_sequence: None,
+        })
+    }
+}
+
+impl std::fmt::Debug for StreamKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StreamKey")
+            .field("public", &self.public_key())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Certificates that authenticate an encrypted stream's key exchange
+///
+/// Holds our device certificate's private key, to sign our [`StreamKey`],
+/// and the public key of the peer's certificate, to check its signature.
+/// Pass the certificate the peer was paired with, i.e. one whose
+/// fingerprint [`PairedDevices::verify`] accepts, so a key exchange relayed
+/// by anything else fails.
+pub struct StreamAuth {
+    signer: RsaKeyPair,
+    peer: Vec<u8>,
+}
+
+impl StreamAuth {
+    /// Create from our certificate and the peer's DER-encoded certificate
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Certificate` if our private key or the peer's
+    /// certificate cannot be parsed
+    pub fn new(ours: &CertificateInfo, peer_certificate: &[u8]) -> Result<Self> {
+        use x509_parser::prelude::*;
+
+        let signer = RsaKeyPair::from_pkcs8(&ours.private_key).map_err(|e| {
+            ProtocolError::Certificate(format!("Unsupported private key: {}", e))
+        })?;
+        let (_, peer) = X509Certificate::from_der(peer_certificate).map_err(|e| {
+            ProtocolError::Certificate(format!("Failed to parse certificate: {}", e))
+        })?;
+        Ok(Self {
+            signer,
+            peer: peer.public_key().subject_public_key.data.to_vec(),
+        })
+    }
+
+    fn message(camera_id: u32, public_key: &[u8]) -> Vec<u8> {
+        [STREAM_KEY_SIGNATURE_CONTEXT, &camera_id.to_be_bytes(), public_key].concat()
+    }
+
+    fn sign(&self, camera_id: u32, public_key: &[u8]) -> Result<String> {
+        let mut signature = vec![0u8; self.signer.public().modulus_len()];
+        self.signer
+            .sign(
+                &RSA_PKCS1_SHA256,
+                &SystemRandom::new(),
+                &Self::message(camera_id, public_key),
+                &mut signature,
+            )
+            .map_err(|_| ProtocolError::Other("Stream key signing failed".to_string()))?;
+        Ok(hex::encode(signature))
+    }
+
+    fn verify(&self, camera_id: u32, public_key: &[u8], signature: &str) -> Result<()> {
+        let signature = hex::decode(signature).unwrap_or_default();
+        signature::UnparsedPublicKey::new(&RSA_PKCS1_2048_8192_SHA256, &self.peer)
+            .verify(&Self::message(camera_id, public_key), &signature)
+            .map_err(|_| {
+                ProtocolError::InvalidPacket("'streamKey' failed authentication".to_string())
+            })
+    }
+}
+
+impl std::fmt::Debug for StreamAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StreamAuth").finish_non_exhaustive()
+    }
+}
+
+/// Key exchange state of an encrypted stream
+#[derive(Debug)]
+enum StreamCrypto {
+    /// Start request sent, waiting for the phone's key
+    Pending(StreamKey),
+    /// Key agreed
+    Established(Box<FrameCipher>),
+    /// The phone's key was rejected; frames are refused until the next start
+    Failed,
+}
+
+/// Encrypts and decrypts one stream's frame payloads
+///
+/// Created by [`StreamKey::agree`]. The nonce is the frame's sequence
+/// number, which is unique within a stream, and the frame type is
+/// authenticated alongside the payload. Sequence numbers must increase, so
+/// [`open`](Self::open) rejects a frame that repeats or precedes the last
+/// one it accepted.
+pub struct FrameCipher {
+    key: LessSafeKey,
+    last_sequence: Option<u64>,
+}
+
+impl FrameCipher {
+    /// Encrypt a frame payload
+    ///
+    /// The result is 16 bytes longer than `payload`; set the frame header's
+    /// `size` to its length.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Other` if sealing fails
+    pub fn seal(&self, frame: &CameraFrame, payload: &[u8]) -> Result<Vec<u8>> {
+        let mut data = payload.to_vec();
+        self.key
+            .seal_in_place_append_tag(Self::nonce(frame), Self::aad(frame), &mut data)
+            .map_err(|_| ProtocolError::Other("Frame encryption failed".to_string()))?;
+        Ok(data)
+    }
+
+    /// Decrypt a frame payload
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::InvalidPacket` if the frame's sequence number
+    /// does not advance past the last accepted frame, or if the payload or
+    /// frame header fails authentication
+    pub fn open(&mut self, frame: &CameraFrame, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        if let Some(last) = self.last_sequence.filter(|&last| frame.sequence_number <= last) {
+            return Err(ProtocolError::InvalidPacket(format!(
+                "Replayed camera frame {} (last accepted {})",
+                frame.sequence_number, last
+            )));
+        }
+        let mut data = ciphertext.to_vec();
+        let len = self
+            .key
+            .open_in_place(Self::nonce(frame), Self::aad(frame), &mut data)
+            .map_err(|_| {
+                ProtocolError::InvalidPacket("Encrypted frame failed authentication".to_string())
+            })?
+            .len();
+        data.truncate(len);
+        // Only authenticated frames advance the window
+        self.last_sequence = Some(frame.sequence_number);
+        Ok(data)
+    }
+
+    fn nonce(frame: &CameraFrame) -> Nonce {
+        let mut nonce = [0u8; NONCE_LEN];
+        nonce[NONCE_LEN - 8..].copy_from_slice(&frame.sequence_number.to_be_bytes());
+        Nonce::assume_unique_for_key(nonce)
+    }
+
+    fn aad(frame: &CameraFrame) -> Aad<[u8; 1]> {
+        Aad::from([frame.frame_type as u8])
+    }
+}
+
+impl std::fmt::Debug for FrameCipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FrameCipher").finish_non_exhaustive()
+    }
+}
+
+// ============================================================================
+// Camera Plugin
+// ============================================================================
+
+/// Window the effective fps of a [`CameraStreamReport`] is measured over
+pub const STREAM_FPS_WINDOW: Duration = Duration::from_secs(1);
+
+/// Why the desktop pipeline discarded a received frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FrameDropReason {
+    /// The jitter buffer released the frame after its presentation time
+    Late,
+    /// The drop policy discarded the frame to relieve backpressure
+    Policy,
+}
+
+/// Frame accounting for one camera's stream
+#[derive(Debug, Clone, PartialEq)]
+pub struct CameraStreamReport {
+    /// Camera the stream belongs to
+    pub camera_id: u32,
+    /// Frames routed to the stream
+    pub frames_received: u64,
+    /// Frames decoded successfully
+    pub frames_decoded: u64,
+    /// Frames the jitter buffer dropped as late
+    pub frames_dropped_late: u64,
+    /// Frames the drop policy discarded under backpressure
+    pub frames_dropped_policy: u64,
+    /// Frames that failed to decode
+    pub decode_errors: u64,
+    /// Frames decoded per second over the last [`STREAM_FPS_WINDOW`]
+    pub fps: f64,
+}
+
+/// State of one camera's stream
+#[derive(Debug, Clone, PartialEq)]
+pub struct CameraStream {
+    /// Latest status reported for this camera
+    pub status: CameraStatus,
+    /// Frames routed to this stream
+    pub frames_received: u64,
+    /// Sequence number of the last routed frame
+    pub last_sequence: Option<u64>,
+    /// Latest rotation reported by a status update or frame
+    pub orientation: Orientation,
+    /// Frames decoded successfully
+    pub frames_decoded: u64,
+    /// Frames the jitter buffer dropped as late
+    pub frames_dropped_late: u64,
+    /// Frames the drop policy discarded under backpressure
+    pub frames_dropped_policy: u64,
+    /// Frames that failed to decode
+    pub decode_errors: u64,
+    /// When frames were decoded within the last fps window
+    decoded_at: VecDeque<Instant>,
+}
+
+impl CameraStream {
+    fn new(status: CameraStatus) -> Self {
+        Self {
+            orientation: status.orientation.unwrap_or_default(),
+            status,
+            frames_received: 0,
+            last_sequence: None,
+            frames_decoded: 0,
+            frames_dropped_late: 0,
+            frames_dropped_policy: 0,
+            decode_errors: 0,
+            decoded_at: VecDeque::new(),
+        }
+    }
+
+    /// Record a decoded frame, forgetting decode times outside the fps window
+    fn record_decoded(&mut self, now: Instant) {
+        self.frames_decoded += 1;
+        self.decoded_at.push_back(now);
+        while self
+            .decoded_at
+            .front()
+            .is_some_and(|&at| now.saturating_duration_since(at) >= STREAM_FPS_WINDOW)
+        {
+            self.decoded_at.pop_front();
+        }
+    }
+
+    /// Get the frame accounting as of `now`
+    fn report(&self, camera_id: u32, now: Instant) -> CameraStreamReport {
+        let recent = self
+            .decoded_at
+            .iter()
+            .filter(|&&at| now.saturating_duration_since(at) < STREAM_FPS_WINDOW)
+            .count();
+
+        CameraStreamReport {
+            camera_id,
+            frames_received: self.frames_received,
+            frames_decoded: self.frames_decoded,
+            frames_dropped_late: self.frames_dropped_late,
+            frames_dropped_policy: self.frames_dropped_policy,
+            decode_errors: self.decode_errors,
+            fps: recent as f64 / STREAM_FPS_WINDOW.as_secs_f64(),
+        }
+    }
+
+    /// Record a reported rotation, returning whether it changed
+    fn update_orientation(&mut self, orientation: Option<Orientation>) -> bool {
+        match orientation {
+            Some(orientation) if orientation != self.orientation => {
+                self.orientation = orientation;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Check whether the stream accepts frames (starting or streaming)
+    pub fn is_active(&self) -> bool {
+        matches!(
+            self.status.status,
+            StreamingStatus::Starting | StreamingStatus::Streaming
+        )
+    }
+}
+
+/// Pending acknowledgment of a start request
+///
+/// Returned by [`CameraPlugin::start_stream`]. Status packets handled by the
+/// plugin after the request was created are seen, so send the start packet
+/// before or after creating it, but await it only once the packet is out.
+#[derive(Debug)]
+pub struct StartAck {
+    /// Camera the start request is for
+    camera_id: u32,
+    /// How long to wait for the phone's answer
+    timeout: Duration,
+    /// Status updates handled by the plugin
+    statuses: broadcast::Receiver<CameraStatus>,
+}
+
+impl StartAck {
+    /// Get the camera the start request is for
+    pub fn camera_id(&self) -> u32 {
+        self.camera_id
+    }
+
+    /// Wait until the phone reports the camera starting or streaming
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::StreamRefused` if the phone reports an error
+    /// for the camera, `ProtocolError::Plugin` if the plugin is dropped, and
+    /// `ProtocolError::Timeout` if the phone does not answer in time.
+    pub async fn acknowledged(mut self) -> Result<()> {
+        let camera_id = self.camera_id;
+        let wait = async {
+            loop {
+                let status = match self.statuses.recv().await {
+                    Ok(status) => status,
+                    Err(RecvError::Lagged(skipped)) => {
+                        debug!("Start acknowledgment skipped {} camera statuses", skipped);
+                        continue;
+                    }
+                    Err(RecvError::Closed) => {
+                        return Err(ProtocolError::Plugin(
+                            "Camera plugin stopped before the stream started".to_string(),
+                        ));
+                    }
+                };
+                if status.camera_id != camera_id {
+                    continue;
+                }
+                match status.status {
+                    StreamingStatus::Starting | StreamingStatus::Streaming => return Ok(()),
+                    StreamingStatus::Error => {
+                        let reason = status.error.unwrap_or_else(|| "unknown error".to_string());
+                        return Err(ProtocolError::StreamRefused { camera_id, reason });
+                    }
+                    _ => {}
+                }
+            }
+        };
+
+        match tokio::time::timeout(self.timeout, wait).await {
+            Ok(result) => result,
+            Err(_) => {
+                warn!("Camera {} did not acknowledge the start request", camera_id);
+                Err(ProtocolError::Timeout)
+            }
+        }
+    }
+}
+
+/// Camera plugin for virtual webcam streaming
+///
+/// Manages camera capability exchange and streaming state between
+/// Android device and COSMIC Desktop.
+pub struct CameraPlugin {
+    /// Plugin name
+    name: String,
+    /// Remote device camera capabilities
+    remote_capabilities: Option<CameraCapability>,
+    /// Whether the capabilities were restored from cache and not yet refreshed
+    capabilities_cached: bool,
+    /// Age after which cached capabilities are not used
+    capability_cache_max_age: Duration,
+    /// Refreshed capabilities that differ from the previous ones, until taken
+    capability_change: Option<CameraCapability>,
+    /// Streams by camera ID
+    streams: BTreeMap<u32, CameraStream>,
+    /// Current camera settings
+    current_settings: Option<CameraStart>,
+    /// Jitter buffer thresholds for flow control
+    flow_policy: FlowControlPolicy,
+    /// Whether frame production is currently paused
+    flow_paused: bool,
+    /// Torch state last reported by the phone
+    torch: Option<CameraTorch>,
+    /// Decode failure thresholds
+    recovery_policy: DecodeRecoveryPolicy,
+    /// Decode recovery state by camera ID
+    recovery: BTreeMap<u32, DecodeRecovery>,
+    /// Last media session offer received
+    remote_offer: Option<SessionDescription>,
+    /// Last media session answer received
+    remote_answer: Option<SessionDescription>,
+    /// Key exchange state of encrypted streams by camera ID
+    ///
+    /// Behind a lock so [`try_create_start_packet`](Self::try_create_start_packet)
+    /// can stay `&self`.
+    stream_keys: Mutex<BTreeMap<u32, StreamCrypto>>,
+    /// Certificates that sign and check stream keys, if encryption is set up
+    stream_auth: Option<StreamAuth>,
+    /// Thumbnail headers whose payload has not been received, by camera ID
+    pending_thumbnails: BTreeMap<u32, CameraThumbnail>,
+    /// Latest received thumbnail JPEGs by camera ID
+    thumbnails: BTreeMap<u32, Vec<u8>>,
+    /// How long the phone has to acknowledge a start request
+    start_timeout: Duration,
+    /// Handled status updates, for pending start acknowledgments
+    status_tx: broadcast::Sender<CameraStatus>,
+}
+
+impl Default for CameraPlugin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CameraPlugin {
+    /// Create a new camera plugin
+    pub fn new() -> Self {
+        Self {
+            name: "camera".to_string(),
+            remote_capabilities: None,
+            capabilities_cached: false,
+            capability_cache_max_age: DEFAULT_CAPABILITY_CACHE_MAX_AGE,
+            capability_change: None,
+            streams: BTreeMap::new(),
+            current_settings: None,
+            flow_policy: FlowControlPolicy::default(),
+            flow_paused: false,
+            torch: None,
+            recovery_policy: DecodeRecoveryPolicy::default(),
+            recovery: BTreeMap::new(),
+            remote_offer: None,
+            remote_answer: None,
+            stream_keys: Mutex::new(BTreeMap::new()),
+            stream_auth: None,
+            pending_thumbnails: BTreeMap::new(),
+            thumbnails: BTreeMap::new(),
+            start_timeout: DEFAULT_START_ACK_TIMEOUT,
+            status_tx: broadcast::channel(STATUS_CHANNEL_CAPACITY).0,
+        }
+    }
+
+    /// Set the certificates that authenticate encrypted streams' keys
+    ///
+    /// Required for [`CameraStart::with_encryption`].
+    pub fn with_stream_auth(mut self, auth: StreamAuth) -> Self {
+        self.stream_auth = Some(auth);
+        self
+    }
+
+    /// Set how long the phone has to acknowledge a start request
+    pub fn with_start_timeout(mut self, timeout: Duration) -> Self {
+        self.start_timeout = timeout;
+        self
+    }
+
+    /// Set the flow control thresholds
+    pub fn with_flow_control(mut self, policy: FlowControlPolicy) -> Self {
+        self.flow_policy = policy;
+        self
+    }
+
+    /// Set the decode failure recovery thresholds
+    pub fn with_recovery_policy(mut self, policy: DecodeRecoveryPolicy) -> Self {
+        self.recovery_policy = policy;
+        self
+    }
+
+    /// Set the age after which cached capabilities are not used
+    pub fn with_capability_cache_max_age(mut self, max_age: Duration) -> Self {
+        self.capability_cache_max_age = max_age;
+        self
+    }
+
+    /// Get remote camera capabilities
+    pub fn capabilities(&self) -> Option<&CameraCapability> {
+        self.remote_capabilities.as_ref()
+    }
+
+    /// Check whether the capabilities are a cached copy awaiting refresh
+    pub fn capabilities_from_cache(&self) -> bool {
+        self.capabilities_cached
+    }
+
+    /// Use a device's cached capabilities until the phone advertises again
+    ///
+    /// Returns `true` if cached capabilities were restored. Capabilities the
+    /// phone already advertised on this connection are never replaced.
+    /// Cached capabilities that are too old or unreadable are invalidated.
+    pub fn restore_capabilities(
+        &mut self,
+        paired: &mut PairedDevices,
+        device_id: &DeviceId,
+    ) -> bool {
+        self.restore_capabilities_at(paired, device_id, SystemTime::now())
+    }
+
+    /// Use a device's cached capabilities, judging their age as of `now`
+    pub fn restore_capabilities_at(
+        &mut self,
+        paired: &mut PairedDevices,
+        device_id: &DeviceId,
+        now: SystemTime,
+    ) -> bool {
+        if self.remote_capabilities.is_some() {
+            return false;
+        }
+        let Some(cached) = paired.cached(device_id, CAMERA_CAPABILITY_CACHE_KEY) else {
+            return false;
+        };
+
+        let age = cached.age_at(now);
+        if age > self.capability_cache_max_age {
+            debug!("Cached camera capabilities of {} are stale ({:?} old)", device_id, age);
+            paired.invalidate(device_id, CAMERA_CAPABILITY_CACHE_KEY);
+            return false;
+        }
+
+        match serde_json::from_value::<CameraCapability>(cached.value.clone()) {
+            Ok(capability) => {
+                debug!(
+                    "Using cached camera capabilities of {}: {} cameras",
+                    device_id,
+                    capability.cameras.len()
+                );
+                self.remote_capabilities = Some(capability);
+                self.capabilities_cached = true;
+                true
+            }
+            Err(e) => {
+                warn!("Discarding unreadable cached camera capabilities: {}", e);
+                paired.invalidate(device_id, CAMERA_CAPABILITY_CACHE_KEY);
+                false
+            }
+        }
+    }
+
+    /// Cache the capabilities the phone advertised for its next connection
+    ///
+    /// Returns `false` without caching if the phone has not advertised
+    /// capabilities on this connection.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::NotPaired` if the device is not paired, or
+    /// `ProtocolError::Json` if the capabilities cannot be serialized.
+    pub fn save_capabilities(
+        &self,
+        paired: &mut PairedDevices,
+        device_id: &DeviceId,
+    ) -> Result<bool> {
+        self.save_capabilities_at(paired, device_id, SystemTime::now())
+    }
+
+    /// Cache the advertised capabilities, stamped with `now`
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::NotPaired` if the device is not paired, or
+    /// `ProtocolError::Json` if the capabilities cannot be serialized.
+    pub fn save_capabilities_at(
+        &self,
+        paired: &mut PairedDevices,
+        device_id: &DeviceId,
+        now: SystemTime,
+    ) -> Result<bool> {
+        let Some(capability) = self.remote_capabilities.as_ref() else {
+            return Ok(false);
+        };
+        if self.capabilities_cached {
+            return Ok(false);
+        }
+        let value = serde_json::to_value(capability)?;
+        paired.cache_at(device_id, CAMERA_CAPABILITY_CACHE_KEY, value, now)?;
+        Ok(true)
+    }
+
+    /// Take capabilities that changed when the phone advertised again
+    pub fn take_capability_change(&mut self) -> Option<CameraCapability> {
+        self.capability_change.take()
+    }
+
+    /// Check if remote device has camera capability
+    pub fn has_camera(&self) -> bool {
+        self.remote_capabilities
+            .as_ref()
+            .map(|c| !c.cameras.is_empty())
+            .unwrap_or(false)
+    }
+
+    /// Get available cameras
+    pub fn cameras(&self) -> Option<&[CameraInfo]> {
+        self.remote_capabilities.as_ref().map(|c| c.cameras.as_slice())
+    }
+
+    /// Check if a camera is currently streaming
+    pub fn is_streaming(&self, camera_id: u32) -> bool {
+        self.streams
+            .get(&camera_id)
+            .map(|stream| stream.status.status == StreamingStatus::Streaming)
+            .unwrap_or(false)
+    }
+
+    /// Check if any camera is currently streaming
+    pub fn is_any_streaming(&self) -> bool {
+        self.streams
+            .values()
+            .any(|stream| stream.status.status == StreamingStatus::Streaming)
+    }
+
+    /// Get the status of every known stream, ordered by camera ID
+    pub fn streaming_status(&self) -> Vec<&CameraStatus> {
+        self.streams.values().map(|stream| &stream.status).collect()
+    }
+
+    /// Get a camera's stream
+    pub fn stream(&self, camera_id: u32) -> Option<&CameraStream> {
+        self.streams.get(&camera_id)
+    }
+
+    /// Get the rotation to apply to a camera's frames for display
+    pub fn orientation(&self, camera_id: u32) -> Option<Orientation> {
+        self.streams.get(&camera_id).map(|stream| stream.orientation)
+    }
+
+    /// Get the camera ID of the stream a frame belongs to
+    ///
+    /// Frames without `streamId` go to the default stream (the active stream
+    /// with the lowest camera ID). Returns `None` if that stream is not active.
+    pub fn route_frame(&self, frame: &CameraFrame) -> Option<u32> {
+        match frame.stream_id {
+            Some(camera_id) => self
+                .streams
+                .get(&camera_id)
+                .filter(|stream| stream.is_active())
+                .map(|_| camera_id),
+            None => self
+                .streams
+                .iter()
+                .find(|(_, stream)| stream.is_active())
+                .map(|(&camera_id, _)| camera_id),
+        }
+    }
+
+    /// Pick the codec to request in [`CameraStart`]
+    ///
+    /// Intersects the desktop's decoders with the phone's `supportedCodecs`.
+    /// `local_decoders` is in order of preference, so the first decoder the
+    /// phone can also encode wins. Codec names are compared case-insensitively
+    /// and the phone's spelling is returned.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Plugin` with a message suitable for the UI if
+    /// no capabilities have been received or no codec is shared.
+    pub fn negotiate_codec(&self, local_decoders: &[DecoderCapability]) -> Result<String> {
+        let capabilities = self.remote_capabilities.as_ref().ok_or_else(|| {
+            ProtocolError::Plugin("Device has not advertised camera capabilities".to_string())
+        })?;
+
+        let offered = &capabilities.supported_codecs;
+        let chosen = local_decoders.iter().find_map(|decoder| {
+            offered
+                .iter()
+                .find(|codec| codec.eq_ignore_ascii_case(&decoder.codec))
+        });
+
+        if let Some(codec) = chosen {
+            debug!("Negotiated camera codec: {}", codec);
+            return Ok(codec.clone());
+        }
+
+        let offered_names = offered
+            .iter()
+            .map(|codec| codec.to_uppercase())
+            .collect::<Vec<_>>();
+        let message = match offered_names.as_slice() {
+            [] => "Device does not offer any video codecs".to_string(),
+            [only] => format!("Device only offers {}, which your desktop can't decode", only),
+            names => format!(
+                "Device only offers {}, none of which your desktop can decode",
+                names.join(", ")
+            ),
+        };
+        Err(ProtocolError::Plugin(message))
+    }
+
+    /// Get current camera settings
+    pub fn current_settings(&self) -> Option<&CameraStart> {
+        self.current_settings.as_ref()
+    }
+
+    /// Create a packet to start camera streaming
+    ///
+    /// If `settings.encrypt` is set, a fresh [`StreamKey`] is generated and
+    /// its public key sent as `streamKey`, signed with the
+    /// [`StreamAuth`] set with [`with_stream_auth`](Self::with_stream_auth);
+    /// the phone's signed key in its next status completes the exchange,
+    /// after which [`open_frame_payload`](Self::open_frame_payload) decrypts
+    /// the stream's frames. Otherwise any previous key for the camera is
+    /// dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Json` if the settings cannot be serialized,
+    /// `ProtocolError::Plugin` if encryption is requested without a
+    /// [`StreamAuth`], or an error from [`StreamKey::generate`] or
+    /// [`StreamKey::sign`].
+    pub fn try_create_start_packet(&self, mut settings: CameraStart) -> Result<Packet> {
+        let key = if settings.encrypt {
+            let auth = self.stream_auth.as_ref().ok_or_else(|| {
+                ProtocolError::Plugin("Encrypted streams need a StreamAuth".to_string())
+            })?;
+            let key = StreamKey::generate()?;
+            settings.stream_key = Some(key.public_key());
+            settings.stream_key_signature = Some(key.sign(settings.camera_id, auth)?);
+            Some(key)
+        } else {
+            settings.stream_key = None;
+            settings.stream_key_signature = None;
+            None
+        };
+        let packet = settings.try_to_packet()?;
+
+        let mut stream_keys = self.stream_keys.lock().unwrap_or_else(PoisonError::into_inner);
+        match key {
+            Some(key) => stream_keys.insert(settings.camera_id, StreamCrypto::Pending(key)),
+            None => stream_keys.remove(&settings.camera_id),
+        };
+        Ok(packet)
+    }
+
+    /// Create a packet to start camera streaming
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`try_create_start_packet`](Self::try_create_start_packet)
+    /// fails.
+    #[deprecated(note = "panics on failure; use `try_create_start_packet`")]
+    pub fn create_start_packet(&self, settings: CameraStart) -> Packet {
+        self.try_create_start_packet(settings)
+            .expect("camera start packet can be created")
+    }
+
+    /// Create a packet to start camera streaming and await its acknowledgment
+    ///
+    /// Send the returned packet, then await [`StartAck::acknowledged`]. See
+    /// the [module docs](self#start-acknowledgment).
+    ///
+    /// This is not an `async fn` resolving on acknowledgment: the plugin does
+    /// not own the connection, so it hands the packet back for the caller to
+    /// send and the acknowledgment is awaited separately.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`try_create_start_packet`](Self::try_create_start_packet).
+    pub fn start_stream(&self, settings: CameraStart) -> Result<(Packet, StartAck)> {
+        let ack = StartAck {
+            camera_id: settings.camera_id,
+            timeout: self.start_timeout,
+            statuses: self.status_tx.subscribe(),
+        };
+        let packet = self.try_create_start_packet(settings)?;
+        Ok((packet, ack))
+    }
+
+    /// Get a frame's payload in the clear
+    ///
+    /// Payloads of encrypted streams are decrypted; others are returned
+    /// unchanged. Frames without `streamId` belong to the default stream.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::InvalidPacket` if the stream's key exchange
+    /// has not completed, or an error from [`FrameCipher::open`].
+    pub fn open_frame_payload(&self, frame: &CameraFrame, payload: &[u8]) -> Result<Vec<u8>> {
+        let Some(camera_id) = frame.stream_id.or_else(|| self.route_frame(frame)) else {
+            return Ok(payload.to_vec());
+        };
+        let mut stream_keys = self.stream_keys.lock().unwrap_or_else(PoisonError::into_inner);
+        match stream_keys.get_mut(&camera_id) {
+            Some(StreamCrypto::Established(cipher)) => cipher.open(frame, payload),
+            Some(StreamCrypto::Pending(_) | StreamCrypto::Failed) => {
+                Err(ProtocolError::InvalidPacket(format!(
+                    "Camera {} frame arrived without an agreed stream key",
+                    camera_id
+                )))
+            }
+            None => Ok(payload.to_vec()),
+        }
+    }
+
+    /// Create a media session offer packet
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Json` if the description cannot be serialized.
+    pub fn create_offer_packet(&self, offer: &SessionDescription) -> Result<Packet> {
+        offer.try_to_packet(PACKET_TYPE_CAMERA_OFFER)
+    }
+
+    /// Create a media session answer packet
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Json` if the description cannot be serialized.
+    pub fn create_answer_packet(&self, answer: &SessionDescription) -> Result<Packet> {
+        answer.try_to_packet(PACKET_TYPE_CAMERA_ANSWER)
+    }
+
+    /// Get the last media session offer received
+    pub fn remote_offer(&self) -> Option<&SessionDescription> {
+        self.remote_offer.as_ref()
+    }
+
+    /// Get the last media session answer received
+    pub fn remote_answer(&self) -> Option<&SessionDescription> {
+        self.remote_answer.as_ref()
+    }
+
+    /// Create a packet to stop all camera streams
+    pub fn create_stop_packet(&self) -> Packet {
+        CameraStop::to_packet()
+    }
+
+    /// Create a packet to stop one camera's stream
+    pub fn create_stop_stream_packet(&self, camera_id: u32) -> Packet {
+        CameraStop::for_camera(camera_id)
     }
-}
 
-/// Camera frame header (Android → Desktop)
-///
-/// The actual frame data is sent as payload after the packet.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-pub struct CameraFrame {
-    /// Type of frame (SPS/PPS, I-frame, P-frame)
-    #[serde(rename = "frameType")]
-    pub frame_type: FrameType,
-    /// Presentation timestamp in microseconds
-    #[serde(rename = "timestampUs")]
-    pub timestamp_us: u64,
-    /// Frame sequence number
-    #[serde(rename = "sequenceNumber")]
-    pub sequence_number: u64,
-    /// Size of frame data in bytes
-    pub size: u64,
-}
+    /// Create a packet to change camera settings
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Json` if the settings cannot be serialized.
+    pub fn try_create_settings_packet(&self, settings: CameraSettings) -> Result<Packet> {
+        settings.try_to_packet()
+    }
 
-impl CameraFrame {
-    /// Parse from packet body
-    pub fn from_packet(packet: &Packet) -> Result<Self> {
-        serde_json::from_value(packet.body.clone())
-            .map_err(|e| crate::error::ProtocolError::InvalidPacket(e.to_string()))
+    /// Create a packet to change camera settings
+    ///
+    /// # Panics
+    ///
+    /// Panics if the settings cannot be serialized.
+    #[deprecated(note = "panics if serialization fails; use `try_create_settings_packet`")]
+    pub fn create_settings_packet(&self, settings: CameraSettings) -> Packet {
+        self.try_create_settings_packet(settings)
+            .expect("camera packet body is serializable")
     }
 
-    /// Create a packet containing this frame header
+    /// Record that a frame of a camera's stream failed to decode
     ///
-    /// Note: The actual frame data is sent as payload
-    pub fn to_packet(&self) -> Packet {
-        Packet::new(PACKET_TYPE_CAMERA_FRAME, serde_json::to_value(self).unwrap())
-            .with_payload_size(self.size as i64)
+    /// See [`Self::record_decode_failure_at`].
+    pub fn record_decode_failure(&mut self, camera_id: u32) -> Result<Option<RecoveryAction>> {
+        self.record_decode_failure_at(camera_id, Instant::now())
     }
-}
 
-/// Camera status update (Android → Desktop)
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-pub struct CameraStatus {
-    /// Current streaming status
-    pub status: StreamingStatus,
-    /// Current camera ID
-    #[serde(rename = "cameraId")]
-    pub camera_id: u32,
-    /// Current resolution
-    pub resolution: Resolution,
-    /// Current frame rate
-    pub fps: u32,
-    /// Current bitrate in kbps
-    pub bitrate: u32,
-    /// Error message if status is Error
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub error: Option<String>,
-}
+    /// Record that a frame of a camera's stream failed to decode at `now`
+    ///
+    /// Returns the recovery step to take once failures cross a threshold of
+    /// the [`DecodeRecoveryPolicy`], and `None` otherwise. After a reset the
+    /// escalation starts over.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Plugin` if the stream must be reset but there
+    /// is nothing to re-negotiate it from (no current settings and no known
+    /// stream for the camera), or `ProtocolError::Json` if a packet cannot
+    /// be serialized.
+    pub fn record_decode_failure_at(
+        &mut self,
+        camera_id: u32,
+        now: Instant,
+    ) -> Result<Option<RecoveryAction>> {
+        if let Some(stream) = self.streams.get_mut(&camera_id) {
+            stream.decode_errors += 1;
+        }
 
-impl CameraStatus {
-    /// Create a streaming status
-    pub fn streaming(camera_id: u32, resolution: Resolution, fps: u32, bitrate: u32) -> Self {
-        Self {
-            status: StreamingStatus::Streaming,
-            camera_id,
-            resolution,
-            fps,
-            bitrate,
-            error: None,
+        let policy = self.recovery_policy;
+        let recovery = self
+            .recovery
+            .entry(camera_id)
+            .or_insert_with(DecodeRecovery::new);
+
+        recovery.failures.push_back(now);
+        while recovery
+            .failures
+            .front()
+            .is_some_and(|&at| now.saturating_duration_since(at) > policy.window)
+        {
+            recovery.failures.pop_front();
+        }
+
+        let failures = recovery.failures.len() as u32;
+        match recovery.stage {
+            RecoveryStage::Normal if failures >= policy.failure_threshold => {
+                warn!(
+                    "Camera {}: {} decode failures, requesting keyframe",
+                    camera_id, failures
+                );
+                recovery.stage = RecoveryStage::KeyframeRequested;
+                recovery.failures.clear();
+                let packet = CameraSettings::keyframe_request(camera_id).try_to_packet()?;
+                Ok(Some(RecoveryAction::RequestKeyframe(packet)))
+            }
+            RecoveryStage::KeyframeRequested if failures >= policy.reset_threshold => {
+                warn!(
+                    "Camera {}: decode still failing after keyframe request, resetting",
+                    camera_id
+                );
+                let start = self.renegotiation_settings(camera_id)?;
+                self.recovery.remove(&camera_id);
+                Ok(Some(RecoveryAction::ResetDecoder(start.try_to_packet()?)))
+            }
+            _ => Ok(None),
         }
     }
 
-    /// Create a stopped status
-    pub fn stopped() -> Self {
-        Self {
-            status: StreamingStatus::Stopped,
-            camera_id: 0,
-            resolution: Resolution::new(0, 0),
-            fps: 0,
-            bitrate: 0,
-            error: None,
+    /// Record that a frame of a camera's stream decoded successfully
+    ///
+    /// Ends any escalation in progress for the stream.
+    pub fn record_decode_success(&mut self, camera_id: u32) {
+        self.record_decode_success_at(camera_id, Instant::now());
+    }
+
+    /// Record that a frame of a camera's stream decoded successfully at `now`
+    pub fn record_decode_success_at(&mut self, camera_id: u32, now: Instant) {
+        if let Some(stream) = self.streams.get_mut(&camera_id) {
+            stream.record_decoded(now);
+        }
+        if self.recovery.remove(&camera_id).is_some() {
+            debug!("Camera {}: decoding recovered", camera_id);
         }
     }
 
-    /// Create an error status
-    pub fn error(message: impl Into<String>) -> Self {
-        Self {
-            status: StreamingStatus::Error,
-            camera_id: 0,
-            resolution: Resolution::new(0, 0),
-            fps: 0,
-            bitrate: 0,
-            error: Some(message.into()),
+    /// Record that the desktop pipeline discarded a frame of a camera's stream
+    pub fn record_frame_dropped(&mut self, camera_id: u32, reason: FrameDropReason) {
+        let Some(stream) = self.streams.get_mut(&camera_id) else {
+            return;
+        };
+        match reason {
+            FrameDropReason::Late => stream.frames_dropped_late += 1,
+            FrameDropReason::Policy => stream.frames_dropped_policy += 1,
         }
     }
 
-    /// Parse from packet body
-    pub fn from_packet(packet: &Packet) -> Result<Self> {
-        serde_json::from_value(packet.body.clone())
-            .map_err(|e| crate::error::ProtocolError::InvalidPacket(e.to_string()))
+    /// Get the frame accounting of a camera's stream
+    ///
+    /// Returns `None` if the camera has no stream.
+    pub fn stream_report(&self, camera_id: u32) -> Option<CameraStreamReport> {
+        self.stream_report_at(camera_id, Instant::now())
     }
 
-    /// Create a packet containing this status
-    pub fn to_packet(&self) -> Packet {
-        Packet::new(PACKET_TYPE_CAMERA_STATUS, serde_json::to_value(self).unwrap())
+    /// Get the frame accounting of a camera's stream as of `now`
+    pub fn stream_report_at(&self, camera_id: u32, now: Instant) -> Option<CameraStreamReport> {
+        self.streams
+            .get(&camera_id)
+            .map(|stream| stream.report(camera_id, now))
     }
-}
 
-// ============================================================================
-// Camera Plugin
-// ============================================================================
+    /// Start settings to re-negotiate a camera's stream with
+    ///
+    /// Prefers the current settings if they are for this camera, and falls
+    /// back to the parameters in the stream's latest status.
+    fn renegotiation_settings(&self, camera_id: u32) -> Result<CameraStart> {
+        if let Some(settings) = self
+            .current_settings
+            .as_ref()
+            .filter(|settings| settings.camera_id == camera_id)
+        {
+            return Ok(settings.clone());
+        }
 
-/// Camera plugin for virtual webcam streaming
-///
-/// Manages camera capability exchange and streaming state between
-/// Android device and COSMIC Desktop.
-pub struct CameraPlugin {
-    /// Plugin name
-    name: String,
-    /// Remote device camera capabilities
-    remote_capabilities: Option<CameraCapability>,
-    /// Current streaming status
-    streaming_status: Option<CameraStatus>,
-    /// Whether we're actively streaming
-    is_streaming: bool,
-    /// Current camera settings
-    current_settings: Option<CameraStart>,
-}
+        let status = self
+            .streams
+            .get(&camera_id)
+            .map(|stream| &stream.status)
+            .ok_or_else(|| {
+                ProtocolError::Plugin(format!(
+                    "No stream to re-negotiate for camera {}",
+                    camera_id
+                ))
+            })?;
+        let codec = self
+            .current_settings
+            .as_ref()
+            .map_or_else(|| "h264".to_string(), |settings| settings.codec.clone());
 
-impl Default for CameraPlugin {
-    fn default() -> Self {
-        Self::new()
+        Ok(CameraStart {
+            camera_id,
+            resolution: status.resolution,
+            fps: status.fps,
+            bitrate: status.bitrate,
+            codec,
+            downscale: false,
+            encrypt: false,
+            stream_key: None,
+            stream_key_signature: None,
+        })
     }
-}
 
-impl CameraPlugin {
-    /// Create a new camera plugin
-    pub fn new() -> Self {
-        Self {
-            name: "camera".to_string(),
-            remote_capabilities: None,
-            streaming_status: None,
-            is_streaming: false,
-            current_settings: None,
-        }
+    /// Replace a refused downscale request with an advertised resolution
+    ///
+    /// Returns `refused` without the `downscale` flag and with the camera's
+    /// advertised resolution nearest to the requested one.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Plugin` if no capabilities have been received,
+    /// the camera is not advertised, or it advertises no resolutions.
+    pub fn downscale_fallback(&self, refused: &CameraStart) -> Result<CameraStart> {
+        let camera = self
+            .cameras()
+            .and_then(|cameras| cameras.iter().find(|camera| camera.id == refused.camera_id))
+            .ok_or_else(|| {
+                ProtocolError::Plugin(format!("Camera {} is not advertised", refused.camera_id))
+            })?;
+        let resolution = camera.nearest_resolution(refused.resolution).ok_or_else(|| {
+            ProtocolError::Plugin(format!("Camera {} advertises no resolutions", camera.id))
+        })?;
+
+        info!(
+            "Camera {}: downscaling to {}x{} refused, falling back to {}x{}",
+            camera.id,
+            refused.resolution.width,
+            refused.resolution.height,
+            resolution.width,
+            resolution.height
+        );
+        Ok(CameraStart {
+            resolution,
+            downscale: false,
+            ..refused.clone()
+        })
     }
 
-    /// Get remote camera capabilities
-    pub fn capabilities(&self) -> Option<&CameraCapability> {
-        self.remote_capabilities.as_ref()
+    /// Create a packet to switch the phone's torch on or off
+    ///
+    /// Works without an active stream. The request names the first camera
+    /// that advertises a flash.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Plugin` if no capabilities have been received
+    /// or no advertised camera has a flash.
+    pub fn set_torch(&self, on: bool) -> Result<Packet> {
+        let camera = self
+            .cameras()
+            .and_then(|cameras| cameras.iter().find(|camera| camera.has_flash))
+            .ok_or_else(|| ProtocolError::Plugin("Device has no camera with a flash".to_string()))?;
+
+        debug!("Requesting torch {} on camera {}", if on { "on" } else { "off" }, camera.id);
+        let request = CameraTorch {
+            enabled: on,
+            camera_id: Some(camera.id),
+        };
+        request.try_to_packet()
     }
 
-    /// Check if remote device has camera capability
-    pub fn has_camera(&self) -> bool {
-        self.remote_capabilities
-            .as_ref()
-            .map(|c| !c.cameras.is_empty())
-            .unwrap_or(false)
+    /// Create a packet requesting a preview thumbnail from a camera
+    ///
+    /// Works without an active stream. The phone answers with a single
+    /// JPEG of at most [`DEFAULT_THUMBNAIL_RESOLUTION`].
+    pub fn request_thumbnail(&self, camera_id: u32) -> Result<Packet> {
+        debug!("Requesting thumbnail from camera {}", camera_id);
+        CameraThumbnailRequest::new(camera_id).try_to_packet()
     }
 
-    /// Get available cameras
-    pub fn cameras(&self) -> Option<&[CameraInfo]> {
-        self.remote_capabilities.as_ref().map(|c| c.cameras.as_slice())
+    /// Get the announced thumbnail whose payload is awaited for a camera
+    pub fn pending_thumbnail(&self, camera_id: u32) -> Option<&CameraThumbnail> {
+        self.pending_thumbnails.get(&camera_id)
     }
 
-    /// Check if currently streaming
-    pub fn is_streaming(&self) -> bool {
-        self.is_streaming
+    /// Accept the payload of a camera's announced thumbnail
+    ///
+    /// Returns the JPEG bytes, which are also kept for
+    /// [`thumbnail`](Self::thumbnail).
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Plugin` if no thumbnail was announced for the
+    /// camera, or `ProtocolError::InvalidPacket` if the bytes do not have
+    /// the announced size or are not a JPEG. The announcement is consumed
+    /// either way.
+    pub fn receive_thumbnail(&mut self, camera_id: u32, jpeg: Vec<u8>) -> Result<&[u8]> {
+        let header = self.pending_thumbnails.remove(&camera_id).ok_or_else(|| {
+            ProtocolError::Plugin(format!("No thumbnail announced for camera {}", camera_id))
+        })?;
+
+        if jpeg.len() as u64 != header.size {
+            return Err(ProtocolError::InvalidPacket(format!(
+                "Thumbnail has {} bytes, {} announced",
+                jpeg.len(),
+                header.size
+            )));
+        }
+        if !jpeg.starts_with(&[0xFF, 0xD8]) {
+            return Err(ProtocolError::InvalidPacket(
+                "Thumbnail is not a JPEG".to_string(),
+            ));
+        }
+
+        let slot = self.thumbnails.entry(camera_id).or_default();
+        *slot = jpeg;
+        Ok(slot)
     }
 
-    /// Get current streaming status
-    pub fn streaming_status(&self) -> Option<&CameraStatus> {
-        self.streaming_status.as_ref()
+    /// Get the latest thumbnail JPEG received from a camera
+    pub fn thumbnail(&self, camera_id: u32) -> Option<&[u8]> {
+        self.thumbnails.get(&camera_id).map(Vec::as_slice)
     }
 
-    /// Get current camera settings
-    pub fn current_settings(&self) -> Option<&CameraStart> {
-        self.current_settings.as_ref()
+    /// Check whether the phone last reported its torch as on
+    pub fn is_torch_on(&self) -> bool {
+        self.torch.as_ref().is_some_and(|torch| torch.enabled)
     }
 
-    /// Create a packet to start camera streaming
-    pub fn create_start_packet(&self, settings: CameraStart) -> Packet {
-        settings.to_packet()
+    /// Get the torch state last reported by the phone
+    pub fn torch_state(&self) -> Option<&CameraTorch> {
+        self.torch.as_ref()
     }
 
-    /// Create a packet to stop camera streaming
-    pub fn create_stop_packet(&self) -> Packet {
-        CameraStop::to_packet()
+    /// Check whether frame production has been paused by flow control
+    pub fn is_flow_paused(&self) -> bool {
+        self.flow_paused
     }
 
-    /// Create a packet to change camera settings
-    pub fn create_settings_packet(&self, settings: CameraSettings) -> Packet {
-        settings.to_packet()
+    /// Get the flow control packet to send for the current jitter buffer depth
+    ///
+    /// Returns a pause packet when the buffer reaches the pause threshold, a
+    /// resume packet once it drains to the resume threshold, and `None`
+    /// otherwise, including while already paused or resumed.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Json` if the request cannot be serialized.
+    pub fn try_flow_control_signal(&mut self, buffer_depth: usize) -> Result<Option<Packet>> {
+        let signal = if !self.flow_paused && buffer_depth >= self.flow_policy.pause_depth {
+            FlowSignal::Pause
+        } else if self.flow_paused && buffer_depth <= self.flow_policy.resume_depth {
+            FlowSignal::Resume
+        } else {
+            return Ok(None);
+        };
+
+        let request = CameraFlowControl {
+            signal,
+            target_queue_depth: self.flow_policy.resume_depth,
+        };
+        let packet = request.try_to_packet()?;
+
+        self.flow_paused = signal == FlowSignal::Pause;
+        debug!("Camera flow control: {:?} at buffer depth {}", signal, buffer_depth);
+        Ok(Some(packet))
+    }
+
+    /// Get the flow control packet to send for the current jitter buffer depth
+    ///
+    /// # Panics
+    ///
+    /// Panics if the request cannot be serialized.
+    #[deprecated(note = "panics if serialization fails; use `try_flow_control_signal`")]
+    pub fn flow_control_signal(&mut self, buffer_depth: usize) -> Option<Packet> {
+        self.try_flow_control_signal(buffer_depth)
+            .expect("camera packet body is serializable")
     }
 
     /// Handle incoming camera capability packet
@@ -527,7 +2491,16 @@ impl CameraPlugin {
             capability.cameras.len(),
             capability.supported_codecs
         );
+        if self
+            .remote_capabilities
+            .as_ref()
+            .is_some_and(|previous| *previous != capability)
+        {
+            info!("Camera capabilities changed");
+            self.capability_change = Some(capability.clone());
+        }
         self.remote_capabilities = Some(capability);
+        self.capabilities_cached = false;
         Ok(())
     }
 
@@ -535,12 +2508,121 @@ impl CameraPlugin {
     fn handle_status(&mut self, packet: &Packet) -> Result<()> {
         let status = CameraStatus::from_packet(packet)?;
         debug!(
-            "Camera status: {:?}, {}x{} @ {}fps",
-            status.status, status.resolution.width, status.resolution.height, status.fps
+            "Camera {} status: {:?}, {}x{} @ {}fps",
+            status.camera_id,
+            status.status,
+            status.resolution.width,
+            status.resolution.height,
+            status.fps
         );
 
-        self.is_streaming = matches!(status.status, StreamingStatus::Streaming);
-        self.streaming_status = Some(status);
+        if status.status == StreamingStatus::Unknown {
+            warn!(
+                "Skipping camera {} status with unknown streaming status",
+                status.camera_id
+            );
+            return Ok(());
+        }
+
+        if let Some(peer_key) = &status.stream_key {
+            let signature = status.stream_key_signature.as_deref();
+            self.complete_key_exchange(status.camera_id, peer_key, signature)?;
+        }
+
+        // Only pending start acknowledgments listen
+        let _ = self.status_tx.send(status.clone());
+
+        if status.status == StreamingStatus::Stopped {
+            self.streams.remove(&status.camera_id);
+            if self.streams.is_empty() {
+                // A new stream starts unpaused on the phone
+                self.flow_paused = false;
+            }
+            return Ok(());
+        }
+
+        match self.streams.get_mut(&status.camera_id) {
+            Some(stream) => {
+                if stream.update_orientation(status.orientation) {
+                    info!(
+                        "Camera {} orientation: {}°",
+                        status.camera_id,
+                        stream.orientation.degrees()
+                    );
+                }
+                stream.status = status;
+            }
+            None => {
+                self.streams.insert(status.camera_id, CameraStream::new(status));
+            }
+        }
+        Ok(())
+    }
+
+    /// Derive a stream's frame cipher from the phone's answering key
+    fn complete_key_exchange(
+        &mut self,
+        camera_id: u32,
+        peer_key: &str,
+        signature: Option<&str>,
+    ) -> Result<()> {
+        let stream_keys = self
+            .stream_keys
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner);
+        match stream_keys.remove(&camera_id) {
+            Some(StreamCrypto::Pending(key)) => match self
+                .stream_auth
+                .as_ref()
+                .ok_or_else(|| {
+                    ProtocolError::Plugin("Encrypted streams need a StreamAuth".to_string())
+                })
+                .and_then(|auth| key.agree(camera_id, peer_key, signature, auth))
+            {
+                Ok(cipher) => {
+                    debug!("Camera {} stream key agreed", camera_id);
+                    stream_keys.insert(camera_id, StreamCrypto::Established(Box::new(cipher)));
+                }
+                Err(e) => {
+                    // Frames must not fall back to plaintext
+                    stream_keys.insert(camera_id, StreamCrypto::Failed);
+                    return Err(e);
+                }
+            },
+            // Later statuses may repeat the key; the first one counts
+            Some(state) => {
+                stream_keys.insert(camera_id, state);
+            }
+            None => warn!(
+                "Camera {} sent a stream key for an unencrypted stream",
+                camera_id
+            ),
+        }
+        Ok(())
+    }
+
+    /// Handle incoming torch state packet
+    fn handle_torch(&mut self, packet: &Packet) -> Result<()> {
+        let torch = CameraTorch::from_packet(packet)?;
+        info!("Camera torch is {}", if torch.enabled { "on" } else { "off" });
+        self.torch = Some(torch);
+        Ok(())
+    }
+
+    /// Handle incoming thumbnail header
+    ///
+    /// The JPEG payload is handed over with
+    /// [`receive_thumbnail`](Self::receive_thumbnail).
+    fn handle_thumbnail(&mut self, packet: &Packet) -> Result<()> {
+        let thumbnail = CameraThumbnail::from_packet(packet)?;
+        debug!(
+            "Camera {} thumbnail: {}x{}, {} bytes",
+            thumbnail.camera_id,
+            thumbnail.resolution.width,
+            thumbnail.resolution.height,
+            thumbnail.size
+        );
+        self.pending_thumbnails.insert(thumbnail.camera_id, thumbnail);
         Ok(())
     }
 
@@ -548,9 +2630,34 @@ impl CameraPlugin {
     fn handle_frame(&mut self, packet: &Packet) -> Result<CameraFrame> {
         let frame = CameraFrame::from_packet(packet)?;
         debug!(
-            "Camera frame: {:?}, seq={}, size={}",
-            frame.frame_type, frame.sequence_number, frame.size
+            "Camera frame: {:?}, stream={:?}, seq={}, size={}",
+            frame.frame_type, frame.stream_id, frame.sequence_number, frame.size
         );
+
+        if frame.frame_type == FrameType::Unknown {
+            warn!(
+                "Skipping camera frame {} with unknown frame type",
+                frame.sequence_number
+            );
+            return Ok(frame);
+        }
+
+        match self.route_frame(&frame) {
+            Some(camera_id) => {
+                if let Some(stream) = self.streams.get_mut(&camera_id) {
+                    stream.frames_received += 1;
+                    stream.last_sequence = Some(frame.sequence_number);
+                    if stream.update_orientation(frame.orientation) {
+                        info!(
+                            "Camera {} orientation: {}°",
+                            camera_id,
+                            stream.orientation.degrees()
+                        );
+                    }
+                }
+            }
+            None => debug!("Dropping frame for inactive stream {:?}", frame.stream_id),
+        }
         Ok(frame)
     }
 }
@@ -566,6 +2673,10 @@ impl Plugin for CameraPlugin {
             PACKET_TYPE_CAMERA_CAPABILITY.to_string(),
             PACKET_TYPE_CAMERA_FRAME.to_string(),
             PACKET_TYPE_CAMERA_STATUS.to_string(),
+            PACKET_TYPE_CAMERA_TORCH.to_string(),
+            PACKET_TYPE_CAMERA_THUMBNAIL.to_string(),
+            PACKET_TYPE_CAMERA_OFFER.to_string(),
+            PACKET_TYPE_CAMERA_ANSWER.to_string(),
         ]
     }
 
@@ -574,9 +2685,18 @@ impl Plugin for CameraPlugin {
             PACKET_TYPE_CAMERA_START.to_string(),
             PACKET_TYPE_CAMERA_STOP.to_string(),
             PACKET_TYPE_CAMERA_SETTINGS.to_string(),
+            PACKET_TYPE_CAMERA_FLOW_CONTROL.to_string(),
+            PACKET_TYPE_CAMERA_TORCH.to_string(),
+            PACKET_TYPE_CAMERA_THUMBNAIL.to_string(),
+            PACKET_TYPE_CAMERA_OFFER.to_string(),
+            PACKET_TYPE_CAMERA_ANSWER.to_string(),
         ]
     }
 
+    fn outgoing_rate_limit(&self) -> RateLimit {
+        MEDIA_RATE_LIMIT
+    }
+
     async fn handle_packet(&mut self, packet: &Packet) -> Result<()> {
         match packet.packet_type.as_str() {
             PACKET_TYPE_CAMERA_CAPABILITY => {
@@ -585,10 +2705,34 @@ impl Plugin for CameraPlugin {
             PACKET_TYPE_CAMERA_STATUS => {
                 self.handle_status(packet)?;
             }
+            PACKET_TYPE_CAMERA_TORCH => {
+                self.handle_torch(packet)?;
+            }
+            PACKET_TYPE_CAMERA_THUMBNAIL => {
+                self.handle_thumbnail(packet)?;
+            }
             PACKET_TYPE_CAMERA_FRAME => {
                 // Frame handling is done separately as it has payload data
                 self.handle_frame(packet)?;
             }
+            PACKET_TYPE_CAMERA_OFFER => {
+                let offer = SessionDescription::from_packet(packet)?;
+                debug!(
+                    "Camera media offer {} with {} candidates",
+                    offer.session_id,
+                    offer.candidates.len()
+                );
+                self.remote_offer = Some(offer);
+            }
+            PACKET_TYPE_CAMERA_ANSWER => {
+                let answer = SessionDescription::from_packet(packet)?;
+                debug!(
+                    "Camera media answer {} with {} candidates",
+                    answer.session_id,
+                    answer.candidates.len()
+                );
+                self.remote_answer = Some(answer);
+            }
             _ => {
                 warn!("Unknown camera packet type: {}", packet.packet_type);
             }
@@ -603,8 +2747,17 @@ impl Plugin for CameraPlugin {
 
     async fn shutdown(&mut self) -> Result<()> {
         info!("Camera plugin shutdown");
-        self.is_streaming = false;
-        self.streaming_status = None;
+        self.streams.clear();
+        self.torch = None;
+        self.recovery.clear();
+        self.remote_offer = None;
+        self.remote_answer = None;
+        self.stream_keys
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+        self.pending_thumbnails.clear();
+        self.thumbnails.clear();
         Ok(())
     }
 }
@@ -875,6 +3028,7 @@ impl FrameType {
             FrameType::SpsPps => FramePriority::Critical,
             FrameType::IFrame => FramePriority::Critical,
             FrameType::PFrame => FramePriority::Low,
+            FrameType::Unknown => FramePriority::Low,
         }
     }
 
@@ -971,7 +3125,20 @@ impl SmartFrameDropper {
 // Performance Optimization: Latency Tracker
 // ============================================================================
 
+/// Default time after which an unmatched sent packet is forgotten
+pub const DEFAULT_ECHO_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
 /// Latency tracker for end-to-end latency measurement
+///
+/// Samples can be recorded directly (e.g. decode latency) or derived from
+/// echoes: [`mark_sent`](Self::mark_sent) stamps an outgoing packet with a
+/// monotonic send time keyed by its id, and [`resolve_echo`](Self::resolve_echo)
+/// turns the matching echo into a wire latency sample. Unmatched entries are
+/// evicted after the echo timeout so the pending map stays bounded.
+///
+/// Packet ids are millisecond timestamps, so several frames sent in the same
+/// millisecond share an id. Their send times are queued under it and echoes
+/// are matched to them in order.
 #[derive(Debug, Clone)]
 pub struct LatencyTracker {
     /// Recent latency samples (circular buffer)
@@ -984,6 +3151,10 @@ pub struct LatencyTracker {
     min_latency_ms: u32,
     /// Maximum observed latency
     max_latency_ms: u32,
+    /// Send times of packets awaiting an echo, oldest first, by packet id
+    pending: std::collections::HashMap<i64, VecDeque<std::time::Instant>>,
+    /// How long to wait for an echo before evicting a pending entry
+    echo_timeout: std::time::Duration,
 }
 
 impl Default for LatencyTracker {
@@ -994,11 +3165,76 @@ impl Default for LatencyTracker {
             max_samples: 100,
             min_latency_ms: u32::MAX,
             max_latency_ms: 0,
+            pending: std::collections::HashMap::new(),
+            echo_timeout: DEFAULT_ECHO_TIMEOUT,
+        }
+    }
+}
+
+impl LatencyTracker {
+    /// Create a tracker with a custom echo timeout
+    pub fn with_echo_timeout(echo_timeout: std::time::Duration) -> Self {
+        Self {
+            echo_timeout,
+            ..Default::default()
+        }
+    }
+
+    /// Stamp an outgoing packet with the current monotonic time
+    pub fn mark_sent(&mut self, packet: &Packet) {
+        self.mark_sent_at(packet.id, std::time::Instant::now());
+    }
+
+    /// Record that the packet with `packet_id` was sent at `sent_at`
+    ///
+    /// Also evicts pending entries older than the echo timeout.
+    pub fn mark_sent_at(&mut self, packet_id: i64, sent_at: std::time::Instant) {
+        self.evict_expired(sent_at);
+        self.pending.entry(packet_id).or_default().push_back(sent_at);
+    }
+
+    /// Resolve an echo for `packet_id` received now
+    ///
+    /// Returns the measured wire latency, or `None` if no matching send is pending.
+    pub fn resolve_echo(&mut self, packet_id: i64) -> Option<u32> {
+        self.resolve_echo_at(packet_id, std::time::Instant::now())
+    }
+
+    /// Resolve an echo for `packet_id` received at `received_at`
+    ///
+    /// The echo is matched to the oldest pending send with that id. The
+    /// latency is recorded as a sample and returned in milliseconds.
+    pub fn resolve_echo_at(&mut self, packet_id: i64, received_at: std::time::Instant) -> Option<u32> {
+        let sends = self.pending.get_mut(&packet_id)?;
+        let sent_at = sends.pop_front()?;
+        if sends.is_empty() {
+            self.pending.remove(&packet_id);
+        }
+        let elapsed = received_at.saturating_duration_since(sent_at);
+        if elapsed > self.echo_timeout {
+            debug!("Echo for packet {} arrived after timeout, ignoring", packet_id);
+            return None;
         }
+
+        let latency_ms = elapsed.as_millis().min(u32::MAX as u128) as u32;
+        self.record(latency_ms);
+        Some(latency_ms)
+    }
+
+    /// Drop pending entries that have waited longer than the echo timeout
+    pub fn evict_expired(&mut self, now: std::time::Instant) {
+        let timeout = self.echo_timeout;
+        self.pending.retain(|_, sends| {
+            sends.retain(|sent_at| now.saturating_duration_since(*sent_at) <= timeout);
+            !sends.is_empty()
+        });
+    }
+
+    /// Number of sent packets still awaiting an echo
+    pub fn pending_count(&self) -> usize {
+        self.pending.values().map(VecDeque::len).sum()
     }
-}
 
-impl LatencyTracker {
     /// Record a latency sample
     pub fn record(&mut self, latency_ms: u32) {
         if self.samples.len() < self.max_samples {
@@ -1021,14 +3257,24 @@ impl LatencyTracker {
         }
     }
 
+    /// Get P50 (median) latency in milliseconds
+    pub fn p50_ms(&self) -> u32 {
+        self.percentile_ms(0.50)
+    }
+
     /// Get P95 latency in milliseconds
     pub fn p95_ms(&self) -> u32 {
+        self.percentile_ms(0.95)
+    }
+
+    /// Get the latency at the given percentile (0.0 - 1.0) of recent samples
+    fn percentile_ms(&self, percentile: f64) -> u32 {
         if self.samples.is_empty() {
             return 0;
         }
         let mut sorted = self.samples.clone();
         sorted.sort_unstable();
-        let idx = (sorted.len() as f64 * 0.95) as usize;
+        let idx = (sorted.len() as f64 * percentile) as usize;
         sorted.get(idx.min(sorted.len() - 1)).copied().unwrap_or(0)
     }
 
@@ -1054,6 +3300,7 @@ impl LatencyTracker {
     /// Reset the tracker
     pub fn reset(&mut self) {
         self.samples.clear();
+        self.pending.clear();
         self.sample_index = 0;
         self.min_latency_ms = u32::MAX;
         self.max_latency_ms = 0;
@@ -1387,6 +3634,7 @@ mod tests {
                 facing: CameraFacing::Back,
                 max_resolution: Resolution::p1080(),
                 resolutions: vec![Resolution::p1080(), Resolution::p720(), Resolution::p480()],
+                has_flash: true,
             }],
             supported_codecs: vec!["h264".to_string()],
             audio_supported: false,
@@ -1395,7 +3643,7 @@ mod tests {
             max_fps: 60,
         };
 
-        let packet = capability.to_packet();
+        let packet = capability.try_to_packet().unwrap();
         assert_eq!(packet.packet_type, PACKET_TYPE_CAMERA_CAPABILITY);
 
         let parsed = CameraCapability::from_packet(&packet).unwrap();
@@ -1407,7 +3655,7 @@ mod tests {
     #[test]
     fn test_camera_start_serialization() {
         let start = CameraStart::default_720p(0);
-        let packet = start.to_packet();
+        let packet = start.try_to_packet().unwrap();
         assert_eq!(packet.packet_type, PACKET_TYPE_CAMERA_START);
 
         let parsed = CameraStart::from_packet(&packet).unwrap();
@@ -1422,6 +3670,15 @@ mod tests {
     fn test_camera_stop_serialization() {
         let packet = CameraStop::to_packet();
         assert_eq!(packet.packet_type, PACKET_TYPE_CAMERA_STOP);
+        assert_eq!(CameraStop::from_packet(&packet).unwrap(), CameraStop::default());
+
+        let packet = CameraStop::for_camera(1);
+        assert_eq!(CameraStop::from_packet(&packet).unwrap().camera_id, Some(1));
+
+        // Peers that send a null body still parse
+        let packet = Packet::new(PACKET_TYPE_CAMERA_STOP, serde_json::Value::Null);
+        assert_eq!(CameraStop::from_packet(&packet).unwrap().camera_id, None);
+        assert_eq!(CameraSettings::from_packet(&packet).unwrap(), CameraSettings::default());
     }
 
     #[test]
@@ -1433,15 +3690,17 @@ mod tests {
             bitrate: None,
             flash: Some(true),
             autofocus: None,
+            request_keyframe: None,
         };
 
-        let packet = settings.to_packet();
+        let packet = settings.try_to_packet().unwrap();
         assert_eq!(packet.packet_type, PACKET_TYPE_CAMERA_SETTINGS);
 
         let json = serde_json::to_string(&packet.body).unwrap();
         // Optional None fields should be omitted
         assert!(!json.contains("fps"));
         assert!(!json.contains("autofocus"));
+        assert!(!json.contains("requestKeyframe"));
         // Present fields should be included
         assert!(json.contains("cameraId"));
         assert!(json.contains("flash"));
@@ -1454,9 +3713,11 @@ mod tests {
             timestamp_us: 1234567890,
             sequence_number: 42,
             size: 65536,
+            stream_id: None,
+            orientation: None,
         };
 
-        let packet = frame.to_packet();
+        let packet = frame.try_to_packet().unwrap();
         assert_eq!(packet.packet_type, PACKET_TYPE_CAMERA_FRAME);
         assert_eq!(packet.payload_size, Some(65536));
 
@@ -1469,7 +3730,7 @@ mod tests {
     #[test]
     fn test_camera_status_serialization() {
         let status = CameraStatus::streaming(0, Resolution::p720(), 30, 2000);
-        let packet = status.to_packet();
+        let packet = status.try_to_packet().unwrap();
         assert_eq!(packet.packet_type, PACKET_TYPE_CAMERA_STATUS);
 
         let parsed = CameraStatus::from_packet(&packet).unwrap();
@@ -1477,6 +3738,43 @@ mod tests {
         assert_eq!(parsed.resolution, Resolution::p720());
     }
 
+    #[test]
+    fn test_parse_snake_case_keys() {
+        let packet = Packet::new(
+            PACKET_TYPE_CAMERA_FRAME,
+            json!({
+                "frame_type": "pframe",
+                "timestamp_us": 99,
+                "sequence_number": 7,
+                "size": 512,
+                "stream_id": 3
+            }),
+        );
+        let frame = CameraFrame::from_packet(&packet).unwrap();
+        assert_eq!(frame.frame_type, FrameType::PFrame);
+        assert_eq!(frame.sequence_number, 7);
+        assert_eq!(frame.stream_id, Some(3));
+
+        // Emitted packets stay camelCase
+        let body = frame.try_to_packet().unwrap().body;
+        assert_eq!(body["sequenceNumber"], 7);
+        assert!(body.get("sequence_number").is_none());
+
+        let packet = Packet::new(
+            PACKET_TYPE_CAMERA_STATUS,
+            json!({
+                "status": "streaming",
+                "camera_id": 1,
+                "resolution": { "width": 1280, "height": 720 },
+                "fps": 30,
+                "bitrate": 2000
+            }),
+        );
+        let status = CameraStatus::from_packet(&packet).unwrap();
+        assert_eq!(status.camera_id, 1);
+        assert_eq!(status.resolution, Resolution::p720());
+    }
+
     #[test]
     fn test_camera_status_error() {
         let status = CameraStatus::error("Camera access denied");
@@ -1489,7 +3787,7 @@ mod tests {
         let plugin = CameraPlugin::new();
         assert_eq!(plugin.name(), "camera");
         assert!(!plugin.has_camera());
-        assert!(!plugin.is_streaming());
+        assert!(!plugin.is_any_streaming());
     }
 
     #[test]
@@ -1507,6 +3805,33 @@ mod tests {
         assert!(outgoing.contains(&PACKET_TYPE_CAMERA_SETTINGS.to_string()));
     }
 
+    #[tokio::test]
+    async fn test_camera_offer_answer_round_trip() {
+        use crate::plugins::media_session::IceCandidate;
+
+        let desktop = CameraPlugin::new();
+        let mut phone = CameraPlugin::new();
+
+        let offer = SessionDescription::new("cam-1", "v=0\r\nm=video 9 UDP/TLS/RTP/SAVPF 96\r\n")
+            .with_candidate(IceCandidate::new("candidate:1 1 UDP 2122 192.168.1.20 49152 typ host"))
+            .with_candidate(IceCandidate::new("candidate:2 1 UDP 1686 203.0.113.7 5000 typ srflx"));
+        let packet = desktop.create_offer_packet(&offer).unwrap();
+        assert_eq!(packet.packet_type, "cconnect.camera.offer");
+        phone.handle_packet(&packet).await.unwrap();
+        assert_eq!(phone.remote_offer(), Some(&offer));
+        assert!(phone.remote_answer().is_none());
+
+        let answer = SessionDescription::new("cam-1", "v=0\r\n")
+            .with_candidate(IceCandidate::new("candidate:1 1 UDP 2122 192.168.1.30 5000 typ host"));
+        let packet = phone.create_answer_packet(&answer).unwrap();
+        assert_eq!(packet.packet_type, "cconnect.camera.answer");
+        phone.handle_packet(&packet).await.unwrap();
+        assert_eq!(phone.remote_answer(), Some(&answer));
+
+        assert!(phone.incoming_capabilities().contains(&PACKET_TYPE_CAMERA_OFFER.to_string()));
+        assert!(phone.outgoing_capabilities().contains(&PACKET_TYPE_CAMERA_ANSWER.to_string()));
+    }
+
     #[tokio::test]
     async fn test_camera_plugin_handle_capability() {
         let mut plugin = CameraPlugin::new();
@@ -1519,6 +3844,7 @@ mod tests {
                     facing: CameraFacing::Back,
                     max_resolution: Resolution::p1080(),
                     resolutions: vec![Resolution::p1080(), Resolution::p720()],
+                    has_flash: true,
                 },
                 CameraInfo {
                     id: 1,
@@ -1526,6 +3852,7 @@ mod tests {
                     facing: CameraFacing::Front,
                     max_resolution: Resolution::p720(),
                     resolutions: vec![Resolution::p720()],
+                    has_flash: false,
                 },
             ],
             supported_codecs: vec!["h264".to_string()],
@@ -1535,26 +3862,853 @@ mod tests {
             max_fps: 60,
         };
 
-        let packet = capability.to_packet();
+        let packet = capability.try_to_packet().unwrap();
         plugin.handle_packet(&packet).await.unwrap();
 
         assert!(plugin.has_camera());
         assert_eq!(plugin.cameras().unwrap().len(), 2);
     }
 
+    #[tokio::test]
+    async fn test_capability_cache_across_reconnect() {
+        let pixel = DeviceId::generate();
+        let mut paired = PairedDevices::new();
+        paired.trust(pixel.clone(), "AA:BB").unwrap();
+        let saved_at = SystemTime::now();
+        let previous = plugin_with_cameras(&[(0, true)]);
+        assert!(previous.save_capabilities_at(&mut paired, &pixel, saved_at).unwrap());
+
+        // Reconnect: usable before the phone advertises again
+        let mut plugin = CameraPlugin::new();
+        let reconnected_at = saved_at + Duration::from_secs(60);
+        assert!(plugin.restore_capabilities_at(&mut paired, &pixel, reconnected_at));
+        assert!(plugin.capabilities_from_cache());
+        assert!(plugin.set_torch(true).is_ok());
+        assert!(!plugin.save_capabilities_at(&mut paired, &pixel, reconnected_at).unwrap());
+
+        // The refresh reports changed hardware once and is cached in turn
+        let mut refreshed = previous.capabilities().unwrap().clone();
+        refreshed.cameras[0].has_flash = false;
+        plugin.handle_packet(&refreshed.try_to_packet().unwrap()).await.unwrap();
+        assert!(!plugin.capabilities_from_cache());
+        assert_eq!(plugin.take_capability_change(), Some(refreshed.clone()));
+        assert!(plugin.take_capability_change().is_none());
+        assert!(plugin.set_torch(true).is_err());
+        assert!(plugin.save_capabilities_at(&mut paired, &pixel, reconnected_at).unwrap());
+
+        // An unchanged refresh is not reported
+        plugin.handle_packet(&refreshed.try_to_packet().unwrap()).await.unwrap();
+        assert!(plugin.take_capability_change().is_none());
+
+        // Stale capabilities are invalidated instead of used
+        let max_age = Duration::from_secs(3600);
+        let mut plugin = CameraPlugin::new().with_capability_cache_max_age(max_age);
+        let later = reconnected_at + max_age * 2;
+        assert!(!plugin.restore_capabilities_at(&mut paired, &pixel, later));
+        assert!(plugin.capabilities().is_none());
+        assert!(paired.cached(&pixel, CAMERA_CAPABILITY_CACHE_KEY).is_none());
+    }
+
     #[tokio::test]
     async fn test_camera_plugin_handle_status() {
         let mut plugin = CameraPlugin::new();
 
         let status = CameraStatus::streaming(0, Resolution::p720(), 30, 2000);
-        let packet = status.to_packet();
+        let packet = status.try_to_packet().unwrap();
         plugin.handle_packet(&packet).await.unwrap();
 
-        assert!(plugin.is_streaming());
+        assert!(plugin.is_streaming(0));
         assert_eq!(
-            plugin.streaming_status().unwrap().status,
+            plugin.stream(0).unwrap().status.status,
             StreamingStatus::Streaming
         );
+
+        plugin
+            .handle_packet(&CameraStatus::stopped().try_to_packet().unwrap())
+            .await
+            .unwrap();
+        assert!(!plugin.is_any_streaming());
+        assert!(plugin.streaming_status().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_start_stream_acknowledged() {
+        let mut plugin = CameraPlugin::new();
+
+        // The phone reports the stream starting
+        let (packet, ack) = plugin.start_stream(CameraStart::default_720p(1)).unwrap();
+        assert!(packet.is_type(PACKET_TYPE_CAMERA_START));
+        assert_eq!(ack.camera_id(), 1);
+        let other = CameraStatus::streaming(0, Resolution::p720(), 30, 2000);
+        plugin.handle_packet(&other.try_to_packet().unwrap()).await.unwrap();
+        let mut starting = CameraStatus::streaming(1, Resolution::p720(), 30, 2000);
+        starting.status = StreamingStatus::Starting;
+        plugin.handle_packet(&starting.try_to_packet().unwrap()).await.unwrap();
+        ack.acknowledged().await.unwrap();
+
+        // The phone refuses, e.g. because camera permission was denied
+        let (_, ack) = plugin.start_stream(CameraStart::default_720p(2)).unwrap();
+        let mut refused = CameraStatus::error("permission denied");
+        refused.camera_id = 2;
+        plugin.handle_packet(&refused.try_to_packet().unwrap()).await.unwrap();
+        match ack.acknowledged().await {
+            Err(ProtocolError::StreamRefused { camera_id, reason }) => {
+                assert_eq!(camera_id, 2);
+                assert_eq!(reason, "permission denied");
+            }
+            other => panic!("expected StreamRefused, got {:?}", other),
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_start_stream_times_out() {
+        let mut plugin = CameraPlugin::new().with_start_timeout(Duration::from_secs(2));
+        let (_, ack) = plugin.start_stream(CameraStart::default_720p(0)).unwrap();
+
+        // Statuses for other cameras are not an answer
+        let other = CameraStatus::streaming(3, Resolution::p720(), 30, 2000);
+        plugin.handle_packet(&other.try_to_packet().unwrap()).await.unwrap();
+
+        let started = tokio::time::Instant::now();
+        assert!(matches!(ack.acknowledged().await, Err(ProtocolError::Timeout)));
+        assert_eq!(started.elapsed(), Duration::from_secs(2));
+    }
+
+    fn frame_packet(stream_id: Option<u32>, sequence_number: u64) -> Packet {
+        CameraFrame {
+            frame_type: FrameType::PFrame,
+            timestamp_us: sequence_number * 33_333,
+            sequence_number,
+            size: 512,
+            stream_id,
+            orientation: None,
+        }
+        .try_to_packet().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_unknown_enum_values() {
+        let mut plugin = CameraPlugin::new();
+        let status = CameraStatus::streaming(0, Resolution::p720(), 30, 2000);
+        plugin.handle_packet(&status.try_to_packet().unwrap()).await.unwrap();
+
+        // A frame type from a newer peer is skipped, not an error
+        let mut packet = frame_packet(None, 1);
+        packet.body["frameType"] = json!("bframe");
+        let frame = CameraFrame::from_packet(&packet).unwrap();
+        assert_eq!(frame.frame_type, FrameType::Unknown);
+        plugin.handle_packet(&packet).await.unwrap();
+        assert_eq!(plugin.stream(0).unwrap().frames_received, 0);
+
+        // Known values behave as before
+        plugin.handle_packet(&frame_packet(None, 2)).await.unwrap();
+        assert_eq!(plugin.stream(0).unwrap().frames_received, 1);
+
+        // An unknown status leaves the stream as it was
+        let mut packet = status.try_to_packet().unwrap();
+        packet.body["status"] = json!("paused");
+        let parsed = CameraStatus::from_packet(&packet).unwrap();
+        assert_eq!(parsed.status, StreamingStatus::Unknown);
+        plugin.handle_packet(&packet).await.unwrap();
+        assert!(plugin.is_streaming(0));
+
+        let capability = CameraCapability {
+            cameras: vec![CameraInfo {
+                id: 0,
+                name: "Periscope".to_string(),
+                facing: CameraFacing::Back,
+                max_resolution: Resolution::p1080(),
+                resolutions: vec![Resolution::p1080()],
+                has_flash: false,
+            }],
+            supported_codecs: vec!["h264".to_string()],
+            audio_supported: false,
+            max_resolution: Resolution::p1080(),
+            max_bitrate: 8000,
+            max_fps: 30,
+        };
+        let mut packet = capability.try_to_packet().unwrap();
+        packet.body["cameras"][0]["facing"] = json!("periscope");
+        let parsed = CameraCapability::from_packet(&packet).unwrap();
+        assert_eq!(parsed.cameras[0].facing, CameraFacing::Unknown);
+    }
+
+    #[tokio::test]
+    async fn test_camera_plugin_multiple_streams() {
+        let mut plugin = CameraPlugin::new();
+        for camera_id in [0, 1] {
+            let status = CameraStatus::streaming(camera_id, Resolution::p720(), 30, 2000);
+            plugin.handle_packet(&status.try_to_packet().unwrap()).await.unwrap();
+        }
+        assert!(plugin.is_streaming(0));
+        assert!(plugin.is_streaming(1));
+        assert_eq!(plugin.streaming_status().len(), 2);
+
+        plugin.handle_packet(&frame_packet(Some(1), 10)).await.unwrap();
+        plugin.handle_packet(&frame_packet(Some(1), 11)).await.unwrap();
+        plugin.handle_packet(&frame_packet(Some(0), 5)).await.unwrap();
+
+        assert_eq!(plugin.stream(0).unwrap().frames_received, 1);
+        assert_eq!(plugin.stream(1).unwrap().frames_received, 2);
+        assert_eq!(plugin.stream(1).unwrap().last_sequence, Some(11));
+
+        // Stopping one stream leaves the other untouched
+        let mut stopped = CameraStatus::stopped();
+        stopped.camera_id = 0;
+        plugin.handle_packet(&stopped.try_to_packet().unwrap()).await.unwrap();
+        assert!(!plugin.is_streaming(0));
+        assert!(plugin.is_streaming(1));
+
+        // Frames for the stopped stream are not routed
+        let late = CameraFrame::from_packet(&frame_packet(Some(0), 6)).unwrap();
+        assert_eq!(plugin.route_frame(&late), None);
+    }
+
+    #[tokio::test]
+    async fn test_camera_frame_without_stream_id_uses_default() {
+        let mut plugin = CameraPlugin::new();
+        let status = CameraStatus::streaming(1, Resolution::p720(), 30, 2000);
+        plugin.handle_packet(&status.try_to_packet().unwrap()).await.unwrap();
+
+        // A single-stream sender omits streamId
+        let packet = frame_packet(None, 1);
+        assert!(packet.body.get("streamId").is_none());
+        plugin.handle_packet(&packet).await.unwrap();
+
+        assert_eq!(plugin.stream(1).unwrap().frames_received, 1);
+        assert_eq!(plugin.create_stop_stream_packet(1).body["cameraId"], 1);
+    }
+
+    #[test]
+    fn test_orientation_round_trip() {
+        for degrees in [0, 90, 180, 270] {
+            let orientation = Orientation::try_from(degrees).unwrap();
+            assert_eq!(orientation.degrees(), degrees);
+            let status = CameraStatus::streaming(0, Resolution::p720(), 30, 2000)
+                .with_orientation(orientation);
+            let packet = status.try_to_packet().unwrap();
+            assert_eq!(packet.body["orientation"], degrees);
+            assert_eq!(CameraStatus::from_packet(&packet).unwrap(), status);
+        }
+        assert!(Orientation::Deg90.swaps_dimensions());
+        assert!(!Orientation::Deg180.swaps_dimensions());
+
+        let status = CameraStatus::streaming(0, Resolution::p720(), 30, 2000);
+        assert!(status.try_to_packet().unwrap().body.get("orientation").is_none());
+
+        let mut packet = frame_packet(None, 1);
+        packet.body["orientation"] = json!(45);
+        assert!(matches!(
+            CameraFrame::from_packet(&packet),
+            Err(ProtocolError::InvalidPacket(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_camera_orientation_changes_mid_stream() {
+        let mut plugin = CameraPlugin::new();
+        assert_eq!(plugin.orientation(0), None);
+
+        let status = CameraStatus::streaming(0, Resolution::p720(), 30, 2000)
+            .with_orientation(Orientation::Deg90);
+        plugin.handle_packet(&status.try_to_packet().unwrap()).await.unwrap();
+        assert_eq!(plugin.orientation(0), Some(Orientation::Deg90));
+
+        // Frames without orientation keep the last value
+        plugin.handle_packet(&frame_packet(Some(0), 1)).await.unwrap();
+        assert_eq!(plugin.orientation(0), Some(Orientation::Deg90));
+
+        // The phone is rotated to landscape
+        let mut packet = frame_packet(Some(0), 2);
+        packet.body["orientation"] = json!(0);
+        plugin.handle_packet(&packet).await.unwrap();
+        assert_eq!(plugin.orientation(0), Some(Orientation::Deg0));
+
+        let status = CameraStatus::streaming(0, Resolution::p720(), 30, 2000)
+            .with_orientation(Orientation::Deg270);
+        plugin.handle_packet(&status.try_to_packet().unwrap()).await.unwrap();
+        assert_eq!(plugin.orientation(0), Some(Orientation::Deg270));
+    }
+
+    fn plugin_with_codecs(codecs: &[&str]) -> CameraPlugin {
+        let mut plugin = CameraPlugin::new();
+        plugin.remote_capabilities = Some(CameraCapability {
+            cameras: vec![],
+            supported_codecs: codecs.iter().map(|c| c.to_string()).collect(),
+            audio_supported: false,
+            max_resolution: Resolution::p1080(),
+            max_bitrate: 8000,
+            max_fps: 60,
+        });
+        plugin
+    }
+
+    #[test]
+    fn test_negotiate_codec_prefers_local_order() {
+        let plugin = plugin_with_codecs(&["H264", "vp9"]);
+        let decoders = [
+            DecoderCapability::new("av1"),
+            DecoderCapability::new("vp9"),
+            DecoderCapability::new("h264"),
+        ];
+        assert_eq!(plugin.negotiate_codec(&decoders).unwrap(), "vp9");
+        assert_eq!(plugin.negotiate_codec(&decoders[2..]).unwrap(), "H264");
+    }
+
+    #[test]
+    fn test_negotiate_codec_no_shared_codec() {
+        let plugin = plugin_with_codecs(&["vp9"]);
+        let err = plugin
+            .negotiate_codec(&[DecoderCapability::new("h264")])
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Plugin error: Device only offers VP9, which your desktop can't decode"
+        );
+
+        assert!(CameraPlugin::new()
+            .negotiate_codec(&[DecoderCapability::new("h264")])
+            .is_err());
+    }
+
+    fn plugin_with_cameras(cameras: &[(u32, bool)]) -> CameraPlugin {
+        let mut plugin = plugin_with_codecs(&["h264"]);
+        plugin.remote_capabilities.as_mut().unwrap().cameras = cameras
+            .iter()
+            .map(|&(id, has_flash)| CameraInfo {
+                id,
+                name: format!("Camera {}", id),
+                facing: CameraFacing::Back,
+                max_resolution: Resolution::p720(),
+                resolutions: vec![Resolution::p720()],
+                has_flash,
+            })
+            .collect();
+        plugin
+    }
+
+    #[test]
+    fn test_torch_on_packet() {
+        let plugin = plugin_with_cameras(&[(1, false), (0, true)]);
+        assert!(!plugin.is_any_streaming());
+
+        let packet = plugin.set_torch(true).unwrap();
+        assert_eq!(packet.packet_type, PACKET_TYPE_CAMERA_TORCH);
+        assert_eq!(packet.body, json!({ "enabled": true, "cameraId": 0 }));
+    }
+
+    #[test]
+    fn test_torch_rejected_without_flash() {
+        let plugin = plugin_with_cameras(&[(0, false), (1, false)]);
+        let err = plugin.set_torch(true).unwrap_err();
+        assert!(matches!(err, ProtocolError::Plugin(_)));
+
+        // No capability advertisement at all
+        assert!(CameraPlugin::new().set_torch(false).is_err());
+    }
+
+    #[test]
+    fn test_downscale_request() {
+        let start = CameraStart::default_720p(0).with_downscale(Resolution::new(320, 180));
+        let packet = start.try_to_packet().unwrap();
+        assert_eq!(packet.body["downscale"], true);
+        assert_eq!(packet.body["resolution"], json!({ "width": 320, "height": 180 }));
+        assert_eq!(CameraStart::from_packet(&packet).unwrap(), start);
+
+        // Ordinary requests omit the flag
+        let packet = CameraStart::default_720p(0).try_to_packet().unwrap();
+        assert!(packet.body.get("downscale").is_none());
+    }
+
+    #[test]
+    fn test_downscale_fallback_to_nearest() {
+        let mut plugin = plugin_with_cameras(&[(0, false)]);
+        plugin.remote_capabilities.as_mut().unwrap().cameras[0].resolutions =
+            vec![Resolution::p1080(), Resolution::p720(), Resolution::p480()];
+
+        let refused = CameraStart::default_720p(0).with_downscale(Resolution::new(320, 180));
+        let fallback = plugin.downscale_fallback(&refused).unwrap();
+        assert_eq!(fallback.resolution, Resolution::p480());
+        assert!(!fallback.downscale);
+        assert_eq!(fallback.bitrate, refused.bitrate);
+
+        let refused = CameraStart::default_720p(0).with_downscale(Resolution::new(1200, 700));
+        assert_eq!(
+            plugin.downscale_fallback(&refused).unwrap().resolution,
+            Resolution::p720()
+        );
+
+        // Unknown camera
+        let refused = CameraStart::default_720p(5).with_downscale(Resolution::p480());
+        assert!(matches!(
+            plugin.downscale_fallback(&refused),
+            Err(ProtocolError::Plugin(_))
+        ));
+    }
+
+    /// Desktop and phone certificates, generated once since RSA is slow
+    fn certificates() -> &'static (CertificateInfo, CertificateInfo) {
+        static CERTIFICATES: std::sync::OnceLock<(CertificateInfo, CertificateInfo)> =
+            std::sync::OnceLock::new();
+        CERTIFICATES.get_or_init(|| {
+            (
+                CertificateInfo::generate(DeviceId::generate()).unwrap(),
+                CertificateInfo::generate(DeviceId::generate()).unwrap(),
+            )
+        })
+    }
+
+    /// Stream authentication of the desktop and the phone
+    fn stream_auths() -> (StreamAuth, StreamAuth) {
+        let (desktop, phone) = certificates();
+        (
+            StreamAuth::new(desktop, &phone.certificate).unwrap(),
+            StreamAuth::new(phone, &desktop.certificate).unwrap(),
+        )
+    }
+
+    fn encrypting_plugin() -> CameraPlugin {
+        CameraPlugin::new().with_stream_auth(stream_auths().0)
+    }
+
+    /// Answer an encrypted start request the way the phone does
+    fn phone_answer(start: &Packet) -> (Packet, FrameCipher) {
+        let start = CameraStart::from_packet(start).unwrap();
+        let auth = stream_auths().1;
+        let key = StreamKey::generate().unwrap();
+        let signature = key.sign(start.camera_id, &auth).unwrap();
+        let status = CameraStatus::streaming(start.camera_id, start.resolution, 30, 2000)
+            .with_stream_key(&key, signature);
+        let cipher = key
+            .agree(
+                start.camera_id,
+                start.stream_key.as_deref().unwrap(),
+                start.stream_key_signature.as_deref(),
+                &auth,
+            )
+            .unwrap();
+        (status.try_to_packet().unwrap(), cipher)
+    }
+
+    fn encrypted_frame(sequence_number: u64, frame_type: FrameType) -> CameraFrame {
+        CameraFrame {
+            frame_type,
+            timestamp_us: 0,
+            sequence_number,
+            size: 0,
+            stream_id: Some(0),
+            orientation: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_frame_round_trip() {
+        let mut plugin = encrypting_plugin();
+        let start = CameraStart::default_720p(0).with_encryption();
+        let packet = plugin.try_create_start_packet(start.clone()).unwrap();
+        assert_eq!(packet.body["encrypt"], true);
+        let sent = CameraStart::from_packet(&packet).unwrap();
+        assert_eq!(sent.stream_key.as_ref().map(String::len), Some(64));
+
+        // Every start uses a fresh key
+        let again = plugin.try_create_start_packet(start).unwrap();
+        assert_ne!(again.body["streamKey"], packet.body["streamKey"]);
+
+        // Frames are refused until the phone's key arrives
+        let (status, phone) = phone_answer(&again);
+        let frame = encrypted_frame(0, FrameType::SpsPps);
+        let sealed = phone.seal(&frame, b"config").unwrap();
+        assert!(plugin.open_frame_payload(&frame, &sealed).is_err());
+        plugin.handle_packet(&status).await.unwrap();
+
+        let frame_types = [FrameType::SpsPps, FrameType::IFrame, FrameType::PFrame];
+        for (sequence_number, frame_type) in (0u64..).zip(frame_types) {
+            let payload = vec![sequence_number as u8; 64];
+            let mut frame = encrypted_frame(sequence_number, frame_type);
+            let sealed = phone.seal(&frame, &payload).unwrap();
+            frame.size = sealed.len() as u64;
+            assert_ne!(&sealed[..payload.len()], &payload[..]);
+            assert_eq!(plugin.open_frame_payload(&frame, &sealed).unwrap(), payload);
+        }
+
+        // Unencrypted streams pass payloads through
+        let frame = CameraFrame {
+            stream_id: Some(1),
+            ..encrypted_frame(0, FrameType::PFrame)
+        };
+        assert_eq!(plugin.open_frame_payload(&frame, b"raw").unwrap(), b"raw");
+    }
+
+    #[tokio::test]
+    async fn test_replayed_frame_rejected() {
+        let mut plugin = encrypting_plugin();
+        let start = plugin
+            .try_create_start_packet(CameraStart::default_720p(0).with_encryption())
+            .unwrap();
+        let (status, phone) = phone_answer(&start);
+        plugin.handle_packet(&status).await.unwrap();
+
+        let frames: Vec<_> = [5, 6]
+            .into_iter()
+            .map(|sequence_number| {
+                let frame = encrypted_frame(sequence_number, FrameType::PFrame);
+                let sealed = phone.seal(&frame, b"delta").unwrap();
+                (frame, sealed)
+            })
+            .collect();
+        for (frame, sealed) in &frames {
+            assert!(plugin.open_frame_payload(frame, sealed).is_ok());
+        }
+
+        // Neither the last frame nor an earlier one can be played again
+        for (frame, sealed) in &frames {
+            let err = plugin.open_frame_payload(frame, sealed).unwrap_err();
+            assert!(matches!(err, ProtocolError::InvalidPacket(_)));
+        }
+
+        // A forged frame does not advance the window
+        let forged = encrypted_frame(100, FrameType::PFrame);
+        assert!(plugin.open_frame_payload(&forged, b"forged payload").is_err());
+        let next = encrypted_frame(7, FrameType::PFrame);
+        let sealed = phone.seal(&next, b"delta").unwrap();
+        assert_eq!(plugin.open_frame_payload(&next, &sealed).unwrap(), b"delta");
+    }
+
+    #[test]
+    fn test_tampered_frame_rejected() {
+        let (desktop_auth, phone_auth) = stream_auths();
+        let desktop = StreamKey::generate().unwrap();
+        let desktop_signature = desktop.sign(0, &desktop_auth).unwrap();
+        let phone = StreamKey::generate().unwrap();
+        let (phone_public, phone_signature) =
+            (phone.public_key(), Some(phone.sign(0, &phone_auth).unwrap()));
+        let sealer = phone
+            .agree(0, &desktop.public_key(), Some(&desktop_signature), &phone_auth)
+            .unwrap();
+        let mut cipher = desktop
+            .agree(0, &phone_public, phone_signature.as_deref(), &desktop_auth)
+            .unwrap();
+        let frame = CameraFrame {
+            stream_id: None,
+            ..encrypted_frame(7, FrameType::IFrame)
+        };
+        let mut sealed = sealer.seal(&frame, b"keyframe").unwrap();
+
+        // Changing the frame type breaks authentication too
+        let relabeled = CameraFrame {
+            frame_type: FrameType::PFrame,
+            ..frame.clone()
+        };
+        assert!(cipher.open(&relabeled, &sealed).is_err());
+
+        sealed[0] ^= 1;
+        let err = cipher.open(&frame, &sealed).unwrap_err();
+        assert!(matches!(err, ProtocolError::InvalidPacket(_)));
+
+        // A different exchange gives a different key
+        let other = StreamKey::generate().unwrap();
+        let mut other = other
+            .agree(0, &phone_public, phone_signature.as_deref(), &desktop_auth)
+            .unwrap();
+        sealed[0] ^= 1;
+        assert!(other.open(&frame, &sealed).is_err());
+        assert_eq!(cipher.open(&frame, &sealed).unwrap(), b"keyframe");
+
+        let key = StreamKey::generate().unwrap();
+        assert!(matches!(
+            key.agree(0, "not a key", None, &desktop_auth),
+            Err(ProtocolError::InvalidPacket(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_substituted_stream_key_rejected() {
+        let (desktop, phone) = certificates();
+        let mallory = CertificateInfo::generate(DeviceId::generate()).unwrap();
+        let mallory_auth = StreamAuth::new(&mallory, &desktop.certificate).unwrap();
+        let start = CameraStart::default_720p(0).with_encryption();
+
+        // Encryption cannot be requested without certificates to sign with
+        assert!(matches!(
+            CameraPlugin::new().try_create_start_packet(start.clone()),
+            Err(ProtocolError::Plugin(_))
+        ));
+
+        // A man in the middle answers with its own key: unsigned, signed by
+        // another certificate, or carrying the phone's signature of its key
+        let mut plugin = encrypting_plugin();
+        let packet = plugin.try_create_start_packet(start.clone()).unwrap();
+        let (genuine, phone_cipher) = phone_answer(&packet);
+        let genuine = CameraStatus::from_packet(&genuine).unwrap();
+        let forged = StreamKey::generate().unwrap();
+        let forged_signature = forged.sign(0, &mallory_auth).unwrap();
+        for signature in [None, Some(forged_signature), genuine.stream_key_signature.clone()] {
+            let status = CameraStatus {
+                stream_key: Some(forged.public_key()),
+                stream_key_signature: signature,
+                ..genuine.clone()
+            };
+            let err = plugin
+                .handle_packet(&status.try_to_packet().unwrap())
+                .await
+                .unwrap_err();
+            assert!(matches!(err, ProtocolError::InvalidPacket(_)));
+
+            // Frames are refused rather than decrypted under any key
+            let frame = encrypted_frame(0, FrameType::IFrame);
+            let sealed = phone_cipher.seal(&frame, b"keyframe").unwrap();
+            assert!(plugin.open_frame_payload(&frame, &sealed).is_err());
+            plugin.try_create_start_packet(start.clone()).unwrap();
+        }
+
+        // The phone likewise rejects a substituted start key
+        let mut substituted = CameraStart::from_packet(&packet).unwrap();
+        substituted.stream_key = Some(forged.public_key());
+        let phone_auth = StreamAuth::new(phone, &desktop.certificate).unwrap();
+        let err = StreamKey::generate()
+            .unwrap()
+            .agree(
+                0,
+                substituted.stream_key.as_deref().unwrap(),
+                substituted.stream_key_signature.as_deref(),
+                &phone_auth,
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("authentication"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_torch_state_reported() {
+        let mut plugin = plugin_with_cameras(&[(0, true)]);
+        assert!(!plugin.is_torch_on());
+
+        let state = CameraTorch {
+            enabled: true,
+            camera_id: Some(0),
+        };
+        plugin.handle_packet(&state.try_to_packet().unwrap()).await.unwrap();
+        assert!(plugin.is_torch_on());
+        assert_eq!(plugin.torch_state(), Some(&state));
+
+        // Capability advertisements without hasFlash parse as no flash
+        let body = json!({ "id": 0, "name": "Back", "facing": "back",
+            "maxResolution": { "width": 1280, "height": 720 }, "resolutions": [] });
+        let info: CameraInfo = serde_json::from_value(body).unwrap();
+        assert!(!info.has_flash);
+    }
+
+    #[test]
+    fn test_thumbnail_request() {
+        let plugin = CameraPlugin::new();
+        let packet = plugin.request_thumbnail(1).unwrap();
+        assert_eq!(packet.packet_type, PACKET_TYPE_CAMERA_THUMBNAIL);
+        assert_eq!(packet.body["cameraId"], 1);
+        assert_eq!(packet.body["maxResolution"]["width"], 320);
+        assert_eq!(packet.body["maxResolution"]["height"], 240);
+        assert!(packet.payload_size.is_none());
+
+        let request = CameraThumbnailRequest::from_packet(&packet).unwrap();
+        assert_eq!(request, CameraThumbnailRequest::new(1));
+    }
+
+    #[tokio::test]
+    async fn test_thumbnail_response() {
+        let mut plugin = CameraPlugin::new();
+        let jpeg = vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10, 0xFF, 0xD9];
+
+        // Header as sent by the phone, payload size included
+        let body = json!({ "cameraId": 0, "resolution": { "width": 320, "height": 240 },
+            "size": jpeg.len() });
+        let packet = Packet::new(PACKET_TYPE_CAMERA_THUMBNAIL, body)
+            .with_payload_size(jpeg.len() as i64);
+        plugin.handle_packet(&packet).await.unwrap();
+
+        let header = plugin.pending_thumbnail(0).unwrap();
+        assert_eq!(header.resolution, DEFAULT_THUMBNAIL_RESOLUTION);
+        assert_eq!(header.try_to_packet().unwrap().payload_size, Some(8));
+        assert!(plugin.thumbnail(0).is_none());
+
+        assert_eq!(plugin.receive_thumbnail(0, jpeg.clone()).unwrap(), jpeg);
+        assert!(plugin.pending_thumbnail(0).is_none());
+        assert_eq!(plugin.thumbnail(0), Some(jpeg.as_slice()));
+
+        // No payload announced, or not a JPEG
+        let no_payload = Packet::new(PACKET_TYPE_CAMERA_THUMBNAIL, packet.body.clone());
+        assert!(plugin.handle_packet(&no_payload).await.is_err());
+        plugin.handle_packet(&packet).await.unwrap();
+        assert!(plugin.receive_thumbnail(0, b"GIF89a!!".to_vec()).is_err());
+        assert!(plugin.receive_thumbnail(0, jpeg).is_err());
+    }
+
+    #[test]
+    fn test_flow_control_hysteresis() {
+        let mut plugin = CameraPlugin::new().with_flow_control(FlowControlPolicy {
+            pause_depth: 6,
+            resume_depth: 2,
+        });
+
+        let signal = |packet: Option<Packet>| {
+            packet.map(|p| {
+                assert_eq!(p.packet_type, PACKET_TYPE_CAMERA_FLOW_CONTROL);
+                let request = CameraFlowControl::from_packet(&p).unwrap();
+                assert_eq!(request.target_queue_depth, 2);
+                request.signal
+            })
+        };
+
+        // Filling up: nothing until the pause threshold, then one pause
+        for depth in 0..6 {
+            assert_eq!(signal(plugin.try_flow_control_signal(depth).unwrap()), None);
+        }
+        assert_eq!(signal(plugin.try_flow_control_signal(6).unwrap()), Some(FlowSignal::Pause));
+        assert!(plugin.is_flow_paused());
+        assert_eq!(signal(plugin.try_flow_control_signal(9).unwrap()), None);
+
+        // Draining: stays paused between the thresholds
+        for depth in (3..6).rev() {
+            assert_eq!(signal(plugin.try_flow_control_signal(depth).unwrap()), None);
+        }
+        assert_eq!(signal(plugin.try_flow_control_signal(2).unwrap()), Some(FlowSignal::Resume));
+        assert!(!plugin.is_flow_paused());
+        assert_eq!(signal(plugin.try_flow_control_signal(0).unwrap()), None);
+
+        // Refilling past the resume threshold does not pause again early
+        assert_eq!(signal(plugin.try_flow_control_signal(4).unwrap()), None);
+        assert_eq!(signal(plugin.try_flow_control_signal(7).unwrap()), Some(FlowSignal::Pause));
+    }
+
+    #[test]
+    fn test_camera_flow_control_serialization() {
+        let packet = CameraFlowControl {
+            signal: FlowSignal::Pause,
+            target_queue_depth: 3,
+        }
+        .try_to_packet().unwrap();
+
+        assert_eq!(packet.body["signal"], "pause");
+        assert_eq!(packet.body["targetQueueDepth"], 3);
+    }
+
+    #[tokio::test]
+    async fn test_decode_recovery_escalation() {
+        let mut plugin = CameraPlugin::new().with_recovery_policy(DecodeRecoveryPolicy {
+            failure_threshold: 3,
+            window: Duration::from_secs(1),
+            reset_threshold: 2,
+        });
+        let status = CameraStatus::streaming(0, Resolution::p720(), 30, 2000);
+        plugin.handle_packet(&status.try_to_packet().unwrap()).await.unwrap();
+
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+
+        // Failures spread wider than the window never escalate
+        for i in 0..4 {
+            assert_eq!(plugin.record_decode_failure_at(0, at(i * 600)).unwrap(), None);
+        }
+        plugin.record_decode_success(0);
+
+        // Sustained failures first request a keyframe...
+        let base = 5_000;
+        assert_eq!(plugin.record_decode_failure_at(0, at(base)).unwrap(), None);
+        assert_eq!(plugin.record_decode_failure_at(0, at(base + 10)).unwrap(), None);
+        let packet = match plugin.record_decode_failure_at(0, at(base + 20)).unwrap() {
+            Some(RecoveryAction::RequestKeyframe(packet)) => packet,
+            other => panic!("expected keyframe request, got {:?}", other),
+        };
+        assert_eq!(packet.packet_type, PACKET_TYPE_CAMERA_SETTINGS);
+        let settings = CameraSettings::from_packet(&packet).unwrap();
+        assert_eq!(settings.camera_id, Some(0));
+        assert_eq!(settings.request_keyframe, Some(true));
+        assert_eq!(packet.body["requestKeyframe"], true);
+
+        // ...then reset and re-negotiate from the stream's parameters
+        assert_eq!(plugin.record_decode_failure_at(0, at(base + 30)).unwrap(), None);
+        let packet = match plugin.record_decode_failure_at(0, at(base + 40)).unwrap() {
+            Some(RecoveryAction::ResetDecoder(packet)) => packet,
+            other => panic!("expected decoder reset, got {:?}", other),
+        };
+        assert_eq!(packet.packet_type, PACKET_TYPE_CAMERA_START);
+        let renegotiated: CameraStart = serde_json::from_value(packet.body).unwrap();
+        assert_eq!(renegotiated.camera_id, 0);
+        assert_eq!(renegotiated.resolution, Resolution::p720());
+        assert_eq!(renegotiated.bitrate, 2000);
+
+        // The escalation starts over after a reset
+        assert_eq!(plugin.record_decode_failure_at(0, at(base + 50)).unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_stream_report_counts() {
+        let mut plugin = CameraPlugin::new();
+        assert_eq!(plugin.stream_report(0), None);
+        let status = CameraStatus::streaming(0, Resolution::p720(), 30, 2000);
+        plugin.handle_packet(&status.try_to_packet().unwrap()).await.unwrap();
+
+        for sequence in 0..10 {
+            plugin.handle_packet(&frame_packet(Some(0), sequence)).await.unwrap();
+        }
+
+        // 10 frames: 6 decoded, 1 failed, 2 late, 1 shed by the drop policy
+        let start = Instant::now();
+        for i in 0..6 {
+            plugin.record_decode_success_at(0, start + Duration::from_millis(i * 100));
+        }
+        plugin.record_decode_failure_at(0, start).unwrap();
+        plugin.record_frame_dropped(0, FrameDropReason::Late);
+        plugin.record_frame_dropped(0, FrameDropReason::Late);
+        plugin.record_frame_dropped(0, FrameDropReason::Policy);
+
+        // Events for cameras without a stream are ignored
+        plugin.record_frame_dropped(7, FrameDropReason::Policy);
+        assert_eq!(plugin.stream_report(7), None);
+
+        let report = plugin
+            .stream_report_at(0, start + Duration::from_millis(500))
+            .unwrap();
+        assert_eq!(
+            report,
+            CameraStreamReport {
+                camera_id: 0,
+                frames_received: 10,
+                frames_decoded: 6,
+                frames_dropped_late: 2,
+                frames_dropped_policy: 1,
+                decode_errors: 1,
+                fps: 6.0,
+            }
+        );
+
+        // Only frames decoded within the last second count towards fps
+        let later = plugin
+            .stream_report_at(0, start + Duration::from_millis(1250))
+            .unwrap();
+        assert_eq!(later.fps, 3.0);
+        assert_eq!(later.frames_decoded, 6);
+    }
+
+    #[test]
+    fn test_decode_recovery_success_and_unknown_stream() {
+        let mut plugin = CameraPlugin::new().with_recovery_policy(DecodeRecoveryPolicy {
+            failure_threshold: 2,
+            window: Duration::from_secs(1),
+            reset_threshold: 1,
+        });
+        let now = Instant::now();
+
+        // A decoded frame ends the escalation before it reaches a reset
+        assert_eq!(plugin.record_decode_failure_at(3, now).unwrap(), None);
+        assert!(matches!(
+            plugin.record_decode_failure_at(3, now).unwrap(),
+            Some(RecoveryAction::RequestKeyframe(_))
+        ));
+        plugin.record_decode_success(3);
+        assert_eq!(plugin.record_decode_failure_at(3, now).unwrap(), None);
+
+        // Resetting a stream nothing is known about is an error
+        assert!(plugin.record_decode_failure_at(3, now).unwrap().is_some());
+        let err = plugin.record_decode_failure_at(3, now).unwrap_err();
+        assert!(matches!(err, ProtocolError::Plugin(_)));
     }
 
     #[test]
@@ -1597,6 +4751,8 @@ mod tests {
             timestamp_us: 1234567890,
             sequence_number: 42,
             size: 1024,
+            stream_id: None,
+            orientation: None,
         };
 
         let payload = vec![0u8; 1024];
@@ -1777,7 +4933,69 @@ mod tests {
         }
 
         let p95 = tracker.p95_ms();
-        assert!(p95 >= 95 && p95 <= 96);
+        assert!((95..=96).contains(&p95));
+    }
+
+    #[test]
+    fn test_latency_tracker_echo_percentiles() {
+        use std::time::{Duration, Instant};
+
+        let mut tracker = LatencyTracker::default();
+        let base = Instant::now();
+
+        // Echo delays of 10, 20, ..., 100 ms
+        for i in 1..=10i64 {
+            tracker.mark_sent_at(i, base);
+            let latency = tracker.resolve_echo_at(i, base + Duration::from_millis(i as u64 * 10));
+            assert_eq!(latency, Some(i as u32 * 10));
+        }
+
+        assert_eq!(tracker.pending_count(), 0);
+        assert_eq!(tracker.p50_ms(), 60);
+        assert_eq!(tracker.p95_ms(), 100);
+
+        // Unknown ids are ignored
+        assert_eq!(tracker.resolve_echo_at(999, base), None);
+    }
+
+    #[test]
+    fn test_latency_tracker_shared_packet_id() {
+        use std::time::{Duration, Instant};
+
+        let mut tracker = LatencyTracker::default();
+        let base = Instant::now();
+
+        // Two frames sent in the same millisecond get the same packet id
+        tracker.mark_sent_at(7, base);
+        tracker.mark_sent_at(7, base + Duration::from_millis(20));
+        assert_eq!(tracker.pending_count(), 2);
+
+        // Each echo resolves one send, oldest first
+        assert_eq!(tracker.resolve_echo_at(7, base + Duration::from_millis(30)), Some(30));
+        assert_eq!(tracker.resolve_echo_at(7, base + Duration::from_millis(60)), Some(40));
+        assert_eq!(tracker.resolve_echo_at(7, base + Duration::from_millis(70)), None);
+        assert_eq!(tracker.pending_count(), 0);
+    }
+
+    #[test]
+    fn test_latency_tracker_evicts_unmatched() {
+        use std::time::{Duration, Instant};
+
+        let mut tracker = LatencyTracker::with_echo_timeout(Duration::from_millis(100));
+        let base = Instant::now();
+
+        tracker.mark_sent_at(1, base);
+        tracker.mark_sent_at(2, base + Duration::from_millis(50));
+        assert_eq!(tracker.pending_count(), 2);
+
+        // Sending after the timeout evicts the stale entry
+        tracker.mark_sent_at(3, base + Duration::from_millis(150));
+        assert_eq!(tracker.pending_count(), 2);
+        assert_eq!(tracker.resolve_echo_at(1, base + Duration::from_millis(160)), None);
+
+        // Late echoes are discarded rather than recorded
+        assert_eq!(tracker.resolve_echo_at(2, base + Duration::from_millis(200)), None);
+        assert_eq!(tracker.p50_ms(), 0);
     }
 
     #[test]
@@ -51,6 +51,19 @@
 //! 4. Incoming updates with timestamp > local timestamp are **accepted**
 //! 5. Connect packets with timestamp `0` are ignored (no content)
 //!
+//! ## Conflicts
+//!
+//! When both sides copy something at nearly the same time, each receives
+//! the other's content while its own is still fresh. If an incoming update
+//! differs from a local change and the two timestamps are within the
+//! conflict window (1 second by default), the plugin records a
+//! [`ClipboardConflict`] carrying both states, retrieved with
+//! [`ClipboardPlugin::take_conflicts`]. The [`ClipboardConflictPolicy`]
+//! decides which content is kept meanwhile; by default the newest wins.
+//! Standard updates carry no timestamp, so they count as made on arrival.
+//! Only the latest [`MAX_CLIPBOARD_CONFLICTS`] conflicts are kept until
+//! taken; older ones are dropped.
+//!
 //! ## Workflow
 //!
 //! ### Sending Updates
@@ -74,7 +87,7 @@
 //!
 //! ```rust,ignore
 //! use cosmic_ext_connect_core::plugins::clipboard::*;
-//! use cosmic_ext_connect_core::{Plugin, PluginManager};
+//! use cosmic_ext_connect_core::plugins::{Plugin, PluginManager};
 //!
 //! // Create and register plugin
 //! let mut manager = PluginManager::new();
@@ -106,8 +119,15 @@ use async_trait::async_trait;
 use chrono::Utc;
 use serde_json::json;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
+
+/// Default time between a local and a remote change that counts as a conflict
+pub const DEFAULT_CLIPBOARD_CONFLICT_WINDOW: Duration = Duration::from_secs(1);
+
+/// Conflicts kept until [`ClipboardPlugin::take_conflicts`] is called
+pub const MAX_CLIPBOARD_CONFLICTS: usize = 32;
 
 /// Clipboard state with content and timestamp
 ///
@@ -228,6 +248,48 @@ impl Default for ClipboardState {
     }
 }
 
+/// Which content to keep when local and remote changes conflict
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ClipboardConflictPolicy {
+    /// Keep the content with the later timestamp (local on a tie)
+    #[default]
+    NewestWins,
+    /// Keep the local content
+    KeepLocal,
+    /// Keep the remote content
+    KeepRemote,
+}
+
+/// Local and remote clipboard changes made within the conflict window
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClipboardConflict {
+    /// Content copied on this device
+    pub local: ClipboardState,
+
+    /// Content received from the peer
+    pub remote: ClipboardState,
+}
+
+impl ClipboardConflict {
+    /// Get the state with the later timestamp (local on a tie)
+    pub fn newest(&self) -> &ClipboardState {
+        if self.remote.is_newer_than(&self.local) {
+            &self.remote
+        } else {
+            &self.local
+        }
+    }
+
+    /// Get the state a policy keeps
+    pub fn resolve(&self, policy: ClipboardConflictPolicy) -> &ClipboardState {
+        match policy {
+            ClipboardConflictPolicy::NewestWins => self.newest(),
+            ClipboardConflictPolicy::KeepLocal => &self.local,
+            ClipboardConflictPolicy::KeepRemote => &self.remote,
+        }
+    }
+}
+
 /// Clipboard sync plugin for text content synchronization
 ///
 /// Handles `cconnect.clipboard` packets for syncing clipboard content
@@ -245,18 +307,27 @@ impl Default for ClipboardState {
 ///
 /// ```rust
 /// use cosmic_ext_connect_core::plugins::clipboard::ClipboardPlugin;
-/// use cosmic_ext_connect_core::Plugin;
+/// use cosmic_ext_connect_core::plugins::Plugin;
 ///
 /// let plugin = ClipboardPlugin::new();
 /// assert_eq!(plugin.name(), "clipboard");
 /// ```
 #[derive(Debug)]
 pub struct ClipboardPlugin {
-    /// Device ID this plugin is attached to
-    device_id: Option<String>,
-
     /// Current clipboard state (content + timestamp)
     state: Arc<RwLock<ClipboardState>>,
+
+    /// Whether the current content was copied on this device
+    local_change: Arc<RwLock<bool>>,
+
+    /// Conflicts not yet taken by the UI
+    conflicts: Arc<RwLock<Vec<ClipboardConflict>>>,
+
+    /// Time between local and remote changes that counts as a conflict
+    conflict_window: Duration,
+
+    /// Which content to keep on a conflict
+    conflict_policy: ClipboardConflictPolicy,
 }
 
 impl ClipboardPlugin {
@@ -273,11 +344,33 @@ impl ClipboardPlugin {
     /// ```
     pub fn new() -> Self {
         Self {
-            device_id: None,
             state: Arc::new(RwLock::new(ClipboardState::empty())),
+            local_change: Arc::new(RwLock::new(false)),
+            conflicts: Arc::new(RwLock::new(Vec::new())),
+            conflict_window: DEFAULT_CLIPBOARD_CONFLICT_WINDOW,
+            conflict_policy: ClipboardConflictPolicy::default(),
         }
     }
 
+    /// Set the time between local and remote changes that counts as a conflict
+    pub fn with_conflict_window(mut self, window: Duration) -> Self {
+        self.conflict_window = window;
+        self
+    }
+
+    /// Set which content to keep on a conflict
+    pub fn with_conflict_policy(mut self, policy: ClipboardConflictPolicy) -> Self {
+        self.conflict_policy = policy;
+        self
+    }
+
+    /// Take the conflicts detected since the last call
+    ///
+    /// Holds at most the latest [`MAX_CLIPBOARD_CONFLICTS`], oldest first.
+    pub async fn take_conflicts(&self) -> Vec<ClipboardConflict> {
+        std::mem::take(&mut *self.conflicts.write().await)
+    }
+
     /// Create a standard clipboard update packet
     ///
     /// Creates `cconnect.clipboard` packet for syncing clipboard changes.
@@ -304,8 +397,7 @@ impl ClipboardPlugin {
     /// ```
     pub async fn create_clipboard_packet(&self, content: String) -> Packet {
         // Update internal state
-        let new_state = ClipboardState::new(content.clone());
-        *self.state.write().await = new_state;
+        self.set_content(content.clone()).await;
 
         Packet::new("cconnect.clipboard", json!({ "content": content }))
     }
@@ -407,7 +499,17 @@ impl ClipboardPlugin {
     /// # }
     /// ```
     pub async fn set_content(&self, content: String) {
-        *self.state.write().await = ClipboardState::new(content);
+        self.set_content_at(content, Utc::now().timestamp_millis())
+            .await;
+    }
+
+    /// Update clipboard content copied on this device at a specific time
+    ///
+    /// Like [`set_content`](Self::set_content), the change takes part in
+    /// conflict detection.
+    pub async fn set_content_at(&self, content: String, timestamp: i64) {
+        *self.state.write().await = ClipboardState::with_timestamp(content, timestamp);
+        *self.local_change.write().await = true;
     }
 
     /// Update clipboard with specific timestamp
@@ -426,6 +528,55 @@ impl ClipboardPlugin {
     /// ```
     pub async fn set_content_with_timestamp(&self, content: String, timestamp: i64) {
         *self.state.write().await = ClipboardState::with_timestamp(content, timestamp);
+        *self.local_change.write().await = false;
+    }
+
+    /// Check a remote change against a recent local one
+    ///
+    /// Returns the conflict if the remote content differs from local
+    /// content changed within the conflict window.
+    async fn detect_conflict(&self, remote: &ClipboardState) -> Option<ClipboardConflict> {
+        if !*self.local_change.read().await {
+            return None;
+        }
+
+        let local = self.state.read().await.clone();
+        if local.is_empty() || local.content == remote.content {
+            return None;
+        }
+
+        let apart = remote.timestamp.abs_diff(local.timestamp);
+        if apart > self.conflict_window.as_millis() as u64 {
+            return None;
+        }
+
+        Some(ClipboardConflict {
+            local,
+            remote: remote.clone(),
+        })
+    }
+
+    /// Apply a conflict's outcome under the plugin's policy and record it
+    async fn resolve_conflict(&self, conflict: ClipboardConflict) {
+        warn!(
+            "Clipboard conflict: local {} chars at {}, remote {} chars at {}",
+            conflict.local.content.len(),
+            conflict.local.timestamp,
+            conflict.remote.content.len(),
+            conflict.remote.timestamp
+        );
+
+        let winner = conflict.resolve(self.conflict_policy);
+        if winner == &conflict.remote {
+            self.set_content_with_timestamp(winner.content.clone(), winner.timestamp)
+                .await;
+        }
+
+        let mut conflicts = self.conflicts.write().await;
+        if conflicts.len() >= MAX_CLIPBOARD_CONFLICTS {
+            conflicts.remove(0);
+        }
+        conflicts.push(conflict);
     }
 
     /// Handle incoming clipboard update packet
@@ -446,8 +597,16 @@ impl ClipboardPlugin {
 
         info!("Received clipboard update: {} chars", content.len());
 
-        // Standard updates always applied (no timestamp validation)
-        self.set_content(content.to_string()).await;
+        // Standard updates carry no timestamp, so they count as made now
+        let remote = ClipboardState::new(content.to_string());
+        if let Some(conflict) = self.detect_conflict(&remote).await {
+            self.resolve_conflict(conflict).await;
+            return;
+        }
+
+        // Otherwise always applied (no timestamp validation)
+        self.set_content_with_timestamp(remote.content, remote.timestamp)
+            .await;
 
         debug!(
             "Clipboard updated - timestamp: {}",
@@ -478,6 +637,12 @@ impl ClipboardPlugin {
             return;
         }
 
+        let remote = ClipboardState::with_timestamp(content.to_string(), timestamp);
+        if let Some(conflict) = self.detect_conflict(&remote).await {
+            self.resolve_conflict(conflict).await;
+            return;
+        }
+
         let current_state = self.state.read().await.clone();
 
         // Only apply if incoming timestamp is newer
@@ -498,8 +663,7 @@ impl ClipboardPlugin {
         } else {
             debug!(
                 "Ignoring connect packet - timestamp {} <= local {}",
-                timestamp,
-                current_state.timestamp
+                timestamp, current_state.timestamp
             );
         }
     }
@@ -517,7 +681,6 @@ impl Plugin for ClipboardPlugin {
         "clipboard"
     }
 
-
     fn incoming_capabilities(&self) -> Vec<String> {
         vec![
             "cconnect.clipboard".to_string(),
@@ -537,11 +700,6 @@ impl Plugin for ClipboardPlugin {
         Ok(())
     }
 
-    async fn start(&mut self) -> Result<()> {
-        info!("Clipboard plugin started");
-        Ok(())
-    }
-
     async fn shutdown(&mut self) -> Result<()> {
         let state = self.state.read().await;
         info!(
@@ -565,9 +723,6 @@ impl Plugin for ClipboardPlugin {
     }
 }
 
-/// Factory for creating ClipboardPlugin instances
-#[derive(Debug, Clone, Copy)]
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -633,9 +788,6 @@ mod tests {
         // Initialize
         plugin.initialize().await.unwrap();
 
-        // Start
-        plugin.start().await.unwrap();
-
         // Stop
         plugin.shutdown().await.unwrap();
     }
@@ -840,10 +992,7 @@ mod tests {
         assert_eq!(content, "First update");
 
         // Second update
-        let packet2 = Packet::new(
-            "cconnect.clipboard",
-            json!({ "content": "Second update" }),
-        );
+        let packet2 = Packet::new("cconnect.clipboard", json!({ "content": "Second update" }));
         plugin.handle_packet(&packet2).await.unwrap();
 
         let content = plugin.get_content().await;
@@ -876,4 +1025,103 @@ mod tests {
         assert_eq!(state.content, "Current");
         assert_eq!(state.timestamp, 2000);
     }
+
+    fn connect_packet(content: &str, timestamp: i64) -> Packet {
+        Packet::new(
+            "cconnect.clipboard.connect",
+            json!({ "content": content, "timestamp": timestamp }),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_conflict_within_window() {
+        let mut plugin = ClipboardPlugin::new();
+        plugin
+            .set_content_at("Local copy".to_string(), 10_000)
+            .await;
+
+        // The peer copied something 400ms later
+        let packet = connect_packet("Remote copy", 10_400);
+        plugin.handle_packet(&packet).await.unwrap();
+
+        let conflicts = plugin.take_conflicts().await;
+        assert_eq!(
+            conflicts,
+            vec![ClipboardConflict {
+                local: ClipboardState::with_timestamp("Local copy".to_string(), 10_000),
+                remote: ClipboardState::with_timestamp("Remote copy".to_string(), 10_400),
+            }]
+        );
+        assert!(plugin.take_conflicts().await.is_empty());
+
+        // Newest wins by default
+        assert_eq!(plugin.get_content().await, "Remote copy");
+
+        // A local change made just after a remote one is a conflict too
+        let mut plugin =
+            ClipboardPlugin::new().with_conflict_policy(ClipboardConflictPolicy::KeepRemote);
+        plugin
+            .set_content_at("Local copy".to_string(), 10_900)
+            .await;
+        plugin
+            .handle_packet(&connect_packet("Remote copy", 10_000))
+            .await
+            .unwrap();
+        let conflicts = plugin.take_conflicts().await;
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].newest().content, "Local copy");
+        assert_eq!(plugin.get_content().await, "Remote copy");
+
+        // Identical content is not a conflict
+        plugin.set_content_at("Same".to_string(), 20_000).await;
+        plugin
+            .handle_packet(&connect_packet("Same", 20_100))
+            .await
+            .unwrap();
+        assert!(plugin.take_conflicts().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_conflicts_capped() {
+        let mut plugin = ClipboardPlugin::new();
+        for i in 0..MAX_CLIPBOARD_CONFLICTS as i64 + 2 {
+            let local = 10_000 + i * 10_000;
+            plugin.set_content_at(format!("Local {}", i), local).await;
+            plugin
+                .handle_packet(&connect_packet(&format!("Remote {}", i), local + 100))
+                .await
+                .unwrap();
+        }
+
+        // The oldest conflicts were dropped
+        let conflicts = plugin.take_conflicts().await;
+        assert_eq!(conflicts.len(), MAX_CLIPBOARD_CONFLICTS);
+        assert_eq!(conflicts[0].local.content, "Local 2");
+    }
+
+    #[tokio::test]
+    async fn test_changes_outside_window_resolved_by_timestamp() {
+        let mut plugin = ClipboardPlugin::new().with_conflict_window(Duration::from_millis(500));
+        plugin
+            .set_content_at("Local copy".to_string(), 10_000)
+            .await;
+
+        // Older remote content is ignored
+        plugin
+            .handle_packet(&connect_packet("Stale copy", 9_000))
+            .await
+            .unwrap();
+        assert_eq!(plugin.get_content().await, "Local copy");
+
+        // Newer remote content replaces it
+        plugin
+            .handle_packet(&connect_packet("Remote copy", 10_600))
+            .await
+            .unwrap();
+        let state = plugin.get_state().await;
+        assert_eq!(state.content, "Remote copy");
+        assert_eq!(state.timestamp, 10_600);
+
+        assert!(plugin.take_conflicts().await.is_empty());
+    }
 }
@@ -2,10 +2,26 @@
 //!
 //! Synchronizes files between devices with bidirectional sync support.
 //! Tracks file changes, detects conflicts, and manages sync folders.
+//!
+//! ## Incremental Scanning
+//!
+//! [`FolderScanner`] keeps a persisted index of every file's size,
+//! modification time and checksum. A rescan only rehashes files whose size
+//! or modification time changed, and reports adds, changes and deletes as
+//! [`FileSyncNotification`]s. Index paths are relative to the folder root,
+//! so the index stays valid when the folder is moved.
 
 use crate::protocol::Packet;
-use crate::error::Result;
+use crate::error::{ProtocolError, Result};
+use serde::{Deserialize, Serialize};
 use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tokio::fs;
+use tokio::io::AsyncReadExt;
+use tracing::{debug, info, warn};
 
 /// FileSync notification packet type
 pub const PACKET_TYPE_FILESYNC: &str = "cconnect.filesync";
@@ -20,7 +36,8 @@ pub const PACKET_TYPE_FILESYNC_CONFLICT: &str = "cconnect.filesync.conflict";
 ///
 /// * `action` - Action type: "file_changed", "file_deleted", "file_added", "sync_complete", "sync_started"
 /// * `path` - Relative file path within sync folder
-/// * `checksum` - Optional file checksum (SHA-256)
+/// * `checksum` - Optional file checksum (hex SHA-256), verified on receipt by
+///   [`PayloadReceiver::receive_file`](crate::protocol::PayloadReceiver::receive_file)
 /// * `size` - Optional file size in bytes
 /// * `timestamp` - Optional last modified timestamp (epoch millis)
 /// * `sync_folder_id` - Identifier for the sync folder pair
@@ -123,10 +140,398 @@ pub fn create_filesync_conflict(
     ))
 }
 
+/// Kind of change reported by a [`FileSyncNotification`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileSyncAction {
+    /// A file appeared
+    FileAdded,
+    /// A file's contents changed
+    FileChanged,
+    /// A file was removed
+    FileDeleted,
+}
+
+impl FileSyncAction {
+    /// Get the protocol action string
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::FileAdded => "file_added",
+            Self::FileChanged => "file_changed",
+            Self::FileDeleted => "file_deleted",
+        }
+    }
+}
+
+/// A file change found by a [`FolderScanner`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileSyncNotification {
+    /// What happened to the file
+    pub action: FileSyncAction,
+
+    /// Path relative to the sync folder, `/`-separated
+    pub path: String,
+
+    /// Hex SHA-256 of the contents (not set for deletes)
+    pub checksum: Option<String>,
+
+    /// File size in bytes (not set for deletes)
+    pub size: Option<u64>,
+
+    /// Last modified timestamp in epoch millis (not set for deletes)
+    pub timestamp: Option<i64>,
+}
+
+impl FileSyncNotification {
+    /// Create the notification packet for a sync folder
+    pub fn try_to_packet(&self, sync_folder_id: &str) -> Result<Packet> {
+        create_filesync_notification(
+            self.action.as_str(),
+            &self.path,
+            self.checksum.clone(),
+            self.size.map(|size| size as i64),
+            self.timestamp,
+            sync_folder_id,
+        )
+    }
+}
+
+/// Indexed state of one file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexEntry {
+    /// File size in bytes
+    pub size: u64,
+
+    /// Last modified timestamp in epoch millis
+    pub mtime: i64,
+
+    /// Hex SHA-256 of the contents
+    pub checksum: String,
+}
+
+/// On-disk form of a [`FolderScanner`] index
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct FolderIndex {
+    /// Folder root at the time of the last scan
+    root: PathBuf,
+
+    /// Entries keyed by relative path
+    entries: BTreeMap<String, IndexEntry>,
+}
+
+/// Incremental scanner for a sync folder
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use cosmic_ext_connect_core::plugins::filesync::FolderScanner;
+///
+/// # async fn example() -> cosmic_ext_connect_core::error::Result<()> {
+/// let mut scanner = FolderScanner::open("/home/user/Sync", "/home/user/.cache/sync.json").await?;
+/// for change in scanner.scan().await? {
+///     let packet = change.try_to_packet("folder-123")?;
+///     // Send packet to the peer...
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct FolderScanner {
+    /// Folder being scanned
+    root: PathBuf,
+
+    /// Where the index is persisted
+    index_path: PathBuf,
+
+    /// Files seen by the last scan
+    entries: BTreeMap<String, IndexEntry>,
+}
+
+impl FolderScanner {
+    /// Open a scanner, loading the index at `index_path` if it exists
+    ///
+    /// If the index was written for a different root, the folder is assumed
+    /// to have moved and the index is kept.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Plugin` if an existing index cannot be read.
+    pub async fn open(root: impl Into<PathBuf>, index_path: impl Into<PathBuf>) -> Result<Self> {
+        let root = root.into();
+        let index_path = index_path.into();
+
+        let index = if fs::try_exists(&index_path).await? {
+            let contents = fs::read_to_string(&index_path).await.map_err(|e| {
+                ProtocolError::Plugin(format!("Failed to read sync index: {}", e))
+            })?;
+            serde_json::from_str(&contents).map_err(|e| {
+                ProtocolError::Plugin(format!("Failed to parse sync index: {}", e))
+            })?
+        } else {
+            FolderIndex::default()
+        };
+
+        if !index.entries.is_empty() && index.root != root {
+            info!("Sync folder moved from {:?} to {:?}", index.root, root);
+        }
+
+        Ok(Self {
+            root,
+            index_path,
+            entries: index.entries,
+        })
+    }
+
+    /// Get the folder being scanned
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Point the scanner at the folder's new location
+    pub fn set_root(&mut self, root: impl Into<PathBuf>) {
+        self.root = root.into();
+    }
+
+    /// Get the indexed state of a file
+    pub fn entry(&self, path: &str) -> Option<&IndexEntry> {
+        self.entries.get(path)
+    }
+
+    /// Get the number of indexed files
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check whether no files are indexed
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Rescan the folder, persist the index and return the changes
+    ///
+    /// Files whose size and modification time match the index are not
+    /// read. A file whose modification time changed but whose checksum did
+    /// not is updated silently. Symlinks are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Io` if the folder cannot be read, or
+    /// `ProtocolError::Plugin` if the index cannot be written.
+    pub async fn scan(&mut self) -> Result<Vec<FileSyncNotification>> {
+        let mut seen = BTreeMap::new();
+        let mut changes = Vec::new();
+        let mut hashed = 0usize;
+
+        let mut pending = vec![self.root.clone()];
+        while let Some(dir) = pending.pop() {
+            let mut read_dir = fs::read_dir(&dir).await?;
+            while let Some(item) = read_dir.next_entry().await? {
+                let path = item.path();
+                if path == self.index_path {
+                    continue;
+                }
+
+                let metadata = fs::symlink_metadata(&path).await?;
+                if metadata.is_dir() {
+                    pending.push(path);
+                    continue;
+                }
+                if !metadata.is_file() {
+                    continue;
+                }
+
+                let Some(relative) = self.relative_path(&path) else {
+                    warn!("Skipping non UTF-8 path {:?}", path);
+                    continue;
+                };
+                let size = metadata.len();
+                let mtime = metadata
+                    .modified()
+                    .ok()
+                    .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                    .map(|since| since.as_millis() as i64)
+                    .unwrap_or(0);
+
+                let previous = self.entries.get(&relative);
+                if let Some(previous) = previous {
+                    if previous.size == size && previous.mtime == mtime {
+                        seen.insert(relative, previous.clone());
+                        continue;
+                    }
+                }
+
+                let checksum = hash_file(&path).await?;
+                hashed += 1;
+
+                let action = match previous {
+                    None => Some(FileSyncAction::FileAdded),
+                    Some(previous) if previous.checksum != checksum => {
+                        Some(FileSyncAction::FileChanged)
+                    }
+                    Some(_) => None,
+                };
+                if let Some(action) = action {
+                    changes.push(FileSyncNotification {
+                        action,
+                        path: relative.clone(),
+                        checksum: Some(checksum.clone()),
+                        size: Some(size),
+                        timestamp: Some(mtime),
+                    });
+                }
+
+                seen.insert(relative, IndexEntry { size, mtime, checksum });
+            }
+        }
+
+        for path in self.entries.keys() {
+            if !seen.contains_key(path) {
+                changes.push(FileSyncNotification {
+                    action: FileSyncAction::FileDeleted,
+                    path: path.clone(),
+                    checksum: None,
+                    size: None,
+                    timestamp: None,
+                });
+            }
+        }
+
+        debug!(
+            "Scanned {:?}: {} files, {} hashed, {} changes",
+            self.root,
+            seen.len(),
+            hashed,
+            changes.len()
+        );
+
+        self.entries = seen;
+        self.save().await?;
+        Ok(changes)
+    }
+
+    /// Get the `/`-separated path of `path` relative to the root
+    fn relative_path(&self, path: &Path) -> Option<String> {
+        let relative = path.strip_prefix(&self.root).ok()?;
+        let parts: Option<Vec<&str>> = relative.iter().map(|part| part.to_str()).collect();
+        Some(parts?.join("/"))
+    }
+
+    /// Write the index, replacing the old one atomically
+    async fn save(&self) -> Result<()> {
+        if let Some(parent) = self.index_path.parent() {
+            fs::create_dir_all(parent).await.map_err(|e| {
+                ProtocolError::Plugin(format!("Failed to create sync index directory: {}", e))
+            })?;
+        }
+
+        let index = FolderIndex {
+            root: self.root.clone(),
+            entries: self.entries.clone(),
+        };
+        let contents = serde_json::to_string(&index).map_err(|e| {
+            ProtocolError::Plugin(format!("Failed to serialize sync index: {}", e))
+        })?;
+
+        let partial = self.index_path.with_extension("partial");
+        fs::write(&partial, contents).await.map_err(|e| {
+            ProtocolError::Plugin(format!("Failed to write sync index: {}", e))
+        })?;
+        fs::rename(&partial, &self.index_path).await.map_err(|e| {
+            ProtocolError::Plugin(format!("Failed to write sync index: {}", e))
+        })?;
+        Ok(())
+    }
+}
+
+/// Compute the hex SHA-256 of a file
+async fn hash_file(path: &Path) -> Result<String> {
+    let mut file = fs::File::open(path).await?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    async fn write(root: &Path, path: &str, contents: &str) {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).await.unwrap();
+        fs::write(path, contents).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_folder_scanner_reports_only_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("sync");
+        let index = dir.path().join("index.json");
+        write(&root, "a.txt", "alpha").await;
+        write(&root, "docs/b.txt", "beta").await;
+        write(&root, "docs/c.txt", "gamma").await;
+
+        let mut scanner = FolderScanner::open(&root, &index).await.unwrap();
+        let changes = scanner.scan().await.unwrap();
+        assert_eq!(changes.len(), 3);
+        assert!(changes.iter().all(|c| c.action == FileSyncAction::FileAdded));
+        assert!(scanner.scan().await.unwrap().is_empty());
+
+        write(&root, "docs/b.txt", "beta, edited").await;
+        let changes = scanner.scan().await.unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].action, FileSyncAction::FileChanged);
+        assert_eq!(changes[0].path, "docs/b.txt");
+        assert_eq!(changes[0].size, Some(12));
+        assert_eq!(
+            changes[0].checksum.as_deref(),
+            Some(hex::encode(Sha256::digest(b"beta, edited")).as_str())
+        );
+
+        let packet = changes[0].try_to_packet("folder-1").unwrap();
+        assert_eq!(packet.body["action"], "file_changed");
+        assert_eq!(packet.body["path"], "docs/b.txt");
+
+        fs::remove_file(root.join("a.txt")).await.unwrap();
+        let changes = scanner.scan().await.unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].action, FileSyncAction::FileDeleted);
+        assert_eq!(changes[0].path, "a.txt");
+        assert_eq!(scanner.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_folder_scanner_index_survives_restart_and_move() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("sync");
+        let index = dir.path().join("state/index.json");
+        write(&root, "a.txt", "alpha").await;
+        write(&root, "nested/deep/b.txt", "beta").await;
+
+        let mut scanner = FolderScanner::open(&root, &index).await.unwrap();
+        assert_eq!(scanner.scan().await.unwrap().len(), 2);
+        drop(scanner);
+
+        // Restarted scanner picks up where the last one left off
+        let mut scanner = FolderScanner::open(&root, &index).await.unwrap();
+        assert_eq!(scanner.len(), 2);
+        assert!(scanner.scan().await.unwrap().is_empty());
+        drop(scanner);
+
+        // Moving the folder keeps the index valid
+        let moved = dir.path().join("moved");
+        fs::rename(&root, &moved).await.unwrap();
+        let mut scanner = FolderScanner::open(&moved, &index).await.unwrap();
+        assert!(scanner.scan().await.unwrap().is_empty());
+        assert!(scanner.entry("nested/deep/b.txt").is_some());
+    }
+
     #[test]
     fn test_create_filesync_notification_minimal() {
         let packet = create_filesync_notification(
@@ -0,0 +1,286 @@
+//! Input Event Coalescing
+//!
+//! Remote mousepad and presenter sessions send a relative-motion event for
+//! every touch sample, which floods the link. [`InputCoalescer`] merges
+//! bursts of motion into fewer events before they are sent or injected.
+//!
+//! ## Rules
+//!
+//! - Consecutive `MouseMove` events within the coalescing window are summed,
+//!   as are consecutive `Scroll` events
+//! - Consecutive `MoveTo` events within the window keep only the latest
+//!   position
+//! - Clicks and key events are never merged, and nothing is merged across
+//!   them, so their order relative to motion is preserved
+//!
+//! Absolute `MoveTo` positions are pushed normalized (0.0 to 1.0) and, if a
+//! target resolution is set, come out of [`InputCoalescer::drain`] in pixels.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use cosmic_ext_connect_core::plugins::input::{InputCoalescer, InputEvent, MouseButton};
+//!
+//! let mut coalescer = InputCoalescer::new();
+//! coalescer.push(InputEvent::MouseMove { dx: 3.0, dy: 1.0 });
+//! coalescer.push(InputEvent::MouseMove { dx: 2.0, dy: -1.0 });
+//! coalescer.push(InputEvent::Click(MouseButton::Left));
+//!
+//! let events = coalescer.drain();
+//! assert_eq!(events[0], InputEvent::MouseMove { dx: 5.0, dy: 0.0 });
+//! assert_eq!(events[1], InputEvent::Click(MouseButton::Left));
+//! ```
+
+use crate::plugins::{RateLimit, INPUT_RATE_LIMIT};
+use std::time::{Duration, Instant};
+
+/// Default window within which motion events are merged (one 60Hz frame)
+pub const DEFAULT_COALESCE_WINDOW: Duration = Duration::from_millis(16);
+
+/// Outgoing rate limit for remote input, which sends coalesced motion
+pub const OUTGOING_RATE_LIMIT: RateLimit = INPUT_RATE_LIMIT;
+
+/// Mouse button
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MouseButton {
+    /// Primary button
+    Left,
+    /// Middle button or wheel click
+    Middle,
+    /// Secondary button
+    Right,
+}
+
+/// Remote input event
+#[derive(Debug, Clone, PartialEq)]
+pub enum InputEvent {
+    /// Relative pointer motion
+    MouseMove {
+        /// Horizontal delta
+        dx: f64,
+        /// Vertical delta
+        dy: f64,
+    },
+
+    /// Relative scroll
+    Scroll {
+        /// Horizontal delta
+        dx: f64,
+        /// Vertical delta
+        dy: f64,
+    },
+
+    /// Absolute pointer position
+    ///
+    /// Normalized (0.0 to 1.0) when pushed; in pixels when drained from a
+    /// coalescer with a target resolution.
+    MoveTo {
+        /// Horizontal position
+        x: f64,
+        /// Vertical position
+        y: f64,
+    },
+
+    /// Button click
+    Click(MouseButton),
+
+    /// Key press (a character or key name)
+    Key(String),
+}
+
+impl InputEvent {
+    /// Merge `next` into this event if both are mergeable motion of one kind
+    fn absorb(&mut self, next: &InputEvent) -> bool {
+        match (self, next) {
+            (InputEvent::MouseMove { dx, dy }, InputEvent::MouseMove { dx: ndx, dy: ndy })
+            | (InputEvent::Scroll { dx, dy }, InputEvent::Scroll { dx: ndx, dy: ndy }) => {
+                *dx += ndx;
+                *dy += ndy;
+                true
+            }
+            (InputEvent::MoveTo { x, y }, InputEvent::MoveTo { x: nx, y: ny }) => {
+                *x = *nx;
+                *y = *ny;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Merges bursts of motion events while keeping discrete events in order
+#[derive(Debug, Clone)]
+pub struct InputCoalescer {
+    /// Window within which motion is merged
+    window: Duration,
+    /// Resolution absolute positions are translated to
+    target: Option<(u32, u32)>,
+    /// Events waiting to be drained
+    queue: Vec<InputEvent>,
+    /// When the last queued event was first pushed
+    last_started: Option<Instant>,
+}
+
+impl Default for InputCoalescer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InputCoalescer {
+    /// Create a coalescer with [`DEFAULT_COALESCE_WINDOW`]
+    pub fn new() -> Self {
+        Self {
+            window: DEFAULT_COALESCE_WINDOW,
+            target: None,
+            queue: Vec::new(),
+            last_started: None,
+        }
+    }
+
+    /// Set the window within which motion events are merged
+    pub fn with_window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Translate absolute positions to a `width` x `height` pixel screen
+    pub fn with_target_resolution(mut self, width: u32, height: u32) -> Self {
+        self.target = Some((width, height));
+        self
+    }
+
+    /// Queue an event
+    pub fn push(&mut self, event: InputEvent) {
+        self.push_at(event, Instant::now());
+    }
+
+    /// Queue an event as if it arrived at `now`
+    pub fn push_at(&mut self, event: InputEvent, now: Instant) {
+        let event = self.translate(event);
+
+        let in_window = self
+            .last_started
+            .is_some_and(|started| now.saturating_duration_since(started) <= self.window);
+        if in_window {
+            if let Some(last) = self.queue.last_mut() {
+                if last.absorb(&event) {
+                    return;
+                }
+            }
+        }
+
+        self.queue.push(event);
+        self.last_started = Some(now);
+    }
+
+    /// Take all queued events in order
+    pub fn drain(&mut self) -> Vec<InputEvent> {
+        self.last_started = None;
+        std::mem::take(&mut self.queue)
+    }
+
+    /// Number of queued events
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Check whether no events are queued
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Map a normalized absolute position to the target resolution
+    fn translate(&self, event: InputEvent) -> InputEvent {
+        match (event, self.target) {
+            (InputEvent::MoveTo { x, y }, Some((width, height))) => InputEvent::MoveTo {
+                x: scale(x, width),
+                y: scale(y, height),
+            },
+            (event, _) => event,
+        }
+    }
+}
+
+/// Scale a normalized coordinate to a pixel index in `0..size`
+fn scale(value: f64, size: u32) -> f64 {
+    let max = size.saturating_sub(1) as f64;
+    (value.clamp(0.0, 1.0) * max).round()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn moved(dx: f64, dy: f64) -> InputEvent {
+        InputEvent::MouseMove { dx, dy }
+    }
+
+    #[test]
+    fn test_moves_coalesce_around_discrete_click() {
+        let mut coalescer = InputCoalescer::new();
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        coalescer.push_at(moved(1.0, 2.0), at(0));
+        coalescer.push_at(moved(3.0, -1.0), at(2));
+        coalescer.push_at(moved(1.0, 1.0), at(4));
+        coalescer.push_at(InputEvent::Click(MouseButton::Left), at(5));
+        coalescer.push_at(InputEvent::Click(MouseButton::Left), at(5));
+        coalescer.push_at(moved(2.0, 0.0), at(6));
+        coalescer.push_at(moved(2.0, 0.0), at(8));
+        coalescer.push_at(InputEvent::Key("a".to_string()), at(9));
+
+        assert_eq!(
+            coalescer.drain(),
+            vec![
+                moved(5.0, 2.0),
+                InputEvent::Click(MouseButton::Left),
+                InputEvent::Click(MouseButton::Left),
+                moved(4.0, 0.0),
+                InputEvent::Key("a".to_string()),
+            ]
+        );
+        assert!(coalescer.is_empty());
+    }
+
+    #[test]
+    fn test_window_and_kind_limit_coalescing() {
+        let mut coalescer = InputCoalescer::new().with_window(Duration::from_millis(10));
+        let start = Instant::now();
+
+        let at = |ms| start + Duration::from_millis(ms);
+
+        coalescer.push_at(moved(1.0, 0.0), at(0));
+        coalescer.push_at(moved(1.0, 0.0), at(20));
+        coalescer.push_at(InputEvent::Scroll { dx: 0.0, dy: 1.0 }, at(21));
+        coalescer.push_at(InputEvent::Scroll { dx: 0.0, dy: 2.0 }, at(22));
+
+        assert_eq!(
+            coalescer.drain(),
+            vec![
+                moved(1.0, 0.0),
+                moved(1.0, 0.0),
+                InputEvent::Scroll { dx: 0.0, dy: 3.0 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_absolute_positions_translate_to_target() {
+        let mut coalescer = InputCoalescer::new().with_target_resolution(1920, 1080);
+        let start = Instant::now();
+
+        coalescer.push_at(InputEvent::MoveTo { x: 0.1, y: 0.1 }, start);
+        coalescer.push_at(InputEvent::MoveTo { x: 0.5, y: 1.5 }, start);
+
+        // Only the latest position survives, clamped to the screen
+        assert_eq!(
+            coalescer.drain(),
+            vec![InputEvent::MoveTo {
+                x: 960.0,
+                y: 1079.0
+            }]
+        );
+    }
+}
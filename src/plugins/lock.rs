@@ -2,10 +2,19 @@
 //!
 //! Allows locking/unlocking the remote device's screen.
 //! Reports lock status and accepts lock/unlock commands.
+//!
+//! ## Packet Types
+//!
+//! - `cconnect.lock` - Lock state report (`isLocked`)
+//! - `cconnect.lock.request` - Either a state query (empty body or
+//!   `requestLocked`) or a set command (`setLocked`)
 
+use crate::error::{ProtocolError, Result};
+use crate::plugins::Plugin;
 use crate::protocol::Packet;
-use crate::error::Result;
+use async_trait::async_trait;
 use serde_json::json;
+use tracing::{debug, info, warn};
 
 /// Lock status packet type
 pub const PACKET_TYPE_LOCK: &str = "cconnect.lock";
@@ -27,6 +36,170 @@ pub fn create_lock_status_request() -> Result<Packet> {
     Ok(Packet::new(PACKET_TYPE_LOCK_REQUEST, json!({"requestLocked": true})))
 }
 
+/// Lock state of a device
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LockState {
+    /// Whether the screen is locked
+    pub locked: bool,
+}
+
+impl LockState {
+    /// Parse a `cconnect.lock` state report
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::InvalidPacket` if `isLocked` is missing
+    pub fn from_packet(packet: &Packet) -> Result<Self> {
+        packet
+            .get_body_field::<bool>("isLocked")
+            .map(|locked| Self { locked })
+            .ok_or_else(|| ProtocolError::InvalidPacket("Missing isLocked".to_string()))
+    }
+
+    /// Create a `cconnect.lock` state report
+    pub fn to_packet(&self) -> Packet {
+        Packet::new(PACKET_TYPE_LOCK, json!({"isLocked": self.locked}))
+    }
+}
+
+/// A parsed `cconnect.lock.request`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockRequest {
+    /// Report the current lock state (empty body or `requestLocked`)
+    QueryState,
+
+    /// Lock or unlock the screen
+    SetLocked(bool),
+}
+
+impl LockRequest {
+    /// Parse a lock request packet
+    ///
+    /// A body carrying `setLocked` is a set command; anything else,
+    /// including an empty body, is a state query.
+    pub fn from_packet(packet: &Packet) -> Self {
+        match packet.get_body_field::<bool>("setLocked") {
+            Some(locked) => LockRequest::SetLocked(locked),
+            None => LockRequest::QueryState,
+        }
+    }
+}
+
+/// Lock plugin
+///
+/// Caches the remote device's reported lock state and queues incoming
+/// requests for platform code, which performs the actual lock/unlock and
+/// replies with [`LockPlugin::create_state_packet`].
+pub struct LockPlugin {
+    /// Plugin name
+    name: String,
+
+    /// Local lock state (set by platform code)
+    local_state: Option<LockState>,
+
+    /// Last state reported by the remote device
+    remote_state: Option<LockState>,
+
+    /// Requests received from the remote device, oldest first
+    pending_requests: Vec<LockRequest>,
+}
+
+impl LockPlugin {
+    /// Create a new lock plugin
+    pub fn new() -> Self {
+        Self {
+            name: "lock".to_string(),
+            local_state: None,
+            remote_state: None,
+            pending_requests: Vec::new(),
+        }
+    }
+
+    /// Create a request for the remote device's lock state
+    pub fn request_state(&self) -> Packet {
+        Packet::new(PACKET_TYPE_LOCK_REQUEST, json!({"requestLocked": true}))
+    }
+
+    /// Create a command locking or unlocking the remote device
+    pub fn set_locked(&self, locked: bool) -> Packet {
+        Packet::new(PACKET_TYPE_LOCK_REQUEST, json!({"setLocked": locked}))
+    }
+
+    /// Get the last lock state reported by the remote device
+    pub fn remote_state(&self) -> Option<LockState> {
+        self.remote_state
+    }
+
+    /// Update the local lock state
+    pub fn update_local_state(&mut self, state: LockState) {
+        self.local_state = Some(state);
+    }
+
+    /// Create a state report for the local lock state, if known
+    pub fn create_state_packet(&self) -> Option<Packet> {
+        self.local_state.map(|state| state.to_packet())
+    }
+
+    /// Take the requests received since the last call
+    pub fn take_requests(&mut self) -> Vec<LockRequest> {
+        std::mem::take(&mut self.pending_requests)
+    }
+}
+
+impl Default for LockPlugin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Plugin for LockPlugin {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn incoming_capabilities(&self) -> Vec<String> {
+        vec![
+            PACKET_TYPE_LOCK.to_string(),
+            PACKET_TYPE_LOCK_REQUEST.to_string(),
+        ]
+    }
+
+    fn outgoing_capabilities(&self) -> Vec<String> {
+        vec![
+            PACKET_TYPE_LOCK.to_string(),
+            PACKET_TYPE_LOCK_REQUEST.to_string(),
+        ]
+    }
+
+    async fn handle_packet(&mut self, packet: &Packet) -> Result<()> {
+        if packet.is_type(PACKET_TYPE_LOCK) {
+            let state = LockState::from_packet(packet)?;
+            info!("Remote lock state: locked={}", state.locked);
+            self.remote_state = Some(state);
+        } else if packet.is_type(PACKET_TYPE_LOCK_REQUEST) {
+            let request = LockRequest::from_packet(packet);
+            debug!("Lock request received: {:?}", request);
+            self.pending_requests.push(request);
+        } else {
+            warn!("Unexpected packet type: {}", packet.packet_type);
+        }
+
+        Ok(())
+    }
+
+    async fn initialize(&mut self) -> Result<()> {
+        info!("Lock plugin initialized");
+        Ok(())
+    }
+
+    async fn shutdown(&mut self) -> Result<()> {
+        info!("Lock plugin shutting down");
+        self.pending_requests.clear();
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -57,4 +230,46 @@ mod tests {
         assert_eq!(packet.packet_type, "cconnect.lock.request");
         assert_eq!(packet.body["requestLocked"], true);
     }
+
+    #[tokio::test]
+    async fn test_plugin_caches_state_report() {
+        let mut plugin = LockPlugin::new();
+        assert_eq!(plugin.remote_state(), None);
+
+        let report = LockState { locked: true }.to_packet();
+        plugin.handle_packet(&report).await.unwrap();
+        assert_eq!(plugin.remote_state(), Some(LockState { locked: true }));
+        assert_eq!(LockState::from_packet(&report).unwrap(), LockState { locked: true });
+
+        let bad = Packet::new(PACKET_TYPE_LOCK, json!({}));
+        assert!(plugin.handle_packet(&bad).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_plugin_parses_requests() {
+        let mut plugin = LockPlugin::new();
+
+        let set = plugin.set_locked(false);
+        assert_eq!(set.body["setLocked"], false);
+        plugin.handle_packet(&set).await.unwrap();
+
+        // Bare request with an empty body is a state query
+        let bare = Packet::new(PACKET_TYPE_LOCK_REQUEST, json!({}));
+        plugin.handle_packet(&bare).await.unwrap();
+        plugin.handle_packet(&plugin.request_state()).await.unwrap();
+
+        assert_eq!(
+            plugin.take_requests(),
+            vec![
+                LockRequest::SetLocked(false),
+                LockRequest::QueryState,
+                LockRequest::QueryState,
+            ]
+        );
+        assert!(plugin.take_requests().is_empty());
+
+        assert!(plugin.create_state_packet().is_none());
+        plugin.update_local_state(LockState { locked: false });
+        assert_eq!(plugin.create_state_packet().unwrap().body["isLocked"], false);
+    }
 }
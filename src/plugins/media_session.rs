@@ -0,0 +1,147 @@
+//! Media Session Negotiation
+//!
+//! Screen share and camera streams can be moved off the main connection onto
+//! a separate media transport (e.g. UDP/SRTP). The transport is negotiated
+//! WebRTC-style: one side sends an offer, the other an answer, each carrying
+//! an opaque session description and the ICE candidates it gathered. Control
+//! stays on the main channel.
+//!
+//! This module only models the exchanged data. The `cconnect.*.offer` and
+//! `cconnect.*.answer` packet types are defined by the plugins that use it.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use cosmic_ext_connect_core::plugins::media_session::{IceCandidate, SessionDescription};
+//!
+//! # fn example() -> cosmic_ext_connect_core::error::Result<()> {
+//! let offer = SessionDescription::new("session-1", "v=0\r\n...")
+//!     .with_candidate(IceCandidate::new("candidate:1 1 UDP 2122 192.168.1.20 49152 typ host"));
+//! let packet = offer.try_to_packet("cconnect.screenshare.offer")?;
+//! assert_eq!(SessionDescription::from_packet(&packet)?, offer);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{ProtocolError, Result};
+use crate::protocol::Packet;
+use serde::{Deserialize, Serialize};
+
+/// An ICE candidate gathered by one side of the negotiation
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct IceCandidate {
+    /// Candidate line, opaque to the protocol
+    pub candidate: String,
+    /// Media stream identification tag the candidate belongs to
+    #[serde(
+        rename = "sdpMid",
+        alias = "sdp_mid",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub sdp_mid: Option<String>,
+    /// Index of the media description the candidate belongs to
+    #[serde(
+        rename = "sdpMLineIndex",
+        alias = "sdp_m_line_index",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub sdp_m_line_index: Option<u32>,
+}
+
+impl IceCandidate {
+    /// Create a candidate not bound to a media description
+    pub fn new(candidate: impl Into<String>) -> Self {
+        Self {
+            candidate: candidate.into(),
+            sdp_mid: None,
+            sdp_m_line_index: None,
+        }
+    }
+
+    /// Builder: bind the candidate to a media description
+    pub fn with_media(mut self, sdp_mid: impl Into<String>, sdp_m_line_index: u32) -> Self {
+        self.sdp_mid = Some(sdp_mid.into());
+        self.sdp_m_line_index = Some(sdp_m_line_index);
+        self
+    }
+}
+
+/// Body of an offer or answer packet
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SessionDescription {
+    /// Identifies the negotiation; an answer echoes its offer's ID
+    #[serde(rename = "sessionId", alias = "session_id")]
+    pub session_id: String,
+    /// Session description blob, opaque to the protocol
+    pub sdp: String,
+    /// ICE candidates gathered so far
+    #[serde(default)]
+    pub candidates: Vec<IceCandidate>,
+}
+
+impl SessionDescription {
+    /// Create a description without candidates
+    pub fn new(session_id: impl Into<String>, sdp: impl Into<String>) -> Self {
+        Self {
+            session_id: session_id.into(),
+            sdp: sdp.into(),
+            candidates: Vec::new(),
+        }
+    }
+
+    /// Builder: add an ICE candidate
+    pub fn with_candidate(mut self, candidate: IceCandidate) -> Self {
+        self.candidates.push(candidate);
+        self
+    }
+
+    /// Parse from packet body
+    pub fn from_packet(packet: &Packet) -> Result<Self> {
+        serde_json::from_value(packet.body.clone())
+            .map_err(|e| ProtocolError::InvalidPacket(e.to_string()))
+    }
+
+    /// Create an offer or answer packet of `packet_type`
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Json` if the body cannot be serialized.
+    pub fn try_to_packet(&self, packet_type: &str) -> Result<Packet> {
+        Packet::try_new(packet_type, self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_session_description() {
+        let packet = Packet::new(
+            "cconnect.camera.answer",
+            json!({
+                "session_id": "s1",
+                "sdp": "v=0",
+                "candidates": [
+                    { "candidate": "candidate:1", "sdp_mid": "0", "sdp_m_line_index": 0 }
+                ]
+            }),
+        );
+        let answer = SessionDescription::from_packet(&packet).unwrap();
+        assert_eq!(answer.session_id, "s1");
+        assert_eq!(answer.candidates, vec![IceCandidate::new("candidate:1").with_media("0", 0)]);
+
+        let packet =
+            Packet::new("cconnect.camera.answer", json!({ "sessionId": "s1", "sdp": "v=0" }));
+        assert!(SessionDescription::from_packet(&packet).unwrap().candidates.is_empty());
+
+        let packet = Packet::new("cconnect.camera.answer", json!({ "sessionId": "s1" }));
+        assert!(matches!(
+            SessionDescription::from_packet(&packet),
+            Err(ProtocolError::InvalidPacket(_))
+        ));
+    }
+}
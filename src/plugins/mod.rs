@@ -13,6 +13,7 @@
 //! - [`Plugin`](trait@Plugin) - Trait that all plugins must implement
 //! - [`PluginManager`](struct@PluginManager) - Manages plugin lifecycle and routing
 //! - [`PluginMetadata`](struct@PluginMetadata) - Plugin information for display
+//! - [`ActionDescriptor`](struct@ActionDescriptor) - Callable plugin actions for FFI/DBus bridges
 //!
 //! ## Built-in Plugins
 //!
@@ -28,12 +29,14 @@
 //! ### Content Sharing Plugins
 //! - [`clipboard`](clipboard) - Clipboard sync
 //! - [`share`](share) - File/text/URL sharing
+//! - [`share_queue`](share_queue) - Multi-file share queue with retry
 //!
 //! ### Remote Control Plugins
 //! - [`remoteinput`](remoteinput) - Mouse/keyboard control
 //! - [`mpris`](mpris) - Media player control
 //! - [`runcommand`](runcommand) - Remote command execution
 //! - [`presenter`](presenter) - Presentation control
+//! - [`systemvolume`](systemvolume) - Remote audio sink volume control
 //!
 //! ### Utility Plugins
 //! - [`findmyphone`](findmyphone) - Find my phone
@@ -116,6 +119,7 @@
 // Module exports
 pub mod r#trait;       // ✅ Plugin trait
 pub mod manager;       // ✅ PluginManager
+pub mod rate_limit;    // ✅ Outgoing rate limits
 
 // Core plugins
 pub mod ping;          // ✅ Ping plugin
@@ -124,17 +128,12 @@ pub mod battery;       // ✅ Battery status monitoring
 // Communication plugins
 pub mod notification;   // ✅ Device architecture refactored for FFI
 pub mod notification_image; // ✅ Rich notification image support (Issue #126)
+pub mod telephony;      // ✅ Call events and SMS/MMS messages
 
 // ## Planned Communication Plugins
 //
 // The following communication plugins are planned but blocked on architecture work:
 //
-// ### telephony
-// - **Status**: Blocked
-// - **Requirements**: Device FFI refactoring (Issue #46)
-// - **Description**: Call and SMS notifications
-// - **Capabilities**: `kdeconnect.telephony`, `kdeconnect.telephony.request_mute`
-//
 // ### contacts
 // - **Status**: Blocked
 // - **Requirements**: Device FFI refactoring (Issue #46)
@@ -142,26 +141,22 @@ pub mod notification_image; // ✅ Rich notification image support (Issue #126)
 // - **Capabilities**: `kdeconnect.contacts.request_all_uids_timestamps`, `kdeconnect.contacts.request_vcards_by_uid`
 
 // Content sharing plugins
-
-// ## Planned Content Sharing Plugins
-//
-// ### clipboard
-// - **Status**: Blocked
-// - **Requirements**: Device FFI refactoring (Issue #46)
-// - **Description**: Clipboard synchronization across devices
-// - **Capabilities**: `kdeconnect.clipboard`, `kdeconnect.clipboard.connect`
-
-pub mod share;            // ✅  Phase 1 complete: Device dependencies removed (Issue #53)
+pub mod clipboard;        // ✅ Clipboard sync with conflict detection
+pub mod share;            // ✅ Phase 1 complete: Device dependencies removed (Issue #53)
+pub mod share_queue;      // ✅ Multi-file share queue with retry
 
 // Streaming plugins
-pub mod camera;           // ✅  Camera webcam streaming (Issue #99-#100)
-pub mod webcam;           // ✅  Webcam streaming (desktop → phone)
-pub mod audiostream;      // ✅  Audio streaming (Issue #153)
+pub mod camera;           // ✅ Camera webcam streaming (Issue #99-#100)
+pub mod webcam;           // ✅ Webcam streaming (desktop → phone)
+pub mod audiostream;      // ✅ Audio streaming (Issue #153)
 
 // App continuity plugins
-pub mod open;             // ✅  Open content on remote devices (Issue #113)
+pub mod open;             // ✅ Open content on remote devices (Issue #113)
 
 // Remote control plugins
+pub mod systemvolume;     // ✅ Remote audio sink volume control
+pub mod input;            // ✅ Input event coalescing for remote input and presenter
+pub mod mpris;            // ✅ Media player control
 
 // ## Planned Remote Control Plugins
 //
@@ -174,12 +169,6 @@ pub mod open;             // ✅  Open content on remote devices (Issue #113)
 // - **Capabilities**: `kdeconnect.mousepad.request`, `kdeconnect.mousepad.keyboardstate`
 // - **Notes**: Needs abstraction layer for Android/Desktop platform differences
 //
-// ### mpris
-// - **Status**: Blocked
-// - **Requirements**: Device FFI refactoring (Issue #46)
-// - **Description**: Media player control via MPRIS2 protocol
-// - **Capabilities**: `kdeconnect.mpris`, `kdeconnect.mpris.request`
-//
 // ### runcommand
 // - **Status**: Blocked
 // - **Requirements**: Device FFI refactoring (Issue #46)
@@ -207,11 +196,15 @@ pub mod open;             // ✅  Open content on remote devices (Issue #113)
 pub mod lock;             // ✅ Lock/unlock device screen
 pub mod filesync;         // ✅ File synchronization
 pub mod screenshare;      // ✅ Screen sharing with configurable resolution and codec
+pub mod media_session;    // ✅ Offer/answer negotiation for media transports
 pub mod virtualmonitor;   // ✅ Virtual monitor plugin
 
 // Re-exports for convenience
-pub use r#trait::{Plugin, PluginMetadata};
-pub use manager::PluginManager;
+pub use r#trait::{ActionDescriptor, ActionParameter, ActionType, Plugin, PluginMetadata};
+pub use manager::{
+    DispatchOutcome, NamespaceWarning, PluginHealth, PluginManager, UnhandledPacketHandler,
+};
+pub use rate_limit::{RateLimit, CONTROL_RATE_LIMIT, INPUT_RATE_LIMIT, MEDIA_RATE_LIMIT};
 
 #[cfg(test)]
 mod tests {
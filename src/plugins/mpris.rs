@@ -180,15 +180,11 @@
 //!
 //! ```rust,ignore
 //! use cosmic_ext_connect_core::plugins::mpris::*;
-//! use cosmic_ext_connect_core::{Plugin, PluginManager};
+//! use cosmic_ext_connect_core::plugins::PluginManager;
 //!
 //! // Create and register plugin
 //! let mut manager = PluginManager::new();
-//! manager.register(Box::new(MprisPlugin::new()))?;
-//!
-//! // Initialize with device
-//! manager.init_all(&device).await?;
-//! manager.start_all().await?;
+//! manager.register_plugin(Box::new(MprisPlugin::new())).await?;
 //!
 //! // Send player list
 //! let plugin = MprisPlugin::new();
@@ -228,7 +224,7 @@ use std::sync::Arc;
 use tokio::sync::RwLock;
 use tracing::{debug, info};
 
-use crate::plugins::Plugin;
+use crate::plugins::{ActionDescriptor, ActionType, Plugin};
 
 /// Loop status for media playback
 ///
@@ -424,16 +420,13 @@ pub struct PlayerState {
 ///
 /// ```rust
 /// use cosmic_ext_connect_core::plugins::mpris::MprisPlugin;
-/// use cosmic_ext_connect_core::Plugin;
+/// use cosmic_ext_connect_core::plugins::Plugin;
 ///
 /// let plugin = MprisPlugin::new();
 /// assert_eq!(plugin.name(), "mpris");
 /// ```
 #[derive(Debug)]
 pub struct MprisPlugin {
-    /// Device ID this plugin is attached to
-    device_id: Option<String>,
-
     /// Map of player name to player state
     players: Arc<RwLock<HashMap<String, PlayerState>>>,
 
@@ -453,7 +446,6 @@ impl MprisPlugin {
     /// ```
     pub fn new() -> Self {
         Self {
-            device_id: None,
             players: Arc::new(RwLock::new(HashMap::new())),
             support_album_art: true,
         }
@@ -688,6 +680,25 @@ impl MprisPlugin {
         )
     }
 
+    /// Create a seek packet from an offset in milliseconds
+    ///
+    /// Backs the `seek` action. The offset is converted to the microseconds
+    /// the packet carries, saturating on overflow.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cosmic_ext_connect_core::plugins::mpris::MprisPlugin;
+    ///
+    /// let plugin = MprisPlugin::new();
+    /// // Seek back 5 seconds
+    /// let packet = plugin.create_seek_ms_packet("vlc".to_string(), -5000);
+    /// assert_eq!(packet.body["Seek"], -5_000_000);
+    /// ```
+    pub fn create_seek_ms_packet(&self, player: String, offset_ms: i64) -> Packet {
+        self.create_seek_packet(player, offset_ms.saturating_mul(1000))
+    }
+
     /// Create a set position packet
     ///
     /// Sets absolute playback position.
@@ -878,7 +889,7 @@ impl MprisPlugin {
     }
 
     /// Handle incoming MPRIS status packet
-    async fn handle_mpris_status(&self, packet: &Packet, device: &Device) {
+    async fn handle_mpris_status(&self, packet: &Packet) {
         // Check if this is a player list
         if let Some(player_list) = packet.body.get("playerList") {
             if let Some(players) = player_list.as_array() {
@@ -887,12 +898,7 @@ impl MprisPlugin {
                     .filter_map(|v| v.as_str().map(|s| s.to_string()))
                     .collect();
 
-                info!(
-                    "Received player list from {} ({}): {:?}",
-                    device.name(),
-                    device.id(),
-                    player_names
-                );
+                info!("Received player list: {:?}", player_names);
                 return;
             }
         }
@@ -991,9 +997,7 @@ impl MprisPlugin {
         };
 
         info!(
-            "Received player status from {} ({}): {} - {} / {}",
-            device.name(),
-            device.id(),
+            "Received player status: {} - {} / {}",
             player_name,
             if status.is_playing {
                 "playing"
@@ -1013,39 +1017,24 @@ impl MprisPlugin {
     }
 
     /// Handle incoming MPRIS request packet
-    fn handle_mpris_request(&self, packet: &Packet, device: &Device) {
+    fn handle_mpris_request(&self, packet: &Packet) {
         // Log the request for now (actual handling would be in application layer)
         if packet.body.get("requestPlayerList").is_some() {
-            info!(
-                "Received player list request from {} ({})",
-                device.name(),
-                device.id()
-            );
+            info!("Received player list request");
         } else if packet.body.get("requestNowPlaying").is_some() {
             let player = packet
                 .body
                 .get("player")
                 .and_then(|v| v.as_str())
                 .unwrap_or("unknown");
-            info!(
-                "Received now playing request from {} ({}) for player: {}",
-                device.name(),
-                device.id(),
-                player
-            );
+            info!("Received now playing request for player: {}", player);
         } else if let Some(action) = packet.body.get("action").and_then(|v| v.as_str()) {
             let player = packet
                 .body
                 .get("player")
                 .and_then(|v| v.as_str())
                 .unwrap_or("unknown");
-            info!(
-                "Received control action '{}' from {} ({}) for player: {}",
-                action,
-                device.name(),
-                device.id(),
-                player
-            );
+            info!("Received control action '{}' for player: {}", action, player);
         }
     }
 }
@@ -1062,7 +1051,6 @@ impl Plugin for MprisPlugin {
         "mpris"
     }
 
-
     fn incoming_capabilities(&self) -> Vec<String> {
         vec![
             "cconnect.mpris".to_string(),
@@ -1077,14 +1065,25 @@ impl Plugin for MprisPlugin {
         ]
     }
 
-    async fn initialize(&mut self) -> Result<()> {
-        self.device_id = Some(device.id().to_string());
-        info!("MPRIS plugin initialized for device {}", device.name());
-        Ok(())
+    fn actions(&self) -> Vec<ActionDescriptor> {
+        let control =
+            |name| ActionDescriptor::new(name).with_parameter("player", ActionType::String);
+        vec![
+            control("play"),
+            control("pause"),
+            control("play_pause"),
+            control("stop"),
+            control("next"),
+            control("previous"),
+            control("seek").with_parameter("offset_ms", ActionType::I64),
+            control("set_position").with_parameter("position_ms", ActionType::I64),
+            control("set_volume").with_parameter("volume", ActionType::I32),
+            ActionDescriptor::new("player_list").with_returns(ActionType::Object),
+        ]
     }
 
     async fn initialize(&mut self) -> Result<()> {
-        info!("MPRIS plugin started");
+        info!("MPRIS plugin initialized");
         Ok(())
     }
 
@@ -1097,10 +1096,10 @@ impl Plugin for MprisPlugin {
     async fn handle_packet(&mut self, packet: &Packet) -> Result<()> {
         match packet.packet_type.as_str() {
             "cconnect.mpris" => {
-                self.handle_mpris_status(packet, device).await;
+                self.handle_mpris_status(packet).await;
             }
             "cconnect.mpris.request" => {
-                self.handle_mpris_request(packet, device);
+                self.handle_mpris_request(packet);
             }
             _ => {}
         }
@@ -1108,18 +1107,9 @@ impl Plugin for MprisPlugin {
     }
 }
 
-/// Factory for creating MprisPlugin instances
-#[derive(Debug, Clone, Copy)]
-
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{DeviceInfo, DeviceType};
-
-    fn create_test_device() -> Device {
-        let info = DeviceInfo::new("Test Device", DeviceType::Desktop, 1816);
-        Device::from_discovery(info)
-    }
 
     #[test]
     fn test_loop_status() {
@@ -1174,13 +1164,36 @@ mod tests {
     #[tokio::test]
     async fn test_plugin_lifecycle() {
         let mut plugin = MprisPlugin::new();
-        let device = create_test_device();
+        plugin.initialize().await.unwrap();
+        plugin.shutdown().await.unwrap();
+    }
 
-        plugin.init(&device).await.unwrap();
-        assert!(plugin.device_id.is_some());
+    #[test]
+    fn test_actions() {
+        let plugin = MprisPlugin::new();
+        let actions = plugin.actions();
+        let names: Vec<_> = actions.iter().map(|action| action.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "play",
+                "pause",
+                "play_pause",
+                "stop",
+                "next",
+                "previous",
+                "seek",
+                "set_position",
+                "set_volume",
+                "player_list"
+            ]
+        );
 
-        plugin.start().await.unwrap();
-        plugin.stop().await.unwrap();
+        let seek = &actions[6];
+        assert_eq!(seek.parameters[0].param_type, ActionType::String);
+        assert_eq!(seek.parameters[1].name, "offset_ms");
+        assert_eq!(seek.parameters[1].param_type, ActionType::I64);
+        assert_eq!(seek.returns, ActionType::Unit);
     }
 
     #[test]
@@ -1281,6 +1294,12 @@ mod tests {
             packet.body.get("Seek").and_then(|v| v.as_i64()),
             Some(5_000_000)
         );
+
+        // The seek action takes milliseconds
+        let packet = plugin.create_seek_ms_packet("spotify".to_string(), -1500);
+        assert_eq!(packet.body["Seek"], -1_500_000);
+        let packet = plugin.create_seek_ms_packet("spotify".to_string(), i64::MAX);
+        assert_eq!(packet.body["Seek"], i64::MAX);
     }
 
     #[test]
@@ -1334,10 +1353,8 @@ mod tests {
     #[tokio::test]
     async fn test_handle_player_list() {
         let mut plugin = MprisPlugin::new();
-        let device = create_test_device();
-        plugin.init(&device).await.unwrap();
+        plugin.initialize().await.unwrap();
 
-        let mut device = create_test_device();
         let packet = Packet::new(
             "cconnect.mpris",
             json!({
@@ -1346,17 +1363,15 @@ mod tests {
             }),
         );
 
-        plugin.handle_packet(&packet, &mut device).await.unwrap();
+        plugin.handle_packet(&packet).await.unwrap();
         // Player list handled (logged)
     }
 
     #[tokio::test]
     async fn test_handle_player_status() {
         let mut plugin = MprisPlugin::new();
-        let device = create_test_device();
-        plugin.init(&device).await.unwrap();
+        plugin.initialize().await.unwrap();
 
-        let mut device = create_test_device();
         let packet = Packet::new(
             "cconnect.mpris",
             json!({
@@ -1378,7 +1393,7 @@ mod tests {
             }),
         );
 
-        plugin.handle_packet(&packet, &mut device).await.unwrap();
+        plugin.handle_packet(&packet).await.unwrap();
 
         let state = plugin.get_player_state("spotify").await.unwrap();
         assert_eq!(state.name, "spotify");
@@ -1436,10 +1451,8 @@ mod tests {
     #[tokio::test]
     async fn test_handle_control_request() {
         let mut plugin = MprisPlugin::new();
-        let device = create_test_device();
-        plugin.init(&device).await.unwrap();
+        plugin.initialize().await.unwrap();
 
-        let mut device = create_test_device();
         let packet = Packet::new(
             "cconnect.mpris.request",
             json!({
@@ -1448,7 +1461,7 @@ mod tests {
             }),
         );
 
-        plugin.handle_packet(&packet, &mut device).await.unwrap();
+        plugin.handle_packet(&packet).await.unwrap();
         // Request logged
     }
 }
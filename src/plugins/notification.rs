@@ -81,6 +81,17 @@
 //! - **Inline Replies**: Reply to messages directly (future)
 //! - **Icon Transfer**: Download notification icons (future)
 //!
+//! ## Grouping
+//!
+//! Phones often post many notifications from the same app. The plugin keeps
+//! a view of active notifications grouped by `appName` (see
+//! [`NotificationPlugin::notification_groups`]) and coalesces bursts: new
+//! notifications from an app arriving within the group window of the first
+//! one are reported as a single [`NotificationEvent::GroupSummary`] instead
+//! of one [`NotificationEvent::Posted`] each. Events are collected with
+//! [`NotificationPlugin::take_events`] once the window has passed.
+//! [`NotificationPlugin::create_group_dismiss_packets`] dismisses a whole group.
+//!
 //! ## Use Cases
 //!
 //! - See phone notifications on desktop
@@ -113,12 +124,13 @@
 //! - [Valent Protocol - Notification](https://valent.andyholmes.ca/documentation/protocol.html)
 
 use crate::error::Result;
-use crate::protocol::Packet;
+use crate::protocol::{DeviceId, Packet};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 use tracing::{debug, info, warn};
 
 use crate::plugins::Plugin;
@@ -547,6 +559,158 @@ impl Notification {
     }
 }
 
+/// Default window in which notifications from one app are coalesced
+pub const DEFAULT_GROUP_WINDOW: Duration = Duration::from_secs(2);
+
+/// Active notifications from one app
+#[derive(Debug, Clone, PartialEq)]
+pub struct NotificationGroup {
+    /// Source application name
+    pub app_name: String,
+
+    /// Number of active notifications
+    pub count: usize,
+
+    /// Most recently received notification
+    pub latest: Notification,
+
+    /// IDs of all active notifications, oldest first
+    pub ids: Vec<String>,
+}
+
+/// Something to show the user about new notifications
+#[derive(Debug, Clone, PartialEq)]
+pub enum NotificationEvent {
+    /// A single notification arrived
+    Posted(Notification),
+
+    /// Several notifications from one app arrived within the group window
+    GroupSummary {
+        /// Source application name
+        app_name: String,
+
+        /// Number of notifications in the burst
+        count: usize,
+
+        /// Most recent notification of the burst
+        latest: Notification,
+    },
+}
+
+/// Groups active notifications by app and coalesces bursts into events
+///
+/// New notifications are held per app until the group window, measured
+/// from the first of them, has passed; [`flush_at`](Self::flush_at) then
+/// turns each app's burst into one event. Silent (preexisting)
+/// notifications are grouped but produce no events.
+#[derive(Debug, Clone)]
+pub struct NotificationGrouper {
+    /// Window in which an app's notifications are coalesced
+    window: Duration,
+
+    /// Active notifications by app, oldest first
+    by_app: BTreeMap<String, Vec<Notification>>,
+
+    /// Start and notifications of each app's pending burst
+    bursts: BTreeMap<String, (Instant, Vec<Notification>)>,
+}
+
+impl NotificationGrouper {
+    /// Create a grouper coalescing bursts within `window`
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            by_app: BTreeMap::new(),
+            bursts: BTreeMap::new(),
+        }
+    }
+
+    /// Add or update a notification received at `now`
+    pub fn insert_at(&mut self, notification: Notification, now: Instant) {
+        self.remove(&notification.id);
+
+        if !notification.is_silent() {
+            self.bursts
+                .entry(notification.app_name.clone())
+                .or_insert_with(|| (now, Vec::new()))
+                .1
+                .push(notification.clone());
+        }
+        self.by_app
+            .entry(notification.app_name.clone())
+            .or_default()
+            .push(notification);
+    }
+
+    /// Remove a notification, returning whether it was active
+    pub fn remove(&mut self, id: &str) -> bool {
+        let mut removed = false;
+        self.by_app.retain(|_, notifications| {
+            let before = notifications.len();
+            notifications.retain(|notification| notification.id != id);
+            removed |= notifications.len() != before;
+            !notifications.is_empty()
+        });
+        self.bursts.retain(|_, (_, pending)| {
+            pending.retain(|notification| notification.id != id);
+            !pending.is_empty()
+        });
+        removed
+    }
+
+    /// Take events for bursts whose window has passed by `now`
+    pub fn flush_at(&mut self, now: Instant) -> Vec<NotificationEvent> {
+        let window = self.window;
+        let due: Vec<String> = self
+            .bursts
+            .iter()
+            .filter(|(_, (started, _))| now.saturating_duration_since(*started) >= window)
+            .map(|(app_name, _)| app_name.clone())
+            .collect();
+
+        due.into_iter()
+            .filter_map(|app_name| {
+                let (_, mut pending) = self.bursts.remove(&app_name)?;
+                let latest = pending.pop()?;
+                Some(if pending.is_empty() {
+                    NotificationEvent::Posted(latest)
+                } else {
+                    NotificationEvent::GroupSummary {
+                        app_name,
+                        count: pending.len() + 1,
+                        latest,
+                    }
+                })
+            })
+            .collect()
+    }
+
+    /// Get the group of an app's active notifications
+    pub fn group(&self, app_name: &str) -> Option<NotificationGroup> {
+        let notifications = self.by_app.get(app_name)?;
+        Some(NotificationGroup {
+            app_name: app_name.to_string(),
+            count: notifications.len(),
+            latest: notifications.last()?.clone(),
+            ids: notifications.iter().map(|n| n.id.clone()).collect(),
+        })
+    }
+
+    /// Get all groups, ordered by app name
+    pub fn groups(&self) -> Vec<NotificationGroup> {
+        self.by_app
+            .keys()
+            .filter_map(|app_name| self.group(app_name))
+            .collect()
+    }
+}
+
+impl Default for NotificationGrouper {
+    fn default() -> Self {
+        Self::new(DEFAULT_GROUP_WINDOW)
+    }
+}
+
 /// Notification sync plugin
 ///
 /// Handles notification mirroring between devices.
@@ -575,10 +739,13 @@ impl Notification {
 #[derive(Debug)]
 pub struct NotificationPlugin {
     /// Device ID this plugin is attached to
-    device_id: Option<String>,
+    device_id: Option<DeviceId>,
 
     /// Active notifications by ID
     notifications: Arc<RwLock<HashMap<String, Notification>>>,
+
+    /// Active notifications grouped by app
+    groups: Arc<RwLock<NotificationGrouper>>,
 }
 
 impl NotificationPlugin {
@@ -596,9 +763,65 @@ impl NotificationPlugin {
         Self {
             device_id: None,
             notifications: Arc::new(RwLock::new(HashMap::new())),
+            groups: Arc::new(RwLock::new(NotificationGrouper::default())),
+        }
+    }
+
+    /// Builder: set the device this plugin receives notifications from
+    pub fn with_device_id(self, device_id: DeviceId) -> Self {
+        Self {
+            device_id: Some(device_id),
+            ..self
+        }
+    }
+
+    /// Builder: set the window in which an app's notifications are coalesced
+    pub fn with_group_window(self, window: Duration) -> Self {
+        Self {
+            groups: Arc::new(RwLock::new(NotificationGrouper::new(window))),
+            ..self
         }
     }
 
+    /// Get active notifications grouped by app, ordered by app name
+    pub fn notification_groups(&self) -> Vec<NotificationGroup> {
+        self.groups
+            .read()
+            .ok()
+            .map(|groups| groups.groups())
+            .unwrap_or_default()
+    }
+
+    /// Take events for notification bursts whose group window has passed
+    ///
+    /// Call periodically (e.g. every group window) to show new notifications.
+    pub fn take_events(&self) -> Vec<NotificationEvent> {
+        self.take_events_at(Instant::now())
+    }
+
+    /// Take events for bursts whose group window has passed by `now`
+    pub fn take_events_at(&self, now: Instant) -> Vec<NotificationEvent> {
+        self.groups
+            .write()
+            .ok()
+            .map(|mut groups| groups.flush_at(now))
+            .unwrap_or_default()
+    }
+
+    /// Create dismiss packets for every active notification of an app
+    ///
+    /// Returns no packets if the app has no active notifications.
+    pub fn create_group_dismiss_packets(&self, app_name: &str) -> Vec<Packet> {
+        let ids = self
+            .groups
+            .read()
+            .ok()
+            .and_then(|groups| groups.group(app_name))
+            .map(|group| group.ids)
+            .unwrap_or_default();
+        ids.iter().map(|id| self.create_dismiss_packet(id)).collect()
+    }
+
     /// Get notification count
     ///
     /// # Example
@@ -720,12 +943,15 @@ impl NotificationPlugin {
 
     /// Handle incoming notification
     fn handle_notification(&self, packet: &Packet) {
-        let device_id = self.device_id.as_deref().unwrap_or("unknown");
+        let device_id = self.device_id.as_ref().map_or("unknown", DeviceId::as_str);
 
         // Check for cancel
         if let Some(is_cancel) = packet.body.get("isCancel").and_then(|v| v.as_bool()) {
             if is_cancel {
                 if let Some(id) = packet.body.get("id").and_then(|v| v.as_str()) {
+                    if let Ok(mut groups) = self.groups.write() {
+                        groups.remove(id);
+                    }
                     if let Ok(mut notifications) = self.notifications.write() {
                         notifications.remove(id);
                         info!(
@@ -749,6 +975,9 @@ impl NotificationPlugin {
                 if let Ok(mut notifications) = self.notifications.write() {
                     notifications.insert(id.clone(), notification.clone());
                 }
+                if let Ok(mut groups) = self.groups.write() {
+                    groups.insert_at(notification.clone(), Instant::now());
+                }
 
                 // Log notification
                 if silent {
@@ -787,7 +1016,7 @@ impl NotificationPlugin {
 
     /// Handle notification request
     fn handle_request(&self, packet: &Packet) {
-        let device_id = self.device_id.as_deref().unwrap_or("unknown");
+        let device_id = self.device_id.as_ref().map_or("unknown", DeviceId::as_str);
 
         // Check for request all
         if let Some(true) = packet.body.get("request").and_then(|v| v.as_bool()) {
@@ -814,7 +1043,7 @@ impl NotificationPlugin {
 
     /// Handle notification action
     fn handle_action(&self, packet: &Packet) {
-        let device_id = self.device_id.as_deref().unwrap_or("unknown");
+        let device_id = self.device_id.as_ref().map_or("unknown", DeviceId::as_str);
         let key = packet.body.get("key").and_then(|v| v.as_str());
         let action = packet.body.get("action").and_then(|v| v.as_str());
 
@@ -832,7 +1061,7 @@ impl NotificationPlugin {
 
     /// Handle notification reply
     fn handle_reply(&self, packet: &Packet) {
-        let device_id = self.device_id.as_deref().unwrap_or("unknown");
+        let device_id = self.device_id.as_ref().map_or("unknown", DeviceId::as_str);
         let reply_id = packet.body.get("requestReplyId").and_then(|v| v.as_str());
         let message = packet.body.get("message").and_then(|v| v.as_str());
 
@@ -987,11 +1216,85 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_plugin_lifecycle() {
+    async fn test_notification_grouping_and_group_dismiss() {
         let mut plugin = NotificationPlugin::new();
+        for (id, app) in [("1", "Messages"), ("2", "Mail"), ("3", "Messages"), ("4", "Messages")] {
+            let notif = Notification::new(id, app, format!("Title {}", id), "Text", true);
+            let packet = plugin.create_notification_packet(&notif);
+            plugin.handle_packet(&packet).await.unwrap();
+        }
+
+        let groups = plugin.notification_groups();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].app_name, "Mail");
+        assert_eq!(groups[0].count, 1);
+        assert_eq!(groups[1].app_name, "Messages");
+        assert_eq!(groups[1].count, 3);
+        assert_eq!(groups[1].latest.id, "4");
+
+        // Cancelling removes the notification from its group
+        plugin
+            .handle_packet(&plugin.create_cancel_packet("4"))
+            .await
+            .unwrap();
+        let messages = &plugin.notification_groups()[1];
+        assert_eq!(messages.ids, vec!["1", "3"]);
+        assert_eq!(messages.latest.id, "3");
+
+        let dismissals: Vec<_> = plugin
+            .create_group_dismiss_packets("Messages")
+            .into_iter()
+            .map(|packet| packet.body["cancel"].clone())
+            .collect();
+        assert_eq!(dismissals, vec![json!("1"), json!("3")]);
+        assert!(plugin.create_group_dismiss_packets("Unknown").is_empty());
+    }
 
-        // Set device ID manually for testing
-        plugin.device_id = Some("test-device-id".to_string());
+    #[test]
+    fn test_notification_burst_events() {
+        let start = Instant::now();
+        let window = Duration::from_secs(2);
+        let mut grouper = NotificationGrouper::new(window);
+
+        grouper.insert_at(Notification::new("1", "Messages", "A", "", true), start);
+        grouper.insert_at(Notification::new("2", "Mail", "B", "", true), start);
+        grouper.insert_at(
+            Notification::new("3", "Messages", "C", "", true),
+            start + Duration::from_millis(500),
+        );
+        grouper.insert_at(
+            Notification::new("4", "Messages", "D", "", true),
+            start + Duration::from_secs(1),
+        );
+        let mut silent = Notification::new("5", "Calendar", "E", "", true);
+        silent.silent = Some("true".to_string());
+        grouper.insert_at(silent, start);
+
+        // Nothing is reported until the window has passed
+        assert!(grouper.flush_at(start + Duration::from_secs(1)).is_empty());
+
+        let events = grouper.flush_at(start + window);
+        assert_eq!(events.len(), 2);
+        assert!(matches!(&events[0], NotificationEvent::Posted(n) if n.id == "2"));
+        match &events[1] {
+            NotificationEvent::GroupSummary {
+                app_name,
+                count,
+                latest,
+            } => {
+                assert_eq!(app_name, "Messages");
+                assert_eq!(*count, 3);
+                assert_eq!(latest.id, "4");
+            }
+            other => panic!("expected group summary, got {:?}", other),
+        }
+        assert!(grouper.flush_at(start + window * 2).is_empty());
+        assert_eq!(grouper.group("Calendar").unwrap().count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_plugin_lifecycle() {
+        let mut plugin = NotificationPlugin::new().with_device_id(DeviceId::generate());
         assert!(plugin.device_id.is_some());
 
         plugin.initialize().await.unwrap();
@@ -1051,7 +1354,7 @@ mod tests {
     #[tokio::test]
     async fn test_handle_notification() {
         let mut plugin = NotificationPlugin::new();
-        plugin.device_id = Some("test-device-id".to_string());
+        plugin.device_id = Some(DeviceId::generate());
 
         let notif = Notification::new("123", "Messages", "New Message", "Hello!", true);
         let packet = plugin.create_notification_packet(&notif);
@@ -1066,7 +1369,7 @@ mod tests {
     #[tokio::test]
     async fn test_handle_cancel_notification() {
         let mut plugin = NotificationPlugin::new();
-        plugin.device_id = Some("test-device-id".to_string());
+        plugin.device_id = Some(DeviceId::generate());
 
         // Add notification
         let notif = Notification::new("123", "Messages", "Title", "Text", true);
@@ -1086,7 +1389,7 @@ mod tests {
     #[tokio::test]
     async fn test_get_all_notifications() {
         let mut plugin = NotificationPlugin::new();
-        plugin.device_id = Some("test-device-id".to_string());
+        plugin.device_id = Some(DeviceId::generate());
 
         // Add multiple notifications
         for i in 1..=3 {
@@ -1108,7 +1411,7 @@ mod tests {
     #[tokio::test]
     async fn test_ignore_non_notification_packets() {
         let mut plugin = NotificationPlugin::new();
-        plugin.device_id = Some("test-device-id".to_string());
+        plugin.device_id = Some(DeviceId::generate());
 
         let packet = Packet::new("cconnect.ping", json!({}));
 
@@ -1335,7 +1638,7 @@ mod tests {
     #[tokio::test]
     async fn test_handle_rich_notification() {
         let mut plugin = NotificationPlugin::new();
-        plugin.device_id = Some("test-device".to_string());
+        plugin.device_id = Some(DeviceId::generate());
 
         // Create rich notification
         let mut notif = Notification::new("rich-123", "Messages", "Rich Message", "Content", true);
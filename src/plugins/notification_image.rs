@@ -156,7 +156,7 @@ impl NotificationImage {
     /// assert_eq!(hint.0, 8);  // width
     /// assert_eq!(hint.1, 8);  // height
     /// assert_eq!(hint.2, 32); // rowstride (8 * 4)
-    /// assert_eq!(hint.3, true); // has_alpha
+    /// assert!(hint.3); // has_alpha
     /// assert_eq!(hint.4, 8);  // bits_per_sample
     /// assert_eq!(hint.5, 4);  // channels
     /// assert_eq!(hint.6.len(), 256); // data (8 * 8 * 4)
@@ -496,7 +496,7 @@ mod tests {
         assert_eq!(hint.0, 8); // width
         assert_eq!(hint.1, 8); // height
         assert_eq!(hint.2, 32); // rowstride
-        assert_eq!(hint.3, true); // has_alpha
+        assert!(hint.3); // has_alpha
         assert_eq!(hint.4, 8); // bits_per_sample
         assert_eq!(hint.5, 4); // channels
         assert_eq!(hint.6.len(), 256); // data
@@ -107,10 +107,11 @@
 use serde::{Deserialize, Serialize};
 
 /// Content type for open requests
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
 #[serde(rename_all = "lowercase")]
 pub enum OpenContentType {
     /// URL to open in browser or appropriate app
+    #[default]
     Url,
     /// File path to open (requires file transfer)
     File,
@@ -118,12 +119,6 @@ pub enum OpenContentType {
     Text,
 }
 
-impl Default for OpenContentType {
-    fn default() -> Self {
-        Self::Url
-    }
-}
-
 /// Device capabilities for opening content
 ///
 /// Advertises what types of content a device can handle.
@@ -386,7 +381,7 @@ impl OpenResponse {
 // ============================================================================
 
 use crate::error::Result;
-use crate::protocol::Packet;
+use crate::protocol::{DeviceId, Packet};
 use async_trait::async_trait;
 use std::collections::HashSet;
 use std::net::IpAddr;
@@ -460,7 +455,7 @@ pub enum OpenError {
 /// Implements URL validation, security checks, and integration with xdg-open.
 pub struct OpenPlugin {
     /// Device ID this plugin is associated with
-    device_id: String,
+    device_id: DeviceId,
 
     /// Plugin configuration
     config: OpenPluginConfig,
@@ -468,17 +463,17 @@ pub struct OpenPlugin {
 
 impl OpenPlugin {
     /// Create a new OpenPlugin with default configuration
-    pub fn new(device_id: impl Into<String>) -> Self {
+    pub fn new(device_id: DeviceId) -> Self {
         Self {
-            device_id: device_id.into(),
+            device_id,
             config: OpenPluginConfig::default(),
         }
     }
 
     /// Create with custom configuration
-    pub fn with_config(device_id: impl Into<String>, config: OpenPluginConfig) -> Self {
+    pub fn with_config(device_id: DeviceId, config: OpenPluginConfig) -> Self {
         Self {
-            device_id: device_id.into(),
+            device_id,
             config,
         }
     }
@@ -930,7 +925,7 @@ mod tests {
 
     #[test]
     fn test_validate_url_allowed_schemes() {
-        let plugin = OpenPlugin::new("test-device");
+        let plugin = OpenPlugin::new(DeviceId::generate());
 
         // Valid schemes
         assert!(plugin.validate_url("https://example.com").is_ok());
@@ -942,7 +937,7 @@ mod tests {
 
     #[test]
     fn test_validate_url_blocked_schemes() {
-        let plugin = OpenPlugin::new("test-device");
+        let plugin = OpenPlugin::new(DeviceId::generate());
 
         // Blocked schemes
         assert!(matches!(
@@ -965,7 +960,7 @@ mod tests {
 
     #[test]
     fn test_validate_url_localhost() {
-        let plugin = OpenPlugin::new("test-device");
+        let plugin = OpenPlugin::new(DeviceId::generate());
 
         // Localhost variations
         assert!(matches!(
@@ -984,7 +979,7 @@ mod tests {
 
     #[test]
     fn test_validate_url_internal_ips() {
-        let plugin = OpenPlugin::new("test-device");
+        let plugin = OpenPlugin::new(DeviceId::generate());
 
         // Private IPv4 ranges
         assert!(matches!(
@@ -1013,7 +1008,7 @@ mod tests {
 
     #[test]
     fn test_validate_url_public_ips() {
-        let plugin = OpenPlugin::new("test-device");
+        let plugin = OpenPlugin::new(DeviceId::generate());
 
         // Public IPs should be allowed
         assert!(plugin.validate_url("http://8.8.8.8").is_ok());
@@ -1023,7 +1018,7 @@ mod tests {
 
     #[test]
     fn test_is_blocked_ip_ipv4() {
-        let plugin = OpenPlugin::new("test-device");
+        let plugin = OpenPlugin::new(DeviceId::generate());
 
         // Private ranges
         assert!(plugin.is_blocked_ip(&"10.0.0.1".parse().unwrap()));
@@ -1048,7 +1043,7 @@ mod tests {
 
     #[test]
     fn test_is_blocked_ip_ipv6() {
-        let plugin = OpenPlugin::new("test-device");
+        let plugin = OpenPlugin::new(DeviceId::generate());
 
         // Loopback
         assert!(plugin.is_blocked_ip(&"::1".parse().unwrap()));
@@ -1063,7 +1058,7 @@ mod tests {
 
     #[test]
     fn test_validate_url_malformed() {
-        let plugin = OpenPlugin::new("test-device");
+        let plugin = OpenPlugin::new(DeviceId::generate());
 
         // Malformed URLs
         assert!(matches!(
@@ -1078,10 +1073,12 @@ mod tests {
 
     #[test]
     fn test_config_disable_host_blocking() {
-        let mut config = OpenPluginConfig::default();
-        config.block_internal_hosts = false;
+        let config = OpenPluginConfig {
+            block_internal_hosts: false,
+            ..Default::default()
+        };
 
-        let plugin = OpenPlugin::with_config("test-device", config);
+        let plugin = OpenPlugin::with_config(DeviceId::generate(), config);
 
         // Internal IPs should be allowed when blocking is disabled
         assert!(plugin.validate_url("http://192.168.1.1").is_ok());
@@ -1094,7 +1091,7 @@ mod tests {
         let mut config = OpenPluginConfig::default();
         config.allowed_schemes.insert("ftp".to_string());
 
-        let plugin = OpenPlugin::with_config("test-device", config);
+        let plugin = OpenPlugin::with_config(DeviceId::generate(), config);
 
         // FTP should now be allowed
         assert!(plugin.validate_url("ftp://example.com").is_ok());
@@ -1102,7 +1099,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_plugin_trait_impl() {
-        let mut plugin = OpenPlugin::new("test-device");
+        let mut plugin = OpenPlugin::new(DeviceId::generate());
 
         assert_eq!(plugin.name(), "open");
 
@@ -1120,7 +1117,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_handle_packet_url_request() {
-        let mut plugin = OpenPlugin::new("test-device");
+        let mut plugin = OpenPlugin::new(DeviceId::generate());
 
         let packet = Packet::new(
             "cconnect.open.request",
@@ -1139,7 +1136,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_handle_packet_invalid_json() {
-        let mut plugin = OpenPlugin::new("test-device");
+        let mut plugin = OpenPlugin::new(DeviceId::generate());
 
         let packet = Packet::new(
             "cconnect.open.request",
@@ -1155,10 +1152,12 @@ mod tests {
 
     #[tokio::test]
     async fn test_handle_open_request_url() {
-        let mut config = OpenPluginConfig::default();
-        config.auto_open_trusted = false; // User confirmation required
+        let config = OpenPluginConfig {
+            auto_open_trusted: false, // User confirmation required
+            ..Default::default()
+        };
 
-        let plugin = OpenPlugin::with_config("test-device", config);
+        let plugin = OpenPlugin::with_config(DeviceId::generate(), config);
 
         let request = OpenRequest::new_url(
             "req-001".to_string(),
@@ -1175,7 +1174,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_handle_open_request_file_not_implemented() {
-        let plugin = OpenPlugin::new("test-device");
+        let plugin = OpenPlugin::new(DeviceId::generate());
 
         let request = OpenRequest::new_file(
             "req-002".to_string(),
@@ -1193,7 +1192,7 @@ mod tests {
 
     #[test]
     fn test_create_response_packet() {
-        let plugin = OpenPlugin::new("test-device");
+        let plugin = OpenPlugin::new(DeviceId::generate());
 
         let response = OpenResponse::success("req-001".to_string(), Some("Firefox".to_string()));
         let packet = plugin.create_response_packet(response);
@@ -1205,7 +1204,7 @@ mod tests {
 
     #[test]
     fn test_create_capability_packet() {
-        let plugin = OpenPlugin::new("test-device");
+        let plugin = OpenPlugin::new(DeviceId::generate());
 
         let packet = plugin.create_capability_packet();
 
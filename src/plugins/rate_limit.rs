@@ -0,0 +1,166 @@
+//! Outgoing Rate Limits
+//!
+//! A buggy plugin, or a peer that keeps triggering one, could flood the link
+//! with packets. Each plugin declares an outgoing [`RateLimit`] with
+//! [`Plugin::outgoing_rate_limit`](crate::plugins::Plugin::outgoing_rate_limit):
+//! control plugins keep the strict [`CONTROL_RATE_LIMIT`], while realtime
+//! media plugins declare the higher [`MEDIA_RATE_LIMIT`] and remote input
+//! the [`INPUT_RATE_LIMIT`]. Plugins that are packet helpers rather than
+//! [`Plugin`](crate::plugins::Plugin) implementations declare an
+//! `OUTGOING_RATE_LIMIT` constant instead, which applies under their plugin
+//! name. [`PluginManager::check_outgoing`](crate::plugins::PluginManager::check_outgoing)
+//! enforces the limit before a packet is sent, and
+//! [`TlsConnection::send_plugin_packet`](crate::crypto::TlsConnection::send_plugin_packet)
+//! calls it on the send path.
+//!
+//! Limits are token buckets that hold one second's worth of packets and
+//! bytes, so a plugin may burst up to its per-second limit and is then held
+//! to the sustained rate. Bytes are counted as the serialized packet;
+//! payloads travel over their own connections and are not counted.
+
+use crate::plugins::{audiostream, input, screenshare, virtualmonitor, webcam};
+use std::time::{Duration, Instant};
+
+/// Limit for plugins exchanging commands and status
+pub const CONTROL_RATE_LIMIT: RateLimit = RateLimit {
+    packets_per_sec: 50,
+    bytes_per_sec: 256 * 1024,
+};
+
+/// Limit for plugins streaming realtime media
+pub const MEDIA_RATE_LIMIT: RateLimit = RateLimit {
+    packets_per_sec: 1000,
+    bytes_per_sec: 64 * 1024 * 1024,
+};
+
+/// Limit for remote input, sent as motion coalesced per 60Hz frame plus
+/// clicks and keys
+pub const INPUT_RATE_LIMIT: RateLimit = RateLimit {
+    packets_per_sec: 250,
+    bytes_per_sec: 256 * 1024,
+};
+
+/// Limits of plugins that are packet helpers, by plugin name
+const HELPER_RATE_LIMITS: [(&str, RateLimit); 5] = [
+    ("audiostream", audiostream::OUTGOING_RATE_LIMIT),
+    ("input", input::OUTGOING_RATE_LIMIT),
+    ("screenshare", screenshare::OUTGOING_RATE_LIMIT),
+    ("virtualmonitor", virtualmonitor::OUTGOING_RATE_LIMIT),
+    ("webcam", webcam::OUTGOING_RATE_LIMIT),
+];
+
+/// Get the limit a packet helper plugin declares
+pub(crate) fn helper_rate_limit(name: &str) -> Option<RateLimit> {
+    HELPER_RATE_LIMITS
+        .iter()
+        .find(|(helper, _)| *helper == name)
+        .map(|(_, limit)| *limit)
+}
+
+/// Sustained outgoing rate a plugin may send at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    /// Packets per second (minimum 1)
+    pub packets_per_sec: u32,
+    /// Serialized packet bytes per second (minimum 1)
+    pub bytes_per_sec: u64,
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        CONTROL_RATE_LIMIT
+    }
+}
+
+/// Token buckets enforcing a [`RateLimit`]
+#[derive(Debug, Clone)]
+pub(crate) struct RateLimiter {
+    limit: RateLimit,
+    /// Packets that may be sent now
+    packets: f64,
+    /// Bytes that may be sent now
+    bytes: f64,
+    /// When the buckets were last refilled
+    refilled: Instant,
+}
+
+impl RateLimiter {
+    /// Create a limiter with full buckets
+    pub(crate) fn new(limit: RateLimit, now: Instant) -> Self {
+        let limit = RateLimit {
+            packets_per_sec: limit.packets_per_sec.max(1),
+            bytes_per_sec: limit.bytes_per_sec.max(1),
+        };
+        Self {
+            limit,
+            packets: limit.packets_per_sec as f64,
+            bytes: limit.bytes_per_sec as f64,
+            refilled: now,
+        }
+    }
+
+    /// Get the enforced limit
+    pub(crate) fn limit(&self) -> RateLimit {
+        self.limit
+    }
+
+    /// Take one packet of `size` bytes from the buckets
+    ///
+    /// Returns how long until the packet would fit if the buckets are too
+    /// empty. A packet larger than one second's worth of bytes fits once
+    /// the byte bucket is full.
+    pub(crate) fn admit(&mut self, size: usize, now: Instant) -> Result<(), Duration> {
+        let packet_rate = self.limit.packets_per_sec as f64;
+        let byte_rate = self.limit.bytes_per_sec as f64;
+
+        let elapsed = now.saturating_duration_since(self.refilled).as_secs_f64();
+        self.packets = (self.packets + elapsed * packet_rate).min(packet_rate);
+        self.bytes = (self.bytes + elapsed * byte_rate).min(byte_rate);
+        self.refilled = now;
+
+        let size = (size as f64).min(byte_rate);
+        let wait = ((1.0 - self.packets) / packet_rate).max((size - self.bytes) / byte_rate);
+        if wait > 0.0 {
+            return Err(Duration::from_secs_f64(wait));
+        }
+
+        self.packets -= 1.0;
+        self.bytes -= size;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limiter_buckets() {
+        let start = Instant::now();
+        let limit = RateLimit {
+            packets_per_sec: 10,
+            bytes_per_sec: 1000,
+        };
+        let mut limiter = RateLimiter::new(limit, start);
+
+        // A one-second burst, then packets are held to the sustained rate
+        for _ in 0..10 {
+            limiter.admit(10, start).unwrap();
+        }
+        assert_eq!(limiter.admit(10, start), Err(Duration::from_millis(100)));
+        let later = start + Duration::from_millis(100);
+        limiter.admit(10, later).unwrap();
+        assert!(limiter.admit(10, later).is_err());
+
+        // Bytes run out before packets do
+        let mut limiter = RateLimiter::new(limit, start);
+        limiter.admit(800, start).unwrap();
+        assert_eq!(limiter.admit(400, start), Err(Duration::from_millis(200)));
+        limiter.admit(200, start).unwrap();
+
+        // Oversized packets wait for a full bucket
+        let later = start + Duration::from_secs(1);
+        assert!(limiter.admit(5000, start).is_err());
+        limiter.admit(5000, later).unwrap();
+    }
+}
@@ -2,15 +2,28 @@
 //!
 //! Allows screen sharing between devices with configurable resolution, codec, and direction.
 //! Reports sharing status and accepts control requests.
+//!
+//! The media transport can be negotiated out of band with
+//! `cconnect.screenshare.offer` and `cconnect.screenshare.answer` packets
+//! carrying a [`SessionDescription`].
 
+use crate::plugins::media_session::SessionDescription;
+use crate::plugins::{RateLimit, MEDIA_RATE_LIMIT};
 use crate::protocol::Packet;
-use crate::error::Result;
+use crate::error::{ProtocolError, Result};
 use serde_json::{json, Value};
 
 /// ScreenShare status packet type
 pub const PACKET_TYPE_SCREENSHARE: &str = "cconnect.screenshare";
 /// ScreenShare request packet type
 pub const PACKET_TYPE_SCREENSHARE_REQUEST: &str = "cconnect.screenshare.request";
+/// ScreenShare media session offer packet type
+pub const PACKET_TYPE_SCREENSHARE_OFFER: &str = "cconnect.screenshare.offer";
+/// ScreenShare media session answer packet type
+pub const PACKET_TYPE_SCREENSHARE_ANSWER: &str = "cconnect.screenshare.answer";
+
+/// Outgoing rate limit for this plugin, which streams realtime media
+pub const OUTGOING_RATE_LIMIT: RateLimit = MEDIA_RATE_LIMIT;
 
 /// Create a screen share status packet
 ///
@@ -89,9 +102,46 @@ pub fn create_screenshare_stop_request() -> Result<Packet> {
     ))
 }
 
+/// Create a media session offer packet
+///
+/// # Errors
+///
+/// Returns `ProtocolError::Json` if the description cannot be serialized.
+pub fn create_screenshare_offer(offer: &SessionDescription) -> Result<Packet> {
+    offer.try_to_packet(PACKET_TYPE_SCREENSHARE_OFFER)
+}
+
+/// Create a media session answer packet
+///
+/// # Errors
+///
+/// Returns `ProtocolError::Json` if the description cannot be serialized.
+pub fn create_screenshare_answer(answer: &SessionDescription) -> Result<Packet> {
+    answer.try_to_packet(PACKET_TYPE_SCREENSHARE_ANSWER)
+}
+
+/// Parse a media session offer or answer packet
+///
+/// # Errors
+///
+/// Returns `ProtocolError::InvalidPacket` if the packet is not an offer or
+/// answer, or its body is malformed.
+pub fn parse_screenshare_session(packet: &Packet) -> Result<SessionDescription> {
+    match packet.packet_type.as_str() {
+        PACKET_TYPE_SCREENSHARE_OFFER | PACKET_TYPE_SCREENSHARE_ANSWER => {
+            SessionDescription::from_packet(packet)
+        }
+        other => Err(ProtocolError::InvalidPacket(format!(
+            "Expected screenshare offer or answer, got {}",
+            other
+        ))),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::plugins::media_session::IceCandidate;
 
     #[test]
     fn test_create_screenshare_status_sharing() {
@@ -188,4 +238,30 @@ mod tests {
             .unwrap();
         assert_eq!(packet2.body["direction"], "desktop_to_phone");
     }
+
+    #[test]
+    fn test_screenshare_offer_answer_round_trip() {
+        let offer = SessionDescription::new("share-1", "v=0\r\no=- 1 1 IN IP4 0.0.0.0\r\n")
+            .with_candidate(IceCandidate::new("candidate:1 1 UDP 2122 192.168.1.20 49152 typ host"))
+            .with_candidate(IceCandidate::new("candidate:2 1 UDP 1686 203.0.113.7 5000 typ srflx"));
+        let packet = create_screenshare_offer(&offer).unwrap();
+        assert_eq!(packet.packet_type, "cconnect.screenshare.offer");
+        assert_eq!(packet.body["sessionId"], "share-1");
+        assert_eq!(packet.body["candidates"].as_array().unwrap().len(), 2);
+        assert_eq!(parse_screenshare_session(&packet).unwrap(), offer);
+
+        let candidate = IceCandidate::new("candidate:1 1 UDP 2122 192.168.1.30 50000 typ host");
+        let answer = SessionDescription::new("share-1", "v=0\r\n")
+            .with_candidate(candidate.with_media("0", 0));
+        let packet = create_screenshare_answer(&answer).unwrap();
+        assert_eq!(packet.packet_type, "cconnect.screenshare.answer");
+        assert_eq!(packet.body["candidates"][0]["sdpMLineIndex"], 0);
+        assert_eq!(parse_screenshare_session(&packet).unwrap(), answer);
+
+        let stop = create_screenshare_stop_request().unwrap();
+        assert!(matches!(
+            parse_screenshare_session(&stop),
+            Err(ProtocolError::InvalidPacket(_))
+        ));
+    }
 }
@@ -35,6 +35,10 @@
 //! }
 //! ```
 //!
+//! The optional `mimeType` field declares the file's MIME type. When it is
+//! missing, the receiver guesses from the filename extension and can refine
+//! the guess from the payload's first bytes (see [`detect_mime_type`]).
+//!
 //! ### Text Sharing
 //!
 //! Shares text content between devices. The receiving device decides how to present it.
@@ -63,6 +67,15 @@
 //! }
 //! ```
 //!
+//! ### Opening Shares
+//!
+//! Text and URL shares may set `"open": true` to ask the desktop to open
+//! them right away (URLs in the browser, text in an editor). These are
+//! passed to the [`OpenHandler`] set with [`SharePlugin::with_open_handler`].
+//! URLs are only opened if their scheme is on the plugin's allowlist
+//! ([`DEFAULT_OPEN_SCHEMES`] by default), so `file:` and `javascript:` URLs
+//! are refused unless explicitly allowed with [`SharePlugin::allow_open_scheme`].
+//!
 //! ### Multi-File Transfer
 //!
 //! For composite transfers, an update packet is sent first with totals:
@@ -78,6 +91,17 @@
 //! }
 //! ```
 //!
+//! ### Directory Structure
+//!
+//! When a folder is shared, each file may carry a `relativePath` with its
+//! directory relative to the shared root (e.g. `"Photos/2024"`), or the
+//! `filename` itself may contain subdirectories. [`ShareReceiver`] recreates
+//! that tree under a destination root and rejects any path that would escape it.
+//!
+//! If a file with the same name already exists, the receiver's
+//! [`CollisionPolicy`] decides whether the new file replaces it or is saved
+//! under a numbered name such as `photo (1).jpg`.
+//!
 //! ## Payload Transfer
 //!
 //! File payloads are transferred via TCP:
@@ -86,6 +110,11 @@
 //! 3. Raw file bytes are transferred
 //! 4. Connection closes when `payloadSize` bytes received
 //!
+//! [`PayloadReceiver::receive_file`](crate::protocol::PayloadReceiver::receive_file)
+//! writes the bytes to a `.part` file and renames it to the path from
+//! [`ShareReceiver::prepare`] only after the transfer completes and verifies,
+//! removing it if the transfer fails or is cancelled.
+//!
 //! The plugin handles packet creation and metadata. Actual payload transfer
 //! is handled by the transport layer.
 //!
@@ -119,6 +148,8 @@
 //!     creation_time: Some(1640000000000),
 //!     last_modified: Some(1640000000000),
 //!     open: false,
+//!     relative_path: None,
+//!     mime_type: None,
 //! };
 //! let packet = plugin.create_file_packet(file_info, 1739);
 //! // Send packet and handle payload transfer...
@@ -128,14 +159,17 @@
 //!
 //! - [Valent Protocol Documentation](https://valent.andyholmes.ca/documentation/protocol.html)
 
-use crate::{Packet, Result};
+use crate::{Packet, ProtocolError, Result};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tracing::{debug, info, warn};
+use url::Url;
 
 use super::Plugin;
 
@@ -150,6 +184,8 @@ use super::Plugin;
 /// - `creation_time`: UNIX epoch timestamp in milliseconds (optional)
 /// - `last_modified`: Last modification timestamp in milliseconds (optional)
 /// - `open`: Whether to auto-open the file after transfer (default: false)
+/// - `relative_path`: Directory relative to the shared folder root (optional)
+/// - `mime_type`: MIME type, declared by the sender or detected (optional)
 ///
 /// ## Example
 ///
@@ -162,6 +198,8 @@ use super::Plugin;
 ///     creation_time: Some(1640000000000),
 ///     last_modified: Some(1640000000000),
 ///     open: false,
+///     relative_path: None,
+///     mime_type: None,
 /// };
 /// ```
 #[derive(Debug, Clone, PartialEq)]
@@ -180,6 +218,322 @@ pub struct FileShareInfo {
 
     /// Auto-open file after transfer
     pub open: bool,
+
+    /// Directory relative to the shared folder root, for folder shares
+    pub relative_path: Option<String>,
+
+    /// MIME type, declared by the sender or detected on receipt
+    pub mime_type: Option<String>,
+}
+
+impl FileShareInfo {
+    /// Refine the MIME type from the first bytes of the payload
+    ///
+    /// Replaces a missing or extension-based type when the bytes match a
+    /// known signature, so a file with a misleading extension gets its real
+    /// type. A type the sender declared that differs from the extension
+    /// guess is kept. Only the first few bytes are inspected.
+    pub fn sniff_mime_type(&mut self, first_chunk: &[u8]) {
+        let guessed = self.mime_type.is_none()
+            || self.mime_type.as_deref() == mime_from_extension(&self.filename);
+        if !guessed {
+            return;
+        }
+        if let Some(detected) = mime_from_magic(first_chunk) {
+            if self.mime_type.as_deref() != Some(detected) {
+                debug!(
+                    "{} looks like {} rather than {:?}",
+                    self.filename, detected, self.mime_type
+                );
+                self.mime_type = Some(detected.to_string());
+            }
+        }
+    }
+}
+
+/// Detect a file's MIME type from its magic bytes, then its extension
+///
+/// # Examples
+///
+/// ```
+/// use cosmic_ext_connect_core::plugins::share::detect_mime_type;
+///
+/// assert_eq!(detect_mime_type("notes.txt", None), Some("text/plain"));
+/// assert_eq!(detect_mime_type("photo.txt", Some(b"\xFF\xD8\xFF\xE0")), Some("image/jpeg"));
+/// assert_eq!(detect_mime_type("unknown", None), None);
+/// ```
+pub fn detect_mime_type(filename: &str, first_chunk: Option<&[u8]>) -> Option<&'static str> {
+    first_chunk
+        .and_then(mime_from_magic)
+        .or_else(|| mime_from_extension(filename))
+}
+
+/// Guess a MIME type from a filename extension (case-insensitive)
+pub fn mime_from_extension(filename: &str) -> Option<&'static str> {
+    let (_, extension) = filename.rsplit_once('.')?;
+    Some(match extension.to_ascii_lowercase().as_str() {
+        "txt" => "text/plain",
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "csv" => "text/csv",
+        "md" => "text/markdown",
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "gz" => "application/gzip",
+        "7z" => "application/x-7z-compressed",
+        "apk" => "application/vnd.android.package-archive",
+        "doc" => "application/msword",
+        "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        "odt" => "application/vnd.oasis.opendocument.text",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "heic" => "image/heic",
+        "svg" => "image/svg+xml",
+        "bmp" => "image/bmp",
+        "mp3" => "audio/mpeg",
+        "ogg" | "oga" => "audio/ogg",
+        "opus" => "audio/opus",
+        "flac" => "audio/flac",
+        "wav" => "audio/wav",
+        "m4a" => "audio/mp4",
+        "mp4" | "m4v" => "video/mp4",
+        "mkv" => "video/x-matroska",
+        "webm" => "video/webm",
+        "mov" => "video/quicktime",
+        "vcf" => "text/vcard",
+        _ => return None,
+    })
+}
+
+/// Detect a MIME type from well-known file signatures
+pub fn mime_from_magic(bytes: &[u8]) -> Option<&'static str> {
+    const SIGNATURES: &[(&[u8], &str)] = &[
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xFF\xD8\xFF", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"%PDF-", "application/pdf"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"\x1F\x8B", "application/gzip"),
+        (b"7z\xBC\xAF\x27\x1C", "application/x-7z-compressed"),
+        (b"ID3", "audio/mpeg"),
+        (b"OggS", "audio/ogg"),
+        (b"fLaC", "audio/flac"),
+        (b"\x1A\x45\xDF\xA3", "video/x-matroska"),
+    ];
+
+    if let Some((_, mime)) = SIGNATURES.iter().find(|(magic, _)| bytes.starts_with(magic)) {
+        return Some(mime);
+    }
+
+    // RIFF containers and ISO media name their format after a size field
+    match (bytes.get(..4), bytes.get(4..8), bytes.get(8..12)) {
+        (Some(b"RIFF"), _, Some(b"WEBP")) => Some("image/webp"),
+        (Some(b"RIFF"), _, Some(b"WAVE")) => Some("audio/wav"),
+        (_, Some(b"ftyp"), Some(b"heic" | b"heix" | b"mif1")) => Some("image/heic"),
+        (_, Some(b"ftyp"), Some(b"qt  ")) => Some("video/quicktime"),
+        (_, Some(b"ftyp"), Some(b"M4A ")) => Some("audio/mp4"),
+        (_, Some(b"ftyp"), Some(_)) => Some("video/mp4"),
+        _ => None,
+    }
+}
+
+/// Receive-side helper that maps incoming files to paths under a root
+///
+/// Recreates the sender's directory structure while guaranteeing that every
+/// target path stays inside the destination root.
+///
+/// ## Example
+///
+/// ```rust
+/// use cosmic_ext_connect_core::plugins::share::{FileShareInfo, ShareReceiver};
+/// use std::path::Path;
+///
+/// let item = FileShareInfo {
+///     filename: "beach.jpg".to_string(),
+///     size: 1024,
+///     creation_time: None,
+///     last_modified: None,
+///     open: false,
+///     relative_path: Some("Photos/2024".to_string()),
+///     mime_type: None,
+/// };
+///
+/// let path = ShareReceiver::target_path(&item, Path::new("/downloads")).unwrap();
+/// assert_eq!(path, Path::new("/downloads/Photos/2024/beach.jpg"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ShareReceiver {
+    /// Destination root for received files
+    root: PathBuf,
+
+    /// What to do when a received file's name is taken
+    collision_policy: CollisionPolicy,
+}
+
+/// How to handle a received file whose target path already exists
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CollisionPolicy {
+    /// Replace the existing file once the new one is complete
+    Overwrite,
+    /// Keep the existing file and save under `name (1).ext`, `name (2).ext`, ...
+    #[default]
+    Rename,
+}
+
+impl ShareReceiver {
+    /// Create a receiver that writes under `root`
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            collision_policy: CollisionPolicy::default(),
+        }
+    }
+
+    /// Set how to handle files whose name is already taken
+    pub fn with_collision_policy(mut self, policy: CollisionPolicy) -> Self {
+        self.collision_policy = policy;
+        self
+    }
+
+    /// Get the destination root
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Compute the target path for an incoming file under `root`
+    ///
+    /// Joins `relative_path` (if any) and `filename`, accepting both `/` and
+    /// `\` as separators.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::InvalidPacket` if the path is absolute, contains
+    /// `..` or a drive/stream separator (`:`), or has no file name.
+    pub fn target_path(item: &FileShareInfo, root: &Path) -> Result<PathBuf> {
+        let mut path = root.to_path_buf();
+        let mut components = 0;
+
+        let parts = item
+            .relative_path
+            .iter()
+            .map(String::as_str)
+            .chain(std::iter::once(item.filename.as_str()));
+
+        for part in parts {
+            if part.starts_with('/') || part.starts_with('\\') {
+                return Err(unsafe_path(item));
+            }
+
+            for segment in part.split(['/', '\\']) {
+                match segment {
+                    "" | "." => continue,
+                    ".." => return Err(unsafe_path(item)),
+                    s if s.contains(':') || s.contains('\0') => return Err(unsafe_path(item)),
+                    s => {
+                        path.push(s);
+                        components += 1;
+                    }
+                }
+            }
+        }
+
+        // The filename must contribute the final component
+        let has_file_name = item
+            .filename
+            .rsplit(['/', '\\'])
+            .next()
+            .is_some_and(|name| !name.is_empty() && name != ".");
+        if components == 0 || !has_file_name {
+            return Err(ProtocolError::InvalidPacket(format!(
+                "Share has no file name: {:?}",
+                item.filename
+            )));
+        }
+
+        Ok(path)
+    }
+
+    /// Compute the target path and create its parent directories
+    ///
+    /// If a file already exists at the target path, the
+    /// [`CollisionPolicy`] decides whether it is returned as is (to be
+    /// replaced) or a free numbered name is returned instead.
+    ///
+    /// Under [`CollisionPolicy::Rename`] the returned name is reserved by
+    /// creating an empty file there, so concurrent transfers of the same
+    /// file never get the same name. The completed transfer replaces it;
+    /// remove it if the transfer fails.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path is unsafe (see [`target_path`](Self::target_path))
+    /// or the directories cannot be created.
+    pub async fn prepare(&self, item: &FileShareInfo) -> Result<PathBuf> {
+        let path = Self::target_path(item, &self.root)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        match self.collision_policy {
+            CollisionPolicy::Overwrite => Ok(path),
+            CollisionPolicy::Rename => Self::free_path(path).await,
+        }
+    }
+
+    /// Reserve the first of `path`, `name (1).ext`, `name (2).ext`, ... that does not exist
+    async fn free_path(path: PathBuf) -> Result<PathBuf> {
+        if Self::reserve(&path).await? {
+            return Ok(path);
+        }
+
+        let stem = path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+        let extension = path
+            .extension()
+            .map(|ext| format!(".{}", ext.to_string_lossy()))
+            .unwrap_or_default();
+
+        for n in 1u32.. {
+            let candidate = path.with_file_name(format!("{} ({}){}", stem, n, extension));
+            if Self::reserve(&candidate).await? {
+                debug!("{:?} exists, saving as {:?}", path, candidate);
+                return Ok(candidate);
+            }
+        }
+        unreachable!("ran out of numbered file names")
+    }
+
+    /// Create an empty file at `path` unless something already exists there
+    ///
+    /// Checking and creating are one operation, so two callers cannot both
+    /// reserve the same name.
+    async fn reserve(path: &Path) -> Result<bool> {
+        let created = tokio::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .await;
+        match created {
+            Ok(_) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+fn unsafe_path(item: &FileShareInfo) -> ProtocolError {
+    ProtocolError::InvalidPacket(format!(
+        "Unsafe share path: {:?} / {:?}",
+        item.relative_path, item.filename
+    ))
 }
 
 /// Information about a multi-file transfer
@@ -199,11 +553,11 @@ pub struct FileShareInfo {
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct MultiFileInfo {
     /// Number of files in the transfer
-    #[serde(rename = "numberOfFiles")]
+    #[serde(rename = "numberOfFiles", alias = "number_of_files")]
     pub number_of_files: i32,
 
     /// Total size of all files in bytes
-    #[serde(rename = "totalPayloadSize")]
+    #[serde(rename = "totalPayloadSize", alias = "total_payload_size")]
     pub total_payload_size: i64,
 }
 
@@ -243,6 +597,20 @@ pub struct ShareRecord {
     pub incoming: bool,
 }
 
+/// URL schemes opened without explicit permission
+pub const DEFAULT_OPEN_SCHEMES: &[&str] = &["http", "https", "mailto", "tel", "geo"];
+
+/// Opens text and URL shares that ask to be opened immediately
+///
+/// Implemented by the platform layer, e.g. to launch the default browser.
+pub trait OpenHandler: Send + Sync + Debug {
+    /// Open a URL whose scheme passed the allowlist
+    fn open_url(&self, url: &str);
+
+    /// Open shared text, e.g. in an editor
+    fn open_text(&self, text: &str);
+}
+
 /// Share plugin for file, text, and URL sharing
 ///
 /// Handles `cconnect.share.request` packets for transferring content between devices.
@@ -280,6 +648,12 @@ pub struct SharePlugin {
 
     /// History of share operations
     shares: Arc<RwLock<Vec<ShareRecord>>>,
+
+    /// Receives text and URL shares flagged to open immediately
+    open_handler: Option<Arc<dyn OpenHandler>>,
+
+    /// URL schemes that may be opened immediately
+    open_schemes: HashSet<String>,
 }
 
 impl SharePlugin {
@@ -299,6 +673,65 @@ impl SharePlugin {
             device_name: None,
             device_host: None,
             shares: Arc::new(RwLock::new(Vec::new())),
+            open_handler: None,
+            open_schemes: DEFAULT_OPEN_SCHEMES.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    /// Builder: set the handler for shares flagged to open immediately
+    pub fn with_open_handler(mut self, handler: Arc<dyn OpenHandler>) -> Self {
+        self.open_handler = Some(handler);
+        self
+    }
+
+    /// Builder: replace the URL schemes that may be opened immediately
+    pub fn with_open_schemes<I, S>(mut self, schemes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.open_schemes = schemes
+            .into_iter()
+            .map(|scheme| scheme.into().to_ascii_lowercase())
+            .collect();
+        self
+    }
+
+    /// Builder: allow one more URL scheme to be opened immediately
+    pub fn allow_open_scheme(mut self, scheme: impl Into<String>) -> Self {
+        self.open_schemes.insert(scheme.into().to_ascii_lowercase());
+        self
+    }
+
+    /// Check whether a URL may be opened immediately
+    ///
+    /// Unparseable URLs and schemes missing from the allowlist are refused.
+    pub fn may_open_url(&self, url: &str) -> bool {
+        self.openable_url(url).is_some()
+    }
+
+    /// Parse a URL, keeping it only if its scheme may be opened
+    fn openable_url(&self, url: &str) -> Option<Url> {
+        Url::parse(url)
+            .ok()
+            .filter(|url| self.open_schemes.contains(url.scheme()))
+    }
+
+    /// Pass a text or URL share flagged to open to the open handler
+    fn open_share(&self, content: &ShareContent, device_name: &str) {
+        let Some(handler) = &self.open_handler else {
+            debug!("No open handler set, not opening share from {}", device_name);
+            return;
+        };
+
+        match content {
+            // Open the URL as parsed, so the handler sees what was checked
+            ShareContent::Url(url) => match self.openable_url(url) {
+                Some(parsed) => handler.open_url(parsed.as_str()),
+                None => warn!("Refusing to open URL shared by {}: {}", device_name, url),
+            },
+            ShareContent::Text(text) => handler.open_text(text),
+            _ => {}
         }
     }
 
@@ -343,6 +776,8 @@ impl SharePlugin {
     ///     creation_time: Some(1640000000000),
     ///     last_modified: Some(1640000000000),
     ///     open: false,
+    ///     relative_path: None,
+    ///     mime_type: None,
     /// };
     ///
     /// let packet = plugin.create_file_packet(file_info, 1739);
@@ -364,6 +799,12 @@ impl SharePlugin {
         if file_info.open {
             body["open"] = json!(true);
         }
+        if let Some(relative_path) = file_info.relative_path {
+            body["relativePath"] = json!(relative_path);
+        }
+        if let Some(mime_type) = file_info.mime_type {
+            body["mimeType"] = json!(mime_type);
+        }
 
         // Create payload transfer info
         let mut transfer_info = HashMap::new();
@@ -553,13 +994,21 @@ impl SharePlugin {
             let file_info = FileShareInfo {
                 filename: filename.to_string(),
                 size: packet.payload_size.unwrap_or(0),
-                creation_time: packet.body.get("creationTime").and_then(|v| v.as_i64()),
-                last_modified: packet.body.get("lastModified").and_then(|v| v.as_i64()),
+                creation_time: packet.body_value("creationTime").and_then(|v| v.as_i64()),
+                last_modified: packet.body_value("lastModified").and_then(|v| v.as_i64()),
                 open: packet
-                    .body
-                    .get("open")
+                    .body_value("open")
                     .and_then(|v| v.as_bool())
                     .unwrap_or(false),
+                relative_path: packet
+                    .body_value("relativePath")
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string),
+                mime_type: packet
+                    .body_value("mimeType")
+                    .and_then(|v| v.as_str())
+                    .or_else(|| mime_from_extension(filename))
+                    .map(str::to_string),
             };
 
             info!(
@@ -580,22 +1029,26 @@ impl SharePlugin {
                     if let Some(host) = device_host {
                         let host_clone = host.to_string();
                         let filename_clone = filename.to_string();
+                        let file_info_clone = file_info.clone();
                         let _size = file_info.size;
                         let device_name_clone = device_name.to_string();
 
                         // Spawn background task to download file
                         tokio::spawn(async move {
-                            // Create downloads directory
-                            let downloads_dir = std::path::PathBuf::from(
-                                std::env::var("HOME").unwrap_or_else(|_| "/tmp".to_string())
-                            ).join("Downloads");
-
-                            if let Err(e) = tokio::fs::create_dir_all(&downloads_dir).await {
-                                warn!("Failed to create downloads directory: {}", e);
-                                return;
-                            }
+                            // Recreate the sender's directory structure under Downloads
+                            let receiver = ShareReceiver::new(
+                                PathBuf::from(
+                                    std::env::var("HOME").unwrap_or_else(|_| "/tmp".to_string())
+                                ).join("Downloads"),
+                            );
 
-                            let file_path = downloads_dir.join(&filename_clone);
+                            let file_path = match receiver.prepare(&file_info_clone).await {
+                                Ok(path) => path,
+                                Err(e) => {
+                                    warn!("Rejecting file '{}': {}", filename_clone, e);
+                                    return;
+                                }
+                            };
 
                             info!(
                                 "Downloading file '{}' from {} ({}:{}) to {:?}",
@@ -617,6 +1070,12 @@ impl SharePlugin {
                             //   client.receive_file(&file_path, size).await?;
                             warn!("Payload download not implemented in core library - use cosmic-connect-protocol for file transfers");
 
+                            // Nothing was downloaded; release the name prepare() reserved
+                            // (the default collision policy always reserves a fresh file)
+                            if let Err(e) = tokio::fs::remove_file(&file_path).await {
+                                debug!("Failed to release {:?}: {}", file_path, e);
+                            }
+
                             /* Reference implementation (see cosmic-connect-protocol/src/plugins/share.rs:708-791):
                             // Connect to payload server and download file
                             use crate::TlsPayloadClient;
@@ -684,6 +1143,14 @@ impl SharePlugin {
             return;
         };
 
+        let open = packet
+            .body_value("open")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        if open {
+            self.open_share(&content, device_name);
+        }
+
         // Record share
         let record = ShareRecord {
             id: packet.id.to_string(),
@@ -843,6 +1310,8 @@ mod tests {
             creation_time: Some(1640000000000),
             last_modified: Some(1640000000000),
             open: false,
+            relative_path: None,
+            mime_type: None,
         };
 
         let packet = plugin.create_file_packet(file_info, 1739);
@@ -861,6 +1330,319 @@ mod tests {
         );
     }
 
+    fn file_item(relative_path: Option<&str>, filename: &str) -> FileShareInfo {
+        FileShareInfo {
+            filename: filename.to_string(),
+            size: 0,
+            creation_time: None,
+            last_modified: None,
+            open: false,
+            relative_path: relative_path.map(str::to_string),
+            mime_type: None,
+        }
+    }
+
+    #[test]
+    fn test_target_path_nested() {
+        let root = Path::new("/downloads");
+
+        let path = ShareReceiver::target_path(&file_item(Some("Photos/2024"), "a.jpg"), root).unwrap();
+        assert_eq!(path, Path::new("/downloads/Photos/2024/a.jpg"));
+
+        let path = ShareReceiver::target_path(&file_item(None, "Docs\\notes/b.txt"), root).unwrap();
+        assert_eq!(path, Path::new("/downloads/Docs/notes/b.txt"));
+
+        let path = ShareReceiver::target_path(&file_item(Some("./x/"), "c.txt"), root).unwrap();
+        assert_eq!(path, Path::new("/downloads/x/c.txt"));
+    }
+
+    #[test]
+    fn test_target_path_rejects_traversal() {
+        let root = Path::new("/downloads");
+
+        for (relative_path, filename) in [
+            (None, "../../etc/passwd"),
+            (Some("../.."), "passwd"),
+            (Some("ok/../../x"), "passwd"),
+            (None, "/etc/passwd"),
+            (Some("/etc"), "passwd"),
+            (None, "..\\..\\windows\\system.ini"),
+            (None, "C:\\windows\\system.ini"),
+            (None, ""),
+            (Some("dir"), "sub/"),
+        ] {
+            let item = file_item(relative_path, filename);
+            assert!(
+                ShareReceiver::target_path(&item, root).is_err(),
+                "{:?} / {:?} should be rejected",
+                relative_path,
+                filename
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_receiver_prepare_creates_tree() {
+        let dir = tempfile::tempdir().unwrap();
+        let receiver = ShareReceiver::new(dir.path());
+
+        let path = receiver.prepare(&file_item(Some("a/b"), "c.txt")).await.unwrap();
+        assert_eq!(path, dir.path().join("a").join("b").join("c.txt"));
+        assert!(dir.path().join("a").join("b").is_dir());
+        assert!(path.starts_with(receiver.root()));
+    }
+
+    #[tokio::test]
+    async fn test_receiver_collision_policy() {
+        let dir = tempfile::tempdir().unwrap();
+        let item = file_item(None, "photo.jpg");
+        let existing = dir.path().join("photo.jpg");
+        std::fs::write(&existing, b"old").unwrap();
+
+        let receiver = ShareReceiver::new(dir.path());
+        assert_eq!(receiver.prepare(&item).await.unwrap(), dir.path().join("photo (1).jpg"));
+        std::fs::write(dir.path().join("photo (1).jpg"), b"older").unwrap();
+        assert_eq!(receiver.prepare(&item).await.unwrap(), dir.path().join("photo (2).jpg"));
+
+        // A prepared name is reserved even before any bytes arrive
+        assert_eq!(receiver.prepare(&item).await.unwrap(), dir.path().join("photo (3).jpg"));
+        let (first, second) = tokio::join!(receiver.prepare(&item), receiver.prepare(&item));
+        assert_ne!(first.unwrap(), second.unwrap());
+        assert_eq!(std::fs::read(&existing).unwrap(), b"old");
+
+        let no_extension = receiver.prepare(&file_item(None, "README")).await.unwrap();
+        assert_eq!(no_extension, dir.path().join("README"));
+
+        let receiver = receiver.with_collision_policy(CollisionPolicy::Overwrite);
+        assert_eq!(receiver.prepare(&item).await.unwrap(), existing);
+    }
+
+    #[tokio::test]
+    async fn test_receive_share_is_atomic() {
+        use crate::protocol::PayloadReceiver;
+
+        let dir = tempfile::tempdir().unwrap();
+        let receiver = ShareReceiver::new(dir.path());
+        let item = file_item(Some("Photos"), "beach.jpg");
+        let path = receiver.prepare(&item).await.unwrap();
+        let photos = dir.path().join("Photos");
+        let partial_files = || {
+            std::fs::read_dir(&photos)
+                .unwrap()
+                .filter(|entry| {
+                    let path = entry.as_ref().unwrap().path();
+                    path.extension().is_some_and(|ext| ext == "part")
+                })
+                .count()
+        };
+
+        // Cancelled after the first chunk: only the empty reservation is left
+        let (mut tx, rx) = tokio::io::duplex(64);
+        tokio::io::AsyncWriteExt::write_all(&mut tx, &[1u8; 10]).await.unwrap();
+        let mut payload = PayloadReceiver::new(rx);
+        let transfer = payload.receive_file(&path, 20, None, |_, _| {});
+        assert!(
+            tokio::time::timeout(std::time::Duration::from_millis(50), transfer)
+                .await
+                .is_err()
+        );
+        assert_eq!(partial_files(), 0);
+        assert!(std::fs::read(&path).unwrap().is_empty());
+
+        // Complete: the finished file appears under its final name only
+        let data = [2u8; 20];
+        let mut payload = PayloadReceiver::new(&data[..]);
+        payload
+            .receive_file(&path, 20, None, |_, _| {
+                assert!(std::fs::read(&path).unwrap().is_empty())
+            })
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), data);
+        assert_eq!(partial_files(), 0);
+        drop(tx);
+    }
+
+    #[tokio::test]
+    async fn test_handle_file_share_relative_path() {
+        let mut plugin = SharePlugin::new();
+        plugin.set_device_info("dev".to_string(), "Phone".to_string(), None);
+
+        let packet = Packet::new(
+            "cconnect.share.request",
+            json!({ "filename": "a.jpg", "relativePath": "Photos/2024" }),
+        )
+        .with_payload_size(10);
+        plugin.handle_packet(&packet).await.unwrap();
+
+        let shares = plugin.get_all_shares().await;
+        if let ShareContent::File(file_info) = &shares[0].content {
+            assert_eq!(file_info.relative_path.as_deref(), Some("Photos/2024"));
+        } else {
+            panic!("Expected File content");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_handle_file_share_snake_case_keys() {
+        let mut plugin = SharePlugin::new();
+        plugin.set_device_info("dev".to_string(), "Phone".to_string(), None);
+
+        let packet = Packet::new(
+            "cconnect.share.request",
+            json!({
+                "filename": "a.jpg",
+                "relative_path": "Photos/2024",
+                "mime_type": "image/jpeg",
+                "last_modified": 1700000000000i64
+            }),
+        )
+        .with_payload_size(10);
+        plugin.handle_packet(&packet).await.unwrap();
+
+        let shares = plugin.get_all_shares().await;
+        if let ShareContent::File(file_info) = &shares[0].content {
+            assert_eq!(file_info.relative_path.as_deref(), Some("Photos/2024"));
+            assert_eq!(file_info.mime_type.as_deref(), Some("image/jpeg"));
+            assert_eq!(file_info.last_modified, Some(1700000000000));
+        } else {
+            panic!("Expected File content");
+        }
+    }
+
+    /// Open handler recording what it was asked to open
+    #[derive(Debug, Default)]
+    struct RecordingOpener(std::sync::Mutex<Vec<String>>);
+
+    impl OpenHandler for RecordingOpener {
+        fn open_url(&self, url: &str) {
+            self.0.lock().unwrap().push(format!("url {}", url));
+        }
+
+        fn open_text(&self, text: &str) {
+            self.0.lock().unwrap().push(format!("text {}", text));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_open_flagged_shares() {
+        let opener = Arc::new(RecordingOpener::default());
+        let mut plugin = SharePlugin::new().with_open_handler(opener.clone());
+        plugin.set_device_info("dev".to_string(), "Phone".to_string(), None);
+
+        for body in [
+            json!({ "url": "http://example.com/a", "open": true }),
+            json!({ "url": "HTTPS://Example.COM", "open": true }),
+            json!({ "url": "javascript:alert(1)", "open": true }),
+            json!({ "url": "file:///etc/passwd", "open": true }),
+            json!({ "url": "https://example.com/not-flagged" }),
+            json!({ "text": "notes", "open": true }),
+        ] {
+            let packet = Packet::new("cconnect.share.request", body);
+            plugin.handle_packet(&packet).await.unwrap();
+        }
+
+        assert_eq!(
+            *opener.0.lock().unwrap(),
+            vec![
+                "url http://example.com/a",
+                "url https://example.com/",
+                "text notes"
+            ]
+        );
+        // Refused shares are still recorded
+        assert_eq!(plugin.share_count(), 6);
+    }
+
+    #[test]
+    fn test_open_scheme_allowlist() {
+        let plugin = SharePlugin::new();
+        assert!(plugin.may_open_url("https://example.com"));
+        assert!(plugin.may_open_url("MAILTO:someone@example.com"));
+        assert!(!plugin.may_open_url("javascript:alert(1)"));
+        assert!(!plugin.may_open_url("file:///tmp/a.txt"));
+        assert!(!plugin.may_open_url("not a url"));
+
+        let plugin = plugin.allow_open_scheme("FILE");
+        assert!(plugin.may_open_url("file:///tmp/a.txt"));
+
+        let plugin = SharePlugin::new().with_open_schemes(["https"]);
+        assert!(!plugin.may_open_url("http://example.com"));
+    }
+
+    #[tokio::test]
+    async fn test_handle_file_share_mime_type() {
+        let mut plugin = SharePlugin::new();
+        plugin.set_device_info("dev".to_string(), "Phone".to_string(), None);
+
+        for body in [
+            json!({ "filename": "scan.PDF" }),
+            json!({ "filename": "scan", "mimeType": "application/pdf" }),
+        ] {
+            let packet = Packet::new("cconnect.share.request", body).with_payload_size(10);
+            plugin.handle_packet(&packet).await.unwrap();
+        }
+        let packet = Packet::new("cconnect.share.request", json!({ "filename": "blob.xyz" }));
+        plugin.handle_packet(&packet).await.unwrap();
+
+        let mime_types: Vec<_> = plugin
+            .get_all_shares()
+            .await
+            .into_iter()
+            .map(|share| match share.content {
+                ShareContent::File(file_info) => file_info.mime_type,
+                other => panic!("Expected File content, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            mime_types,
+            vec![Some("application/pdf".to_string()), Some("application/pdf".to_string()), None]
+        );
+
+        // Declared types are sent on the wire
+        let mut item = file_item(None, "scan");
+        item.mime_type = Some("application/pdf".to_string());
+        let packet = plugin.create_file_packet(item, 1739);
+        assert_eq!(packet.body["mimeType"], "application/pdf");
+        assert!(plugin
+            .create_file_packet(file_item(None, "a.txt"), 1739)
+            .body
+            .get("mimeType")
+            .is_none());
+    }
+
+    #[test]
+    fn test_sniff_mime_type_misleading_extension() {
+        const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+        // Named like a JPEG, guessed from the extension, actually a PNG
+        let mut item = file_item(None, "holiday.jpg");
+        item.mime_type = mime_from_extension(&item.filename).map(str::to_string);
+        assert_eq!(item.mime_type.as_deref(), Some("image/jpeg"));
+        item.sniff_mime_type(PNG);
+        assert_eq!(item.mime_type.as_deref(), Some("image/png"));
+
+        // Unrecognized bytes keep the extension guess
+        let mut item = file_item(None, "notes.txt");
+        item.mime_type = Some("text/plain".to_string());
+        item.sniff_mime_type(b"hello");
+        assert_eq!(item.mime_type.as_deref(), Some("text/plain"));
+
+        // A type declared by the sender is kept
+        let mut item = file_item(None, "holiday.jpg");
+        item.mime_type = Some("image/x-custom".to_string());
+        item.sniff_mime_type(PNG);
+        assert_eq!(item.mime_type.as_deref(), Some("image/x-custom"));
+
+        assert_eq!(mime_from_magic(b"RIFF\0\0\0\0WEBPVP8 "), Some("image/webp"));
+        assert_eq!(mime_from_magic(b"\0\0\0\x18ftypisom"), Some("video/mp4"));
+        assert_eq!(mime_from_magic(b"\0\0\0\x18ftypheic"), Some("image/heic"));
+        assert_eq!(mime_from_magic(b""), None);
+        assert_eq!(detect_mime_type("clip.MKV", None), Some("video/x-matroska"));
+        assert_eq!(detect_mime_type("archive.tar.gz", Some(b"\x1F\x8B\x08")), Some("application/gzip"));
+        assert_eq!(mime_from_extension("no_extension"), None);
+    }
+
     #[test]
     fn test_create_text_packet() {
         let plugin = SharePlugin::new();
@@ -0,0 +1,603 @@
+//! Share Transfer Queue
+//!
+//! Sharing many files at once should survive a momentary disconnect.
+//! [`ShareQueue`] tracks each file of a multi-file share through its
+//! lifecycle and retries failed transfers with backoff, so a transient error
+//! does not lose the file.
+//!
+//! ## Lifecycle
+//!
+//! `Pending` → `InProgress` → `Completed`, or back to `Pending` with a retry
+//! delay when a transfer fails. Once an item has used all of its attempts it
+//! becomes `Failed` and stays there; the rest of the queue carries on.
+//!
+//! Items are handed out in the order they were enqueued. An item waiting out
+//! its retry delay does not hold back the items after it, so the selection
+//! arrives roughly, not strictly, in sequence.
+//!
+//! ## Batch Progress
+//!
+//! Besides each item's [`ShareItem::fraction`], [`ShareQueue::batch_progress`]
+//! sums up the whole share ("12 of 50 files, 340 MB of 1.2 GB") from the
+//! latest recorded progress. A file whose size is not known yet (a negative
+//! `size`) adds only the bytes sent so far to the total, so the total grows
+//! until [`ShareQueue::set_size`] records the real size. Files that failed
+//! permanently are left out of the totals.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use cosmic_ext_connect_core::plugins::share::FileShareInfo;
+//! use cosmic_ext_connect_core::plugins::share_queue::{ShareItemState, ShareQueue};
+//!
+//! let mut queue = ShareQueue::new();
+//! let id = queue.enqueue(FileShareInfo {
+//!     filename: "photo.jpg".to_string(),
+//!     size: 1024,
+//!     creation_time: None,
+//!     last_modified: None,
+//!     open: false,
+//!     relative_path: None,
+//!     mime_type: None,
+//! });
+//!
+//! let (next, _info) = queue.next_ready().unwrap();
+//! assert_eq!(next, id);
+//! queue.update_progress(id, 1024).unwrap();
+//! queue.complete(id).unwrap();
+//! assert_eq!(queue.item(id).unwrap().state, ShareItemState::Completed);
+//! assert!(queue.is_finished());
+//! ```
+
+use crate::error::{ProtocolError, Result};
+use crate::plugins::share::FileShareInfo;
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+/// Default number of transfer attempts per item
+pub const DEFAULT_SHARE_ATTEMPTS: u32 = 3;
+
+/// Default delay before the first retry
+pub const DEFAULT_SHARE_RETRY_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Default upper bound for the retry delay
+pub const DEFAULT_MAX_SHARE_RETRY_BACKOFF: Duration = Duration::from_secs(30);
+
+/// How failed transfers are retried
+///
+/// The delay before a retry starts at `initial_backoff` and doubles with
+/// each failure up to `max_backoff`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShareRetryPolicy {
+    /// Transfer attempts per item (minimum 1)
+    pub max_attempts: u32,
+    /// Delay before the first retry
+    pub initial_backoff: Duration,
+    /// Upper bound for the retry delay
+    pub max_backoff: Duration,
+}
+
+impl Default for ShareRetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_SHARE_ATTEMPTS,
+            initial_backoff: DEFAULT_SHARE_RETRY_BACKOFF,
+            max_backoff: DEFAULT_MAX_SHARE_RETRY_BACKOFF,
+        }
+    }
+}
+
+impl ShareRetryPolicy {
+    /// Delay before the retry that follows the `failures`th failure
+    fn backoff(&self, failures: u32) -> Duration {
+        let doublings = failures.saturating_sub(1).min(31);
+        self.initial_backoff
+            .saturating_mul(1 << doublings)
+            .min(self.max_backoff)
+    }
+}
+
+/// Lifecycle state of a queued item
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShareItemState {
+    /// Waiting to be transferred (or retried)
+    Pending,
+    /// Being transferred
+    InProgress,
+    /// Transferred successfully
+    Completed,
+    /// Gave up after the last attempt, with the last error
+    Failed(String),
+}
+
+/// A file in the queue and its progress
+#[derive(Debug, Clone)]
+pub struct ShareItem {
+    /// Queue-assigned item ID
+    pub id: u64,
+    /// File being shared
+    pub info: FileShareInfo,
+    /// Lifecycle state
+    pub state: ShareItemState,
+    /// Bytes transferred in the current attempt
+    pub transferred: u64,
+    /// Transfer attempts started so far
+    pub attempts: u32,
+    /// Earliest time a pending retry may start
+    retry_at: Option<Instant>,
+}
+
+impl ShareItem {
+    /// File size in bytes
+    pub fn size(&self) -> u64 {
+        self.info.size.max(0) as u64
+    }
+
+    /// File size in bytes, if known
+    pub fn known_size(&self) -> Option<u64> {
+        u64::try_from(self.info.size).ok()
+    }
+
+    /// Fraction of the file transferred, from 0.0 to 1.0
+    pub fn fraction(&self) -> f64 {
+        match self.state {
+            ShareItemState::Completed => 1.0,
+            _ if self.size() == 0 => 0.0,
+            _ => (self.transferred as f64 / self.size() as f64).min(1.0),
+        }
+    }
+}
+
+/// Progress of the whole queue
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ShareProgress {
+    /// Items in the queue
+    pub total_items: usize,
+    /// Items transferred successfully
+    pub completed_items: usize,
+    /// Items that failed permanently
+    pub failed_items: usize,
+    /// Bytes transferred, counting completed items in full
+    pub transferred_bytes: u64,
+    /// Bytes across all items
+    pub total_bytes: u64,
+}
+
+/// Files and bytes transferred across a whole share
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BatchProgress {
+    /// Files transferred successfully
+    pub files_done: usize,
+    /// Files in the share, not counting permanently failed ones
+    pub files_total: usize,
+    /// Bytes transferred, counting completed files in full
+    pub bytes_done: u64,
+    /// Bytes in the share; files of unknown size count what was sent so far
+    pub bytes_total: u64,
+    /// Files whose size is not known yet
+    pub files_unsized: usize,
+    /// Files that failed permanently
+    pub files_failed: usize,
+}
+
+impl BatchProgress {
+    /// Fraction of the bytes transferred, from 0.0 to 1.0
+    ///
+    /// Falls back to the fraction of files done when no bytes are known.
+    pub fn fraction(&self) -> f64 {
+        if self.bytes_total > 0 {
+            (self.bytes_done as f64 / self.bytes_total as f64).min(1.0)
+        } else if self.files_total > 0 {
+            self.files_done as f64 / self.files_total as f64
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Ordered transfer queue with retry for multi-file shares
+#[derive(Debug, Clone, Default)]
+pub struct ShareQueue {
+    /// Items in enqueue order
+    items: Vec<ShareItem>,
+    /// ID for the next enqueued item
+    next_id: u64,
+    /// Retry behaviour for failed transfers
+    policy: ShareRetryPolicy,
+}
+
+impl ShareQueue {
+    /// Create a queue with the default [`ShareRetryPolicy`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set how failed transfers are retried
+    pub fn with_policy(mut self, policy: ShareRetryPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Add a file to the end of the queue and return its item ID
+    pub fn enqueue(&mut self, info: FileShareInfo) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.items.push(ShareItem {
+            id,
+            info,
+            state: ShareItemState::Pending,
+            transferred: 0,
+            attempts: 0,
+            retry_at: None,
+        });
+        id
+    }
+
+    /// Start the next item that is ready to transfer
+    pub fn next_ready(&mut self) -> Option<(u64, FileShareInfo)> {
+        self.next_ready_at(Instant::now())
+    }
+
+    /// Start the next item that is ready to transfer at `now`
+    ///
+    /// Returns the earliest-enqueued pending item whose retry delay has
+    /// passed and marks it in progress.
+    pub fn next_ready_at(&mut self, now: Instant) -> Option<(u64, FileShareInfo)> {
+        let item = self.items.iter_mut().find(|item| {
+            item.state == ShareItemState::Pending
+                && item.retry_at.map_or(true, |retry_at| retry_at <= now)
+        })?;
+
+        item.state = ShareItemState::InProgress;
+        item.transferred = 0;
+        item.attempts += 1;
+        item.retry_at = None;
+        debug!(
+            "Starting share of {} (attempt {})",
+            item.info.filename, item.attempts
+        );
+        Some((item.id, item.info.clone()))
+    }
+
+    /// Time the next pending retry becomes ready, if any item is waiting
+    pub fn next_retry_at(&self) -> Option<Instant> {
+        self.items
+            .iter()
+            .filter(|item| item.state == ShareItemState::Pending)
+            .filter_map(|item| item.retry_at)
+            .min()
+    }
+
+    /// Record bytes transferred so far for an in-progress item
+    pub fn update_progress(&mut self, id: u64, transferred: u64) -> Result<()> {
+        let item = self.in_progress(id)?;
+        item.transferred = transferred;
+        Ok(())
+    }
+
+    /// Mark an in-progress item as transferred
+    pub fn complete(&mut self, id: u64) -> Result<()> {
+        let item = self.in_progress(id)?;
+        if let Some(size) = item.known_size() {
+            item.transferred = size;
+        }
+        item.state = ShareItemState::Completed;
+        Ok(())
+    }
+
+    /// Record the size of an item once it is known
+    pub fn set_size(&mut self, id: u64, size: u64) -> Result<()> {
+        let item = self
+            .items
+            .iter_mut()
+            .find(|item| item.id == id)
+            .ok_or_else(|| ProtocolError::Plugin(format!("Unknown share item: {}", id)))?;
+        item.info.size = i64::try_from(size).unwrap_or(i64::MAX);
+        Ok(())
+    }
+
+    /// Record a failed transfer
+    pub fn fail(&mut self, id: u64, error: impl Into<String>) -> Result<()> {
+        self.fail_at(id, error, Instant::now())
+    }
+
+    /// Record a failed transfer at `now`
+    ///
+    /// The item is retried after a backoff delay while it has attempts left.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Plugin` if this was the item's last attempt;
+    /// the item is then `Failed` and the rest of the queue is unaffected.
+    pub fn fail_at(&mut self, id: u64, error: impl Into<String>, now: Instant) -> Result<()> {
+        let policy = self.policy;
+        let item = self.in_progress(id)?;
+        let error = error.into();
+
+        if item.attempts < policy.max_attempts.max(1) {
+            let backoff = policy.backoff(item.attempts);
+            debug!(
+                "Share of {} failed, retrying in {:?}: {}",
+                item.info.filename, backoff, error
+            );
+            item.state = ShareItemState::Pending;
+            item.transferred = 0;
+            item.retry_at = Some(now + backoff);
+            return Ok(());
+        }
+
+        warn!(
+            "Share of {} failed after {} attempts: {}",
+            item.info.filename, item.attempts, error
+        );
+        let message = format!(
+            "Share of {} failed after {} attempts: {}",
+            item.info.filename, item.attempts, error
+        );
+        item.state = ShareItemState::Failed(error);
+        Err(ProtocolError::Plugin(message))
+    }
+
+    /// Look up an item by ID
+    pub fn item(&self, id: u64) -> Option<&ShareItem> {
+        self.items.iter().find(|item| item.id == id)
+    }
+
+    /// All items in enqueue order
+    pub fn items(&self) -> &[ShareItem] {
+        &self.items
+    }
+
+    /// Progress across the whole queue
+    pub fn progress(&self) -> ShareProgress {
+        let mut progress = ShareProgress {
+            total_items: self.items.len(),
+            ..Default::default()
+        };
+        for item in &self.items {
+            progress.total_bytes += item.size();
+            match item.state {
+                ShareItemState::Completed => {
+                    progress.completed_items += 1;
+                    progress.transferred_bytes += item.size();
+                }
+                ShareItemState::Failed(_) => progress.failed_items += 1,
+                _ => progress.transferred_bytes += item.transferred.min(item.size()),
+            }
+        }
+        progress
+    }
+
+    /// Files and bytes transferred across the whole share
+    pub fn batch_progress(&self) -> BatchProgress {
+        let mut progress = BatchProgress::default();
+        for item in &self.items {
+            if matches!(item.state, ShareItemState::Failed(_)) {
+                progress.files_failed += 1;
+                continue;
+            }
+
+            progress.files_total += 1;
+            let done = match item.known_size() {
+                Some(size) => {
+                    progress.bytes_total += size;
+                    if item.state == ShareItemState::Completed {
+                        size
+                    } else {
+                        item.transferred.min(size)
+                    }
+                }
+                None => {
+                    progress.files_unsized += 1;
+                    progress.bytes_total += item.transferred;
+                    item.transferred
+                }
+            };
+            progress.bytes_done += done;
+            if item.state == ShareItemState::Completed {
+                progress.files_done += 1;
+            }
+        }
+        progress
+    }
+
+    /// Check whether every item has completed or failed permanently
+    pub fn is_finished(&self) -> bool {
+        self.items.iter().all(|item| {
+            matches!(
+                item.state,
+                ShareItemState::Completed | ShareItemState::Failed(_)
+            )
+        })
+    }
+
+    fn in_progress(&mut self, id: u64) -> Result<&mut ShareItem> {
+        let item = self
+            .items
+            .iter_mut()
+            .find(|item| item.id == id)
+            .ok_or_else(|| ProtocolError::Plugin(format!("Unknown share item: {}", id)))?;
+
+        if item.state != ShareItemState::InProgress {
+            return Err(ProtocolError::Plugin(format!(
+                "Share item {} is not in progress",
+                id
+            )));
+        }
+        Ok(item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(name: &str, size: i64) -> FileShareInfo {
+        FileShareInfo {
+            filename: name.to_string(),
+            size,
+            creation_time: None,
+            last_modified: None,
+            open: false,
+            relative_path: None,
+            mime_type: None,
+        }
+    }
+
+    fn policy(max_attempts: u32) -> ShareRetryPolicy {
+        ShareRetryPolicy {
+            max_attempts,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(4),
+        }
+    }
+
+    #[test]
+    fn test_transient_failure_retries_while_others_proceed() {
+        let mut queue = ShareQueue::new().with_policy(policy(3));
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        let a = queue.enqueue(file("a.jpg", 100));
+        let b = queue.enqueue(file("b.jpg", 200));
+        let c = queue.enqueue(file("c.jpg", 300));
+
+        // a fails halfway through
+        assert_eq!(queue.next_ready_at(at(0)).unwrap().0, a);
+        queue.update_progress(a, 50).unwrap();
+        queue.fail_at(a, "connection reset", at(0)).unwrap();
+        assert_eq!(queue.item(a).unwrap().state, ShareItemState::Pending);
+        assert_eq!(queue.next_retry_at(), Some(at(1)));
+
+        // b and c go ahead while a waits out its backoff
+        assert_eq!(queue.next_ready_at(at(0)).unwrap().0, b);
+        queue.update_progress(b, 150).unwrap();
+        assert_eq!(queue.item(b).unwrap().fraction(), 0.75);
+        queue.complete(b).unwrap();
+
+        // a is ready again first, ahead of c
+        assert_eq!(queue.next_ready_at(at(1)).unwrap().0, a);
+        queue.complete(a).unwrap();
+        assert_eq!(queue.item(a).unwrap().attempts, 2);
+
+        assert_eq!(queue.next_ready_at(at(1)).unwrap().0, c);
+        queue.update_progress(c, 100).unwrap();
+        assert_eq!(
+            queue.progress(),
+            ShareProgress {
+                total_items: 3,
+                completed_items: 2,
+                failed_items: 0,
+                transferred_bytes: 400,
+                total_bytes: 600,
+            }
+        );
+
+        queue.complete(c).unwrap();
+        assert!(queue.is_finished());
+        assert!(queue.next_ready_at(at(10)).is_none());
+    }
+
+    #[test]
+    fn test_permanent_failure_surfaces_without_blocking() {
+        let mut queue = ShareQueue::new().with_policy(policy(2));
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        let a = queue.enqueue(file("a.jpg", 100));
+        let b = queue.enqueue(file("b.jpg", 100));
+
+        queue.next_ready_at(at(0));
+        queue.fail_at(a, "timeout", at(0)).unwrap();
+
+        // Not ready before the backoff has passed
+        assert_eq!(queue.next_ready_at(at(0)).unwrap().0, b);
+        assert!(queue.next_ready_at(at(0)).is_none());
+
+        assert_eq!(queue.next_ready_at(at(1)).unwrap().0, a);
+        let err = queue.fail_at(a, "timeout", at(1)).unwrap_err();
+        assert!(err.to_string().contains("a.jpg failed after 2 attempts"));
+        assert_eq!(
+            queue.item(a).unwrap().state,
+            ShareItemState::Failed("timeout".to_string())
+        );
+
+        queue.complete(b).unwrap();
+        assert!(queue.is_finished());
+        assert_eq!(queue.progress().failed_items, 1);
+        assert_eq!(queue.progress().completed_items, 1);
+
+        // Finished items cannot be updated again
+        assert!(queue.complete(a).is_err());
+    }
+
+    #[test]
+    fn test_batch_progress_across_items() {
+        let mut queue = ShareQueue::new().with_policy(policy(1));
+        let a = queue.enqueue(file("a.mp4", 1000));
+        let b = queue.enqueue(file("b.mp4", 500));
+        let c = queue.enqueue(file("c.mp4", 250));
+        let d = queue.enqueue(file("stream.log", -1));
+        let e = queue.enqueue(file("e.jpg", 100));
+
+        assert_eq!(
+            queue.batch_progress(),
+            BatchProgress {
+                files_done: 0,
+                files_total: 5,
+                bytes_done: 0,
+                bytes_total: 1850,
+                files_unsized: 1,
+                files_failed: 0,
+            }
+        );
+
+        // a completes, b is a fifth of the way through
+        queue.next_ready();
+        queue.update_progress(a, 600).unwrap();
+        assert_eq!(queue.batch_progress().bytes_done, 600);
+        queue.complete(a).unwrap();
+        queue.next_ready();
+        queue.update_progress(b, 100).unwrap();
+
+        let progress = queue.batch_progress();
+        assert_eq!((progress.files_done, progress.files_total), (1, 5));
+        assert_eq!((progress.bytes_done, progress.bytes_total), (1100, 1850));
+        assert!((progress.fraction() - 1100.0 / 1850.0).abs() < 1e-9);
+
+        // c fails for good and drops out of the totals
+        queue.next_ready();
+        assert!(queue.fail(c, "denied").is_err());
+
+        // The unsized file counts what it has sent until its size is known
+        queue.next_ready();
+        queue.update_progress(d, 40).unwrap();
+        let progress = queue.batch_progress();
+        assert_eq!((progress.files_total, progress.files_failed), (4, 1));
+        assert_eq!((progress.bytes_done, progress.bytes_total), (1140, 1640));
+        assert_eq!(progress.files_unsized, 1);
+
+        queue.set_size(d, 80).unwrap();
+        let progress = queue.batch_progress();
+        assert_eq!((progress.bytes_done, progress.bytes_total), (1140, 1680));
+        assert_eq!(progress.files_unsized, 0);
+        assert!(queue.set_size(99, 1).is_err());
+
+        queue.complete(d).unwrap();
+        queue.next_ready();
+        queue.complete(e).unwrap();
+        queue.complete(b).unwrap();
+        let progress = queue.batch_progress();
+        assert_eq!((progress.files_done, progress.files_total), (4, 4));
+        assert_eq!(progress.fraction(), 1.0);
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        let policy = policy(10);
+        assert_eq!(policy.backoff(1), Duration::from_secs(1));
+        assert_eq!(policy.backoff(2), Duration::from_secs(2));
+        assert_eq!(policy.backoff(3), Duration::from_secs(4));
+        assert_eq!(policy.backoff(8), Duration::from_secs(4));
+    }
+}
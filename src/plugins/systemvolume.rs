@@ -0,0 +1,363 @@
+//! System Volume Plugin
+//!
+//! Controls the audio sinks (outputs) of a remote desktop.
+//!
+//! ## Packet Types
+//!
+//! - **Incoming**:
+//!   - `cconnect.systemvolume` - Sink list (`sinkList`) or a single sink update
+//! - **Outgoing**:
+//!   - `cconnect.systemvolume.request` - Request the sink list, or change a
+//!     sink's `volume`, `muted` or `enabled` (default output) state
+//!
+//! ## Example
+//!
+//! ```rust
+//! use cosmic_ext_connect_core::plugins::systemvolume::{AudioSink, SystemVolumePlugin};
+//!
+//! let mut plugin = SystemVolumePlugin::new();
+//! plugin.update_sinks(vec![AudioSink {
+//!     name: "alsa_output.speakers".to_string(),
+//!     description: "Speakers".to_string(),
+//!     volume: 40,
+//!     muted: false,
+//!     max_volume: 100,
+//!     enabled: true,
+//! }]);
+//!
+//! // Out-of-range values are clamped to 0..=maxVolume
+//! let packet = plugin.set_volume("alsa_output.speakers", 150).unwrap();
+//! assert_eq!(packet.body["volume"], 100);
+//! ```
+
+use crate::error::{ProtocolError, Result};
+use crate::plugins::{ActionDescriptor, ActionType, Plugin};
+use crate::protocol::Packet;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::{debug, info, warn};
+
+/// System volume packet type (sink list / updates)
+pub const PACKET_TYPE_SYSTEMVOLUME: &str = "cconnect.systemvolume";
+/// System volume request packet type
+pub const PACKET_TYPE_SYSTEMVOLUME_REQUEST: &str = "cconnect.systemvolume.request";
+
+/// Default maximum volume when a sink does not report one
+pub const DEFAULT_MAX_VOLUME: i32 = 100;
+
+fn default_max_volume() -> i32 {
+    DEFAULT_MAX_VOLUME
+}
+
+/// An audio output on the remote device
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioSink {
+    /// Sink identifier used in requests
+    pub name: String,
+
+    /// Human-readable description
+    #[serde(default)]
+    pub description: String,
+
+    /// Current volume (0..=max_volume)
+    #[serde(default)]
+    pub volume: i32,
+
+    /// Whether the sink is muted
+    #[serde(default)]
+    pub muted: bool,
+
+    /// Maximum volume for this sink
+    #[serde(default = "default_max_volume")]
+    pub max_volume: i32,
+
+    /// Whether this is the default output
+    #[serde(default)]
+    pub enabled: bool,
+}
+
+impl AudioSink {
+    /// Clamp a volume to `0..=max_volume`
+    pub fn clamp_volume(&self, volume: i32) -> i32 {
+        volume.clamp(0, self.max_volume.max(0))
+    }
+}
+
+/// System volume plugin
+///
+/// Caches the remote sink list and builds control packets. Platform code
+/// sends the returned packets and renders the sinks.
+pub struct SystemVolumePlugin {
+    /// Plugin name
+    name: String,
+
+    /// Last known sink list from the remote device
+    sinks: Vec<AudioSink>,
+}
+
+impl SystemVolumePlugin {
+    /// Create a new system volume plugin
+    pub fn new() -> Self {
+        Self {
+            name: "systemvolume".to_string(),
+            sinks: Vec::new(),
+        }
+    }
+
+    /// Get the cached sinks
+    pub fn sinks(&self) -> &[AudioSink] {
+        &self.sinks
+    }
+
+    /// Get a cached sink by name
+    pub fn sink(&self, name: &str) -> Option<&AudioSink> {
+        self.sinks.iter().find(|sink| sink.name == name)
+    }
+
+    /// Replace the cached sink list
+    pub fn update_sinks(&mut self, sinks: Vec<AudioSink>) {
+        debug!("Sink list updated: {} sinks", sinks.len());
+        self.sinks = sinks;
+    }
+
+    /// Create a packet requesting the sink list
+    pub fn request_sinks(&self) -> Packet {
+        Packet::new(PACKET_TYPE_SYSTEMVOLUME_REQUEST, json!({ "requestSinks": true }))
+    }
+
+    /// Create a packet setting a sink's volume
+    ///
+    /// The volume is clamped to `0..=maxVolume` of the cached sink.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::DeviceNotFound` if the sink is not in the cache
+    pub fn set_volume(&self, sink: &str, volume: i32) -> Result<Packet> {
+        let volume = self.known_sink(sink)?.clamp_volume(volume);
+        Ok(Packet::new(
+            PACKET_TYPE_SYSTEMVOLUME_REQUEST,
+            json!({ "name": sink, "volume": volume }),
+        ))
+    }
+
+    /// Create a packet muting or unmuting a sink
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::DeviceNotFound` if the sink is not in the cache
+    pub fn set_muted(&self, sink: &str, muted: bool) -> Result<Packet> {
+        self.known_sink(sink)?;
+        Ok(Packet::new(
+            PACKET_TYPE_SYSTEMVOLUME_REQUEST,
+            json!({ "name": sink, "muted": muted }),
+        ))
+    }
+
+    /// Create a packet making a sink the default output
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::DeviceNotFound` if the sink is not in the cache
+    pub fn set_enabled(&self, sink: &str) -> Result<Packet> {
+        self.known_sink(sink)?;
+        Ok(Packet::new(
+            PACKET_TYPE_SYSTEMVOLUME_REQUEST,
+            json!({ "name": sink, "enabled": true }),
+        ))
+    }
+
+    fn known_sink(&self, name: &str) -> Result<&AudioSink> {
+        self.sink(name)
+            .ok_or_else(|| ProtocolError::DeviceNotFound(format!("Unknown audio sink: {}", name)))
+    }
+
+    /// Apply a single-sink update to the cache
+    fn apply_update(&mut self, packet: &Packet) -> Result<()> {
+        let name = packet
+            .get_body_field::<String>("name")
+            .ok_or_else(|| ProtocolError::InvalidPacket("Missing sinkList or name".to_string()))?;
+
+        let Some(sink) = self.sinks.iter_mut().find(|sink| sink.name == name) else {
+            debug!("Update for unknown sink '{}' ignored", name);
+            return Ok(());
+        };
+
+        if let Some(volume) = packet.get_body_field::<i32>("volume") {
+            sink.volume = volume;
+        }
+        if let Some(muted) = packet.get_body_field::<bool>("muted") {
+            sink.muted = muted;
+        }
+        if let Some(enabled) = packet.get_body_field::<bool>("enabled") {
+            sink.enabled = enabled;
+        }
+
+        debug!("Sink '{}' updated", name);
+        Ok(())
+    }
+}
+
+impl Default for SystemVolumePlugin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Plugin for SystemVolumePlugin {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn incoming_capabilities(&self) -> Vec<String> {
+        vec![PACKET_TYPE_SYSTEMVOLUME.to_string()]
+    }
+
+    fn outgoing_capabilities(&self) -> Vec<String> {
+        vec![PACKET_TYPE_SYSTEMVOLUME_REQUEST.to_string()]
+    }
+
+    fn actions(&self) -> Vec<ActionDescriptor> {
+        vec![
+            ActionDescriptor::new("request_sinks"),
+            ActionDescriptor::new("sinks").with_returns(ActionType::Object),
+            ActionDescriptor::new("set_volume")
+                .with_parameter("sink", ActionType::String)
+                .with_parameter("volume", ActionType::I32),
+            ActionDescriptor::new("set_muted")
+                .with_parameter("sink", ActionType::String)
+                .with_parameter("muted", ActionType::Bool),
+            ActionDescriptor::new("set_enabled").with_parameter("sink", ActionType::String),
+        ]
+    }
+
+    async fn handle_packet(&mut self, packet: &Packet) -> Result<()> {
+        if !packet.is_type(PACKET_TYPE_SYSTEMVOLUME) {
+            warn!("Unexpected packet type: {}", packet.packet_type);
+            return Ok(());
+        }
+
+        match packet.body.get("sinkList") {
+            Some(list) => {
+                let sinks: Vec<AudioSink> =
+                    serde_json::from_value(list.clone()).map_err(|e| {
+                        ProtocolError::InvalidPacket(format!("Invalid sink list: {}", e))
+                    })?;
+                info!("Received {} audio sinks", sinks.len());
+                self.update_sinks(sinks);
+                Ok(())
+            }
+            None => self.apply_update(packet),
+        }
+    }
+
+    async fn initialize(&mut self) -> Result<()> {
+        info!("SystemVolume plugin initialized");
+        Ok(())
+    }
+
+    async fn shutdown(&mut self) -> Result<()> {
+        info!("SystemVolume plugin shutting down");
+        self.sinks.clear();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sink_list_packet() -> Packet {
+        Packet::new(
+            PACKET_TYPE_SYSTEMVOLUME,
+            json!({
+                "sinkList": [
+                    {
+                        "name": "speakers",
+                        "description": "Built-in Speakers",
+                        "volume": 30,
+                        "muted": false,
+                        "maxVolume": 65536,
+                        "enabled": true,
+                    },
+                    {
+                        "name": "hdmi",
+                        "description": "HDMI Output",
+                        "volume": 80,
+                        "muted": true,
+                        "maxVolume": 100,
+                        "enabled": false,
+                    },
+                ]
+            }),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_sink_list_round_trip() {
+        let mut plugin = SystemVolumePlugin::new();
+        let packet = sink_list_packet();
+        plugin.handle_packet(&packet).await.unwrap();
+
+        assert_eq!(plugin.sinks().len(), 2);
+        let speakers = plugin.sink("speakers").unwrap();
+        assert_eq!(speakers.description, "Built-in Speakers");
+        assert_eq!(speakers.max_volume, 65536);
+        assert!(speakers.enabled);
+        assert!(plugin.sink("hdmi").unwrap().muted);
+
+        // Serializes back to the same wire form
+        assert_eq!(serde_json::to_value(plugin.sinks()).unwrap(), packet.body["sinkList"]);
+    }
+
+    #[tokio::test]
+    async fn test_sink_update() {
+        let mut plugin = SystemVolumePlugin::new();
+        plugin.handle_packet(&sink_list_packet()).await.unwrap();
+
+        let update = Packet::new(
+            PACKET_TYPE_SYSTEMVOLUME,
+            json!({ "name": "hdmi", "volume": 55, "muted": false }),
+        );
+        plugin.handle_packet(&update).await.unwrap();
+
+        let hdmi = plugin.sink("hdmi").unwrap();
+        assert_eq!(hdmi.volume, 55);
+        assert!(!hdmi.muted);
+    }
+
+    #[tokio::test]
+    async fn test_set_volume_clamps() {
+        let mut plugin = SystemVolumePlugin::new();
+        plugin.handle_packet(&sink_list_packet()).await.unwrap();
+
+        let packet = plugin.set_volume("hdmi", 250).unwrap();
+        assert_eq!(packet.packet_type, PACKET_TYPE_SYSTEMVOLUME_REQUEST);
+        assert_eq!(packet.body["name"], "hdmi");
+        assert_eq!(packet.body["volume"], 100);
+
+        assert_eq!(plugin.set_volume("hdmi", -5).unwrap().body["volume"], 0);
+        assert_eq!(plugin.set_volume("speakers", 40000).unwrap().body["volume"], 40000);
+
+        assert!(matches!(
+            plugin.set_volume("missing", 10),
+            Err(ProtocolError::DeviceNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_control_packets() {
+        let mut plugin = SystemVolumePlugin::new();
+        plugin.handle_packet(&sink_list_packet()).await.unwrap();
+
+        let packet = plugin.set_muted("speakers", true).unwrap();
+        assert_eq!(packet.body["muted"], true);
+
+        let packet = plugin.set_enabled("hdmi").unwrap();
+        assert_eq!(packet.body["enabled"], true);
+
+        assert_eq!(plugin.request_sinks().body["requestSinks"], true);
+    }
+}
@@ -13,10 +13,12 @@
 //! - `cconnect.sms.request_conversations` - Request conversation list (outgoing)
 //! - `cconnect.sms.request_conversation` - Request thread messages (outgoing)
 //! - `cconnect.sms.request_attachment` - Request message attachment (outgoing)
+//! - `cconnect.sms.attachment_file` - Requested attachment as a payload (incoming)
 //! - `cconnect.sms.request` - Send SMS message (outgoing)
 //!
 //! **Capabilities**:
-//! - Incoming: `cconnect.telephony`, `cconnect.sms.messages`
+//! - Incoming: `cconnect.telephony`, `cconnect.sms.messages`,
+//!   `cconnect.sms.attachment_file`
 //! - Outgoing: `cconnect.telephony.request_mute`, `cconnect.sms.request*`
 //!
 //! ## Call Events
@@ -35,6 +37,22 @@
 //! - Message bodies
 //! - Read/unread status
 //! - Sender information
+//! - MMS attachments
+//!
+//! ## MMS Attachments
+//!
+//! Messages list their attachments as [`SmsAttachment`] metadata only. To
+//! get the bytes, send [`TelephonyPlugin::fetch_attachment`]'s request; the
+//! phone answers with a `cconnect.sms.attachment_file` packet carrying the
+//! file as a payload, which becomes an [`AttachmentDownload`] in
+//! [`TelephonyPlugin::take_attachment_downloads`]. Its
+//! [`receive`](AttachmentDownload::receive) verifies the bytes against the
+//! attachment's payload hash.
+//!
+//! Sending works the other way round: [`MmsAttachmentFile::stage`] hashes a
+//! file, [`TelephonyPlugin::create_send_mms_request`] advertises it as the
+//! request's payload, and the file is then streamed with
+//! [`PayloadSender::send_file`](crate::protocol::PayloadSender::send_file).
 //!
 //! ## References
 //!
@@ -43,11 +61,13 @@
 //! - [Valent Protocol Documentation](https://valent.andyholmes.ca/documentation/protocol.html)
 
 use crate::error::{ProtocolError, Result};
-use crate::protocol::Packet;
+use crate::protocol::{Packet, PayloadConfig, PayloadReceiver};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
-use serde_json::json;
-use std::any::Any;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncRead;
 use tracing::{debug, info, warn};
 
 use crate::plugins::Plugin;
@@ -70,6 +90,9 @@ pub const PACKET_TYPE_SMS_REQUEST_CONVERSATION: &str = "cconnect.sms.request_con
 /// Packet type for requesting message attachment
 pub const PACKET_TYPE_SMS_REQUEST_ATTACHMENT: &str = "cconnect.sms.request_attachment";
 
+/// Packet type for a requested attachment, sent as a payload
+pub const PACKET_TYPE_SMS_ATTACHMENT_FILE: &str = "cconnect.sms.attachment_file";
+
 /// Packet type for sending SMS
 pub const PACKET_TYPE_SMS_REQUEST: &str = "cconnect.sms.request";
 
@@ -97,7 +120,7 @@ impl CallEvent {
         }
     }
 
-    pub fn from_str(s: &str) -> Option<Self> {
+    pub fn parse_str(s: &str) -> Option<Self> {
         match s {
             "ringing" => Some(Self::Ringing),
             "talking" => Some(Self::Talking),
@@ -153,6 +176,117 @@ pub struct SmsMessage {
 
     /// Read status (0 = unread, 1 = read)
     pub read: i32,
+
+    /// MMS attachments
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<SmsAttachment>,
+}
+
+/// MMS attachment of an SMS message
+///
+/// Only metadata travels with the message; fetch the bytes with
+/// [`TelephonyPlugin::fetch_attachment`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SmsAttachment {
+    /// Attachment part ID (assigned by the phone; 0 for outgoing attachments)
+    #[serde(rename = "part_id")]
+    pub part_id: i64,
+
+    /// MIME type of the attachment
+    #[serde(rename = "mime_type")]
+    pub mime_type: String,
+
+    /// File name, unique per attachment
+    #[serde(rename = "unique_identifier", alias = "filename")]
+    pub filename: String,
+
+    /// Hex-encoded SHA-256 of the attachment's bytes
+    #[serde(
+        rename = "payload_hash",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub payload_hash: Option<String>,
+}
+
+/// Attachment the phone has sent as a payload, ready to be received
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttachmentDownload {
+    /// Attachment being downloaded
+    pub attachment: SmsAttachment,
+
+    /// Payload size in bytes
+    pub size: u64,
+
+    /// Where to connect for the payload (e.g. `port`)
+    pub transfer_info: HashMap<String, Value>,
+}
+
+impl AttachmentDownload {
+    /// Receive the attachment's bytes into a file at `path`
+    ///
+    /// `reader` is the payload connection described by `transfer_info`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::ChecksumMismatch` if the bytes do not match
+    /// the attachment's payload hash, or the errors of
+    /// [`PayloadReceiver::receive_file`].
+    pub async fn receive<R: AsyncRead + Unpin>(
+        &self,
+        receiver: &mut PayloadReceiver<R>,
+        path: impl AsRef<Path>,
+    ) -> Result<u64> {
+        receiver
+            .receive_file(path, self.size, self.attachment.payload_hash.as_deref(), |_, _| {})
+            .await
+    }
+}
+
+/// Local file staged as the payload of an outgoing MMS
+#[derive(Debug, Clone, PartialEq)]
+pub struct MmsAttachmentFile {
+    /// File to send
+    pub path: PathBuf,
+
+    /// Attachment metadata advertised in the request
+    pub attachment: SmsAttachment,
+
+    /// File size in bytes
+    pub size: u64,
+}
+
+impl MmsAttachmentFile {
+    /// Stage a file for sending, hashing its contents
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Io` if the file cannot be read.
+    pub async fn stage(path: impl Into<PathBuf>, mime_type: impl Into<String>) -> Result<Self> {
+        let path = path.into();
+        let file = tokio::fs::File::open(&path).await?;
+        let size = file.metadata().await?.len();
+        let (_, hash) = PayloadReceiver::new(file)
+            .with_config(PayloadConfig::unlimited())
+            .receive_into(tokio::io::sink(), size, |_, _| {})
+            .await?;
+
+        let filename = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        Ok(Self {
+            attachment: SmsAttachment {
+                part_id: 0,
+                mime_type: mime_type.into(),
+                filename,
+                payload_hash: Some(hash),
+            },
+            path,
+            size,
+        })
+    }
 }
 
 /// SMS conversation thread
@@ -217,13 +351,20 @@ pub struct SendSmsRequest {
 
 /// Telephony and SMS plugin
 pub struct TelephonyPlugin {
-    device_id: Option<String>,
+    /// Requested attachments by file name, until their payload arrives
+    pending_attachments: HashMap<String, SmsAttachment>,
+
+    /// Attachments whose payload has arrived
+    attachment_downloads: Vec<AttachmentDownload>,
 }
 
 impl TelephonyPlugin {
     /// Create a new Telephony plugin
     pub fn new() -> Self {
-        Self { device_id: None }
+        Self {
+            pending_attachments: HashMap::new(),
+            attachment_downloads: Vec::new(),
+        }
     }
 
     /// Create a mute ringer request packet
@@ -301,6 +442,52 @@ impl TelephonyPlugin {
         )
     }
 
+    /// Request an attachment's bytes from the phone
+    ///
+    /// The phone answers with the file as a payload, which then appears in
+    /// [`take_attachment_downloads`](Self::take_attachment_downloads).
+    pub fn fetch_attachment(&mut self, attachment: &SmsAttachment) -> Packet {
+        self.pending_attachments
+            .insert(attachment.filename.clone(), attachment.clone());
+        self.create_attachment_request(attachment.part_id, attachment.filename.clone())
+    }
+
+    /// Take the fetched attachments whose payload has arrived
+    pub fn take_attachment_downloads(&mut self) -> Vec<AttachmentDownload> {
+        std::mem::take(&mut self.attachment_downloads)
+    }
+
+    /// Create a request to send an MMS with a staged attachment
+    ///
+    /// The attachment is advertised as the packet's payload on `port`;
+    /// stream `attachment.path` there after sending the packet.
+    pub fn create_send_mms_request(
+        &self,
+        phone_number: String,
+        message: String,
+        attachment: &MmsAttachmentFile,
+        port: u16,
+    ) -> Packet {
+        debug!(
+            "Creating send MMS request to {} with {}",
+            phone_number, attachment.attachment.filename
+        );
+
+        let mut transfer_info = HashMap::new();
+        transfer_info.insert("port".to_string(), json!(port));
+
+        Packet::new(
+            PACKET_TYPE_SMS_REQUEST,
+            json!({
+                "phoneNumber": phone_number,
+                "messageBody": message,
+                "attachments": [attachment.attachment],
+            }),
+        )
+        .with_payload_size(attachment.size as i64)
+        .with_payload_transfer_info(transfer_info)
+    }
+
     /// Create a request to send an SMS
     ///
     /// # Arguments
@@ -324,7 +511,7 @@ impl TelephonyPlugin {
         let event: TelephonyEvent = serde_json::from_value(packet.body.clone())
             .map_err(|e| ProtocolError::InvalidPacket(format!("Failed to parse event: {}", e)))?;
 
-        let event_type = CallEvent::from_str(&event.event).unwrap_or_else(|| {
+        let event_type = CallEvent::parse_str(&event.event).unwrap_or_else(|| {
             warn!("Unknown telephony event: {}", event.event);
             CallEvent::Ringing
         });
@@ -360,6 +547,34 @@ impl TelephonyPlugin {
         Ok(())
     }
 
+    /// Handle a requested attachment's payload announcement
+    fn handle_attachment_file(&mut self, packet: &Packet) -> Result<()> {
+        let filename = packet
+            .body
+            .get("filename")
+            .and_then(Value::as_str)
+            .ok_or_else(|| {
+                ProtocolError::InvalidPacket("Attachment file without filename".to_string())
+            })?;
+
+        let Some(attachment) = self.pending_attachments.remove(filename) else {
+            warn!("Ignoring attachment {} that was not requested", filename);
+            return Ok(());
+        };
+
+        let size = packet.payload_size.ok_or_else(|| {
+            ProtocolError::InvalidPacket(format!("Attachment {} without payload", filename))
+        })?;
+
+        info!("Attachment {} ready ({} bytes)", filename, size);
+        self.attachment_downloads.push(AttachmentDownload {
+            attachment,
+            size: size.max(0) as u64,
+            transfer_info: packet.payload_transfer_info.clone().unwrap_or_default(),
+        });
+        Ok(())
+    }
+
     /// Handle SMS messages packet
     async fn handle_sms_messages(&self, packet: &Packet) -> Result<()> {
         let messages: SmsMessages = serde_json::from_value(packet.body.clone())
@@ -406,6 +621,7 @@ impl Plugin for TelephonyPlugin {
         vec![
             PACKET_TYPE_TELEPHONY.to_string(),
             PACKET_TYPE_SMS_MESSAGES.to_string(),
+            PACKET_TYPE_SMS_ATTACHMENT_FILE.to_string(),
         ]
     }
 
@@ -424,11 +640,6 @@ impl Plugin for TelephonyPlugin {
         Ok(())
     }
 
-    async fn start(&mut self) -> Result<()> {
-        info!("Telephony plugin started");
-        Ok(())
-    }
-
     async fn shutdown(&mut self) -> Result<()> {
         info!("Telephony plugin stopped");
         Ok(())
@@ -444,6 +655,10 @@ impl Plugin for TelephonyPlugin {
                 debug!("Received SMS messages");
                 self.handle_sms_messages(packet).await
             }
+            PACKET_TYPE_SMS_ATTACHMENT_FILE => {
+                debug!("Received SMS attachment file");
+                self.handle_attachment_file(packet)
+            }
             _ => {
                 warn!("Unexpected packet type: {}", packet.packet_type);
                 Ok(())
@@ -455,12 +670,13 @@ impl Plugin for TelephonyPlugin {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use sha2::Digest;
 
     #[tokio::test]
     async fn test_plugin_creation() {
         let plugin = TelephonyPlugin::new();
         assert_eq!(plugin.name(), "telephony");
-        assert!(plugin.device_id.is_none());
+        assert!(plugin.pending_attachments.is_empty());
     }
 
     #[tokio::test]
@@ -468,7 +684,7 @@ mod tests {
         let mut plugin = TelephonyPlugin::new();
 
         assert!(plugin.initialize().await.is_ok());
-        assert!(plugin.device_id.is_none());
+        assert!(plugin.pending_attachments.is_empty());
     }
 
     #[test]
@@ -513,8 +729,8 @@ mod tests {
     #[test]
     fn test_call_event_conversion() {
         assert_eq!(CallEvent::Ringing.as_str(), "ringing");
-        assert_eq!(CallEvent::from_str("talking"), Some(CallEvent::Talking));
-        assert_eq!(CallEvent::from_str("invalid"), None);
+        assert_eq!(CallEvent::parse_str("talking"), Some(CallEvent::Talking));
+        assert_eq!(CallEvent::parse_str("invalid"), None);
     }
 
     #[tokio::test]
@@ -533,12 +749,124 @@ mod tests {
         assert!(plugin.handle_telephony_event(&packet).await.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_mms_attachments() {
+        let image = b"not really a jpeg";
+        let image_hash = hex::encode(sha2::Sha256::digest(image));
+        let packet = Packet::new(
+            PACKET_TYPE_SMS_MESSAGES,
+            json!({
+                "conversations": [{
+                    "thread_id": 7,
+                    "messages": [{
+                        "_id": 42,
+                        "thread_id": 7,
+                        "address": "+1234567890",
+                        "body": "Look",
+                        "date": 1700000000000i64,
+                        "type": 1,
+                        "read": 0,
+                        "attachments": [
+                            {
+                                "part_id": 3,
+                                "mime_type": "image/jpeg",
+                                "unique_identifier": "PART_3.jpg",
+                                "payload_hash": image_hash,
+                            },
+                            {
+                                "part_id": 4,
+                                "mime_type": "video/mp4",
+                                "unique_identifier": "PART_4.mp4",
+                            },
+                        ],
+                    }],
+                }],
+            }),
+        );
+
+        let messages: SmsMessages = serde_json::from_value(packet.body.clone()).unwrap();
+        let attachments = &messages.conversations[0].messages[0].attachments;
+        assert_eq!(attachments.len(), 2);
+        assert_eq!(attachments[0].part_id, 3);
+        assert_eq!(attachments[0].mime_type, "image/jpeg");
+        assert_eq!(attachments[0].filename, "PART_3.jpg");
+        assert_eq!(attachments[0].payload_hash.as_deref(), Some(image_hash.as_str()));
+        assert_eq!(attachments[1].filename, "PART_4.mp4");
+        assert_eq!(attachments[1].payload_hash, None);
+
+        // Fetching requests the attachment by part ID and file name
+        let mut plugin = TelephonyPlugin::new();
+        plugin.handle_packet(&packet).await.unwrap();
+        let request = plugin.fetch_attachment(&attachments[0]);
+        assert_eq!(request.packet_type, PACKET_TYPE_SMS_REQUEST_ATTACHMENT);
+        assert_eq!(request.body["part_id"], 3);
+        assert_eq!(request.body["unique_identifier"], "PART_3.jpg");
+
+        // Unrequested attachments are ignored
+        let unrequested = Packet::new(PACKET_TYPE_SMS_ATTACHMENT_FILE, json!({ "filename": "x" }))
+            .with_payload_size(1);
+        plugin.handle_packet(&unrequested).await.unwrap();
+        assert!(plugin.take_attachment_downloads().is_empty());
+
+        let mut transfer_info = HashMap::new();
+        transfer_info.insert("port".to_string(), json!(1739));
+        let file = Packet::new(
+            PACKET_TYPE_SMS_ATTACHMENT_FILE,
+            json!({ "filename": "PART_3.jpg" }),
+        )
+        .with_payload_size(image.len() as i64)
+        .with_payload_transfer_info(transfer_info);
+        plugin.handle_packet(&file).await.unwrap();
+
+        let downloads = plugin.take_attachment_downloads();
+        assert_eq!(downloads.len(), 1);
+        assert_eq!(downloads[0].attachment, attachments[0]);
+        assert_eq!(downloads[0].transfer_info["port"], 1739);
+
+        // The received bytes are checked against the payload hash
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("PART_3.jpg");
+        let mut receiver = PayloadReceiver::new(&image[..]);
+        downloads[0].receive(&mut receiver, &path).await.unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), image);
+
+        let mut corrupted = PayloadReceiver::new(&b"NOT really a jpeg"[..]);
+        assert!(downloads[0].receive(&mut corrupted, &path).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_send_mms_stages_payload() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("photo.png");
+        std::fs::write(&path, b"png bytes").unwrap();
+
+        let staged = MmsAttachmentFile::stage(&path, "image/png").await.unwrap();
+        assert_eq!(staged.size, 9);
+        assert_eq!(staged.attachment.filename, "photo.png");
+        assert_eq!(
+            staged.attachment.payload_hash,
+            Some(hex::encode(sha2::Sha256::digest(b"png bytes")))
+        );
+
+        let plugin = TelephonyPlugin::new();
+        let packet = plugin.create_send_mms_request(
+            "+1234567890".to_string(),
+            "Photo".to_string(),
+            &staged,
+            1739,
+        );
+        assert_eq!(packet.packet_type, PACKET_TYPE_SMS_REQUEST);
+        assert_eq!(packet.payload_size, Some(9));
+        assert_eq!(packet.payload_transfer_info.unwrap()["port"], 1739);
+        assert_eq!(packet.body["attachments"][0]["unique_identifier"], "photo.png");
+        assert_eq!(packet.body["attachments"][0]["mime_type"], "image/png");
+    }
+
     #[tokio::test]
     async fn test_plugin_lifecycle() {
         let mut plugin = TelephonyPlugin::new();
 
         assert!(plugin.initialize().await.is_ok());
-        assert!(plugin.start().await.is_ok());
         assert!(plugin.shutdown().await.is_ok());
     }
 }
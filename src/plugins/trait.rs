@@ -56,8 +56,11 @@
 //! ```
 
 use crate::error::Result;
-use crate::protocol::Packet;
+use crate::plugins::rate_limit::{RateLimit, CONTROL_RATE_LIMIT};
+use crate::protocol::{Packet, VersionRange};
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 /// Plugin trait for KDE Connect plugins
 ///
@@ -69,9 +72,11 @@ use async_trait::async_trait;
 ///
 /// 1. **Construction**: Plugin is created (constructor)
 /// 2. **Registration**: Plugin is registered with PluginManager
-/// 3. **Initialization**: `initialize()` is called to set up resources
+/// 3. **Initialization**: `initialize()` is called to set up resources, after
+///    the plugins named in `depends_on()`
 /// 4. **Operation**: `handle_packet()` is called for incoming packets
-/// 5. **Shutdown**: `shutdown()` is called to clean up resources
+/// 5. **Shutdown**: `shutdown()` is called to clean up resources, before
+///    the plugins it depends on
 ///
 /// ## Thread Safety
 ///
@@ -213,6 +218,155 @@ pub trait Plugin: Send + Sync {
     fn get_capabilities(&self) -> (Vec<String>, Vec<String>) {
         (self.incoming_capabilities(), self.outgoing_capabilities())
     }
+
+    /// Get the protocol versions supported per capability
+    ///
+    /// Capabilities not listed are advertised as version 1 only. Override
+    /// this when a plugin supports newer packet formats, then branch on the
+    /// version from [`NegotiatedCapabilities`](crate::protocol::NegotiatedCapabilities)
+    /// when building packets.
+    fn capability_versions(&self) -> HashMap<String, VersionRange> {
+        HashMap::new()
+    }
+
+    /// Get the outgoing packet types where only the latest packet matters
+    ///
+    /// A queued packet of one of these types is replaced by a newer one
+    /// instead of both being sent (see
+    /// [`SendQueue`](crate::network::transport::SendQueue)). Use this for
+    /// frequent status updates, not for requests or realtime data.
+    fn coalescing_packet_types(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Get the rate this plugin may send packets at
+    ///
+    /// Enforced by
+    /// [`PluginManager::check_outgoing`](crate::plugins::PluginManager::check_outgoing).
+    /// Control plugins keep the strict default; realtime media plugins
+    /// should return [`MEDIA_RATE_LIMIT`](crate::plugins::MEDIA_RATE_LIMIT).
+    fn outgoing_rate_limit(&self) -> RateLimit {
+        CONTROL_RATE_LIMIT
+    }
+
+    /// Get the names of plugins this plugin depends on
+    ///
+    /// The PluginManager initializes dependencies before this plugin and
+    /// shuts them down after it. Registration fails if a dependency is not
+    /// registered or the dependencies form a cycle.
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// fn depends_on(&self) -> Vec<&str> {
+    ///     vec!["share"]
+    /// }
+    /// ```
+    fn depends_on(&self) -> Vec<&str> {
+        Vec::new()
+    }
+
+    /// Describe the actions platform code can call on this plugin
+    ///
+    /// This is introspection metadata for generic FFI and DBus bridges: it
+    /// lets them expose a plugin's methods without per-plugin glue. It does
+    /// not execute anything.
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// fn actions(&self) -> Vec<ActionDescriptor> {
+    ///     vec![ActionDescriptor::new("seek").with_parameter("offset_ms", ActionType::I64)]
+    /// }
+    /// ```
+    fn actions(&self) -> Vec<ActionDescriptor> {
+        Vec::new()
+    }
+}
+
+/// Type of an action parameter or return value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ActionType {
+    /// No value
+    Unit,
+    /// Boolean
+    Bool,
+    /// 32-bit signed integer
+    I32,
+    /// 64-bit signed integer
+    I64,
+    /// 32-bit unsigned integer
+    U32,
+    /// 64-bit unsigned integer
+    U64,
+    /// 64-bit float
+    F64,
+    /// UTF-8 string
+    String,
+    /// JSON value in the plugin's packet format, or `null` if absent
+    Object,
+}
+
+/// Named parameter of an action
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActionParameter {
+    /// Parameter name
+    pub name: String,
+
+    /// Parameter type
+    #[serde(rename = "type")]
+    pub param_type: ActionType,
+}
+
+/// Description of an action a plugin exposes to platform code
+///
+/// See [`Plugin::actions`].
+///
+/// ```rust
+/// use cosmic_ext_connect_core::plugins::{ActionDescriptor, ActionType};
+///
+/// let seek = ActionDescriptor::new("seek")
+///     .with_parameter("player", ActionType::String)
+///     .with_parameter("offset_ms", ActionType::I64);
+/// assert_eq!(seek.returns, ActionType::Unit);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActionDescriptor {
+    /// Action name
+    pub name: String,
+
+    /// Parameters in call order
+    pub parameters: Vec<ActionParameter>,
+
+    /// Return type
+    pub returns: ActionType,
+}
+
+impl ActionDescriptor {
+    /// Create a descriptor for an action without parameters or return value
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            parameters: Vec::new(),
+            returns: ActionType::Unit,
+        }
+    }
+
+    /// Builder: Append a parameter
+    pub fn with_parameter(mut self, name: impl Into<String>, param_type: ActionType) -> Self {
+        self.parameters.push(ActionParameter {
+            name: name.into(),
+            param_type,
+        });
+        self
+    }
+
+    /// Builder: Set the return type
+    pub fn with_returns(mut self, returns: ActionType) -> Self {
+        self.returns = returns;
+        self
+    }
 }
 
 /// Plugin metadata
@@ -4,6 +4,7 @@
 //! Reports virtual monitor status and accepts enable/disable commands.
 
 use crate::protocol::Packet;
+use crate::plugins::{RateLimit, MEDIA_RATE_LIMIT};
 use crate::error::Result;
 use serde_json::json;
 
@@ -12,6 +13,9 @@ pub const PACKET_TYPE_VIRTUALMONITOR: &str = "cconnect.virtualmonitor";
 /// Virtual monitor request packet type
 pub const PACKET_TYPE_VIRTUALMONITOR_REQUEST: &str = "cconnect.virtualmonitor.request";
 
+/// Outgoing rate limit for this plugin, which streams realtime media
+pub const OUTGOING_RATE_LIMIT: RateLimit = MEDIA_RATE_LIMIT;
+
 /// Create a virtual monitor status packet
 pub fn create_virtualmonitor_status(
     is_active: bool,
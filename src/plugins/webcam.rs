@@ -4,6 +4,7 @@
 //! This is the reverse direction of the Camera plugin — desktop webcam → phone display.
 
 use crate::protocol::Packet;
+use crate::plugins::{RateLimit, MEDIA_RATE_LIMIT};
 use crate::error::Result;
 use serde_json::json;
 
@@ -14,6 +15,9 @@ pub const PACKET_TYPE_WEBCAM_REQUEST: &str = "cconnect.webcam.request";
 /// Webcam capability packet type
 pub const PACKET_TYPE_WEBCAM_CAPABILITY: &str = "cconnect.webcam.capability";
 
+/// Outgoing rate limit for this plugin, which streams realtime media
+pub const OUTGOING_RATE_LIMIT: RateLimit = MEDIA_RATE_LIMIT;
+
 /// Create a webcam start request packet
 ///
 /// Requests the desktop to start streaming its webcam.
@@ -0,0 +1,411 @@
+//! Packet Capture and Replay
+//!
+//! Protocol bugs are often hard to reproduce without the peer that
+//! triggered them. [`PacketTap`] records the packets exchanged with a device,
+//! with their [`Direction`] and a timestamp, and [`replay`] feeds the
+//! captured incoming packets through a [`PluginManager`] again, offline.
+//!
+//! ## Format
+//!
+//! A capture is a sequence of records, one per line. Each line is the byte
+//! length of the record's JSON in decimal, a space, the JSON of a
+//! [`CapturedPacket`] and a newline:
+//!
+//! ```text
+//! 95 {"direction":"incoming","timestamp":1700000000000,"packet":{...}}
+//! ```
+//!
+//! The length prefix catches records cut short by a crash mid-write, which a
+//! line alone would not. Packets are stored whole, so a capture replays
+//! exactly what was received.
+//!
+//! ## Redaction
+//!
+//! Captures are meant to be attached to bug reports. With
+//! [`PacketTap::with_redaction`], the sensitive fields of known packet types
+//! (clipboard content, notification text, ...) are masked as in
+//! [`Packet::redacted`]. Redacted captures still replay, but plugins see the
+//! masked values.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use cosmic_ext_connect_core::plugins::{ping::PingPlugin, PluginManager};
+//! use cosmic_ext_connect_core::protocol::capture::{self, PacketTap};
+//! use cosmic_ext_connect_core::protocol::Packet;
+//! use serde_json::json;
+//!
+//! # async fn example() -> cosmic_ext_connect_core::Result<()> {
+//! let path = std::env::temp_dir().join("session.capture");
+//! let mut tap = PacketTap::create(&path)?.with_redaction(true);
+//! tap.incoming(&Packet::new("cconnect.ping", json!({})))?;
+//! tap.flush()?;
+//!
+//! let mut manager = PluginManager::new();
+//! manager.register_plugin(Box::new(PingPlugin::new())).await?;
+//! for outcome in capture::replay(&path, &manager).await? {
+//!     println!("{:?}", outcome);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{ProtocolError, Result};
+use crate::plugins::{DispatchOutcome, PluginManager};
+use crate::protocol::packet::current_timestamp;
+use crate::protocol::Packet;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use tracing::debug;
+
+/// Which way a captured packet travelled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    /// Received from the peer
+    Incoming,
+    /// Sent to the peer
+    Outgoing,
+}
+
+/// One record of a capture
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CapturedPacket {
+    /// Which way the packet travelled
+    pub direction: Direction,
+    /// When the packet was recorded (UNIX epoch milliseconds)
+    pub timestamp: i64,
+    /// The packet, redacted if the tap redacts
+    pub packet: Packet,
+}
+
+/// Records packets to a capture
+///
+/// Records are buffered by the writer; call [`flush`](Self::flush) before
+/// reading the capture back.
+#[derive(Debug)]
+pub struct PacketTap<W: Write> {
+    /// Where records are written
+    writer: W,
+    /// Whether sensitive fields are masked
+    redact: bool,
+    /// Records written so far
+    recorded: usize,
+}
+
+impl PacketTap<BufWriter<File>> {
+    /// Create a tap writing to a new capture file, replacing any existing one
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Io` if the file cannot be created.
+    pub fn create(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self::new(BufWriter::new(File::create(path)?)))
+    }
+}
+
+impl<W: Write> PacketTap<W> {
+    /// Create a tap writing to `writer`
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            redact: false,
+            recorded: 0,
+        }
+    }
+
+    /// Mask sensitive fields of known packet types in the capture
+    pub fn with_redaction(mut self, redact: bool) -> Self {
+        self.redact = redact;
+        self
+    }
+
+    /// Get the number of packets recorded
+    pub fn recorded(&self) -> usize {
+        self.recorded
+    }
+
+    /// Record a packet received from the peer
+    ///
+    /// # Errors
+    ///
+    /// Same as [`record`](Self::record).
+    pub fn incoming(&mut self, packet: &Packet) -> Result<()> {
+        self.record(Direction::Incoming, packet)
+    }
+
+    /// Record a packet sent to the peer
+    ///
+    /// # Errors
+    ///
+    /// Same as [`record`](Self::record).
+    pub fn outgoing(&mut self, packet: &Packet) -> Result<()> {
+        self.record(Direction::Outgoing, packet)
+    }
+
+    /// Record a packet with the current time
+    ///
+    /// # Errors
+    ///
+    /// Same as [`record_at`](Self::record_at).
+    pub fn record(&mut self, direction: Direction, packet: &Packet) -> Result<()> {
+        self.record_at(direction, packet, current_timestamp())
+    }
+
+    /// Record a packet with a timestamp in UNIX epoch milliseconds
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Json` if the packet cannot be serialized, or
+    /// `ProtocolError::Io` if the record cannot be written.
+    pub fn record_at(
+        &mut self,
+        direction: Direction,
+        packet: &Packet,
+        timestamp: i64,
+    ) -> Result<()> {
+        let packet = if self.redact {
+            packet.redacted().packet().clone()
+        } else {
+            packet.clone()
+        };
+        let record = CapturedPacket {
+            direction,
+            timestamp,
+            packet,
+        };
+
+        let json = serde_json::to_string(&record)?;
+        writeln!(self.writer, "{} {}", json.len(), json)?;
+        self.recorded += 1;
+        Ok(())
+    }
+
+    /// Flush buffered records to the writer
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Io` if flushing fails.
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Flush and return the writer
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Io` if flushing fails.
+    pub fn into_inner(mut self) -> Result<W> {
+        self.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Read every record of a capture
+///
+/// # Errors
+///
+/// Returns `ProtocolError::Io` if reading fails,
+/// `ProtocolError::TruncatedPacket` if the last record was cut short, and
+/// `ProtocolError::InvalidPacket` if a record is malformed.
+pub fn read_capture<R: Read>(reader: R) -> Result<Vec<CapturedPacket>> {
+    let mut records = Vec::new();
+
+    for (index, line) in BufReader::new(reader).lines().enumerate() {
+        let line = line?;
+        if line.is_empty() {
+            continue;
+        }
+        let invalid = |reason: &str| {
+            ProtocolError::InvalidPacket(format!("Capture record {}: {}", index + 1, reason))
+        };
+
+        let (length, json) = line
+            .split_once(' ')
+            .ok_or_else(|| invalid("missing length prefix"))?;
+        let length: usize = length.parse().map_err(|_| invalid("invalid length prefix"))?;
+        if json.len() < length {
+            return Err(ProtocolError::TruncatedPacket {
+                buffered: json.len(),
+            });
+        }
+        if json.len() != length {
+            return Err(invalid("length does not match record"));
+        }
+
+        let record = serde_json::from_str(json).map_err(|e| invalid(&e.to_string()))?;
+        records.push(record);
+    }
+
+    Ok(records)
+}
+
+/// Dispatch the incoming packets of a capture file through `manager`
+///
+/// Outgoing packets are skipped. Returns the dispatch result of each
+/// incoming packet in capture order; a failing packet does not stop the
+/// replay.
+///
+/// # Errors
+///
+/// Returns an error from [`read_capture`] if the file cannot be read.
+pub async fn replay(
+    path: impl AsRef<Path>,
+    manager: &PluginManager,
+) -> Result<Vec<Result<DispatchOutcome>>> {
+    let records = read_capture(File::open(path)?)?;
+    let mut outcomes = Vec::new();
+
+    for record in records {
+        if record.direction != Direction::Incoming {
+            continue;
+        }
+        debug!("Replaying captured {}", record.packet.packet_type);
+        outcomes.push(manager.dispatch(&record.packet).await);
+    }
+
+    Ok(outcomes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins::Plugin;
+    use async_trait::async_trait;
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+
+    /// Packets handled, as (plugin, packet type, body)
+    type HandledLog = Arc<Mutex<Vec<(String, String, serde_json::Value)>>>;
+
+    struct RecordingPlugin {
+        name: String,
+        incoming: Vec<String>,
+        handled: HandledLog,
+    }
+
+    #[async_trait]
+    impl Plugin for RecordingPlugin {
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        fn incoming_capabilities(&self) -> Vec<String> {
+            self.incoming.clone()
+        }
+
+        fn outgoing_capabilities(&self) -> Vec<String> {
+            Vec::new()
+        }
+
+        async fn handle_packet(&mut self, packet: &Packet) -> Result<()> {
+            self.handled.lock().unwrap().push((
+                self.name.clone(),
+                packet.packet_type.clone(),
+                packet.body.clone(),
+            ));
+            Ok(())
+        }
+
+        async fn initialize(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        async fn shutdown(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    async fn recording_manager() -> (PluginManager, HandledLog) {
+        let handled = HandledLog::default();
+        let mut manager = PluginManager::new();
+        for (name, incoming) in [("ping", "cconnect.ping"), ("clipboard", "cconnect.clipboard")] {
+            let plugin = RecordingPlugin {
+                name: name.to_string(),
+                incoming: vec![incoming.to_string()],
+                handled: handled.clone(),
+            };
+            manager.register_plugin(Box::new(plugin)).await.unwrap();
+        }
+        (manager, handled)
+    }
+
+    #[tokio::test]
+    async fn test_capture_and_replay() {
+        let packets = [
+            (Direction::Incoming, Packet::new("cconnect.ping", json!({ "message": "hi" }))),
+            (Direction::Outgoing, Packet::new("cconnect.ping", json!({}))),
+            (Direction::Incoming, Packet::new("cconnect.clipboard", json!({ "content": "x" }))),
+            (Direction::Incoming, Packet::new("cconnect.unknown", json!({}))),
+        ];
+
+        // Capture a live session
+        let (live, live_handled) = recording_manager().await;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.capture");
+        let mut tap = PacketTap::create(&path).unwrap();
+        for (direction, packet) in &packets {
+            tap.record(*direction, packet).unwrap();
+            if *direction == Direction::Incoming {
+                live.dispatch(packet).await.unwrap();
+            }
+        }
+        assert_eq!(tap.recorded(), 4);
+        tap.flush().unwrap();
+
+        let records = read_capture(File::open(&path).unwrap()).unwrap();
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[1].direction, Direction::Outgoing);
+        assert_eq!(records[2].packet, packets[2].1);
+
+        // The same plugins handle the same packets on replay
+        let (offline, offline_handled) = recording_manager().await;
+        let outcomes = replay(&path, &offline).await.unwrap();
+        assert_eq!(outcomes.len(), 3);
+        assert!(matches!(outcomes[0], Ok(DispatchOutcome::Handled)));
+        assert!(
+            matches!(&outcomes[2], Ok(DispatchOutcome::Unhandled(t)) if t == "cconnect.unknown")
+        );
+        assert_eq!(live_handled.lock().unwrap().len(), 2);
+        assert_eq!(*offline_handled.lock().unwrap(), *live_handled.lock().unwrap());
+    }
+
+    #[test]
+    fn test_redacted_capture() {
+        let packet = Packet::new("cconnect.clipboard", json!({ "content": "hunter2" }));
+        let mut tap = PacketTap::new(Vec::new()).with_redaction(true);
+        tap.record_at(Direction::Incoming, &packet, 1_700_000_000_000).unwrap();
+        let bytes = tap.into_inner().unwrap();
+
+        let text = String::from_utf8(bytes.clone()).unwrap();
+        assert!(!text.contains("hunter2"));
+        let (length, json) = text.trim_end().split_once(' ').unwrap();
+        assert_eq!(length.parse::<usize>().unwrap(), json.len());
+
+        let records = read_capture(bytes.as_slice()).unwrap();
+        assert_eq!(records[0].timestamp, 1_700_000_000_000);
+        assert_eq!(records[0].packet.body["content"], crate::protocol::REDACTED);
+        assert_eq!(records[0].packet.id, packet.id);
+    }
+
+    #[test]
+    fn test_truncated_capture_rejected() {
+        let mut tap = PacketTap::new(Vec::new());
+        tap.incoming(&Packet::new("cconnect.ping", json!({}))).unwrap();
+        let bytes = tap.into_inner().unwrap();
+
+        // A crash cut the record short
+        let cut = &bytes[..bytes.len() - 10];
+        assert!(matches!(
+            read_capture(cut),
+            Err(ProtocolError::TruncatedPacket { .. })
+        ));
+        assert!(matches!(
+            read_capture(&b"not a record\n"[..]),
+            Err(ProtocolError::InvalidPacket(_))
+        ));
+    }
+}
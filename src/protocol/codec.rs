@@ -0,0 +1,200 @@
+//! Packet Framing
+//!
+//! KDE Connect packets are newline-terminated JSON lines. [`PacketCodec`]
+//! splits a byte stream into packets without doing any I/O itself: callers
+//! feed it whatever bytes a read returned and pull complete packets out.
+//!
+//! A read may end in the middle of a line. The codec keeps the partial line
+//! and completes it when more bytes arrive. If the stream ends while a
+//! partial line is buffered, [`PacketCodec::decode_eof`] reports
+//! `ProtocolError::TruncatedPacket` instead of silently dropping the data.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use cosmic_ext_connect_core::protocol::{Packet, PacketCodec};
+//! use serde_json::json;
+//!
+//! let bytes = Packet::new("cconnect.ping", json!({})).to_bytes().unwrap();
+//! let (first, rest) = bytes.split_at(10);
+//!
+//! let mut codec = PacketCodec::new();
+//! codec.feed(first);
+//! assert!(codec.decode().unwrap().is_none()); // need more bytes
+//!
+//! codec.feed(rest);
+//! let packet = codec.decode().unwrap().unwrap();
+//! assert_eq!(packet.packet_type, "cconnect.ping");
+//! ```
+
+use crate::error::{ProtocolError, Result};
+use crate::protocol::Packet;
+
+/// Default maximum size of a single packet line (10 MB)
+pub const DEFAULT_MAX_PACKET_SIZE: usize = 10 * 1024 * 1024;
+
+/// Incremental decoder for newline-delimited packets
+#[derive(Debug, Clone)]
+pub struct PacketCodec {
+    /// Bytes received but not yet decoded
+    buffer: Vec<u8>,
+    /// Maximum line length accepted
+    max_packet_size: usize,
+}
+
+impl Default for PacketCodec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PacketCodec {
+    /// Create a codec with [`DEFAULT_MAX_PACKET_SIZE`]
+    pub fn new() -> Self {
+        Self::with_max_packet_size(DEFAULT_MAX_PACKET_SIZE)
+    }
+
+    /// Create a codec that rejects lines longer than `max_packet_size`
+    pub fn with_max_packet_size(max_packet_size: usize) -> Self {
+        Self {
+            buffer: Vec::new(),
+            max_packet_size,
+        }
+    }
+
+    /// Append bytes read from the stream
+    pub fn feed(&mut self, data: &[u8]) {
+        self.buffer.extend_from_slice(data);
+    }
+
+    /// Number of buffered bytes not yet decoded
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Decode the next complete packet
+    ///
+    /// Returns `Ok(None)` if more bytes are needed. Blank lines are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::InvalidPacket` if a line exceeds the maximum
+    /// packet size or is not a valid packet. The offending line is discarded,
+    /// so decoding can continue with the next one.
+    pub fn decode(&mut self) -> Result<Option<Packet>> {
+        loop {
+            let Some(end) = self.buffer.iter().position(|&b| b == b'\n') else {
+                if self.buffer.len() > self.max_packet_size {
+                    let size = self.buffer.len();
+                    self.buffer.clear();
+                    return Err(Self::too_large(size, self.max_packet_size));
+                }
+                return Ok(None);
+            };
+
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            if line.len() > self.max_packet_size {
+                return Err(Self::too_large(line.len(), self.max_packet_size));
+            }
+            if line.iter().all(u8::is_ascii_whitespace) {
+                continue;
+            }
+            return Packet::from_bytes(&line).map(Some);
+        }
+    }
+
+    /// Decode the next packet after the stream has ended
+    ///
+    /// Returns complete packets that are still buffered, then `Ok(None)`
+    /// once the buffer is drained.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::TruncatedPacket` if the stream ended in the
+    /// middle of a packet. The partial line is discarded.
+    pub fn decode_eof(&mut self) -> Result<Option<Packet>> {
+        if let Some(packet) = self.decode()? {
+            return Ok(Some(packet));
+        }
+
+        if self.buffer.iter().all(u8::is_ascii_whitespace) {
+            self.buffer.clear();
+            return Ok(None);
+        }
+
+        let buffered = self.buffer.len();
+        self.buffer.clear();
+        Err(ProtocolError::TruncatedPacket { buffered })
+    }
+
+    fn too_large(size: usize, max: usize) -> ProtocolError {
+        ProtocolError::InvalidPacket(format!(
+            "Packet too large: {} bytes (max {})",
+            size, max
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn ping_bytes() -> Vec<u8> {
+        Packet::new("cconnect.ping", json!({ "message": "hi" }))
+            .to_bytes()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_partial_line_then_rest_decodes() {
+        let bytes = ping_bytes();
+        let mut codec = PacketCodec::new();
+
+        codec.feed(&bytes[..5]);
+        assert!(codec.decode().unwrap().is_none());
+        assert_eq!(codec.buffered(), 5);
+
+        // Rest of the line plus half of the next packet
+        codec.feed(&bytes[5..]);
+        codec.feed(&bytes[..5]);
+        let packet = codec.decode().unwrap().unwrap();
+        assert_eq!(packet.body["message"], "hi");
+        assert!(codec.decode().unwrap().is_none());
+
+        codec.feed(&bytes[5..]);
+        assert!(codec.decode_eof().unwrap().is_some());
+        assert!(codec.decode_eof().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_partial_line_then_eof_is_truncated() {
+        let bytes = ping_bytes();
+        let mut codec = PacketCodec::new();
+
+        codec.feed(&bytes[..bytes.len() - 3]);
+        assert!(codec.decode().unwrap().is_none());
+
+        match codec.decode_eof() {
+            Err(ProtocolError::TruncatedPacket { buffered }) => {
+                assert_eq!(buffered, bytes.len() - 3)
+            }
+            other => panic!("expected TruncatedPacket, got {:?}", other),
+        }
+        assert_eq!(codec.buffered(), 0);
+
+        // A clean close with nothing buffered is not an error
+        assert!(codec.decode_eof().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_oversized_line_rejected() {
+        let mut codec = PacketCodec::with_max_packet_size(16);
+        codec.feed(&[b'x'; 17]);
+        assert!(matches!(
+            codec.decode(),
+            Err(ProtocolError::InvalidPacket(_))
+        ));
+        assert_eq!(codec.buffered(), 0);
+    }
+}
@@ -0,0 +1,187 @@
+//! Device IDs
+//!
+//! KDE Connect identifies devices by an ID of 32 to 38 ASCII letters,
+//! digits and underscores, and peers reject identities whose ID does not
+//! follow that rule. [`DeviceId`] holds an ID that has been checked, so an
+//! invalid one is caught where it enters (e.g. an identity packet) instead
+//! of being stored, paired with or handed to plugins.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use cosmic_ext_connect_core::protocol::DeviceId;
+//!
+//! let id = DeviceId::generate();
+//! assert_eq!(DeviceId::parse(id.as_str()).unwrap(), id);
+//!
+//! assert!(DeviceId::parse("my-phone").is_err());
+//! ```
+
+use crate::error::{ProtocolError, Result};
+use serde::{Deserialize, Serialize};
+use std::borrow::Borrow;
+use std::fmt;
+use std::str::FromStr;
+use uuid::Uuid;
+
+/// Shortest valid device ID
+pub const DEVICE_ID_MIN_LENGTH: usize = 32;
+
+/// Longest valid device ID
+pub const DEVICE_ID_MAX_LENGTH: usize = 38;
+
+/// Validated device ID
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct DeviceId(String);
+
+impl DeviceId {
+    /// Generate a random device ID
+    ///
+    /// The ID is a UUIDv4 with underscores instead of hyphens (36
+    /// characters), as KDE Connect generates them.
+    pub fn generate() -> Self {
+        Self(Uuid::new_v4().to_string().replace('-', "_"))
+    }
+
+    /// Parse and validate a device ID
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::InvalidDeviceId` if the ID is not 32 to 38
+    /// characters long or contains anything but ASCII letters, digits and
+    /// underscores.
+    pub fn parse(device_id: &str) -> Result<Self> {
+        Self::validate(device_id)?;
+        Ok(Self(device_id.to_string()))
+    }
+
+    fn validate(device_id: &str) -> Result<()> {
+        if !(DEVICE_ID_MIN_LENGTH..=DEVICE_ID_MAX_LENGTH).contains(&device_id.len()) {
+            return Err(ProtocolError::InvalidDeviceId(format!(
+                "{:?} has {} characters, expected {} to {}",
+                device_id,
+                device_id.len(),
+                DEVICE_ID_MIN_LENGTH,
+                DEVICE_ID_MAX_LENGTH
+            )));
+        }
+        if let Some(c) = device_id
+            .chars()
+            .find(|c| !(c.is_ascii_alphanumeric() || *c == '_'))
+        {
+            return Err(ProtocolError::InvalidDeviceId(format!(
+                "{:?} contains {:?}",
+                device_id, c
+            )));
+        }
+        Ok(())
+    }
+
+    /// Get the ID as a string slice
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Consume the ID and return it as a string
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+impl TryFrom<String> for DeviceId {
+    type Error = ProtocolError;
+
+    fn try_from(device_id: String) -> Result<Self> {
+        Self::validate(&device_id)?;
+        Ok(Self(device_id))
+    }
+}
+
+impl FromStr for DeviceId {
+    type Err = ProtocolError;
+
+    fn from_str(device_id: &str) -> Result<Self> {
+        Self::parse(device_id)
+    }
+}
+
+impl From<DeviceId> for String {
+    fn from(device_id: DeviceId) -> Self {
+        device_id.0
+    }
+}
+
+impl AsRef<str> for DeviceId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for DeviceId {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq<str> for DeviceId {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for DeviceId {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+impl fmt::Display for DeviceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_ids_are_valid() {
+        let id = DeviceId::generate();
+        assert_eq!(id.as_str().len(), 36);
+        assert_eq!(DeviceId::parse(id.as_str()).unwrap(), id);
+        assert_ne!(DeviceId::generate(), id);
+
+        // Round trip through text and JSON
+        assert_eq!(id.to_string().parse::<DeviceId>().unwrap(), id);
+        let json = serde_json::to_string(&id).unwrap();
+        assert_eq!(json, format!("\"{}\"", id));
+        assert_eq!(serde_json::from_str::<DeviceId>(&json).unwrap(), id);
+    }
+
+    #[test]
+    fn test_invalid_ids_rejected() {
+        let valid = "a".repeat(DEVICE_ID_MIN_LENGTH);
+        assert!(DeviceId::parse(&valid).is_ok());
+        assert!(DeviceId::parse(&"Z_9".repeat(13)[..DEVICE_ID_MAX_LENGTH]).is_ok());
+
+        let invalid = [
+            String::new(),
+            "a".repeat(DEVICE_ID_MIN_LENGTH - 1),
+            "a".repeat(DEVICE_ID_MAX_LENGTH + 1),
+            valid.replacen('a', "-", 1),
+            valid.replacen('a', " ", 1),
+            valid.replacen('a', "é", 1),
+            "7b3f1c0e-2d4a-4e8b-9f6a-1c2d3e4f5a6b".to_string(),
+        ];
+        for id in invalid {
+            assert!(
+                matches!(DeviceId::parse(&id), Err(ProtocolError::InvalidDeviceId(_))),
+                "{:?} accepted",
+                id
+            );
+            assert!(serde_json::from_value::<DeviceId>(id.into()).is_err());
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! Device Types
+//!
+//! The `deviceType` field of an identity packet says what kind of device a
+//! peer is. [`DeviceType`] is shared by [`Identity`](super::Identity) and by
+//! discovery, which re-exports it.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use cosmic_ext_connect_core::protocol::DeviceType;
+//!
+//! assert_eq!("smartphone".parse::<DeviceType>().unwrap(), DeviceType::Phone);
+//! assert_eq!(DeviceType::Phone.icon_name(), "phone");
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+/// Device types supported by COSMIC Connect
+///
+/// String values match KDE Connect's `deviceType` identity field. Unrecognised
+/// values deserialize to [`DeviceType::Unknown`] instead of failing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeviceType {
+    Desktop,
+    Laptop,
+    Phone,
+    Tablet,
+    Tv,
+    /// Fallback for device types this library does not recognise
+    #[serde(other)]
+    Unknown,
+}
+
+impl DeviceType {
+    /// Convert device type to string
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeviceType::Desktop => "desktop",
+            DeviceType::Laptop => "laptop",
+            DeviceType::Phone => "phone",
+            DeviceType::Tablet => "tablet",
+            DeviceType::Tv => "tv",
+            DeviceType::Unknown => "unknown",
+        }
+    }
+
+    /// Freedesktop icon name for this device type
+    ///
+    /// Unknown devices map to the generic `computer` icon.
+    pub fn icon_name(&self) -> &'static str {
+        match self {
+            DeviceType::Desktop | DeviceType::Unknown => "computer",
+            DeviceType::Laptop => "computer-laptop",
+            DeviceType::Phone => "phone",
+            DeviceType::Tablet => "tablet",
+            DeviceType::Tv => "tv",
+        }
+    }
+
+    /// Whether this is a handheld, battery-powered device (phone or tablet)
+    pub fn is_mobile(&self) -> bool {
+        matches!(self, DeviceType::Phone | DeviceType::Tablet)
+    }
+}
+
+impl std::fmt::Display for DeviceType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for DeviceType {
+    type Err = std::convert::Infallible;
+
+    /// Parse a KDE Connect device type string
+    ///
+    /// Matching is case-insensitive and never fails: unrecognised values
+    /// become [`DeviceType::Unknown`].
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Ok(match s.to_ascii_lowercase().as_str() {
+            "desktop" => DeviceType::Desktop,
+            "laptop" => DeviceType::Laptop,
+            "phone" | "smartphone" => DeviceType::Phone,
+            "tablet" => DeviceType::Tablet,
+            "tv" => DeviceType::Tv,
+            _ => DeviceType::Unknown,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_device_type_serialization() {
+        assert_eq!(DeviceType::Desktop.as_str(), "desktop");
+        assert_eq!(DeviceType::Laptop.as_str(), "laptop");
+        assert_eq!(DeviceType::Phone.as_str(), "phone");
+        assert_eq!(DeviceType::Tablet.as_str(), "tablet");
+        assert_eq!(DeviceType::Tv.as_str(), "tv");
+    }
+
+    #[test]
+    fn test_device_type_string_roundtrip() {
+        for device_type in [
+            DeviceType::Desktop,
+            DeviceType::Laptop,
+            DeviceType::Phone,
+            DeviceType::Tablet,
+            DeviceType::Tv,
+        ] {
+            let s = device_type.to_string();
+            assert_eq!(s.parse::<DeviceType>().unwrap(), device_type);
+
+            let json = serde_json::to_string(&device_type).unwrap();
+            assert_eq!(json, format!("\"{}\"", s));
+            assert_eq!(serde_json::from_str::<DeviceType>(&json).unwrap(), device_type);
+        }
+    }
+
+    #[test]
+    fn test_device_type_unknown_fallback() {
+        assert_eq!("fridge".parse::<DeviceType>().unwrap(), DeviceType::Unknown);
+        assert_eq!(
+            serde_json::from_str::<DeviceType>("\"fridge\"").unwrap(),
+            DeviceType::Unknown
+        );
+        assert_eq!(DeviceType::Unknown.icon_name(), "computer");
+        assert!(!DeviceType::Unknown.is_mobile());
+    }
+
+    #[test]
+    fn test_device_type_classification() {
+        assert_eq!(DeviceType::Phone.icon_name(), "phone");
+        assert_eq!(DeviceType::Desktop.icon_name(), "computer");
+        assert_eq!(DeviceType::Laptop.icon_name(), "computer-laptop");
+        assert_eq!(DeviceType::Tablet.icon_name(), "tablet");
+        assert_eq!(DeviceType::Tv.icon_name(), "tv");
+
+        assert!(DeviceType::Phone.is_mobile());
+        assert!(DeviceType::Tablet.is_mobile());
+        assert!(!DeviceType::Desktop.is_mobile());
+        assert!(!DeviceType::Laptop.is_mobile());
+        assert!(!DeviceType::Tv.is_mobile());
+    }
+}
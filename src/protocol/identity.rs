@@ -0,0 +1,1173 @@
+//! Identity Capabilities and Version Negotiation
+//!
+//! Identity packets advertise the protocol version and the packet types a
+//! device can send and receive. Plugins may also advertise which versions
+//! they support per capability, so that a newer plugin does not send packets
+//! an older peer cannot parse.
+//!
+//! Peers whose `protocolVersion` is below [`min_supported_version()`] are
+//! rejected by [`Identity::try_negotiate`], and by discovery and the TLS
+//! identity exchange before that (see [`check_peer_version`]). A newer peer
+//! is accepted and both sides use the lower of the two versions. A peer that
+//! omits `protocolVersion`, or sends a malformed one, is held to the oldest
+//! supported version.
+//!
+//! The device ID is kept as a validated [`DeviceId`]:
+//! [`Identity::from_packet`] rejects a malformed one.
+//!
+//! ## Wire Format
+//!
+//! Identity bodies use KDE Connect's field names so that the upstream apps
+//! can parse ours and we can parse theirs. Versions are carried in an
+//! optional `capabilityVersions` object. Capabilities without an entry are
+//! treated as version 1 only, which keeps peers that never send this field
+//! compatible.
+//!
+//! ```json
+//! {
+//!     "deviceId": "a1b2c3d4_e5f6_a7b8_c9d0_e1f2a3b4c5d6",
+//!     "deviceName": "Pixel 7",
+//!     "deviceType": "phone",
+//!     "protocolVersion": 8,
+//!     "tcpPort": 1716,
+//!     "incomingCapabilities": ["cconnect.camera.frame"],
+//!     "outgoingCapabilities": ["cconnect.camera.frame"],
+//!     "capabilityVersions": {
+//!         "cconnect.camera.frame": { "min": 1, "max": 2 }
+//!     }
+//! }
+//! ```
+//!
+//! ## UDP and TCP Identities
+//!
+//! Identity is sent twice. The UDP discovery announcement only needs to say
+//! who the device is and where to connect, so
+//! [`Identity::try_to_udp_packet`] omits the capability lists and versions to
+//! keep broadcasts small. The full identity from
+//! [`Identity::try_to_tcp_packet`] is sent over the TCP link after connecting.
+//! Capability negotiation must use the TCP identity; a parsed UDP identity
+//! has empty capability lists.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use cosmic_ext_connect_core::protocol::identity::{Identity, VersionRange};
+//!
+//! let ours = Identity::new(vec!["cconnect.camera.frame".into()], vec![])
+//!     .with_capability_version("cconnect.camera.frame", VersionRange::new(1, 3));
+//! let theirs = Identity::new(vec![], vec!["cconnect.camera.frame".into()])
+//!     .with_capability_version("cconnect.camera.frame", VersionRange::new(1, 2));
+//!
+//! let negotiated = ours.negotiate(&theirs);
+//! assert_eq!(negotiated.version("cconnect.camera.frame"), Some(2));
+//! ```
+//!
+//! ## Features
+//!
+//! UIs gate their controls on [`Feature`]s rather than raw packet types. A
+//! capability belongs to the feature named by its first segment after the
+//! namespace prefix (`cconnect.screenshare.request` is
+//! [`Feature::ScreenShare`]); capabilities this library does not know map to
+//! [`Feature::Unknown`] so they are still visible.
+//!
+//! ```rust
+//! use cosmic_ext_connect_core::protocol::identity::{Feature, Identity};
+//!
+//! let ours = Identity::new(vec![], vec!["cconnect.screenshare.request".into()]);
+//! let theirs = Identity::new(vec!["cconnect.screenshare.request".into()], vec![]);
+//!
+//! let negotiated = ours.negotiate(&theirs);
+//! assert!(negotiated.supports("screenshare"));
+//! assert_eq!(negotiated.supported_features(), vec![Feature::ScreenShare]);
+//! ```
+
+use crate::error::{ProtocolError, Result};
+use crate::protocol::{
+    min_supported_version, DeviceId, DeviceType, Packet, PacketType, CCONNECT_PREFIX,
+    KDECONNECT_PREFIX, PROTOCOL_VERSION,
+};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeSet, HashMap};
+use tracing::debug;
+
+/// Identity body field carrying per-capability version ranges
+pub const CAPABILITY_VERSIONS_FIELD: &str = "capabilityVersions";
+
+/// KDE Connect's default TCP port, assumed when `tcpPort` is omitted
+pub const DEFAULT_TCP_PORT: u16 = 1716;
+
+/// Inclusive range of supported protocol versions for a capability
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct VersionRange {
+    /// Lowest supported version
+    pub min: u32,
+
+    /// Highest supported version
+    pub max: u32,
+}
+
+impl VersionRange {
+    /// Create a version range; the bounds are swapped if given in reverse
+    pub fn new(min: u32, max: u32) -> Self {
+        Self {
+            min: min.min(max),
+            max: min.max(max),
+        }
+    }
+
+    /// Create a range containing a single version
+    pub fn single(version: u32) -> Self {
+        Self::new(version, version)
+    }
+
+    /// Check whether a version is in range
+    pub fn contains(&self, version: u32) -> bool {
+        (self.min..=self.max).contains(&version)
+    }
+
+    /// Highest version supported by both ranges
+    pub fn highest_common(&self, other: &VersionRange) -> Option<u32> {
+        let max = self.max.min(other.max);
+        (max >= self.min.max(other.min)).then_some(max)
+    }
+}
+
+impl Default for VersionRange {
+    /// Version 1 only, assumed for capabilities without an advertised range
+    fn default() -> Self {
+        Self::single(1)
+    }
+}
+
+/// Capabilities advertised in an identity packet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Identity {
+    /// Unique device identifier, if known
+    ///
+    /// `None` until set with [`with_device`](Self::with_device), or when a
+    /// parsed identity did not carry one.
+    pub device_id: Option<DeviceId>,
+
+    /// Human-readable device name
+    pub device_name: String,
+
+    /// Type of device
+    pub device_type: DeviceType,
+
+    /// Protocol version spoken by the device
+    pub protocol_version: u32,
+
+    /// TCP port for connections (omitted by some UDP-only builds)
+    pub tcp_port: Option<u16>,
+
+    /// Packet types the device can receive
+    pub incoming_capabilities: Vec<String>,
+
+    /// Packet types the device can send
+    pub outgoing_capabilities: Vec<String>,
+
+    /// Advertised version ranges, by capability
+    pub capability_versions: HashMap<String, VersionRange>,
+}
+
+impl Identity {
+    /// Create an identity with capabilities and no explicit versions
+    ///
+    /// The protocol version is [`PROTOCOL_VERSION`]. Device fields are empty
+    /// until set with [`with_device`](Self::with_device).
+    pub fn new(incoming_capabilities: Vec<String>, outgoing_capabilities: Vec<String>) -> Self {
+        Self {
+            device_id: None,
+            device_name: String::new(),
+            device_type: DeviceType::Unknown,
+            protocol_version: PROTOCOL_VERSION as u32,
+            tcp_port: None,
+            incoming_capabilities,
+            outgoing_capabilities,
+            capability_versions: HashMap::new(),
+        }
+    }
+
+    /// Set the device ID, name and type
+    pub fn with_device(
+        mut self,
+        device_id: DeviceId,
+        device_name: impl Into<String>,
+        device_type: DeviceType,
+    ) -> Self {
+        self.device_id = Some(device_id);
+        self.device_name = device_name.into();
+        self.device_type = device_type;
+        self
+    }
+
+    /// Set the TCP port
+    pub fn with_tcp_port(mut self, tcp_port: u16) -> Self {
+        self.tcp_port = Some(tcp_port);
+        self
+    }
+
+    /// Get the TCP port, or [`DEFAULT_TCP_PORT`] if not advertised
+    pub fn tcp_port_or_default(&self) -> u16 {
+        self.tcp_port.unwrap_or(DEFAULT_TCP_PORT)
+    }
+
+    /// Advertise a version range for a capability
+    pub fn with_capability_version(mut self, capability: impl Into<String>, range: VersionRange) -> Self {
+        self.capability_versions.insert(capability.into(), range);
+        self
+    }
+
+    /// Advertise version ranges for several capabilities
+    pub fn with_capability_versions(mut self, versions: HashMap<String, VersionRange>) -> Self {
+        self.capability_versions.extend(versions);
+        self
+    }
+
+    /// Get the version range for a capability (version 1 if not advertised)
+    pub fn version_range(&self, capability: &str) -> VersionRange {
+        self.capability_versions
+            .get(capability)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Parse an identity packet
+    ///
+    /// Accepts `cconnect.identity` and `kdeconnect.identity` packets, and
+    /// capability lists as JSON arrays or stringified arrays, matching
+    /// [`DeviceInfo::from_identity_packet`](crate::discovery::DeviceInfo::from_identity_packet).
+    /// Missing or malformed optional fields get defaults: no device ID,
+    /// empty device fields and capability lists, no `tcpPort`, no capability
+    /// versions, and [`min_supported_version()`] for `protocolVersion`, so a
+    /// peer never gets newer behavior than it asked for.
+    ///
+    /// # Errors
+    ///
+    /// - `ProtocolError::InvalidPacket` - The packet is not an identity packet
+    /// - `ProtocolError::InvalidDeviceId` - The device ID is present but malformed
+    pub fn from_packet(packet: &Packet) -> Result<Self> {
+        if !packet.is_type("cconnect.identity") {
+            return Err(ProtocolError::InvalidPacket(
+                "Not an identity packet".to_string(),
+            ));
+        }
+
+        let body: IdentityBody = serde_json::from_value(packet.body.clone()).map_err(|e| {
+            ProtocolError::InvalidPacket(format!("Invalid identity packet: {}", e))
+        })?;
+        body.try_into()
+    }
+
+    /// Create an identity packet in KDE Connect's schema
+    ///
+    /// Same as [`try_to_tcp_packet`](Self::try_to_tcp_packet).
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Json` if the body cannot be serialized.
+    pub fn try_to_packet(&self) -> Result<Packet> {
+        self.try_to_tcp_packet()
+    }
+
+    /// Create the full identity packet sent over TCP after connecting
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Json` if the body cannot be serialized.
+    pub fn try_to_tcp_packet(&self) -> Result<Packet> {
+        Ok(Packet::new("cconnect.identity", self.body()?))
+    }
+
+    /// Create the minimal identity packet for UDP discovery
+    ///
+    /// Carries the device fields, protocol version and TCP port, but no
+    /// capability lists or capability versions.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Json` if the body cannot be serialized.
+    pub fn try_to_udp_packet(&self) -> Result<Packet> {
+        let mut body = self.body()?;
+        if let Some(fields) = body.as_object_mut() {
+            fields.remove("incomingCapabilities");
+            fields.remove("outgoingCapabilities");
+            fields.remove(CAPABILITY_VERSIONS_FIELD);
+        }
+        Ok(Packet::new("cconnect.identity", body))
+    }
+
+    /// Write the protocol version, capabilities and versions into an identity packet body
+    ///
+    /// Device fields already in the packet are left unchanged;
+    /// `capabilityVersions` is omitted when no versions are advertised.
+    pub fn write_to(&self, packet: &mut Packet) {
+        packet.body["protocolVersion"] = json!(self.protocol_version);
+        packet.body["incomingCapabilities"] = json!(self.incoming_capabilities);
+        packet.body["outgoingCapabilities"] = json!(self.outgoing_capabilities);
+        if !self.capability_versions.is_empty() {
+            packet.body[CAPABILITY_VERSIONS_FIELD] = json!(self.capability_versions);
+        }
+    }
+
+    fn body(&self) -> Result<Value> {
+        let body = serde_json::to_value(IdentityBody {
+            device_id: self.device_id.clone().map(String::from).unwrap_or_default(),
+            device_name: self.device_name.clone(),
+            device_type: self.device_type,
+            protocol_version: self.protocol_version,
+            tcp_port: self.tcp_port,
+            incoming_capabilities: self.incoming_capabilities.clone(),
+            outgoing_capabilities: self.outgoing_capabilities.clone(),
+            capability_versions: self.capability_versions.clone(),
+        })?;
+        Ok(body)
+    }
+
+    /// Check that a peer's protocol version is supported
+    ///
+    /// Returns the protocol version to use with the peer, the lower of the
+    /// two versions.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::UnsupportedVersion` if the peer's version is
+    /// below [`min_supported_version()`]
+    pub fn check_protocol_version(&self, peer: &Identity) -> Result<u32> {
+        check_peer_version(self.protocol_version, peer.protocol_version)
+    }
+
+    /// Check the peer's protocol version, then negotiate capabilities
+    ///
+    /// An older but supported peer is accepted; features it cannot handle
+    /// are disabled through capability negotiation rather than failing the
+    /// connection, since such peers advertise no capability versions and
+    /// are therefore held to version 1 of every capability.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::UnsupportedVersion` if the peer's protocol
+    /// version is below [`min_supported_version()`]
+    pub fn try_negotiate(&self, peer: &Identity) -> Result<NegotiatedCapabilities> {
+        let protocol_version = self.check_protocol_version(peer)?;
+        Ok(NegotiatedCapabilities {
+            protocol_version,
+            ..self.negotiate(peer)
+        })
+    }
+
+    /// Negotiate capabilities and versions with a peer
+    ///
+    /// Does not check protocol versions; see [`try_negotiate`](Self::try_negotiate).
+    ///
+    /// A capability is active when one side sends it and the other receives
+    /// it. Its version is the highest version in both advertised ranges;
+    /// capabilities whose ranges do not overlap are reported as incompatible.
+    pub fn negotiate(&self, peer: &Identity) -> NegotiatedCapabilities {
+        let mut negotiated = NegotiatedCapabilities {
+            protocol_version: self.protocol_version.min(peer.protocol_version),
+            ..Default::default()
+        };
+
+        let shared = self
+            .outgoing_capabilities
+            .iter()
+            .filter(|cap| peer.incoming_capabilities.contains(cap))
+            .chain(
+                self.incoming_capabilities
+                    .iter()
+                    .filter(|cap| peer.outgoing_capabilities.contains(cap)),
+            );
+
+        for capability in shared {
+            if negotiated.versions.contains_key(capability)
+                || negotiated.incompatible.contains(capability)
+            {
+                continue;
+            }
+
+            match self
+                .version_range(capability)
+                .highest_common(&peer.version_range(capability))
+            {
+                Some(version) => {
+                    negotiated.versions.insert(capability.clone(), version);
+                }
+                None => {
+                    debug!("No common version for capability '{}'", capability);
+                    negotiated.incompatible.push(capability.clone());
+                }
+            }
+        }
+
+        negotiated.incompatible.sort();
+        negotiated
+    }
+}
+
+/// Identity packet body with KDE Connect's field names
+#[derive(Serialize, Deserialize)]
+struct IdentityBody {
+    #[serde(rename = "deviceId", alias = "device_id", default)]
+    device_id: String,
+
+    #[serde(rename = "deviceName", alias = "device_name", default)]
+    device_name: String,
+
+    #[serde(
+        rename = "deviceType",
+        alias = "device_type",
+        default = "unknown_device_type",
+        deserialize_with = "device_type"
+    )]
+    device_type: DeviceType,
+
+    #[serde(
+        rename = "protocolVersion",
+        alias = "protocol_version",
+        default = "default_protocol_version",
+        deserialize_with = "protocol_version"
+    )]
+    protocol_version: u32,
+
+    #[serde(
+        rename = "tcpPort",
+        alias = "tcp_port",
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "lenient"
+    )]
+    tcp_port: Option<u16>,
+
+    #[serde(
+        rename = "incomingCapabilities",
+        alias = "incoming_capabilities",
+        default,
+        deserialize_with = "capability_list"
+    )]
+    incoming_capabilities: Vec<String>,
+
+    #[serde(
+        rename = "outgoingCapabilities",
+        alias = "outgoing_capabilities",
+        default,
+        deserialize_with = "capability_list"
+    )]
+    outgoing_capabilities: Vec<String>,
+
+    #[serde(
+        rename = "capabilityVersions",
+        alias = "capability_versions",
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        deserialize_with = "lenient"
+    )]
+    capability_versions: HashMap<String, VersionRange>,
+}
+
+impl TryFrom<IdentityBody> for Identity {
+    type Error = ProtocolError;
+
+    fn try_from(body: IdentityBody) -> Result<Self> {
+        let device_id = if body.device_id.is_empty() {
+            None
+        } else {
+            Some(DeviceId::try_from(body.device_id)?)
+        };
+        Ok(Self {
+            device_id,
+            device_name: body.device_name,
+            device_type: body.device_type,
+            protocol_version: body.protocol_version,
+            tcp_port: body.tcp_port,
+            incoming_capabilities: body.incoming_capabilities,
+            outgoing_capabilities: body.outgoing_capabilities,
+            capability_versions: body.capability_versions,
+        })
+    }
+}
+
+fn unknown_device_type() -> DeviceType {
+    DeviceType::Unknown
+}
+
+fn default_protocol_version() -> u32 {
+    min_supported_version() as u32
+}
+
+/// Check a peer's protocol version and pick the version to use with it
+///
+/// `ours` is our own protocol version. Returns the lower of the two
+/// versions, as [`Identity::negotiate`] does; a newer peer is expected to
+/// fall back to ours. Used wherever an identity is accepted, so unsupported
+/// peers are turned away before any other packet is exchanged.
+///
+/// # Errors
+///
+/// Returns `ProtocolError::UnsupportedVersion` if `theirs` is below
+/// [`min_supported_version()`]
+pub fn check_peer_version(ours: u32, theirs: u32) -> Result<u32> {
+    if theirs < min_supported_version() as u32 {
+        return Err(ProtocolError::UnsupportedVersion { ours, theirs });
+    }
+    Ok(ours.min(theirs))
+}
+
+/// Deserialize a value, falling back to the default if it is malformed
+fn lenient<'de, D, T>(deserializer: D) -> std::result::Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: serde::de::DeserializeOwned + Default,
+{
+    let value = Value::deserialize(deserializer)?;
+    Ok(serde_json::from_value(value).unwrap_or_default())
+}
+
+/// Deserialize a protocol version, treating a malformed one as unstated
+fn protocol_version<'de, D>(deserializer: D) -> std::result::Result<u32, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = Value::deserialize(deserializer)?;
+    Ok(serde_json::from_value(value).unwrap_or_else(|_| default_protocol_version()))
+}
+
+/// Deserialize a device type string, accepting aliases such as `smartphone`
+fn device_type<'de, D>(deserializer: D) -> std::result::Result<DeviceType, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = Value::deserialize(deserializer)?;
+    Ok(value
+        .as_str()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(DeviceType::Unknown))
+}
+
+/// Deserialize a capability list sent as a JSON array or a stringified array
+fn capability_list<'de, D>(deserializer: D) -> std::result::Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = Value::deserialize(deserializer)?;
+    let list = match value {
+        Value::String(s) => serde_json::from_str(&s).ok(),
+        value => serde_json::from_value(value).ok(),
+    };
+    Ok(list.unwrap_or_default())
+}
+
+/// Capabilities forced into or out of the identity sent to one peer
+///
+/// Used to work around a peer that misbehaves when it sees a capability, or
+/// to advertise one for testing, without changing which plugins are loaded.
+/// Removal wins over addition and hides every spelling of a known packet
+/// type, so removing `cconnect.battery` also drops `kdeconnect.battery`.
+///
+/// ```rust
+/// use cosmic_ext_connect_core::protocol::identity::{CapabilityOverride, Identity};
+///
+/// let mut identity = Identity::new(vec!["cconnect.ping".into()], vec![]);
+/// let shim = CapabilityOverride::new().without("cconnect.ping");
+/// assert!(shim.apply(&mut identity));
+/// assert!(identity.incoming_capabilities.is_empty());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CapabilityOverride {
+    /// Capabilities added to the incoming list
+    pub add_incoming: BTreeSet<String>,
+
+    /// Capabilities added to the outgoing list
+    pub add_outgoing: BTreeSet<String>,
+
+    /// Capabilities removed from both lists
+    pub remove: BTreeSet<String>,
+}
+
+impl CapabilityOverride {
+    /// Create an override that changes nothing
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Advertise a capability as incoming
+    pub fn with_incoming(mut self, capability: impl Into<String>) -> Self {
+        self.add_incoming.insert(capability.into());
+        self
+    }
+
+    /// Advertise a capability as outgoing
+    pub fn with_outgoing(mut self, capability: impl Into<String>) -> Self {
+        self.add_outgoing.insert(capability.into());
+        self
+    }
+
+    /// Hide a capability from both lists
+    pub fn without(mut self, capability: impl Into<String>) -> Self {
+        self.remove.insert(capability.into());
+        self
+    }
+
+    /// Check whether the override changes nothing
+    pub fn is_empty(&self) -> bool {
+        self.add_incoming.is_empty() && self.add_outgoing.is_empty() && self.remove.is_empty()
+    }
+
+    /// Apply the override to an identity
+    ///
+    /// Returns `true` if the identity changed.
+    pub fn apply(&self, identity: &mut Identity) -> bool {
+        let before = identity.clone();
+
+        for (list, added) in [
+            (&mut identity.incoming_capabilities, &self.add_incoming),
+            (&mut identity.outgoing_capabilities, &self.add_outgoing),
+        ] {
+            for capability in added {
+                if !list.contains(capability) {
+                    list.push(capability.clone());
+                }
+            }
+        }
+
+        let hidden: BTreeSet<String> = self
+            .remove
+            .iter()
+            .flat_map(|capability| match PacketType::from_alias(capability) {
+                Some(known) => known.canonical_aliases(),
+                None => vec![capability.clone()],
+            })
+            .collect();
+        identity.incoming_capabilities.retain(|c| !hidden.contains(c));
+        identity.outgoing_capabilities.retain(|c| !hidden.contains(c));
+        identity.capability_versions.retain(|c, _| !hidden.contains(c));
+
+        *identity != before
+    }
+}
+
+/// Result of capability negotiation with a peer
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NegotiatedCapabilities {
+    /// Protocol version used with the peer
+    protocol_version: u32,
+
+    /// Chosen version for each active capability
+    versions: HashMap<String, u32>,
+
+    /// Shared capabilities with no common version
+    incompatible: Vec<String>,
+}
+
+impl NegotiatedCapabilities {
+    /// Get the protocol version used with the peer
+    pub fn protocol_version(&self) -> u32 {
+        self.protocol_version
+    }
+
+    /// Get the negotiated version of a capability, if active
+    pub fn version(&self, capability: &str) -> Option<u32> {
+        self.versions.get(capability).copied()
+    }
+
+    /// Check whether a capability is active
+    pub fn is_active(&self, capability: &str) -> bool {
+        self.versions.contains_key(capability)
+    }
+
+    /// Get all active capabilities and their versions
+    pub fn versions(&self) -> &HashMap<String, u32> {
+        &self.versions
+    }
+
+    /// Get capabilities both sides share but cannot agree on a version for
+    pub fn incompatible(&self) -> &[String] {
+        &self.incompatible
+    }
+
+    /// Check whether any active capability belongs to a feature
+    ///
+    /// `feature` is the name from [`Feature::name`], e.g. `"screenshare"`.
+    /// Unknown features match by their first segment too, so
+    /// `supports("future")` is true if `cconnect.future.request` is active.
+    pub fn supports(&self, feature: &str) -> bool {
+        self.versions
+            .keys()
+            .any(|capability| feature_segment(capability) == feature)
+    }
+
+    /// Get the features backed by at least one active capability
+    ///
+    /// Sorted and without duplicates. Each unknown capability is reported
+    /// as its own [`Feature::Unknown`].
+    pub fn supported_features(&self) -> Vec<Feature> {
+        self.versions
+            .keys()
+            .map(|capability| Feature::from_capability(capability))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+}
+
+/// User-facing feature a device can support
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Feature {
+    /// Ping
+    Ping,
+    /// Battery status
+    Battery,
+    /// Clipboard sync
+    Clipboard,
+    /// Notification sync
+    Notifications,
+    /// File and text sharing
+    Share,
+    /// Media player control
+    MediaControl,
+    /// Run commands
+    RunCommand,
+    /// Find my phone
+    FindMyPhone,
+    /// Lock the device
+    Lock,
+    /// Presentation remote
+    Presenter,
+    /// Mouse and keyboard input
+    RemoteInput,
+    /// Call notifications
+    Telephony,
+    /// SMS messaging
+    Sms,
+    /// Contact sync
+    Contacts,
+    /// System volume control
+    SystemVolume,
+    /// Cellular connectivity reports
+    Connectivity,
+    /// Open URLs and files
+    Open,
+    /// Remote filesystem browsing
+    Sftp,
+    /// Drawing tablet input
+    Digitizer,
+    /// Folder sync
+    FileSync,
+    /// Audio streaming
+    AudioStream,
+    /// Phone camera streaming
+    Camera,
+    /// Phone as a webcam
+    Webcam,
+    /// Screen sharing
+    ScreenShare,
+    /// Device as an extra monitor
+    VirtualMonitor,
+    /// Capability this library does not know, kept as its raw string
+    Unknown(String),
+}
+
+impl Feature {
+    /// Map a capability to the feature it belongs to
+    ///
+    /// Accepts `cconnect.` and `kdeconnect.` prefixes.
+    pub fn from_capability(capability: &str) -> Self {
+        Self::from_name(feature_segment(capability))
+            .unwrap_or_else(|| Feature::Unknown(capability.to_string()))
+    }
+
+    /// Look up a known feature by name
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "ping" => Feature::Ping,
+            "battery" => Feature::Battery,
+            "clipboard" => Feature::Clipboard,
+            "notification" => Feature::Notifications,
+            "share" => Feature::Share,
+            "mpris" => Feature::MediaControl,
+            "runcommand" => Feature::RunCommand,
+            "findmyphone" => Feature::FindMyPhone,
+            "lock" => Feature::Lock,
+            "presenter" => Feature::Presenter,
+            "mousepad" => Feature::RemoteInput,
+            "telephony" => Feature::Telephony,
+            "sms" => Feature::Sms,
+            "contacts" => Feature::Contacts,
+            "systemvolume" => Feature::SystemVolume,
+            "connectivity_report" => Feature::Connectivity,
+            "open" => Feature::Open,
+            "sftp" => Feature::Sftp,
+            "digitizer" => Feature::Digitizer,
+            "filesync" => Feature::FileSync,
+            "audiostream" => Feature::AudioStream,
+            "camera" => Feature::Camera,
+            "webcam" => Feature::Webcam,
+            "screenshare" => Feature::ScreenShare,
+            "virtualmonitor" => Feature::VirtualMonitor,
+            _ => return None,
+        })
+    }
+
+    /// Get the feature's name, the capability segment it is keyed by
+    ///
+    /// Unknown features return their raw capability string.
+    pub fn name(&self) -> &str {
+        match self {
+            Feature::Ping => "ping",
+            Feature::Battery => "battery",
+            Feature::Clipboard => "clipboard",
+            Feature::Notifications => "notification",
+            Feature::Share => "share",
+            Feature::MediaControl => "mpris",
+            Feature::RunCommand => "runcommand",
+            Feature::FindMyPhone => "findmyphone",
+            Feature::Lock => "lock",
+            Feature::Presenter => "presenter",
+            Feature::RemoteInput => "mousepad",
+            Feature::Telephony => "telephony",
+            Feature::Sms => "sms",
+            Feature::Contacts => "contacts",
+            Feature::SystemVolume => "systemvolume",
+            Feature::Connectivity => "connectivity_report",
+            Feature::Open => "open",
+            Feature::Sftp => "sftp",
+            Feature::Digitizer => "digitizer",
+            Feature::FileSync => "filesync",
+            Feature::AudioStream => "audiostream",
+            Feature::Camera => "camera",
+            Feature::Webcam => "webcam",
+            Feature::ScreenShare => "screenshare",
+            Feature::VirtualMonitor => "virtualmonitor",
+            Feature::Unknown(capability) => capability,
+        }
+    }
+}
+
+/// First segment of a capability after its namespace prefix
+fn feature_segment(capability: &str) -> &str {
+    let rest = capability
+        .strip_prefix(CCONNECT_PREFIX)
+        .or_else(|| capability.strip_prefix(KDECONNECT_PREFIX))
+        .unwrap_or(capability);
+    rest.split('.').next().unwrap_or(rest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME: &str = "cconnect.camera.frame";
+
+    fn camera_identity(range: Option<VersionRange>) -> Identity {
+        let identity = Identity::new(vec![FRAME.to_string()], vec![FRAME.to_string()]);
+        match range {
+            Some(range) => identity.with_capability_version(FRAME, range),
+            None => identity,
+        }
+    }
+
+    #[test]
+    fn test_negotiate_highest_common_version() {
+        let ours = camera_identity(Some(VersionRange::new(1, 3)));
+        let theirs = camera_identity(Some(VersionRange::new(2, 4)));
+
+        assert_eq!(ours.negotiate(&theirs).version(FRAME), Some(3));
+        assert_eq!(theirs.negotiate(&ours).version(FRAME), Some(3));
+
+        // A peer without versions is treated as v1 only
+        let legacy = camera_identity(None);
+        assert_eq!(ours.negotiate(&legacy).version(FRAME), Some(1));
+    }
+
+    #[test]
+    fn test_negotiate_incompatible_and_inactive() {
+        let ours = camera_identity(Some(VersionRange::new(2, 3)));
+        let legacy = camera_identity(None);
+
+        let negotiated = ours.negotiate(&legacy);
+        assert!(!negotiated.is_active(FRAME));
+        assert_eq!(negotiated.incompatible(), &[FRAME.to_string()]);
+
+        // Capabilities only one side knows are not active
+        let other = Identity::new(vec!["cconnect.ping".to_string()], vec![]);
+        assert!(ours.negotiate(&other).versions().is_empty());
+    }
+
+    #[test]
+    fn test_protocol_version_range() {
+        let ours = camera_identity(Some(VersionRange::new(1, 2)));
+
+        let mut legacy = camera_identity(None);
+        legacy.protocol_version = min_supported_version() as u32;
+        let negotiated = ours.try_negotiate(&legacy).unwrap();
+        assert_eq!(negotiated.protocol_version(), min_supported_version() as u32);
+        assert_eq!(negotiated.version(FRAME), Some(1));
+
+        // A peer that does not state its version is the oldest supported one
+        let mut packet = legacy.try_to_packet().unwrap();
+        packet.body.as_object_mut().unwrap().remove("protocolVersion");
+        let unstated = Identity::from_packet(&packet).unwrap();
+        assert_eq!(unstated.protocol_version, min_supported_version() as u32);
+        assert_eq!(ours.try_negotiate(&unstated).unwrap().protocol_version(), 7);
+
+        // So is one whose version is malformed
+        for malformed in [json!("8"), json!(null), json!(-1)] {
+            packet.body["protocolVersion"] = malformed;
+            let identity = Identity::from_packet(&packet).unwrap();
+            assert_eq!(identity.protocol_version, min_supported_version() as u32);
+            assert!(ours.try_negotiate(&identity).is_ok());
+        }
+
+        // A newer peer is held to our version
+        let mut newer = camera_identity(None);
+        newer.protocol_version = PROTOCOL_VERSION as u32 + 1;
+        assert_eq!(
+            ours.try_negotiate(&newer).unwrap().protocol_version(),
+            PROTOCOL_VERSION as u32
+        );
+
+        let mut older = camera_identity(None);
+        older.protocol_version = min_supported_version() as u32 - 1;
+        match ours.try_negotiate(&older) {
+            Err(ProtocolError::UnsupportedVersion { ours: o, theirs: t }) => {
+                assert_eq!(o, PROTOCOL_VERSION as u32);
+                assert_eq!(t, older.protocol_version);
+            }
+            other => panic!("expected UnsupportedVersion, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_supported_features() {
+        let capabilities: Vec<String> = [
+            "cconnect.screenshare.request",
+            "cconnect.battery",
+            "cconnect.battery.request",
+            "kdeconnect.mousepad.request",
+            "cconnect.future.request",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        let ours = Identity::new(capabilities.clone(), vec![]);
+        let theirs = Identity::new(vec![], capabilities);
+        let negotiated = ours.negotiate(&theirs);
+
+        assert!(negotiated.supports("screenshare"));
+        assert!(negotiated.supports("battery"));
+        assert!(negotiated.supports("mousepad"));
+        assert!(negotiated.supports("future"));
+        assert!(!negotiated.supports("camera"));
+        assert_eq!(
+            negotiated.supported_features(),
+            vec![
+                Feature::Battery,
+                Feature::RemoteInput,
+                Feature::ScreenShare,
+                Feature::Unknown("cconnect.future.request".to_string()),
+            ]
+        );
+
+        // Incompatible capabilities are not supported
+        let ours = camera_identity(Some(VersionRange::new(2, 3)));
+        let negotiated = ours.negotiate(&camera_identity(None));
+        assert!(!negotiated.supports("camera"));
+        assert!(negotiated.supported_features().is_empty());
+    }
+
+    #[test]
+    fn test_feature_names_round_trip() {
+        for name in ["ping", "share", "mpris", "connectivity_report", "virtualmonitor"] {
+            assert_eq!(Feature::from_name(name).unwrap().name(), name);
+        }
+        assert_eq!(Feature::from_name("future"), None);
+        assert_eq!(Feature::from_capability("kdeconnect.sms.request"), Feature::Sms);
+        assert_eq!(
+            Feature::from_capability("org.example.thing").name(),
+            "org.example.thing"
+        );
+    }
+
+    /// Device ID of the fixture identity
+    const PEER_ID: &str = "a1b2c3d4_e5f6_a7b8_c9d0_e1f2a3b4c5d6";
+
+    fn peer_id() -> DeviceId {
+        DeviceId::parse(PEER_ID).unwrap()
+    }
+
+    /// Identity packet in the shape sent by the KDE Connect Android app
+    const KDE_IDENTITY_FIXTURE: &str = r#"{
+        "id": 1700000000000,
+        "type": "kdeconnect.identity",
+        "body": {
+            "deviceId": "a1b2c3d4_e5f6_a7b8_c9d0_e1f2a3b4c5d6",
+            "deviceName": "Pixel 7",
+            "deviceType": "phone",
+            "protocolVersion": 7,
+            "incomingCapabilities": [
+                "kdeconnect.battery.request",
+                "kdeconnect.ping",
+                "kdeconnect.share.request"
+            ],
+            "outgoingCapabilities": [
+                "kdeconnect.battery",
+                "kdeconnect.ping",
+                "kdeconnect.share.request"
+            ],
+            "tcpPort": 1716
+        }
+    }"#;
+
+    #[test]
+    fn test_parse_kde_identity_fixture() {
+        let packet = Packet::from_bytes(KDE_IDENTITY_FIXTURE.as_bytes()).unwrap();
+        let identity = Identity::from_packet(&packet).unwrap();
+
+        assert_eq!(identity.device_id, Some(peer_id()));
+        assert_eq!(identity.device_name, "Pixel 7");
+        assert_eq!(identity.device_type, DeviceType::Phone);
+        assert_eq!(identity.protocol_version, 7);
+        assert_eq!(identity.tcp_port, Some(1716));
+        assert_eq!(identity.incoming_capabilities.len(), 3);
+        assert!(identity
+            .outgoing_capabilities
+            .contains(&"kdeconnect.battery".to_string()));
+        assert!(identity.capability_versions.is_empty());
+
+        // UDP-only builds omit tcpPort
+        let mut packet = packet;
+        packet.body.as_object_mut().unwrap().remove("tcpPort");
+        let identity = Identity::from_packet(&packet).unwrap();
+        assert_eq!(identity.tcp_port, None);
+        assert_eq!(identity.tcp_port_or_default(), DEFAULT_TCP_PORT);
+    }
+
+    #[test]
+    fn test_device_id_validated() {
+        let mut packet = Packet::from_bytes(KDE_IDENTITY_FIXTURE.as_bytes()).unwrap();
+        packet.body["deviceId"] = json!("a1b2c3d4e5f6a7b8");
+        assert!(matches!(
+            Identity::from_packet(&packet),
+            Err(ProtocolError::InvalidDeviceId(_))
+        ));
+
+        // A missing ID is not an error, just unknown
+        packet.body.as_object_mut().unwrap().remove("deviceId");
+        assert_eq!(Identity::from_packet(&packet).unwrap().device_id, None);
+        assert_eq!(Identity::new(vec![], vec![]).device_id, None);
+
+        let device_id = DeviceId::generate();
+        let identity = Identity::new(vec![], vec![]).with_device(
+            device_id.clone(),
+            "Pixel 7",
+            DeviceType::Phone,
+        );
+        assert_eq!(identity.try_to_packet().unwrap().body["deviceId"], device_id.as_str());
+    }
+
+    #[test]
+    fn test_to_packet_matches_kde_schema() {
+        let ours = Identity::new(
+            vec!["cconnect.ping".to_string()],
+            vec!["cconnect.ping".to_string()],
+        )
+            .with_device(peer_id(), "My Desktop", DeviceType::Desktop)
+            .with_tcp_port(1816);
+        let packet = ours.try_to_packet().unwrap();
+
+        let mut fields: Vec<_> = packet.body.as_object().unwrap().keys().cloned().collect();
+        fields.sort();
+        assert_eq!(
+            fields,
+            vec![
+                "deviceId",
+                "deviceName",
+                "deviceType",
+                "incomingCapabilities",
+                "outgoingCapabilities",
+                "protocolVersion",
+                "tcpPort",
+            ]
+        );
+        assert_eq!(packet.body["deviceType"], "desktop");
+        assert_eq!(Identity::from_packet(&packet).unwrap(), ours);
+    }
+
+    #[test]
+    fn test_udp_identity_omits_capabilities() {
+        let ours = camera_identity(Some(VersionRange::new(1, 2)))
+            .with_device(peer_id(), "My Desktop", DeviceType::Desktop)
+            .with_tcp_port(1816);
+
+        let udp = ours.try_to_udp_packet().unwrap();
+        let mut fields: Vec<_> = udp.body.as_object().unwrap().keys().cloned().collect();
+        fields.sort();
+        assert_eq!(
+            fields,
+            vec!["deviceId", "deviceName", "deviceType", "protocolVersion", "tcpPort"]
+        );
+        let parsed = Identity::from_packet(&udp).unwrap();
+        assert_eq!(parsed.device_id, Some(peer_id()));
+        assert_eq!(parsed.tcp_port, Some(1816));
+        assert!(parsed.incoming_capabilities.is_empty());
+
+        let tcp = ours.try_to_tcp_packet().unwrap();
+        assert_eq!(tcp.body["incomingCapabilities"], json!([FRAME]));
+        assert_eq!(tcp.body["outgoingCapabilities"], json!([FRAME]));
+        assert_eq!(tcp.body[CAPABILITY_VERSIONS_FIELD][FRAME]["max"], 2);
+        assert_eq!(Identity::from_packet(&tcp).unwrap(), ours);
+        assert!(udp.to_bytes().unwrap().len() < tcp.to_bytes().unwrap().len());
+    }
+
+    #[test]
+    fn test_parse_snake_case_identity() {
+        let packet = Packet::new(
+            "kdeconnect.identity",
+            json!({
+                "device_id": PEER_ID,
+                "device_name": "Pixel",
+                "device_type": "phone",
+                "protocol_version": 7,
+                "tcp_port": 1716,
+                "incoming_capabilities": ["cconnect.ping"],
+                "outgoing_capabilities": ["cconnect.ping"]
+            }),
+        );
+        let identity = Identity::from_packet(&packet).unwrap();
+        assert_eq!(identity.device_id, Some(peer_id()));
+        assert_eq!(identity.device_name, "Pixel");
+        assert_eq!(identity.device_type, DeviceType::Phone);
+        assert_eq!(identity.tcp_port, Some(1716));
+        assert_eq!(identity.incoming_capabilities, vec!["cconnect.ping".to_string()]);
+
+        // Re-serialized identities are camelCase only
+        let body = identity.try_to_packet().unwrap().body;
+        assert_eq!(body["deviceId"], PEER_ID);
+        assert!(body.get("device_id").is_none());
+    }
+
+    #[test]
+    fn test_identity_packet_round_trip() {
+        let identity = camera_identity(Some(VersionRange::new(1, 2)));
+        let mut packet = Packet::new("cconnect.identity", json!({ "deviceId": PEER_ID }));
+        identity.write_to(&mut packet);
+
+        assert_eq!(packet.body[CAPABILITY_VERSIONS_FIELD][FRAME]["max"], 2);
+        assert_eq!(packet.body["protocolVersion"], PROTOCOL_VERSION);
+        assert_eq!(packet.body["deviceId"], PEER_ID);
+        assert_eq!(
+            Identity::from_packet(&packet).unwrap(),
+            identity.with_device(peer_id(), "", DeviceType::Unknown)
+        );
+
+        // Peers that never send versions parse with an empty map
+        let legacy = Packet::new(
+            "kdeconnect.identity",
+            json!({ "incomingCapabilities": "[\"cconnect.ping\"]" }),
+        );
+        let parsed = Identity::from_packet(&legacy).unwrap();
+        assert_eq!(parsed.incoming_capabilities, vec!["cconnect.ping".to_string()]);
+        assert!(parsed.capability_versions.is_empty());
+    }
+}
@@ -5,6 +5,13 @@
 //! ## Implemented Modules
 //!
 //! - [`packet`] - NetworkPacket serialization/deserialization (Issue #45)
+//! - [`packet_type`] - Registry of known packet types
+//! - [`identity`] - Identity capabilities and per-capability version negotiation
+//! - [`device_id`] - Validated device IDs
+//! - [`device_type`] - Device kinds from the identity `deviceType` field
+//! - [`payload`] - Streaming payload sender and checksum-verifying receiver
+//! - [`codec`] - Incremental newline-delimited packet framing
+//! - [`capture`] - Packet capture and offline replay for debugging
 //!
 //! ## Planned Modules
 //!
@@ -19,27 +26,54 @@
 //! - **Blockers**: Multiple plugins depend on this extraction (telephony, contacts, clipboard, etc.)
 //!
 //! ### identity
-//! - **Status**: Planned creation
+//! - **Status**: Partially created (capability version negotiation)
 //! - **Description**: Device identity packet generation and validation
 //! - **Requirements**: Extract from Device implementation
-//! - **Components**: Identity packet builder, capability negotiation, device type enum
+//! - **Components**: Identity packet builder, device type enum
 //! - **Dependencies**: Requires `device` module extraction first
 //!
 //! ### payload
-//! - **Status**: Planned extraction
+//! - **Status**: Partially extracted (streaming sender)
 //! - **Description**: Large file/data payload transfer handling
-//! - **Location**: Currently in `cosmic-connect-daemon/src/device.rs` and plugins
+//! - **Location**: Payload servers/clients currently in `cosmic-connect-daemon/src/device.rs` and plugins
 //! - **Components**: Payload metadata, streaming transfer, progress tracking
 //! - **Use cases**: File sharing, camera streaming, clipboard large content
 
 // Module exports
 pub mod packet;       // ✅ Extracted from applet (Issue #45)
+pub mod packet_type;  // ✅ Known packet type registry
+pub mod identity;     // ✅ Capability version negotiation
+pub mod device_id;    // ✅ Validated device IDs
+pub mod device_type;  // ✅ Device kinds
+pub mod payload;      // ✅ Streaming payload sender and receiver
+pub mod codec;        // ✅ Incremental packet framing
+pub mod capture;      // ✅ Packet capture and replay
 
 // Re-exports for convenience
-pub use packet::Packet;
+pub use packet::{JsonFormat, Packet, RedactedPacket, REDACTED};
+pub use device_id::{DeviceId, DEVICE_ID_MAX_LENGTH, DEVICE_ID_MIN_LENGTH};
+pub use device_type::DeviceType;
+pub use identity::{
+    check_peer_version, CapabilityOverride, Feature, Identity, NegotiatedCapabilities,
+    VersionRange,
+};
+pub use packet_type::{NamespaceIssue, PacketType, CCONNECT_PREFIX, KDECONNECT_PREFIX};
+pub use payload::{
+    PayloadConfig, PayloadReceiver, PayloadSender, DEFAULT_MAX_PAYLOAD_SIZE,
+    DEFAULT_PAYLOAD_CHUNK_SIZE, DEFAULT_PAYLOAD_CONNECT_TIMEOUT,
+};
+pub use codec::{PacketCodec, DEFAULT_MAX_PACKET_SIZE};
+pub use capture::{CapturedPacket, Direction, PacketTap};
 // pub use device::{Device, DeviceInfo, DeviceType};
-// pub use identity::Identity;
 
 /// KDE Connect protocol version implemented by this library
 /// Updated to version 8 to match latest KDE Connect Android app
 pub const PROTOCOL_VERSION: i32 = 8;
+
+/// Lowest peer protocol version this library can talk to
+///
+/// Peers between this and [`PROTOCOL_VERSION`] are accepted, and a peer
+/// that does not state its version is treated as this one.
+pub const fn min_supported_version() -> i32 {
+    7
+}
@@ -17,11 +17,29 @@
 //! - [KDE Connect Repository](https://invent.kde.org/network/kdeconnect-kde)
 
 use crate::error::{ProtocolError, Result};
+use crate::protocol::PacketType;
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
 
+/// JSON output format for packet serialization
+///
+/// The wire format is always [`JsonFormat::Compact`]; [`JsonFormat::Pretty`]
+/// is intended for logs and debugging only.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum JsonFormat {
+    /// Single-line JSON with no insignificant whitespace (wire format)
+    #[default]
+    Compact,
+
+    /// Indented multi-line JSON for human inspection
+    Pretty,
+}
+
+/// Placeholder written over sensitive values by [`Packet::redacted`]
+pub const REDACTED: &str = "***";
+
 /// Represents a KDE Connect network packet
 ///
 /// # Examples
@@ -105,6 +123,16 @@ impl Packet {
         }
     }
 
+    /// Creates a new packet whose body is a serialized value
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Json` if `body` cannot be represented as JSON
+    /// (e.g. a map with non-string keys).
+    pub fn try_new(packet_type: impl Into<String>, body: &impl Serialize) -> Result<Self> {
+        Ok(Self::new(packet_type, serde_json::to_value(body)?))
+    }
+
     /// Create a new packet with an explicit timestamp
     ///
     /// Useful for testing or when you need specific timestamp control
@@ -118,12 +146,43 @@ impl Packet {
         }
     }
 
+    /// Serialize packet to a JSON string in the given format
+    ///
+    /// No newline terminator is appended. Use [`Packet::to_bytes`] for the
+    /// wire representation.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Json` if serialization fails
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cosmic_ext_connect_core::protocol::{JsonFormat, Packet};
+    /// use serde_json::json;
+    ///
+    /// let packet = Packet::new("cconnect.ping", json!({}));
+    /// let pretty = packet.to_json(JsonFormat::Pretty).unwrap();
+    /// assert!(pretty.contains('\n'));
+    /// ```
+    pub fn to_json(&self, format: JsonFormat) -> Result<String> {
+        let json = match format {
+            JsonFormat::Compact => serde_json::to_string(self)?,
+            JsonFormat::Pretty => serde_json::to_string_pretty(self)?,
+        };
+        Ok(json)
+    }
+
     /// Serialize packet to bytes with newline terminator
     ///
     /// KDE Connect packets are JSON-formatted and terminated with a single
     /// newline character (`\n`). This format allows packets to be easily
     /// delimited when sent over TCP streams.
     ///
+    /// This is the canonical wire form: it always uses [`JsonFormat::Compact`],
+    /// and framing, checksums and transports should all use it so that every
+    /// layer agrees on the byte representation.
+    ///
     /// # Errors
     ///
     /// Returns `ProtocolError::Json` if serialization fails
@@ -141,7 +200,7 @@ impl Packet {
     /// assert_eq!(bytes.last(), Some(&b'\n'));
     /// ```
     pub fn to_bytes(&self) -> Result<Vec<u8>> {
-        let json = serde_json::to_string(self)?;
+        let json = self.to_json(JsonFormat::Compact)?;
         let mut bytes = json.into_bytes();
         // Add newline terminator as per KDE Connect protocol specification
         bytes.push(b'\n');
@@ -199,6 +258,80 @@ impl Packet {
         self
     }
 
+    /// Look up this packet's type in the [`PacketType`] registry
+    ///
+    /// Returns `None` for types not in the registry; such packets are still
+    /// valid and can be routed by their raw type string.
+    pub fn known_type(&self) -> Option<PacketType> {
+        PacketType::parse(&self.packet_type)
+    }
+
+    /// Get a copy of the packet that is safe to log
+    ///
+    /// Masks the [`sensitive_fields`](PacketType::sensitive_fields) of known
+    /// packet types with [`REDACTED`]. The type, ID and other fields stay
+    /// visible. Unknown packet types are returned unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cosmic_ext_connect_core::protocol::Packet;
+    /// use serde_json::json;
+    ///
+    /// let packet = Packet::new("cconnect.clipboard", json!({ "content": "hunter2" }));
+    /// let logged = packet.redacted().to_string();
+    /// assert!(logged.contains("cconnect.clipboard"));
+    /// assert!(!logged.contains("hunter2"));
+    /// ```
+    pub fn redacted(&self) -> RedactedPacket {
+        let mut packet = self.clone();
+        if let Some(packet_type) = self.known_type() {
+            redact_fields(&mut packet.body, packet_type.sensitive_fields());
+        }
+        RedactedPacket(packet)
+    }
+
+    /// Describe the packet for support logs and bug reports
+    ///
+    /// Produces a multi-line summary of the type, ID, payload size and the
+    /// pretty-printed body. The body is [`redacted`](Self::redacted) first,
+    /// so the output is safe to paste into an issue.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cosmic_ext_connect_core::protocol::Packet;
+    /// use serde_json::json;
+    ///
+    /// let packet = Packet::with_id(7, "cconnect.ping", json!({ "message": "hi" }));
+    /// assert_eq!(
+    ///     packet.describe(),
+    ///     "type: cconnect.ping\nid: 7\npayload: none\nbody:\n  {\n    \"message\": \"hi\"\n  }\n"
+    /// );
+    /// ```
+    pub fn describe(&self) -> String {
+        let redacted = self.redacted();
+        let packet = redacted.packet();
+
+        let payload = match packet.payload_size {
+            Some(size) => format!("{} bytes", size),
+            None => "none".to_string(),
+        };
+        let body = serde_json::to_string_pretty(&packet.body)
+            .unwrap_or_else(|_| "<unprintable>".to_string());
+
+        let mut out = format!(
+            "type: {}\nid: {}\npayload: {}\nbody:\n",
+            packet.packet_type, packet.id, payload
+        );
+        for line in body.lines() {
+            out.push_str("  ");
+            out.push_str(line);
+            out.push('\n');
+        }
+        out
+    }
+
     /// Check if packet is of a specific type
     ///
     /// This method supports both "cconnect." and "kdeconnect." prefixes for compatibility
@@ -225,15 +358,67 @@ impl Packet {
         false
     }
 
+    /// Get a raw field from the body
+    ///
+    /// `key` is the camelCase wire name. Some third-party clients send
+    /// snake_case keys instead, so the snake_case spelling is accepted as a
+    /// fallback when the camelCase key is absent.
+    pub fn body_value(&self, key: &str) -> Option<&Value> {
+        self.body
+            .get(key)
+            .or_else(|| self.body.get(snake_case(key)?.as_str()))
+    }
+
     /// Get a field from the body as a specific type
+    ///
+    /// Accepts snake_case keys as well, see [`Packet::body_value`].
     pub fn get_body_field<T>(&self, key: &str) -> Option<T>
     where
         T: serde::de::DeserializeOwned,
     {
-        self.body
-            .get(key)
+        self.body_value(key)
             .and_then(|v| serde_json::from_value(v.clone()).ok())
     }
+
+    /// Parse the body as `T`, treating an empty body as `T::default()`
+    ///
+    /// Packets such as `cconnect.camera.stop` carry no fields, and peers
+    /// variously send `{}`, `null` or no `body` at all. All three parse to
+    /// the default instead of depending on how `T` deserializes nothing.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::InvalidPacket` if a non-empty body does not
+    /// deserialize as `T`.
+    pub fn body_or_default<T>(&self) -> Result<T>
+    where
+        T: Default + serde::de::DeserializeOwned,
+    {
+        match &self.body {
+            Value::Null => Ok(T::default()),
+            Value::Object(fields) if fields.is_empty() => Ok(T::default()),
+            body => serde_json::from_value(body.clone())
+                .map_err(|e| ProtocolError::InvalidPacket(e.to_string())),
+        }
+    }
+}
+
+/// Convert a camelCase key to snake_case, or `None` if it has no capitals
+fn snake_case(key: &str) -> Option<String> {
+    if !key.chars().any(|c| c.is_ascii_uppercase()) {
+        return None;
+    }
+
+    let mut out = String::with_capacity(key.len() + 4);
+    for c in key.chars() {
+        if c.is_ascii_uppercase() {
+            out.push('_');
+            out.push(c.to_ascii_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    Some(out)
 }
 
 /// Custom deserializer for the `id` field to handle both string and number formats
@@ -255,6 +440,49 @@ where
     }
 }
 
+/// Packet with sensitive body fields masked, for logging
+///
+/// Created by [`Packet::redacted`]. Displays as compact JSON.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RedactedPacket(Packet);
+
+impl RedactedPacket {
+    /// Get the masked packet
+    pub fn packet(&self) -> &Packet {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for RedactedPacket {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0.to_json(JsonFormat::Compact) {
+            Ok(json) => f.write_str(&json),
+            Err(_) => write!(f, "{} <unprintable>", self.0.packet_type),
+        }
+    }
+}
+
+/// Replace values of `fields` anywhere in `value` with [`REDACTED`]
+fn redact_fields(value: &mut Value, fields: &[&str]) {
+    match value {
+        Value::Object(map) => {
+            for (key, field) in map.iter_mut() {
+                if fields.contains(&key.as_str()) && !field.is_null() {
+                    *field = Value::String(REDACTED.to_string());
+                } else {
+                    redact_fields(field, fields);
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                redact_fields(item, fields);
+            }
+        }
+        _ => {}
+    }
+}
+
 /// Custom serializer for the `id` field - always serialize as a number
 fn serialize_id<S>(id: &i64, serializer: S) -> std::result::Result<S::Ok, S::Error>
 where
@@ -281,6 +509,17 @@ mod tests {
         assert!(packet.id > 0);
     }
 
+    #[test]
+    fn test_try_new_reports_unserializable_body() {
+        // JSON object keys must be strings
+        let body: HashMap<(u32, u32), u32> = [((1, 2), 3)].into_iter().collect();
+        let result = Packet::try_new("cconnect.camera.settings", &body);
+        assert!(matches!(result, Err(ProtocolError::Json(_))));
+
+        let packet = Packet::try_new("cconnect.ping", &json!({ "a": 1 })).unwrap();
+        assert_eq!(packet.body["a"], 1);
+    }
+
     #[test]
     fn test_packet_serialization() {
         let packet = Packet::new(
@@ -419,6 +658,19 @@ mod tests {
         assert_eq!(packet.get_body_field::<String>("nonexistent"), None);
     }
 
+    #[test]
+    fn test_get_body_field_snake_case_fallback() {
+        let packet = Packet::new(
+            "cconnect.battery",
+            json!({ "is_charging": true, "currentCharge": 85, "current_charge": 10 }),
+        );
+
+        assert_eq!(packet.get_body_field::<bool>("isCharging"), Some(true));
+        // The camelCase key wins when both spellings are present
+        assert_eq!(packet.get_body_field::<i64>("currentCharge"), Some(85));
+        assert_eq!(packet.body_value("thresholdEvent"), None);
+    }
+
     #[test]
     fn test_invalid_packet() {
         let invalid_json = b"not json data";
@@ -436,6 +688,39 @@ mod tests {
         assert!(timestamp_str.len() >= 13);
     }
 
+    #[test]
+    fn test_json_format_compact_vs_pretty() {
+        let packet = Packet::with_id(
+            1234567890,
+            "cconnect.battery",
+            json!({"isCharging": true, "currentCharge": 85}),
+        );
+
+        let compact = packet.to_json(JsonFormat::Compact).unwrap();
+        let pretty = packet.to_json(JsonFormat::Pretty).unwrap();
+
+        // Byte-different...
+        assert_ne!(compact, pretty);
+        assert!(!compact.contains('\n'));
+        assert!(pretty.contains('\n'));
+
+        // ...but semantically equal
+        let compact_value: Value = serde_json::from_str(&compact).unwrap();
+        let pretty_value: Value = serde_json::from_str(&pretty).unwrap();
+        assert_eq!(compact_value, pretty_value);
+    }
+
+    #[test]
+    fn test_to_bytes_uses_compact() {
+        let packet = Packet::with_id(1234567890, "cconnect.ping", json!({"message": "hi"}));
+
+        let bytes = packet.to_bytes().unwrap();
+        let compact = packet.to_json(JsonFormat::Compact).unwrap();
+
+        assert_eq!(&bytes[..bytes.len() - 1], compact.as_bytes());
+        assert_eq!(bytes.iter().filter(|&&b| b == b'\n').count(), 1);
+    }
+
     #[test]
     fn test_complex_body() {
         let packet = Packet::new(
@@ -459,4 +744,90 @@ mod tests {
         );
         assert_eq!(parsed.get_body_field::<bool>("isClearable"), Some(true));
     }
+
+    #[test]
+    fn test_redacted_clipboard_packet() {
+        let packet = Packet::with_id(
+            42,
+            "kdeconnect.clipboard",
+            json!({ "content": "my bank password" }),
+        );
+
+        let redacted = packet.redacted();
+        let logged = redacted.to_string();
+        assert!(logged.contains("\"type\":\"kdeconnect.clipboard\""));
+        assert!(!logged.contains("my bank password"));
+        assert_eq!(redacted.packet().body["content"], REDACTED);
+        assert_eq!(redacted.packet().id, 42);
+
+        // The original packet is untouched
+        assert_eq!(packet.body["content"], "my bank password");
+    }
+
+    #[test]
+    fn test_redacted_nested_and_unlisted_fields() {
+        let packet = Packet::new(
+            "cconnect.sms.messages",
+            json!({
+                "messages": [
+                    { "body": "see you at 5", "thread_id": 7 },
+                    { "body": "ok", "thread_id": 7 }
+                ]
+            }),
+        );
+        let redacted = packet.redacted();
+        let messages = &redacted.packet().body["messages"];
+        assert_eq!(messages[0]["body"], REDACTED);
+        assert_eq!(messages[1]["body"], REDACTED);
+        assert_eq!(messages[0]["thread_id"], 7);
+
+        // Types without sensitive fields are unchanged
+        let ping = Packet::new("cconnect.ping", json!({ "message": "hi" }));
+        assert_eq!(ping.redacted().packet(), &ping);
+    }
+
+    #[test]
+    fn test_body_or_default() {
+        #[derive(Debug, Default, PartialEq, Deserialize)]
+        struct Stop {
+            #[serde(rename = "cameraId", default)]
+            camera_id: Option<u32>,
+        }
+
+        let empty = Packet::new("cconnect.camera.stop", json!({}));
+        let null = Packet::new("cconnect.camera.stop", Value::Null);
+        let absent = Packet::from_bytes(br#"{"id":1,"type":"cconnect.camera.stop"}"#).unwrap();
+        for packet in [&empty, &null, &absent] {
+            assert_eq!(packet.body_or_default::<Stop>().unwrap(), Stop::default());
+            // Unit-like types that only deserialize from `null` parse too
+            packet.body_or_default::<()>().unwrap();
+        }
+
+        let one = Packet::new("cconnect.camera.stop", json!({ "cameraId": 2 }));
+        assert_eq!(one.body_or_default::<Stop>().unwrap().camera_id, Some(2));
+
+        let bad = Packet::new("cconnect.camera.stop", json!({ "cameraId": "front" }));
+        assert!(matches!(
+            bad.body_or_default::<Stop>(),
+            Err(ProtocolError::InvalidPacket(_))
+        ));
+    }
+
+    #[test]
+    fn test_describe_redacts_clipboard() {
+        let packet = Packet::with_id(
+            42,
+            "cconnect.clipboard",
+            json!({ "content": "my bank password", "timestamp": 1000 }),
+        )
+        .with_payload_size(2048);
+
+        let described = packet.describe();
+        assert!(described.starts_with("type: cconnect.clipboard\n"));
+        assert!(described.contains("id: 42\n"));
+        assert!(described.contains("payload: 2048 bytes\n"));
+        assert!(described.contains("\"timestamp\": 1000"));
+        assert!(described.contains(REDACTED));
+        assert!(!described.contains("my bank password"));
+    }
 }
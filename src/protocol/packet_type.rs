@@ -0,0 +1,492 @@
+//! Packet Type Registry
+//!
+//! Central list of the packet types this library knows about.
+//!
+//! Packet types travel as strings on the wire, and plugins and routing still
+//! accept arbitrary strings so that newer KDE Connect types keep working.
+//! [`PacketType`] gives known types a single spelling: [`PacketType::parse`]
+//! returns `None` for unknown strings instead of failing, and accepts both
+//! the `cconnect.` and `kdeconnect.` prefixes.
+//!
+//! ## Namespaces
+//!
+//! Plugins in this library advertise and send `cconnect.` types. Types that
+//! also exist in upstream KDE Connect (ping, battery, clipboard, share,
+//! notifications, MPRIS, ...) can be spelled with the `kdeconnect.` prefix
+//! for interop via [`PacketType::to_upstream`]; extensions such as camera
+//! streaming, screen sharing and virtual monitors have no upstream spelling.
+//! [`PacketType::check_namespace`] flags capabilities that break this
+//! convention.
+//!
+//! [`PacketType::canonical_aliases`] lists every spelling of a type. The
+//! plugin manager advertises all of them, so upstream peers see the
+//! capabilities they know, and routes a packet by any of them
+//! ([`PacketType::from_alias`]).
+//!
+//! ## Example
+//!
+//! ```rust
+//! use cosmic_ext_connect_core::protocol::PacketType;
+//!
+//! assert_eq!(PacketType::parse("cconnect.ping"), Some(PacketType::Ping));
+//! assert_eq!(PacketType::parse("kdeconnect.ping"), Some(PacketType::Ping));
+//! assert_eq!(PacketType::Ping.as_str(), "cconnect.ping");
+//! assert_eq!(PacketType::parse("cconnect.future.feature"), None);
+//! assert_eq!(PacketType::to_upstream("cconnect.ping"), "kdeconnect.ping");
+//! ```
+
+use std::borrow::Cow;
+
+/// Prefix of the packet types used by this library
+pub const CCONNECT_PREFIX: &str = "cconnect.";
+
+/// Prefix of the packet types used by upstream KDE Connect
+pub const KDECONNECT_PREFIX: &str = "kdeconnect.";
+
+macro_rules! packet_types {
+    ($($(#[$meta:meta])* $variant:ident => $name:literal,)+) => {
+        /// Known packet types
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum PacketType {
+            $($(#[$meta])* $variant,)+
+        }
+
+        impl PacketType {
+            /// Every known packet type
+            pub const ALL: &'static [PacketType] = &[$(PacketType::$variant,)+];
+
+            /// Get the canonical (`cconnect.`-prefixed) wire string
+            pub fn as_str(&self) -> &'static str {
+                match self {
+                    $(PacketType::$variant => $name,)+
+                }
+            }
+
+            /// Look up a known packet type
+            ///
+            /// Accepts `cconnect.` and `kdeconnect.` prefixes. Returns `None`
+            /// for types not in the registry. Does not allocate.
+            pub fn parse(packet_type: &str) -> Option<PacketType> {
+                match packet_type {
+                    $($name => Some(PacketType::$variant),)+
+                    _ => {
+                        let rest = packet_type.strip_prefix(KDECONNECT_PREFIX)?;
+                        PacketType::ALL.iter().copied().find(|known| {
+                            known.as_str().strip_prefix(CCONNECT_PREFIX) == Some(rest)
+                        })
+                    }
+                }
+            }
+        }
+    };
+}
+
+packet_types! {
+    /// Device identity
+    Identity => "cconnect.identity",
+    /// Encrypted envelope for non-TLS transports
+    Encrypted => "cconnect.encrypted",
+    /// Per-connection nonce exchanged before encrypted envelopes
+    EncryptedHello => "cconnect.encrypted.hello",
+    /// Pair request / response
+    Pair => "cconnect.pair",
+    /// Ping
+    Ping => "cconnect.ping",
+    /// Battery status
+    Battery => "cconnect.battery",
+    /// Battery status request
+    BatteryRequest => "cconnect.battery.request",
+    /// Clipboard content
+    Clipboard => "cconnect.clipboard",
+    /// Clipboard content on connect
+    ClipboardConnect => "cconnect.clipboard.connect",
+    /// Notification
+    Notification => "cconnect.notification",
+    /// Notification action
+    NotificationAction => "cconnect.notification.action",
+    /// Notification reply
+    NotificationReply => "cconnect.notification.reply",
+    /// Notification request
+    NotificationRequest => "cconnect.notification.request",
+    /// File or text share
+    ShareRequest => "cconnect.share.request",
+    /// Multi-file share update
+    ShareRequestUpdate => "cconnect.share.request.update",
+    /// Media player state
+    Mpris => "cconnect.mpris",
+    /// Media player control
+    MprisRequest => "cconnect.mpris.request",
+    /// Run command list
+    RunCommand => "cconnect.runcommand",
+    /// Run command request
+    RunCommandRequest => "cconnect.runcommand.request",
+    /// Find my phone
+    FindMyPhoneRequest => "cconnect.findmyphone.request",
+    /// Lock state
+    Lock => "cconnect.lock",
+    /// Lock request
+    LockRequest => "cconnect.lock.request",
+    /// Presenter pointer
+    Presenter => "cconnect.presenter",
+    /// Mouse/keyboard input request
+    MousepadRequest => "cconnect.mousepad.request",
+    /// Mouse/keyboard input echo
+    MousepadEcho => "cconnect.mousepad.echo",
+    /// Keyboard state
+    MousepadKeyboardState => "cconnect.mousepad.keyboardstate",
+    /// Telephony event
+    Telephony => "cconnect.telephony",
+    /// Mute ringer request
+    TelephonyRequestMute => "cconnect.telephony.request_mute",
+    /// SMS messages
+    SmsMessages => "cconnect.sms.messages",
+    /// SMS request
+    SmsRequest => "cconnect.sms.request",
+    /// SMS attachment request
+    SmsRequestAttachment => "cconnect.sms.request_attachment",
+    /// SMS attachment contents, sent as a payload
+    SmsAttachmentFile => "cconnect.sms.attachment_file",
+    /// SMS conversation request
+    SmsRequestConversation => "cconnect.sms.request_conversation",
+    /// SMS conversation list request
+    SmsRequestConversations => "cconnect.sms.request_conversations",
+    /// Contact UID/timestamp request
+    ContactsRequestAllUidsTimestamps => "cconnect.contacts.request_all_uids_timestamps",
+    /// Contact vCard request
+    ContactsRequestVcardsByUid => "cconnect.contacts.request_vcards_by_uid",
+    /// Contact UID/timestamp response
+    ContactsResponseUidsTimestamps => "cconnect.contacts.response_uids_timestamps",
+    /// Contact vCard response
+    ContactsResponseVcards => "cconnect.contacts.response_vcards",
+    /// System volume sink list / update
+    SystemVolume => "cconnect.systemvolume",
+    /// System volume request
+    SystemVolumeRequest => "cconnect.systemvolume.request",
+    /// Connectivity report
+    ConnectivityReport => "cconnect.connectivity_report",
+    /// Open URL/file capability
+    OpenCapability => "cconnect.open.capability",
+    /// Open URL/file request
+    OpenRequest => "cconnect.open.request",
+    /// Open URL/file response
+    OpenResponse => "cconnect.open.response",
+    /// SFTP
+    Sftp => "cconnect.sftp",
+    /// Digitizer input
+    Digitizer => "cconnect.digitizer",
+    /// Digitizer session
+    DigitizerSession => "cconnect.digitizer.session",
+    /// File sync
+    FileSync => "cconnect.filesync",
+    /// File sync conflict
+    FileSyncConflict => "cconnect.filesync.conflict",
+    /// File sync request
+    FileSyncRequest => "cconnect.filesync.request",
+    /// Audio stream
+    AudioStream => "cconnect.audiostream",
+    /// Audio stream capability
+    AudioStreamCapability => "cconnect.audiostream.capability",
+    /// Audio stream request
+    AudioStreamRequest => "cconnect.audiostream.request",
+    /// Audio stream volume control
+    AudioStreamControl => "cconnect.audiostream.control",
+    /// Camera capability
+    CameraCapability => "cconnect.camera.capability",
+    /// Camera flow control
+    CameraFlowControl => "cconnect.camera.flowcontrol",
+    /// Camera frame
+    CameraFrame => "cconnect.camera.frame",
+    /// Camera settings
+    CameraSettings => "cconnect.camera.settings",
+    /// Camera start
+    CameraStart => "cconnect.camera.start",
+    /// Camera status
+    CameraStatus => "cconnect.camera.status",
+    /// Camera stop
+    CameraStop => "cconnect.camera.stop",
+    /// Camera torch (flashlight)
+    CameraTorch => "cconnect.camera.torch",
+    /// Camera preview thumbnail request / response
+    CameraThumbnail => "cconnect.camera.thumbnail",
+    /// Camera media session offer
+    CameraOffer => "cconnect.camera.offer",
+    /// Camera media session answer
+    CameraAnswer => "cconnect.camera.answer",
+    /// Webcam
+    Webcam => "cconnect.webcam",
+    /// Webcam capability
+    WebcamCapability => "cconnect.webcam.capability",
+    /// Webcam request
+    WebcamRequest => "cconnect.webcam.request",
+    /// Screen share
+    ScreenShare => "cconnect.screenshare",
+    /// Screen share request
+    ScreenShareRequest => "cconnect.screenshare.request",
+    /// Screen share media session offer
+    ScreenShareOffer => "cconnect.screenshare.offer",
+    /// Screen share media session answer
+    ScreenShareAnswer => "cconnect.screenshare.answer",
+    /// Virtual monitor
+    VirtualMonitor => "cconnect.virtualmonitor",
+    /// Virtual monitor request
+    VirtualMonitorRequest => "cconnect.virtualmonitor.request",
+}
+
+impl PacketType {
+    /// Body fields that may carry private user data
+    ///
+    /// These are masked by [`Packet::redacted`](crate::protocol::Packet::redacted)
+    /// wherever they appear in the body, including inside nested objects and
+    /// arrays (e.g. each message in `cconnect.sms.messages`).
+    pub fn sensitive_fields(&self) -> &'static [&'static str] {
+        match self {
+            PacketType::Clipboard | PacketType::ClipboardConnect => &["content"],
+            PacketType::Notification => &["title", "text", "ticker"],
+            PacketType::NotificationReply => &["message"],
+            PacketType::ShareRequest => &["text"],
+            PacketType::MousepadRequest => &["key"],
+            PacketType::Telephony => &["messageBody"],
+            PacketType::SmsMessages => &["body"],
+            PacketType::SmsRequest => &["messageBody"],
+            PacketType::Sftp => &["password"],
+            _ => &[],
+        }
+    }
+}
+
+/// A capability that does not follow the `cconnect.` naming convention
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NamespaceIssue {
+    /// Uses the upstream `kdeconnect.` prefix instead of `cconnect.`
+    UpstreamPrefix {
+        /// The `cconnect.` spelling to use instead
+        canonical: String,
+    },
+
+    /// Uses neither the `cconnect.` nor the `kdeconnect.` prefix
+    UnknownPrefix,
+}
+
+impl PacketType {
+    /// Check whether this type only exists in this library
+    ///
+    /// Extensions have no `kdeconnect.` equivalent, so upstream KDE Connect
+    /// peers do not understand them.
+    pub fn is_extension(&self) -> bool {
+        matches!(
+            self,
+            PacketType::Encrypted
+                | PacketType::EncryptedHello
+                | PacketType::OpenCapability
+                | PacketType::OpenRequest
+                | PacketType::OpenResponse
+                | PacketType::FileSync
+                | PacketType::FileSyncConflict
+                | PacketType::FileSyncRequest
+                | PacketType::AudioStream
+                | PacketType::AudioStreamCapability
+                | PacketType::AudioStreamRequest
+                | PacketType::AudioStreamControl
+                | PacketType::CameraCapability
+                | PacketType::CameraFlowControl
+                | PacketType::CameraFrame
+                | PacketType::CameraSettings
+                | PacketType::CameraStart
+                | PacketType::CameraStatus
+                | PacketType::CameraStop
+                | PacketType::CameraTorch
+                | PacketType::CameraThumbnail
+                | PacketType::CameraOffer
+                | PacketType::CameraAnswer
+                | PacketType::Webcam
+                | PacketType::WebcamCapability
+                | PacketType::WebcamRequest
+                | PacketType::ScreenShare
+                | PacketType::ScreenShareRequest
+                | PacketType::ScreenShareOffer
+                | PacketType::ScreenShareAnswer
+                | PacketType::VirtualMonitor
+                | PacketType::VirtualMonitorRequest
+        )
+    }
+
+    /// Get the upstream `kdeconnect.` spelling, if this type has one
+    pub fn upstream_name(&self) -> Option<String> {
+        if self.is_extension() {
+            return None;
+        }
+        let rest = self.as_str().strip_prefix(CCONNECT_PREFIX)?;
+        Some(format!("{}{}", KDECONNECT_PREFIX, rest))
+    }
+
+    /// Get every wire spelling of this type, canonical first
+    ///
+    /// Types shared with upstream KDE Connect are followed by their
+    /// `kdeconnect.` spelling; extensions only have their `cconnect.` name.
+    pub fn canonical_aliases(&self) -> Vec<String> {
+        let mut aliases = vec![self.as_str().to_string()];
+        aliases.extend(self.upstream_name());
+        aliases
+    }
+
+    /// Look up a known packet type by one of its [`canonical_aliases`](Self::canonical_aliases)
+    ///
+    /// Stricter than [`parse`](Self::parse): an extension spelled with the
+    /// `kdeconnect.` prefix is not an alias and returns `None`.
+    ///
+    /// Does not allocate, so it is cheap enough to run on every routed packet.
+    pub fn from_alias(packet_type: &str) -> Option<PacketType> {
+        let upstream = packet_type.starts_with(KDECONNECT_PREFIX);
+        PacketType::parse(packet_type).filter(|known| !(upstream && known.is_extension()))
+    }
+
+    /// Translate a packet type for an upstream KDE Connect peer
+    ///
+    /// Known types with a `kdeconnect.` equivalent are translated; extensions
+    /// and unknown types are returned unchanged.
+    pub fn to_upstream(packet_type: &str) -> Cow<'_, str> {
+        match PacketType::parse(packet_type).and_then(|known| known.upstream_name()) {
+            Some(upstream) => Cow::Owned(upstream),
+            None => Cow::Borrowed(packet_type),
+        }
+    }
+
+    /// Check a capability advertised by one of our plugins
+    ///
+    /// Returns `None` if it uses the `cconnect.` prefix.
+    pub fn check_namespace(capability: &str) -> Option<NamespaceIssue> {
+        if capability.starts_with(CCONNECT_PREFIX) {
+            return None;
+        }
+        match capability.strip_prefix(KDECONNECT_PREFIX) {
+            Some(rest) => Some(NamespaceIssue::UpstreamPrefix {
+                canonical: format!("{}{}", CCONNECT_PREFIX, rest),
+            }),
+            None => Some(NamespaceIssue::UnknownPrefix),
+        }
+    }
+}
+
+impl std::fmt::Display for PacketType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<PacketType> for String {
+    fn from(packet_type: PacketType) -> Self {
+        packet_type.as_str().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_known_types_round_trip() {
+        for &packet_type in PacketType::ALL {
+            assert_eq!(PacketType::parse(packet_type.as_str()), Some(packet_type));
+
+            let kde = packet_type.as_str().replacen("cconnect.", "kdeconnect.", 1);
+            assert_eq!(PacketType::parse(&kde), Some(packet_type));
+        }
+
+        let unique: HashSet<_> = PacketType::ALL.iter().map(|t| t.as_str()).collect();
+        assert_eq!(unique.len(), PacketType::ALL.len());
+    }
+
+    #[test]
+    fn test_plugin_types_registered() {
+        use crate::plugins::{audiostream, camera, screenshare, telephony};
+
+        for packet_type in [
+            audiostream::PACKET_TYPE_AUDIOSTREAM_CONTROL,
+            camera::PACKET_TYPE_CAMERA_THUMBNAIL,
+            camera::PACKET_TYPE_CAMERA_OFFER,
+            camera::PACKET_TYPE_CAMERA_ANSWER,
+            screenshare::PACKET_TYPE_SCREENSHARE_OFFER,
+            screenshare::PACKET_TYPE_SCREENSHARE_ANSWER,
+            telephony::PACKET_TYPE_SMS_ATTACHMENT_FILE,
+        ] {
+            let known = PacketType::parse(packet_type).expect(packet_type);
+            assert_eq!(known.as_str(), packet_type);
+        }
+    }
+
+    #[test]
+    fn test_sensitive_fields() {
+        assert_eq!(PacketType::Clipboard.sensitive_fields(), &["content"]);
+        assert_eq!(PacketType::Sftp.sensitive_fields(), &["password"]);
+        assert!(PacketType::Ping.sensitive_fields().is_empty());
+    }
+
+    #[test]
+    fn test_upstream_translation() {
+        assert_eq!(PacketType::to_upstream("cconnect.ping"), "kdeconnect.ping");
+        assert_eq!(PacketType::to_upstream("kdeconnect.ping"), "kdeconnect.ping");
+        assert_eq!(
+            PacketType::to_upstream("cconnect.camera.start"),
+            "cconnect.camera.start"
+        );
+        assert_eq!(
+            PacketType::to_upstream("cconnect.future.feature"),
+            "cconnect.future.feature"
+        );
+
+        for &packet_type in PacketType::ALL {
+            assert!(packet_type.as_str().starts_with(CCONNECT_PREFIX));
+            assert_eq!(packet_type.upstream_name().is_none(), packet_type.is_extension());
+        }
+    }
+
+    #[test]
+    fn test_canonical_aliases() {
+        assert_eq!(
+            PacketType::Ping.canonical_aliases(),
+            vec!["cconnect.ping", "kdeconnect.ping"]
+        );
+        assert_eq!(
+            PacketType::CameraStart.canonical_aliases(),
+            vec!["cconnect.camera.start"]
+        );
+
+        assert_eq!(PacketType::from_alias("kdeconnect.mpris"), Some(PacketType::Mpris));
+        assert_eq!(
+            PacketType::from_alias("cconnect.virtualmonitor"),
+            Some(PacketType::VirtualMonitor)
+        );
+        assert_eq!(PacketType::from_alias("kdeconnect.screenshare"), None);
+        assert_eq!(PacketType::from_alias("cconnect.future.feature"), None);
+
+        for &packet_type in PacketType::ALL {
+            for alias in packet_type.canonical_aliases() {
+                assert_eq!(PacketType::from_alias(&alias), Some(packet_type));
+            }
+        }
+    }
+
+    #[test]
+    fn test_check_namespace() {
+        assert_eq!(PacketType::check_namespace("cconnect.ping"), None);
+        assert_eq!(PacketType::check_namespace("cconnect.future.feature"), None);
+        assert_eq!(
+            PacketType::check_namespace("kdeconnect.ping"),
+            Some(NamespaceIssue::UpstreamPrefix {
+                canonical: "cconnect.ping".to_string()
+            })
+        );
+        assert_eq!(
+            PacketType::check_namespace("mousepad.request"),
+            Some(NamespaceIssue::UnknownPrefix)
+        );
+    }
+
+    #[test]
+    fn test_unknown_type() {
+        assert_eq!(PacketType::parse("cconnect.future.feature"), None);
+        assert_eq!(PacketType::parse("ping"), None);
+        assert_eq!(PacketType::parse(""), None);
+    }
+}
@@ -0,0 +1,772 @@
+//! Payload Transfer
+//!
+//! Streams payload bytes (e.g. shared files) over a payload connection.
+//!
+//! KDE Connect sends large data out of band: the packet carries
+//! `payloadSize` and `payloadTransferInfo`, and the bytes follow on a separate
+//! connection. [`PayloadSender`] writes those bytes in fixed-size chunks, so
+//! memory use stays bounded regardless of file size.
+//!
+//! [`PayloadReceiver`] does the reverse. It hashes the bytes with SHA-256 as
+//! they are written, so a declared checksum (e.g. the `checksum` field of a
+//! filesync packet) is verified without reading the file a second time.
+//! It also refuses payloads above the ceiling in its [`PayloadConfig`]
+//! before reading any bytes, so a peer cannot fill the disk by declaring
+//! a huge transfer.
+//!
+//! Files are received into a `.part` file that is renamed into place only
+//! once complete and verified. Each transfer gets its own randomly named
+//! `.part` file, so two transfers to the same path never share one.
+//! Cancelling a transfer (dropping the
+//! [`PayloadReceiver::receive_file`] future, e.g. with `tokio::select!` or
+//! by aborting its task) removes the `.part` file, so a truncated file never
+//! appears where the user expects the complete one.
+//!
+//! ## Connection Timeouts
+//!
+//! The packet announcing a payload arrives before the payload connection
+//! opens, and a peer that crashes in between would leave the other side
+//! waiting forever. [`PayloadReceiver::establish`] and
+//! [`PayloadSender::establish`] wait for the connection for a bounded time
+//! ([`DEFAULT_PAYLOAD_CONNECT_TIMEOUT`] unless told otherwise). When it
+//! passes, the pending connection is dropped, freeing any socket or listener
+//! it holds, and `ProtocolError::Timeout` is returned.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use cosmic_ext_connect_core::protocol::PayloadSender;
+//! use tokio::net::TcpStream;
+//!
+//! # async fn example() -> cosmic_ext_connect_core::error::Result<()> {
+//! let stream = TcpStream::connect("192.168.1.100:1739").await?;
+//! let mut sender = PayloadSender::new(stream);
+//!
+//! let sent = sender
+//!     .send_file("/home/user/video.mkv", |sent, total| {
+//!         println!("{} / {} bytes", sent, total);
+//!     })
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{ProtocolError, Result};
+use sha2::{Digest, Sha256};
+use std::future::Future;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tracing::{debug, info, warn};
+
+/// Default chunk size for payload reads and writes (64 KiB)
+pub const DEFAULT_PAYLOAD_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Default largest payload accepted in one transfer (10 GiB)
+pub const DEFAULT_MAX_PAYLOAD_SIZE: u64 = 10 * 1024 * 1024 * 1024;
+
+/// Default time allowed for a payload connection to open
+pub const DEFAULT_PAYLOAD_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Wait up to `timeout` for a payload connection, dropping it on timeout
+async fn establish<C, F>(connection: F, timeout: Duration) -> Result<C>
+where
+    F: Future<Output = io::Result<C>>,
+{
+    match tokio::time::timeout(timeout, connection).await {
+        Ok(connection) => Ok(connection?),
+        Err(_) => {
+            warn!("Payload connection not opened within {:?}, abandoning", timeout);
+            Err(ProtocolError::Timeout)
+        }
+    }
+}
+
+/// Limits applied to received payloads
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PayloadConfig {
+    /// Largest payload accepted in one transfer, in bytes (0 = unlimited)
+    pub max_payload_size: u64,
+}
+
+impl Default for PayloadConfig {
+    fn default() -> Self {
+        Self {
+            max_payload_size: DEFAULT_MAX_PAYLOAD_SIZE,
+        }
+    }
+}
+
+impl PayloadConfig {
+    /// Config that accepts payloads of any size
+    pub fn unlimited() -> Self {
+        Self { max_payload_size: 0 }
+    }
+
+    /// Check a declared payload size against the ceiling
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::PayloadTooLarge` if `size` exceeds a non-zero
+    /// ceiling.
+    pub fn check(&self, size: u64) -> Result<()> {
+        if self.max_payload_size != 0 && size > self.max_payload_size {
+            return Err(ProtocolError::PayloadTooLarge {
+                size,
+                max: self.max_payload_size,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Streams payload data to a writer in bounded chunks
+#[derive(Debug)]
+pub struct PayloadSender<W> {
+    /// Payload connection
+    writer: W,
+
+    /// Bytes read and written per step
+    chunk_size: usize,
+}
+
+impl<W: AsyncWrite + Unpin> PayloadSender<W> {
+    /// Create a sender with the default chunk size
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            chunk_size: DEFAULT_PAYLOAD_CHUNK_SIZE,
+        }
+    }
+
+    /// Create a sender once the receiver accepts the payload connection
+    ///
+    /// `connection` resolves when the receiver has connected, typically by
+    /// accepting on the listener advertised in `payloadTransferInfo`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Timeout` if the connection does not open
+    /// within `timeout`, after dropping `connection` and everything it owns,
+    /// or `ProtocolError::Io` if it fails.
+    pub async fn establish<F>(connection: F, timeout: Duration) -> Result<Self>
+    where
+        F: Future<Output = io::Result<W>>,
+    {
+        establish(connection, timeout).await.map(Self::new)
+    }
+
+    /// Set the chunk size (minimum 1 byte)
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// Stream a file without loading it into memory
+    ///
+    /// The file size is snapshotted when it is opened, and exactly that many
+    /// bytes are sent, since the size has already been advertised as
+    /// `payloadSize`. Bytes appended during the transfer are ignored.
+    ///
+    /// `progress` is called after each chunk with (bytes sent, total bytes).
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Io` if the file cannot be read, the writer
+    /// fails, or the file shrinks below the snapshot size during transfer.
+    pub async fn send_file<F>(&mut self, path: impl AsRef<Path>, progress: F) -> Result<u64>
+    where
+        F: FnMut(u64, u64),
+    {
+        let path = path.as_ref();
+        let file = tokio::fs::File::open(path).await?;
+        let size = file.metadata().await?.len();
+
+        info!("Sending payload {:?} ({} bytes)", path, size);
+        self.send_from(file, size, progress).await
+    }
+
+    /// Stream exactly `size` bytes from a reader
+    ///
+    /// `progress` is called after each chunk with (bytes sent, total bytes).
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Io` if reading or writing fails, or if the
+    /// reader ends before `size` bytes.
+    pub async fn send_from<R, F>(&mut self, reader: R, size: u64, mut progress: F) -> Result<u64>
+    where
+        R: AsyncRead + Unpin,
+        F: FnMut(u64, u64),
+    {
+        let mut reader = reader.take(size);
+        let mut buffer = vec![0u8; self.chunk_size.min(size.max(1) as usize)];
+        let mut sent = 0u64;
+
+        while sent < size {
+            let read = reader.read(&mut buffer).await?;
+            if read == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!("Payload source ended after {} of {} bytes", sent, size),
+                )
+                .into());
+            }
+
+            self.writer.write_all(&buffer[..read]).await?;
+            sent += read as u64;
+            progress(sent, size);
+        }
+
+        self.writer.flush().await?;
+
+        debug!("Payload sent ({} bytes)", sent);
+        Ok(sent)
+    }
+
+    /// Get a reference to the underlying writer
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Consume the sender and return the underlying writer
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Receives payload data from a reader, verifying its SHA-256 checksum
+#[derive(Debug)]
+pub struct PayloadReceiver<R> {
+    /// Payload connection
+    reader: R,
+
+    /// Bytes read and written per step
+    chunk_size: usize,
+
+    /// Size limits for received payloads
+    config: PayloadConfig,
+}
+
+impl<R: AsyncRead + Unpin> PayloadReceiver<R> {
+    /// Create a receiver with the default chunk size and [`PayloadConfig`]
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            chunk_size: DEFAULT_PAYLOAD_CHUNK_SIZE,
+            config: PayloadConfig::default(),
+        }
+    }
+
+    /// Create a receiver once the payload connection opens
+    ///
+    /// `connection` resolves when the data connection of an announced
+    /// transfer is open, e.g. a connect to the sender's advertised port.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::Timeout` if the connection does not open
+    /// within `timeout`, after dropping `connection` and everything it owns,
+    /// or `ProtocolError::Io` if it fails.
+    pub async fn establish<F>(connection: F, timeout: Duration) -> Result<Self>
+    where
+        F: Future<Output = io::Result<R>>,
+    {
+        establish(connection, timeout).await.map(Self::new)
+    }
+
+    /// Set the size limits for received payloads
+    pub fn with_config(mut self, config: PayloadConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Set the chunk size (minimum 1 byte)
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// Receive a payload of `size` bytes into a file
+    ///
+    /// The bytes are written to a uniquely named `.part` file next to
+    /// `path`, which is renamed to `path` once the transfer completes and the checksum (if
+    /// any) matches, replacing any existing file. On any failure, or if the
+    /// returned future is dropped before completing, the partial file is
+    /// removed, so a truncated or corrupted payload never appears at `path`.
+    ///
+    /// `expected_sha256` is the hex-encoded digest declared by the sender
+    /// (case-insensitive). `progress` is called after each chunk with
+    /// (bytes received, total bytes).
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::PayloadTooLarge` before creating any file if
+    /// `size` exceeds the configured ceiling,
+    /// `ProtocolError::ChecksumMismatch` if the received bytes do not match
+    /// `expected_sha256`, or `ProtocolError::Io` if reading or writing fails
+    /// or the reader ends before `size` bytes.
+    pub async fn receive_file<F>(
+        &mut self,
+        path: impl AsRef<Path>,
+        size: u64,
+        expected_sha256: Option<&str>,
+        progress: F,
+    ) -> Result<u64>
+    where
+        F: FnMut(u64, u64),
+    {
+        let path = path.as_ref();
+        self.config.check(size)?;
+        let (file, mut partial) = Self::create_partial(path).await?;
+
+        info!("Receiving payload {:?} ({} bytes)", path, size);
+        let result = self
+            .receive_partial(file, size, expected_sha256, progress)
+            .await;
+
+        match result {
+            Ok(received) => {
+                tokio::fs::rename(&partial.path, path).await?;
+                partial.keep();
+                Ok(received)
+            }
+            Err(e) => {
+                warn!("Discarding payload {:?}: {}", path, e);
+                Err(e)
+            }
+        }
+    }
+
+    async fn receive_partial<F>(
+        &mut self,
+        mut file: tokio::fs::File,
+        size: u64,
+        expected_sha256: Option<&str>,
+        progress: F,
+    ) -> Result<u64>
+    where
+        F: FnMut(u64, u64),
+    {
+        let (received, digest) = self.receive_into(&mut file, size, progress).await?;
+        file.sync_all().await?;
+
+        if let Some(expected) = expected_sha256 {
+            Self::verify(expected, &digest)?;
+        }
+        Ok(received)
+    }
+
+    /// Stream exactly `size` bytes into a writer
+    ///
+    /// Returns the number of bytes received and their hex-encoded SHA-256.
+    /// `progress` is called after each chunk with (bytes received, total
+    /// bytes).
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::PayloadTooLarge` before reading if `size`
+    /// exceeds the configured ceiling, or `ProtocolError::Io` if reading or
+    /// writing fails, or if the reader ends before `size` bytes.
+    pub async fn receive_into<W, F>(
+        &mut self,
+        mut writer: W,
+        size: u64,
+        mut progress: F,
+    ) -> Result<(u64, String)>
+    where
+        W: AsyncWrite + Unpin,
+        F: FnMut(u64, u64),
+    {
+        self.config.check(size)?;
+        let mut reader = (&mut self.reader).take(size);
+        let mut buffer = vec![0u8; self.chunk_size.min(size.max(1) as usize)];
+        let mut hasher = Sha256::new();
+        let mut received = 0u64;
+
+        while received < size {
+            let read = reader.read(&mut buffer).await?;
+            if read == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!("Payload ended after {} of {} bytes", received, size),
+                )
+                .into());
+            }
+
+            hasher.update(&buffer[..read]);
+            writer.write_all(&buffer[..read]).await?;
+            received += read as u64;
+            progress(received, size);
+        }
+
+        writer.flush().await?;
+
+        debug!("Payload received ({} bytes)", received);
+        Ok((received, hex::encode(hasher.finalize())))
+    }
+
+    fn verify(expected: &str, actual: &str) -> Result<()> {
+        if expected.trim().eq_ignore_ascii_case(actual) {
+            Ok(())
+        } else {
+            Err(ProtocolError::ChecksumMismatch {
+                expected: expected.trim().to_lowercase(),
+                actual: actual.to_string(),
+            })
+        }
+    }
+
+    /// Create a `.part` file next to `path` under a name no other transfer uses
+    async fn create_partial(path: &Path) -> Result<(tokio::fs::File, PartialFile)> {
+        loop {
+            let mut name = path.file_name().unwrap_or_default().to_os_string();
+            name.push(format!(".{:016x}.part", rand::random::<u64>()));
+            let partial = path.with_file_name(name);
+
+            let opened = tokio::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&partial)
+                .await;
+            match opened {
+                Ok(file) => return Ok((file, PartialFile::new(partial))),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// Consume the receiver and return the underlying reader
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+/// `.part` file that is removed when dropped unless kept
+///
+/// Dropping happens on errors and when a receive future is cancelled, which
+/// is why removal is synchronous.
+#[derive(Debug)]
+struct PartialFile {
+    path: PathBuf,
+    keep: bool,
+}
+
+impl PartialFile {
+    fn new(path: PathBuf) -> Self {
+        Self { path, keep: false }
+    }
+
+    /// Leave the file alone on drop (it has been renamed into place)
+    fn keep(&mut self) {
+        self.keep = true;
+    }
+}
+
+impl Drop for PartialFile {
+    fn drop(&mut self) {
+        if self.keep {
+            return;
+        }
+        match std::fs::remove_file(&self.path) {
+            Ok(()) => debug!("Removed partial payload {:?}", self.path),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => warn!("Failed to remove partial payload {:?}: {}", self.path, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Seek, SeekFrom, Write};
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    /// Writer that hashes incoming bytes instead of storing them
+    #[derive(Debug, Default)]
+    struct HashingSink {
+        hash: u64,
+        len: u64,
+        largest_write: usize,
+    }
+
+    impl HashingSink {
+        fn update(&mut self, data: &[u8]) {
+            for &byte in data {
+                self.hash = self.hash.wrapping_mul(31).wrapping_add(byte as u64);
+            }
+            self.len += data.len() as u64;
+        }
+    }
+
+    impl AsyncWrite for HashingSink {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            self.largest_write = self.largest_write.max(buf.len());
+            self.update(buf);
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_send_large_sparse_file() {
+        const SIZE: u64 = 32 * 1024 * 1024;
+
+        // Sparse file with data only at the start and end
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(b"start").unwrap();
+        file.as_file().set_len(SIZE).unwrap();
+        file.seek(SeekFrom::End(-3)).unwrap();
+        file.write_all(b"end").unwrap();
+        file.flush().unwrap();
+
+        let mut expected = HashingSink::default();
+        expected.update(&std::fs::read(file.path()).unwrap());
+
+        let mut sender = PayloadSender::new(HashingSink::default());
+        let mut progress_calls = 0;
+        let sent = sender
+            .send_file(file.path(), |sent, total| {
+                assert!(sent <= total);
+                progress_calls += 1;
+            })
+            .await
+            .unwrap();
+
+        let sink = sender.into_inner();
+        assert_eq!(sent, SIZE);
+        assert_eq!(sink.len, SIZE);
+        assert_eq!(sink.hash, expected.hash);
+
+        // Never buffers more than one chunk
+        assert!(sink.largest_write <= DEFAULT_PAYLOAD_CHUNK_SIZE);
+        assert!(progress_calls >= (SIZE as usize) / DEFAULT_PAYLOAD_CHUNK_SIZE);
+    }
+
+    #[tokio::test]
+    async fn test_send_file_honors_size_snapshot() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(&[7u8; 1000]).unwrap();
+        file.flush().unwrap();
+
+        // Grow the file after the first chunk has gone out
+        let mut appender = file.reopen().unwrap();
+        let mut sender = PayloadSender::new(Vec::new()).with_chunk_size(100);
+        let sent = sender
+            .send_file(file.path(), |sent, _| {
+                if sent == 100 {
+                    appender.seek(SeekFrom::End(0)).unwrap();
+                    appender.write_all(&[9u8; 500]).unwrap();
+                }
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(sent, 1000);
+        assert_eq!(sender.get_ref(), &vec![7u8; 1000]);
+    }
+
+    fn sha256_hex(data: &[u8]) -> String {
+        hex::encode(Sha256::digest(data))
+    }
+
+    /// `.part` files left in `dir`
+    fn partial_files(dir: &Path) -> Vec<PathBuf> {
+        std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "part"))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_transfers_to_same_path_use_separate_part_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("photo.jpg");
+
+        type Receiver = PayloadReceiver<&'static [u8]>;
+        let (_first_file, first) = Receiver::create_partial(&path).await.unwrap();
+        let (_second_file, second) = Receiver::create_partial(&path).await.unwrap();
+        assert_ne!(first.path, second.path);
+        assert_eq!(partial_files(dir.path()).len(), 2);
+
+        // Each transfer only removes its own part file
+        drop(first);
+        assert_eq!(partial_files(dir.path()), vec![second.path.clone()]);
+    }
+
+    #[tokio::test]
+    async fn test_receive_file_with_matching_checksum() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("photo.jpg");
+        let data = vec![42u8; 1000];
+        let checksum = sha256_hex(&data).to_uppercase();
+
+        let mut receiver = PayloadReceiver::new(&data[..]).with_chunk_size(64);
+        let received = receiver
+            .receive_file(&path, 1000, Some(&checksum), |_, _| {})
+            .await
+            .unwrap();
+
+        assert_eq!(received, 1000);
+        assert_eq!(std::fs::read(&path).unwrap(), data);
+        assert!(partial_files(dir.path()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_receive_file_with_wrong_checksum_is_discarded() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("photo.jpg");
+        let data = vec![42u8; 1000];
+        let wrong = sha256_hex(b"something else");
+
+        let mut receiver = PayloadReceiver::new(&data[..]);
+        let result = receiver.receive_file(&path, 1000, Some(&wrong), |_, _| {}).await;
+
+        match result {
+            Err(ProtocolError::ChecksumMismatch { expected, actual }) => {
+                assert_eq!(expected, wrong);
+                assert_eq!(actual, sha256_hex(&data));
+            }
+            other => panic!("expected ChecksumMismatch, got {:?}", other),
+        }
+        assert!(!path.exists());
+        assert!(partial_files(dir.path()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_payload_ceiling() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("huge.bin");
+        let data = [1u8; 100];
+        let config = PayloadConfig {
+            max_payload_size: 100,
+        };
+
+        // Declared size above the ceiling is refused before any bytes are read
+        let mut receiver = PayloadReceiver::new(&data[..]).with_config(config);
+        match receiver.receive_file(&path, 101, None, |_, _| {}).await {
+            Err(ProtocolError::PayloadTooLarge { size, max }) => {
+                assert_eq!((size, max), (101, 100));
+            }
+            other => panic!("expected PayloadTooLarge, got {:?}", other),
+        }
+        assert!(partial_files(dir.path()).is_empty());
+        assert_eq!(receiver.into_inner().len(), 100);
+
+        // At the ceiling is accepted
+        let mut receiver = PayloadReceiver::new(&data[..]).with_config(config);
+        let received = receiver.receive_file(&path, 100, None, |_, _| {}).await;
+        assert_eq!(received.unwrap(), 100);
+
+        // Zero means unlimited
+        assert!(PayloadConfig::unlimited().check(u64::MAX).is_ok());
+        assert!(PayloadConfig::default().check(DEFAULT_MAX_PAYLOAD_SIZE + 1).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_receive_leaves_no_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("video.mkv");
+
+        // The sender stalls after the first 100 of 1000 bytes
+        let (mut tx, rx) = tokio::io::duplex(1024);
+        tx.write_all(&[5u8; 100]).await.unwrap();
+
+        let mut receiver = PayloadReceiver::new(rx);
+        let (started_tx, started_rx) = tokio::sync::oneshot::channel();
+        let mut started_tx = Some(started_tx);
+        let transfer = receiver.receive_file(&path, 1000, None, |received, _| {
+            if received == 100 {
+                if let Some(tx) = started_tx.take() {
+                    let _ = tx.send(());
+                }
+            }
+        });
+
+        tokio::select! {
+            result = transfer => panic!("transfer should stall, got {:?}", result),
+            _ = async {
+                started_rx.await.unwrap();
+                assert_eq!(partial_files(dir.path()).len(), 1);
+            } => {}
+        }
+
+        assert!(partial_files(dir.path()).is_empty());
+        assert!(!path.exists());
+
+        // A complete transfer renames the part file over an existing file
+        std::fs::write(&path, b"old").unwrap();
+        let data = vec![6u8; 1000];
+        let mut receiver = PayloadReceiver::new(&data[..]);
+        receiver.receive_file(&path, 1000, Some(&sha256_hex(&data)), |_, _| {
+            assert_eq!(partial_files(dir.path()).len(), 1);
+        })
+        .await
+        .unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), data);
+        assert!(partial_files(dir.path()).is_empty());
+        drop(tx);
+    }
+
+    #[tokio::test]
+    async fn test_receiver_abandons_unopened_connection() {
+        use tokio::net::{TcpListener, TcpStream};
+
+        // Expect the sender to connect, which it never does
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let connection = async move { listener.accept().await.map(|(stream, _)| stream) };
+
+        let timeout = Duration::from_millis(50);
+        let start = std::time::Instant::now();
+        let result = PayloadReceiver::<TcpStream>::establish(connection, timeout).await;
+        assert!(matches!(result, Err(ProtocolError::Timeout)));
+        assert!(start.elapsed() >= timeout);
+
+        // The listener was closed and its port freed
+        assert!(TcpStream::connect(addr).await.is_err());
+        TcpListener::bind(addr).await.unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_sender_times_out_waiting_for_accept() {
+        let pending = std::sync::Arc::new(());
+        let connection = {
+            let pending = std::sync::Arc::clone(&pending);
+            async move {
+                let _pending = pending;
+                std::future::pending::<io::Result<Vec<u8>>>().await
+            }
+        };
+
+        let result = PayloadSender::establish(connection, DEFAULT_PAYLOAD_CONNECT_TIMEOUT).await;
+        assert!(matches!(result, Err(ProtocolError::Timeout)));
+        assert_eq!(std::sync::Arc::strong_count(&pending), 1);
+
+        // A connection that opens in time is used
+        let sender = PayloadSender::establish(async { Ok(Vec::new()) }, Duration::ZERO).await;
+        assert!(sender.unwrap().get_ref().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_send_from_short_source_fails() {
+        let mut sender = PayloadSender::new(Vec::new());
+        let result = sender.send_from(&b"short"[..], 10, |_, _| {}).await;
+        assert!(result.is_err());
+    }
+}
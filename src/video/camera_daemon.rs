@@ -6,6 +6,7 @@
 use crate::plugins::camera::{CameraFrame, FrameType};
 use crate::video::frame::{PixelFormat, VideoFrame};
 use crate::video::h264_decoder::{DecoderError, H264Decoder};
+use crate::video::nal::split_nal_units;
 use crate::video::performance::PerformanceMonitor;
 use crate::video::v4l2_device::{V4l2Error, V4l2LoopbackDevice};
 use std::fmt;
@@ -406,52 +407,61 @@ impl CameraDaemon {
                 continue;
             }
 
-            // Decode frame with timing
-            let decode_start = Instant::now();
-            match decoder.decode(&frame_data.data, frame_data.timestamp_us) {
-                Ok(Some(frame)) => {
-                    let decode_time_ns = decode_start.elapsed().as_nanos() as u64;
-                    stats.frames_decoded.fetch_add(1, Ordering::Relaxed);
+            // A frame may carry several NAL units; decode them one at a time
+            let mut nal_units = split_nal_units(&frame_data.data);
+            if nal_units.is_empty() {
+                nal_units.push(&frame_data.data[..]);
+            }
 
-                    if let Some(ref monitor) = perf_monitor {
-                        monitor.on_frame_decoded(decode_time_ns);
-                    }
+            for nal_unit in nal_units {
+                // Decode frame with timing
+                let decode_start = Instant::now();
+                match decoder.decode(nal_unit, frame_data.timestamp_us) {
+                    Ok(Some(frame)) => {
+                        let decode_time_ns = decode_start.elapsed().as_nanos() as u64;
+                        stats.frames_decoded.fetch_add(1, Ordering::Relaxed);
 
-                    // Write to V4L2 device with timing
-                    let write_start = Instant::now();
-                    if let Err(e) = v4l2_device.write_frame(&frame) {
-                        stats.write_errors.fetch_add(1, Ordering::Relaxed);
                         if let Some(ref monitor) = perf_monitor {
-                            monitor.on_write_error();
+                            monitor.on_frame_decoded(decode_time_ns);
                         }
-                        warn!("Failed to write frame: {}", e);
-                    } else {
-                        let write_time_ns = write_start.elapsed().as_nanos() as u64;
-                        stats.frames_written.fetch_add(1, Ordering::Relaxed);
 
-                        if let Some(ref monitor) = perf_monitor {
-                            monitor.on_frame_written(write_time_ns);
+                        // Write to V4L2 device with timing
+                        let write_start = Instant::now();
+                        if let Err(e) = v4l2_device.write_frame(&frame) {
+                            stats.write_errors.fetch_add(1, Ordering::Relaxed);
+                            if let Some(ref monitor) = perf_monitor {
+                                monitor.on_write_error();
+                            }
+                            warn!("Failed to write frame: {}", e);
+                        } else {
+                            let write_time_ns = write_start.elapsed().as_nanos() as u64;
+                            stats.frames_written.fetch_add(1, Ordering::Relaxed);
+
+                            if let Some(ref monitor) = perf_monitor {
+                                monitor.on_frame_written(write_time_ns);
+                            }
                         }
                     }
-                }
-                Ok(None) => {
-                    // Need more data (normal for P-frames)
-                    debug!("Decoder needs more data");
-                }
-                Err(DecoderError::NeedMoreData) => {
-                    // Decoder not initialized yet
-                    debug!("Waiting for SPS/PPS");
-                }
-                Err(e) => {
-                    stats.decode_errors.fetch_add(1, Ordering::Relaxed);
-                    if let Some(ref monitor) = perf_monitor {
-                        monitor.on_decode_error();
+                    Ok(None) => {
+                        // Need more data (normal for P-frames)
+                        debug!("Decoder needs more data");
+                    }
+                    Err(DecoderError::NeedMoreData) => {
+                        // Decoder not initialized yet
+                        debug!("Waiting for SPS/PPS");
                     }
-                    warn!("Decode error: {}", e);
+                    Err(e) => {
+                        stats.decode_errors.fetch_add(1, Ordering::Relaxed);
+                        if let Some(ref monitor) = perf_monitor {
+                            monitor.on_decode_error();
+                        }
+                        warn!("Decode error: {}", e);
 
-                    // Reset decoder on error
-                    if let Err(reset_err) = decoder.reset() {
-                        error!("Failed to reset decoder: {}", reset_err);
+                        // Reset decoder on error and drop the rest of the frame
+                        if let Err(reset_err) = decoder.reset() {
+                            error!("Failed to reset decoder: {}", reset_err);
+                        }
+                        break;
                     }
                 }
             }
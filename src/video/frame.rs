@@ -77,8 +77,12 @@ pub struct VideoFrame {
     pub height: u32,
     /// Pixel format
     pub format: PixelFormat,
-    /// Presentation timestamp in microseconds
+    /// Presentation timestamp in microseconds, as sent by the device
     pub timestamp_us: u64,
+    /// Presentation timestamp on a zero-based monotonic timeline
+    ///
+    /// Set by the decoder; see [`PtsNormalizer`](super::PtsNormalizer).
+    pub normalized_pts_us: Option<u64>,
     /// Frame data buffer
     pub data: Vec<u8>,
     /// Stride for each plane (if planar format)
@@ -96,6 +100,7 @@ impl VideoFrame {
             height,
             format,
             timestamp_us,
+            normalized_pts_us: None,
             data: vec![0u8; size],
             strides,
         }
@@ -115,11 +120,25 @@ impl VideoFrame {
             height,
             format,
             timestamp_us,
+            normalized_pts_us: None,
             data,
             strides,
         }
     }
 
+    /// Attach a normalized presentation timestamp
+    pub fn with_normalized_pts(mut self, normalized_pts_us: u64) -> Self {
+        self.normalized_pts_us = Some(normalized_pts_us);
+        self
+    }
+
+    /// Timestamp to schedule presentation by
+    ///
+    /// The normalized PTS if the frame has one, otherwise the raw PTS.
+    pub fn presentation_us(&self) -> u64 {
+        self.normalized_pts_us.unwrap_or(self.timestamp_us)
+    }
+
     /// Compute strides for each plane
     fn compute_strides(width: u32, format: &PixelFormat) -> Vec<u32> {
         match format {
@@ -177,12 +196,14 @@ impl VideoFrame {
     /// - I420 → YUYV
     /// - NV12 → YUYV
     pub fn convert(&self, target_format: PixelFormat) -> Option<VideoFrame> {
-        match (self.format, target_format) {
-            (PixelFormat::I420, PixelFormat::YUYV) => Some(self.i420_to_yuyv()),
-            (PixelFormat::NV12, PixelFormat::YUYV) => Some(self.nv12_to_yuyv()),
-            (a, b) if a == b => Some(self.clone()),
-            _ => None,
-        }
+        let mut converted = match (self.format, target_format) {
+            (PixelFormat::I420, PixelFormat::YUYV) => self.i420_to_yuyv(),
+            (PixelFormat::NV12, PixelFormat::YUYV) => self.nv12_to_yuyv(),
+            (a, b) if a == b => self.clone(),
+            _ => return None,
+        };
+        converted.normalized_pts_us = self.normalized_pts_us;
+        Some(converted)
     }
 
     /// Convert I420 to YUYV
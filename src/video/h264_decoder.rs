@@ -3,6 +3,7 @@
 //! Wrapper around OpenH264 for decoding H.264 NAL units from Android camera.
 
 use crate::video::frame::{PixelFormat, VideoFrame};
+use crate::video::timeline::PtsNormalizer;
 use openh264::decoder::{Decoder, DecodedYUV};
 use openh264::formats::YUVSource;
 use openh264::Error as OpenH264Error;
@@ -59,6 +60,8 @@ pub struct H264Decoder {
     pps: Option<Vec<u8>>,
     /// Whether decoder is initialized with SPS/PPS
     initialized: bool,
+    /// Maps device timestamps onto a monotonic timeline
+    timeline: PtsNormalizer,
 }
 
 impl H264Decoder {
@@ -75,6 +78,7 @@ impl H264Decoder {
             sps: None,
             pps: None,
             initialized: false,
+            timeline: PtsNormalizer::new(),
         })
     }
 
@@ -125,7 +129,8 @@ impl H264Decoder {
     /// Decode an H.264 NAL unit
     ///
     /// The input should be in Annex B format (with 00 00 00 01 start codes).
-    /// Returns a decoded frame if one is available.
+    /// Returns a decoded frame if one is available, with its
+    /// [`normalized_pts_us`](VideoFrame::normalized_pts_us) set.
     pub fn decode(&mut self, nal_unit: &[u8], timestamp_us: u64) -> Result<Option<VideoFrame>, DecoderError> {
         if !self.initialized {
             warn!("Decoder not initialized, need SPS/PPS first");
@@ -151,7 +156,8 @@ impl H264Decoder {
             self.height = Some(height as u32);
 
             // Convert to VideoFrame (using standalone function to avoid borrow conflict)
-            let frame = yuv_to_frame(yuv, timestamp_us);
+            let normalized = self.timeline.normalize(timestamp_us);
+            let frame = yuv_to_frame(yuv, timestamp_us).with_normalized_pts(normalized);
             Ok(Some(frame))
         } else {
             // Need more data
@@ -227,6 +233,7 @@ impl H264Decoder {
     /// Called when stream restarts or after errors.
     pub fn reset(&mut self) -> Result<(), DecoderError> {
         debug!("Resetting H.264 decoder");
+        self.timeline.reset();
 
         // Create new decoder instance
         self.decoder = Decoder::new()
@@ -38,6 +38,11 @@
 //! # }
 //! ```
 //!
+//! ## Recording
+//!
+//! [`StreamRecorder`] saves the encoded stream to an MP4 (H.264) or WebM
+//! (VP9) file alongside, or instead of, decoding it.
+//!
 //! ## Requirements
 //!
 //! - Linux kernel with V4L2 support
@@ -55,12 +60,20 @@
 
 mod frame;
 mod h264_decoder;
+mod nal;
+mod pacer;
+mod timeline;
 mod v4l2_device;
 mod camera_daemon;
 mod performance;
+mod recorder;
 
 pub use frame::{VideoFrame, PixelFormat};
 pub use h264_decoder::{H264Decoder, DecoderError};
+pub use nal::{split_nal_units, strip_start_code};
+pub use pacer::{FramePacer, DEFAULT_PACER_LATENCY, DEFAULT_PACER_MAX_GAP};
+pub use timeline::{PtsNormalizer, DEFAULT_PTS_WRAP_THRESHOLD_US};
 pub use v4l2_device::{V4l2LoopbackDevice, V4l2Error};
 pub use camera_daemon::{CameraDaemon, CameraDaemonConfig, DaemonError};
 pub use performance::{PerformanceMonitor, PerformanceMetrics, PerformanceStatus};
+pub use recorder::{ContainerFormat, RecorderError, StreamRecorder};
@@ -0,0 +1,121 @@
+//! H.264 Annex B NAL unit splitting
+//!
+//! Android may pack several NAL units (e.g. SPS + PPS + IDR) into a single
+//! frame payload. The decoder expects one NAL unit per call, so payloads are
+//! split on Annex B start codes first.
+
+/// Split an Annex B byte stream into NAL units
+///
+/// Recognizes both 3-byte (`00 00 01`) and 4-byte (`00 00 00 01`) start
+/// codes. Each returned slice starts with its own start code, so it can be
+/// passed to the decoder as-is; trailing zero padding before the next start
+/// code is dropped. Bytes before the first start code are ignored.
+///
+/// Emulation prevention bytes (`00 00 03`) never form a start code, so NAL
+/// payloads that contain them are not split.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// use cosmic_ext_connect_core::video::split_nal_units;
+///
+/// let data = [0, 0, 0, 1, 0x67, 0xAA, 0, 0, 1, 0x68, 0xBB];
+/// let units = split_nal_units(&data);
+/// assert_eq!(units, vec![&data[0..6], &data[6..11]]);
+/// ```
+pub fn split_nal_units(data: &[u8]) -> Vec<&[u8]> {
+    let mut starts = Vec::new();
+    let mut i = 0;
+
+    while i + 3 <= data.len() {
+        if data[i] == 0 && data[i + 1] == 0 && data[i + 2] == 1 {
+            // A preceding zero byte makes this a 4-byte start code
+            let start = if i > 0 && data[i - 1] == 0 { i - 1 } else { i };
+            starts.push(start);
+            i += 3;
+        } else {
+            i += 1;
+        }
+    }
+
+    let mut units = Vec::with_capacity(starts.len());
+    for (index, &start) in starts.iter().enumerate() {
+        let mut end = starts.get(index + 1).copied().unwrap_or(data.len());
+
+        // Drop trailing_zero_8bits padding between units
+        while end > start && data[end - 1] == 0 {
+            end -= 1;
+        }
+
+        let unit = &data[start..end];
+        if unit.len() > start_code_len(unit) {
+            units.push(unit);
+        }
+    }
+
+    units
+}
+
+/// Get the NAL unit without its start code
+///
+/// Returns the input unchanged if it does not begin with a start code.
+pub fn strip_start_code(unit: &[u8]) -> &[u8] {
+    &unit[start_code_len(unit)..]
+}
+
+/// Length of the start code at the beginning of `data` (0, 3 or 4)
+fn start_code_len(data: &[u8]) -> usize {
+    if data.starts_with(&[0, 0, 0, 1]) {
+        4
+    } else if data.starts_with(&[0, 0, 1]) {
+        3
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_mixed_start_codes() {
+        let sps = [0, 0, 0, 1, 0x67, 0x42, 0x00, 0x1F];
+        let pps = [0, 0, 1, 0x68, 0xCE, 0x3C, 0x80];
+        let idr = [0, 0, 0, 1, 0x65, 0x88, 0x84, 0x00, 0x21];
+
+        let mut data = Vec::new();
+        data.extend_from_slice(&sps);
+        data.extend_from_slice(&pps);
+        data.extend_from_slice(&idr);
+
+        let units = split_nal_units(&data);
+        assert_eq!(units.len(), 3);
+        assert_eq!(units[0], &sps[..]);
+        assert_eq!(units[1], &pps[..]);
+        assert_eq!(units[2], &idr[..]);
+
+        assert_eq!(strip_start_code(units[0])[0] & 0x1F, 7);
+        assert_eq!(strip_start_code(units[1])[0] & 0x1F, 8);
+        assert_eq!(strip_start_code(units[2])[0] & 0x1F, 5);
+    }
+
+    #[test]
+    fn test_emulation_prevention_not_split() {
+        // 00 00 03 01 inside the payload is an escaped 00 00 01
+        let data = [0, 0, 0, 1, 0x65, 0x00, 0x00, 0x03, 0x01, 0x42];
+        let units = split_nal_units(&data);
+        assert_eq!(units, vec![&data[..]]);
+    }
+
+    #[test]
+    fn test_split_edge_cases() {
+        assert!(split_nal_units(&[]).is_empty());
+        assert!(split_nal_units(&[0x65, 0x88]).is_empty());
+
+        // Leading garbage, trailing zero padding and empty units are dropped
+        let data = [0xFF, 0, 0, 1, 0x67, 0x01, 0, 0, 0, 0, 1, 0, 0, 1, 0x68];
+        let units = split_nal_units(&data);
+        assert_eq!(units, vec![&data[1..6], &data[11..15]]);
+    }
+}
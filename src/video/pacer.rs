@@ -0,0 +1,278 @@
+//! PTS-Based Frame Pacing
+//!
+//! The decoder emits frames as fast as it can decode them, which makes
+//! playback stutter whenever decode time varies. [`FramePacer`] holds decoded
+//! frames and releases each one at the time its presentation timestamp asks
+//! for, relative to an anchor taken from the first frame of the stream.
+//!
+//! ## Schedule
+//!
+//! A frame with PTS `p` is released at `anchor_time + (p - anchor_pts)`. The
+//! anchor time is the moment the first frame arrived plus a small latency, so
+//! frames that decode slightly late still go out on time.
+//!
+//! The pacer re-anchors on the next frame instead of waiting when:
+//! - the PTS goes backwards (the stream restarted)
+//! - the PTS jumps forward by more than the maximum gap
+//! - a frame arrives more than the maximum gap behind schedule
+//!
+//! Smaller PTS gaps, such as a few dropped frames, keep the schedule, so the
+//! frames after the gap are not delayed.
+//!
+//! Frames are scheduled by [`VideoFrame::presentation_us`], so frames from
+//! the decoder use their normalized PTS and never look like a restart when
+//! the device clock wraps.
+
+use super::frame::VideoFrame;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::time::Instant;
+
+/// Default delay between a frame arriving and its scheduled release
+pub const DEFAULT_PACER_LATENCY: Duration = Duration::from_millis(50);
+
+/// Default PTS jump or lateness that triggers re-anchoring
+pub const DEFAULT_PACER_MAX_GAP: Duration = Duration::from_secs(1);
+
+/// Releases decoded frames according to their presentation timestamps
+///
+/// `push` and `next` take `&self`, so the decoder task and the output task
+/// can share one pacer through an `Arc`.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// use cosmic_ext_connect_core::video::{FramePacer, PixelFormat, VideoFrame};
+///
+/// # async fn example() {
+/// let pacer = FramePacer::new();
+/// pacer.push(VideoFrame::new(1280, 720, PixelFormat::I420, 0));
+/// pacer.push(VideoFrame::new(1280, 720, PixelFormat::I420, 33_333));
+///
+/// let first = pacer.next().await;
+/// let second = pacer.next().await; // about 33ms later
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct FramePacer {
+    /// Queue and anchor
+    state: Mutex<PacerState>,
+    /// Wakes `next` when a frame is pushed
+    pushed: Notify,
+    /// Delay added to the anchor to absorb decode jitter
+    latency: Duration,
+    /// PTS jump or lateness that triggers re-anchoring
+    max_gap: Duration,
+}
+
+#[derive(Debug, Default)]
+struct PacerState {
+    /// Frames waiting for release, with their release times
+    queue: VecDeque<(Instant, VideoFrame)>,
+    /// PTS and release time the schedule is measured from
+    anchor: Option<(u64, Instant)>,
+    /// PTS of the most recently pushed frame
+    last_pts: Option<u64>,
+}
+
+impl Default for FramePacer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FramePacer {
+    /// Create a pacer with [`DEFAULT_PACER_LATENCY`] and [`DEFAULT_PACER_MAX_GAP`]
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(PacerState::default()),
+            pushed: Notify::new(),
+            latency: DEFAULT_PACER_LATENCY,
+            max_gap: DEFAULT_PACER_MAX_GAP,
+        }
+    }
+
+    /// Set the delay added to the anchor
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Set the PTS jump or lateness that triggers re-anchoring
+    pub fn with_max_gap(mut self, max_gap: Duration) -> Self {
+        self.max_gap = max_gap;
+        self
+    }
+
+    /// Queue a decoded frame for release at its presentation time
+    pub fn push(&self, frame: VideoFrame) {
+        self.push_at(frame, Instant::now());
+    }
+
+    /// Queue a decoded frame as if it arrived at `now`
+    pub fn push_at(&self, frame: VideoFrame, now: Instant) {
+        let mut state = self.state.lock().unwrap();
+        let pts = frame.presentation_us();
+        let max_gap_us = self.max_gap.as_micros() as u64;
+
+        let jumped = state
+            .last_pts
+            .is_some_and(|last| pts < last || pts - last > max_gap_us);
+
+        let mut release_at = match state.anchor {
+            Some((anchor_pts, anchor_at)) if !jumped => {
+                anchor_at + Duration::from_micros(pts - anchor_pts)
+            }
+            _ => self.reanchor(&mut state, pts, now),
+        };
+
+        if release_at + self.max_gap < now {
+            // Fell far behind schedule (e.g. the decoder stalled)
+            release_at = self.reanchor(&mut state, pts, now);
+        }
+
+        state.last_pts = Some(pts);
+        state.queue.push_back((release_at, frame));
+        drop(state);
+
+        self.pushed.notify_one();
+    }
+
+    fn reanchor(&self, state: &mut PacerState, pts: u64, now: Instant) -> Instant {
+        let release_at = now + self.latency;
+        state.anchor = Some((pts, release_at));
+        release_at
+    }
+
+    /// Wait for the next frame's presentation time and return it
+    pub async fn next(&self) -> VideoFrame {
+        loop {
+            let pushed = self.pushed.notified();
+
+            let deadline = {
+                let mut state = self.state.lock().unwrap();
+                match state.queue.front() {
+                    Some(&(release_at, _)) if release_at <= Instant::now() => {
+                        return state.queue.pop_front().unwrap().1;
+                    }
+                    Some(&(release_at, _)) => Some(release_at),
+                    None => None,
+                }
+            };
+
+            match deadline {
+                Some(release_at) => tokio::time::sleep_until(release_at).await,
+                None => pushed.await,
+            }
+        }
+    }
+
+    /// Number of frames waiting for release
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().queue.len()
+    }
+
+    /// Check whether no frames are waiting
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drop queued frames and forget the anchor
+    pub fn reset(&self) {
+        let mut state = self.state.lock().unwrap();
+        *state = PacerState::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::video::PixelFormat;
+
+    // 25fps, so release times land on whole milliseconds
+    const FRAME_US: u64 = 40_000;
+
+    fn frame(pts: u64) -> VideoFrame {
+        VideoFrame::from_data(2, 2, PixelFormat::I420, pts, Vec::new())
+    }
+
+    async fn release_offsets(pacer: &FramePacer, count: usize, start: Instant) -> Vec<u128> {
+        let mut offsets = Vec::new();
+        for _ in 0..count {
+            pacer.next().await;
+            offsets.push((Instant::now() - start).as_millis());
+        }
+        offsets
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_releases_at_pts_cadence() {
+        let pacer = FramePacer::new().with_latency(Duration::ZERO);
+        let start = Instant::now();
+
+        // Frames decoded in a burst are spread out by PTS
+        for i in 0..4 {
+            pacer.push(frame(i * FRAME_US));
+        }
+        assert_eq!(release_offsets(&pacer, 4, start).await, vec![0, 40, 80, 120]);
+        assert!(pacer.is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_gap_keeps_schedule_and_reset_reanchors() {
+        let pacer = FramePacer::new()
+            .with_latency(Duration::from_millis(10))
+            .with_max_gap(Duration::from_millis(500));
+        let start = Instant::now();
+
+        // Two dropped frames: the next frame keeps its slot, no stall
+        pacer.push(frame(0));
+        pacer.push(frame(3 * FRAME_US));
+        assert_eq!(release_offsets(&pacer, 2, start).await, vec![10, 130]);
+
+        // Stream restart: PTS goes back to 0 and is released after the latency
+        let restart = Instant::now();
+        pacer.push(frame(0));
+        pacer.push(frame(FRAME_US));
+        assert_eq!(release_offsets(&pacer, 2, restart).await, vec![10, 50]);
+
+        // A large forward jump re-anchors instead of waiting
+        let jump = Instant::now();
+        pacer.push(frame(60_000_000));
+        assert_eq!(release_offsets(&pacer, 1, jump).await, vec![10]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_schedules_by_normalized_pts() {
+        let pacer = FramePacer::new().with_latency(Duration::ZERO);
+        let mut timeline = crate::video::PtsNormalizer::new();
+        let start = Instant::now();
+
+        // The raw clock wraps between the second and third frame
+        for raw in [u32::MAX as u64 - FRAME_US, u32::MAX as u64, 10_000, 50_000] {
+            pacer.push(frame(raw).with_normalized_pts(timeline.normalize(raw)));
+        }
+        assert_eq!(release_offsets(&pacer, 4, start).await, vec![0, 40, 80, 120]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_next_waits_for_push() {
+        let pacer = std::sync::Arc::new(FramePacer::new().with_latency(Duration::ZERO));
+        let start = Instant::now();
+
+        let producer = {
+            let pacer = pacer.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                pacer.push(frame(0));
+            })
+        };
+
+        let released = pacer.next().await;
+        assert_eq!(released.timestamp_us, 0);
+        assert_eq!((Instant::now() - start).as_millis(), 20);
+        producer.await.unwrap();
+    }
+}
@@ -0,0 +1,998 @@
+//! Stream Recording
+//!
+//! [`StreamRecorder`] writes the encoded camera stream to disk as it
+//! arrives, so a virtual webcam session can also be saved. Frames are muxed
+//! without re-encoding:
+//!
+//! - H.264 goes into a fragmented MP4. The `ftyp`/`moov` header is written
+//!   once the stream's SPS and PPS are known (they become the `avcC` record),
+//!   then every frame is written as its own `moof`/`mdat` fragment.
+//! - VP9 goes into WebM, one cluster per keyframe.
+//!
+//! Nothing is held in memory beyond the frame being written (MP4 holds one
+//! frame back, because a sample's duration is only known once the next PTS
+//! arrives). Frames before the first keyframe are dropped, since they cannot
+//! be decoded.
+//!
+//! [`StreamRecorder::finish`] finalizes the container: it writes the held
+//! frame and seeks back to fill in the total duration and, for WebM, the
+//! element sizes. A recording that is never finished is still playable up to
+//! its last complete fragment or cluster.
+//!
+//! The first SPS and PPS are kept for the whole recording; a stream that
+//! changes resolution should be recorded to a new file.
+
+use super::nal::{split_nal_units, strip_start_code};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+use tracing::debug;
+
+/// Timescale of MP4 timestamps (microseconds, matching frame PTS)
+const MP4_TIMESCALE: u32 = 1_000_000;
+
+/// WebM timestamp unit in nanoseconds (milliseconds)
+const WEBM_TIMECODE_SCALE: u64 = 1_000_000;
+
+/// Duration given to the last frame when no frame interval was seen (30fps)
+const DEFAULT_FRAME_DURATION_US: u64 = 33_333;
+
+/// Application name written into WebM headers
+const MUXING_APP: &str = "cosmic-ext-connect-core";
+
+/// 8-byte EBML size meaning "unknown", patched when the element is closed
+const EBML_UNKNOWN_SIZE: [u8; 8] = [0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF];
+
+/// Error types for stream recording
+#[derive(Debug)]
+pub enum RecorderError {
+    /// Writing the output failed
+    Io(io::Error),
+    /// A frame is too large for the container
+    FrameTooLarge(usize),
+    /// The stream's dimensions do not fit the container
+    DimensionsTooLarge(u32, u32),
+    /// The stream's codec configuration cannot be stored in the container
+    InvalidCodecConfig(String),
+}
+
+impl fmt::Display for RecorderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecorderError::Io(e) => write!(f, "Recording I/O error: {}", e),
+            RecorderError::FrameTooLarge(size) => {
+                write!(f, "Frame of {} bytes is too large to record", size)
+            }
+            RecorderError::DimensionsTooLarge(width, height) => {
+                write!(f, "Dimensions {}x{} are too large to record", width, height)
+            }
+            RecorderError::InvalidCodecConfig(reason) => {
+                write!(f, "Invalid codec configuration: {}", reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RecorderError {}
+
+impl From<io::Error> for RecorderError {
+    fn from(e: io::Error) -> Self {
+        RecorderError::Io(e)
+    }
+}
+
+/// Container written by a [`StreamRecorder`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContainerFormat {
+    /// Fragmented MP4 holding H.264
+    Mp4,
+    /// WebM holding VP9
+    WebM,
+}
+
+impl ContainerFormat {
+    /// Get the container for a camera codec name (`"h264"` or `"vp9"`)
+    pub fn for_codec(codec: &str) -> Option<Self> {
+        match codec.to_ascii_lowercase().as_str() {
+            "h264" | "avc" => Some(ContainerFormat::Mp4),
+            "vp9" => Some(ContainerFormat::WebM),
+            _ => None,
+        }
+    }
+
+    /// Get the file extension for this container
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ContainerFormat::Mp4 => "mp4",
+            ContainerFormat::WebM => "webm",
+        }
+    }
+}
+
+/// A frame waiting for the next PTS to give its duration (MP4)
+#[derive(Debug)]
+struct PendingSample {
+    /// Decode time relative to the first frame, in microseconds
+    time_us: u64,
+    /// Length-prefixed NAL units
+    data: Vec<u8>,
+    keyframe: bool,
+}
+
+/// An open WebM cluster
+#[derive(Debug, Clone, Copy)]
+struct Cluster {
+    /// Offset of the cluster's size field
+    size_offset: u64,
+    /// Cluster timestamp in milliseconds
+    timecode_ms: u64,
+}
+
+/// Container-specific muxing state
+#[derive(Debug)]
+enum Muxer {
+    Mp4 {
+        /// SPS NAL unit without start code
+        sps: Option<Vec<u8>>,
+        /// PPS NAL unit without start code
+        pps: Option<Vec<u8>>,
+        /// Offset of the `mehd` fragment duration
+        duration_offset: u64,
+        /// Last `moof` sequence number written
+        sequence: u32,
+        pending: Option<PendingSample>,
+    },
+    WebM {
+        /// Offset of the Segment size field
+        segment_size_offset: u64,
+        /// Offset of the Info Duration value
+        duration_offset: u64,
+        cluster: Option<Cluster>,
+    },
+}
+
+/// Records an encoded camera stream to an MP4 or WebM file
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// use cosmic_ext_connect_core::video::{ContainerFormat, StreamRecorder};
+///
+/// # fn example(frames: Vec<(Vec<u8>, u64, bool)>) -> Result<(), Box<dyn std::error::Error>> {
+/// let mut recorder =
+///     StreamRecorder::create("/tmp/camera.mp4", ContainerFormat::Mp4, 1280, 720)?;
+/// for (data, pts_us, keyframe) in frames {
+///     recorder.write_frame(&data, pts_us, keyframe)?;
+/// }
+/// recorder.finish()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct StreamRecorder<W: Write + Seek> {
+    /// Output
+    writer: W,
+    /// Frame width in pixels
+    width: u32,
+    /// Frame height in pixels
+    height: u32,
+    /// Container state
+    muxer: Muxer,
+    /// Whether the container header has been written
+    started: bool,
+    /// PTS of the first recorded frame
+    first_pts_us: Option<u64>,
+    /// Time of the last recorded frame, relative to the first
+    last_time_us: u64,
+    /// Spacing between the last two recorded frames
+    frame_duration_us: u64,
+    /// Number of frames recorded
+    frame_count: u64,
+}
+
+impl StreamRecorder<BufWriter<File>> {
+    /// Create a recorder writing to a new file at `path`
+    pub fn create(
+        path: impl AsRef<Path>,
+        format: ContainerFormat,
+        width: u32,
+        height: u32,
+    ) -> Result<Self, RecorderError> {
+        let file = File::create(path)?;
+        Ok(Self::new(BufWriter::new(file), format, width, height))
+    }
+}
+
+impl<W: Write + Seek> StreamRecorder<W> {
+    /// Create a recorder writing to `writer`
+    ///
+    /// Nothing is written until the first keyframe arrives.
+    pub fn new(writer: W, format: ContainerFormat, width: u32, height: u32) -> Self {
+        let muxer = match format {
+            ContainerFormat::Mp4 => Muxer::Mp4 {
+                sps: None,
+                pps: None,
+                duration_offset: 0,
+                sequence: 0,
+                pending: None,
+            },
+            ContainerFormat::WebM => Muxer::WebM {
+                segment_size_offset: 0,
+                duration_offset: 0,
+                cluster: None,
+            },
+        };
+
+        Self {
+            writer,
+            width,
+            height,
+            muxer,
+            started: false,
+            first_pts_us: None,
+            last_time_us: 0,
+            frame_duration_us: DEFAULT_FRAME_DURATION_US,
+            frame_count: 0,
+        }
+    }
+
+    /// Get the container being written
+    pub fn format(&self) -> ContainerFormat {
+        match self.muxer {
+            Muxer::Mp4 { .. } => ContainerFormat::Mp4,
+            Muxer::WebM { .. } => ContainerFormat::WebM,
+        }
+    }
+
+    /// Get the number of frames recorded
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// Record one encoded frame
+    ///
+    /// `data` is an Annex B access unit for MP4 or a VP9 frame for WebM.
+    /// H.264 SPS and PPS units are taken for the container header and not
+    /// stored as frames, so a parameter-set-only frame records nothing.
+    ///
+    /// Returns whether the frame was recorded; frames before the first
+    /// keyframe (and, for MP4, before the SPS and PPS) are dropped.
+    /// Timestamps that do not increase are moved just past the previous
+    /// frame.
+    pub fn write_frame(
+        &mut self,
+        data: &[u8],
+        pts_us: u64,
+        keyframe: bool,
+    ) -> Result<bool, RecorderError> {
+        let sample = match &mut self.muxer {
+            Muxer::Mp4 { sps, pps, .. } => {
+                let sample = avcc_sample(data, sps, pps);
+                if sample.is_empty() || sps.is_none() || pps.is_none() {
+                    return Ok(false);
+                }
+                sample
+            }
+            Muxer::WebM { .. } => data.to_vec(),
+        };
+
+        if !self.started {
+            if !keyframe {
+                debug!("Dropping frame before first keyframe");
+                return Ok(false);
+            }
+            self.write_header()?;
+            self.started = true;
+        }
+
+        let first_pts_us = *self.first_pts_us.get_or_insert(pts_us);
+        let mut time_us = pts_us.saturating_sub(first_pts_us);
+        if self.frame_count > 0 {
+            time_us = time_us.max(self.last_time_us + 1);
+            self.frame_duration_us = time_us - self.last_time_us;
+        }
+
+        match self.muxer {
+            Muxer::Mp4 { .. } => self.push_mp4_sample(PendingSample {
+                time_us,
+                data: sample,
+                keyframe,
+            })?,
+            Muxer::WebM { .. } => self.write_webm_block(&sample, time_us, keyframe)?,
+        }
+
+        self.last_time_us = time_us;
+        self.frame_count += 1;
+        Ok(true)
+    }
+
+    /// Finalize the container and return the writer
+    ///
+    /// A recording that never received a keyframe leaves the output empty.
+    pub fn finish(mut self) -> Result<W, RecorderError> {
+        if self.started {
+            let duration_us = self.last_time_us + self.frame_duration_us;
+            match self.muxer {
+                Muxer::Mp4 {
+                    ref mut pending,
+                    duration_offset,
+                    ..
+                } => {
+                    if let Some(sample) = pending.take() {
+                        let duration = self.frame_duration_us;
+                        self.write_mp4_fragment(sample, duration)?;
+                    }
+                    self.patch(duration_offset, &duration_us.to_be_bytes())?;
+                }
+                Muxer::WebM {
+                    segment_size_offset,
+                    duration_offset,
+                    ..
+                } => {
+                    self.close_cluster()?;
+                    let end = self.writer.stream_position()?;
+                    let size = end - segment_size_offset - 8;
+                    self.patch(segment_size_offset, &ebml_size8(size))?;
+                    let duration_ms = duration_us as f64 / 1000.0;
+                    self.patch(duration_offset, &duration_ms.to_be_bytes())?;
+                }
+            }
+        }
+
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    /// Write the container header
+    fn write_header(&mut self) -> Result<(), RecorderError> {
+        let start = self.writer.stream_position()?;
+        match &mut self.muxer {
+            Muxer::Mp4 {
+                sps,
+                pps,
+                duration_offset,
+                ..
+            } => {
+                let (Some(sps), Some(pps)) = (sps.as_deref(), pps.as_deref()) else {
+                    return Ok(());
+                };
+                // Major brand iso5, minor version 512, compatible brands
+                let ftyp = mp4_box(b"ftyp", b"iso5\0\0\x02\0iso5iso6mp41");
+                let moov = mp4_moov(self.width, self.height, sps, pps)?;
+
+                // mehd is the last box in moov and ends with its duration
+                *duration_offset = start + (ftyp.len() + moov.len()) as u64 - 8;
+                self.writer.write_all(&ftyp)?;
+                self.writer.write_all(&moov)?;
+            }
+            Muxer::WebM {
+                segment_size_offset,
+                duration_offset,
+                ..
+            } => {
+                let header = ebml_element(
+                    0x1A45DFA3,
+                    &[
+                        ebml_uint(0x4286, 1),
+                        ebml_uint(0x42F7, 1),
+                        ebml_uint(0x42F2, 4),
+                        ebml_uint(0x42F3, 8),
+                        ebml_element(0x4282, b"webm"),
+                        ebml_uint(0x4287, 4),
+                        ebml_uint(0x4285, 2),
+                    ]
+                    .concat(),
+                );
+
+                // Duration is the last element in Info
+                let info = ebml_element(
+                    0x1549A966,
+                    &[
+                        ebml_uint(0x2AD7B1, WEBM_TIMECODE_SCALE),
+                        ebml_element(0x4D80, MUXING_APP.as_bytes()),
+                        ebml_element(0x5741, MUXING_APP.as_bytes()),
+                        ebml_element(0x4489, &0f64.to_be_bytes()),
+                    ]
+                    .concat(),
+                );
+
+                let video = ebml_element(
+                    0xE0,
+                    &[
+                        ebml_uint(0xB0, u64::from(self.width)),
+                        ebml_uint(0xBA, u64::from(self.height)),
+                    ]
+                    .concat(),
+                );
+                let tracks = ebml_element(
+                    0x1654AE6B,
+                    &ebml_element(
+                        0xAE,
+                        &[
+                            ebml_uint(0xD7, 1),
+                            ebml_uint(0x73C5, 1),
+                            ebml_uint(0x83, 1),
+                            ebml_element(0x86, b"V_VP9"),
+                            video,
+                        ]
+                        .concat(),
+                    ),
+                );
+
+                *segment_size_offset = start + header.len() as u64 + 4;
+                *duration_offset = *segment_size_offset + 8 + info.len() as u64 - 8;
+                self.writer.write_all(&header)?;
+                self.writer.write_all(&id_bytes(0x18538067))?;
+                self.writer.write_all(&EBML_UNKNOWN_SIZE)?;
+                self.writer.write_all(&info)?;
+                self.writer.write_all(&tracks)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Hold an MP4 sample, writing the previously held one
+    fn push_mp4_sample(&mut self, sample: PendingSample) -> Result<(), RecorderError> {
+        let Muxer::Mp4 { pending, .. } = &mut self.muxer else {
+            return Ok(());
+        };
+        let time_us = sample.time_us;
+        if let Some(previous) = pending.replace(sample) {
+            let duration = time_us - previous.time_us;
+            self.write_mp4_fragment(previous, duration)?;
+        }
+        Ok(())
+    }
+
+    /// Write one sample as a `moof`/`mdat` fragment
+    fn write_mp4_fragment(
+        &mut self,
+        sample: PendingSample,
+        duration_us: u64,
+    ) -> Result<(), RecorderError> {
+        let Muxer::Mp4 { sequence, .. } = &mut self.muxer else {
+            return Ok(());
+        };
+        let mdat_size = u32::try_from(sample.data.len() + 8)
+            .map_err(|_| RecorderError::FrameTooLarge(sample.data.len()))?;
+        let duration = u32::try_from(duration_us).unwrap_or(u32::MAX);
+        *sequence += 1;
+
+        // sample_depends_on = 2 for sync samples; 1 plus non-sync otherwise
+        let flags: u32 = if sample.keyframe {
+            0x0200_0000
+        } else {
+            0x0101_0000
+        };
+
+        let mfhd = full_box(b"mfhd", 0, 0, &sequence.to_be_bytes());
+        // default-base-is-moof
+        let tfhd = full_box(b"tfhd", 0, 0x02_0000, &1u32.to_be_bytes());
+        let tfdt = full_box(b"tfdt", 1, 0, &sample.time_us.to_be_bytes());
+        // data_offset, sample_duration, sample_size and sample_flags present
+        let trun = full_box(
+            b"trun",
+            0,
+            0x0701,
+            &[1u32, 0, duration, mdat_size - 8, flags]
+                .iter()
+                .flat_map(|v| v.to_be_bytes())
+                .collect::<Vec<_>>(),
+        );
+        let traf = mp4_box(b"traf", &[tfhd, tfdt, trun].concat());
+        let mut moof = mp4_box(b"moof", &[mfhd, traf].concat());
+
+        // The data offset (the trun field before the last three) counts
+        // from the start of the moof to the first byte of mdat data
+        let data_offset = moof.len() as u32 + 8;
+        let at = moof.len() - 16;
+        moof[at..at + 4].copy_from_slice(&data_offset.to_be_bytes());
+
+        self.writer.write_all(&moof)?;
+        self.writer.write_all(&mdat_size.to_be_bytes())?;
+        self.writer.write_all(b"mdat")?;
+        self.writer.write_all(&sample.data)?;
+        Ok(())
+    }
+
+    /// Write a VP9 frame as a WebM SimpleBlock
+    fn write_webm_block(
+        &mut self,
+        data: &[u8],
+        time_us: u64,
+        keyframe: bool,
+    ) -> Result<(), RecorderError> {
+        let time_ms = time_us / 1000;
+        let cluster = match self.muxer {
+            Muxer::WebM { cluster, .. } => cluster,
+            Muxer::Mp4 { .. } => return Ok(()),
+        };
+
+        // Block timestamps are signed 16-bit offsets from the cluster
+        let cluster = match cluster {
+            Some(cluster) if !keyframe && time_ms - cluster.timecode_ms <= i16::MAX as u64 => {
+                cluster
+            }
+            _ => {
+                self.close_cluster()?;
+                let size_offset = self.writer.stream_position()? + 4;
+                self.writer.write_all(&id_bytes(0x1F43B675))?;
+                self.writer.write_all(&EBML_UNKNOWN_SIZE)?;
+                self.writer.write_all(&ebml_uint(0xE7, time_ms))?;
+                let cluster = Cluster {
+                    size_offset,
+                    timecode_ms: time_ms,
+                };
+                if let Muxer::WebM { cluster: open, .. } = &mut self.muxer {
+                    *open = Some(cluster);
+                }
+                cluster
+            }
+        };
+
+        let offset = (time_ms - cluster.timecode_ms) as i16;
+        let mut block = Vec::with_capacity(data.len() + 4);
+        block.push(0x81); // track 1
+        block.extend_from_slice(&offset.to_be_bytes());
+        block.push(if keyframe { 0x80 } else { 0x00 });
+        block.extend_from_slice(data);
+
+        self.writer.write_all(&ebml_element(0xA3, &block))?;
+        Ok(())
+    }
+
+    /// Fill in the size of the open WebM cluster
+    fn close_cluster(&mut self) -> Result<(), RecorderError> {
+        let Muxer::WebM { cluster, .. } = &mut self.muxer else {
+            return Ok(());
+        };
+        if let Some(cluster) = cluster.take() {
+            let end = self.writer.stream_position()?;
+            let size = end - cluster.size_offset - 8;
+            self.patch(cluster.size_offset, &ebml_size8(size))?;
+        }
+        Ok(())
+    }
+
+    /// Overwrite bytes at `offset` and return to the end of the output
+    fn patch(&mut self, offset: u64, bytes: &[u8]) -> Result<(), RecorderError> {
+        let end = self.writer.stream_position()?;
+        self.writer.seek(SeekFrom::Start(offset))?;
+        self.writer.write_all(bytes)?;
+        self.writer.seek(SeekFrom::Start(end))?;
+        Ok(())
+    }
+}
+
+/// Convert an Annex B access unit to 4-byte length-prefixed NAL units
+///
+/// SPS and PPS units are stored in `sps`/`pps` (first seen wins) and access
+/// unit delimiters are dropped.
+fn avcc_sample(data: &[u8], sps: &mut Option<Vec<u8>>, pps: &mut Option<Vec<u8>>) -> Vec<u8> {
+    let mut sample = Vec::with_capacity(data.len());
+    for unit in split_nal_units(data) {
+        let nal = strip_start_code(unit);
+        match nal[0] & 0x1F {
+            7 => {
+                sps.get_or_insert_with(|| nal.to_vec());
+            }
+            8 => {
+                pps.get_or_insert_with(|| nal.to_vec());
+            }
+            9 => {}
+            _ => {
+                sample.extend_from_slice(&(nal.len() as u32).to_be_bytes());
+                sample.extend_from_slice(nal);
+            }
+        }
+    }
+    sample
+}
+
+// =============================================================================
+// MP4 boxes
+// =============================================================================
+
+/// Build an MP4 box
+fn mp4_box(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(body.len() + 8);
+    out.extend_from_slice(&(body.len() as u32 + 8).to_be_bytes());
+    out.extend_from_slice(kind);
+    out.extend_from_slice(body);
+    out
+}
+
+/// Build an MP4 full box (with version and flags)
+fn full_box(kind: &[u8; 4], version: u8, flags: u32, body: &[u8]) -> Vec<u8> {
+    let mut full = Vec::with_capacity(body.len() + 4);
+    full.push(version);
+    full.extend_from_slice(&flags.to_be_bytes()[1..]);
+    full.extend_from_slice(body);
+    mp4_box(kind, &full)
+}
+
+/// Identity transformation matrix used by `mvhd` and `tkhd`
+fn unity_matrix() -> Vec<u8> {
+    [0x0001_0000u32, 0, 0, 0, 0x0001_0000, 0, 0, 0, 0x4000_0000]
+        .iter()
+        .flat_map(|v| v.to_be_bytes())
+        .collect()
+}
+
+/// Build the `moov` box for a single H.264 track of unknown duration
+///
+/// Fails if the dimensions or parameter sets do not fit their fields, or the
+/// SPS is too short to carry the profile and level.
+fn mp4_moov(width: u32, height: u32, sps: &[u8], pps: &[u8]) -> Result<Vec<u8>, RecorderError> {
+    let (Ok(width), Ok(height)) = (u16::try_from(width), u16::try_from(height)) else {
+        return Err(RecorderError::DimensionsTooLarge(width, height));
+    };
+    let &[_, profile, compatibility, level, ..] = sps else {
+        return Err(RecorderError::InvalidCodecConfig(format!(
+            "SPS of {} bytes is too short",
+            sps.len()
+        )));
+    };
+    let parameter_set_len = |kind: &str, set: &[u8]| {
+        u16::try_from(set.len()).map_err(|_| {
+            RecorderError::InvalidCodecConfig(format!(
+                "{} of {} bytes is too long",
+                kind,
+                set.len()
+            ))
+        })
+    };
+    let sps_len = parameter_set_len("SPS", sps)?;
+    let pps_len = parameter_set_len("PPS", pps)?;
+
+    let mvhd = full_box(
+        b"mvhd",
+        0,
+        0,
+        &[
+            &[0u8; 8][..], // creation and modification time
+            &MP4_TIMESCALE.to_be_bytes(),
+            &0u32.to_be_bytes(),           // duration
+            &0x0001_0000u32.to_be_bytes(), // rate 1.0
+            &0x0100u16.to_be_bytes(),      // volume 1.0
+            &[0u8; 10],
+            &unity_matrix(),
+            &[0u8; 24],
+            &2u32.to_be_bytes(), // next track ID
+        ]
+        .concat(),
+    );
+
+    // Track enabled and in movie
+    let tkhd = full_box(
+        b"tkhd",
+        0,
+        0x03,
+        &[
+            &[0u8; 8][..],
+            &1u32.to_be_bytes(), // track ID
+            &[0u8; 4],
+            &0u32.to_be_bytes(), // duration
+            &[0u8; 16],          // reserved, layer, group, volume
+            &unity_matrix(),
+            &(u32::from(width) << 16).to_be_bytes(),
+            &(u32::from(height) << 16).to_be_bytes(),
+        ]
+        .concat(),
+    );
+
+    let mdhd = full_box(
+        b"mdhd",
+        0,
+        0,
+        &[
+            &[0u8; 8][..],
+            &MP4_TIMESCALE.to_be_bytes(),
+            &0u32.to_be_bytes(),
+            &0x55C4u16.to_be_bytes(), // language "und"
+            &[0u8; 2],
+        ]
+        .concat(),
+    );
+    let hdlr = full_box(
+        b"hdlr",
+        0,
+        0,
+        &[&[0u8; 4][..], b"vide", &[0u8; 12], b"VideoHandler\0"].concat(),
+    );
+
+    let avcc = mp4_box(
+        b"avcC",
+        &[
+            // version, profile, compatibility, level, 4-byte lengths, 1 SPS
+            &[1, profile, compatibility, level, 0xFF, 0xE1][..],
+            &sps_len.to_be_bytes(),
+            sps,
+            &[1],
+            &pps_len.to_be_bytes(),
+            pps,
+        ]
+        .concat(),
+    );
+    let avc1 = mp4_box(
+        b"avc1",
+        &[
+            &[0u8; 6][..],
+            &1u16.to_be_bytes(), // data reference index
+            &[0u8; 16],
+            &width.to_be_bytes(),
+            &height.to_be_bytes(),
+            &0x0048_0000u32.to_be_bytes(), // 72 dpi
+            &0x0048_0000u32.to_be_bytes(),
+            &[0u8; 4],
+            &1u16.to_be_bytes(),      // frame count
+            &[0u8; 32],               // compressor name
+            &0x0018u16.to_be_bytes(), // depth
+            &(-1i16).to_be_bytes(),
+            &avcc,
+        ]
+        .concat(),
+    );
+
+    let empty_table = |kind: &[u8; 4]| full_box(kind, 0, 0, &0u32.to_be_bytes());
+    let stbl = mp4_box(
+        b"stbl",
+        &[
+            full_box(b"stsd", 0, 0, &[&1u32.to_be_bytes()[..], &avc1].concat()),
+            empty_table(b"stts"),
+            empty_table(b"stsc"),
+            full_box(b"stsz", 0, 0, &[0u8; 8]),
+            empty_table(b"stco"),
+        ]
+        .concat(),
+    );
+    let dref = full_box(
+        b"dref",
+        0,
+        0,
+        &[&1u32.to_be_bytes()[..], &full_box(b"url ", 0, 1, &[])].concat(),
+    );
+    let minf = mp4_box(
+        b"minf",
+        &[
+            full_box(b"vmhd", 0, 1, &[0u8; 8]),
+            mp4_box(b"dinf", &dref),
+            stbl,
+        ]
+        .concat(),
+    );
+    let mdia = mp4_box(b"mdia", &[mdhd, hdlr, minf].concat());
+    let trak = mp4_box(b"trak", &[tkhd, mdia].concat());
+
+    let trex = full_box(
+        b"trex",
+        0,
+        0,
+        &[1u32, 1, 0, 0, 0]
+            .iter()
+            .flat_map(|v| v.to_be_bytes())
+            .collect::<Vec<_>>(),
+    );
+    // Fragment duration, filled in by finish()
+    let mehd = full_box(b"mehd", 1, 0, &0u64.to_be_bytes());
+    let mvex = mp4_box(b"mvex", &[trex, mehd].concat());
+
+    Ok(mp4_box(b"moov", &[mvhd, trak, mvex].concat()))
+}
+
+// =============================================================================
+// EBML elements
+// =============================================================================
+
+/// Encode an element ID (IDs carry their own length marker)
+fn id_bytes(id: u32) -> Vec<u8> {
+    let bytes = id.to_be_bytes();
+    let skip = bytes.iter().take_while(|&&b| b == 0).count().min(3);
+    bytes[skip..].to_vec()
+}
+
+/// Encode a size as the shortest EBML variable-length integer
+fn ebml_size(size: u64) -> Vec<u8> {
+    let len = (1..=8)
+        .find(|&len| size < (1u64 << (7 * len)) - 1)
+        .unwrap_or(8);
+    let marked = size | (1u64 << (7 * len));
+    marked.to_be_bytes()[8 - len..].to_vec()
+}
+
+/// Encode a size as an 8-byte EBML variable-length integer
+fn ebml_size8(size: u64) -> [u8; 8] {
+    (size | (1u64 << 56)).to_be_bytes()
+}
+
+/// Build an EBML element
+fn ebml_element(id: u32, body: &[u8]) -> Vec<u8> {
+    let mut out = id_bytes(id);
+    out.extend_from_slice(&ebml_size(body.len() as u64));
+    out.extend_from_slice(body);
+    out
+}
+
+/// Build an unsigned integer EBML element
+fn ebml_uint(id: u32, value: u64) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    let skip = bytes.iter().take_while(|&&b| b == 0).count().min(7);
+    ebml_element(id, &bytes[skip..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const SPS: [u8; 8] = [0x67, 0x42, 0x00, 0x1F, 0xAC, 0xD9, 0x40, 0x50];
+    const PPS: [u8; 4] = [0x68, 0xCE, 0x3C, 0x80];
+
+    fn annex_b(units: &[&[u8]]) -> Vec<u8> {
+        units
+            .iter()
+            .flat_map(|unit| [&[0, 0, 0, 1][..], unit].concat())
+            .collect()
+    }
+
+    /// Top-level MP4 boxes as (type, body)
+    fn mp4_boxes(data: &[u8]) -> Vec<([u8; 4], &[u8])> {
+        let mut boxes = Vec::new();
+        let mut rest = data;
+        while rest.len() >= 8 {
+            let size = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+            boxes.push((rest[4..8].try_into().unwrap(), &rest[8..size]));
+            rest = &rest[size..];
+        }
+        boxes
+    }
+
+    /// Read an EBML ID or size, returning (value with marker, length)
+    fn read_vint(data: &[u8], keep_marker: bool) -> (u64, usize) {
+        let len = data[0].leading_zeros() as usize + 1;
+        let mut value = u64::from(data[0]);
+        if !keep_marker {
+            value &= (1u64 << (8 - len)) - 1;
+        }
+        for &byte in &data[1..len] {
+            value = (value << 8) | u64::from(byte);
+        }
+        (value, len)
+    }
+
+    /// EBML elements in `data` as (id, body)
+    fn ebml_children(data: &[u8]) -> Vec<(u64, &[u8])> {
+        let mut children = Vec::new();
+        let mut rest = data;
+        while !rest.is_empty() {
+            let (id, id_len) = read_vint(rest, true);
+            let (size, size_len) = read_vint(&rest[id_len..], false);
+            let start = id_len + size_len;
+            let end = start + size as usize;
+            children.push((id, &rest[start..end]));
+            rest = &rest[end..];
+        }
+        children
+    }
+
+    #[test]
+    fn test_record_h264_to_mp4() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("camera.mp4");
+        let mut recorder = StreamRecorder::create(&path, ContainerFormat::Mp4, 1280, 720).unwrap();
+
+        // P frame before the stream is configured is dropped
+        let p_frame = annex_b(&[&[0x41, 0x9A, 0x02]]);
+        assert!(!recorder.write_frame(&p_frame, 900_000, false).unwrap());
+        assert!(!recorder
+            .write_frame(&annex_b(&[&SPS, &PPS]), 1_000_000, false)
+            .unwrap());
+
+        let idr = annex_b(&[&[0x65, 0x88, 0x84, 0x21]]);
+        assert!(recorder.write_frame(&idr, 1_000_000, true).unwrap());
+        assert!(recorder.write_frame(&p_frame, 1_033_333, false).unwrap());
+        assert!(recorder.write_frame(&p_frame, 1_066_666, false).unwrap());
+        assert_eq!(recorder.frame_count(), 3);
+        recorder.finish().unwrap();
+
+        let data = std::fs::read(&path).unwrap();
+        let boxes = mp4_boxes(&data);
+        assert_eq!(&boxes[0].0, b"ftyp");
+        assert_eq!(&boxes[1].0, b"moov");
+
+        // SPS and PPS are in the header, not the samples
+        let moov = boxes[1].1;
+        let avcc = moov.windows(4).position(|w| w == b"avcC").unwrap();
+        assert_eq!(&moov[avcc + 4..avcc + 8], &[1, 0x42, 0x00, 0x1F]);
+        assert_eq!(&moov[avcc + 12..avcc + 20], &SPS);
+        let mehd_duration = u64::from_be_bytes(moov[moov.len() - 8..].try_into().unwrap());
+        assert_eq!(mehd_duration, 66_666 + 33_333);
+
+        let fragments: Vec<_> = boxes[2..].iter().map(|(kind, _)| kind).collect();
+        assert_eq!(
+            fragments,
+            [b"moof", b"mdat", b"moof", b"mdat", b"moof", b"mdat"]
+        );
+        assert_eq!(boxes[3].1, &[0, 0, 0, 4, 0x65, 0x88, 0x84, 0x21]);
+    }
+
+    #[test]
+    fn test_mp4_header_rejects_unrepresentable_config() {
+        assert!(mp4_moov(1280, 720, &SPS, &PPS).is_ok());
+        assert!(matches!(
+            mp4_moov(70_000, 720, &SPS, &PPS),
+            Err(RecorderError::DimensionsTooLarge(70_000, 720))
+        ));
+        assert!(matches!(
+            mp4_moov(1280, 720, &SPS[..3], &PPS),
+            Err(RecorderError::InvalidCodecConfig(_))
+        ));
+        let huge = vec![0u8; usize::from(u16::MAX) + 1];
+        assert!(matches!(
+            mp4_moov(1280, 720, &SPS, &huge),
+            Err(RecorderError::InvalidCodecConfig(_))
+        ));
+
+        // The error surfaces from the frame that triggers the header
+        let mut recorder =
+            StreamRecorder::new(Cursor::new(Vec::new()), ContainerFormat::Mp4, 70_000, 720);
+        recorder
+            .write_frame(&annex_b(&[&SPS, &PPS]), 0, false)
+            .unwrap();
+        let idr = annex_b(&[&[0x65, 0x88, 0x84, 0x21]]);
+        assert!(recorder.write_frame(&idr, 0, true).is_err());
+    }
+
+    #[test]
+    fn test_record_vp9_to_webm() {
+        let mut recorder =
+            StreamRecorder::new(Cursor::new(Vec::new()), ContainerFormat::WebM, 640, 480);
+        assert!(!recorder.write_frame(&[0x86, 0x01], 0, false).unwrap());
+        assert!(recorder
+            .write_frame(&[0x82, 0x49, 0x83], 5_000, true)
+            .unwrap());
+        assert!(recorder.write_frame(&[0x86, 0x02], 38_333, false).unwrap());
+        assert!(recorder
+            .write_frame(&[0x82, 0x49, 0x84], 71_666, true)
+            .unwrap());
+        let data = recorder.finish().unwrap().into_inner();
+
+        let top = ebml_children(&data);
+        assert_eq!(top.len(), 2);
+        assert_eq!(top[0].0, 0x1A45DFA3);
+        assert!(ebml_children(top[0].1).contains(&(0x4282, &b"webm"[..])));
+
+        // The segment size was filled in, so its children parse to the end
+        assert_eq!(top[1].0, 0x18538067);
+        let segment = ebml_children(top[1].1);
+        let info = ebml_children(segment[0].1);
+        let duration = f64::from_be_bytes(info[3].1.try_into().unwrap());
+        assert_eq!(duration, 99.999);
+
+        let clusters: Vec<_> = segment.iter().filter(|(id, _)| *id == 0x1F43B675).collect();
+        assert_eq!(clusters.len(), 2);
+        let blocks: Vec<_> = clusters
+            .iter()
+            .flat_map(|(_, body)| ebml_children(body))
+            .filter(|(id, _)| *id == 0xA3)
+            .collect();
+        assert_eq!(blocks.len(), 3);
+        assert_eq!(blocks[0].1, &[0x81, 0, 0, 0x80, 0x82, 0x49, 0x83]);
+        assert_eq!(blocks[1].1, &[0x81, 0, 33, 0x00, 0x86, 0x02]);
+    }
+
+    #[test]
+    fn test_container_for_codec() {
+        assert_eq!(
+            ContainerFormat::for_codec("H264"),
+            Some(ContainerFormat::Mp4)
+        );
+        assert_eq!(
+            ContainerFormat::for_codec("vp9"),
+            Some(ContainerFormat::WebM)
+        );
+        assert_eq!(ContainerFormat::for_codec("mjpeg"), None);
+        assert_eq!(ContainerFormat::WebM.extension(), "webm");
+    }
+}
@@ -0,0 +1,161 @@
+//! Presentation Timestamp Normalization
+//!
+//! Android encoders stamp frames with whatever clock they were started
+//! from, so the first PTS is rarely zero, and the value can wrap or restart
+//! mid-stream. [`PtsNormalizer`] maps raw timestamps onto a zero-based,
+//! monotonic timeline that the [`FramePacer`](super::FramePacer) can
+//! schedule against.
+//!
+//! ## Rules
+//!
+//! - The first frame is normalized to zero
+//! - Forward steps keep their spacing
+//! - A backward jump larger than the wrap threshold starts a new epoch: the
+//!   raw clock is rebased so the frame lands one frame interval after the
+//!   previous one
+//! - A smaller backward step (e.g. reordering jitter) is held at the previous
+//!   normalized value, so the output never goes backwards
+
+use tracing::debug;
+
+/// Default backward jump treated as a wrap or stream restart (1 second)
+pub const DEFAULT_PTS_WRAP_THRESHOLD_US: u64 = 1_000_000;
+
+/// Frame interval assumed before two frames have been seen (30fps)
+const DEFAULT_FRAME_INTERVAL_US: u64 = 33_333;
+
+/// Maps raw presentation timestamps onto a zero-based monotonic timeline
+#[derive(Debug, Clone)]
+pub struct PtsNormalizer {
+    /// Backward jump that starts a new epoch
+    wrap_threshold_us: u64,
+    /// Raw PTS and normalized PTS at the start of the current epoch
+    epoch: Option<(u64, u64)>,
+    /// Most recent raw PTS
+    last_raw: u64,
+    /// Most recent normalized PTS
+    last_normalized: u64,
+    /// Most recent forward step, used to place a new epoch
+    frame_interval_us: u64,
+    /// Number of epochs started after the first
+    wraps: u64,
+}
+
+impl Default for PtsNormalizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PtsNormalizer {
+    /// Create a normalizer with [`DEFAULT_PTS_WRAP_THRESHOLD_US`]
+    pub fn new() -> Self {
+        Self {
+            wrap_threshold_us: DEFAULT_PTS_WRAP_THRESHOLD_US,
+            epoch: None,
+            last_raw: 0,
+            last_normalized: 0,
+            frame_interval_us: DEFAULT_FRAME_INTERVAL_US,
+            wraps: 0,
+        }
+    }
+
+    /// Set the backward jump treated as a wrap or stream restart
+    pub fn with_wrap_threshold_us(mut self, threshold_us: u64) -> Self {
+        self.wrap_threshold_us = threshold_us;
+        self
+    }
+
+    /// Normalize the next raw PTS in decode order
+    pub fn normalize(&mut self, raw_us: u64) -> u64 {
+        let normalized = match self.epoch {
+            None => {
+                self.epoch = Some((raw_us, 0));
+                0
+            }
+            Some((base_raw, base_normalized)) if raw_us >= self.last_raw => {
+                let step = raw_us - self.last_raw;
+                if step > 0 {
+                    self.frame_interval_us = step;
+                }
+                base_normalized + (raw_us - base_raw)
+            }
+            Some(_) if self.last_raw - raw_us > self.wrap_threshold_us => {
+                let normalized = self.last_normalized + self.frame_interval_us;
+                debug!(
+                    "PTS jumped back from {}us to {}us, starting new epoch at {}us",
+                    self.last_raw, raw_us, normalized
+                );
+                self.epoch = Some((raw_us, normalized));
+                self.wraps += 1;
+                normalized
+            }
+            Some(_) => self.last_normalized,
+        };
+
+        self.last_raw = raw_us;
+        self.last_normalized = self.last_normalized.max(normalized);
+        self.last_normalized
+    }
+
+    /// Number of wraps or restarts detected
+    pub fn wraps(&self) -> u64 {
+        self.wraps
+    }
+
+    /// Forget the timeline so the next frame is normalized to zero
+    pub fn reset(&mut self) {
+        *self = Self::new().with_wrap_threshold_us(self.wrap_threshold_us);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalize_all(normalizer: &mut PtsNormalizer, raw: &[u64]) -> Vec<u64> {
+        raw.iter().map(|&pts| normalizer.normalize(pts)).collect()
+    }
+
+    #[test]
+    fn test_nonzero_start_is_rebased_to_zero() {
+        let mut normalizer = PtsNormalizer::new();
+        let raw = [5_000_000_000, 5_000_033_333, 5_000_066_666, 5_000_100_000];
+        assert_eq!(
+            normalize_all(&mut normalizer, &raw),
+            vec![0, 33_333, 66_666, 100_000]
+        );
+        assert_eq!(normalizer.wraps(), 0);
+    }
+
+    #[test]
+    fn test_wrap_starts_new_epoch() {
+        let mut normalizer = PtsNormalizer::new();
+
+        // The raw clock wraps from near u32::MAX back to a small value
+        let raw = [
+            u32::MAX as u64 - 66_000,
+            u32::MAX as u64 - 33_000,
+            u32::MAX as u64,
+            20_000,
+            53_000,
+        ];
+        assert_eq!(
+            normalize_all(&mut normalizer, &raw),
+            vec![0, 33_000, 66_000, 99_000, 132_000]
+        );
+        assert_eq!(normalizer.wraps(), 1);
+    }
+
+    #[test]
+    fn test_small_backward_step_is_held() {
+        let mut normalizer = PtsNormalizer::new();
+        let raw = [1_000, 34_000, 30_000, 67_000];
+        let normalized = normalize_all(&mut normalizer, &raw);
+        assert_eq!(normalized, vec![0, 33_000, 33_000, 66_000]);
+        assert!(normalized.windows(2).all(|w| w[0] <= w[1]));
+
+        normalizer.reset();
+        assert_eq!(normalizer.normalize(9_999), 0);
+    }
+}
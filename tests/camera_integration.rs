@@ -305,6 +305,8 @@ fn test_camera_frame_size_field() {
         timestamp_us: 0,
         sequence_number: 1,
         size: 4,
+        stream_id: None,
+        orientation: None,
     };
     assert_eq!(tiny_frame.size, 4);
 
@@ -313,6 +315,8 @@ fn test_camera_frame_size_field() {
         timestamp_us: 0,
         sequence_number: 2,
         size: 1024 * 1024,
+        stream_id: None,
+        orientation: None,
     };
     assert_eq!(large_frame.size, 1024 * 1024);
 }
@@ -325,9 +329,11 @@ fn test_camera_frame_to_packet() {
         timestamp_us: 33333,
         sequence_number: 1,
         size: 2048,
+        stream_id: None,
+        orientation: None,
     };
 
-    let packet = frame.to_packet();
+    let packet = frame.try_to_packet().unwrap();
     assert_eq!(packet.payload_size, Some(2048));
 }
 
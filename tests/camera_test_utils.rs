@@ -129,6 +129,8 @@ impl MockCameraFrame {
             timestamp_us: self.timestamp_us,
             sequence_number: self.sequence_number,
             size: self.data.len() as u64,
+            stream_id: None,
+            orientation: None,
         }
     }
 }
@@ -0,0 +1,324 @@
+//! Round-trip tests for packet structs
+//!
+//! Each struct is generated by a proptest strategy, written to a packet,
+//! sent through the wire format and parsed back, and must compare equal to
+//! the original. This catches serde rename mismatches and lossy conversions.
+//!
+//! Every strategy mixes in edge values (zero resolution, empty camera and
+//! codec lists, `u32::MAX` bitrate, `u64::MAX` timestamps, empty and
+//! non-ASCII strings) alongside random ones. Failing cases are shrunk to a
+//! minimal value before being reported.
+
+use cosmic_ext_connect_core::plugins::camera::{
+    CameraCapability, CameraFacing, CameraFrame, CameraInfo, CameraStart, CameraStatus, FrameType,
+    Orientation, Resolution, StreamingStatus,
+};
+use cosmic_ext_connect_core::protocol::{
+    DeviceId, DeviceType, Identity, VersionRange, DEVICE_ID_MAX_LENGTH, DEVICE_ID_MIN_LENGTH,
+};
+use cosmic_ext_connect_core::Packet;
+use proptest::prelude::*;
+use proptest::{collection, option, sample, string};
+
+/// Number of generated cases per struct
+const CASES: u32 = 256;
+
+/// Serialize a packet to the wire format and parse it back
+fn over_the_wire(packet: Packet) -> Packet {
+    Packet::from_bytes(&packet.to_bytes().unwrap()).unwrap()
+}
+
+// =============================================================================
+// Strategies
+// =============================================================================
+
+fn any_u32() -> impl Strategy<Value = u32> {
+    prop_oneof![1 => Just(0), 1 => Just(u32::MAX), 2 => any::<u32>()]
+}
+
+fn any_u64() -> impl Strategy<Value = u64> {
+    prop_oneof![1 => Just(0), 1 => Just(u64::MAX), 2 => any::<u64>()]
+}
+
+fn any_string() -> impl Strategy<Value = String> {
+    const SAMPLES: &[&str] = &["", " ", "h264", "Café 📷", "line\nbreak", "quote\"\\"];
+    prop_oneof![
+        1 => sample::select(SAMPLES).prop_map(String::from),
+        2 => collection::vec(any::<char>(), 1..24).prop_map(String::from_iter),
+    ]
+}
+
+fn any_device_id() -> impl Strategy<Value = DeviceId> {
+    let pattern = format!(
+        "[a-zA-Z0-9_]{{{},{}}}",
+        DEVICE_ID_MIN_LENGTH, DEVICE_ID_MAX_LENGTH
+    );
+    string::string_regex(&pattern)
+        .unwrap()
+        .prop_map(|id| DeviceId::parse(&id).unwrap())
+}
+
+fn any_resolution() -> impl Strategy<Value = Resolution> {
+    prop_oneof![
+        1 => Just(Resolution::new(0, 0)),
+        1 => Just(Resolution::new(u32::MAX, u32::MAX)),
+        2 => (any_u32(), any_u32()).prop_map(|(width, height)| Resolution::new(width, height)),
+    ]
+}
+
+fn any_orientation() -> impl Strategy<Value = Orientation> {
+    sample::select(vec![
+        Orientation::Deg0,
+        Orientation::Deg90,
+        Orientation::Deg180,
+        Orientation::Deg270,
+    ])
+}
+
+fn any_camera_info() -> impl Strategy<Value = CameraInfo> {
+    (
+        any_u32(),
+        any_string(),
+        sample::select(vec![
+            CameraFacing::Front,
+            CameraFacing::Back,
+            CameraFacing::External,
+        ]),
+        any_resolution(),
+        collection::vec(any_resolution(), 0..=4),
+        any::<bool>(),
+    )
+        .prop_map(
+            |(id, name, facing, max_resolution, resolutions, has_flash)| CameraInfo {
+                id,
+                name,
+                facing,
+                max_resolution,
+                resolutions,
+                has_flash,
+            },
+        )
+}
+
+fn any_camera_capability() -> impl Strategy<Value = CameraCapability> {
+    (
+        collection::vec(any_camera_info(), 0..=3),
+        collection::vec(any_string(), 0..=3),
+        any::<bool>(),
+        any_resolution(),
+        any_u32(),
+        any_u32(),
+    )
+        .prop_map(
+            |(cameras, supported_codecs, audio_supported, max_resolution, max_bitrate, max_fps)| {
+                CameraCapability {
+                    cameras,
+                    supported_codecs,
+                    audio_supported,
+                    max_resolution,
+                    max_bitrate,
+                    max_fps,
+                }
+            },
+        )
+}
+
+fn any_camera_start() -> impl Strategy<Value = CameraStart> {
+    (
+        any_u32(),
+        any_resolution(),
+        any_u32(),
+        any_u32(),
+        any_string(),
+        any::<bool>(),
+        any::<bool>(),
+        option::of(any_string()),
+        option::of(any_string()),
+    )
+        .prop_map(
+            |(
+                camera_id,
+                resolution,
+                fps,
+                bitrate,
+                codec,
+                downscale,
+                encrypt,
+                stream_key,
+                stream_key_signature,
+            )| CameraStart {
+                camera_id,
+                resolution,
+                fps,
+                bitrate,
+                codec,
+                downscale,
+                encrypt,
+                stream_key,
+                stream_key_signature,
+            },
+        )
+}
+
+fn any_camera_status() -> impl Strategy<Value = CameraStatus> {
+    let status = sample::select(vec![
+        StreamingStatus::Starting,
+        StreamingStatus::Streaming,
+        StreamingStatus::Stopping,
+        StreamingStatus::Stopped,
+        StreamingStatus::Error,
+    ]);
+    (
+        status,
+        any_u32(),
+        any_resolution(),
+        any_u32(),
+        any_u32(),
+        option::of(any_string()),
+        option::of(any_orientation()),
+        option::of(any_string()),
+        option::of(any_string()),
+    )
+        .prop_map(
+            |(
+                status,
+                camera_id,
+                resolution,
+                fps,
+                bitrate,
+                error,
+                orientation,
+                stream_key,
+                stream_key_signature,
+            )| CameraStatus {
+                status,
+                camera_id,
+                resolution,
+                fps,
+                bitrate,
+                error,
+                orientation,
+                stream_key,
+                stream_key_signature,
+            },
+        )
+}
+
+fn any_camera_frame() -> impl Strategy<Value = CameraFrame> {
+    (
+        sample::select(vec![
+            FrameType::SpsPps,
+            FrameType::IFrame,
+            FrameType::PFrame,
+        ]),
+        any_u64(),
+        any_u64(),
+        // Sizes above i64::MAX cannot be sent; see test_oversized_frame_rejected
+        any_u64().prop_map(|size| size.min(i64::MAX as u64)),
+        option::of(any_u32()),
+        option::of(any_orientation()),
+    )
+        .prop_map(
+            |(frame_type, timestamp_us, sequence_number, size, stream_id, orientation)| {
+                CameraFrame {
+                    frame_type,
+                    timestamp_us,
+                    sequence_number,
+                    size,
+                    stream_id,
+                    orientation,
+                }
+            },
+        )
+}
+
+fn any_identity() -> impl Strategy<Value = Identity> {
+    let device_type = sample::select(vec![
+        DeviceType::Desktop,
+        DeviceType::Laptop,
+        DeviceType::Phone,
+        DeviceType::Tablet,
+        DeviceType::Tv,
+        DeviceType::Unknown,
+    ]);
+    let version_range = (any_u32(), any_u32()).prop_map(|(min, max)| VersionRange::new(min, max));
+    (
+        collection::vec(any_string(), 0..=4),
+        collection::vec(any_string(), 0..=4),
+        any_device_id(),
+        any_string(),
+        device_type,
+        any_u32(),
+        option::of(any::<u16>()),
+        collection::hash_map(any_string(), version_range, 0..=3),
+    )
+        .prop_map(
+            |(incoming, outgoing, device_id, name, device_type, version, tcp_port, versions)| {
+                let mut identity =
+                    Identity::new(incoming, outgoing).with_device(device_id, name, device_type);
+                identity.protocol_version = version;
+                identity.tcp_port = tcp_port;
+                identity.with_capability_versions(versions)
+            },
+        )
+}
+
+// =============================================================================
+// Round Trips
+// =============================================================================
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(CASES))]
+
+    #[test]
+    fn test_resolution_round_trip(resolution in any_resolution()) {
+        let json = serde_json::to_value(resolution).unwrap();
+        prop_assert_eq!(serde_json::from_value::<Resolution>(json).unwrap(), resolution);
+    }
+
+    #[test]
+    fn test_camera_start_round_trip(start in any_camera_start()) {
+        let packet = over_the_wire(start.try_to_packet().unwrap());
+        prop_assert_eq!(CameraStart::from_packet(&packet).unwrap(), start);
+    }
+
+    #[test]
+    fn test_camera_capability_round_trip(capability in any_camera_capability()) {
+        let packet = over_the_wire(capability.try_to_packet().unwrap());
+        prop_assert_eq!(CameraCapability::from_packet(&packet).unwrap(), capability);
+    }
+
+    #[test]
+    fn test_camera_status_round_trip(status in any_camera_status()) {
+        let packet = over_the_wire(status.try_to_packet().unwrap());
+        prop_assert_eq!(CameraStatus::from_packet(&packet).unwrap(), status);
+    }
+
+    #[test]
+    fn test_camera_frame_round_trip(frame in any_camera_frame()) {
+        let packet = over_the_wire(frame.try_to_packet().unwrap());
+        prop_assert_eq!(packet.payload_size, Some(frame.size as i64));
+        prop_assert_eq!(CameraFrame::from_packet(&packet).unwrap(), frame);
+    }
+
+    #[test]
+    fn test_identity_round_trip(identity in any_identity()) {
+        let packet = over_the_wire(identity.try_to_tcp_packet().unwrap());
+        prop_assert_eq!(Identity::from_packet(&packet).unwrap(), identity);
+    }
+}
+
+#[test]
+fn test_oversized_frame_rejected() {
+    // Regression: the size was cast to the signed payload size, so a
+    // u64::MAX frame advertised a payload size of -1
+    let frame = CameraFrame {
+        frame_type: FrameType::IFrame,
+        timestamp_us: 0,
+        sequence_number: 0,
+        size: u64::MAX,
+        stream_id: None,
+        orientation: None,
+    };
+    assert!(frame.try_to_packet().is_err());
+}